@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rustwav::{testsig, WaveReader, WaveWriter};
+use rustwav::format_specs::{DataFormat, SampleFormat, Spec};
+use rustwav::options::FileSizeOption;
+
+/// * About 100 MB of 24-bit stereo 48 kHz PCM.
+const DURATION_SECS: f64 = 350.0;
+
+fn bench_pcm_decode(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pcm24.wav");
+    let spec = Spec {
+        channels: 2,
+        channel_mask: None,
+        sample_rate: 48000,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    testsig::write_white_noise(&mut wavewriter, 1, DURATION_SECS).unwrap();
+    wavewriter.finalize().unwrap();
+    let path = path.to_str().unwrap();
+    let data_length = WaveReader::open(path).unwrap().storage_info().data_length;
+
+    let mut group = c.benchmark_group("pcm24_to_f32");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data_length));
+    group.bench_function("frame_iter", |b| b.iter(|| {
+        let mut wavereader = WaveReader::open(path).unwrap();
+        wavereader.frame_iter::<f32>().unwrap().count()
+    }));
+    group.bench_function("stereo_iter", |b| b.iter(|| {
+        let mut wavereader = WaveReader::open(path).unwrap();
+        wavereader.stereo_iter::<f32>().unwrap().count()
+    }));
+    group.bench_function("decode_into", |b| b.iter(|| {
+        let mut wavereader = WaveReader::open(path).unwrap();
+        let mut buf = vec![0.0f32; 65536];
+        let mut num_samples = 0;
+        loop {
+            let written = wavereader.decode_into(&mut buf).unwrap();
+            if written == 0 {
+                break num_samples;
+            }
+            num_samples += written;
+        }
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_pcm_decode);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rustwav::WaveWriter;
+use rustwav::format_specs::{DataFormat, SampleFormat, Spec};
+use rustwav::options::FileSizeOption;
+
+/// * About 16 MB of 16-bit stereo PCM.
+const NUM_SAMPLES: usize = 8 * 1024 * 1024;
+
+fn bench_pcm_encode(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pcm16.wav");
+    let spec = Spec {
+        channels: 2,
+        channel_mask: None,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let samples: Vec<i16> = (0..NUM_SAMPLES).map(|i| (i.wrapping_mul(7919) & 0xFFFF) as i16).collect();
+    let samples_i32: Vec<i32> = samples.iter().map(|&s| (s as i32) << 16).collect();
+
+    // The samples of the same type as the file are written as bytes, the others go through the per-sample conversion.
+    let mut group = c.benchmark_group("pcm16_write");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes((NUM_SAMPLES * 2) as u64));
+    group.bench_function("same_type_i16", |b| b.iter(|| {
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        wavewriter.finalize().unwrap();
+    }));
+    group.bench_function("converted_i32", |b| b.iter(|| {
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_interleaved_samples(&samples_i32).unwrap();
+        wavewriter.finalize().unwrap();
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_pcm_encode);
+criterion_main!(benches);
@@ -15,6 +15,11 @@ pub trait AdpcmEncoder: Debug {
     where
         Self: Sized;
 
+    /// * How many bits a code word has, this is the `bits_per_sample` of the `fmt ` chunk.
+    fn bits_per_sample(&self) -> u16 {
+        4
+    }
+
     /// * The `encode()` function uses two closures to input samples and output the encoded data.
     /// * If you have samples to encode, just feed it through the `input` closure. When it has encoded data to excrete, it will call the `output()` closure to give you back the encoded data.
     /// * It will endlessly ask for new samples to encode through calling `input` closure, give it a `None` and let it return.
@@ -112,18 +117,26 @@ pub fn test(encoder: &mut impl AdpcmEncoder, decoder: &mut impl AdpcmDecoder, mu
 }
 
 pub type AdpcmEncoderIMA     = ima::Encoder;
+pub type AdpcmEncoderIMA2    = ima_bits::Encoder<2>;
+pub type AdpcmEncoderIMA3    = ima_bits::Encoder<3>;
+pub type AdpcmEncoderIMA5    = ima_bits::Encoder<5>;
 pub type AdpcmEncoderMS      = ms::Encoder;
 pub type AdpcmEncoderYAMAHA  = yamaha::Encoder;
 
 pub type AdpcmDecoderIMA     = ima::Decoder;
+pub type AdpcmDecoderIMABits = ima_bits::Decoder;
 pub type AdpcmDecoderMS      = ms::Decoder;
 pub type AdpcmDecoderYAMAHA  = yamaha::Decoder;
 
 pub type EncIMA     = AdpcmEncoderIMA;
+pub type EncIMA2    = AdpcmEncoderIMA2;
+pub type EncIMA3    = AdpcmEncoderIMA3;
+pub type EncIMA5    = AdpcmEncoderIMA5;
 pub type EncMS      = AdpcmEncoderMS;
 pub type EncYAMAHA  = AdpcmEncoderYAMAHA;
 
 pub type DecIMA     = AdpcmDecoderIMA;
+pub type DecIMABits = AdpcmDecoderIMABits;
 pub type DecMS      = AdpcmDecoderMS;
 pub type DecYAMAHA  = AdpcmDecoderYAMAHA;
 
@@ -163,7 +176,7 @@ pub mod ima {
         -1, -1, -1, -1, 2, 4, 6, 8 
     ];
 
-    const IMAADPCM_STEPSIZE_TABLE: [u16; 89] = [
+    pub(super) const IMAADPCM_STEPSIZE_TABLE: [u16; 89] = [
         7,     8,     9,     10,    11,    12,    13,    14, 
         16,    17,    19,    21,    23,    25,    28,    31, 
         34,    37,    41,    45,    50,    55,    60,    66,
@@ -184,6 +197,16 @@ pub mod ima {
     const INTERLEAVE_SAMPLES: usize = INTERLEAVE_BYTES * 2;
     const NIBBLE_BUFFER_SIZE: usize = HEADER_SIZE + INTERLEAVE_BYTES;
 
+    /// * Per channel, the header sample plus two samples for every byte after the header, as the Microsoft spec defines it.
+    const SAMPLES_PER_BLOCK: usize = (BLOCK_SIZE - HEADER_SIZE) * 2 + 1;
+
+    /// * The `samples_per_block` that the old encoder wrote into the `fmt ` chunk extension.
+    /// * The old encoder put the predictor value instead of the first sample into the block header and encoded the first sample as a nibble,
+    ///   so the header sample of these files is a duplicated sample that should be dropped.
+    fn legacy_samples_per_block(block_size: usize, channels: u16) -> usize {
+        (block_size - HEADER_SIZE) * channels as usize * 2
+    }
+
     /// * The core encoder of ADPCM-IMA for mono-channel
     #[derive(Debug, Clone, Copy)]
     pub struct EncoderCore {
@@ -235,7 +258,7 @@ pub mod ima {
         }
 
         /// * Encoder logic:
-        /// 1. Initially outputs 4 bytes of the decoder's state machine register values.
+        /// 1. Initially outputs 4 bytes of the decoder's state machine register values, the first sample of the block is stored verbatim in it.
         /// 2. Processes samples by converting two raw samples into one encoded unit combined by two nibbles (a byte).
         pub fn encode(
             &mut self,
@@ -244,14 +267,16 @@ pub mod ima {
         ) -> io::Result<()> {
             while let Some(sample) = input() {
                 if !self.header_written {
-                    // Write the four bytes header
-                    let buf = self.prev_sample.to_le_bytes();
+                    // Write the four bytes header, the decoder outputs the header sample as the first sample of the block.
+                    self.prev_sample = sample;
+                    let buf = sample.to_le_bytes();
                     output(buf[0]);
                     output(buf[1]);
                     output(self.stepsize_index as u8);
                     output(0);
-                    self.num_outputs += 4;
+                    self.num_outputs += HEADER_SIZE;
                     self.header_written = true;
+                    continue;
                 }
                 if !self.half_byte_written {
                     self.nibble = self.encode_sample(sample);
@@ -263,7 +288,6 @@ pub mod ima {
                     self.num_outputs += 1;
                     if self.num_outputs >= BLOCK_SIZE {
                         // Reaches the block size limit; resets the encoder.
-                        self.prev_sample = sample;
                         self.header_written = false;
                         self.num_outputs = 0;
                     }
@@ -272,12 +296,15 @@ pub mod ima {
             Ok(())
         }
 
-        /// * Continue feeding zeroes to the encoder until it finishes processing a whole block of data.
+        /// * Check if there's a nibble cached or the encoded data of the block is not aligned to the 4-byte interleave group.
+        pub fn has_pending(&self) -> bool {
+            self.half_byte_written || self.num_outputs % INTERLEAVE_BYTES != 0
+        }
+
+        /// * Continue feeding zeroes to the encoder until the encoded data is aligned to the 4-byte interleave group.
         pub fn flush(&mut self, mut output: impl FnMut(u8)) -> io::Result<()> {
-            let aligned_size = ((self.num_outputs - 1) / INTERLEAVE_BYTES + 1) * INTERLEAVE_BYTES;
-            let pad_size = aligned_size - self.num_outputs;
-            if pad_size != 0 {
-                let mut iter = vec![0i16; pad_size].into_iter();
+            while self.has_pending() {
+                let mut iter = [0i16].into_iter();
                 self.encode(|| -> Option<i16> {iter.next()}, |nibble: u8| {output(nibble)})?
             }
             Ok(())
@@ -290,17 +317,15 @@ pub mod ima {
         }
     }
 
-    type EncoderSampleBuffer = CopiableBuffer<i16, INTERLEAVE_SAMPLES>;
     type EncoderNibbleBuffer = CopiableBuffer<u8, NIBBLE_BUFFER_SIZE>;
 
     /// * A wrapper for the `EncoderCore` to encode stereo audio.
+    /// * Each channel is encoded by its own core, the encoded data is interleaved in 4-byte (8-nibble) groups per channel, begins with the 4-byte header of each channel.
     #[derive(Debug, Clone, Copy)]
     pub struct StereoEncoder {
         current_channel: CurrentChannel,
         core_l: EncoderCore,
         core_r: EncoderCore,
-        sample_l: EncoderSampleBuffer,
-        sample_r: EncoderSampleBuffer,
         nibble_l: EncoderNibbleBuffer,
         nibble_r: EncoderNibbleBuffer,
     }
@@ -318,8 +343,6 @@ pub mod ima {
                 current_channel: CurrentChannel::Left,
                 core_l: EncoderCore::new(),
                 core_r: EncoderCore::new(),
-                sample_l: EncoderSampleBuffer::new(),
-                sample_r: EncoderSampleBuffer::new(),
                 nibble_l: EncoderNibbleBuffer::new(),
                 nibble_r: EncoderNibbleBuffer::new(),
             }
@@ -333,22 +356,17 @@ pub mod ima {
             mut output: impl FnMut(u8),
         ) -> io::Result<()> {
             while let Some(sample) = input() {
+                let mut iter = [sample].into_iter();
                 match self.current_channel {
                     CurrentChannel::Left => {
                         self.current_channel = CurrentChannel::Right;
-                        self.sample_l.push(sample);
+                        self.core_l.encode(|| -> Option<i16> {iter.next()}, |nibble:u8|{self.nibble_l.push(nibble)})?;
                     }
                     CurrentChannel::Right => {
                         self.current_channel = CurrentChannel::Left;
-                        self.sample_r.push(sample);
+                        self.core_r.encode(|| -> Option<i16> {iter.next()}, |nibble:u8|{self.nibble_r.push(nibble)})?;
                     }
                 }
-                if self.sample_l.is_full() && self.sample_r.is_full() {
-                    let mut iter_l = mem::replace(&mut self.sample_l, EncoderSampleBuffer::new()).into_iter();
-                    let mut iter_r = mem::replace(&mut self.sample_r, EncoderSampleBuffer::new()).into_iter();
-                    self.core_l.encode(|| -> Option<i16> {iter_l.next()}, |nibble:u8|{self.nibble_l.push(nibble)})?;
-                    self.core_r.encode(|| -> Option<i16> {iter_r.next()}, |nibble:u8|{self.nibble_r.push(nibble)})?;
-                }
                 while self.nibble_l.len() >= INTERLEAVE_BYTES && self.nibble_r.len() >= INTERLEAVE_BYTES {
                     for i in 0..INTERLEAVE_BYTES {output(self.nibble_l[i]);}
                     for i in 0..INTERLEAVE_BYTES {output(self.nibble_r[i]);}
@@ -360,8 +378,14 @@ pub mod ima {
         }
 
         /// * Let the encoder excrete all of the data, finish encoding.
+        /// * Zero samples are fed to both channels until the data of both channels is aligned to the 4-byte interleave group.
         pub fn flush(&mut self, mut output: impl FnMut(u8)) -> io::Result<()> {
-            while !self.sample_l.is_empty() || !self.sample_r.is_empty() {
+            while self.core_l.has_pending()
+                || self.core_r.has_pending()
+                || !self.nibble_l.is_empty()
+                || !self.nibble_r.is_empty()
+                || matches!(self.current_channel, CurrentChannel::Right)
+            {
                 let mut iter = [0i16].into_iter();
                 self.encode(
                     || -> Option<i16> { iter.next() },
@@ -416,11 +440,11 @@ pub mod ima {
                 format_tag: 0x0011,
                 channels,
                 sample_rate,
-                byte_rate: sample_rate * bits_per_sample as u32 * channels as u32 / 8,
+                byte_rate: (sample_rate as u64 * block_align as u64 / SAMPLES_PER_BLOCK as u64) as u32,
                 block_align,
                 bits_per_sample,
                 extension: Some(FmtExtension::new_adpcm_ima(AdpcmImaData {
-                    samples_per_block: (BLOCK_SIZE as u16 - HEADER_SIZE as u16) * channels * 2,
+                    samples_per_block: SAMPLES_PER_BLOCK as u16,
                 })),
             })
         }
@@ -428,11 +452,10 @@ pub mod ima {
         fn modify_fmt_chunk(&self, fmt_chunk: &mut FmtChunk) -> io::Result<()> {
             fmt_chunk.block_align = BLOCK_SIZE as u16 * fmt_chunk.channels;
             fmt_chunk.bits_per_sample = 4;
-            fmt_chunk.byte_rate = fmt_chunk.sample_rate * 8
-                / (fmt_chunk.channels as u32 * fmt_chunk.bits_per_sample as u32);
-            if let Some(extension) = &fmt_chunk.extension {
-                if let ExtensionData::AdpcmIma(mut adpcm_ima) = extension.data {
-                    adpcm_ima.samples_per_block = (BLOCK_SIZE as u16 - 4) * fmt_chunk.channels * 2;
+            fmt_chunk.byte_rate = (fmt_chunk.sample_rate as u64 * fmt_chunk.block_align as u64 / SAMPLES_PER_BLOCK as u64) as u32;
+            if let Some(extension) = &mut fmt_chunk.extension {
+                if let ExtensionData::AdpcmIma(adpcm_ima) = &mut extension.data {
+                    adpcm_ima.samples_per_block = SAMPLES_PER_BLOCK as u16;
                     Ok(())
                 } else {
                     Err(io::Error::new(
@@ -467,17 +490,54 @@ pub mod ima {
         nibble_buffer: DecoderNibbleBuffer,
         input_count: usize,
         block_size: usize,
+        legacy_layout: bool,
     }
 
     impl DecoderCore {
         pub fn new(fmt_chunk: &FmtChunk) -> Self {
+            let block_size = (fmt_chunk.block_align / fmt_chunk.channels) as usize;
             Self {
                 sample_val: 0,
                 stepsize_index: 0,
                 ready: false,
                 nibble_buffer: DecoderNibbleBuffer::new(),
                 input_count: 0,
-                block_size: (fmt_chunk.block_align / fmt_chunk.channels) as usize,
+                block_size,
+                legacy_layout: Self::is_legacy_layout(fmt_chunk, block_size),
+            }
+        }
+
+        /// * Detect the files written by the old encoder by the `samples_per_block` it wrote.
+        fn is_legacy_layout(fmt_chunk: &FmtChunk, block_size: usize) -> bool {
+            match &fmt_chunk.extension {
+                Some(extension) => match extension.data {
+                    ExtensionData::AdpcmIma(adpcm_ima) => {
+                        block_size > HEADER_SIZE
+                            && adpcm_ima.samples_per_block as usize == legacy_samples_per_block(block_size, fmt_chunk.channels)
+                    }
+                    _ => false,
+                },
+                None => false,
+            }
+        }
+
+        /// * Force the compatibility mode to decode the files written by the old encoder: the header sample of each block is dropped.
+        pub fn set_legacy_layout(&mut self, legacy_layout: bool) {
+            self.legacy_layout = legacy_layout;
+        }
+
+        /// * Is the decoder decoding the files written by the old encoder
+        pub fn is_legacy_layout_enabled(&self) -> bool {
+            self.legacy_layout
+        }
+
+        /// * How many samples this core decodes from a block.
+        pub fn samples_per_block(&self) -> usize {
+            let samples = (self.block_size - HEADER_SIZE) * 2;
+            if self.legacy_layout {
+                samples
+            } else {
+                samples + 1
             }
         }
 
@@ -525,7 +585,9 @@ pub mod ima {
                         }
                         self.nibble_buffer.clear();
                         self.ready = true;
-                        output(self.sample_val);
+                        if !self.legacy_layout {
+                            output(self.sample_val);
+                        }
                     }
                 } else {
                     self.nibble_buffer.push(byte);
@@ -623,7 +685,7 @@ pub mod ima {
         }
 
         /// * This `encode()` function needs 8 initial bytes to initialize 2 decoder cores, after being initialized, every 8 bytes decode to 16 samples.
-        /// * The output samples are interleaved by channels.
+        /// * The input data is interleaved in 4-byte groups per channel, the output samples are interleaved by channels.
         pub fn decode(
             &mut self,
             mut input: impl FnMut() -> Option<u8>,
@@ -672,6 +734,28 @@ pub mod ima {
         }
     }
 
+    impl Decoder {
+        /// * The compatibility mode for the files written by the old encoder is detected from the `fmt ` chunk.
+        ///   If the `fmt ` chunk was rewritten by other software, use this to force it on or off.
+        pub fn set_legacy_layout(&mut self, legacy_layout: bool) {
+            match self {
+                Decoder::Mono(dec) => dec.set_legacy_layout(legacy_layout),
+                Decoder::Stereo(dec) => {
+                    dec.core_l.set_legacy_layout(legacy_layout);
+                    dec.core_r.set_legacy_layout(legacy_layout);
+                }
+            }
+        }
+
+        /// * Is the decoder decoding the files written by the old encoder
+        pub fn is_legacy_layout_enabled(&self) -> bool {
+            match self {
+                Decoder::Mono(dec) => dec.is_legacy_layout_enabled(),
+                Decoder::Stereo(dec) => dec.core_l.is_legacy_layout_enabled(),
+            }
+        }
+    }
+
     impl AdpcmDecoder for Decoder {
         fn new(fmt_chunk: &FmtChunk) -> io::Result<Self>
         where
@@ -697,9 +781,13 @@ pub mod ima {
         /// Effective decodable bytes per block: BLOCK_SIZE - HEADER_SIZE.
         /// Mono: Block size = BLOCK_SIZE.
         /// Stereo: Block size doubles (2×BLOCK_SIZE), but two samples (L+R) form one audio frame.
-        /// Thus, total samples = (BLOCK_SIZE - HEADER_SIZE) × 2 samples (1 frame per stereo pair).
+        /// Thus, total samples = (BLOCK_SIZE - HEADER_SIZE) × 2 + 1 samples (1 frame per stereo pair), the extra one is the header sample.
+        /// Files written by the old encoder don't have the extra header sample.
         fn frames_per_block(&self) -> usize {
-            (self.get_block_size() - HEADER_SIZE) * 2
+            match self {
+                Decoder::Mono(dec) => dec.samples_per_block(),
+                Decoder::Stereo(dec) => dec.core_l.samples_per_block(),
+            }
         }
         fn reset_states(&mut self) {
             match self {
@@ -726,6 +814,406 @@ pub mod ima {
     }
 }
 
+/// * ADPCM-IMA with 2, 3, 4 or 5 bits per code word, the low bit-rate variants are used by the telephony archives.
+/// * The layout is the one that FFmpeg's `adpcm_ima_wav` decodes: every channel has a 4-byte header, then the code words are packed into groups from the lowest bit.
+///   The groups are interleaved across the channels by 4-byte words.
+/// * The 4-bit layout is the same as the `ima` module, the `ima` module is still used for it.
+pub mod ima_bits {
+    use std::{cmp::min, io};
+
+    use super::{AdpcmDecoder, AdpcmEncoder};
+    use super::ima::IMAADPCM_STEPSIZE_TABLE;
+    use crate::chunks::{FmtChunk, ext::{FmtExtension, ExtensionData, AdpcmImaData}};
+
+    const INDEX_TABLE_2: [i8; 2] = [-1, 2];
+    const INDEX_TABLE_3: [i8; 4] = [-1, -1, 1, 2];
+    const INDEX_TABLE_4: [i8; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+    const INDEX_TABLE_5: [i8; 16] = [-1, -1, -1, -1, -1, -1, -1, -1, 1, 2, 4, 6, 8, 10, 13, 16];
+
+    const BLOCK_SIZE: usize = 512;
+    const HEADER_SIZE: usize = 4;
+    const WORD_SIZE: usize = 4;
+
+    /// * The step size index adjustment table, indexed by the code word without the sign bit.
+    fn index_table(bits: u16) -> &'static [i8] {
+        match bits {
+            2 => &INDEX_TABLE_2,
+            3 => &INDEX_TABLE_3,
+            4 => &INDEX_TABLE_4,
+            _ => &INDEX_TABLE_5,
+        }
+    }
+
+    /// * How many bytes and code words a group has for one channel. A group is made of whole words and whole code words.
+    fn group_layout(bits: u16) -> (usize, usize) {
+        match bits {
+            2 => (4, 16),
+            3 => (12, 32),
+            4 => (4, 8),
+            _ => (20, 32),
+        }
+    }
+
+    /// * The layout of a block for the number of bits and channels.
+    #[derive(Debug, Clone, Copy)]
+    pub struct BlockLayout {
+        pub bits: u16,
+        pub channels: u16,
+        pub group_bytes: usize,
+        pub group_samples: usize,
+        pub groups_per_block: usize,
+    }
+
+    impl BlockLayout {
+        /// * The layout that the encoder uses, every channel has about 512 bytes for a block.
+        pub fn new(bits: u16, channels: u16) -> io::Result<Self> {
+            if !(2..=5).contains(&bits) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("ADPCM-IMA supports 2 to 5 bits per sample, not {bits}.")));
+            }
+            if channels == 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Wrong channel number \"0\" for ADPCM-IMA.".to_owned()));
+            }
+            let (group_bytes, group_samples) = group_layout(bits);
+            Ok(Self {
+                bits,
+                channels,
+                group_bytes,
+                group_samples,
+                groups_per_block: (BLOCK_SIZE - HEADER_SIZE) / group_bytes,
+            })
+        }
+
+        /// * The layout of the file, the number of the groups is derived from the `block_align` of the `fmt ` chunk.
+        pub fn from_fmt_chunk(fmt_chunk: &FmtChunk) -> io::Result<Self> {
+            let mut layout = Self::new(fmt_chunk.bits_per_sample, fmt_chunk.channels)?;
+            let block_size = (fmt_chunk.block_align / fmt_chunk.channels) as usize;
+            if block_size < HEADER_SIZE + layout.group_bytes {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("The block align {} is too small for {}-bit ADPCM-IMA", fmt_chunk.block_align, layout.bits),
+                ));
+            }
+            layout.groups_per_block = (block_size - HEADER_SIZE) / layout.group_bytes;
+            Ok(layout)
+        }
+
+        /// * The size of the block, this is the `block_align` of the `fmt ` chunk.
+        pub fn block_align(&self) -> usize {
+            (HEADER_SIZE + self.groups_per_block * self.group_bytes) * self.channels as usize
+        }
+
+        /// * The header sample and the samples of every group.
+        pub fn samples_per_block(&self) -> usize {
+            1 + self.groups_per_block * self.group_samples
+        }
+    }
+
+    /// * The state of one channel, the encoder and the decoder update it in the same way.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ChannelState {
+        predictor: i16,
+        stepsize_index: i8,
+    }
+
+    impl ChannelState {
+        /// * Decode one code word
+        pub fn decode_code(&mut self, bits: u16, code: u8) -> i16 {
+            let shift = bits - 1;
+            let sign_bit = 1u8 << shift;
+            let magnitude = code & (sign_bit - 1);
+            let stepsize = IMAADPCM_STEPSIZE_TABLE[self.stepsize_index as usize] as i32;
+            let qdiff = ((((magnitude as i32) << 1) + 1) * stepsize) >> shift;
+            let predict = if (code & sign_bit) != 0 {
+                self.predictor as i32 - qdiff
+            } else {
+                self.predictor as i32 + qdiff
+            };
+            self.predictor = predict.clamp(-32768, 32767) as i16;
+            self.stepsize_index = (self.stepsize_index + index_table(bits)[magnitude as usize]).clamp(0, 88);
+            self.predictor
+        }
+
+        /// * Encode one sample, get a code word
+        pub fn encode_sample(&mut self, bits: u16, sample: i16) -> u8 {
+            let shift = bits - 1;
+            let stepsize = IMAADPCM_STEPSIZE_TABLE[self.stepsize_index as usize] as i32;
+            let diff = sample as i32 - self.predictor as i32;
+            let mut code = min((diff.abs() << shift) / (stepsize << 1), (1 << shift) - 1) as u8;
+            if diff < 0 {
+                code |= 1 << shift;
+            }
+            self.decode_code(bits, code);
+            code
+        }
+    }
+
+    /// * Pack the code words of a group from the lowest bit.
+    fn pack_group(bits: u16, codes: &[u8], group: &mut [u8]) {
+        group.fill(0);
+        let mut bit_pos = 0usize;
+        for code in codes.iter() {
+            for bit in 0..bits {
+                if (code >> bit) & 1 != 0 {
+                    group[bit_pos / 8] |= 1 << (bit_pos % 8);
+                }
+                bit_pos += 1;
+            }
+        }
+    }
+
+    /// * Unpack the code words of a group from the lowest bit.
+    fn unpack_group(bits: u16, group: &[u8], codes: &mut [u8]) {
+        let mut bit_pos = 0usize;
+        for code in codes.iter_mut() {
+            *code = 0;
+            for bit in 0..bits {
+                if (group[bit_pos / 8] >> (bit_pos % 8)) & 1 != 0 {
+                    *code |= 1 << bit;
+                }
+                bit_pos += 1;
+            }
+        }
+    }
+
+    /// * The ADPCM-IMA encoder with `BITS` bits per code word, from 2 to 5.
+    /// * The samples are cached until a block is full, `flush()` pads the last block with zero samples.
+    #[derive(Debug, Clone)]
+    pub struct Encoder<const BITS: u16> {
+        layout: BlockLayout,
+        states: Vec<ChannelState>,
+        samples: Vec<i16>,
+    }
+
+    impl<const BITS: u16> Encoder<BITS> {
+        /// * Encode the cached samples of a block. The header of each channel stores the first sample verbatim.
+        fn encode_block(&mut self, mut output: impl FnMut(u8)) {
+            let channels = self.layout.channels as usize;
+            for (channel, state) in self.states.iter_mut().enumerate() {
+                let sample = self.samples[channel];
+                state.predictor = sample;
+                let buf = sample.to_le_bytes();
+                output(buf[0]);
+                output(buf[1]);
+                output(state.stepsize_index as u8);
+                output(0);
+            }
+            let mut codes = vec![0u8; self.layout.group_samples];
+            let mut groups = vec![vec![0u8; self.layout.group_bytes]; channels];
+            for group_index in 0..self.layout.groups_per_block {
+                let first_sample = 1 + group_index * self.layout.group_samples;
+                for (channel, state) in self.states.iter_mut().enumerate() {
+                    for (i, code) in codes.iter_mut().enumerate() {
+                        *code = state.encode_sample(BITS, self.samples[(first_sample + i) * channels + channel]);
+                    }
+                    pack_group(BITS, &codes, &mut groups[channel]);
+                }
+                for word in 0..self.layout.group_bytes / WORD_SIZE {
+                    for group in groups.iter() {
+                        group[word * WORD_SIZE..(word + 1) * WORD_SIZE].iter().for_each(|byte| output(*byte));
+                    }
+                }
+            }
+            self.samples.clear();
+        }
+
+        fn samples_per_block_all_channels(&self) -> usize {
+            self.layout.samples_per_block() * self.layout.channels as usize
+        }
+    }
+
+    impl<const BITS: u16> AdpcmEncoder for Encoder<BITS> {
+        fn new(channels: u16) -> io::Result<Self>
+        where
+            Self: Sized,
+        {
+            let layout = BlockLayout::new(BITS, channels)?;
+            Ok(Self {
+                layout,
+                states: vec![ChannelState::default(); channels as usize],
+                samples: Vec::with_capacity(layout.samples_per_block() * channels as usize),
+            })
+        }
+
+        fn bits_per_sample(&self) -> u16 {
+            BITS
+        }
+
+        fn encode(&mut self, mut input: impl FnMut() -> Option<i16>, mut output: impl FnMut(u8)) -> io::Result<()> {
+            let block_samples = self.samples_per_block_all_channels();
+            while let Some(sample) = input() {
+                self.samples.push(sample);
+                if self.samples.len() >= block_samples {
+                    self.encode_block(|byte: u8| output(byte));
+                }
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self, mut output: impl FnMut(u8)) -> io::Result<()> {
+            if !self.samples.is_empty() {
+                self.samples.resize(self.samples_per_block_all_channels(), 0);
+                self.encode_block(|byte: u8| output(byte));
+            }
+            Ok(())
+        }
+
+        fn new_fmt_chunk(
+            &mut self,
+            channels: u16,
+            sample_rate: u32,
+            bits_per_sample: u16,
+        ) -> io::Result<FmtChunk> {
+            if bits_per_sample != BITS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The ADPCM-IMA encoder encodes {BITS} bits per sample, not {bits_per_sample}."),
+                ));
+            }
+            let block_align = self.layout.block_align() as u16;
+            let samples_per_block = self.layout.samples_per_block();
+            Ok(FmtChunk {
+                format_tag: 0x0011,
+                channels,
+                sample_rate,
+                byte_rate: (sample_rate as u64 * block_align as u64 / samples_per_block as u64) as u32,
+                block_align,
+                bits_per_sample,
+                extension: Some(FmtExtension::new_adpcm_ima(AdpcmImaData {
+                    samples_per_block: samples_per_block as u16,
+                })),
+            })
+        }
+
+        fn modify_fmt_chunk(&self, fmt_chunk: &mut FmtChunk) -> io::Result<()> {
+            let samples_per_block = self.layout.samples_per_block();
+            fmt_chunk.block_align = self.layout.block_align() as u16;
+            fmt_chunk.bits_per_sample = BITS;
+            fmt_chunk.byte_rate = (fmt_chunk.sample_rate as u64 * fmt_chunk.block_align as u64 / samples_per_block as u64) as u32;
+            if let Some(extension) = &mut fmt_chunk.extension {
+                if let ExtensionData::AdpcmIma(adpcm_ima) = &mut extension.data {
+                    adpcm_ima.samples_per_block = samples_per_block as u16;
+                    Ok(())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Wrong extension data stored in the `fmt ` chunk for ADPCM-IMA: {:?}",
+                            extension
+                        ),
+                    ))
+                }
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "For ADPCM-IMA, the extension data in the `fmt ` chunk is needed".to_owned(),
+                ))
+            }
+        }
+    }
+
+    /// * The ADPCM-IMA decoder for 2 to 5 bits per code word, the number of bits is the `bits_per_sample` of the `fmt ` chunk.
+    /// * The bytes are cached until a block is full, then the whole block is decoded.
+    #[derive(Debug, Clone)]
+    pub struct Decoder {
+        layout: BlockLayout,
+        block_size: usize,
+        states: Vec<ChannelState>,
+        block: Vec<u8>,
+    }
+
+    impl Decoder {
+        fn decode_block(&mut self, mut output: impl FnMut(i16)) -> io::Result<()> {
+            let channels = self.layout.channels as usize;
+            let bits = self.layout.bits;
+            for (channel, state) in self.states.iter_mut().enumerate() {
+                let header = &self.block[channel * HEADER_SIZE..(channel + 1) * HEADER_SIZE];
+                if header[2] > 88 || header[3] != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Bad ADPCM-IMA block header: step size index {}, reserved byte 0x{:02x}", header[2], header[3]),
+                    ));
+                }
+                state.predictor = i16::from_le_bytes([header[0], header[1]]);
+                state.stepsize_index = header[2] as i8;
+            }
+            self.states.iter().for_each(|state| output(state.predictor));
+
+            let group_size = self.layout.group_bytes * channels;
+            let data = &self.block[HEADER_SIZE * channels..];
+            let mut group = vec![0u8; self.layout.group_bytes];
+            let mut codes = vec![0u8; self.layout.group_samples];
+            let mut samples = vec![0i16; self.layout.group_samples * channels];
+            for group_data in data.chunks_exact(group_size).take(self.layout.groups_per_block) {
+                for (channel, state) in self.states.iter_mut().enumerate() {
+                    for word in 0..self.layout.group_bytes / WORD_SIZE {
+                        let offset = (word * channels + channel) * WORD_SIZE;
+                        group[word * WORD_SIZE..(word + 1) * WORD_SIZE].copy_from_slice(&group_data[offset..offset + WORD_SIZE]);
+                    }
+                    unpack_group(bits, &group, &mut codes);
+                    for (i, code) in codes.iter().enumerate() {
+                        samples[i * channels + channel] = state.decode_code(bits, *code);
+                    }
+                }
+                samples.iter().for_each(|sample| output(*sample));
+            }
+            Ok(())
+        }
+    }
+
+    impl AdpcmDecoder for Decoder {
+        fn new(fmt_chunk: &FmtChunk) -> io::Result<Self>
+        where
+            Self: Sized,
+        {
+            let layout = BlockLayout::from_fmt_chunk(fmt_chunk)?;
+            Ok(Self {
+                layout,
+                block_size: fmt_chunk.block_align as usize,
+                states: vec![ChannelState::default(); layout.channels as usize],
+                block: Vec::with_capacity(fmt_chunk.block_align as usize),
+            })
+        }
+
+        fn get_block_size(&self) -> usize {
+            self.block_size
+        }
+
+        fn frames_per_block(&self) -> usize {
+            self.layout.samples_per_block()
+        }
+
+        fn reset_states(&mut self) {
+            self.block.clear();
+            self.states.fill(ChannelState::default());
+        }
+
+        fn decode(
+            &mut self,
+            mut input: impl FnMut() -> Option<u8>,
+            mut output: impl FnMut(i16),
+        ) -> io::Result<()> {
+            while let Some(byte) = input() {
+                self.block.push(byte);
+                if self.block.len() >= self.block_size {
+                    self.decode_block(|sample: i16| output(sample))?;
+                    self.block.clear();
+                }
+            }
+            Ok(())
+        }
+
+        /// * The last block may be truncated, the missing part is decoded as zero bytes. Without the headers, nothing can be decoded.
+        fn flush(&mut self, mut output: impl FnMut(i16)) -> io::Result<()> {
+            if self.block.len() >= HEADER_SIZE * self.layout.channels as usize {
+                self.block.resize(self.block_size, 0);
+                self.decode_block(|sample: i16| output(sample))?;
+            }
+            self.block.clear();
+            Ok(())
+        }
+    }
+}
+
 pub mod ms {
     // MS-ADPCM
     // https://ffmpeg.org/doxygen/3.1/adpcmenc_8c_source.html
@@ -756,6 +1244,61 @@ pub mod ms {
     const BLOCK_SIZE: usize = 1024;
     const HEADER_SIZE: usize = 7;
 
+    /// * Per channel, the two header samples plus two samples for every byte after the header, as the Microsoft spec defines it.
+    const SAMPLES_PER_BLOCK: usize = (BLOCK_SIZE - HEADER_SIZE) * 2 + 2;
+
+    /// * The `samples_per_block` that the old encoder wrote into the `fmt ` chunk extension, the blocks of these files are full blocks.
+    fn legacy_samples_per_block(channels: u16) -> usize {
+        (BLOCK_SIZE - HEADER_SIZE) * channels as usize * 2
+    }
+
+    /// * How many samples of each channel a block of `block_size` bytes can hold.
+    fn max_samples_per_block(block_size: usize) -> usize {
+        block_size.saturating_sub(HEADER_SIZE) * 2 + 2
+    }
+
+    /// * How many samples of each channel the decoder decodes from a block.
+    /// * Some encoders don't fill up the blocks, the `samples_per_block` of the `fmt ` chunk extension is honored if the block can hold that many samples.
+    /// * Otherwise, the blocks are decoded as full blocks.
+    pub fn samples_per_block(fmt_chunk: &FmtChunk) -> usize {
+        let channels = fmt_chunk.channels.max(1);
+        let block_size = fmt_chunk.block_align as usize / channels as usize;
+        let max_samples = max_samples_per_block(block_size);
+        match &fmt_chunk.extension {
+            Some(FmtExtension{data: ExtensionData::AdpcmMs(adpcm_ms), ..}) => {
+                let samples = adpcm_ms.samples_per_block as usize;
+                if !(2..=max_samples).contains(&samples) || (block_size == BLOCK_SIZE && samples == legacy_samples_per_block(channels)) {
+                    max_samples
+                } else {
+                    samples
+                }
+            }
+            _ => max_samples,
+        }
+    }
+
+    /// * Check the `fmt ` chunk for what the decoder has to work around, the reader puts the messages into its warnings.
+    pub fn check_fmt_chunk(fmt_chunk: &FmtChunk) -> Vec<String> {
+        let channels = fmt_chunk.channels.max(1);
+        let block_size = fmt_chunk.block_align as usize / channels as usize;
+        let max_samples = max_samples_per_block(block_size);
+        let mut messages = Vec::<String>::new();
+        match &fmt_chunk.extension {
+            Some(FmtExtension{ext_len, data: ExtensionData::AdpcmMs(adpcm_ms)}) => {
+                if (*ext_len as usize) < AdpcmMsData::sizeof() || adpcm_ms.num_coeff < 7 {
+                    messages.push(format!("The coefficient table of ADPCM-MS is missing or incomplete ({} coefficients in {ext_len} bytes of the extension data), using the standard coefficient table.", adpcm_ms.num_coeff));
+                }
+                let samples = adpcm_ms.samples_per_block as usize;
+                let is_legacy = block_size == BLOCK_SIZE && samples == legacy_samples_per_block(channels);
+                if !is_legacy && !(2..=max_samples).contains(&samples) {
+                    messages.push(format!("The `samples_per_block` of ADPCM-MS is {samples}, but a block of {block_size} bytes holds {max_samples} samples, decoding {max_samples} samples per block."));
+                }
+            }
+            _ => messages.push(format!("There's no extension data for ADPCM-MS in the `fmt ` chunk, using the standard coefficient table and decoding {max_samples} samples per block.")),
+        }
+        messages
+    }
+
     impl Default for AdpcmCoeffSet {
         fn default() -> Self {
             Self::new()
@@ -1081,11 +1624,11 @@ pub mod ms {
                 format_tag: 0x0002,
                 channels,
                 sample_rate,
-                byte_rate: sample_rate * bits_per_sample as u32 * channels as u32 / 8,
+                byte_rate: (sample_rate as u64 * block_align as u64 / SAMPLES_PER_BLOCK as u64) as u32,
                 block_align,
                 bits_per_sample,
                 extension: Some(FmtExtension::new_adpcm_ms(AdpcmMsData {
-                    samples_per_block: (BLOCK_SIZE as u16 - HEADER_SIZE as u16) * 2 * channels,
+                    samples_per_block: SAMPLES_PER_BLOCK as u16,
                     num_coeff: self.coeff_table.len() as u16,
                     coeffs: self.coeff_table,
                 })),
@@ -1096,14 +1639,10 @@ pub mod ms {
         fn modify_fmt_chunk(&self, fmt_chunk: &mut FmtChunk) -> io::Result<()> {
             fmt_chunk.block_align = BLOCK_SIZE as u16 * fmt_chunk.channels;
             fmt_chunk.bits_per_sample = 4;
-            fmt_chunk.byte_rate = fmt_chunk.sample_rate
-                * fmt_chunk.channels as u32
-                * fmt_chunk.bits_per_sample as u32
-                / 8;
-            if let Some(extension) = &fmt_chunk.extension {
-                if let ExtensionData::AdpcmMs(mut adpcm_ms) = extension.data {
-                    adpcm_ms.samples_per_block =
-                        (BLOCK_SIZE as u16 - HEADER_SIZE as u16) * 2 * fmt_chunk.channels;
+            fmt_chunk.byte_rate = (fmt_chunk.sample_rate as u64 * fmt_chunk.block_align as u64 / SAMPLES_PER_BLOCK as u64) as u32;
+            if let Some(extension) = &mut fmt_chunk.extension {
+                if let ExtensionData::AdpcmMs(adpcm_ms) = &mut extension.data {
+                    adpcm_ms.samples_per_block = SAMPLES_PER_BLOCK as u16;
                     adpcm_ms.num_coeff = self.coeff_table.len() as u16;
                     adpcm_ms.coeffs = self.coeff_table;
                     Ok(())
@@ -1146,6 +1685,8 @@ pub mod ms {
         header_buffer: CopiableBuffer<u8, HEADER_SIZE>,
         bytes_eaten: usize,
         max_bytes_can_eat: usize,
+        samples_per_block: usize,
+        samples_decoded: usize,
     }
 
     /// * The header data for the decoder to initialize.
@@ -1167,27 +1708,24 @@ pub mod ms {
                 delta: 0,
                 ready: false,
                 coeff_table: match &fmt_chunk.extension {
-                    None => DEF_COEFF_TABLE,
-                    Some(extension) => {
-                        if extension.ext_len < 12 {
-                            DEF_COEFF_TABLE
-                        } else {
-                            match extension.data {
-                                ExtensionData::AdpcmMs(adpcm_ms) => {
-                                    if adpcm_ms.num_coeff != 7 {
-                                        DEF_COEFF_TABLE
-                                    } else {
-                                        adpcm_ms.coeffs
-                                    }
-                                }
-                                _ => DEF_COEFF_TABLE,
-                            }
-                        }
-                    }
+                    // The blocks only choose from the first 7 coefficients, the rest of the table is ignored.
+                    Some(FmtExtension{ext_len, data: ExtensionData::AdpcmMs(adpcm_ms)})
+                        if *ext_len as usize >= AdpcmMsData::sizeof() && adpcm_ms.num_coeff >= 7 => adpcm_ms.coeffs,
+                    _ => DEF_COEFF_TABLE,
                 },
                 header_buffer: CopiableBuffer::<u8, 7>::new(),
                 bytes_eaten: 0,
                 max_bytes_can_eat: fmt_chunk.block_align as usize,
+                samples_per_block: samples_per_block(fmt_chunk),
+                samples_decoded: 0,
+            }
+        }
+
+        /// * Output the decoded sample unless the block already has all of its samples, the rest of the block is padding.
+        fn output_sample(&mut self, sample: i16, mut output: impl FnMut(i16)) {
+            if self.samples_decoded < self.samples_per_block {
+                self.samples_decoded += 1;
+                output(sample);
             }
         }
 
@@ -1221,6 +1759,7 @@ pub mod ms {
         pub fn unready(&mut self) {
             self.header_buffer.clear();
             self.bytes_eaten = 0;
+            self.samples_decoded = 0;
             self.ready = false;
         }
 
@@ -1256,6 +1795,7 @@ pub mod ms {
             self.sample2 = breakfast.sample2;
             self.ready = true;
             self.bytes_eaten += 7;
+            self.samples_decoded += 2;
             output(breakfast.sample2);
             output(breakfast.sample1);
             Ok(())
@@ -1286,6 +1826,8 @@ pub mod ms {
         core_r: DecoderCore,
         bytes_eaten: usize,
         max_bytes_can_eat: usize,
+        frames_per_block: usize,
+        frames_decoded: usize,
         ready: bool,
     }
 
@@ -1303,6 +1845,8 @@ pub mod ms {
                 core_r: DecoderCore::new(fmt_chunk),
                 bytes_eaten: 0,
                 max_bytes_can_eat: fmt_chunk.block_align as usize,
+                frames_per_block: samples_per_block(fmt_chunk),
+                frames_decoded: 0,
                 ready: false,
             }
         }
@@ -1317,6 +1861,7 @@ pub mod ms {
             self.core_l.unready();
             self.core_r.unready();
             self.bytes_eaten = 0;
+            self.frames_decoded = 0;
             self.ready = false;
         }
 
@@ -1346,6 +1891,7 @@ pub mod ms {
             output(sample_buffer[1]);
             output(sample_buffer[3]);
             self.bytes_eaten += 14;
+            self.frames_decoded += 2;
             self.ready = true;
             Ok(())
         }
@@ -1418,13 +1964,15 @@ pub mod ms {
             self.get_max_bytes_can_eat() / self.get_channels()
         }
 
-        /// Each byte stores two 4-bit samples (packed as high/low nibbles).
-        /// Effective decodable bytes per block: BLOCK_SIZE - HEADER_SIZE.
+        /// Each byte stores two 4-bit samples (packed as high/low nibbles), and the header stores two samples.
         /// Mono: Block size = BLOCK_SIZE.
         /// Stereo: Block size doubles (2×BLOCK_SIZE), but two samples (L+R) form one audio frame.
-        /// Thus, total samples = (BLOCK_SIZE - HEADER_SIZE) × 2 samples (1 frame per stereo pair).
+        /// The `samples_per_block` of the `fmt ` chunk extension decides it if the block can hold that many.
         fn frames_per_block(&self) -> usize {
-            (self.get_block_size() - HEADER_SIZE) * 2
+            match self {
+                Self::Mono(mono) => mono.samples_per_block,
+                Self::Stereo(stereo) => stereo.frames_per_block,
+            }
         }
 
         fn reset_states(&mut self) {
@@ -1448,8 +1996,10 @@ pub mod ms {
                                 mono.get_ready(&breakfast, |sample: i16| output(sample))?;
                             }
                         } else {
-                            output(mono.expand_nibble(byte >> 4));
-                            output(mono.expand_nibble(byte & 0x0F));
+                            let sample = mono.expand_nibble(byte >> 4);
+                            mono.output_sample(sample, &mut output);
+                            let sample = mono.expand_nibble(byte & 0x0F);
+                            mono.output_sample(sample, &mut output);
                             mono.bytes_eaten += 1;
                             if mono.bytes_eaten >= mono.max_bytes_can_eat {
                                 mono.unready();
@@ -1477,8 +2027,13 @@ pub mod ms {
                                 })?;
                             }
                         } else {
-                            output(stereo.core_l.expand_nibble(byte >> 4));
-                            output(stereo.core_r.expand_nibble(byte & 0x0F));
+                            let sample_l = stereo.core_l.expand_nibble(byte >> 4);
+                            let sample_r = stereo.core_r.expand_nibble(byte & 0x0F);
+                            if stereo.frames_decoded < stereo.frames_per_block {
+                                stereo.frames_decoded += 1;
+                                output(sample_l);
+                                output(sample_r);
+                            }
                             stereo.bytes_eaten += 1;
                             if stereo.bytes_eaten >= stereo.max_bytes_can_eat {
                                 stereo.unready();
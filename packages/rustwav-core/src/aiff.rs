@@ -0,0 +1,334 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+};
+
+use io_utils::Reader;
+use crate::chunks::{AdtlChunk, CueChunk, FmtChunk, InstChunk, LablChunk, ListChunk, SmplChunk, SmplSampleLoop};
+use crate::wavcore::CuePoint;
+use crate::format_specs::format_tags::*;
+use crate::errors::AudioReadError;
+
+// The AIFF and AIFF-C files are read by the `WaveReader`, their chunks are presented as the WAV chunks.
+// Everything in the AIFF file is big-endian, and the chunks are aligned to 2 bytes like the WAV file.
+
+fn read_bytes_be<const N: usize>(reader: &mut impl Reader) -> Result<[u8; N], AudioReadError> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn read_u16_be(reader: &mut impl Reader) -> Result<u16, AudioReadError> {
+    Ok(u16::from_be_bytes(read_bytes_be(reader)?))
+}
+
+pub fn read_i16_be(reader: &mut impl Reader) -> Result<i16, AudioReadError> {
+    Ok(i16::from_be_bytes(read_bytes_be(reader)?))
+}
+
+pub fn read_u32_be(reader: &mut impl Reader) -> Result<u32, AudioReadError> {
+    Ok(u32::from_be_bytes(read_bytes_be(reader)?))
+}
+
+/// * Read the 80-bit IEEE 754 extended precision number, the AIFF file stores the sample rate in it.
+pub fn read_extended(reader: &mut impl Reader) -> Result<f64, AudioReadError> {
+    let sign_exponent = read_u16_be(reader)?;
+    let mantissa = u64::from_be_bytes(read_bytes_be(reader)?);
+    if mantissa == 0 {
+        return Ok(0.0);
+    }
+    let exponent = (sign_exponent & 0x7FFF) as i32 - 16383 - 63;
+    let value = mantissa as f64 * 2f64.powi(exponent);
+    Ok(if sign_exponent & 0x8000 != 0 { -value } else { value })
+}
+
+/// * Read the Pascal-style string: a count byte, the text, and a pad byte if the total length is odd.
+pub fn read_pstring(reader: &mut impl Reader) -> Result<String, AudioReadError> {
+    let count = read_bytes_be::<1>(reader)?[0] as usize;
+    let mut buf = vec![0u8; count + (!count & 1)];
+    reader.read_exact(&mut buf)?;
+    buf.truncate(count);
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// * The chunk header of the AIFF file, the size is big-endian.
+#[derive(Clone, Copy, Default)]
+pub struct AiffChunkHeader {
+    pub flag: [u8; 4],
+    pub size: u32,
+    pub chunk_start_pos: u64,
+}
+
+impl AiffChunkHeader {
+    pub fn read_unseekable(reader: &mut impl Reader, cur_pos: &mut u64) -> Result<Self, AudioReadError> {
+        let flag = read_bytes_be::<4>(reader)?;
+        let size = read_u32_be(reader)?;
+        *cur_pos += 8;
+        Ok(Self {
+            flag,
+            size,
+            chunk_start_pos: *cur_pos,
+        })
+    }
+
+    /// * Calculate the position of the next chunk
+    pub fn next_chunk_pos(&self) -> u64 {
+        let end = self.chunk_start_pos + self.size as u64;
+        end + (end & 1)
+    }
+}
+
+impl Debug for AiffChunkHeader {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("AiffChunkHeader")
+        .field("flag", &String::from_utf8_lossy(&self.flag).into_owned())
+        .field("size", &format_args!("0x{:x}", self.size))
+        .field("chunk_start_pos", &format_args!("0x{:x}", self.chunk_start_pos))
+        .finish()
+    }
+}
+
+/// * How to turn the samples of the `SSND` chunk into the samples of the WAV `data` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleConversion {
+    /// * The samples are the same, e.g. the little-endian `sowt` samples, the A-law and the µ-law samples.
+    Keep,
+
+    /// * The big-endian samples of the given bytes are byte-swapped.
+    SwapBytes(usize),
+
+    /// * The signed 8-bit samples become the unsigned 8-bit samples.
+    FlipSign,
+}
+
+impl SampleConversion {
+    /// * The size of the unit to convert, the buffer to `convert()` must be a multiple of it.
+    pub fn unit_size(&self) -> usize {
+        match self {
+            Self::SwapBytes(bytes) => *bytes,
+            _ => 1,
+        }
+    }
+
+    pub fn convert(&self, buf: &mut [u8]) {
+        match self {
+            Self::Keep => (),
+            Self::SwapBytes(bytes) => buf.chunks_exact_mut(*bytes).for_each(|sample| sample.reverse()),
+            Self::FlipSign => buf.iter_mut().for_each(|byte| *byte ^= 0x80),
+        }
+    }
+}
+
+/// * The `COMM` chunk of the AIFF file, the format of the audio.
+#[derive(Debug, Clone, Default)]
+pub struct AiffCommChunk {
+    pub channels: u16,
+    pub num_frames: u32,
+    pub bits_per_sample: u16,
+    pub sample_rate: f64,
+
+    /// * Only the AIFF-C file has the compression type, it's `NONE` for the AIFF file.
+    pub compression_type: [u8; 4],
+    pub compression_name: String,
+}
+
+impl AiffCommChunk {
+    pub fn read(reader: &mut impl Reader, is_aifc: bool) -> Result<Self, AudioReadError> {
+        let mut ret = Self {
+            channels: read_u16_be(reader)?,
+            num_frames: read_u32_be(reader)?,
+            bits_per_sample: read_u16_be(reader)?,
+            sample_rate: read_extended(reader)?,
+            compression_type: *b"NONE",
+            compression_name: String::new(),
+        };
+        if is_aifc {
+            ret.compression_type = read_bytes_be(reader)?;
+            ret.compression_name = read_pstring(reader)?;
+        }
+        Ok(ret)
+    }
+
+    /// * Create the `fmt ` chunk of the same format and the conversion of the samples.
+    pub fn to_fmt_chunk(&self) -> Result<(FmtChunk, SampleConversion), AudioReadError> {
+        if self.channels == 0 {
+            return Err(AudioReadError::InvalidData(String::from("the \"COMM\" chunk has no channels")));
+        }
+        let int_bytes = self.bits_per_sample.div_ceil(8);
+        let (format_tag, bits_per_sample, conversion) = match &self.compression_type {
+            b"NONE" | b"twos" => match int_bytes {
+                1 => (FORMAT_TAG_PCM, 8, SampleConversion::FlipSign),
+                2..=4 => (FORMAT_TAG_PCM, int_bytes * 8, SampleConversion::SwapBytes(int_bytes as usize)),
+                _ => return Err(AudioReadError::Unsupported(format!("{} bits per sample of the AIFF file", self.bits_per_sample))),
+            },
+            b"sowt" => match int_bytes {
+                1 => (FORMAT_TAG_PCM, 8, SampleConversion::FlipSign),
+                2..=4 => (FORMAT_TAG_PCM, int_bytes * 8, SampleConversion::Keep),
+                _ => return Err(AudioReadError::Unsupported(format!("{} bits per sample of the AIFF file", self.bits_per_sample))),
+            },
+            b"raw " => (FORMAT_TAG_PCM, 8, SampleConversion::Keep),
+            b"fl32" | b"FL32" => (FORMAT_TAG_PCM_IEEE, 32, SampleConversion::SwapBytes(4)),
+            b"fl64" | b"FL64" => (FORMAT_TAG_PCM_IEEE, 64, SampleConversion::SwapBytes(8)),
+            b"alaw" | b"ALAW" => (FORMAT_TAG_ALAW, 8, SampleConversion::Keep),
+            b"ulaw" | b"ULAW" => (FORMAT_TAG_MULAW, 8, SampleConversion::Keep),
+            other => return Err(AudioReadError::Unsupported(format!(
+                "the AIFF-C compression type \"{}\" ({})",
+                String::from_utf8_lossy(other),
+                self.compression_name
+            ))),
+        };
+        let sample_rate = self.sample_rate.round() as u32;
+        let block_align = self.channels * (bits_per_sample / 8);
+        Ok((
+            FmtChunk {
+                format_tag,
+                channels: self.channels,
+                sample_rate,
+                byte_rate: sample_rate * block_align as u32,
+                block_align,
+                bits_per_sample,
+                extension: None,
+            },
+            conversion,
+        ))
+    }
+}
+
+/// * A marker of the `MARK` chunk, the position is the frame index.
+#[derive(Debug, Clone, Default)]
+pub struct AiffMarker {
+    pub id: u16,
+    pub position: u32,
+    pub name: String,
+}
+
+impl AiffMarker {
+    /// * Read all of the markers of the `MARK` chunk.
+    pub fn read_markers(reader: &mut impl Reader) -> Result<Vec<Self>, AudioReadError> {
+        let num_markers = read_u16_be(reader)?;
+        let mut markers = Vec::<Self>::with_capacity(num_markers as usize);
+        for _ in 0..num_markers {
+            markers.push(Self {
+                id: read_u16_be(reader)?,
+                position: read_u32_be(reader)?,
+                name: read_pstring(reader)?,
+            });
+        }
+        Ok(markers)
+    }
+
+    /// * Create the `cue ` chunk and the `LIST adtl` chunk with the labels, the cue point IDs are the marker IDs.
+    pub fn to_cue_chunks(markers: &[Self]) -> (CueChunk, ListChunk) {
+        let mut adtl_map = BTreeMap::<u32, Vec<AdtlChunk>>::new();
+        let cue_points: Vec<CuePoint> = markers.iter().map(|marker| {
+            let cue_point_id = marker.id as u32;
+            if !marker.name.is_empty() {
+                adtl_map.entry(cue_point_id).or_default().push(AdtlChunk::Labl(LablChunk {
+                    cue_point_id,
+                    data: marker.name.clone(),
+                }));
+            }
+            CuePoint {
+                cue_point_id,
+                position: marker.position as u64,
+                data_chunk_id: *b"data",
+                chunk_start: 0,
+                block_start: 0,
+                offset: marker.position as u64,
+            }
+        }).collect();
+        (
+            CueChunk {
+                num_cues: cue_points.len() as u32,
+                cue_points,
+            },
+            ListChunk::Adtl(adtl_map),
+        )
+    }
+}
+
+/// * A loop of the `INST` chunk, the beginning and the end are the marker IDs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AiffLoop {
+    /// * 0: no looping, 1: forward looping, 2: forward-backward looping.
+    pub play_mode: i16,
+    pub begin_marker: u16,
+    pub end_marker: u16,
+}
+
+impl AiffLoop {
+    pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
+        Ok(Self {
+            play_mode: read_i16_be(reader)?,
+            begin_marker: read_u16_be(reader)?,
+            end_marker: read_u16_be(reader)?,
+        })
+    }
+}
+
+/// * The `INST` chunk of the AIFF file, it's for the samplers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AiffInstChunk {
+    pub base_note: i8,
+    pub detune: i8,
+    pub low_note: i8,
+    pub high_note: i8,
+    pub low_velocity: i8,
+    pub high_velocity: i8,
+    pub gain: i16,
+    pub sustain_loop: AiffLoop,
+    pub release_loop: AiffLoop,
+}
+
+impl AiffInstChunk {
+    pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
+        let [base_note, detune, low_note, high_note, low_velocity, high_velocity] = read_bytes_be::<6>(reader)?.map(|byte| byte as i8);
+        Ok(Self {
+            base_note,
+            detune,
+            low_note,
+            high_note,
+            low_velocity,
+            high_velocity,
+            gain: read_i16_be(reader)?,
+            sustain_loop: AiffLoop::read(reader)?,
+            release_loop: AiffLoop::read(reader)?,
+        })
+    }
+
+    /// * Create the `inst` chunk, the gain in dB is clamped to the range of the `inst` chunk.
+    pub fn to_inst_chunk(&self) -> InstChunk {
+        InstChunk {
+            base_note: self.base_note as u8,
+            detune: self.detune as u8,
+            gain: self.gain.clamp(-64, 64) as i8 as u8,
+            low_note: self.low_note as u8,
+            high_note: self.high_note as u8,
+            low_velocity: self.low_velocity as u8,
+            high_velocity: self.high_velocity as u8,
+        }
+    }
+
+    /// * Create the `smpl` chunk with the sustain loop and the release loop, the marker IDs are resolved to the frame positions.
+    /// * Returns `None` if there are no loops or the markers of the loops are missing.
+    pub fn to_smpl_chunk(&self, markers: &[AiffMarker], sample_rate: u32) -> Option<SmplChunk> {
+        let mut smpl_chunk = SmplChunk::new(sample_rate, self.base_note as u8, 0);
+        for aiff_loop in [self.sustain_loop, self.release_loop] {
+            let loop_type = match aiff_loop.play_mode {
+                1 => SmplSampleLoop::LOOP_FORWARD,
+                2 => SmplSampleLoop::LOOP_ALTERNATING,
+                _ => continue,
+            };
+            let position_of = |id: u16| markers.iter().find(|marker| marker.id == id).map(|marker| marker.position);
+            if let (Some(begin), Some(end)) = (position_of(aiff_loop.begin_marker), position_of(aiff_loop.end_marker)) {
+                // The end marker of the AIFF loop is after the last frame of the loop, the end of the `smpl` loop is the last frame.
+                if end > begin {
+                    smpl_chunk.add_loop(begin, end - 1, loop_type, 0);
+                }
+            }
+        }
+        (!smpl_chunk.loops.is_empty()).then_some(smpl_chunk)
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    io::SeekFrom,
+    marker::PhantomData,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+    sync::{mpsc, oneshot},
+    task::{self, JoinHandle},
+};
+
+use crate::{FinalizeReport, SampleType, WaveDataSource, WaveReader, WaveWriter};
+use crate::errors::{AudioReadError, AudioWriteError};
+use crate::wavcore::{DataFormat, Spec};
+use crate::wavwriter::FileSizeOption;
+
+/// * How many frames the worker decodes for each block sent to the `AsyncWaveReader`.
+const BLOCK_FRAMES: usize = 4096;
+
+/// * How many blocks can wait in the channel, the worker waits when the channel is full.
+const CHANNEL_BLOCKS: usize = 4;
+
+/// * The async façade of the `WaveReader` for the tokio runtime.
+/// * The `WaveReader` decodes in a blocking worker task, the decoded frames come through a bounded channel,
+///   the worker stops decoding when the channel is full until the frames are taken by `next_frames()`.
+/// * The async reader is spooled into a temporary file first, the WAV file needs seeking to be parsed.
+pub struct AsyncWaveReader<S = f32>
+where
+    S: SampleType + Send + 'static,
+{
+    spec: Spec,
+    total_frames: Option<u64>,
+    receiver: mpsc::Receiver<Result<Vec<Vec<S>>, AudioReadError>>,
+    pending: VecDeque<Vec<S>>,
+    finished: bool,
+}
+
+impl<S> AsyncWaveReader<S>
+where
+    S: SampleType + Send + 'static,
+{
+    /// * Open the WAV file through a file path, and decode it in a blocking worker task.
+    pub async fn open(filename: &str) -> Result<Self, AudioReadError> {
+        Self::spawn(filename.to_owned(), None).await
+    }
+
+    /// * Read the whole WAV file from the async reader into a temporary file, then decode it in a blocking worker task.
+    pub async fn from_async_read(mut reader: impl AsyncRead + Unpin + Send + 'static) -> Result<Self, AudioReadError> {
+        let temp = tempfile::NamedTempFile::new()?;
+        let mut file = tokio::fs::File::from_std(temp.reopen()?);
+        tokio::io::copy(&mut reader, &mut file).await?;
+        file.flush().await?;
+        let filename = temp.path().to_string_lossy().into_owned();
+        Self::spawn(filename, Some(temp)).await
+    }
+
+    async fn spawn(filename: String, temp: Option<tempfile::NamedTempFile>) -> Result<Self, AudioReadError> {
+        let (info_sender, info_receiver) = oneshot::channel();
+        let (sender, receiver) = mpsc::channel(CHANNEL_BLOCKS);
+        task::spawn_blocking(move || {
+            // The temporary file lives as long as the worker.
+            let _temp = temp;
+            let wavereader = match WaveReader::new(WaveDataSource::Filename(filename)) {
+                Ok(wavereader) => wavereader,
+                Err(e) => {
+                    let _ = info_sender.send(Err(e));
+                    return;
+                }
+            };
+            let _ = info_sender.send(Ok((wavereader.spec(), wavereader.total_frames())));
+            let mut iter = match wavereader.frame_intoiter::<S>() {
+                Ok(iter) => iter,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            loop {
+                match iter.decode_frames(BLOCK_FRAMES) {
+                    Ok(block) if block.is_empty() => break,
+                    Ok(block) => {
+                        // The `AsyncWaveReader` was dropped, no one wants the frames.
+                        if sender.blocking_send(Ok(block)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.blocking_send(Err(e));
+                        break;
+                    }
+                }
+            }
+        });
+        let (spec, total_frames) = info_receiver.await.map_err(|_| {
+            AudioReadError::OtherReason(String::from("The worker task of the `AsyncWaveReader` quit unexpectedly"))
+        })??;
+        Ok(Self {
+            spec,
+            total_frames,
+            receiver,
+            pending: VecDeque::new(),
+            finished: false,
+        })
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// * The number of the audio frames, see `WaveReader::total_frames()`.
+    pub fn total_frames(&self) -> Option<u64> {
+        self.total_frames
+    }
+
+    /// * Get at most `num_frames` audio frames, fewer frames only at the end of the audio, empty after the end.
+    /// * The decoding error of the worker is returned once, then it's the end of the audio.
+    pub async fn next_frames(&mut self, num_frames: usize) -> Result<Vec<Vec<S>>, AudioReadError> {
+        while self.pending.len() < num_frames && !self.finished {
+            match self.receiver.recv().await {
+                Some(Ok(block)) => self.pending.extend(block),
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Err(e);
+                }
+                None => self.finished = true,
+            }
+        }
+        let num_frames = num_frames.min(self.pending.len());
+        Ok(self.pending.drain(..num_frames).collect())
+    }
+}
+
+impl<S> Debug for AsyncWaveReader<S>
+where
+    S: SampleType + Send + 'static,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("AsyncWaveReader")
+            .field("spec", &self.spec)
+            .field("total_frames", &self.total_frames)
+            .field("pending", &format_args!("[Vec<{}>; {}]", std::any::type_name::<S>(), self.pending.len()))
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// * The async façade of the `WaveWriter` for the tokio runtime.
+/// * The `WaveWriter` encodes in a blocking worker task into a temporary file, the frames go to it through a bounded channel,
+///   `write_frames()` waits when the channel is full.
+/// * `finalize()` finalizes the WAV file and copies it to the async writer, the WAV header can only be updated when the audio is finished.
+pub struct AsyncWaveWriter<S = f32>
+where
+    S: SampleType + Send + 'static,
+{
+    spec: Spec,
+    sender: Option<mpsc::Sender<Vec<Vec<S>>>>,
+    worker: Option<JoinHandle<Result<(std::fs::File, FinalizeReport), AudioWriteError>>>,
+    output: Box<dyn AsyncWrite + Unpin + Send>,
+    _sample: PhantomData<S>,
+}
+
+impl<S> AsyncWaveWriter<S>
+where
+    S: SampleType + Send + 'static,
+{
+    /// * Create the `WaveWriter` in a blocking worker task, the WAV file goes to `output` when it's finalized.
+    pub async fn new(
+        output: impl AsyncWrite + Unpin + Send + 'static,
+        spec: Spec,
+        data_format: DataFormat,
+        file_size_option: FileSizeOption,
+    ) -> Result<Self, AudioWriteError> {
+        let (ready_sender, ready_receiver) = oneshot::channel();
+        let (sender, mut receiver) = mpsc::channel::<Vec<Vec<S>>>(CHANNEL_BLOCKS);
+        let worker = task::spawn_blocking(move || -> Result<(std::fs::File, FinalizeReport), AudioWriteError> {
+            let file = tempfile::tempfile()?;
+            let created = file.try_clone().map_err(AudioWriteError::from).and_then(|writer| {
+                WaveWriter::from(Box::new(std::io::BufWriter::new(writer)), spec, data_format, file_size_option)
+            });
+            let mut wavewriter = match created {
+                Ok(wavewriter) => {
+                    let _ = ready_sender.send(Ok(()));
+                    wavewriter
+                }
+                Err(e) => {
+                    let _ = ready_sender.send(Err(e));
+                    return Err(AudioWriteError::AlreadyFinished(String::from("The `WaveWriter` wasn't created")));
+                }
+            };
+            while let Some(frames) = receiver.blocking_recv() {
+                wavewriter.write_frames(&frames)?;
+            }
+            let report = wavewriter.finalize()?;
+            Ok((file, report))
+        });
+        ready_receiver.await.map_err(|_| {
+            AudioWriteError::OtherReason(String::from("The worker task of the `AsyncWaveWriter` quit unexpectedly"))
+        })??;
+        Ok(Self {
+            spec,
+            sender: Some(sender),
+            worker: Some(worker),
+            output: Box::new(output),
+            _sample: PhantomData,
+        })
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// * Get the error of the worker task, it quits only by an error before finalizing.
+    async fn join_worker(&mut self) -> Result<(std::fs::File, FinalizeReport), AudioWriteError> {
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => worker.await.map_err(|e| {
+                AudioWriteError::OtherReason(format!("The worker task of the `AsyncWaveWriter` failed: {e}"))
+            })?,
+            None => Err(AudioWriteError::AlreadyFinished(String::from("The `AsyncWaveWriter` has failed"))),
+        }
+    }
+
+    /// * Send the frames to the worker to encode, waits if the worker is busy. The encoding error of the worker is returned here.
+    pub async fn write_frames(&mut self, frames: &[Vec<S>]) -> Result<(), AudioWriteError> {
+        let Some(sender) = &self.sender else {
+            return Err(AudioWriteError::AlreadyFinished(String::from("The `AsyncWaveWriter` has failed")));
+        };
+        if sender.send(frames.to_vec()).await.is_err() {
+            // The worker quits only by an error.
+            self.join_worker().await?;
+        }
+        Ok(())
+    }
+
+    /// * Finalize the WAV file and copy it to the async writer, see `WaveWriter::finalize()`.
+    pub async fn finalize(mut self) -> Result<FinalizeReport, AudioWriteError> {
+        let (file, report) = self.join_worker().await?;
+        let mut file = tokio::fs::File::from_std(file);
+        file.seek(SeekFrom::Start(0)).await?;
+        tokio::io::copy(&mut file, &mut self.output).await?;
+        self.output.flush().await?;
+        Ok(report)
+    }
+}
+
+impl<S> Debug for AsyncWaveWriter<S>
+where
+    S: SampleType + Send + 'static,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("AsyncWaveWriter")
+            .field("spec", &self.spec)
+            .field("finished", &self.sender.is_none())
+            .finish_non_exhaustive()
+    }
+}
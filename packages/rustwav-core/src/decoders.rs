@@ -1,16 +1,19 @@
 #![allow(dead_code)]
 #![allow(non_snake_case)]
 
-use std::{cmp::min, fmt::Debug, io::SeekFrom, marker::PhantomData};
+use std::{cmp::min, fmt::Debug, io::SeekFrom, marker::PhantomData, time::{Duration, Instant}};
 
-use xlaw::{PcmXLawDecoder, XLaw};
+use xlaw::XLaw;
 use io_utils::Reader;
 use downmixer::{Downmixer, DownmixerParams};
 use sampletypes::{SampleType, i24, u24};
 use crate::adpcm;
-use crate::format_specs::{Spec, WaveSampleType, format_tags::*};
+use crate::g711;
+use crate::gsm610;
+use crate::format_specs::{Spec, WaveSampleType, format_tags::*, guids::GUID};
+use crate::wavcore::{float_sample_from, is_float_to_float, is_sample_bytes_copiable};
 use crate::chunks::{FmtChunk, ext::{ExtensibleData, ExtensionData}};
-use crate::errors::{AudioError, AudioReadError};
+use crate::errors::{AudioError, AudioReadError, DecodeBudgetLimit, DecodeWarning};
 
 #[cfg(feature = "mp3dec")]
 use mp3::Mp3Decoder;
@@ -112,6 +115,39 @@ where
         Ok(stereos)
     }
 
+    /// Decode the interleaved samples into `buf`, as many whole frames as it can hold. Returns the number of the samples written, fewer at the end of the audio.
+    /// The default implementation goes through `decode_frame()`, the PCM, aLaw/MuLaw and ADPCM decoders fill `buf` without allocating.
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> {
+        let channels = self.get_channels() as usize;
+        if channels == 0 {
+            return Ok(0);
+        }
+        let mut written = 0;
+        for frame_buf in buf.chunks_exact_mut(channels) {
+            match self.decode_frame()? {
+                Some(frame) => frame_buf.copy_from_slice(&frame),
+                None => break,
+            }
+            written += channels;
+        }
+        Ok(written)
+    }
+
+    /// Skip multiple audio frames forward by seeking. If the decoder can't seek, the frames are decoded and discarded.
+    fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        match self.seek(SeekFrom::Current(num_frames as i64)) {
+            Err(AudioReadError::Unsupported(_)) | Err(AudioReadError::Unimplemented(_)) => {
+                for _ in 0..num_frames {
+                    if self.decode_frame()?.is_none() {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
     /// Set the downmixer
     fn set_downmixer(&mut self, _downmixer: &Downmixer) {
         eprintln!("This decoder doesn't supports downmixing");
@@ -121,6 +157,11 @@ where
     fn get_downmixer(&self) -> Option<Downmixer> {
         None
     }
+
+    /// Get the warnings recorded when the decoder recovered from corrupted packets.
+    fn get_warnings(&self) -> &[DecodeWarning] {
+        &[]
+    }
 }
 
 impl<S> Decoder<S> for PcmDecoder<S>
@@ -131,6 +172,7 @@ impl<S> Decoder<S> for PcmDecoder<S>
     fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame() }
     fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo() }
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono() }
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> { self.decode_interleaved(buf) }
     fn set_downmixer(&mut self, downmixer: &Downmixer) { self.downmixer = *downmixer; }
     fn get_downmixer(&self) -> Option<Downmixer> { Some(self.downmixer) }
 }
@@ -144,6 +186,7 @@ impl<S, D> Decoder<S> for AdpcmDecoderWrap<D>
     fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame::<S>() }
     fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo::<S>() }
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> { self.decode_interleaved::<S>(buf) }
 }
 
 impl<S> Decoder<S> for PcmXLawDecoderWrap
@@ -154,6 +197,21 @@ impl<S> Decoder<S> for PcmXLawDecoderWrap
     fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame::<S>() }
     fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo::<S>() }
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
+    fn decode_frames(&mut self, num_frames: usize) -> Result<Vec<Vec<S>>, AudioReadError> { self.decode_frames::<S>(num_frames) }
+    fn decode_monos(&mut self, num_monos: usize) -> Result<Vec<S>, AudioReadError> { self.decode_monos::<S>(num_monos) }
+    fn decode_stereos(&mut self, num_stereos: usize) -> Result<Vec<(S, S)>, AudioReadError> { self.decode_stereos::<S>(num_stereos) }
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> { self.decode_interleaved::<S>(buf) }
+}
+
+impl<S> Decoder<S> for Gsm610DecoderWrap
+    where S: SampleType {
+    fn get_channels(&self) -> u16 { 1 }
+    fn get_cur_frame_index(&mut self) -> Result<u64, AudioReadError> { Ok(Gsm610DecoderWrap::get_cur_frame_index(self)) }
+    fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> { self.seek(seek_from) }
+    fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame::<S>() }
+    fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo::<S>() }
+    fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> { self.decode_interleaved::<S>(buf) }
 }
 
 #[cfg(feature = "mp3dec")]
@@ -165,6 +223,7 @@ impl<S> Decoder<S> for Mp3Decoder
     fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame::<S>() }
     fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo::<S>() }
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
+    fn get_warnings(&self) -> &[DecodeWarning] { Mp3Decoder::get_warnings(self) }
 }
 
 #[cfg(feature = "opus")]
@@ -176,6 +235,7 @@ impl<S> Decoder<S> for OpusDecoder
     fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { self.decode_frame::<S>() }
     fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { self.decode_stereo::<S>() }
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
+    fn get_warnings(&self) -> &[DecodeWarning] { OpusDecoder::get_warnings(self) }
 }
 
 #[cfg(feature = "flac")]
@@ -202,6 +262,7 @@ impl<S> Decoder<S> for OggVorbisDecoderWrap
     fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { self.decode_mono::<S>() }
     fn set_downmixer(&mut self, downmixer: &Downmixer) { self.downmixer = *downmixer; }
     fn get_downmixer(&self) -> Option<Downmixer> { Some(self.downmixer) }
+    fn get_warnings(&self) -> &[DecodeWarning] { OggVorbisDecoderWrap::get_warnings(self) }
 }
 
 #[derive(Debug)]
@@ -225,9 +286,9 @@ where
         fmt: &FmtChunk,
         downmixer_params: Option<DownmixerParams>
     ) -> Result<Box<dyn Decoder<S>>, AudioError> {
-        if fmt.format_tag != FORMAT_TAG_EXTENSIBLE {
+        if fmt.format_tag != FORMAT_TAG_EXTENSIBLE && fmt.format_tag != FORMAT_TAG_DEVELOPMENT {
             Err(AudioError::InvalidArguments(
-                "The `format_tag` from `fmt ` chunk must be 0xFFFE for the extensible decoder."
+                "The `format_tag` from `fmt ` chunk must be 0xFFFE or 0xFFFF for the extensible decoder."
                     .to_string(),
             ))
         } else {
@@ -262,14 +323,17 @@ where
                         } else {
                             let spec = Spec {
                                 channels: spec.channels,
-                                channel_mask: extensible.channel_mask,
+                                channel_mask: Some(extensible.channel_mask),
                                 sample_rate: spec.sample_rate,
                                 bits_per_sample: spec.bits_per_sample,
                                 sample_format: spec.sample_format,
                             };
                             use crate::wavcore::guids::*;
                             match extensible.sub_format {
-                                GUID_PCM_FORMAT | GUID_IEEE_FLOAT_FORMAT => {
+                                GUID_PCM_FORMAT |
+                                GUID_IEEE_FLOAT_FORMAT |
+                                GUID_AMBISONIC_B_FORMAT_PCM |
+                                GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT => {
                                     Ok(Box::new(PcmDecoder::<S>::new(
                                         reader,
                                         data_offset,
@@ -295,6 +359,196 @@ where
     }
 }
 
+/// * The wall time is checked once per this number of frames to keep the budget check cheap.
+const BUDGET_CHECK_INTERVAL: u64 = 1024;
+
+/// * The function to create a decoder for a third-party codec.
+/// * The arguments are the reader, the offset and the length of the `data` chunk, the `fmt ` chunk, and the total samples from the `fact` chunk.
+pub type CustomDecoderCreator = fn(
+    reader: Box<dyn Reader>,
+    data_offset: u64,
+    data_length: u64,
+    fmt: &FmtChunk,
+    fact_data: u64,
+) -> Result<Box<dyn Decoder<i32>>, AudioReadError>;
+
+/// * The decoder of a third-party codec for the `WaveReader`, see `ReadOptions::custom_decoder`.
+/// * When the `format_tag` of the `fmt ` chunk is `format_tag`, the decoder is created by `create` instead of the built-in decoders.
+/// * With `sub_format`, the `SubFormat` GUID of the extensible data must be it too, e.g. for an experimental codec under the format tag `0xFFFF` or `0xFFFE`.
+/// * The decoder decodes `i32` samples, they are converted to the sample type of the iterators.
+#[derive(Clone, Copy)]
+pub struct CustomDecoder {
+    pub format_tag: u16,
+    pub sub_format: Option<GUID>,
+    pub create: CustomDecoderCreator,
+}
+
+impl CustomDecoder {
+    /// * Is the `fmt ` chunk for this decoder.
+    pub fn matches(&self, fmt: &FmtChunk) -> bool {
+        let fmt_sub_format = fmt.extension.as_ref().and_then(|extension| match &extension.data {
+            ExtensionData::Extensible(extensible) => Some(extensible.sub_format),
+            _ => None,
+        });
+        self.format_tag == fmt.format_tag && self.sub_format.is_none_or(|sub_format| fmt_sub_format == Some(sub_format))
+    }
+}
+
+impl Debug for CustomDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CustomDecoder")
+            .field("format_tag", &format_args!("0x{:04x}", self.format_tag))
+            .field("sub_format", &self.sub_format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomDecoder {
+    fn eq(&self, other: &Self) -> bool {
+        self.format_tag == other.format_tag && self.sub_format == other.sub_format && std::ptr::fn_addr_eq(self.create, other.create)
+    }
+}
+
+/// * Converts the `i32` samples from the decoder of a third-party codec to the sample type `S`.
+#[derive(Debug)]
+pub struct CustomDecoderWrap<S>
+where
+    S: SampleType,
+{
+    decoder: Box<dyn Decoder<i32>>,
+    phantom: PhantomData<S>,
+}
+
+impl<S> CustomDecoderWrap<S>
+where
+    S: SampleType,
+{
+    pub fn new(decoder: Box<dyn Decoder<i32>>) -> Self {
+        Self {
+            decoder,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> Decoder<S> for CustomDecoderWrap<S>
+    where S: SampleType {
+    fn get_channels(&self) -> u16 { self.decoder.get_channels() }
+    fn get_cur_frame_index(&mut self) -> Result<u64, AudioReadError> { self.decoder.get_cur_frame_index() }
+    fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> { self.decoder.seek(seek_from) }
+    fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> { Ok(self.decoder.decode_frame()?.map(|frame| frame.into_iter().map(S::scale_from).collect())) }
+    fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> { Ok(self.decoder.decode_stereo()?.map(|(l, r)| (S::scale_from(l), S::scale_from(r)))) }
+    fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> { Ok(self.decoder.decode_mono()?.map(S::scale_from)) }
+    fn set_downmixer(&mut self, downmixer: &Downmixer) { self.decoder.set_downmixer(downmixer) }
+    fn get_downmixer(&self) -> Option<Downmixer> { self.decoder.get_downmixer() }
+    fn get_warnings(&self) -> &[DecodeWarning] { self.decoder.get_warnings() }
+}
+
+/// * The `BudgetedDecoder<S>` wraps another decoder, counts the decoded frames and the time spent on decoding.
+/// * When the budget was exceeded, it returns `AudioReadError::BudgetExceeded` instead of decoding more.
+#[derive(Debug)]
+pub struct BudgetedDecoder<S>
+where
+    S: SampleType,
+{
+    decoder: Box<dyn Decoder<S>>,
+    max_decoded_frames: Option<u64>,
+    max_decode_wall_time: Option<Duration>,
+    frames_decoded: u64,
+    start_time: Option<Instant>,
+}
+
+impl<S> BudgetedDecoder<S>
+where
+    S: SampleType,
+{
+    pub fn new(decoder: Box<dyn Decoder<S>>, max_decoded_frames: Option<u64>, max_decode_wall_time: Option<Duration>) -> Self {
+        Self {
+            decoder,
+            max_decoded_frames,
+            max_decode_wall_time,
+            frames_decoded: 0,
+            start_time: None,
+        }
+    }
+
+    /// * The timer starts at the first decoded frame.
+    fn check_wall_time(&mut self) -> Result<(), AudioReadError> {
+        if let Some(max_decode_wall_time) = self.max_decode_wall_time {
+            if self.frames_decoded % BUDGET_CHECK_INTERVAL == 0 {
+                let start_time = *self.start_time.get_or_insert_with(Instant::now);
+                if start_time.elapsed() >= max_decode_wall_time {
+                    return Err(AudioReadError::BudgetExceeded(DecodeBudgetLimit::WallTime(max_decode_wall_time)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// * Count the decoded frame. The audio that has exactly `max_decoded_frames` frames ends normally.
+    fn count_frame(&mut self, decoded: bool) -> Result<(), AudioReadError> {
+        if decoded {
+            self.frames_decoded += 1;
+            if let Some(max_decoded_frames) = self.max_decoded_frames {
+                if self.frames_decoded > max_decoded_frames {
+                    return Err(AudioReadError::BudgetExceeded(DecodeBudgetLimit::Frames(max_decoded_frames)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// * How many frames were decoded through this decoder.
+    pub fn get_frames_decoded(&self) -> u64 {
+        self.frames_decoded
+    }
+}
+
+impl<S> Decoder<S> for BudgetedDecoder<S>
+    where S: SampleType {
+    fn get_channels(&self) -> u16 { self.decoder.get_channels() }
+    fn get_cur_frame_index(&mut self) -> Result<u64, AudioReadError> { self.decoder.get_cur_frame_index() }
+    fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> { self.decoder.seek(seek_from) }
+    fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> {
+        self.check_wall_time()?;
+        let frame = self.decoder.decode_frame()?;
+        self.count_frame(frame.is_some())?;
+        Ok(frame)
+    }
+    fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> {
+        self.check_wall_time()?;
+        let stereo = self.decoder.decode_stereo()?;
+        self.count_frame(stereo.is_some())?;
+        Ok(stereo)
+    }
+    fn decode_mono(&mut self) -> Result<Option<S>, AudioReadError> {
+        self.check_wall_time()?;
+        let mono = self.decoder.decode_mono()?;
+        self.count_frame(mono.is_some())?;
+        Ok(mono)
+    }
+    fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> {
+        self.check_wall_time()?;
+        let channels = self.decoder.get_channels().max(1) as usize;
+
+        // Never decode past the budget, the frames within it are returned. When the budget is used up, one more frame tells if the audio goes beyond it.
+        let max_frames = match self.max_decoded_frames {
+            Some(max_decoded_frames) => max_decoded_frames.saturating_sub(self.frames_decoded).max(1),
+            None => u64::MAX,
+        };
+        let length = buf.len().min(usize::try_from(max_frames).unwrap_or(usize::MAX).saturating_mul(channels));
+        let written = self.decoder.decode_interleaved(&mut buf[..length])?;
+        for _ in 0..written / channels {
+            self.count_frame(true)?;
+            self.check_wall_time()?;
+        }
+        Ok(written)
+    }
+    fn set_downmixer(&mut self, downmixer: &Downmixer) { self.decoder.set_downmixer(downmixer) }
+    fn get_downmixer(&self) -> Option<Downmixer> { self.decoder.get_downmixer() }
+    fn get_warnings(&self) -> &[DecodeWarning] { self.decoder.get_warnings() }
+}
+
 /// * The `PcmDecoder<S>` to decode WAV PCM samples to your specific format
 #[derive(Debug)]
 pub struct PcmDecoder<S>
@@ -307,7 +561,9 @@ where
     block_align: u16,
     total_frames: u64,
     spec: Spec,
-    sample_decoder: fn(&mut dyn Reader, &mut [S]) -> Result<(), AudioReadError>,
+    sample_decoder: fn(&[u8], &mut [S]) -> Result<(), AudioReadError>,
+    bytes: Vec<u8>,
+    cache_frames: usize,
     cache: Vec<S>,
     cache_position: u64,
     frame_index: u64,
@@ -318,7 +574,8 @@ impl<S> PcmDecoder<S>
 where
     S: SampleType,
 {
-    const CACHE_SIZE: usize = 4096;
+    /// * How many bytes are read from the reader at a time, the cache holds the whole frames in them.
+    const READ_BLOCK_SIZE: usize = 65536;
 
     pub fn new(
         reader: Box<dyn Reader>,
@@ -329,6 +586,7 @@ where
         downmixer_params: Option<DownmixerParams>,
     ) -> Result<Self, AudioError> {
         let wave_sample_type = spec.get_sample_type();
+        let cache_frames = (Self::READ_BLOCK_SIZE / fmt.block_align.max(1) as usize).max(1);
         Ok(Self {
             reader,
             data_offset,
@@ -337,10 +595,12 @@ where
             total_frames: data_length / fmt.block_align as u64,
             spec,
             sample_decoder: Self::choose_sample_decoder(wave_sample_type)?,
-            cache: Vec::with_capacity(Self::CACHE_SIZE),
+            bytes: Vec::new(),
+            cache_frames,
+            cache: Vec::new(),
             cache_position: 0,
             frame_index: 0,
-            downmixer: Downmixer::new(spec.channel_mask, downmixer_params.unwrap_or_default()),
+            downmixer: Downmixer::new(spec.effective_channel_mask(), downmixer_params.unwrap_or_default()),
         })
     }
 
@@ -380,32 +640,67 @@ where
         Ok(())
     }
 
-    fn decode_samples_to<T>(r: &mut dyn Reader, buf: &mut [S]) -> Result<(), AudioReadError>
+    /// * Convert the bytes read from the WAV file to the samples. Each function is specialized for the pair of the sample type `T` in the WAV file and `S`,
+    ///   the samples are parsed from the byte slice in a tight loop, not through the `dyn Reader` one by one.
+    fn decode_samples_to<T>(mut bytes: &[u8], buf: &mut [S]) -> Result<(), AudioReadError>
+    where
+        T: SampleType,
+    {
+        for sample in buf.iter_mut() {
+            *sample = S::scale_from(T::read_le(&mut bytes)?);
+        }
+        Ok(())
+    }
+
+    /// * The float samples are converted to the float type `S` as they are, the values out of `[-1.0, 1.0]` are kept.
+    fn decode_float_samples_to<T>(mut bytes: &[u8], buf: &mut [S]) -> Result<(), AudioReadError>
     where
         T: SampleType,
     {
         for sample in buf.iter_mut() {
-            *sample = S::scale_from(T::read_le(r)?);
+            *sample = float_sample_from::<S>(T::read_le(&mut bytes)?.to_f64());
         }
         Ok(())
     }
 
+    /// * The fast path for the same sample type in the WAV file, the bytes are copied as they are.
+    fn decode_sample_bytes(bytes: &[u8], buf: &mut [S]) -> Result<(), AudioReadError> {
+        // Safety: `is_sample_bytes_copiable()` checked by the `TypeId` that `S` is a primitive number type that any bytes are valid for it, and the bytes in the WAV file are its bytes in the memory.
+        let buf_bytes = unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, std::mem::size_of_val(buf)) };
+        buf_bytes.copy_from_slice(&bytes[..buf_bytes.len()]);
+        Ok(())
+    }
+
     #[allow(clippy::type_complexity)]
-    fn choose_sample_decoder(wave_sample_type: WaveSampleType) -> Result<fn(&mut dyn Reader, &mut [S]) -> Result<(), AudioReadError>, AudioError> {
+    fn choose_decode_fn<T>() -> fn(&[u8], &mut [S]) -> Result<(), AudioReadError>
+    where
+        T: SampleType,
+    {
+        if is_sample_bytes_copiable::<S, T>() {
+            Self::decode_sample_bytes
+        } else if is_float_to_float::<S, T>() {
+            Self::decode_float_samples_to::<T>
+        } else {
+            Self::decode_samples_to::<T>
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn choose_sample_decoder(wave_sample_type: WaveSampleType) -> Result<fn(&[u8], &mut [S]) -> Result<(), AudioReadError>, AudioError> {
         use WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64, Unknown};
         match wave_sample_type {
-            S8 =>  Ok(Self::decode_samples_to::<i8 >),
-            S16 => Ok(Self::decode_samples_to::<i16>),
-            S24 => Ok(Self::decode_samples_to::<i24>),
-            S32 => Ok(Self::decode_samples_to::<i32>),
-            S64 => Ok(Self::decode_samples_to::<i64>),
-            U8 =>  Ok(Self::decode_samples_to::<u8 >),
-            U16 => Ok(Self::decode_samples_to::<u16>),
-            U24 => Ok(Self::decode_samples_to::<u24>),
-            U32 => Ok(Self::decode_samples_to::<u32>),
-            U64 => Ok(Self::decode_samples_to::<u64>),
-            F32 => Ok(Self::decode_samples_to::<f32>),
-            F64 => Ok(Self::decode_samples_to::<f64>),
+            S8 =>  Ok(Self::choose_decode_fn::<i8 >()),
+            S16 => Ok(Self::choose_decode_fn::<i16>()),
+            S24 => Ok(Self::choose_decode_fn::<i24>()),
+            S32 => Ok(Self::choose_decode_fn::<i32>()),
+            S64 => Ok(Self::choose_decode_fn::<i64>()),
+            U8 =>  Ok(Self::choose_decode_fn::<u8 >()),
+            U16 => Ok(Self::choose_decode_fn::<u16>()),
+            U24 => Ok(Self::choose_decode_fn::<u24>()),
+            U32 => Ok(Self::choose_decode_fn::<u32>()),
+            U64 => Ok(Self::choose_decode_fn::<u64>()),
+            F32 => Ok(Self::choose_decode_fn::<f32>()),
+            F64 => Ok(Self::choose_decode_fn::<f64>()),
             Unknown => Err(AudioError::InvalidArguments(format!(
                 "unknown sample type \"{:?}\"",
                 wave_sample_type
@@ -413,19 +708,27 @@ where
         }
     }
 
+    /// * Make sure the frame at `frame_index` is in the cache, returns the index of its first sample in the cache. The cache keeps its capacity when refilled.
+    fn fill_cache(&mut self) -> Result<usize, AudioReadError> {
+        if self.is_frame_index_out_of_cache() {
+            self.clear_cache();
+        }
+        if self.cache.is_empty() {
+            // Read the whole frames of a block at once, then convert them, the buffers keep their capacity.
+            let num_frames_to_read = min(self.cache_frames, (self.total_frames - self.cache_position) as usize);
+            self.bytes.resize(num_frames_to_read * self.block_align as usize, 0);
+            self.reader.read_exact(&mut self.bytes)?;
+            self.cache.resize(num_frames_to_read * self.spec.channels as usize, S::new());
+            (self.sample_decoder)(&self.bytes, &mut self.cache)?;
+        }
+        Ok(((self.frame_index - self.cache_position) * self.spec.channels as u64) as usize)
+    }
+
     pub fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> {
         if self.is_end_of_data() {
             Ok(None)
         } else {
-            if self.is_frame_index_out_of_cache() {
-                self.clear_cache();
-            }
-            if self.cache.is_empty() {
-                let num_samples_to_read = min(Self::CACHE_SIZE, (self.total_frames - self.cache_position) as usize) * self.spec.channels as usize;
-                self.cache.resize(num_samples_to_read, S::new());
-                (self.sample_decoder)(&mut self.reader, &mut self.cache)?;
-            }
-            let sample_start = ((self.frame_index - self.cache_position) * self.spec.channels as u64) as usize;
+            let sample_start = self.fill_cache()?;
             let sample_end = sample_start + self.spec.channels as usize;
             let frame = self.cache[sample_start..sample_end].to_vec();
             self.frame_index += 1;
@@ -433,6 +736,20 @@ where
         }
     }
 
+    /// * Copy the whole frames from the cache into `buf`, refilling the cache as needed. Returns the number of the samples written.
+    pub fn decode_interleaved(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError> {
+        let channels = self.spec.channels as usize;
+        let mut written = 0;
+        while written + channels <= buf.len() && !self.is_end_of_data() {
+            let sample_start = self.fill_cache()?;
+            let length = min(self.cache.len() - sample_start, (buf.len() - written) / channels * channels);
+            buf[written..written + length].copy_from_slice(&self.cache[sample_start..sample_start + length]);
+            written += length;
+            self.frame_index += (length / channels) as u64;
+        }
+        Ok(written)
+    }
+
     pub fn decode_stereo(&mut self) -> Result<Option<(S, S)>, AudioReadError> {
         match self.decode_frame()? {
             None => Ok(None),
@@ -466,9 +783,11 @@ where
     frame_index: u64,
     frames_decoded: u64,
     total_frames: u64,
+    end_frame: Option<u64>,
     decoder: D,
     samples: Vec<i16>,
     first_frame_of_samples: u64,
+    block: Vec<u8>,
 }
 
 impl<D> AdpcmDecoderWrap<D>
@@ -499,9 +818,12 @@ where
             frame_index: 0,
             frames_decoded: 0,
             total_frames,
+            // The last block is padded, the `fact` chunk tells where the audio ends.
+            end_frame: if total_samples == 0 { None } else { Some(total_frames) },
             decoder,
             samples: Vec::<i16>::new(),
             first_frame_of_samples: 0,
+            block: Vec::<u8>::new(),
         })
     }
 
@@ -522,13 +844,17 @@ where
         let end_of_data = self.data_offset + self.data_length;
         let mut sample_decoded = 0u64;
         while self.samples.len() < wanted_length {
+            if self.end_frame.is_some_and(|end_frame| self.frames_decoded + sample_decoded / self.channels as u64 >= end_frame) {
+                break;
+            }
             let cur_pos = self.reader.stream_position()?;
             if cur_pos < end_of_data {
                 let remains = end_of_data - cur_pos;
                 let to_read = min(remains, self.block_align as u64);
-                let mut buf = vec![0u8; to_read as usize];
-                self.reader.read_exact(&mut buf)?;
-                let mut iter = buf.into_iter();
+                // The block buffer is reused, so is the sample buffer, the steady decoding doesn't allocate.
+                self.block.resize(to_read as usize, 0);
+                self.reader.read_exact(&mut self.block)?;
+                let mut iter = self.block.iter().copied();
                 self.decoder.decode(
                     || -> Option<u8> { iter.next() },
                     |sample: i16| {
@@ -544,7 +870,14 @@ where
                 break;
             }
         }
-        self.frames_decoded += sample_decoded / self.channels as u64;
+        let mut frames_decoded = sample_decoded / self.channels as u64;
+        if let Some(end_frame) = self.end_frame {
+            // Drop the padding frames of the last block.
+            let excess = (self.frames_decoded + frames_decoded).saturating_sub(end_frame).min(frames_decoded);
+            self.samples.truncate(self.samples.len() - (excess * self.channels as u64) as usize);
+            frames_decoded -= excess;
+        }
+        self.frames_decoded += frames_decoded;
         Ok(())
     }
 
@@ -688,9 +1021,53 @@ where
             ))),
         }
     }
+
+    /// * Convert the whole frames of the decoded blocks into `buf`, decoding the next block as needed. Returns the number of the samples written.
+    pub fn decode_interleaved<S>(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let channels = match self.channels {
+            1 => 1,
+            2 => 2,
+            o => return Err(AudioReadError::Unsupported(format!(
+                "Unsupported channels {o}"
+            ))),
+        };
+        let mut written = 0;
+        while written + channels <= buf.len() {
+            // Force-decodes at least 1 frame to ensure data availability, empty after feeding indicates end-of-stream
+            if self.samples.is_empty() {
+                self.feed_until_output(channels)?;
+                if self.samples.is_empty() {
+                    break;
+                }
+            }
+            if self.frame_index < self.first_frame_of_samples {
+                panic!(
+                    "Unknown error occured when decoding the ADPCM data: the sample cache was updated while the previous cache is needed: FI = {}, FF = {}",
+                    self.frame_index, self.first_frame_of_samples
+                );
+            } else if self.frame_index < self.frames_decoded {
+                let start = (self.frame_index - self.first_frame_of_samples) as usize * channels;
+                let length = min((self.frames_decoded - self.frame_index) as usize, (buf.len() - written) / channels) * channels;
+                for (sample, decoded) in buf[written..written + length].iter_mut().zip(&self.samples[start..start + length]) {
+                    *sample = S::scale_from(*decoded);
+                }
+                written += length;
+                self.frame_index += (length / channels) as u64;
+            } else {
+                // Need to decode the next block
+                self.first_frame_of_samples += (self.samples.len() / channels) as u64;
+                self.samples.clear();
+            }
+        }
+        Ok(written)
+    }
 }
 
 /// * The `PcmXLawDecoderWrap` to decode aLaw or MuLaw PCM data to your specific format samples
+/// * Each code is looked up in the G.711 table to get the `i16` sample, then converted to your sample type. For `i16` it's the table value as is.
 #[derive(Debug)]
 pub struct PcmXLawDecoderWrap {
     reader: Box<dyn Reader>,
@@ -699,7 +1076,7 @@ pub struct PcmXLawDecoderWrap {
     data_length: u64,
     total_frames: u64,
     frame_index: u64,
-    dec: PcmXLawDecoder,
+    table: &'static [i16; 256],
 }
 
 impl PcmXLawDecoderWrap {
@@ -727,12 +1104,47 @@ impl PcmXLawDecoderWrap {
             data_length,
             total_frames: total_samples / fmt.channels as u64,
             frame_index: 0,
-            dec: PcmXLawDecoder::new(which_law),
+            table: g711::decode_table(&which_law),
         })
     }
 
     fn decode(&mut self) -> Result<i16, AudioReadError> {
-        Ok(self.dec.decode(u8::read_le(&mut self.reader)?))
+        Ok(self.table[u8::read_le(&mut self.reader)? as usize])
+    }
+
+    /// * Decode up to `num_frames` frames into the interleaved `i16` samples by reading the codes at once, fewer at the end of the data.
+    pub fn decode_interleaved_i16(&mut self, num_frames: usize) -> Result<Vec<i16>, AudioReadError> {
+        let end_of_data = self.data_offset + self.data_length;
+        let channels = self.channels as u64;
+        let frames_left = end_of_data.saturating_sub(self.reader.stream_position()?) / channels;
+        let num_frames = min(num_frames as u64, frames_left);
+        let mut codes = vec![0u8; (num_frames * channels) as usize];
+        self.reader.read_exact(&mut codes)?;
+        self.frame_index += num_frames;
+        Ok(codes.into_iter().map(|code| self.table[code as usize]).collect())
+    }
+
+    /// * Decode the whole frames into `buf` through a buffer of codes on the stack. Returns the number of the samples written, fewer at the end of the data.
+    pub fn decode_interleaved<S>(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let end_of_data = self.data_offset + self.data_length;
+        let channels = self.channels as usize;
+        let frames_left = end_of_data.saturating_sub(self.reader.stream_position()?) / channels as u64;
+        let num_samples = min((buf.len() / channels) as u64, frames_left) as usize * channels;
+
+        // The size of the code buffer is even, each piece of `buf` has whole frames.
+        let mut codes = [0u8; 4096];
+        for piece in buf[..num_samples].chunks_mut(codes.len()) {
+            let piece_codes = &mut codes[..piece.len()];
+            self.reader.read_exact(piece_codes)?;
+            for (sample, code) in piece.iter_mut().zip(piece_codes.iter()) {
+                *sample = S::scale_from(self.table[*code as usize]);
+            }
+        }
+        self.frame_index += (num_samples / channels) as u64;
+        Ok(num_samples)
     }
 
     pub fn get_cur_frame_index(&self) -> u64 {
@@ -834,6 +1246,175 @@ impl PcmXLawDecoderWrap {
             ))),
         }
     }
+
+    pub fn decode_frames<S>(&mut self, num_frames: usize) -> Result<Vec<Vec<S>>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let samples = self.decode_interleaved_i16(num_frames)?;
+        Ok(samples.chunks(self.channels as usize).map(|frame| frame.iter().map(|s| S::scale_from(*s)).collect()).collect())
+    }
+
+    pub fn decode_monos<S>(&mut self, num_monos: usize) -> Result<Vec<S>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let samples = self.decode_interleaved_i16(num_monos)?;
+        Ok(match self.channels {
+            1 => samples.into_iter().map(S::scale_from).collect(),
+            _ => samples.chunks(2).map(|lr| S::average(S::scale_from(lr[0]), S::scale_from(lr[1]))).collect(),
+        })
+    }
+
+    pub fn decode_stereos<S>(&mut self, num_stereos: usize) -> Result<Vec<(S, S)>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let samples = self.decode_interleaved_i16(num_stereos)?;
+        Ok(match self.channels {
+            1 => samples.into_iter().map(|s| (S::scale_from(s), S::scale_from(s))).collect(),
+            _ => samples.chunks(2).map(|lr| (S::scale_from(lr[0]), S::scale_from(lr[1]))).collect(),
+        })
+    }
+}
+
+/// * The `Gsm610DecoderWrap` to decode the `WAVE_FORMAT_GSM610` data to your specific format samples
+/// * Each 65-byte block has two GSM frames packed together, it's decoded to 320 `i16` samples at once, then converted to your sample type.
+/// * The GSM decoder carries its states across the blocks. After seeking, it starts over from the block of the frame to seek to.
+#[derive(Debug)]
+pub struct Gsm610DecoderWrap {
+    reader: Box<dyn Reader>,
+    data_offset: u64,
+    total_frames: u64,
+    frame_index: u64,
+    decoder: gsm610::Gsm610Decoder,
+    block: [u8; gsm610::BLOCK_SIZE],
+    samples: [i16; gsm610::SAMPLES_PER_BLOCK],
+
+    /// * Which block is decoded into `samples`
+    block_index: Option<u64>,
+}
+
+impl Gsm610DecoderWrap {
+    pub fn new(
+        reader: Box<dyn Reader>,
+        data_offset: u64,
+        data_length: u64,
+        fmt: &FmtChunk,
+        total_samples: u64,
+    ) -> Result<Self, AudioReadError> {
+        if fmt.channels != 1 {
+            return Err(AudioReadError::Unsupported(format!(
+                "GSM 6.10 is mono only, but the `fmt ` chunk says {} channels",
+                fmt.channels
+            )));
+        }
+        if fmt.block_align as usize != gsm610::BLOCK_SIZE {
+            return Err(AudioReadError::Unsupported(format!(
+                "The block size of GSM 6.10 must be {}, got {}",
+                gsm610::BLOCK_SIZE, fmt.block_align
+            )));
+        }
+
+        // The extension of the `fmt ` chunk is the samples per block, the only valid value is 320.
+        let samples_per_block = match fmt.extension.as_ref().map(|extension| &extension.data) {
+            Some(ExtensionData::Raw(data)) if data.len() >= 2 => u16::from_le_bytes([data[0], data[1]]) as usize,
+            _ => gsm610::SAMPLES_PER_BLOCK,
+        };
+        if samples_per_block != gsm610::SAMPLES_PER_BLOCK {
+            return Err(AudioReadError::Unsupported(format!(
+                "The samples per block of GSM 6.10 must be {}, got {samples_per_block}",
+                gsm610::SAMPLES_PER_BLOCK
+            )));
+        }
+
+        let total_blocks = data_length / gsm610::BLOCK_SIZE as u64;
+        let frames_of_blocks = total_blocks * gsm610::SAMPLES_PER_BLOCK as u64;
+        Ok(Self {
+            reader,
+            data_offset,
+            total_frames: if total_samples == 0 { frames_of_blocks } else { min(total_samples, frames_of_blocks) },
+            frame_index: 0,
+            decoder: gsm610::Gsm610Decoder::new(),
+            block: [0u8; gsm610::BLOCK_SIZE],
+            samples: [0i16; gsm610::SAMPLES_PER_BLOCK],
+            block_index: None,
+        })
+    }
+
+    /// * Decode the sample of the current frame, the block is decoded if the frame is in another block.
+    fn decode(&mut self) -> Result<Option<i16>, AudioReadError> {
+        if self.frame_index >= self.total_frames {
+            return Ok(None);
+        }
+        let samples_per_block = gsm610::SAMPLES_PER_BLOCK as u64;
+        let block_index = self.frame_index / samples_per_block;
+        if self.block_index != Some(block_index) {
+            // Not the next block, the decoder can't continue from the previous block.
+            if self.block_index.map(|index| index + 1) != Some(block_index) {
+                self.decoder.reset_states();
+                self.reader.seek(SeekFrom::Start(
+                    self.data_offset + block_index * gsm610::BLOCK_SIZE as u64,
+                ))?;
+            }
+            self.reader.read_exact(&mut self.block)?;
+            self.decoder.decode_block(&self.block, &mut self.samples);
+            self.block_index = Some(block_index);
+        }
+        let sample = self.samples[(self.frame_index % samples_per_block) as usize];
+        self.frame_index += 1;
+        Ok(Some(sample))
+    }
+
+    pub fn get_cur_frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// * Seeking doesn't touch the file, the block is read on the next decoding.
+    pub fn seek(&mut self, from: SeekFrom) -> Result<(), AudioReadError> {
+        let frame_index = match from {
+            SeekFrom::Start(fi) => fi,
+            SeekFrom::Current(cur) => (self.frame_index as i64 + cur) as u64,
+            SeekFrom::End(end) => (self.total_frames as i64 + end) as u64,
+        };
+        self.frame_index = min(frame_index, self.total_frames);
+        Ok(())
+    }
+
+    pub fn decode_mono<S>(&mut self) -> Result<Option<S>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        Ok(self.decode()?.map(S::scale_from))
+    }
+
+    pub fn decode_stereo<S>(&mut self) -> Result<Option<(S, S)>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        Ok(self.decode()?.map(|s| (S::scale_from(s), S::scale_from(s))))
+    }
+
+    pub fn decode_frame<S>(&mut self) -> Result<Option<Vec<S>>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        Ok(self.decode()?.map(|s| vec![S::scale_from(s)]))
+    }
+
+    /// * Decode the samples into `buf`. Returns the number of the samples written, fewer at the end of the data.
+    pub fn decode_interleaved<S>(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError>
+    where
+        S: SampleType,
+    {
+        for (written, sample) in buf.iter_mut().enumerate() {
+            match self.decode()? {
+                Some(s) => *sample = S::scale_from(s),
+                None => return Ok(written),
+            }
+        }
+        Ok(buf.len())
+    }
 }
 
 /// * The MP3 decoder for `WaveReader`
@@ -846,10 +1427,12 @@ pub mod mp3 {
     };
 
     use crate::SampleType;
-    use crate::errors::AudioReadError;
+    use crate::errors::{AudioReadError, DecodeWarning, DecodeWarningKind};
+    use crate::options::DecodeErrorPolicy;
     use crate::io_utils::Reader;
     use crate::audioutils;
     use crate::chunks::FmtChunk;
+    use crate::wavcore::mp3::{Mp3VbrHeader, MP3_DECODER_DELAY};
 
     use resampler::Resampler;
     use rmp3::{DecoderOwned, Frame};
@@ -863,6 +1446,23 @@ pub mod mp3 {
         sample_pos: u64,
         total_frames: u64,
         resampler: Resampler,
+        data_offset: u64,
+        on_decode_error: DecodeErrorPolicy,
+        pending_frame: Option<Mp3AudioData>,
+        truncated: bool,
+        warnings: Vec<DecodeWarning>,
+
+        /// * Where the audio frames start, after the Xing/Info or VBRI frame if there is one.
+        start_position: usize,
+
+        /// * The seek table from the VBR header: the frame indices and the byte positions, for `seek_approximate()`.
+        seek_points: Vec<(u64, usize)>,
+
+        /// * The encoder delay and the decoder delay from the LAME tag, these frames are skipped.
+        leading_frames: u64,
+
+        /// * The number of frames of the audio without the padding, from the LAME tag.
+        valid_frames: Option<u64>,
     }
 
     impl Debug for Mp3Decoder {
@@ -875,6 +1475,15 @@ pub mod mp3 {
                 .field("sample_pos", &self.sample_pos)
                 .field("total_frames", &self.total_frames)
                 .field("resampler", &self.resampler)
+                .field("data_offset", &self.data_offset)
+                .field("on_decode_error", &self.on_decode_error)
+                .field("pending_frame", &self.pending_frame)
+                .field("truncated", &self.truncated)
+                .field("warnings", &self.warnings)
+                .field("start_position", &self.start_position)
+                .field("seek_points", &format_args!("[(u64, usize); {}]", self.seek_points.len()))
+                .field("leading_frames", &self.leading_frames)
+                .field("valid_frames", &self.valid_frames)
                 .finish()
         }
     }
@@ -915,12 +1524,31 @@ pub mod mp3 {
             data_length: u64,
             fmt: &FmtChunk,
             total_samples: u64,
+            on_decode_error: DecodeErrorPolicy,
         ) -> Result<Self, AudioReadError> {
             let mut reader = reader;
             let mut mp3_raw_data = vec![0u8; data_length as usize];
             reader.seek(SeekFrom::Start(data_offset))?;
             reader.read_exact(&mut mp3_raw_data)?;
-            let the_decoder = rmp3::DecoderOwned::new(mp3_raw_data);
+
+            // The Xing/Info or VBRI frame isn't audio, it tells the length and the seek table.
+            // The LAME tag after the Xing/Info header tells how many frames to trim for gapless playback.
+            let vbr_header = Mp3VbrHeader::parse(&mp3_raw_data);
+            let start_position = vbr_header.as_ref().map_or(0, |(_, frame_end)| *frame_end);
+            let (leading_frames, valid_frames, seek_points) = match vbr_header {
+                Some((header, _)) => {
+                    // The decoder resamples to the sample rate of the `fmt ` chunk.
+                    let to_target = |frames: u64| frames * fmt.sample_rate as u64 / header.sample_rate as u64;
+                    let leading_frames = header.gapless.map_or(0, |info| to_target((info.encoder_delay + MP3_DECODER_DELAY) as u64));
+                    let seek_points = header.seek_points.iter().map(|&(frame_index, position)| {
+                        (to_target(frame_index * header.samples_per_frame as u64), position.max(start_position))
+                    }).collect();
+                    (leading_frames, header.total_samples_at(fmt.sample_rate), seek_points)
+                }
+                None => (0, None, Vec::new()),
+            };
+            let mut the_decoder = rmp3::DecoderOwned::new(mp3_raw_data);
+            the_decoder.set_position(start_position);
             let mut ret = Self {
                 target_sample_rate: fmt.sample_rate,
                 target_channels: fmt.channels,
@@ -929,20 +1557,43 @@ pub mod mp3 {
                 sample_pos: 0,
                 total_frames: total_samples,
                 resampler: Resampler::new(Resampler::get_rounded_up_fft_size(fmt.sample_rate)),
+                data_offset,
+                on_decode_error,
+                pending_frame: None,
+                truncated: false,
+                warnings: Vec::new(),
+                start_position,
+                seek_points,
+                leading_frames,
+                valid_frames,
             };
             ret.cur_frame = ret.get_next_frame();
             if let Some(ref mp3frame) = ret.cur_frame {
                 ret.total_frames /= mp3frame.channels as u64;
             }
+
+            // Without the `fact` chunk, the length is from the VBR header.
+            if ret.total_frames == 0 {
+                ret.total_frames = valid_frames.unwrap_or(0);
+            }
+            ret.seek_raw(leading_frames)?;
             Ok(ret)
         }
 
         fn reset(&mut self) {
-            self.the_decoder.set_position(0);
+            self.the_decoder.set_position(self.start_position);
+            self.cur_frame = None;
+            self.pending_frame = None;
+            self.truncated = false;
             self.cur_frame = self.get_next_frame();
             self.sample_pos = 0;
         }
 
+        /// * The warnings recorded when the non-MP3 data between two MP3 frames was skipped or truncated.
+        pub fn get_warnings(&self) -> &[DecodeWarning] {
+            &self.warnings
+        }
+
         fn do_resample(&self, samples: &[i16], channels: u16, src_sample_rate: u32) -> Vec<i16> {
             let process_size = self.resampler.get_process_size(
                 self.resampler.get_fft_size(),
@@ -969,12 +1620,33 @@ pub mod mp3 {
         }
 
         fn get_next_frame(&mut self) -> Option<Mp3AudioData> {
-            while let Some(frame) = self.the_decoder.next() {
-                if let Frame::Audio(audio) = frame {
-                    if let Some(cur_frame) = &self.cur_frame {
-                        self.sample_pos += cur_frame.sample_count as u64;
-                    }
+            let ret = match self.pending_frame.take() {
+                Some(frame) => Some(frame),
+                None => self.decode_next_frame(),
+            };
+            if ret.is_some() {
+                if let Some(cur_frame) = &self.cur_frame {
+                    self.sample_pos += cur_frame.sample_count as u64;
+                }
+            }
+            ret
+        }
 
+        /// * The decoder resyncs after the data that isn't an MP3 frame. If the data was found between two MP3 frames, it's a corrupted frame.
+        fn decode_next_frame(&mut self) -> Option<Mp3AudioData> {
+            if self.truncated {
+                return None;
+            }
+            let mut bad_data: Option<(u64, usize)> = None;
+            loop {
+                let position = self.data_offset + self.the_decoder.position() as u64;
+                let frame = self.the_decoder.next()?;
+                if let Frame::Other(data) = frame {
+                    match bad_data {
+                        Some((_, ref mut length)) => *length += data.len(),
+                        None => bad_data = Some((position, data.len())),
+                    }
+                } else if let Frame::Audio(audio) = frame {
                     let mut ret = Mp3AudioData {
                         bitrate: audio.bitrate(),
                         channels: audio.channels(),
@@ -1019,13 +1691,36 @@ pub mod mp3 {
                     ret.sample_rate = self.target_sample_rate;
                     ret.sample_count = ret.samples.len() / ret.channels as usize;
 
+                    if let (Some((offset, length)), Some(prev_frame)) = (bad_data, &self.cur_frame) {
+                        let prev_sample_count = prev_frame.sample_count;
+                        match self.on_decode_error {
+                            DecodeErrorPolicy::Abort => (),
+                            DecodeErrorPolicy::SkipPacket => {
+                                self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, offset, format!("Skipped {length} bytes of corrupted MP3 data.")));
+                                let silence = Mp3AudioData {
+                                    sample_count: prev_sample_count,
+                                    samples: vec![0; prev_sample_count * ret.channels as usize],
+                                    buffer_index: 0,
+                                    ..ret.clone()
+                                };
+                                self.pending_frame = Some(ret);
+                                return Some(silence);
+                            }
+                            DecodeErrorPolicy::TruncateStream => {
+                                self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, offset, format!("Truncated at {length} bytes of corrupted MP3 data.")));
+                                self.truncated = true;
+                                return None;
+                            }
+                        }
+                    }
+
                     return Some(ret);
                 }
             }
-            None
         }
 
-        pub fn get_cur_frame_index(&self) -> u64 {
+        /// * The frame index of the decoded audio, including the leading frames of the encoder delay.
+        fn get_raw_frame_index(&self) -> u64 {
             if let Some(frame) = &self.cur_frame {
                 self.sample_pos + (frame.buffer_index as u64)
             } else {
@@ -1033,12 +1728,55 @@ pub mod mp3 {
             }
         }
 
-        pub fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> {
-            let frame_index = match seek_from {
+        pub fn get_cur_frame_index(&self) -> u64 {
+            self.get_raw_frame_index().saturating_sub(self.leading_frames)
+        }
+
+        /// * The padding after the valid frames from the LAME tag isn't audio.
+        fn is_end_of_audio(&self) -> bool {
+            self.valid_frames.is_some_and(|valid_frames| self.get_raw_frame_index() >= self.leading_frames + valid_frames)
+        }
+
+        fn get_seek_target(&self, seek_from: SeekFrom) -> u64 {
+            match seek_from {
                 SeekFrom::Start(fi) => fi,
                 SeekFrom::Current(cur) => (self.get_cur_frame_index() as i64 + cur) as u64,
                 SeekFrom::End(end) => (self.total_frames as i64 + end) as u64,
+            }
+        }
+
+        pub fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> {
+            let frame_index = self.get_seek_target(seek_from);
+            self.seek_raw(frame_index + self.leading_frames)
+        }
+
+        /// * Seek fast by the seek table of the Xing/Info or VBRI header, the frames before the nearest seek point aren't decoded.
+        /// * The seek table is approximate, so is the position. The Xing TOC has a point for each percent of the audio.
+        /// * The frame after the seek point may sound wrong, its bit reservoir is in the frames that weren't decoded.
+        /// * Without the seek table, it's the same as `seek()`.
+        pub fn seek_approximate(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> {
+            let frame_index = self.get_seek_target(seek_from) + self.leading_frames;
+            let seek_point = self.seek_points.iter().rev().find(|(point_frame_index, _)| *point_frame_index <= frame_index).copied();
+            let Some((point_frame_index, position)) = seek_point else {
+                return self.seek_raw(frame_index);
             };
+
+            // Decoding on is faster if the target is in the current segment.
+            if (point_frame_index..=frame_index).contains(&self.get_raw_frame_index()) && self.cur_frame.is_some() {
+                return self.seek_raw(frame_index);
+            }
+
+            // The decoder resyncs to the next frame. Without the previous frame, the data before it isn't reported as corrupted.
+            self.the_decoder.set_position(position);
+            self.cur_frame = None;
+            self.pending_frame = None;
+            self.truncated = false;
+            self.sample_pos = point_frame_index;
+            self.cur_frame = self.get_next_frame();
+            self.seek_raw(frame_index)
+        }
+
+        fn seek_raw(&mut self, frame_index: u64) -> Result<(), AudioReadError> {
             if self.sample_pos > frame_index {
                 self.reset();
             }
@@ -1072,6 +1810,9 @@ pub mod mp3 {
         }
 
         pub fn decode_mono_raw(&mut self) -> Result<Option<i16>, AudioReadError> {
+            if self.is_end_of_audio() {
+                return Ok(None);
+            }
             match self.cur_frame {
                 None => Ok(None),
                 Some(ref mut frame) => match frame.channels {
@@ -1100,6 +1841,9 @@ pub mod mp3 {
         }
 
         pub fn decode_stereo_raw(&mut self) -> Result<Option<(i16, i16)>, AudioReadError> {
+            if self.is_end_of_audio() {
+                return Ok(None);
+            }
             match self.cur_frame {
                 None => Ok(None),
                 Some(ref mut frame) => match frame.channels {
@@ -1170,29 +1914,137 @@ pub mod mp3 {
 #[cfg(feature = "opus")]
 pub mod opus {
     use std::{
+        cmp::min,
+        ffi::CStr,
         fmt::{self, Debug, Formatter},
         io::SeekFrom,
+        os::raw::c_int,
     };
 
     use crate::SampleType;
-    use crate::errors::AudioReadError;
-    use crate::chunks::FmtChunk;
+    use crate::errors::{AudioReadError, DecodeWarning, DecodeWarningKind};
+    use crate::chunks::{FmtChunk, ext::{ExtensionData, FmtExtension}};
+    use crate::options::DecodeErrorPolicy;
     use crate::io_utils::Reader;
+    use crate::wavcore::{OpusData, OpusHead, opus::OPUS_MAX_CHANNELS};
+    use downmixer::speaker_positions::guess_channel_mask;
 
+    use audiopus_sys as ffi;
     use opus::{self, Channels, Decoder, ErrorCode};
 
+    /// * The libopus multistream decoder for 3 to 8 channels, the `opus` crate only has the mono and stereo decoder.
+    pub struct OpusMultistreamDecoder {
+        decoder: *mut ffi::OpusMSDecoder,
+        channels: u16,
+    }
+
+    impl OpusMultistreamDecoder {
+        /// * The mapping of the `OpusData` is in the WAV channel order, the decoded channels are in the WAV channel order too.
+        pub fn new(sample_rate: u32, channels: u16, opus_data: &OpusData) -> Result<Self, AudioReadError> {
+            if channels as usize > opus_data.mapping.len() {
+                return Err(AudioReadError::InvalidArguments(format!(
+                    "Bad channels: {channels} for the opus multistream decoder."
+                )));
+            }
+            let mut error: c_int = 0;
+            let decoder = unsafe {
+                ffi::opus_multistream_decoder_create(
+                    sample_rate as i32,
+                    channels as c_int,
+                    opus_data.streams as c_int,
+                    opus_data.coupled_streams as c_int,
+                    opus_data.mapping.as_ptr(),
+                    &mut error,
+                )
+            };
+            if decoder.is_null() || error < 0 {
+                return Err(opus_error_code("opus_multistream_decoder_create", error));
+            }
+            Ok(Self { decoder, channels })
+        }
+
+        /// * Decode the packet into the interleaved samples, returns the number of the decoded frames.
+        pub fn decode_float(&mut self, input: &[u8], output: &mut [f32], fec: bool) -> Result<usize, AudioReadError> {
+            let ret = unsafe {
+                ffi::opus_multistream_decode_float(
+                    self.decoder,
+                    input.as_ptr(),
+                    input.len() as i32,
+                    output.as_mut_ptr(),
+                    (output.len() / self.channels as usize) as c_int,
+                    fec as c_int,
+                )
+            };
+            if ret < 0 {
+                Err(opus_error_code("opus_multistream_decode_float", ret))
+            } else {
+                Ok(ret as usize)
+            }
+        }
+    }
+
+    impl Drop for OpusMultistreamDecoder {
+        fn drop(&mut self) {
+            unsafe { ffi::opus_multistream_decoder_destroy(self.decoder) };
+        }
+    }
+
+    impl Debug for OpusMultistreamDecoder {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            f.debug_struct("OpusMultistreamDecoder")
+                .field("decoder", &self.decoder)
+                .field("channels", &self.channels)
+                .finish()
+        }
+    }
+
+    /// * The error code returned by the libopus functions.
+    fn opus_error_code(function: &str, code: c_int) -> AudioReadError {
+        let description = unsafe { CStr::from_ptr(ffi::opus_strerror(code)) }.to_string_lossy();
+        let info = format!("On calling `{function}`: {description}");
+        match code {
+            -1 => AudioReadError::InvalidArguments(info),
+            -2 => AudioReadError::BufferTooSmall(info),
+            -4 => AudioReadError::InvalidData(info),
+            -5 => AudioReadError::Unimplemented(info),
+            _ => AudioReadError::OtherReason(info),
+        }
+    }
+
+    /// * Mono and stereo go with the plain Opus decoder, more channels go with the multistream decoder.
+    #[derive(Debug)]
+    enum OpusDecoderKind {
+        Plain(Decoder),
+        Multistream(OpusMultistreamDecoder),
+    }
+
+    impl OpusDecoderKind {
+        fn decode_float(&mut self, input: &[u8], output: &mut [f32], fec: bool) -> Result<usize, AudioReadError> {
+            match self {
+                Self::Plain(decoder) => Ok(decoder.decode_float(input, output, fec)?),
+                Self::Multistream(decoder) => decoder.decode_float(input, output, fec),
+            }
+        }
+    }
+
     pub struct OpusDecoder {
         reader: Box<dyn Reader>,
-        decoder: Decoder,
+        decoder: OpusDecoderKind,
         channels: u16,
         sample_rate: u32,
         data_offset: u64,
         data_length: u64,
         total_frames: u64,
         block_align: usize,
+        frames_per_block: u64,
+        packet_offsets: Option<Vec<u64>>,
         decoded_samples: Vec<f32>,
         decoded_samples_index: usize,
         frame_index: u64,
+        pre_skip: u64,
+        on_decode_error: DecodeErrorPolicy,
+        truncated: bool,
+        warnings: Vec<DecodeWarning>,
     }
 
     impl OpusDecoder {
@@ -1202,33 +2054,210 @@ pub mod opus {
             data_length: u64,
             fmt: &FmtChunk,
             total_samples: u64,
+            on_decode_error: DecodeErrorPolicy,
         ) -> Result<Self, AudioReadError> {
             let channels = fmt.channels;
             let sample_rate = fmt.sample_rate;
-            let opus_channels = match channels {
-                1 => Channels::Mono,
-                2 => Channels::Stereo,
-                o => {
+            let block_align = fmt.block_align as usize;
+
+            // The files from the other tools carry the `OpusHead` in the `fmt ` chunk or at the beginning of the `data` chunk.
+            let (mut data_offset, mut data_length) = (data_offset, data_length);
+            let (opus_head, head_is_prefixed) = match &fmt.extension {
+                Some(FmtExtension { data: ExtensionData::OpusHead(opus_head), .. }) => (Some(opus_head.clone()), false),
+                _ => match Self::read_opus_head(reader.as_mut(), data_offset, data_length)? {
+                    Some((opus_head, is_prefixed, headers_length)) => {
+                        data_offset += headers_length;
+                        data_length -= headers_length;
+                        (Some(opus_head), is_prefixed)
+                    }
+                    None => (None, false),
+                },
+            };
+            let opus_data = match (&opus_head, &fmt.extension) {
+                (Some(opus_head), _) => {
+                    if opus_head.channels as u16 != channels {
+                        return Err(AudioReadError::InvalidData(format!(
+                            "The `OpusHead` has {} channels, the `fmt ` chunk has {channels} channels.",
+                            opus_head.channels
+                        )));
+                    }
+                    Some(opus_head.to_opus_data(sample_rate, guess_channel_mask(channels).unwrap_or(0)))
+                }
+                (None, Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. })) => Some(*opus_data),
+                (None, _) => None,
+            };
+
+            let decoder = match (channels, &opus_data) {
+                (1, _) => OpusDecoderKind::Plain(Decoder::new(sample_rate, Channels::Mono)?),
+                (2, _) => OpusDecoderKind::Plain(Decoder::new(sample_rate, Channels::Stereo)?),
+                (3..=OPUS_MAX_CHANNELS, Some(opus_data)) if opus_data.mapping_family != 0 => {
+                    OpusDecoderKind::Multistream(OpusMultistreamDecoder::new(sample_rate, channels, opus_data)?)
+                }
+                (o, _) => {
                     return Err(AudioReadError::InvalidArguments(format!(
                         "Bad channels: {o} for the opus decoder."
                     )));
                 }
             };
-            let decoder = Decoder::new(sample_rate, opus_channels)?;
             reader.seek(SeekFrom::Start(data_offset))?;
-            Ok(Self {
+
+            // Without the Opus extension data, the encoder delay is kept, and the `fact` chunk tells the length if there is one.
+            // With `frames_per_packet`, the packets are prefixed by their sizes, otherwise every packet is `block_align` bytes.
+            // With the `OpusHead`, the packets are prefixed by their sizes if the `OpusHead` is, or if `block_align` doesn't tell the packet size.
+            let (pre_skip, frames_per_block, packet_offsets, total_frames) = match opus_data {
+                Some(opus_data) if opus_head.is_some() => {
+                    let packet_offsets = if head_is_prefixed || block_align <= 1 {
+                        let (packet_offsets, packets_end) = Self::scan_packets(reader.as_mut(), data_offset, data_length)?;
+                        data_length = packets_end - data_offset;
+                        Some(packet_offsets)
+                    } else {
+                        None
+                    };
+                    let frames_per_block = Self::peek_packet_frames(reader.as_mut(), data_offset, data_length, packet_offsets.is_some(), block_align, sample_rate)?;
+                    let num_packets = match &packet_offsets {
+                        Some(packet_offsets) => packet_offsets.len() as u64,
+                        None => data_length / block_align as u64,
+                    };
+                    let total_frames = match total_samples {
+                        0 => (num_packets * frames_per_block).saturating_sub(opus_data.pre_skip as u64),
+                        total_samples => total_samples / channels as u64,
+                    };
+                    (opus_data.pre_skip as u64, frames_per_block, packet_offsets, total_frames)
+                }
+                Some(opus_data) if opus_data.frames_per_packet > 0 => {
+                    let frames_per_block = opus_data.frames_per_packet as u64;
+                    let (packet_offsets, packets_end) = Self::scan_packets(reader.as_mut(), data_offset, data_length)?;
+                    // A truncated packet at the end is not counted.
+                    data_length = packets_end - data_offset;
+                    let encoded_frames = packet_offsets.len() as u64 * frames_per_block;
+                    (opus_data.pre_skip as u64, frames_per_block, Some(packet_offsets), encoded_frames.saturating_sub(opus_data.padding as u64))
+                }
+                Some(opus_data) => {
+                    let frames_per_block = (block_align / channels as usize) as u64;
+                    let encoded_frames = data_length / block_align as u64 * frames_per_block;
+                    (opus_data.pre_skip as u64, frames_per_block, None, encoded_frames.saturating_sub(opus_data.padding as u64))
+                }
+                None => (0, (block_align / channels as usize) as u64, None, total_samples / channels as u64),
+            };
+            if frames_per_block == 0 {
+                return Err(AudioReadError::InvalidArguments(format!(
+                    "Bad block size for the opus decoder: block_align = {block_align}, channels = {channels}."
+                )));
+            }
+            let mut ret = Self {
                 reader,
                 decoder,
                 channels,
                 sample_rate,
                 data_offset,
                 data_length,
-                total_frames: total_samples / channels as u64,
-                block_align: fmt.block_align as usize,
+                total_frames,
+                block_align,
+                frames_per_block,
+                packet_offsets,
                 decoded_samples: Vec::<f32>::new(),
                 decoded_samples_index: 0,
                 frame_index: 0,
-            })
+                pre_skip,
+                on_decode_error,
+                truncated: false,
+                warnings: Vec::new(),
+            };
+            if pre_skip > 0 {
+                ret.seek(SeekFrom::Start(0))?;
+            }
+            Ok(ret)
+        }
+
+        /// * Look for the `OpusHead` at the beginning of the `data` chunk, either as is or as a size-prefixed packet, and skip the `OpusTags` after it.
+        /// * Returns the `OpusHead`, whether it's size-prefixed, and the length of the headers before the audio packets.
+        fn read_opus_head(reader: &mut dyn Reader, data_offset: u64, data_length: u64) -> Result<Option<(OpusHead, bool, u64)>, AudioReadError> {
+            let mut head = vec![0u8; min(data_length, 2 + 21 + 255) as usize];
+            reader.seek(SeekFrom::Start(data_offset))?;
+            reader.read_exact(&mut head)?;
+            let ret = if head.starts_with(&OpusHead::MAGIC) {
+                let opus_head = OpusHead::parse(&head)?;
+                let mut headers_length = opus_head.sizeof() as u64;
+
+                // The `OpusTags` may carry pictures, skip it by its fields rather than reading it.
+                reader.seek(SeekFrom::Start(data_offset + headers_length))?;
+                let mut magic = [0u8; 8];
+                if headers_length + 16 <= data_length && reader.read_exact(&mut magic).is_ok() && &magic == b"OpusTags" {
+                    let read_u32 = |reader: &mut dyn Reader| -> Result<u32, AudioReadError> {
+                        let mut buf = [0u8; 4];
+                        reader.read_exact(&mut buf)?;
+                        Ok(u32::from_le_bytes(buf))
+                    };
+                    let vendor_length = read_u32(reader)?;
+                    reader.seek(SeekFrom::Current(vendor_length as i64))?;
+                    let mut tags_length = 8 + 4 + vendor_length as u64 + 4;
+                    for _ in 0..read_u32(reader)? {
+                        let comment_length = read_u32(reader)?;
+                        reader.seek(SeekFrom::Current(comment_length as i64))?;
+                        tags_length += 4 + comment_length as u64;
+                    }
+                    headers_length += tags_length;
+                }
+                Some((opus_head, false, min(headers_length, data_length)))
+            } else if head.len() >= 10 && head[2..].starts_with(&OpusHead::MAGIC) {
+                let head_size = u16::from_le_bytes([head[0], head[1]]) as u64;
+                let opus_head = OpusHead::parse(&head[2..])?;
+                let mut headers_length = 2 + head_size;
+                reader.seek(SeekFrom::Start(data_offset + headers_length))?;
+                let mut tags = [0u8; 10];
+                if headers_length + 10 <= data_length && reader.read_exact(&mut tags).is_ok() && &tags[2..] == b"OpusTags" {
+                    headers_length += 2 + u16::from_le_bytes([tags[0], tags[1]]) as u64;
+                }
+                Some((opus_head, true, min(headers_length, data_length)))
+            } else {
+                None
+            };
+            reader.seek(SeekFrom::Start(data_offset))?;
+            Ok(ret)
+        }
+
+        /// * Get the number of frames in the first packet from its TOC byte, the packets of the other tools are normally 20 ms.
+        fn peek_packet_frames(reader: &mut dyn Reader, data_offset: u64, data_length: u64, is_prefixed: bool, block_align: usize, sample_rate: u32) -> Result<u64, AudioReadError> {
+            let default_frames = sample_rate as u64 / 50;
+            reader.seek(SeekFrom::Start(data_offset))?;
+            let packet_size = if is_prefixed {
+                if data_length < 2 {
+                    return Ok(default_frames);
+                }
+                let mut size = [0u8; 2];
+                reader.read_exact(&mut size)?;
+                u16::from_le_bytes(size) as usize
+            } else {
+                block_align
+            };
+            if packet_size == 0 || packet_size as u64 > data_length {
+                reader.seek(SeekFrom::Start(data_offset))?;
+                return Ok(default_frames);
+            }
+            let mut packet = vec![0u8; packet_size];
+            reader.read_exact(&mut packet)?;
+            reader.seek(SeekFrom::Start(data_offset))?;
+            Ok(opus::packet::get_nb_samples(&packet, sample_rate).map_or(default_frames, |frames| frames as u64))
+        }
+
+        /// * Find out where each size-prefixed packet begins, returns the offsets and where the last complete packet ends.
+        fn scan_packets(reader: &mut dyn Reader, data_offset: u64, data_length: u64) -> Result<(Vec<u64>, u64), AudioReadError> {
+            let data_end = data_offset + data_length;
+            let mut packet_offsets = Vec::<u64>::new();
+            let mut offset = data_offset;
+            while offset + 2 <= data_end {
+                let mut size = [0u8; 2];
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.read_exact(&mut size)?;
+                let next_offset = offset + 2 + u16::from_le_bytes(size) as u64;
+                if next_offset > data_end {
+                    break;
+                }
+                packet_offsets.push(offset);
+                offset = next_offset;
+            }
+            reader.seek(SeekFrom::Start(data_offset))?;
+            Ok((packet_offsets, offset))
         }
 
         pub fn get_channels(&self) -> u16 {
@@ -1243,8 +2272,13 @@ pub mod opus {
             self.frame_index
         }
 
+        /// * The warnings recorded when a corrupted packet was skipped or truncated.
+        pub fn get_warnings(&self) -> &[DecodeWarning] {
+            &self.warnings
+        }
+
         fn is_end_of_data(&mut self) -> Result<bool, AudioReadError> {
-            if self.reader.stream_position()? >= self.data_offset + self.data_length {
+            if self.truncated || self.reader.stream_position()? >= self.data_offset + self.data_length {
                 Ok(true)
             } else {
                 Ok(false)
@@ -1257,7 +2291,11 @@ pub mod opus {
         }
 
         fn get_samples_per_block(&self) -> usize {
-            self.block_align
+            self.frames_per_block as usize * self.channels as usize
+        }
+
+        fn get_frames_per_block(&self) -> u64 {
+            self.frames_per_block
         }
 
         fn decode_block(&mut self) -> Result<(), AudioReadError> {
@@ -1267,8 +2305,16 @@ pub mod opus {
             }
 
             // Prepare the buffers
-            let mut buf = vec![0u8; self.block_align];
             let samples_to_get = self.get_samples_per_block();
+            let packet_offset = self.reader.stream_position()?;
+            let packet_size = if self.packet_offsets.is_some() {
+                let mut size = [0u8; 2];
+                self.reader.read_exact(&mut size)?;
+                u16::from_le_bytes(size) as usize
+            } else {
+                self.block_align
+            };
+            let mut buf = vec![0u8; packet_size];
             self.reader.read_exact(&mut buf)?;
             self.decoded_samples = vec![0.0; samples_to_get];
 
@@ -1276,9 +2322,24 @@ pub mod opus {
             self.decoded_samples_index = 0;
 
             // Perform the decode call
-            let frames =
-                self.decoder
-                    .decode_float(&buf, &mut self.decoded_samples, /*fec*/ false)?;
+            let frames = match self.decoder.decode_float(&buf, &mut self.decoded_samples, /*fec*/ false) {
+                Ok(frames) => frames,
+                Err(e) => match self.on_decode_error {
+                    DecodeErrorPolicy::Abort => return Err(e.into()),
+                    DecodeErrorPolicy::SkipPacket => {
+                        // Excrete the silence of the packet to keep the timing.
+                        self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, packet_offset, format!("Skipped the Opus packet: {e}")));
+                        self.decoded_samples.fill(0.0);
+                        return Ok(());
+                    }
+                    DecodeErrorPolicy::TruncateStream => {
+                        self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, packet_offset, format!("Truncated at the Opus packet: {e}")));
+                        self.truncated = true;
+                        self.clear_decoded_samples_buffer();
+                        return Ok(());
+                    }
+                },
+            };
 
             // Check out the result
             let samples = frames * self.channels as usize;
@@ -1298,14 +2359,24 @@ pub mod opus {
                 SeekFrom::End(end) => (self.total_frames as i64 + end) as u64,
             };
             self.frame_index = frame_index;
-            let block_align = self.block_align as u64;
-            let block_index = frame_index / block_align;
-            let seek_to = self.data_offset + block_index * block_align;
+            self.truncated = false;
+
+            // The decoded audio is delayed by the encoder for `pre_skip` frames.
+            let decoded_frame_index = frame_index + self.pre_skip;
+            let frames_per_block = self.get_frames_per_block();
+            let block_index = decoded_frame_index / frames_per_block;
+            let seek_to = match &self.packet_offsets {
+                Some(packet_offsets) => match packet_offsets.get(block_index as usize) {
+                    Some(offset) => *offset,
+                    None => self.data_offset + self.data_length,
+                },
+                None => self.data_offset + block_index * self.block_align as u64,
+            };
             self.reader.seek(SeekFrom::Start(seek_to))?;
             if seek_to < self.data_offset + self.data_length {
                 self.decode_block()?;
-                self.decoded_samples_index = ((frame_index * self.channels as u64)
-                    - block_index * self.get_samples_per_block() as u64)
+                self.decoded_samples_index = ((decoded_frame_index - block_index * frames_per_block)
+                    * self.channels as u64)
                     as usize;
             } else {
                 self.clear_decoded_samples_buffer();
@@ -1329,23 +2400,32 @@ pub mod opus {
             }
         }
 
-        pub fn decode_mono<S>(&mut self) -> Result<Option<S>, AudioReadError>
+        /// * Decode one sample for every channel. The padding at the end is not excreted.
+        fn decode_samples_of_frame<S>(&mut self) -> Result<Option<Vec<S>>, AudioReadError>
         where
             S: SampleType,
         {
-            let frame: Result<Vec<Option<S>>, AudioReadError> = (0..self.channels)
-                .map(|_| self.decode_sample::<S>())
-                .collect();
-            match frame {
-                Ok(frame) => {
-                    let frame: Vec<S> = frame.into_iter().flatten().collect();
-                    if frame.is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(S::average_arr(&frame)))
-                    }
+            if self.total_frames > 0 && self.frame_index >= self.total_frames {
+                return Ok(None);
+            }
+            let mut frame = Vec::<S>::with_capacity(self.channels as usize);
+            for _ in 0..self.channels {
+                match self.decode_sample::<S>()? {
+                    Some(sample) => frame.push(sample),
+                    None => return Ok(None),
                 }
-                Err(e) => Err(e),
+            }
+            self.frame_index += 1;
+            Ok(Some(frame))
+        }
+
+        pub fn decode_mono<S>(&mut self) -> Result<Option<S>, AudioReadError>
+        where
+            S: SampleType,
+        {
+            match self.decode_samples_of_frame::<S>()? {
+                Some(frame) => Ok(Some(S::average_arr(&frame))),
+                None => Ok(None),
             }
         }
 
@@ -1353,28 +2433,18 @@ pub mod opus {
         where
             S: SampleType,
         {
-            match self.channels {
-                1 => {
-                    if let Some(s) = self.decode_sample::<S>()? {
-                        self.frame_index += 1;
-                        Ok(Some((s, s)))
-                    } else {
-                        Ok(None)
-                    }
-                }
-                2 => {
-                    let l = self.decode_sample::<S>()?;
-                    let r = self.decode_sample::<S>()?;
-                    if l.is_some() || r.is_some() {
-                        self.frame_index += 1;
-                        Ok(Some((l.unwrap(), r.unwrap())))
-                    } else {
-                        Ok(None)
-                    }
-                }
-                o => Err(AudioReadError::InvalidData(format!(
-                    "Can't convert {o} channel audio to stereo channel audio"
-                ))),
+            if self.channels > 2 {
+                return Err(AudioReadError::InvalidData(format!(
+                    "Can't convert {} channel audio to stereo channel audio", self.channels
+                )));
+            }
+            match self.decode_samples_of_frame::<S>()? {
+                Some(frame) => match frame[..] {
+                    [s] => Ok(Some((s, s))),
+                    [l, r] => Ok(Some((l, r))),
+                    _ => unreachable!(),
+                },
+                None => Ok(None),
             }
         }
 
@@ -1382,20 +2452,7 @@ pub mod opus {
         where
             S: SampleType,
         {
-            let frame: Result<Vec<Option<S>>, AudioReadError> = (0..self.channels)
-                .map(|_| self.decode_sample::<S>())
-                .collect();
-            match frame {
-                Ok(frame) => {
-                    let frame: Vec<S> = frame.into_iter().flatten().collect();
-                    if frame.is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(frame))
-                    }
-                }
-                Err(e) => Err(e),
-            }
+            self.decode_samples_of_frame::<S>()
         }
     }
 
@@ -1410,12 +2467,18 @@ pub mod opus {
                 .field("data_length", &self.data_length)
                 .field("total_frames", &self.total_frames)
                 .field("block_align", &self.block_align)
+                .field("frames_per_block", &self.frames_per_block)
+                .field(
+                    "packet_offsets",
+                    &self.packet_offsets.as_ref().map(|offsets| format!("[u64; {}]", offsets.len())),
+                )
                 .field(
                     "decoded_samples",
                     &format_args!("[f32; {}]", self.decoded_samples.len()),
                 )
                 .field("decoded_samples_index", &self.decoded_samples_index)
                 .field("frame_index", &self.frame_index)
+                .field("pre_skip", &self.pre_skip)
                 .finish()
         }
     }
@@ -1441,7 +2504,6 @@ pub mod opus {
 pub mod flac_dec {
     use std::{
         cmp::Ordering,
-        collections::BTreeMap,
         fmt::{self, Debug, Formatter},
         io::{self, ErrorKind, Read, Seek, SeekFrom},
         ptr,
@@ -1458,8 +2520,8 @@ pub mod flac_dec {
     use io_utils::Reader;
     use audioutils::{do_resample_frames, sample_conv, sample_conv_batch};
     use crate::errors::{AudioReadError, IOErrorInfo};
-    use crate::chunks::{FmtChunk, ListChunk, ListInfo};
-    use crate::wavcore::flac::get_listinfo_flacmeta;
+    use crate::chunks::{FmtChunk, InfoDict, ListChunk, ListInfo};
+    use crate::wavcore::flac::{FLAC_CHANNEL_MASK_TAG, get_flac_channel_mask, get_flac_channel_order, get_listinfo_flacmeta};
 
     /// A wrapper for the WaveReader to decode FLAC audio encapsulated in the WAV file.
     pub struct FlacDecoderWrap<'a> {
@@ -1496,6 +2558,15 @@ pub mod flac_dec {
         /// A boxed pointer points to this struct itself. The `extern "system"` callback functions rely on this poiner to convert the call to our closure calls.
         self_ptr: Box<*mut FlacDecoderWrap<'a>>,
 
+        /// The channel mask of the FLAC audio, from the `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` comment or by the FLAC channel order. `None` before the comments were read.
+        channel_mask: Option<u32>,
+
+        /// The WAV channel of each FLAC channel, `None` if the FLAC channels are already in the WAV channel order.
+        channel_order: Option<Vec<usize>>,
+
+        /// Whether the downmixer was created by us, then it follows the channel mask.
+        downmixer_by_channel_mask: bool,
+
         /// The downmixer for multiple channels audio to decode into 2 or 1 channels
         pub downmixer: Downmixer,
     }
@@ -1589,6 +2660,11 @@ pub mod flac_dec {
                             this.decoded_frames = frames.to_vec();
                         }
 
+                        // The comments were read before the first audio block.
+                        if this.channel_mask.is_none() {
+                            this.load_channel_order();
+                        }
+                        this.reorder_decoded_frames();
                         Ok(())
                     },
                 ),
@@ -1600,6 +2676,7 @@ pub mod flac_dec {
                 true, // scale_to_i32_range
                 FlacAudioForm::FrameArray,
             )?);
+            let downmixer_by_channel_mask = downmixer.is_none();
             let downmixer = if let Some(downmixer) = downmixer {
                 downmixer
             } else {
@@ -1629,6 +2706,9 @@ pub mod flac_dec {
                 frame_index: 0,
                 total_frames: total_samples / fmt.channels as u64,
                 self_ptr,
+                channel_mask: None,
+                channel_order: None,
+                downmixer_by_channel_mask,
                 downmixer,
             };
             *ret.self_ptr = &mut ret as *mut Self;
@@ -1640,6 +2720,48 @@ pub mod flac_dec {
             Ok(self.decoder.eof())
         }
 
+        // Without the `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` comment, the channels are in the FLAC channel order, which is the WAV channel order of its channel mask.
+        // With the comment, the channels were rearranged into the FLAC channel order if the speaker positions fit, otherwise they are in the WAV channel order.
+        fn load_channel_order(&mut self) {
+            let channels = self.channels;
+            let tagged_mask = self.decoder.get_comments().get(FLAC_CHANNEL_MASK_TAG).and_then(|value| {
+                let value = value.trim();
+                let digits = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+                u32::from_str_radix(digits, 16).ok()
+            }).filter(|mask| mask.count_ones() == channels as u32);
+            let channel_mask = match tagged_mask {
+                Some(channel_mask) => {
+                    self.channel_order = get_flac_channel_order(channels, channel_mask)
+                        .filter(|order| order.iter().enumerate().any(|(i, channel)| i != *channel));
+                    if self.downmixer_by_channel_mask {
+                        self.downmixer = Downmixer::new(channel_mask, downmixer::DownmixerParams::new());
+                    }
+                    channel_mask
+                }
+                None => get_flac_channel_mask(channels),
+            };
+            self.channel_mask = Some(channel_mask);
+        }
+
+        // Put the channels of the decoded frames back to the WAV channel order.
+        fn reorder_decoded_frames(&mut self) {
+            let Some(order) = &self.channel_order else {
+                return;
+            };
+            let mut flac_frame = vec![0i32; order.len()];
+            for frame in self.decoded_frames.iter_mut() {
+                flac_frame.copy_from_slice(frame);
+                for (i, channel) in order.iter().enumerate() {
+                    frame[*channel] = flac_frame[i];
+                }
+            }
+        }
+
+        /// * The channel mask of the decoded frames, from the `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` comment, or the one of the FLAC channel order without the comment.
+        pub fn get_channel_mask(&self) -> u32 {
+            self.channel_mask.unwrap_or_else(|| get_flac_channel_mask(self.channels))
+        }
+
         fn clear_decoded_frames(&mut self) {
             self.decoded_frames.clear();
             self.decoded_frames_index = 0;
@@ -1660,7 +2782,7 @@ pub mod flac_dec {
 
         pub fn get_metadata_as_list(&self) -> Result<ListChunk, AudioReadError> {
             let comments = self.decoder.get_comments();
-            let mut listinfo = ListChunk::Info(BTreeMap::<String, String>::new());
+            let mut listinfo = ListChunk::Info(InfoDict::new());
 
             for (list_key, flac_key) in get_listinfo_flacmeta().iter() {
                 if let Some(data) = comments.get(flac_key.to_owned()) {
@@ -1892,9 +3014,9 @@ pub mod oggvorbis_dec {
     use vorbis_rs::VorbisDecoder;
     use downmixer::Downmixer;
     use sampletypes::SampleType;
-    use crate::errors::{AudioReadError, IOErrorInfo};
-    use crate::chunks::{FmtChunk, ext::ExtensionData};
-    use crate::options::{OggVorbisMode, OggVorbisEncoderParams};
+    use crate::errors::{AudioReadError, DecodeWarning, DecodeWarningKind, IOErrorInfo};
+    use crate::chunks::{FmtChunk, ext::{ExtensionData, OggVorbisData}};
+    use crate::options::{DecodeErrorPolicy, OggVorbisMode, OggVorbisEncoderParams};
 
     type OggVorbisHeaderToBodyCombinedReader = CombinedReader<CursorVecU8, SharedReader<Box<dyn Reader>>>;
     type OggVorbisDecoderReader = SharedReader<DishonestReader<OggVorbisHeaderToBodyCombinedReader>>;
@@ -1933,6 +3055,18 @@ pub mod oggvorbis_dec {
 
         /// The downmixer for multiple channels audio to decode into 2 or 1 channels
         pub downmixer: Downmixer,
+
+        /// What to do when the decoder fails to decode a block
+        on_decode_error: DecodeErrorPolicy,
+
+        /// The offset of the last block that failed to decode, if it fails at the same offset again, the stream is truncated.
+        last_error_offset: Option<u64>,
+
+        /// Is the stream truncated by the `DecodeErrorPolicy::TruncateStream` policy
+        truncated: bool,
+
+        /// The warnings recorded when a corrupted block was skipped or truncated
+        warnings: Vec<DecodeWarning>,
     }
 
     // ## An shared `OggStreamWriteToCursor`
@@ -1965,14 +3099,48 @@ pub mod oggvorbis_dec {
         }
     }
 
+    /// * Regenerate the Vorbis header for `OggVorbisMode::HaveNoCodebookHeader`: an encoder with the same parameters writes the same header as the one the encoder discarded.
+    /// * The stream serial is taken from the first Ogg page of the `data` chunk, then the header pages continue into the audio pages.
+    fn regenerate_vorbis_header(reader: &mut Box<dyn Reader>, data_offset: u64, fmt: &FmtChunk, oggvorbis_data: &OggVorbisData) -> Result<Vec<u8>, AudioReadError> {
+        let mut page_header = [0u8; 18];
+        reader.seek(SeekFrom::Start(data_offset))?;
+        reader.read_exact(&mut page_header)?;
+        reader.seek(SeekFrom::Start(data_offset))?;
+        if &page_header[0..4] != b"OggS" {
+            return Err(AudioReadError::FormatError("The `data` chunk of the OggVorbis audio doesn't begin with an Ogg page.".to_string()));
+        }
+        let stream_serial = i32::from_le_bytes([page_header[14], page_header[15], page_header[16], page_header[17]]);
+        let header = SharedCursor::new();
+        let mut builder = OggVorbisEncoderParams {
+            mode: OggVorbisMode::HaveNoCodebookHeader,
+            channels: fmt.channels,
+            sample_rate: fmt.sample_rate,
+            stream_serial: Some(stream_serial),
+            bitrate: oggvorbis_data.bitrate,
+            minimum_page_data_size: None,
+            granule_offset: 0,
+            max_frames_per_write: 16384,
+        }.create_vorbis_builder(header.clone()).map_err(|err| AudioReadError::FormatError(format!("Can't create the encoder to regenerate the Vorbis header: {err}")))?;
+
+        // The encoder of `WaveWriter` puts only this comment in the header.
+        builder.comment_tag("ENCODER", "rustwav")?;
+        let encoder = builder.build()?;
+
+        // The encoder writes the end of the stream when it's dropped, take the header before that.
+        let vorbis_header = header.get_vec();
+        drop(encoder);
+        Ok(vorbis_header)
+    }
+
     impl OggVorbisDecoderWrap {
         pub fn new(
-            reader: Box<dyn Reader>,
+            mut reader: Box<dyn Reader>,
             data_offset: u64,
             data_length: u64,
             fmt: &FmtChunk,
             total_samples: u64,
             downmixer: Option<Downmixer>,
+            on_decode_error: DecodeErrorPolicy,
         ) -> Result<Self, AudioReadError> {
             use crate::wavcore::format_tags::*;
             let mut ogg_stream_writer: Option<SharedOggStreamWriteToCursor> = None;
@@ -1986,7 +3154,7 @@ pub mod oggvorbis_dec {
                             return Err(AudioReadError::FormatError("The extension data of the `fmt ` chunk provides the Ogg Vorbis header data, but the `format_tag` value indicates that there shouldn't need to be any Ogg Vorbis header data in the `fmt ` chunk.".to_string()));
                         }
                     }
-                    ExtensionData::OggVorbis(_) => {
+                    ExtensionData::OggVorbis(oggvorbis_data) => {
                         if [
                             FORMAT_TAG_OGG_VORBIS1,
                             FORMAT_TAG_OGG_VORBIS1P,
@@ -1996,16 +3164,7 @@ pub mod oggvorbis_dec {
                             FORMAT_TAG_OGG_VORBIS3,
                             FORMAT_TAG_OGG_VORBIS3P,
                         ].contains(&fmt.format_tag) {
-                            let header = SharedCursor::new();
-                            OggVorbisEncoderParams {
-                                mode: OggVorbisMode::HaveNoCodebookHeader,
-                                channels: fmt.channels,
-                                sample_rate: fmt.sample_rate,
-                                stream_serial: None,
-                                bitrate: None,
-                                minimum_page_data_size: None
-                            }.create_vorbis_builder(header.clone()).unwrap().build()?;
-                            header.get_vec()
+                            regenerate_vorbis_header(&mut reader, data_offset, fmt, oggvorbis_data)?
                         } else {
                             return Err(AudioReadError::FormatError("For `format_tag` is `FORMAT_TAG_OGG_VORBIS2` or `FORMAT_TAG_OGG_VORBIS2P`, the `fmt ` chunk must provide the Ogg Vorbis header data.".to_string()));
                         }
@@ -2168,6 +3327,10 @@ pub mod oggvorbis_dec {
                 cur_frame_index: 0,
                 cur_block_frame_index: 0,
                 downmixer,
+                on_decode_error,
+                last_error_offset: None,
+                truncated: false,
+                warnings: Vec::new(),
             };
             assert_eq!(fmt.channels, ret.channels);
             assert_eq!(fmt.sample_rate, ret.sample_rate);
@@ -2183,16 +3346,48 @@ pub mod oggvorbis_dec {
         }
 
         fn decode(&mut self) -> Result<(), AudioReadError> {
-            self.cur_block_frame_index += self.cur_block_frames() as u64;
+            let prev_block_frames = self.cur_block_frames();
+            self.cur_block_frame_index += prev_block_frames as u64;
             self.cur_frame_index = self.cur_block_frame_index;
-            self.decoded_samples = self.decoder.decode_audio_block()?.map(|samples| {
-                samples
-                    .samples()
-                    .iter()
-                    .map(|frame| frame.to_vec())
-                    .collect()
-            });
-            Ok(())
+            if self.truncated {
+                self.decoded_samples = None;
+                return Ok(());
+            }
+            let block_offset = self.reader.stream_position()?;
+            match self.decoder.decode_audio_block() {
+                Ok(block) => {
+                    self.decoded_samples = block.map(|samples| {
+                        samples
+                            .samples()
+                            .iter()
+                            .map(|frame| frame.to_vec())
+                            .collect()
+                    });
+                    Ok(())
+                }
+                Err(e) => match self.on_decode_error {
+                    DecodeErrorPolicy::Abort => Err(e.into()),
+                    DecodeErrorPolicy::SkipPacket if self.last_error_offset != Some(block_offset) => {
+                        // The nominal duration of the bad block is unknown, use the duration of the previous block.
+                        self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, block_offset, format!("Skipped the Vorbis block: {e}")));
+                        self.last_error_offset = Some(block_offset);
+                        self.decoded_samples = Some(vec![vec![0.0; prev_block_frames]; self.channels as usize]);
+                        Ok(())
+                    }
+                    _ => {
+                        // Also for the decoder that can't get over the bad data, it keeps failing at the same offset.
+                        self.warnings.push(DecodeWarning::new(DecodeWarningKind::CorruptedPacket, block_offset, format!("Truncated at the Vorbis block: {e}")));
+                        self.truncated = true;
+                        self.decoded_samples = None;
+                        Ok(())
+                    }
+                },
+            }
+        }
+
+        /// Get the warnings recorded when a corrupted block was skipped or truncated.
+        pub fn get_warnings(&self) -> &[DecodeWarning] {
+            &self.warnings
         }
 
         /// Get how many channels in the OggVorbis audio data
@@ -2215,6 +3410,7 @@ pub mod oggvorbis_dec {
             if frame_index < self.cur_block_frame_index {
                 self.reader.seek(SeekFrom::Start(self.data_offset))?;
                 self.cur_block_frame_index = 0;
+                self.truncated = false;
             }
             self.cur_frame_index = frame_index;
             while self.cur_block_frame_index + (self.cur_block_frames() as u64) < self.cur_frame_index {
@@ -2293,6 +3489,10 @@ pub mod oggvorbis_dec {
                 )
                 .field("cur_frame_index", &self.cur_frame_index)
                 .field("cur_block_frame_index", &self.cur_block_frame_index)
+                .field("on_decode_error", &self.on_decode_error)
+                .field("last_error_offset", &self.last_error_offset)
+                .field("truncated", &self.truncated)
+                .field("warnings", &self.warnings)
                 .finish()
         }
     }
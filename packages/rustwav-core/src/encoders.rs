@@ -3,16 +3,18 @@
 
 use std::fmt::Debug;
 
-use xlaw::{PcmXLawEncoder, XLaw};
+use xlaw::XLaw;
 use io_utils::Writer;
-use audioutils::{sample_conv, sample_conv_batch, stereo_conv, stereos_conv};
+use audioutils::{sample_conv, stereo_conv, stereos_conv};
 use sampletypes::{SampleType, i24, u24};
 use crate::adpcm;
+use crate::g711;
 use crate::errors::AudioWriteError;
 use crate::format_specs::format_tags::*;
 use crate::format_specs::guids::*;
-use crate::wavcore::{ExtensibleData, FmtChunk, FmtExtension};
-use crate::wavcore::{Spec, WaveSampleType};
+use crate::wavcore::{ExtensibleData, ExtensionData, FmtChunk, FmtExtension};
+use crate::wavcore::{Spec, WaveSampleType, GUID, float_sample_from, is_float_to_float, is_sample_bytes_copiable};
+use crate::wavcore::pcm::{DitherMode, PcmEncoderOptions};
 
 /// An encoder that accepts samples of type `S` and encodes them into the file's target format.
 /// Due to trait bounds prohibiting generic parameters, each function must be explicitly
@@ -20,12 +22,56 @@ use crate::wavcore::{Spec, WaveSampleType};
 pub trait EncoderToImpl: Debug {
     fn get_channels(&self) -> u16;
     fn get_max_channels(&self) -> u16;
+
+    /// * The bits per second of the whole encoded stream, all of the channels together, including the block headers and the packet framing.
+    /// * The `byte_rate` of the `fmt ` chunk is this divided by 8. Before anything was encoded, it's the bitrate that the encoder aims at.
     fn get_bitrate(&self) -> u32;
     fn new_fmt_chunk(&mut self) -> Result<FmtChunk, AudioWriteError>;
     fn update_fmt_chunk(&self, fmt: &mut FmtChunk) -> Result<(), AudioWriteError>;
     fn begin_encoding(&mut self) -> Result<(), AudioWriteError>;
     fn finish(&mut self) -> Result<(), AudioWriteError>;
 
+    /// * How many accepted audio frames are still sitting in the buffers of the encoder, not yet written into the writer.
+    /// * The encoders that write everything immediately don't have to implement it. After `finish()`, it must be zero.
+    fn get_frames_buffered(&self) -> u64 {
+        0
+    }
+
+    /// * The sample type that the encoder takes without converting it, the samples of the other types are converted to it first.
+    /// * The callers that can choose the sample type, e.g. `transfer_audio_from_decoder_to_encoder()`, use it to avoid the needless conversions.
+    fn native_sample_type(&self) -> WaveSampleType {
+        WaveSampleType::F32
+    }
+
+    /// * Put a metadata tag e.g. `TITLE`, `ARTIST` into the encoded stream, must be called before `begin_encoding()`.
+    /// * Returns `false` if the format doesn't have native tags, then the `WaveWriter` puts it into the `LIST INFO` chunk.
+    fn set_metadata_tag(&mut self, _key: &str, _value: &str) -> Result<bool, AudioWriteError> {
+        Ok(false)
+    }
+
+    /// * Tell the encoder how many audio frames are going to be written, must be called before `begin_encoding()`.
+    /// * It's only a hint for the encoders that put the length into their headers, e.g. the FLAC `STREAMINFO`. The others ignore it.
+    fn set_total_frames_estimate(&mut self, _num_frames: u64) -> Result<(), AudioWriteError> {
+        Ok(())
+    }
+
+    /// * Set how to dither the samples when the bit depth is reduced, must be called before writing any samples.
+    /// * Only the PCM encoder dithers, the others accept `DitherMode::None` only.
+    fn set_dither(&mut self, dither: DitherMode) -> Result<(), AudioWriteError> {
+        match dither {
+            DitherMode::None => Ok(()),
+            other => Err(AudioWriteError::Unsupported(format!(
+                "Only the PCM encoder dithers the samples, can't use the dither mode {other:?}"
+            ))),
+        }
+    }
+
+    /// * The most frames the encoder takes at a time. The `Encoder` splits a larger write into the pieces of this size before converting the samples.
+    /// * `None` means no limit, the encoder handles the whole slice at once.
+    fn get_max_frames_per_write(&self) -> Option<usize> {
+        None
+    }
+
     // Write interleaved samples
     fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError>;
     fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError>;
@@ -55,6 +101,7 @@ pub trait EncoderToImpl: Debug {
     fn write_frame_f64(&mut self, frame: &[f64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f64(&audioutils::frames_to_interleaved_samples(&[frame.to_vec()])?)}
 
     // Convenience interfaces for writing multiple audio frames. Default implementations are provided.
+    // The `Encoder` doesn't call these, it interleaves the frames into its scratch buffer and calls `write_interleaved_samples_*()`.
     fn write_frames__i8(&mut self, frames: &[Vec<i8 >]) -> Result<(), AudioWriteError> {self.write_interleaved_samples__i8(&audioutils::frames_to_interleaved_samples(frames)?)}
     fn write_frames_i16(&mut self, frames: &[Vec<i16>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_i16(&audioutils::frames_to_interleaved_samples(frames)?)}
     fn write_frames_i24(&mut self, frames: &[Vec<i24>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_i24(&audioutils::frames_to_interleaved_samples(frames)?)}
@@ -124,7 +171,8 @@ pub trait EncoderToImpl: Debug {
     fn write_dual_monos_f32(&mut self, mono1: &[f32], mono2: &[f32]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&audioutils::monos_to_interleaved_samples(&[mono1.to_vec(), mono2.to_vec()])?)}
     fn write_dual_monos_f64(&mut self, mono1: &[f64], mono2: &[f64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f64(&audioutils::monos_to_interleaved_samples(&[mono1.to_vec(), mono2.to_vec()])?)}
 
-    // Interfaces for writing batched stereo audio (two separate channel buffers). Default implementations are provided.
+    // Interfaces for writing batched multi-channel audio, a `Vec` for each channel. Default implementations are provided.
+    // The `Encoder` doesn't call these either, it interleaves the channels into its scratch buffer too.
     fn write_monos__i8(&mut self, monos_array: &[Vec<i8 >]) -> Result<(), AudioWriteError> {self.write_interleaved_samples__i8(&audioutils::monos_to_interleaved_samples(monos_array)?)}
     fn write_monos_i16(&mut self, monos_array: &[Vec<i16>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_i16(&audioutils::monos_to_interleaved_samples(monos_array)?)}
     fn write_monos_i24(&mut self, monos_array: &[Vec<i24>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_i24(&audioutils::monos_to_interleaved_samples(monos_array)?)}
@@ -138,6 +186,20 @@ pub trait EncoderToImpl: Debug {
     fn write_monos_f32(&mut self, monos_array: &[Vec<f32>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&audioutils::monos_to_interleaved_samples(monos_array)?)}
     fn write_monos_f64(&mut self, monos_array: &[Vec<f64>]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f64(&audioutils::monos_to_interleaved_samples(monos_array)?)}
 
+    // Interfaces for writing batched multi-channel audio from borrowed planar buffers, no `Vec` for each channel is needed. Default implementations are provided.
+    fn write_monos_planar__i8(&mut self, monos_array: &[&[i8 ]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples__i8(&buf)}
+    fn write_monos_planar_i16(&mut self, monos_array: &[&[i16]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_i16(&buf)}
+    fn write_monos_planar_i24(&mut self, monos_array: &[&[i24]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_i24(&buf)}
+    fn write_monos_planar_i32(&mut self, monos_array: &[&[i32]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_i32(&buf)}
+    fn write_monos_planar_i64(&mut self, monos_array: &[&[i64]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_i64(&buf)}
+    fn write_monos_planar__u8(&mut self, monos_array: &[&[u8 ]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples__u8(&buf)}
+    fn write_monos_planar_u16(&mut self, monos_array: &[&[u16]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_u16(&buf)}
+    fn write_monos_planar_u24(&mut self, monos_array: &[&[u24]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_u24(&buf)}
+    fn write_monos_planar_u32(&mut self, monos_array: &[&[u32]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_u32(&buf)}
+    fn write_monos_planar_u64(&mut self, monos_array: &[&[u64]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_u64(&buf)}
+    fn write_monos_planar_f32(&mut self, monos_array: &[&[f32]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_f32(&buf)}
+    fn write_monos_planar_f64(&mut self, monos_array: &[&[f64]]) -> Result<(), AudioWriteError> {let mut buf = Vec::new(); monos_to_interleaved_samples_into(monos_array, &mut buf)?; self.write_interleaved_samples_f64(&buf)}
+
     // Interfaces for writing stereo audio frames using tuples (L, R). Default implementations are provided.
     fn write_stereo__i8(&mut self, stereo: (i8 , i8 )) -> Result<(), AudioWriteError> {self.write_interleaved_samples__i8(&[stereo.0, stereo.1])}
     fn write_stereo_i16(&mut self, stereo: (i16, i16)) -> Result<(), AudioWriteError> {self.write_interleaved_samples_i16(&[stereo.0, stereo.1])}
@@ -218,11 +280,151 @@ impl EncoderToImpl for DummyEncoder {
     fn write_interleaved_samples_f64(&mut self, samples: &[f64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
 }
 
+/// * The `PassThroughEncoder` is for `DataFormat::PassThrough`, it encodes nothing. The `fmt ` chunk is the one cloned from the source file,
+///   and the `data` chunk is copied as is by `WaveWriter::copy_data_from_reader()`.
+#[derive(Debug, Clone)]
+pub struct PassThroughEncoder {
+    fmt: FmtChunk,
+}
+
+impl PassThroughEncoder {
+    pub fn new(fmt: FmtChunk) -> Self {
+        Self { fmt }
+    }
+}
+
+impl EncoderToImpl for PassThroughEncoder {
+    fn get_channels(&self) -> u16 {
+        self.fmt.channels
+    }
+
+    fn get_max_channels(&self) -> u16 {
+        self.fmt.channels
+    }
+
+    fn get_bitrate(&self) -> u32 {
+        self.fmt.byte_rate * 8
+    }
+
+    fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
+        Ok(())
+    }
+
+    fn new_fmt_chunk(&mut self) -> Result<FmtChunk, AudioWriteError> {
+        Ok(self.fmt.clone())
+    }
+
+    fn update_fmt_chunk(&self, _fmt: &mut FmtChunk) -> Result<(), AudioWriteError> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), AudioWriteError> {
+        Ok(())
+    }
+
+    fn write_interleaved_samples_f32(&mut self, _samples: &[f32]) -> Result<(), AudioWriteError> {
+        Err(AudioWriteError::Unsupported(
+            "The pass-through format doesn't encode samples, the `data` chunk is copied by `copy_data_from_reader()`.".to_owned(),
+        ))
+    }
+
+    fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_i32(&mut self, samples: &[i32]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_i64(&mut self, samples: &[i64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples__u8(&mut self, samples: &[u8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_u16(&mut self, samples: &[u16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_u24(&mut self, samples: &[u24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_u32(&mut self, samples: &[u32]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_u64(&mut self, samples: &[u64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+    fn write_interleaved_samples_f64(&mut self, samples: &[f64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples_f32(&sample_conv(samples))}
+}
+
+/// * Interleave the audio frames into `buf` and convert the sample type at the same time.
+/// * The `buf` is cleared first, its capacity is reused, so calling this repeatedly with the same `buf` won't allocate after the warm-up.
+/// * The frames could be `Vec`s or borrowed slices.
+pub fn frames_to_interleaved_samples_into<S, T, F>(frames: &[F], buf: &mut Vec<T>) -> Result<(), AudioWriteError>
+where
+    S: SampleType,
+    T: SampleType,
+    F: AsRef<[S]>,
+{
+    buf.clear();
+    let channels = match frames.first() {
+        Some(frame) => frame.as_ref().len(),
+        None => return Ok(()),
+    };
+    buf.reserve(frames.len() * channels);
+    for frame in frames.iter().map(|frame| frame.as_ref()) {
+        if frame.len() != channels {
+            return Err(AudioWriteError::FrameChannelsNotSame);
+        }
+        buf.extend(frame.iter().map(|sample| T::scale_from(*sample)));
+    }
+    Ok(())
+}
+
+/// * Interleave the channels into `buf` and convert the sample type at the same time.
+/// * The `buf` is cleared first, its capacity is reused, so calling this repeatedly with the same `buf` won't allocate after the warm-up.
+/// * The channels could be `Vec`s or borrowed planar slices.
+pub fn monos_to_interleaved_samples_into<S, T, M>(monos_array: &[M], buf: &mut Vec<T>) -> Result<(), AudioWriteError>
+where
+    S: SampleType,
+    T: SampleType,
+    M: AsRef<[S]>,
+{
+    buf.clear();
+    let length = match monos_array.first() {
+        Some(monos) => monos.as_ref().len(),
+        None => return Ok(()),
+    };
+    if monos_array.iter().any(|monos| monos.as_ref().len() != length) {
+        return Err(AudioWriteError::ChannelsNotInSameSize);
+    }
+    buf.reserve(length * monos_array.len());
+    for i in 0..length {
+        buf.extend(monos_array.iter().map(|monos| T::scale_from(monos.as_ref()[i])));
+    }
+    Ok(())
+}
+
+/// * The scratch buffers for the `Encoder` to interleave the audio without allocating memory for every call.
+#[derive(Debug, Default)]
+struct ScratchBuffers {
+    buf__i8: Vec<i8 >,
+    buf_i16: Vec<i16>,
+    buf_i24: Vec<i24>,
+    buf_i32: Vec<i32>,
+    buf_i64: Vec<i64>,
+    buf__u8: Vec<u8 >,
+    buf_u16: Vec<u16>,
+    buf_u24: Vec<u24>,
+    buf_u32: Vec<u32>,
+    buf_u64: Vec<u64>,
+    buf_f32: Vec<f32>,
+    buf_f64: Vec<f64>,
+}
+
+/// * The clipping counted by the `Encoder` when the clip detection is enabled.
+/// * Only the float samples can clip: the samples out of `[-1.0, 1.0]` are clamped when converted to the integer samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClipStats {
+    /// * How many samples were out of `[-1.0, 1.0]`.
+    pub clipped_samples: u64,
+
+    /// * How far the loudest clipped sample went beyond the full scale, e.g. `0.25` for the sample `-1.25`.
+    pub max_overshoot: f64,
+}
+
 /// * The `Encoder` struct contains all of the encoder types and provides convenient functions that have generic type parameters.
 /// * It just translates the API to the inner encoder API.
 #[derive(Debug)]
 pub struct Encoder<'a> {
     encoder: Box<dyn EncoderToImpl + 'a>,
+    scratch: ScratchBuffers,
+    clip_detection: bool,
+    clip_stats: ClipStats,
 }
 
 impl Default for Encoder<'_> {
@@ -238,6 +440,19 @@ impl<'a> Encoder<'a> {
     {
         Self {
             encoder: Box::new(encoder),
+            scratch: ScratchBuffers::default(),
+            clip_detection: false,
+            clip_stats: ClipStats::default(),
+        }
+    }
+
+    /// * Use the boxed encoder, e.g. a third-party encoder for the `DataFormat::Custom` format.
+    pub fn from_boxed(encoder: Box<dyn EncoderToImpl + 'a>) -> Self {
+        Self {
+            encoder,
+            scratch: ScratchBuffers::default(),
+            clip_detection: false,
+            clip_stats: ClipStats::default(),
         }
     }
 
@@ -249,6 +464,51 @@ impl<'a> Encoder<'a> {
         self.encoder.get_max_channels()
     }
 
+    /// * Count the float samples that clip when converted to the integer samples, for every `write_*()` function.
+    /// * When it's disabled, the only cost is a flag check for each call. Disabling it keeps the counted stats.
+    pub fn enable_clip_detection(&mut self, enabled: bool) {
+        self.clip_detection = enabled;
+    }
+
+    pub fn clip_stats(&self) -> ClipStats {
+        self.clip_stats
+    }
+
+    /// * If the encoder has a limit of the frames per write and `num_frames` exceeds it, returns the limit to split the write by.
+    fn split_frames_by(&self, num_frames: usize) -> Option<usize> {
+        match self.encoder.get_max_frames_per_write() {
+            Some(max_frames) if max_frames > 0 && num_frames > max_frames => Some(max_frames),
+            _ => None,
+        }
+    }
+
+    /// * If the planar channels are all the same length and it exceeds the limit of the frames per write, returns the limit to split the write by.
+    fn split_planar_by<S>(&self, monos: &[&[S]]) -> Option<usize> {
+        let length = monos.first().map_or(0, |mono| mono.len());
+        if monos.iter().all(|mono| mono.len() == length) {
+            self.split_frames_by(length)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn detect_clips<S>(&mut self, samples: impl IntoIterator<Item = S>)
+    where
+        S: SampleType,
+    {
+        if !self.clip_detection || !matches!(S::TYPE_NAME, "f32" | "f64") {
+            return;
+        }
+        for sample in samples {
+            let overshoot = sample.to_f64().abs() - 1.0;
+            if overshoot > 0.0 {
+                self.clip_stats.clipped_samples += 1;
+                self.clip_stats.max_overshoot = self.clip_stats.max_overshoot.max(overshoot);
+            }
+        }
+    }
+
     pub fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
         self.encoder.begin_encoding()
     }
@@ -269,11 +529,36 @@ impl<'a> Encoder<'a> {
         self.encoder.finish()
     }
 
+    pub fn get_frames_buffered(&self) -> u64 {
+        self.encoder.get_frames_buffered()
+    }
+
+    pub fn native_sample_type(&self) -> WaveSampleType {
+        self.encoder.native_sample_type()
+    }
+
+    pub fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<bool, AudioWriteError> {
+        self.encoder.set_metadata_tag(key, value)
+    }
+
+    pub fn set_total_frames_estimate(&mut self, num_frames: u64) -> Result<(), AudioWriteError> {
+        self.encoder.set_total_frames_estimate(num_frames)
+    }
+
+    pub fn set_dither(&mut self, dither: DitherMode) -> Result<(), AudioWriteError> {
+        self.encoder.set_dither(dither)
+    }
+
     /// * Write samples regardless of channels
     pub fn write_interleaved_samples<S>(&mut self, samples: &[S]) -> Result<(), AudioWriteError>
     where
         S: SampleType,
     {
+        let channels = self.get_channels().max(1) as usize;
+        if let Some(max_frames) = self.split_frames_by(samples.len() / channels) {
+            return samples.chunks(max_frames * channels).try_for_each(|chunk| self.write_interleaved_samples(chunk));
+        }
+        self.detect_clips(samples.iter().copied());
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_interleaved_samples__i8(&sample_conv(samples)),
             "i16" => self.encoder.write_interleaved_samples_i16(&sample_conv(samples)),
@@ -299,6 +584,7 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        self.detect_clips(frame.iter().copied());
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_frame__i8(&sample_conv(frame)),
             "i16" => self.encoder.write_frame_i16(&sample_conv(frame)),
@@ -320,23 +606,28 @@ impl<'a> Encoder<'a> {
     }
 
     /// * Write audio frames, each frame contains one sample for all channels
+    /// * The frames are interleaved into the scratch buffer of the `Encoder` which is reused across calls.
     pub fn write_frames<S>(&mut self, frames: &[Vec<S>]) -> Result<(), AudioWriteError>
     where
         S: SampleType,
     {
+        if let Some(max_frames) = self.split_frames_by(frames.len()) {
+            return frames.chunks(max_frames).try_for_each(|chunk| self.write_frames(chunk));
+        }
+        self.detect_clips(frames.iter().flatten().copied());
         match S::TYPE_NAME {
-            "i8"  => self.encoder.write_frames__i8(&sample_conv_batch(frames)),
-            "i16" => self.encoder.write_frames_i16(&sample_conv_batch(frames)),
-            "i24" => self.encoder.write_frames_i24(&sample_conv_batch(frames)),
-            "i32" => self.encoder.write_frames_i32(&sample_conv_batch(frames)),
-            "i64" => self.encoder.write_frames_i64(&sample_conv_batch(frames)),
-            "u8"  => self.encoder.write_frames__u8(&sample_conv_batch(frames)),
-            "u16" => self.encoder.write_frames_u16(&sample_conv_batch(frames)),
-            "u24" => self.encoder.write_frames_u24(&sample_conv_batch(frames)),
-            "u32" => self.encoder.write_frames_u32(&sample_conv_batch(frames)),
-            "u64" => self.encoder.write_frames_u64(&sample_conv_batch(frames)),
-            "f32" => self.encoder.write_frames_f32(&sample_conv_batch(frames)),
-            "f64" => self.encoder.write_frames_f64(&sample_conv_batch(frames)),
+            "i8"  => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf__i8)?; self.encoder.write_interleaved_samples__i8(&self.scratch.buf__i8)}
+            "i16" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i16)?; self.encoder.write_interleaved_samples_i16(&self.scratch.buf_i16)}
+            "i24" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i24)?; self.encoder.write_interleaved_samples_i24(&self.scratch.buf_i24)}
+            "i32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i32)?; self.encoder.write_interleaved_samples_i32(&self.scratch.buf_i32)}
+            "i64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i64)?; self.encoder.write_interleaved_samples_i64(&self.scratch.buf_i64)}
+            "u8"  => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf__u8)?; self.encoder.write_interleaved_samples__u8(&self.scratch.buf__u8)}
+            "u16" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u16)?; self.encoder.write_interleaved_samples_u16(&self.scratch.buf_u16)}
+            "u24" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u24)?; self.encoder.write_interleaved_samples_u24(&self.scratch.buf_u24)}
+            "u32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u32)?; self.encoder.write_interleaved_samples_u32(&self.scratch.buf_u32)}
+            "u64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u64)?; self.encoder.write_interleaved_samples_u64(&self.scratch.buf_u64)}
+            "f32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_f32)?; self.encoder.write_interleaved_samples_f32(&self.scratch.buf_f32)}
+            "f64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_f64)?; self.encoder.write_interleaved_samples_f64(&self.scratch.buf_f64)}
             other => Err(AudioWriteError::InvalidArguments(format!(
                 "Bad sample type: {}",
                 other
@@ -349,6 +640,7 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        self.detect_clips([mono]);
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_mono__i8(mono.to_i8 ()),
             "i16" => self.encoder.write_mono_i16(mono.to_i16()),
@@ -374,6 +666,7 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        self.detect_clips([monos]);
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_mono__i8(monos.as_i8 ()),
             "i16" => self.encoder.write_mono_i16(monos.as_i16()),
@@ -399,6 +692,10 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        if let Some(max_frames) = self.split_frames_by(monos.len()) {
+            return monos.chunks(max_frames).try_for_each(|chunk| self.write_mono_channel(chunk));
+        }
+        self.detect_clips(monos.iter().copied());
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_mono_channel__i8(&sample_conv(monos)),
             "i16" => self.encoder.write_mono_channel_i16(&sample_conv(monos)),
@@ -424,6 +721,7 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        self.detect_clips([mono1, mono2]);
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_dual_sample__i8(mono1.to_i8 (), mono2.to_i8 ()),
             "i16" => self.encoder.write_dual_sample_i16(mono1.to_i16(), mono2.to_i16()),
@@ -449,6 +747,10 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        if let Some(max_frames) = self.split_planar_by(&[mono1, mono2]) {
+            return mono1.chunks(max_frames).zip(mono2.chunks(max_frames)).try_for_each(|(mono1, mono2)| self.write_dual_monos(mono1, mono2));
+        }
+        self.detect_clips(mono1.iter().chain(mono2).copied());
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_dual_monos__i8(&sample_conv(mono1), &sample_conv(mono2)),
             "i16" => self.encoder.write_dual_monos_i16(&sample_conv(mono1), &sample_conv(mono2)),
@@ -470,23 +772,95 @@ impl<'a> Encoder<'a> {
     }
 
     /// * Write multiple channels of audio to the encoder
+    /// * The channels are interleaved into the scratch buffer of the `Encoder` which is reused across calls.
     pub fn write_monos<S>(&mut self, monos: &[Vec<S>]) -> Result<(), AudioWriteError>
     where
         S: SampleType,
     {
+        let length = monos.first().map_or(0, |mono| mono.len());
+        if monos.iter().all(|mono| mono.len() == length) && self.split_frames_by(length).is_some() {
+            let planar: Vec<&[S]> = monos.iter().map(|mono| mono.as_slice()).collect();
+            return self.write_monos_planar(&planar);
+        }
+        self.detect_clips(monos.iter().flatten().copied());
+        match S::TYPE_NAME {
+            "i8"  => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf__i8)?; self.encoder.write_interleaved_samples__i8(&self.scratch.buf__i8)}
+            "i16" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i16)?; self.encoder.write_interleaved_samples_i16(&self.scratch.buf_i16)}
+            "i24" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i24)?; self.encoder.write_interleaved_samples_i24(&self.scratch.buf_i24)}
+            "i32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i32)?; self.encoder.write_interleaved_samples_i32(&self.scratch.buf_i32)}
+            "i64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i64)?; self.encoder.write_interleaved_samples_i64(&self.scratch.buf_i64)}
+            "u8"  => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf__u8)?; self.encoder.write_interleaved_samples__u8(&self.scratch.buf__u8)}
+            "u16" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u16)?; self.encoder.write_interleaved_samples_u16(&self.scratch.buf_u16)}
+            "u24" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u24)?; self.encoder.write_interleaved_samples_u24(&self.scratch.buf_u24)}
+            "u32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u32)?; self.encoder.write_interleaved_samples_u32(&self.scratch.buf_u32)}
+            "u64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u64)?; self.encoder.write_interleaved_samples_u64(&self.scratch.buf_u64)}
+            "f32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_f32)?; self.encoder.write_interleaved_samples_f32(&self.scratch.buf_f32)}
+            "f64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_f64)?; self.encoder.write_interleaved_samples_f64(&self.scratch.buf_f64)}
+            other => Err(AudioWriteError::InvalidArguments(format!(
+                "Bad sample type: {}",
+                other
+            ))),
+        }
+    }
+
+    /// * Write multiple channels of audio from the borrowed planar buffers, e.g. the buffers owned by a DSP graph.
+    /// * Like `write_monos()`, the channels are interleaved into the scratch buffer of the `Encoder`, nothing is allocated after the warm-up.
+    pub fn write_monos_planar<S>(&mut self, monos: &[&[S]]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        if let Some(max_frames) = self.split_planar_by(monos) {
+            let length = monos[0].len();
+            return (0..length).step_by(max_frames).try_for_each(|begin| {
+                let end = (begin + max_frames).min(length);
+                let chunk: Vec<&[S]> = monos.iter().map(|mono| &mono[begin..end]).collect();
+                self.write_monos_planar(&chunk)
+            });
+        }
+        self.detect_clips(monos.iter().flat_map(|monos| monos.iter()).copied());
+        match S::TYPE_NAME {
+            "i8"  => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf__i8)?; self.encoder.write_interleaved_samples__i8(&self.scratch.buf__i8)}
+            "i16" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i16)?; self.encoder.write_interleaved_samples_i16(&self.scratch.buf_i16)}
+            "i24" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i24)?; self.encoder.write_interleaved_samples_i24(&self.scratch.buf_i24)}
+            "i32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i32)?; self.encoder.write_interleaved_samples_i32(&self.scratch.buf_i32)}
+            "i64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_i64)?; self.encoder.write_interleaved_samples_i64(&self.scratch.buf_i64)}
+            "u8"  => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf__u8)?; self.encoder.write_interleaved_samples__u8(&self.scratch.buf__u8)}
+            "u16" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u16)?; self.encoder.write_interleaved_samples_u16(&self.scratch.buf_u16)}
+            "u24" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u24)?; self.encoder.write_interleaved_samples_u24(&self.scratch.buf_u24)}
+            "u32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u32)?; self.encoder.write_interleaved_samples_u32(&self.scratch.buf_u32)}
+            "u64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_u64)?; self.encoder.write_interleaved_samples_u64(&self.scratch.buf_u64)}
+            "f32" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_f32)?; self.encoder.write_interleaved_samples_f32(&self.scratch.buf_f32)}
+            "f64" => {monos_to_interleaved_samples_into(monos, &mut self.scratch.buf_f64)?; self.encoder.write_interleaved_samples_f64(&self.scratch.buf_f64)}
+            other => Err(AudioWriteError::InvalidArguments(format!(
+                "Bad sample type: {}",
+                other
+            ))),
+        }
+    }
+
+    /// * Write audio frames from the borrowed slices, each slice is a frame containing one sample for all channels.
+    /// * Like `write_frames()`, the frames are interleaved into the scratch buffer of the `Encoder`, nothing is allocated after the warm-up.
+    pub fn write_frames_planar<S>(&mut self, frames: &[&[S]]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        if let Some(max_frames) = self.split_frames_by(frames.len()) {
+            return frames.chunks(max_frames).try_for_each(|chunk| self.write_frames_planar(chunk));
+        }
+        self.detect_clips(frames.iter().flat_map(|frame| frame.iter()).copied());
         match S::TYPE_NAME {
-            "i8"  => self.encoder.write_monos__i8(&sample_conv_batch(monos)),
-            "i16" => self.encoder.write_monos_i16(&sample_conv_batch(monos)),
-            "i24" => self.encoder.write_monos_i24(&sample_conv_batch(monos)),
-            "i32" => self.encoder.write_monos_i32(&sample_conv_batch(monos)),
-            "i64" => self.encoder.write_monos_i64(&sample_conv_batch(monos)),
-            "u8"  => self.encoder.write_monos__u8(&sample_conv_batch(monos)),
-            "u16" => self.encoder.write_monos_u16(&sample_conv_batch(monos)),
-            "u24" => self.encoder.write_monos_u24(&sample_conv_batch(monos)),
-            "u32" => self.encoder.write_monos_u32(&sample_conv_batch(monos)),
-            "u64" => self.encoder.write_monos_u64(&sample_conv_batch(monos)),
-            "f32" => self.encoder.write_monos_f32(&sample_conv_batch(monos)),
-            "f64" => self.encoder.write_monos_f64(&sample_conv_batch(monos)),
+            "i8"  => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf__i8)?; self.encoder.write_interleaved_samples__i8(&self.scratch.buf__i8)}
+            "i16" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i16)?; self.encoder.write_interleaved_samples_i16(&self.scratch.buf_i16)}
+            "i24" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i24)?; self.encoder.write_interleaved_samples_i24(&self.scratch.buf_i24)}
+            "i32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i32)?; self.encoder.write_interleaved_samples_i32(&self.scratch.buf_i32)}
+            "i64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_i64)?; self.encoder.write_interleaved_samples_i64(&self.scratch.buf_i64)}
+            "u8"  => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf__u8)?; self.encoder.write_interleaved_samples__u8(&self.scratch.buf__u8)}
+            "u16" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u16)?; self.encoder.write_interleaved_samples_u16(&self.scratch.buf_u16)}
+            "u24" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u24)?; self.encoder.write_interleaved_samples_u24(&self.scratch.buf_u24)}
+            "u32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u32)?; self.encoder.write_interleaved_samples_u32(&self.scratch.buf_u32)}
+            "u64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_u64)?; self.encoder.write_interleaved_samples_u64(&self.scratch.buf_u64)}
+            "f32" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_f32)?; self.encoder.write_interleaved_samples_f32(&self.scratch.buf_f32)}
+            "f64" => {frames_to_interleaved_samples_into(frames, &mut self.scratch.buf_f64)?; self.encoder.write_interleaved_samples_f64(&self.scratch.buf_f64)}
             other => Err(AudioWriteError::InvalidArguments(format!(
                 "Bad sample type: {}",
                 other
@@ -499,6 +873,7 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        self.detect_clips([stereo.0, stereo.1]);
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_stereo__i8(stereo_conv(stereo)),
             "i16" => self.encoder.write_stereo_i16(stereo_conv(stereo)),
@@ -524,6 +899,10 @@ impl<'a> Encoder<'a> {
     where
         S: SampleType,
     {
+        if let Some(max_frames) = self.split_frames_by(stereos.len()) {
+            return stereos.chunks(max_frames).try_for_each(|chunk| self.write_stereos(chunk));
+        }
+        self.detect_clips(stereos.iter().flat_map(|&(l, r)| [l, r]));
         match S::TYPE_NAME {
             "i8"  => self.encoder.write_stereos__i8(&stereos_conv(stereos)),
             "i16" => self.encoder.write_stereos_i16(&stereos_conv(stereos)),
@@ -563,18 +942,18 @@ where
         use WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64};
         Ok(Self {
             write_fn: match target_sample {
-                S8  => Self::write_sample_to::<i8 >,
-                S16 => Self::write_sample_to::<i16>,
-                S24 => Self::write_sample_to::<i24>,
-                S32 => Self::write_sample_to::<i32>,
-                S64 => Self::write_sample_to::<i64>,
-                U8  => Self::write_sample_to::<u8 >,
-                U16 => Self::write_sample_to::<u16>,
-                U24 => Self::write_sample_to::<u24>,
-                U32 => Self::write_sample_to::<u32>,
-                U64 => Self::write_sample_to::<u64>,
-                F32 => Self::write_sample_to::<f32>,
-                F64 => Self::write_sample_to::<f64>,
+                S8  => Self::choose_write_fn::<i8 >(),
+                S16 => Self::choose_write_fn::<i16>(),
+                S24 => Self::choose_write_fn::<i24>(),
+                S32 => Self::choose_write_fn::<i32>(),
+                S64 => Self::choose_write_fn::<i64>(),
+                U8  => Self::choose_write_fn::<u8 >(),
+                U16 => Self::choose_write_fn::<u16>(),
+                U24 => Self::choose_write_fn::<u24>(),
+                U32 => Self::choose_write_fn::<u32>(),
+                U64 => Self::choose_write_fn::<u64>(),
+                F32 => Self::choose_write_fn::<f32>(),
+                F64 => Self::choose_write_fn::<f64>(),
                 other => {
                     return Err(AudioWriteError::InvalidArguments(format!(
                         "Unknown target sample type: \"{:?}\"",
@@ -585,6 +964,21 @@ where
         })
     }
 
+    /// * Write the samples as bytes directly if no conversion is needed, otherwise convert them one by one.
+    #[allow(clippy::type_complexity)]
+    fn choose_write_fn<T>() -> fn(&mut dyn Writer, frame: &[S]) -> Result<(), AudioWriteError>
+    where
+        T: SampleType,
+    {
+        if is_sample_bytes_copiable::<S, T>() {
+            Self::write_sample_bytes
+        } else if is_float_to_float::<S, T>() {
+            Self::write_float_sample_to::<T>
+        } else {
+            Self::write_sample_to::<T>
+        }
+    }
+
     /// S: The input format provided to us (external source).
     /// T: The target format to be written into the WAV file.
     fn write_sample_to<T>(writer: &mut dyn Writer, frame: &[S]) -> Result<(), AudioWriteError>
@@ -597,6 +991,25 @@ where
         Ok(())
     }
 
+    /// * The float samples are written as the float type `T` as they are, the values out of `[-1.0, 1.0]` are kept.
+    fn write_float_sample_to<T>(writer: &mut dyn Writer, frame: &[S]) -> Result<(), AudioWriteError>
+    where
+        T: SampleType,
+    {
+        for sample in frame.iter() {
+            float_sample_from::<T>(sample.to_f64()).write_le(writer)?;
+        }
+        Ok(())
+    }
+
+    /// * The fast path for the same sample type in the WAV file, writes the whole slice in one call.
+    fn write_sample_bytes(writer: &mut dyn Writer, frame: &[S]) -> Result<(), AudioWriteError> {
+        // Safety: `is_sample_bytes_copiable()` checked by the `TypeId` that `S` is a primitive number type, its bytes in the memory are the bytes in the WAV file.
+        let bytes = unsafe { std::slice::from_raw_parts(frame.as_ptr() as *const u8, std::mem::size_of_val(frame)) };
+        writer.write_all(bytes)?;
+        Ok(())
+    }
+
     pub fn write_frame(
         &mut self,
         writer: &mut dyn Writer,
@@ -625,11 +1038,114 @@ where
     }
 }
 
+/// * `Ditherer`: quantize the samples to the integer sample type of the WAV file with the dither, see `DitherMode`.
+/// * This is a component for the `PcmEncoder`, it's used when the samples have more bits than the sample type of the WAV file.
+#[derive(Debug, Clone)]
+struct Ditherer {
+    mode: DitherMode,
+    target_bits: u16,
+    channels: usize,
+
+    /// * The channel of the next sample
+    channel: usize,
+
+    /// * The state of the xorshift noise generator
+    noise_state: u32,
+
+    /// * The last two quantization errors of each channel for the noise shaping
+    errors: Vec<[f64; 2]>,
+
+    /// * The quantized bytes to write
+    bytes: Vec<u8>,
+}
+
+impl Ditherer {
+    /// * Returns `None` if there's nothing to dither: no dither mode, or the float or 64-bit sample type of the WAV file.
+    pub fn new(mode: DitherMode, target_sample: WaveSampleType, channels: u16) -> Option<Self> {
+        use WaveSampleType::{S16, S24, S32, U8};
+        let target_bits = match target_sample {
+            U8 => 8,
+            S16 => 16,
+            S24 => 24,
+            S32 => 32,
+            _ => return None,
+        };
+        if mode == DitherMode::None {
+            return None;
+        }
+        Some(Self {
+            mode,
+            target_bits,
+            channels: channels as usize,
+            channel: 0,
+            noise_state: 0x9E3779B9,
+            errors: vec![[0.0; 2]; channels as usize],
+            bytes: Vec::new(),
+        })
+    }
+
+    /// * The samples of type `S` have more bits than the sample type of the WAV file, the float samples always do.
+    pub fn reduces_bit_depth<S>(&self) -> bool
+    where
+        S: SampleType,
+    {
+        match S::TYPE_NAME {
+            "f32" | "f64" => true,
+            other => other[1..].parse::<u16>().is_ok_and(|source_bits| source_bits > self.target_bits),
+        }
+    }
+
+    /// * The uniform noise in `[-0.5, 0.5)`
+    fn next_noise(&mut self) -> f64 {
+        self.noise_state ^= self.noise_state << 13;
+        self.noise_state ^= self.noise_state >> 17;
+        self.noise_state ^= self.noise_state << 5;
+        self.noise_state as f64 / 4294967296.0 - 0.5
+    }
+
+    pub fn write_interleaved_samples<S>(&mut self, writer: &mut dyn Writer, samples: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        let scale = (1u64 << (self.target_bits - 1)) as f64;
+        let bytes_per_sample = self.target_bits as usize / 8;
+        self.bytes.clear();
+        for sample in samples.iter() {
+            let noise = match self.mode {
+                DitherMode::None => 0.0,
+                DitherMode::Rectangular => self.next_noise(),
+                DitherMode::Triangular | DitherMode::NoiseShaped => self.next_noise() + self.next_noise(),
+            };
+            let errors = &mut self.errors[self.channel];
+
+            // The error feedback makes the noise transfer function `(1 - z^-1)^2`, the noise rises toward the Nyquist frequency.
+            let shaped = match self.mode {
+                DitherMode::NoiseShaped => sample.to_f64() * scale - 2.0 * errors[0] + errors[1],
+                _ => sample.to_f64() * scale,
+            };
+            let quantized = (shaped + noise).round().clamp(-scale, scale - 1.0);
+
+            // The clipped samples have huge errors, limit them or the feedback would go wild.
+            *errors = [(quantized - shaped).clamp(-2.0, 2.0), errors[0]];
+            let value = quantized as i32;
+            if self.target_bits == 8 {
+                self.bytes.push((value + 128) as u8);
+            } else {
+                self.bytes.extend_from_slice(&value.to_le_bytes()[..bytes_per_sample]);
+            }
+            self.channel = (self.channel + 1) % self.channels;
+        }
+        writer.write_all(&self.bytes)?;
+        Ok(())
+    }
+}
+
 /// * `PcmEncoder`: convert various formats of PCM samples to the WAV file specific sample type
 #[derive(Debug)]
 pub struct PcmEncoder<'a> {
     spec: Spec,
     sample_type: WaveSampleType,
+    sub_format: Option<GUID>,
     writer: &'a mut dyn Writer,
     writer_from__i8: PcmEncoderFrom< i8>,
     writer_from_i16: PcmEncoderFrom<i16>,
@@ -643,21 +1159,44 @@ pub struct PcmEncoder<'a> {
     writer_from_u64: PcmEncoderFrom<u64>,
     writer_from_f32: PcmEncoderFrom<f32>,
     writer_from_f64: PcmEncoderFrom<f64>,
+    dither: Option<Ditherer>,
 }
 
 impl<'a> PcmEncoder<'a> {
     /// * target_sample: The specific PCM format (e.g., bit depth, signedness) to encode into the WAV file.
     pub fn new(writer: &'a mut dyn Writer, spec: Spec) -> Result<Self, AudioWriteError> {
+        Self::new_with_options(writer, spec, &PcmEncoderOptions::default())
+    }
+
+    /// * Create the encoder with the options, the `sub_format_guid` forces the extensible `fmt ` chunk and is emitted verbatim.
+    pub fn new_with_options(writer: &'a mut dyn Writer, spec: Spec, options: &PcmEncoderOptions) -> Result<Self, AudioWriteError> {
         if !spec.is_channel_mask_valid() {
+            let layout = spec.channel_layout().unwrap_or_default();
             return Err(AudioWriteError::InvalidArguments(format!(
-                "Number of bits of channel mask 0x{:08x} does not match {} channels",
-                spec.channel_mask, spec.channels
+                "The channel layout {layout} (channel mask 0x{:08x}) has {} speakers, it does not match {} channels",
+                layout.mask(), layout.num_channels(), spec.channels
             )));
         }
         let target_sample = spec.get_sample_type();
+        let sub_format = options.sub_format_guid;
+        if let Some(guid) = sub_format {
+            use WaveSampleType::{F32, F64, U8, S16, S24, S32, S64};
+            // For the well-known GUIDs, the first 4 bytes is the format tag, it must match the sample type.
+            let compatible = match guid.0 {
+                1 => matches!(target_sample, U8 | S16 | S24 | S32 | S64),
+                3 => matches!(target_sample, F32 | F64),
+                _ => true,
+            };
+            if !compatible {
+                return Err(AudioWriteError::InvalidArguments(format!(
+                    "The sub-format GUID {guid} is not compatible with the sample type {target_sample}"
+                )));
+            }
+        }
         Ok(Self {
             spec,
             sample_type: target_sample,
+            sub_format,
             writer,
             writer_from__i8: PcmEncoderFrom::< i8>::new(target_sample)?,
             writer_from_i16: PcmEncoderFrom::<i16>::new(target_sample)?,
@@ -671,8 +1210,20 @@ impl<'a> PcmEncoder<'a> {
             writer_from_u64: PcmEncoderFrom::<u64>::new(target_sample)?,
             writer_from_f32: PcmEncoderFrom::<f32>::new(target_sample)?,
             writer_from_f64: PcmEncoderFrom::<f64>::new(target_sample)?,
+            dither: None,
         })
     }
+
+    /// * Write the samples with the dither if they have more bits than the sample type of the WAV file, otherwise convert them by `writer_from`.
+    fn write_samples<S>(&mut self, mut writer_from: PcmEncoderFrom<S>, samples: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        match self.dither.as_mut() {
+            Some(ditherer) if ditherer.reduces_bit_depth::<S>() => ditherer.write_interleaved_samples(self.writer, samples),
+            _ => writer_from.write_interleaved_samples(self.writer, samples),
+        }
+    }
 }
 
 impl EncoderToImpl for PcmEncoder<'_> {
@@ -680,8 +1231,19 @@ impl EncoderToImpl for PcmEncoder<'_> {
         self.spec.channels
     }
 
+    /// * With the zero channel mask, the extensible PCM can have as many channels as the `fmt ` chunk permits.
     fn get_max_channels(&self) -> u16 {
-        18
+        u16::MAX
+    }
+
+    /// * The sample type of the WAV file, the samples are written without conversion.
+    fn native_sample_type(&self) -> WaveSampleType {
+        // The dither works on the samples with more bits than the WAV file.
+        if self.dither.is_some() {
+            WaveSampleType::F64
+        } else {
+            self.sample_type
+        }
     }
 
     fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
@@ -692,11 +1254,18 @@ impl EncoderToImpl for PcmEncoder<'_> {
         use WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64, Unknown};
         let bytes_per_sample = self.spec.bits_per_sample / 8;
         let byte_rate = self.spec.sample_rate * self.spec.channels as u32 * bytes_per_sample as u32;
-        let extensible = match self.spec.channel_mask {
-            0 => None,
-            channel_mask => Some(FmtExtension::new_extensible(ExtensibleData {
+        let extension = match (self.spec.channel_layout(), self.sub_format) {
+            // The IEEE float `fmt ` chunk isn't `PCMWAVEFORMAT`, it's the `WAVEFORMATEX` with the zero `cbSize`.
+            (None, None) if matches!(self.sample_type, F32 | F64) => Some(FmtExtension::new_empty()),
+            (None, None) => None,
+            (layout, Some(sub_format)) => Some(FmtExtension::new_extensible(ExtensibleData {
+                valid_bits_per_sample: self.spec.bits_per_sample,
+                channel_mask: layout.unwrap_or_default().mask(),
+                sub_format,
+            })),
+            (Some(layout), None) => Some(FmtExtension::new_extensible(ExtensibleData {
                 valid_bits_per_sample: self.spec.bits_per_sample,
-                channel_mask,
+                channel_mask: layout.mask(),
                 sub_format: match self.sample_type {
                     U8 | S16 | S24 | S32 | S64 => GUID_PCM_FORMAT,
                     F32 | F64 => GUID_IEEE_FLOAT_FORMAT,
@@ -710,7 +1279,7 @@ impl EncoderToImpl for PcmEncoder<'_> {
             })),
         };
         Ok(FmtChunk {
-            format_tag: if extensible.is_some() {
+            format_tag: if matches!(extension, Some(FmtExtension { data: ExtensionData::Extensible(_), .. })) {
                 FORMAT_TAG_EXTENSIBLE
             } else {
                 match self.sample_type {
@@ -730,7 +1299,7 @@ impl EncoderToImpl for PcmEncoder<'_> {
             byte_rate,
             block_align: bytes_per_sample * self.spec.channels,
             bits_per_sample: self.spec.bits_per_sample,
-            extension: extensible,
+            extension,
         })
     }
 
@@ -746,18 +1315,23 @@ impl EncoderToImpl for PcmEncoder<'_> {
         Ok(self.writer.flush()?)
     }
 
-    fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.writer_from__i8.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.writer_from_i16.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.writer_from_i24.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_i32(&mut self, samples: &[i32]) -> Result<(), AudioWriteError> {self.writer_from_i32.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_i64(&mut self, samples: &[i64]) -> Result<(), AudioWriteError> {self.writer_from_i64.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples__u8(&mut self, samples: &[u8 ]) -> Result<(), AudioWriteError> {self.writer_from__u8.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_u16(&mut self, samples: &[u16]) -> Result<(), AudioWriteError> {self.writer_from_u16.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_u24(&mut self, samples: &[u24]) -> Result<(), AudioWriteError> {self.writer_from_u24.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_u32(&mut self, samples: &[u32]) -> Result<(), AudioWriteError> {self.writer_from_u32.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_u64(&mut self, samples: &[u64]) -> Result<(), AudioWriteError> {self.writer_from_u64.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_f32(&mut self, samples: &[f32]) -> Result<(), AudioWriteError> {self.writer_from_f32.write_interleaved_samples(self.writer, samples)}
-    fn write_interleaved_samples_f64(&mut self, samples: &[f64]) -> Result<(), AudioWriteError> {self.writer_from_f64.write_interleaved_samples(self.writer, samples)}
+    fn set_dither(&mut self, dither: DitherMode) -> Result<(), AudioWriteError> {
+        self.dither = Ditherer::new(dither, self.sample_type, self.spec.channels);
+        Ok(())
+    }
+
+    fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from__i8, samples)}
+    fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_i16, samples)}
+    fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_i24, samples)}
+    fn write_interleaved_samples_i32(&mut self, samples: &[i32]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_i32, samples)}
+    fn write_interleaved_samples_i64(&mut self, samples: &[i64]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_i64, samples)}
+    fn write_interleaved_samples__u8(&mut self, samples: &[u8 ]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from__u8, samples)}
+    fn write_interleaved_samples_u16(&mut self, samples: &[u16]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_u16, samples)}
+    fn write_interleaved_samples_u24(&mut self, samples: &[u24]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_u24, samples)}
+    fn write_interleaved_samples_u32(&mut self, samples: &[u32]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_u32, samples)}
+    fn write_interleaved_samples_u64(&mut self, samples: &[u64]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_u64, samples)}
+    fn write_interleaved_samples_f32(&mut self, samples: &[f32]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_f32, samples)}
+    fn write_interleaved_samples_f64(&mut self, samples: &[f64]) -> Result<(), AudioWriteError> {self.write_samples(self.writer_from_f64, samples)}
 }
 
 /// * `AdpcmEncoderWrap<E>`: encode `i16` audio samples to ADPCM nibbles
@@ -770,8 +1344,12 @@ where
     channels: u16,
     sample_rate: u32,
     bytes_written: u64,
+    frames_accepted: u64,
+    block_align: u64,
+    frames_per_block: u64,
     encoder: E,
     nibbles: Vec<u8>,
+    last_frame: Vec<i16>,
 }
 
 const MAX_BUFFER_USAGE: usize = 1024;
@@ -786,13 +1364,18 @@ where
             channels: spec.channels,
             sample_rate: spec.sample_rate,
             bytes_written: 0,
+            frames_accepted: 0,
+            block_align: 0,
+            frames_per_block: 0,
             encoder: E::new(spec.channels)?,
             nibbles: Vec::<u8>::with_capacity(MAX_BUFFER_USAGE),
+            last_frame: vec![0; spec.channels as usize],
         })
     }
 
     fn flush_buffers(&mut self) -> Result<(), AudioWriteError> {
         self.writer.write_all(&self.nibbles)?;
+        self.bytes_written += self.nibbles.len() as u64;
 
         // Avoid using `clear()`. If a user writes a large batch of samples once,
         // `clear()` retains the original capacity without shrinking it, leading to persistent memory usage.
@@ -808,6 +1391,10 @@ where
                 self.nibbles.push(byte);
             },
         )?;
+        self.frames_accepted += (samples.len() / self.channels as usize) as u64;
+        if let Some(frame) = samples.chunks_exact(self.channels as usize).last() {
+            self.last_frame.copy_from_slice(frame);
+        }
         if self.nibbles.len() >= MAX_BUFFER_USAGE {
             self.flush_buffers()?;
         }
@@ -828,6 +1415,10 @@ where
                 self.nibbles.push(byte);
             },
         )?;
+        self.frames_accepted += stereos.len() as u64;
+        if let Some(&(l, r)) = stereos.last() {
+            self.last_frame.copy_from_slice(&[l, r]);
+        }
         if self.nibbles.len() >= MAX_BUFFER_USAGE {
             self.flush_buffers()?;
         }
@@ -847,25 +1438,58 @@ where
         2
     }
 
+    fn native_sample_type(&self) -> WaveSampleType {
+        WaveSampleType::S16
+    }
+
     fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
         Ok(())
     }
 
     fn new_fmt_chunk(&mut self) -> Result<FmtChunk, AudioWriteError> {
-        Ok(self
+        let fmt = self
             .encoder
-            .new_fmt_chunk(self.channels, self.sample_rate, 4)?)
+            .new_fmt_chunk(self.channels, self.sample_rate, self.encoder.bits_per_sample())?;
+
+        // Remember the block layout to tell how many frames the written bytes hold.
+        self.block_align = fmt.block_align as u64;
+        self.frames_per_block = match &fmt.extension {
+            Some(extension) => match &extension.data {
+                ExtensionData::AdpcmMs(adpcm_ms) => adpcm_ms.samples_per_block as u64,
+                ExtensionData::AdpcmIma(adpcm_ima) => adpcm_ima.samples_per_block as u64,
+                _ => self.block_align * 8 / (self.encoder.bits_per_sample() as u64 * self.channels as u64),
+            },
+            None => self.block_align * 8 / (self.encoder.bits_per_sample() as u64 * self.channels as u64),
+        };
+        Ok(fmt)
     }
 
     fn get_bitrate(&self) -> u32 {
-        self.sample_rate * self.channels as u32 * 4
+        if self.frames_per_block > 0 {
+            (self.sample_rate as u64 * self.block_align * 8 / self.frames_per_block) as u32
+        } else {
+            // The block layout is known after `new_fmt_chunk()`, before that the block headers aren't counted.
+            self.sample_rate * self.channels as u32 * self.encoder.bits_per_sample() as u32
+        }
     }
 
     fn update_fmt_chunk(&self, fmt: &mut FmtChunk) -> Result<(), AudioWriteError> {
         Ok(self.encoder.modify_fmt_chunk(fmt)?)
     }
 
+    /// * The last block is filled up with the last frame repeated, some decoders drop or glitch on a block shorter than `block_align`.
+    ///   The `fact` chunk has the number of the frames before the padding.
     fn finish(&mut self) -> Result<(), AudioWriteError> {
+        if self.frames_per_block > 0 {
+            let padding = (self.frames_per_block - self.frames_accepted % self.frames_per_block) % self.frames_per_block;
+            let mut iter = self.last_frame.iter().copied().cycle().take(padding as usize * self.channels as usize);
+            self.encoder.encode(
+                || -> Option<i16> { iter.next() },
+                |byte: u8| {
+                    self.nibbles.push(byte);
+                },
+            )?;
+        }
         self.encoder.flush(|nibble: u8| {
             self.nibbles.push(nibble);
         })?;
@@ -873,6 +1497,15 @@ where
         Ok(self.writer.flush()?)
     }
 
+    /// * Only the complete blocks written count as committed, the last block is padded on `finish()`.
+    fn get_frames_buffered(&self) -> u64 {
+        if self.block_align == 0 {
+            return 0;
+        }
+        let frames_committed = self.bytes_written / self.block_align * self.frames_per_block;
+        self.frames_accepted - frames_committed.min(self.frames_accepted)
+    }
+
     fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
     fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
     fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
@@ -901,10 +1534,12 @@ where
 }
 
 /// * `PcmXLawEncoderWrap`: encode `i16` audio samples to bytes
+/// * The `i16` samples are encoded directly, the other sample types are converted to `i16` first. The decoded values of the codes encode back to the same codes.
 #[derive(Debug)]
 pub struct PcmXLawEncoderWrap<'a> {
     writer: &'a mut dyn Writer,
-    enc: PcmXLawEncoder,
+    encode: fn(i16) -> u8,
+    format_tag: u16,
     channels: u16,
     sample_rate: u32,
 }
@@ -913,7 +1548,11 @@ impl<'a> PcmXLawEncoderWrap<'a> {
     pub fn new(writer: &'a mut dyn Writer, spec: Spec, which_law: XLaw) -> Self {
         Self {
             writer,
-            enc: PcmXLawEncoder::new(which_law),
+            encode: g711::encoder(&which_law),
+            format_tag: match which_law {
+                XLaw::ALaw => FORMAT_TAG_ALAW,
+                XLaw::MuLaw => FORMAT_TAG_MULAW,
+            },
             channels: spec.channels,
             sample_rate: spec.sample_rate,
         }
@@ -923,7 +1562,7 @@ impl<'a> PcmXLawEncoderWrap<'a> {
         self.writer.write_all(
             &samples
                 .iter()
-                .map(|sample| -> u8 { self.enc.encode(*sample) })
+                .map(|sample| -> u8 { (self.encode)(*sample) })
                 .collect::<Vec<u8>>(),
         )?;
         Ok(())
@@ -939,6 +1578,10 @@ impl EncoderToImpl for PcmXLawEncoderWrap<'_> {
         2
     }
 
+    fn native_sample_type(&self) -> WaveSampleType {
+        WaveSampleType::S16
+    }
+
     fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
         Ok(())
     }
@@ -947,10 +1590,7 @@ impl EncoderToImpl for PcmXLawEncoderWrap<'_> {
         let bits_per_sample = 8u16;
         let block_align = self.channels;
         Ok(FmtChunk {
-            format_tag: match self.enc.get_which_law() {
-                XLaw::ALaw => FORMAT_TAG_ALAW,
-                XLaw::MuLaw => FORMAT_TAG_MULAW,
-            },
+            format_tag: self.format_tag,
             channels: self.channels,
             sample_rate: self.sample_rate,
             byte_rate: self.sample_rate * bits_per_sample as u32 * self.channels as u32 / 8,
@@ -973,7 +1613,7 @@ impl EncoderToImpl for PcmXLawEncoderWrap<'_> {
     }
 
     fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
-    fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
+    fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(samples)}
     fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
     fn write_interleaved_samples_i32(&mut self, samples: &[i32]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
     fn write_interleaved_samples_i64(&mut self, samples: &[i64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
@@ -1000,17 +1640,18 @@ pub mod mp3 {
         use crate::io_utils::Writer;
         use crate::audioutils::{self, sample_conv, stereos_conv};
         use crate::wavcore::format_tags::*;
-        use crate::wavcore::{FmtChunk, FmtExtension, Mp3Data, Spec};
+        use crate::wavcore::{FmtChunk, FmtExtension, Mp3Data, Spec, WaveSampleType};
         use crate::{SampleType, i24, u24};
         use std::{
             any::type_name,
             fmt::{self, Debug, Formatter},
+            io::SeekFrom,
             ops::DerefMut,
             sync::{Arc, Mutex},
         };
 
         use mp3lame_encoder::{Bitrate, Id3Tag, Mode, Quality, VbrMode};
-        use mp3lame_encoder::{Builder, DualPcm, Encoder, FlushNoGap, MonoPcm};
+        use mp3lame_encoder::{Builder, DualPcm, Encoder, FlushGap, MonoPcm};
 
         const MAX_SAMPLES_TO_ENCODE: usize = 1024;
 
@@ -1164,12 +1805,10 @@ pub mod mp3 {
                 mp3_builder.set_quality(options.quality)?;
                 mp3_builder.set_vbr_mode(options.vbr_mode)?;
 
-                match options.vbr_mode {
-                    VbrMode::Off => mp3_builder.set_to_write_vbr_tag(false)?,
-                    _ => {
-                        mp3_builder.set_to_write_vbr_tag(true)?;
-                        mp3_builder.set_vbr_quality(options.quality)?;
-                    }
+                // The Xing/Info frame with the LAME tag is written by the `ChannelBuffers`, LAME only writes a blank one.
+                mp3_builder.set_to_write_vbr_tag(false)?;
+                if options.vbr_mode != VbrMode::Off {
+                    mp3_builder.set_vbr_quality(options.quality)?;
                 }
 
                 if let Some(id3tag) = options.id3tag {
@@ -1198,6 +1837,8 @@ pub mod mp3 {
                             encoder.clone(),
                             MAX_SAMPLES_TO_ENCODE,
                             channels,
+                            spec.sample_rate,
+                            mp3_options.vbr_mode != Mp3VbrMode::Off,
                         )?,
                         o => {
                             return Err(AudioWriteError::Unsupported(format!(
@@ -1295,6 +1936,64 @@ pub mod mp3 {
             encoder: SharedMp3Encoder,
             channels: Channels<S>,
             max_frames: usize,
+            sample_rate: u32,
+            is_vbr: bool,
+
+            /// * How many audio frames were fed to LAME.
+            frames_fed: u64,
+
+            /// * The encoded data before the first MP3 frame, e.g. the ID3v2 tag, held until the Xing/Info frame is reserved after it.
+            held: Vec<u8>,
+
+            /// * Where the Xing/Info frame was reserved and the header of the first MP3 frame. The frame is rewritten on `finish()`.
+            info_frame: Option<(u64, Mp3FrameHeader)>,
+
+            scanner: Mp3FrameScanner,
+        }
+
+        /// * Counts the MP3 frames of the encoded data, the data could be split anywhere.
+        #[derive(Debug, Default)]
+        struct Mp3FrameScanner {
+            unscanned: Vec<u8>,
+            position: u64,
+            first_frame: Option<(u64, Mp3FrameHeader)>,
+            num_frames: u32,
+            num_bytes: u64,
+        }
+
+        impl Mp3FrameScanner {
+            fn feed(&mut self, data: &[u8]) {
+                self.unscanned.extend_from_slice(data);
+                let mut pos = 0;
+                loop {
+                    let rest = &self.unscanned[pos..];
+                    if rest.len() < 4 {
+                        break;
+                    }
+                    let header = Mp3FrameHeader::parse(rest);
+                    let size = match header {
+                        Some(header) => header.frame_size(),
+                        None if rest.starts_with(b"ID3") => match id3v2_tag_size(rest) {
+                            Some(size) => size,
+                            None => break,
+                        },
+                        // The ID3v1 tag at the end
+                        None if rest.starts_with(b"TAG") => 128,
+                        None => 1,
+                    };
+                    if size > rest.len() {
+                        break;
+                    }
+                    if let Some(header) = header {
+                        self.first_frame.get_or_insert((self.position + pos as u64, header));
+                        self.num_frames += 1;
+                        self.num_bytes += size as u64;
+                    }
+                    pos += size;
+                }
+                self.unscanned.drain(..pos);
+                self.position += pos as u64;
+            }
         }
 
         impl<S> Channels<S>
@@ -1410,6 +2109,8 @@ pub mod mp3 {
                 encoder: SharedMp3Encoder,
                 max_frames: usize,
                 channels: u16,
+                sample_rate: u32,
+                is_vbr: bool,
             ) -> Result<Self, AudioWriteError> {
                 Ok(Self {
                     writer,
@@ -1424,11 +2125,52 @@ pub mod mp3 {
                         }
                     },
                     max_frames,
+                    sample_rate,
+                    is_vbr,
+                    frames_fed: 0,
+                    held: Vec::new(),
+                    info_frame: None,
+                    scanner: Mp3FrameScanner::default(),
                 })
             }
 
-            pub fn is_full(&self) -> bool {
-                self.channels.len() >= self.max_frames
+            /// * Write the data from LAME, the Xing/Info frame is reserved right before the first MP3 frame.
+            fn write_encoded(&mut self, data: &[u8]) -> Result<(), AudioWriteError> {
+                self.scanner.feed(data);
+                if self.info_frame.is_some() {
+                    self.writer.write_all(data)?;
+                    return Ok(());
+                }
+                self.held.extend_from_slice(data);
+                if let Some((offset, header)) = self.scanner.first_frame {
+                    let held = std::mem::take(&mut self.held);
+                    self.writer.write_all(&held[..offset as usize])?;
+                    let info_frame_offset = self.writer.stream_position()?;
+                    let info_frame = self.gapless_info(&header).build_frame(&header, self.is_vbr, 0);
+                    self.writer.write_all(&info_frame)?;
+                    self.writer.write_all(&held[offset as usize..])?;
+                    self.info_frame = Some((info_frame_offset, header));
+                }
+                Ok(())
+            }
+
+            /// * LAME puts `LAME_ENCODER_DELAY` samples before the audio, and the padding fills the last frame.
+            fn gapless_info(&self, first_frame: &Mp3FrameHeader) -> Mp3GaplessInfo {
+                // LAME may encode at a different sample rate than the input.
+                let num_samples = self.frames_fed * first_frame.sample_rate as u64 / self.sample_rate as u64;
+                let num_frames = self.scanner.num_frames;
+                let total_samples = num_frames as u64 * first_frame.samples_per_frame() as u64;
+                Mp3GaplessInfo {
+                    num_frames: Some(num_frames),
+                    encoder_delay: LAME_ENCODER_DELAY,
+                    padding: total_samples.saturating_sub(LAME_ENCODER_DELAY as u64 + num_samples) as u32,
+                    samples_per_frame: first_frame.samples_per_frame(),
+                    sample_rate: first_frame.sample_rate,
+                }
+            }
+
+            pub fn is_full(&self) -> bool {
+                self.channels.len() >= self.max_frames
             }
 
             pub fn add_mono_channel(&mut self, monos: &[S]) -> Result<(), AudioWriteError> {
@@ -1515,23 +2257,40 @@ pub mod mp3 {
                         Ok(to_save)
                     },
                 )?;
-                self.writer.write_all(&to_save)?;
+                self.write_encoded(&to_save)?;
+                self.frames_fed += self.channels.len() as u64;
                 self.channels.clear(self.max_frames);
                 Ok(())
             }
 
             pub fn finish(&mut self) -> Result<(), AudioWriteError> {
                 self.flush()?;
-                self.encoder
-                    .escorted_encode(|encoder| -> Result<(), AudioWriteError> {
+                let to_save = self.encoder
+                    .escorted_encode(|encoder| -> Result<Vec<u8>, AudioWriteError> {
                         let mut to_save = Vec::<u8>::with_capacity(
                             mp3lame_encoder::max_required_buffer_size(self.max_frames),
                         );
-                        encoder.flush_to_vec::<FlushNoGap>(&mut to_save)?;
-                        self.writer.write_all(&to_save)?;
-                        Ok(())
+                        encoder.flush_to_vec::<FlushGap>(&mut to_save)?;
+                        Ok(to_save)
                     })?;
+                self.write_encoded(&to_save)?;
                 self.channels.clear(self.max_frames);
+
+                // No MP3 frame was encoded, nothing to tag.
+                let Some((info_frame_offset, header)) = self.info_frame else {
+                    let held = std::mem::take(&mut self.held);
+                    self.writer.write_all(&held)?;
+                    return Ok(());
+                };
+
+                // Rewrite the Xing/Info frame with the numbers of the frames and the bytes, the encoder delay and the padding.
+                let info = self.gapless_info(&header);
+                let info_frame_size = info.build_frame(&header, self.is_vbr, 0).len() as u64;
+                let num_bytes = (self.scanner.num_bytes + info_frame_size).min(u32::MAX as u64) as u32;
+                let end_of_stream = self.writer.stream_position()?;
+                self.writer.seek(SeekFrom::Start(info_frame_offset))?;
+                self.writer.write_all(&info.build_frame(&header, self.is_vbr, num_bytes))?;
+                self.writer.seek(SeekFrom::Start(end_of_stream))?;
                 Ok(())
             }
         }
@@ -1548,6 +2307,11 @@ pub mod mp3 {
                 2
             }
 
+            /// * LAME takes the samples in the type that the encoder was created with.
+            fn native_sample_type(&self) -> WaveSampleType {
+                WaveSampleType::of::<S>()
+            }
+
             fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
                 Ok(())
             }
@@ -1572,7 +2336,7 @@ pub mod mp3 {
             }
 
             fn get_bitrate(&self) -> u32 {
-                self.bitrate * self.channels as u32
+                self.bitrate
             }
 
             fn update_fmt_chunk(&self, _fmt: &mut FmtChunk) -> Result<(), AudioWriteError> {
@@ -1583,6 +2347,11 @@ pub mod mp3 {
                 self.finish()
             }
 
+            /// * The frames in the `ChannelBuffers` waiting to be fed to LAME. The frames that LAME holds for its look-ahead count as committed.
+            fn get_frames_buffered(&self) -> u64 {
+                self.buffers.channels.len() as u64
+            }
+
             fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(samples)}
             fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(samples)}
             fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(samples)}
@@ -1701,8 +2470,9 @@ pub mod opus {
     #[cfg(feature = "opus")]
     pub mod impl_opus {
         use std::{
+            ffi::CStr,
             fmt::{self, Debug, Formatter},
-            mem,
+            os::raw::c_int,
         };
 
         use super::*;
@@ -1710,11 +2480,20 @@ pub mod opus {
         use audioutils::sample_conv;
         use crate::errors::AudioWriteError;
         use crate::wavcore::format_tags::*;
-        use crate::wavcore::{FmtChunk, Spec};
+        use crate::wavcore::{FmtChunk, FmtExtension, ExtensionData, OpusData, Spec, WaveSampleType};
         use crate::{i24, u24};
 
+        use audiopus_sys as ffi;
         use opus::{self, Application, Bitrate, Channels, Encoder, ErrorCode};
 
+        const OPUS_APPLICATION_AUDIO: c_int = 2049;
+        const OPUS_SET_BITRATE_REQUEST: c_int = 4002;
+        const OPUS_GET_BITRATE_REQUEST: c_int = 4003;
+        const OPUS_SET_VBR_REQUEST: c_int = 4006;
+        const OPUS_GET_LOOKAHEAD_REQUEST: c_int = 4027;
+        const OPUS_AUTO: i32 = -1000;
+        const OPUS_BITRATE_MAX: i32 = -1;
+
         impl OpusBitrate {
             pub fn to_opus_bitrate(&self) -> Bitrate {
                 match self {
@@ -1725,16 +2504,202 @@ pub mod opus {
             }
         }
 
+        /// * The libopus multistream encoder for 3 to 8 channels, the `opus` crate only has the mono and stereo encoder.
+        pub struct OpusMultistreamEncoder {
+            encoder: *mut ffi::OpusMSEncoder,
+            channels: u16,
+
+            /// * Which WAV channel goes to each Opus channel.
+            order: Vec<usize>,
+            buffer: Vec<f32>,
+        }
+
+        impl OpusMultistreamEncoder {
+            /// * Create the surround encoder, the mapping family is 1 if the channel mask fits the Vorbis channel order, otherwise it's 255.
+            /// * Returns the encoder and the `OpusData` that has the channel mapping filled.
+            pub fn new(sample_rate: u32, channels: u16, channel_mask: u32) -> Result<(Self, OpusData), AudioWriteError> {
+                let (mapping_family, order) = match get_vorbis_channel_order(channels, channel_mask) {
+                    Some(order) => (1, order),
+                    None => (255, (0..channels as usize).collect()),
+                };
+                let mut streams: c_int = 0;
+                let mut coupled_streams: c_int = 0;
+                let mut mapping = [0u8; 255];
+                let mut error: c_int = 0;
+                let encoder = unsafe {
+                    ffi::opus_multistream_surround_encoder_create(
+                        sample_rate as i32,
+                        channels as c_int,
+                        mapping_family as c_int,
+                        &mut streams,
+                        &mut coupled_streams,
+                        mapping.as_mut_ptr(),
+                        OPUS_APPLICATION_AUDIO,
+                        &mut error,
+                    )
+                };
+                if encoder.is_null() || error < 0 {
+                    return Err(opus_error_code("opus_multistream_surround_encoder_create", error));
+                }
+
+                // The decoder outputs the WAV channels directly by the mapping in the WAV channel order.
+                let mut opus_data = OpusData {
+                    mapping_family,
+                    streams: streams as u8,
+                    coupled_streams: coupled_streams as u8,
+                    ..Default::default()
+                };
+                for (opus_channel, wav_channel) in order.iter().enumerate() {
+                    opus_data.mapping[*wav_channel] = mapping[opus_channel];
+                }
+                Ok((Self {
+                    encoder,
+                    channels,
+                    order,
+                    buffer: Vec::new(),
+                }, opus_data))
+            }
+
+            fn ctl(&mut self, function: &str, request: c_int, value: i32) -> Result<(), AudioWriteError> {
+                let ret = unsafe { ffi::opus_multistream_encoder_ctl(self.encoder, request, value) };
+                if ret < 0 {
+                    Err(opus_error_code(function, ret))
+                } else {
+                    Ok(())
+                }
+            }
+
+            pub fn set_bitrate(&mut self, bitrate: OpusBitrate) -> Result<(), AudioWriteError> {
+                let value = match bitrate {
+                    OpusBitrate::Bits(bitrate) => bitrate,
+                    OpusBitrate::Max => OPUS_BITRATE_MAX,
+                    OpusBitrate::Auto => OPUS_AUTO,
+                };
+                self.ctl("opus_multistream_encoder_ctl(OPUS_SET_BITRATE)", OPUS_SET_BITRATE_REQUEST, value)
+            }
+
+            pub fn set_vbr(&mut self, vbr: bool) -> Result<(), AudioWriteError> {
+                self.ctl("opus_multistream_encoder_ctl(OPUS_SET_VBR)", OPUS_SET_VBR_REQUEST, vbr as i32)
+            }
+
+            /// * The bitrate for all of the streams, libopus resolves `Auto` and `Max` into the bits per second.
+            pub fn get_bitrate(&mut self) -> Result<i32, AudioWriteError> {
+                let mut bitrate: i32 = 0;
+                let ret = unsafe { ffi::opus_multistream_encoder_ctl(self.encoder, OPUS_GET_BITRATE_REQUEST, &mut bitrate as *mut i32) };
+                if ret < 0 {
+                    Err(opus_error_code("opus_multistream_encoder_ctl(OPUS_GET_BITRATE)", ret))
+                } else {
+                    Ok(bitrate)
+                }
+            }
+
+            pub fn get_lookahead(&mut self) -> Result<i32, AudioWriteError> {
+                let mut lookahead: i32 = 0;
+                let ret = unsafe { ffi::opus_multistream_encoder_ctl(self.encoder, OPUS_GET_LOOKAHEAD_REQUEST, &mut lookahead as *mut i32) };
+                if ret < 0 {
+                    Err(opus_error_code("opus_multistream_encoder_ctl(OPUS_GET_LOOKAHEAD)", ret))
+                } else {
+                    Ok(lookahead)
+                }
+            }
+
+            /// * Encode the interleaved samples in the WAV channel order into a packet.
+            pub fn encode_float(&mut self, samples: &[f32], buf: &mut [u8]) -> Result<usize, AudioWriteError> {
+                let channels = self.channels as usize;
+                self.buffer.clear();
+                for frame in samples.chunks_exact(channels) {
+                    self.buffer.extend(self.order.iter().map(|wav_channel| frame[*wav_channel]));
+                }
+                let ret = unsafe {
+                    ffi::opus_multistream_encode_float(
+                        self.encoder,
+                        self.buffer.as_ptr(),
+                        (samples.len() / channels) as c_int,
+                        buf.as_mut_ptr(),
+                        buf.len() as i32,
+                    )
+                };
+                if ret < 0 {
+                    Err(opus_error_code("opus_multistream_encode_float", ret))
+                } else {
+                    Ok(ret as usize)
+                }
+            }
+        }
+
+        impl Drop for OpusMultistreamEncoder {
+            fn drop(&mut self) {
+                unsafe { ffi::opus_multistream_encoder_destroy(self.encoder) };
+            }
+        }
+
+        impl Debug for OpusMultistreamEncoder {
+            fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+                fmt.debug_struct("OpusMultistreamEncoder")
+                    .field("encoder", &self.encoder)
+                    .field("channels", &self.channels)
+                    .field("order", &self.order)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        /// * The error code returned by the libopus functions.
+        fn opus_error_code(function: &str, code: c_int) -> AudioWriteError {
+            let description = unsafe { CStr::from_ptr(ffi::opus_strerror(code)) }.to_string_lossy();
+            let info = format!("On calling `{function}`: {description}");
+            match code {
+                -1 => AudioWriteError::InvalidArguments(info),
+                -2 => AudioWriteError::BufferIsFull(info),
+                -4 => AudioWriteError::InvalidData(info),
+                -5 => AudioWriteError::Unimplemented(info),
+                _ => AudioWriteError::OtherReason(info),
+            }
+        }
+
+        /// * Mono and stereo go with the plain Opus encoder, more channels go with the multistream encoder.
+        #[derive(Debug)]
+        enum OpusEncoderKind {
+            Plain(Encoder),
+            Multistream(OpusMultistreamEncoder),
+        }
+
+        impl OpusEncoderKind {
+            fn encode_float(&mut self, samples: &[f32], buf: &mut [u8]) -> Result<usize, AudioWriteError> {
+                match self {
+                    Self::Plain(encoder) => Ok(encoder.encode_float(samples, buf)?),
+                    Self::Multistream(encoder) => encoder.encode_float(samples, buf),
+                }
+            }
+
+            /// * The bits per second that the encoder aims at, for the `byte_rate` before anything was encoded.
+            fn get_bitrate(&mut self) -> Result<u32, AudioWriteError> {
+                let bitrate = match self {
+                    Self::Plain(encoder) => match encoder.get_bitrate()? {
+                        Bitrate::Bits(bitrate) => bitrate,
+                        other => return Err(AudioWriteError::OtherReason(format!("The opus encoder didn't resolve its bitrate: {other:?}"))),
+                    },
+                    Self::Multistream(encoder) => encoder.get_bitrate()?,
+                };
+                Ok(bitrate.max(0) as u32)
+            }
+        }
+
         pub struct OpusEncoder<'a> {
             writer: &'a mut dyn Writer,
-            encoder: Encoder,
+            encoder: OpusEncoderKind,
             channels: u16,
             sample_rate: u32,
             cache_duration: OpusEncoderSampleDuration,
             num_samples_per_encode: usize,
+            max_frames_per_write: usize,
             sample_cache: Vec<f32>,
+            packet_buf: Vec<u8>,
             samples_written: u64,
             bytes_written: u64,
+
+            /// * The bitrate that the encoder aims at, it's the `byte_rate` until some packets were written.
+            nominal_bitrate: u32,
+            opus_data: OpusData,
         }
 
         impl<'a> OpusEncoder<'a> {
@@ -1743,10 +2708,6 @@ pub mod opus {
                 spec: Spec,
                 options: &OpusEncoderOptions,
             ) -> Result<Self, AudioWriteError> {
-                let mut opus_channels = Channels::Mono;
-                unsafe { // See <https://github.com/SpaceManiac/opus-rs/blob/master/src/lib.rs#L52>
-                    *(&mut opus_channels as *mut Channels as usize as *mut u8) = spec.channels as u8;
-                };
                 if !OPUS_ALLOWED_SAMPLE_RATES.contains(&spec.sample_rate) {
                     return Err(AudioWriteError::InvalidArguments(format!(
                         "Bad sample rate: {} for the opus encoder. The sample rate must be one of {}",
@@ -1758,13 +2719,35 @@ pub mod opus {
                             .join(", ")
                     )));
                 }
-                let mut encoder =
-                    Encoder::new(spec.sample_rate, opus_channels, Application::Audio)?;
-                encoder.set_bitrate(options.bitrate.to_opus_bitrate())?;
-                encoder.set_vbr(options.encode_vbr)?;
                 let num_samples_per_encode = options
                     .samples_cache_duration
                     .get_num_samples(spec.channels, spec.sample_rate);
+                let frames_per_packet = (num_samples_per_encode / spec.channels as usize) as u32;
+                let (mut encoder, opus_data) = match spec.channels {
+                    1 | 2 => {
+                        let opus_channels = if spec.channels == 1 { Channels::Mono } else { Channels::Stereo };
+                        let mut encoder = Encoder::new(spec.sample_rate, opus_channels, Application::Audio)?;
+                        encoder.set_bitrate(options.bitrate.to_opus_bitrate())?;
+                        encoder.set_vbr(options.encode_vbr)?;
+                        let pre_skip = encoder.get_lookahead()? as u16;
+                        (OpusEncoderKind::Plain(encoder), OpusData::new(pre_skip, frames_per_packet))
+                    }
+                    3..=OPUS_MAX_CHANNELS => {
+                        let (mut encoder, opus_data) = OpusMultistreamEncoder::new(spec.sample_rate, spec.channels, spec.effective_channel_mask())?;
+                        encoder.set_bitrate(options.bitrate)?;
+                        encoder.set_vbr(options.encode_vbr)?;
+                        let pre_skip = encoder.get_lookahead()? as u16;
+                        (OpusEncoderKind::Multistream(encoder), OpusData {
+                            pre_skip,
+                            frames_per_packet,
+                            ..opus_data
+                        })
+                    }
+                    o => return Err(AudioWriteError::WrongChannels(format!(
+                        "The Opus encoder can encode 1 to {OPUS_MAX_CHANNELS} channels, got {o} channels."
+                    ))),
+                };
+                let nominal_bitrate = encoder.get_bitrate()?;
                 Ok(Self {
                     writer,
                     encoder,
@@ -1772,9 +2755,13 @@ pub mod opus {
                     sample_rate: spec.sample_rate,
                     cache_duration: options.samples_cache_duration,
                     num_samples_per_encode,
+                    max_frames_per_write: options.max_frames_per_write.max(1),
                     sample_cache: Vec::<f32>::new(),
+                    packet_buf: Vec::<u8>::new(),
                     samples_written: 0,
                     bytes_written: 0,
+                    nominal_bitrate,
+                    opus_data,
                 })
             }
 
@@ -1785,46 +2772,50 @@ pub mod opus {
                 self.cache_duration = samples_cache_duration;
                 self.num_samples_per_encode =
                     samples_cache_duration.get_num_samples(self.channels, self.sample_rate);
+                self.opus_data.frames_per_packet = (self.num_samples_per_encode / self.channels as usize) as u32;
             }
 
+            /// * The samples are cached at most `max_frames_per_write` frames at a time, the cache never grows beyond that plus a packet.
             pub fn write_interleaved_samples(&mut self, samples: &[f32]) -> Result<(), AudioWriteError> {
-                self.sample_cache.extend(samples);
-                let mut cached_length = self.sample_cache.len();
-                let mut iter = mem::take(&mut self.sample_cache).into_iter();
-                while cached_length >= self.num_samples_per_encode {
-                    // Extract `self.num_samples_per_encode` samples to encode
-                    let samples_to_write: Vec<f32> =
-                        iter.by_ref().take(self.num_samples_per_encode).collect();
-                    if samples_to_write.is_empty() {
-                        break;
-                    }
+                for chunk in samples.chunks(self.max_frames_per_write * self.channels as usize) {
+                    self.sample_cache.extend_from_slice(chunk);
+                    self.encode_cached_packets()?;
+                }
+                Ok(())
+            }
 
-                    // Allocates a buffer of sufficient size, reserving one byte per sample.
-                    let mut buf = vec![0u8; self.num_samples_per_encode];
+            /// * Encode every complete packet in the cache, the remaining samples stay in the cache for the next time.
+            fn encode_cached_packets(&mut self) -> Result<(), AudioWriteError> {
+                // A buffer of sufficient size, reserving one byte per sample, the size must fit in the `u16` size prefix.
+                self.packet_buf.resize(self.num_samples_per_encode.min(u16::MAX as usize), 0);
+                let mut consumed = 0usize;
+                while self.sample_cache.len() - consumed >= self.num_samples_per_encode {
+                    let samples_to_write = &self.sample_cache[consumed..consumed + self.num_samples_per_encode];
 
-                    // Do encode. The output size should be the same as the input samples a.k.a. block size.
-                    let size = self.encoder.encode_float(&samples_to_write, &mut buf)?;
-                    assert_eq!(size, buf.len());
-                    self.writer.write_all(&buf)?;
+                    // Do encode. The packet size varies for VBR, so the packet is prefixed by its size.
+                    let size = self.encoder.encode_float(samples_to_write, &mut self.packet_buf)?;
+                    self.writer.write_all(&(size as u16).to_le_bytes())?;
+                    self.writer.write_all(&self.packet_buf[..size])?;
 
                     // Update statistics
-                    cached_length -= self.num_samples_per_encode;
+                    consumed += self.num_samples_per_encode;
                     self.samples_written += self.num_samples_per_encode as u64;
-                    self.bytes_written += buf.len() as u64;
+                    self.bytes_written += 2 + size as u64;
                 }
-                self.sample_cache = iter.collect();
+                self.sample_cache.drain(..consumed);
                 Ok(())
             }
 
+            /// * Pad silence to flush the delayed audio out of the encoder, then pad to the block size to trigger it to write.
+            /// * The number of the padded frames is recorded in the `fmt ` chunk for the decoder to drop them.
             pub fn flush(&mut self) -> Result<(), AudioWriteError> {
-                if !self.sample_cache.is_empty() {
-                    let pad = (self.num_samples_per_encode
-                        - self.sample_cache.len() % self.num_samples_per_encode)
+                let delay = self.opus_data.pre_skip as usize * self.channels as usize;
+                let cached = self.sample_cache.len() + delay;
+                let pad = delay
+                    + (self.num_samples_per_encode - cached % self.num_samples_per_encode)
                         % self.num_samples_per_encode;
-
-                    // Pad to the block size to trigger it to write.
-                    self.write_interleaved_samples(&vec![0.0f32; pad])?;
-                }
+                self.write_interleaved_samples(&vec![0.0f32; pad])?;
+                self.opus_data.padding += (pad / self.channels as usize) as u32;
                 Ok(())
             }
         }
@@ -1838,12 +2829,15 @@ pub mod opus {
                     .field("sample_rate", &self.sample_rate)
                     .field("cache_duration", &self.cache_duration)
                     .field("num_samples_per_encode", &self.num_samples_per_encode)
+                    .field("max_frames_per_write", &self.max_frames_per_write)
                     .field(
                         "sample_cache",
                         &format_args!("[f32; {}]", self.sample_cache.len()),
                     )
                     .field("samples_written", &self.samples_written)
                     .field("bytes_written", &self.bytes_written)
+                    .field("nominal_bitrate", &self.nominal_bitrate)
+                    .field("opus_data", &self.opus_data)
                     .finish()
             }
         }
@@ -1854,7 +2848,15 @@ pub mod opus {
             }
 
             fn get_max_channels(&self) -> u16 {
-                255
+                OPUS_MAX_CHANNELS
+            }
+
+            fn get_max_frames_per_write(&self) -> Option<usize> {
+                Some(self.max_frames_per_write)
+            }
+
+            fn native_sample_type(&self) -> WaveSampleType {
+                WaveSampleType::F32
             }
 
             fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
@@ -1862,12 +2864,11 @@ pub mod opus {
             }
 
             fn get_bitrate(&self) -> u32 {
+                // The `samples_written` counts the samples of all of the channels.
                 if self.samples_written != 0 {
-                    (self.sample_rate as u64 * self.bytes_written / self.samples_written
-                        * self.channels as u64
-                        * 8) as u32
+                    (self.bytes_written * 8 * self.sample_rate as u64 * self.channels as u64 / self.samples_written) as u32
                 } else {
-                    self.sample_rate * self.channels as u32 * 8 // Fake data
+                    self.nominal_bitrate
                 }
             }
 
@@ -1877,15 +2878,17 @@ pub mod opus {
                     channels: self.channels,
                     sample_rate: self.sample_rate,
                     byte_rate: self.get_bitrate() / 8,
-                    block_align: self.num_samples_per_encode as u16,
+                    block_align: 1,
                     bits_per_sample: 0,
-                    extension: None,
+                    extension: Some(FmtExtension::new_opus(self.opus_data)),
                 })
             }
 
             fn update_fmt_chunk(&self, fmt: &mut FmtChunk) -> Result<(), AudioWriteError> {
                 fmt.byte_rate = self.get_bitrate() / 8;
-                fmt.block_align = self.num_samples_per_encode as u16;
+                if let Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. }) = &mut fmt.extension {
+                    *opus_data = self.opus_data;
+                }
                 Ok(())
             }
 
@@ -1895,6 +2898,11 @@ pub mod opus {
                 Ok(())
             }
 
+            /// * The samples in the `sample_cache` waiting for a whole Opus block.
+            fn get_frames_buffered(&self) -> u64 {
+                (self.sample_cache.len() / self.channels as usize) as u64
+            }
+
             fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
             fn write_interleaved_samples_i16(&mut self, samples: &[i16]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
             fn write_interleaved_samples_i24(&mut self, samples: &[i24]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
@@ -1936,17 +2944,26 @@ pub mod opus {
 pub mod flac_enc {
     use std::{
         borrow::Cow,
+        cell::RefCell,
+        fmt::{self, Debug, Formatter},
         io::{self, ErrorKind, Seek, SeekFrom, Write},
+        rc::Rc,
+        thread,
     };
 
-    use super::EncoderToImpl;
+    use super::{Encoder, EncoderToImpl};
+
+    #[cfg(feature = "fs")]
+    use std::{fs::File, io::BufWriter, path::Path};
 
     use flac::{FlacEncoderUnmovable, options::{FlacCompression as RealFlacCompression, FlacEncoderParams as RealFlacEncoderParams}};
-    use io_utils::Writer;
+    use io_utils::{Writer, CursorVecU8};
     use sampletypes::{i24, u24};
-    use audioutils::{sample_conv, sample_conv_batch, stereos_conv};
+    use audioutils::{sample_conv, stereos_conv};
+    use sampletypes::SampleType;
     use crate::errors::{AudioWriteError, IOErrorInfo};
-    use crate::wavcore::{format_tags::*, FmtChunk, ListChunk, flac::{FlacCompression, FlacEncoderParams, get_listinfo_flacmeta}};
+    use crate::hacks;
+    use crate::wavcore::{format_tags::*, FmtChunk, ListChunk, WaveSampleType, flac::{FlacCompression, FlacEncoderParams, FLAC_CHANNEL_MASK_TAG, get_flac_channel_mask, get_flac_channel_order, get_listinfo_flacmeta}};
 
     impl Into<RealFlacCompression> for FlacCompression {
         fn into(self) -> RealFlacCompression {
@@ -1979,11 +2996,244 @@ pub mod flac_enc {
 
     #[derive(Debug)]
     pub struct FlacEncoderWrap<'a> {
-        encoder: Box<FlacEncoderUnmovable<'a, &'a mut dyn Writer>>,
+        encoder: Box<FlacEncoderUnmovable<'a, SharedFlacWriter<'a>>>,
+        writer: SharedFlacWriter<'a>,
         params: FlacEncoderParams,
         write_offset: u64,
         frames_written: u64,
         bytes_written: Box<u64>,
+        initialized: bool,
+
+        /// * The comments inserted into the encoder, kept to insert them again when the encoder is recreated by `set_total_frames_estimate()`.
+        comments: Vec<(String, String)>,
+
+        /// * The WAV channel of each FLAC channel, `None` if the WAV channel order is the FLAC channel order.
+        channel_order: Option<Vec<usize>>,
+
+        /// * The multithreaded encoding state, `None` for single-threaded encoding.
+        pool: Option<FlacWorkerPool>,
+    }
+
+    /// * The writer shared by the `libFLAC` encoders and the `FlacEncoderWrap`, every write borrows it only for the duration of the call.
+    #[derive(Clone)]
+    struct SharedFlacWriter<'a>(Rc<RefCell<&'a mut dyn Writer>>);
+
+    impl<'a> SharedFlacWriter<'a> {
+        fn new(writer: &'a mut dyn Writer) -> Self {
+            Self(Rc::new(RefCell::new(writer)))
+        }
+    }
+
+    impl Debug for SharedFlacWriter<'_> {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            f.debug_struct("SharedFlacWriter").finish_non_exhaustive()
+        }
+    }
+
+    impl Write for SharedFlacWriter<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.borrow_mut().flush()
+        }
+    }
+
+    impl Seek for SharedFlacWriter<'_> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.borrow_mut().seek(pos)
+        }
+    }
+
+    /// * How many FLAC blocks at least are in a segment for a worker thread to encode.
+    const FLAC_SEGMENT_BLOCKS: usize = 64;
+
+    /// * The multithreaded FLAC encoding state.
+    /// * The encoder of the `FlacEncoderWrap` only writes the header, and every segment of the audio is encoded by a worker thread with its own `libFLAC` encoder.
+    ///   The frames from the workers are renumbered and written in order, then the `STREAMINFO` of the header is updated on `finish()`.
+    struct FlacWorkerPool {
+        num_threads: usize,
+        segment_frames: usize,
+        pending: Vec<i32>,
+        frames_encoded: u64,
+        next_frame_number: u64,
+        min_frame_size: u32,
+        max_frame_size: u32,
+        md5: md5::Context,
+        md5_buffer: Vec<u8>,
+    }
+
+    impl FlacWorkerPool {
+        fn new(params: &FlacEncoderParams) -> Self {
+            // The block size of each compression level of `libFLAC`
+            let block_size = match params.compression {
+                FlacCompression::Level0 | FlacCompression::Level1 | FlacCompression::Level2 => 1152,
+                _ => 4096,
+            };
+            // `libFLAC` decides the loose mid-side stereo once every this many blocks, the segments must be aligned to it.
+            let mid_side_blocks = ((params.sample_rate as f64 * 0.4 / block_size as f64 + 0.5) as usize).max(1);
+            let segment_blocks = FLAC_SEGMENT_BLOCKS.div_ceil(mid_side_blocks) * mid_side_blocks;
+            Self {
+                num_threads: params.worker_threads,
+                segment_frames: segment_blocks * block_size,
+                pending: Vec::new(),
+                frames_encoded: 0,
+                next_frame_number: 0,
+                min_frame_size: u32::MAX,
+                max_frame_size: 0,
+                md5: md5::Context::new(),
+                md5_buffer: Vec::new(),
+            }
+        }
+
+        // `libFLAC` calculates the MD5 of the samples in little-endian bytes of the `bits_per_sample`.
+        fn consume_md5(&mut self, samples: &[i32], bits_per_sample: u32) {
+            let bytes_per_sample = bits_per_sample.div_ceil(8) as usize;
+            self.md5_buffer.clear();
+            for sample in samples.iter() {
+                self.md5_buffer.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample]);
+            }
+            self.md5.consume(&self.md5_buffer);
+        }
+    }
+
+    impl Debug for FlacWorkerPool {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            f.debug_struct("FlacWorkerPool")
+                .field("num_threads", &self.num_threads)
+                .field("segment_frames", &self.segment_frames)
+                .field("pending", &format_args!("[i32; {}]", self.pending.len()))
+                .field("frames_encoded", &self.frames_encoded)
+                .field("next_frame_number", &self.next_frame_number)
+                .field("min_frame_size", &self.min_frame_size)
+                .field("max_frame_size", &self.max_frame_size)
+                .finish_non_exhaustive()
+        }
+    }
+
+    // What a worker encoder has written
+    #[derive(Default)]
+    struct FlacSegmentOutput {
+        header_written: bool,
+        updating_header: bool,
+        position: u64,
+        frames: Vec<Vec<u8>>,
+    }
+
+    // Encode a segment of samples with a new `libFLAC` encoder, returns the encoded frames.
+    fn encode_segment(params: &FlacEncoderParams, samples: &[i32]) -> Result<Vec<Vec<u8>>, AudioWriteError> {
+        let mut sink = CursorVecU8::default();
+        encode_segment_to(params, samples, &mut sink)
+    }
+
+    fn encode_segment_to<'b>(params: &FlacEncoderParams, samples: &[i32], sink: &'b mut dyn Writer) -> Result<Vec<Vec<u8>>, AudioWriteError> {
+        let output = Rc::new(RefCell::new(FlacSegmentOutput::default()));
+        let (on_write, on_seek, on_tell) = (output.clone(), output.clone(), output.clone());
+        let real_params: RealFlacEncoderParams = (*params).into();
+        let mut encoder = Box::new(FlacEncoderUnmovable::new(
+            sink,
+            Box::new(
+                move |_writer: &mut &'b mut dyn Writer, data: &[u8]| -> io::Result<()> {
+                    let mut output = on_write.borrow_mut();
+                    output.position += data.len() as u64;
+                    // `libFLAC` writes a whole frame in one call, the header is written before and updated after the frames.
+                    if output.header_written && !output.updating_header {
+                        output.frames.push(data.to_vec());
+                    }
+                    Ok(())
+                },
+            ),
+            Box::new(
+                move |_writer: &mut &'b mut dyn Writer, position: u64| -> io::Result<()> {
+                    let mut output = on_seek.borrow_mut();
+                    output.updating_header = true;
+                    output.position = position;
+                    Ok(())
+                },
+            ),
+            Box::new(move |_writer: &mut &'b mut dyn Writer| -> io::Result<u64> {
+                Ok(on_tell.borrow().position)
+            }),
+            &real_params,
+        )?);
+        encoder.initialize()?;
+        output.borrow_mut().header_written = true;
+        encoder.write_interleaved_samples(samples)?;
+        encoder.finish()?;
+        drop(encoder);
+        Ok(std::mem::take(&mut output.borrow_mut().frames))
+    }
+
+    // The CRC-8 of the FLAC frame header, polynomial `x^8 + x^2 + x^1 + x^0`
+    fn flac_crc8(data: &[u8]) -> u8 {
+        data.iter().fold(0u8, |crc, byte| {
+            (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 })
+        })
+    }
+
+    // The CRC-16 of the FLAC frame, polynomial `x^16 + x^15 + x^2 + x^0`
+    fn flac_crc16(data: &[u8]) -> u16 {
+        data.iter().fold(0u16, |crc, byte| {
+            (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 })
+        })
+    }
+
+    // The "UTF-8" coded number of the FLAC frame header
+    fn flac_utf8_number(number: u64) -> Vec<u8> {
+        if number < 0x80 {
+            return vec![number as u8];
+        }
+        let num_bytes = match number {
+            0..0x800 => 2,
+            0x800..0x10000 => 3,
+            0x10000..0x200000 => 4,
+            0x200000..0x4000000 => 5,
+            0x4000000..0x80000000 => 6,
+            _ => 7,
+        };
+        let mut ret = vec![0u8; num_bytes];
+        let mut number = number;
+        for byte in ret[1..].iter_mut().rev() {
+            *byte = 0x80 | (number & 0x3F) as u8;
+            number >>= 6;
+        }
+        ret[0] = ((0xFF00u16 >> num_bytes) as u8) | number as u8;
+        ret
+    }
+
+    // Change the frame number of a fixed block size FLAC frame, and update the CRCs.
+    fn renumber_flac_frame(frame: &[u8], frame_number: u64) -> Result<Vec<u8>, AudioWriteError> {
+        let bad_frame = || AudioWriteError::OtherReason("Got a bad FLAC frame from the worker thread.".to_string());
+        if frame.len() < 8 || frame[0] != 0xFF || frame[1] != 0xF8 {
+            return Err(bad_frame());
+        }
+        let number_length = match (!frame[4]).leading_zeros() {
+            0 => 1,
+            n => n as usize,
+        };
+        let block_size_length = match frame[2] >> 4 {
+            6 => 1,
+            7 => 2,
+            _ => 0,
+        };
+        let sample_rate_length = match frame[2] & 0x0F {
+            12 => 1,
+            13 | 14 => 2,
+            _ => 0,
+        };
+        let crc8_pos = 4 + number_length + block_size_length + sample_rate_length;
+        if crc8_pos + 3 > frame.len() {
+            return Err(bad_frame());
+        }
+        let mut ret = Vec::with_capacity(frame.len() + 6);
+        ret.extend_from_slice(&frame[..4]);
+        ret.extend(flac_utf8_number(frame_number));
+        ret.extend_from_slice(&frame[4 + number_length..crc8_pos]);
+        ret.push(flac_crc8(&ret));
+        ret.extend_from_slice(&frame[crc8_pos + 1..frame.len() - 2]);
+        ret.extend(flac_crc16(&ret).to_be_bytes());
+        Ok(ret)
     }
 
     impl<'a> FlacEncoderWrap<'a> {
@@ -1992,36 +3242,210 @@ pub mod flac_enc {
             params: &FlacEncoderParams,
         ) -> Result<Self, AudioWriteError> {
             let params = *params;
-            let real_params: RealFlacEncoderParams = params.into();
             let write_offset = writer.stream_position()?;
+            let writer = SharedFlacWriter::new(writer);
             let mut bytes_written = Box::new(0u64);
             let bytes_written_ptr = (&mut *bytes_written) as *mut u64;
-            // Let the closures capture the pointer of the boxed variables, then use these pointers to update the variables.
             Ok(Self {
-                encoder: Box::new(FlacEncoderUnmovable::new(
+                encoder: Self::create_encoder(writer.clone(), &params, write_offset, bytes_written_ptr)?,
+                writer,
+                params,
+                write_offset,
+                frames_written: 0,
+                bytes_written,
+                initialized: false,
+                comments: Vec::new(),
+                channel_order: None,
+                pool: if params.worker_threads > 1 {
+                    Some(FlacWorkerPool::new(&params))
+                } else {
+                    None
+                },
+            })
+        }
+
+        // Let the closures capture the pointer of the boxed variables, then use these pointers to update the variables.
+        fn create_encoder(
+            writer: SharedFlacWriter<'a>,
+            params: &FlacEncoderParams,
+            write_offset: u64,
+            bytes_written_ptr: *mut u64,
+        ) -> Result<Box<FlacEncoderUnmovable<'a, SharedFlacWriter<'a>>>, AudioWriteError> {
+            let real_params: RealFlacEncoderParams = (*params).into();
+            Ok(Box::new(FlacEncoderUnmovable::new(
                     writer,
                     Box::new(
-                        move |writer: &mut &'a mut dyn Writer, data: &[u8]| -> io::Result<()> {
+                        move |writer: &mut SharedFlacWriter<'a>, data: &[u8]| -> io::Result<()> {
                             unsafe { *bytes_written_ptr += data.len() as u64 };
                             writer.write_all(data)
                         },
                     ),
                     Box::new(
-                        move |writer: &mut &'a mut dyn Writer, position: u64| -> io::Result<()> {
+                        move |writer: &mut SharedFlacWriter<'a>, position: u64| -> io::Result<()> {
                             writer.seek(SeekFrom::Start(write_offset + position))?;
                             Ok(())
                         },
                     ),
-                    Box::new(move |writer: &mut &'a mut dyn Writer| -> io::Result<u64> {
+                    Box::new(move |writer: &mut SharedFlacWriter<'a>| -> io::Result<u64> {
                         Ok(write_offset + writer.stream_position()?)
                     }),
                     &real_params,
-                )?),
-                params,
-                write_offset,
-                frames_written: 0,
-                bytes_written,
-            })
+                )?))
+        }
+
+        /// * Set the `total_samples_estimate` of the params, the encoder is recreated with the new params, and the inserted comments are inserted again.
+        /// * The ID3 metadata from `inherit_metadata_from_id3()` isn't kept, call it after this.
+        pub fn set_total_samples_estimate(&mut self, total_samples_estimate: u64) -> Result<(), AudioWriteError> {
+            if self.initialized {
+                return Err(AudioWriteError::InvalidArguments(
+                    "The FLAC encoder was initialized, the total samples estimate can't be changed.".to_owned(),
+                ));
+            }
+            self.params.total_samples_estimate = total_samples_estimate;
+            let bytes_written_ptr = (&mut *self.bytes_written) as *mut u64;
+            self.encoder = Self::create_encoder(self.writer.clone(), &self.params, self.write_offset, bytes_written_ptr)?;
+            for (key, value) in self.comments.iter() {
+                self.encoder.insert_comments(key, value)?;
+            }
+            Ok(())
+        }
+
+        /// * Set the channel mask of the audio to write, must be called before `begin_encoding()`.
+        /// * The channels are rearranged into the FLAC channel order if the speaker positions fit, see `get_flac_channel_order()`.
+        /// * If the channel mask isn't the one of the FLAC channel order, it's saved in the `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` comment.
+        pub fn set_channel_mask(&mut self, channel_mask: u32) -> Result<(), AudioWriteError> {
+            if self.initialized {
+                return Err(AudioWriteError::InvalidArguments(
+                    "The FLAC encoder was initialized, the channel mask can't be changed.".to_owned(),
+                ));
+            }
+            let channels = self.params.channels;
+            if channels < 3 || channel_mask == 0 {
+                return Ok(());
+            }
+            if channel_mask != get_flac_channel_mask(channels) {
+                self.insert_comment(FLAC_CHANNEL_MASK_TAG, &format!("0x{channel_mask:04X}"))?;
+            }
+            self.channel_order = get_flac_channel_order(channels, channel_mask)
+                .filter(|order| order.iter().enumerate().any(|(i, channel)| i != *channel));
+            Ok(())
+        }
+
+        // Rearrange the interleaved samples into the FLAC channel order.
+        fn reorder_samples<'b>(&self, samples: &'b [i32]) -> Cow<'b, [i32]> {
+            match &self.channel_order {
+                None => Cow::Borrowed(samples),
+                Some(order) => Cow::Owned(
+                    samples
+                        .chunks_exact(order.len())
+                        .flat_map(|frame| order.iter().map(|channel| frame[*channel]))
+                        .collect(),
+                ),
+            }
+        }
+
+        fn insert_comment(&mut self, key: &str, value: &str) -> Result<(), AudioWriteError> {
+            self.encoder.insert_comments(key, &value.to_string())?;
+            self.comments.push((key.to_string(), value.to_string()));
+            Ok(())
+        }
+
+        // Put the samples into the worker pool, and encode them if there are enough samples for every worker.
+        fn queue_samples(&mut self, samples: &[i32]) -> Result<(), AudioWriteError> {
+            let fitted = self.fit_samples_to_bps(samples);
+            if let Some(pool) = self.pool.as_mut() {
+                pool.pending.extend_from_slice(&fitted);
+            }
+            self.frames_written += samples.len() as u64 / self.get_channels() as u64;
+            self.encode_pending(false)
+        }
+
+        // Let the workers encode the queued samples, then write the frames in order.
+        // If `flush_all` is false, only the whole batches of segments are encoded.
+        fn encode_pending(&mut self, flush_all: bool) -> Result<(), AudioWriteError> {
+            let Some(pool) = self.pool.as_mut() else {
+                return Ok(());
+            };
+            let channels = self.params.channels as usize;
+            let segment_length = pool.segment_frames * channels;
+            let batch_length = segment_length * pool.num_threads;
+            while pool.pending.len() >= batch_length || (flush_all && !pool.pending.is_empty()) {
+                let batch: Vec<i32> = pool.pending.drain(..batch_length.min(pool.pending.len())).collect();
+                let params = self.params;
+                let segments: Vec<Result<Vec<Vec<u8>>, AudioWriteError>> = thread::scope(|scope| {
+                    let workers: Vec<_> = batch
+                        .chunks(segment_length)
+                        .map(|segment| scope.spawn(move || encode_segment(&params, segment)))
+                        .collect();
+                    workers
+                        .into_iter()
+                        .map(|worker| worker.join().unwrap_or_else(|_| Err(AudioWriteError::OtherReason("The FLAC worker thread panicked.".to_string()))))
+                        .collect()
+                });
+                pool.consume_md5(&batch, self.params.bits_per_sample);
+                pool.frames_encoded += (batch.len() / channels) as u64;
+
+                for frames in segments {
+                    for frame in frames?.iter() {
+                        let frame = renumber_flac_frame(frame, pool.next_frame_number)?;
+                        pool.next_frame_number += 1;
+                        pool.min_frame_size = pool.min_frame_size.min(frame.len() as u32);
+                        pool.max_frame_size = pool.max_frame_size.max(frame.len() as u32);
+                        self.writer.write_all(&frame)?;
+                        *self.bytes_written += frame.len() as u64;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        // After the encoder of the header finished, write the actual frame sizes, number of samples and the MD5 into the `STREAMINFO`.
+        fn update_stream_info(&mut self, end_of_stream: u64) -> Result<(), AudioWriteError> {
+            let Some(pool) = self.pool.as_mut() else {
+                return Ok(());
+            };
+            if pool.next_frame_number == 0 {
+                return Ok(());
+            }
+            let mut frame_sizes = [0u8; 6];
+            frame_sizes[0..3].copy_from_slice(&pool.min_frame_size.to_be_bytes()[1..]);
+            frame_sizes[3..6].copy_from_slice(&pool.max_frame_size.to_be_bytes()[1..]);
+
+            // The 36-bit total samples share a byte with the low 4 bits of the bits per sample.
+            let frames_encoded = pool.frames_encoded;
+            let md5 = std::mem::replace(&mut pool.md5, md5::Context::new()).compute();
+            let mut samples_and_md5 = [0u8; 21];
+            samples_and_md5[0..5].copy_from_slice(&self.total_samples_bytes(frames_encoded));
+            samples_and_md5[5..21].copy_from_slice(&md5.0);
+
+            // The `STREAMINFO` is the first metadata block right after `fLaC` and the block header.
+            let stream_info_offset = self.write_offset + 8;
+            let writer = &mut self.writer;
+            writer.seek(SeekFrom::Start(stream_info_offset + 4))?;
+            writer.write_all(&frame_sizes)?;
+            writer.seek(SeekFrom::Start(stream_info_offset + 13))?;
+            writer.write_all(&samples_and_md5)?;
+            writer.seek(SeekFrom::Start(end_of_stream))?;
+            Ok(())
+        }
+
+        // The 36-bit total samples share a byte with the low 4 bits of the bits per sample. Zero means unknown if it doesn't fit.
+        fn total_samples_bytes(&self, total_samples: u64) -> [u8; 5] {
+            let total_samples = if total_samples < 1 << 36 { total_samples } else { 0 };
+            let mut ret = [0u8; 5];
+            ret[0] = ((((self.params.bits_per_sample - 1) & 0x0F) as u8) << 4) | ((total_samples >> 32) as u8 & 0x0F);
+            ret[1..5].copy_from_slice(&(total_samples as u32).to_be_bytes());
+            ret
+        }
+
+        // The estimate from the params could be different from what was actually written, write the real total samples into the `STREAMINFO`.
+        fn update_total_samples(&mut self, end_of_stream: u64) -> Result<(), AudioWriteError> {
+            let total_samples = self.total_samples_bytes(self.frames_written);
+            let writer = &mut self.writer;
+            writer.seek(SeekFrom::Start(self.write_offset + 8 + 13))?;
+            writer.write_all(&total_samples)?;
+            writer.seek(SeekFrom::Start(end_of_stream))?;
+            Ok(())
         }
 
         // The input samples fill all the domains of the i32, so we should shrink the bits to `self.params.bits_per_sample` to achieve good compression.
@@ -2107,9 +3531,10 @@ pub mod flac_enc {
         ) -> Result<(), AudioWriteError> {
             match list_chunk {
                 ListChunk::Info(list) => {
+                    // The keys without the FLAC comment are skipped.
                     for (list_key, flac_key) in get_listinfo_flacmeta().iter() {
-                        if let Some(data) = list.get(list_key.to_owned()) {
-                            self.encoder.insert_comments(flac_key, data).unwrap();
+                        if let Some(data) = list.get(list_key) {
+                            self.insert_comment(flac_key, data)?;
                         }
                     }
                 }
@@ -2125,6 +3550,11 @@ pub mod flac_enc {
             &mut self,
             samples: &[i32],
         ) -> Result<(), AudioWriteError> {
+            let samples = self.reorder_samples(samples);
+            let samples = &samples[..];
+            if self.pool.is_some() {
+                return self.queue_samples(samples);
+            }
             match self
                 .encoder
                 .write_interleaved_samples(&self.fit_samples_to_bps(samples))
@@ -2138,6 +3568,10 @@ pub mod flac_enc {
         }
 
         pub fn write_mono_channel(&mut self, monos: &[i32]) -> Result<(), AudioWriteError> {
+            if self.pool.is_some() {
+                let channels = self.get_channels() as usize;
+                return self.queue_samples(&monos.iter().flat_map(|mono| std::iter::repeat_n(*mono, channels)).collect::<Vec<i32>>());
+            }
             match self
                 .encoder
                 .write_mono_channel(&self.fit_samples_to_bps(monos))
@@ -2151,6 +3585,10 @@ pub mod flac_enc {
         }
 
         pub fn write_stereos(&mut self, stereos: &[(i32, i32)]) -> Result<(), AudioWriteError> {
+            if self.pool.is_some() {
+                self.check_channels(2)?;
+                return self.queue_samples(&stereos.iter().flat_map(|(l, r)| [*l, *r]).collect::<Vec<i32>>());
+            }
             match self
                 .encoder
                 .write_stereos(&self.fit_stereos_to_bps(stereos))
@@ -2164,6 +3602,10 @@ pub mod flac_enc {
         }
 
         pub fn write_monos(&mut self, monos: &[Vec<i32>]) -> Result<(), AudioWriteError> {
+            if self.pool.is_some() || self.channel_order.is_some() {
+                self.check_channels(monos.len() as u16)?;
+                return self.write_interleaved_samples(&audioutils::monos_to_interleaved_samples(monos)?);
+            }
             match self.encoder.write_monos(&self.fit_2d_to_bps(monos)) {
                 Ok(_) => {
                     self.frames_written += monos[0].len() as u64;
@@ -2174,6 +3616,9 @@ pub mod flac_enc {
         }
 
         pub fn write_frames(&mut self, frames: &[Vec<i32>]) -> Result<(), AudioWriteError> {
+            if self.pool.is_some() || self.channel_order.is_some() {
+                return self.write_interleaved_samples(&audioutils::frames_to_interleaved_samples(frames)?);
+            }
             match self.encoder.write_frames(&self.fit_2d_to_bps(frames)) {
                 Ok(_) => {
                     self.frames_written += frames.len() as u64;
@@ -2193,17 +3638,32 @@ pub mod flac_enc {
             8
         }
 
+        fn native_sample_type(&self) -> WaveSampleType {
+            WaveSampleType::S32
+        }
+
         fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
             self.encoder.initialize()?;
+            self.initialized = true;
             Ok(())
         }
 
+        fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<bool, AudioWriteError> {
+            self.insert_comment(key, value)?;
+            Ok(true)
+        }
+
+        fn set_total_frames_estimate(&mut self, num_frames: u64) -> Result<(), AudioWriteError> {
+            self.set_total_samples_estimate(num_frames)
+        }
+
         fn get_bitrate(&self) -> u32 {
             if self.frames_written != 0 {
                 (*self.bytes_written * self.get_sample_rate() as u64 * 8 / self.frames_written)
                     as u32
             } else {
-                self.get_sample_rate() * self.get_channels() as u32 * 8 // Fake data
+                // Nothing was encoded, the uncompressed bitrate is the upper bound.
+                self.get_sample_rate() * self.get_channels() as u32 * self.params.bits_per_sample
             }
         }
 
@@ -2224,8 +3684,26 @@ pub mod flac_enc {
             Ok(())
         }
 
+        fn get_frames_buffered(&self) -> u64 {
+            match &self.pool {
+                Some(pool) => (pool.pending.len() / self.params.channels as usize) as u64,
+                None => 0,
+            }
+        }
+
         fn finish(&mut self) -> Result<(), AudioWriteError> {
-            Ok(self.encoder.finish()?)
+            if self.pool.is_none() {
+                self.encoder.finish()?;
+                if !self.initialized || self.frames_written == 0 {
+                    return Ok(());
+                }
+                let end_of_stream = self.writer.stream_position()?;
+                return self.update_total_samples(end_of_stream);
+            }
+            self.encode_pending(true)?;
+            let end_of_stream = self.writer.stream_position()?;
+            self.encoder.finish()?;
+            self.update_stream_info(end_of_stream)
         }
 
         fn write_interleaved_samples__i8(&mut self, samples: &[i8 ]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
@@ -2266,32 +3744,196 @@ pub mod flac_enc {
         fn write_stereos_u64(&mut self, stereos: &[(u64, u64)]) -> Result<(), AudioWriteError> {self.write_stereos(&stereos_conv(stereos))}
         fn write_stereos_f32(&mut self, stereos: &[(f32, f32)]) -> Result<(), AudioWriteError> {self.write_stereos(&stereos_conv(stereos))}
         fn write_stereos_f64(&mut self, stereos: &[(f64, f64)]) -> Result<(), AudioWriteError> {self.write_stereos(&stereos_conv(stereos))}
+    }
 
-        fn write_monos__i8(&mut self, monos_array: &[Vec<i8 >]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_i16(&mut self, monos_array: &[Vec<i16>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_i24(&mut self, monos_array: &[Vec<i24>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_i32(&mut self, monos_array: &[Vec<i32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_i64(&mut self, monos_array: &[Vec<i64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos__u8(&mut self, monos_array: &[Vec<u8 >]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_u16(&mut self, monos_array: &[Vec<u16>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_u24(&mut self, monos_array: &[Vec<u24>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_u32(&mut self, monos_array: &[Vec<u32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_u64(&mut self, monos_array: &[Vec<u64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_f32(&mut self, monos_array: &[Vec<f32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-        fn write_monos_f64(&mut self, monos_array: &[Vec<f64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-
-        fn write_frames__i8(&mut self, frames: &[Vec<i8 >]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_i16(&mut self, frames: &[Vec<i16>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_i24(&mut self, frames: &[Vec<i24>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_i32(&mut self, frames: &[Vec<i32>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_i64(&mut self, frames: &[Vec<i64>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames__u8(&mut self, frames: &[Vec<u8 >]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_u16(&mut self, frames: &[Vec<u16>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_u24(&mut self, frames: &[Vec<u24>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_u32(&mut self, frames: &[Vec<u32>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_u64(&mut self, frames: &[Vec<u64>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_f32(&mut self, frames: &[Vec<f32>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
-        fn write_frames_f64(&mut self, frames: &[Vec<f64>]) -> Result<(), AudioWriteError> {self.write_frames(&sample_conv_batch(frames))}
+    /// * Write a bare `.flac` file without the RIFF container, by the same `FlacEncoderWrap` that the `WaveWriter` uses for the FLAC-in-WAV.
+    /// * The samples of any type are written like the `WaveWriter`, the metadata becomes the Vorbis comments, and the `STREAMINFO` is completed when finalizing.
+    /// * The metadata and the channel mask must be set before writing any samples.
+    /// * The FLAC file is finalized when the `FlacFileWriter` is dropped, or call `finalize()` to get the errors.
+    pub struct FlacFileWriter<'a> {
+        // The encoders borrow the boxed writer, they are declared before it to be dropped first.
+        /// * The encoder to be configured, it goes into `encoder` when the encoding begins.
+        unstarted: Option<FlacEncoderWrap<'a>>,
+        encoder: Encoder<'a>,
+        writer: Box<dyn Writer + 'a>,
+        channels: u16,
+        num_frames_written: u64,
+        finished: bool,
+    }
+
+    impl<'a> FlacFileWriter<'a> {
+        /// * Create the FLAC file through a file path.
+        #[cfg(feature = "fs")]
+        pub fn create<P: AsRef<Path>>(filename: P, params: &FlacEncoderParams) -> Result<FlacFileWriter<'static>, AudioWriteError> {
+            let file_writer = BufWriter::new(File::create(filename)?);
+            FlacFileWriter::from(Box::new(file_writer), params)
+        }
+
+        /// * Write the FLAC file to the writer, it starts at the current position of the writer.
+        /// * The writer must be seekable for the `STREAMINFO` to be updated when finalizing.
+        pub fn from(mut writer: Box<dyn Writer + 'a>, params: &FlacEncoderParams) -> Result<Self, AudioWriteError> {
+            let encoder = FlacEncoderWrap::new(hacks::force_borrow_mut!(*writer, dyn Writer + 'a), params)?;
+            Ok(Self {
+                unstarted: Some(encoder),
+                encoder: Encoder::default(),
+                writer,
+                channels: params.channels,
+                num_frames_written: 0,
+                finished: false,
+            })
+        }
+
+        fn get_unstarted(&mut self, what: &str) -> Result<&mut FlacEncoderWrap<'a>, AudioWriteError> {
+            self.unstarted.as_mut().ok_or_else(|| AudioWriteError::InvalidArguments(format!(
+                "The FLAC encoding has begun, the {what} can't be changed."
+            )))
+        }
+
+        /// * See `FlacEncoderWrap::set_channel_mask()`, the channels are rearranged into the FLAC channel order if the speaker positions fit.
+        pub fn set_channel_mask(&mut self, channel_mask: u32) -> Result<(), AudioWriteError> {
+            self.get_unstarted("channel mask")?.set_channel_mask(channel_mask)
+        }
+
+        /// * Set how many frames will be written, it's not needed for a seekable writer.
+        pub fn set_total_frames_estimate(&mut self, num_frames: u64) -> Result<(), AudioWriteError> {
+            self.get_unstarted("total frames estimate")?.set_total_samples_estimate(num_frames)
+        }
+
+        /// * Add a Vorbis comment, e.g. `TITLE`, `ARTIST`.
+        pub fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<(), AudioWriteError> {
+            self.get_unstarted("metadata")?.insert_comment(key, value)
+        }
+
+        /// * Add the Vorbis comments from the `LIST INFO` chunk, e.g. the one from `WaveReader::get_list_chunk()`.
+        pub fn inherit_metadata_from_list(&mut self, list_chunk: &ListChunk) -> Result<(), AudioWriteError> {
+            self.get_unstarted("metadata")?.inherit_metadata_from_list(list_chunk)
+        }
+
+        /// * Add the Vorbis comments from the ID3 tag.
+        #[cfg(feature = "id3")]
+        pub fn inherit_metadata_from_id3(&mut self, id3_tag: &id3::Tag) -> Result<(), AudioWriteError> {
+            self.get_unstarted("metadata")?.inherit_metadata_from_id3(id3_tag)
+        }
+
+        // The encoder writes the header of the FLAC file when it begins, then only the samples can be written.
+        fn begin_encoding(&mut self) -> Result<&mut Encoder<'a>, AudioWriteError> {
+            if self.finished {
+                return Err(AudioWriteError::AlreadyFinished("the FLAC file".to_owned()));
+            }
+            if let Some(encoder) = self.unstarted.take() {
+                self.encoder = Encoder::new(encoder);
+                self.encoder.begin_encoding()?;
+            }
+            Ok(&mut self.encoder)
+        }
+
+        pub fn get_channels(&self) -> u16 {
+            self.channels
+        }
+
+        pub fn get_num_frames_written(&self) -> u64 {
+            self.num_frames_written
+        }
+
+        /// * Write the interleaved samples, the length must be a multiple of the channel count.
+        pub fn write_interleaved_samples<S>(&mut self, samples: &[S]) -> Result<(), AudioWriteError>
+        where
+            S: SampleType,
+        {
+            if samples.len() % self.channels as usize != 0 {
+                return Err(AudioWriteError::TruncatedSamples);
+            }
+            self.begin_encoding()?.write_interleaved_samples(samples)?;
+            self.num_frames_written += (samples.len() / self.channels as usize) as u64;
+            Ok(())
+        }
+
+        /// * Write the audio frames, each frame has a sample for every channel.
+        pub fn write_frames<S>(&mut self, frames: &[Vec<S>]) -> Result<(), AudioWriteError>
+        where
+            S: SampleType,
+        {
+            self.begin_encoding()?.write_frames(frames)?;
+            self.num_frames_written += frames.len() as u64;
+            Ok(())
+        }
+
+        /// * Write the stereo samples, the FLAC file must have 2 channels.
+        pub fn write_stereos<S>(&mut self, stereos: &[(S, S)]) -> Result<(), AudioWriteError>
+        where
+            S: SampleType,
+        {
+            self.begin_encoding()?.write_stereos(stereos)?;
+            self.num_frames_written += stereos.len() as u64;
+            Ok(())
+        }
+
+        /// * Write the samples of each channel, all of the channels must have the same length.
+        pub fn write_monos<S>(&mut self, monos: &[Vec<S>]) -> Result<(), AudioWriteError>
+        where
+            S: SampleType,
+        {
+            self.begin_encoding()?.write_monos(monos)?;
+            self.num_frames_written += monos.first().map_or(0, |mono| mono.len()) as u64;
+            Ok(())
+        }
+
+        /// * Write the mono samples to all of the channels.
+        pub fn write_mono_channel<S>(&mut self, monos: &[S]) -> Result<(), AudioWriteError>
+        where
+            S: SampleType,
+        {
+            self.begin_encoding()?.write_mono_channel(monos)?;
+            self.num_frames_written += monos.len() as u64;
+            Ok(())
+        }
+
+        fn on_finish(&mut self) -> Result<(), AudioWriteError> {
+            // The FLAC file without any samples still has the header.
+            let begun = self.begin_encoding().map(|_| ());
+            self.finished = true;
+            begun?;
+            self.encoder.finish()?;
+            self.writer.flush()?;
+            Ok(())
+        }
+
+        /// * Finish the encoding, the `STREAMINFO` gets the number of the samples, the frame sizes and the MD5 of the audio.
+        pub fn finalize(mut self) -> Result<(), AudioWriteError> {
+            self.on_finish()
+        }
+
+        /// * Finalize the FLAC file and get the writer back.
+        pub fn into_inner(mut self) -> Result<Box<dyn Writer + 'a>, AudioWriteError> {
+            if !self.finished {
+                self.on_finish()?;
+            }
+
+            // The encoder borrows the writer, it must be gone before the writer is taken away.
+            self.encoder = Encoder::default();
+            Ok(std::mem::replace(&mut self.writer, Box::new(CursorVecU8::default())))
+        }
+    }
+
+    impl Debug for FlacFileWriter<'_> {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            f.debug_struct("FlacFileWriter")
+                .field("channels", &self.channels)
+                .field("num_frames_written", &self.num_frames_written)
+                .field("finished", &self.finished)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Drop for FlacFileWriter<'_> {
+        fn drop(&mut self) {
+            // Panicking here could abort the program, so the errors are just printed, call `finalize()` to get them.
+            if !self.finished {
+                if let Err(e) = self.on_finish() {
+                    eprintln!("Failed to finalize the FLAC file on drop: {e}");
+                }
+            }
+        }
     }
 
     use flac::errors::*;
@@ -2434,8 +4076,9 @@ pub mod oggvorbis_enc {
 
         use crate::errors::{AudioWriteError, IOErrorInfo};
         use crate::io_utils::{Reader, Writer, ReadWrite, CursorVecU8, SharedMultistreamIO, StreamType};
-        use crate::audioutils::{self, sample_conv, sample_conv_batch};
+        use crate::audioutils::{self, sample_conv};
         use crate::chunks::{FmtChunk, ext::{FmtExtension, VorbisHeaderData, OggVorbisData, OggVorbisWithHeaderData}};
+        use crate::wavcore::WaveSampleType;
         use crate::format_specs::format_tags::*;
         use crate::{i24, u24};
 
@@ -2452,45 +4095,59 @@ pub mod oggvorbis_enc {
                 }
             }
 
-            fn get_bitrate(&self, channels: u16, sample_rate: u32) -> Result<u32, AudioWriteError> {
+            /// * The nominal bitrate, for the quality VBR mode it's interpolated by the sample rate from `QUALITY_VBR_BITRATES`.
+            /// * Below the lowest sample rate of the table, the bitrate is scaled down by the sample rate.
+            ///   Above the highest one, the bitrate of the highest one is used, the encoder lowpasses the audio around 20 kHz anyway.
+            /// * For more than 2 channels, the stereo bitrate is scaled by the number of the channel pairs.
+            fn get_bitrate(&self, channels: u16, sample_rate: u32) -> u32 {
                 match self {
-                    Self::Vbr(bitrate) => Ok(*bitrate),
+                    Self::Vbr(bitrate) => *bitrate,
                     Self::QualityVbr(quality) => {
-                        let quality = ((quality * 10.0) as i32).clamp(0, 10);
-                        match sample_rate {
-                            48000 => {
-                                Ok([
-                                    [64000, 80000, 96000, 112000, 128000, 160000, 192000, 240000, 256000, 350000, 450000],
-                                    [48000, 64000, 72000,  80000,  88000,  96000, 112000, 128000, 144000, 192000, 256000],
-                                ][channels as usize][quality as usize])
-                            }
-                            44100 => {
-                                Ok([
-                                    [64000, 80000, 96000, 112000, 128000, 160000, 192000, 240000, 256000, 350000, 450000],
-                                    [48000, 64000, 72000,  80000,  88000,  96000, 112000, 128000, 144000, 192000, 256000],
-                                ][channels as usize][quality as usize])
-                            }
-                            22050 => {
-                                Ok([
-                                    [56000, 72000, 80000,  88000,  96000, 112000, 144000, 176000, 192000, 256000, 320000],
-                                    [36000, 42000, 48000,  52000,  56000,  64000,  80000,  88000,  96000, 128000, 168000],
-                                ][channels as usize][quality as usize])
+                        let quality = ((quality * 10.0) as i32).clamp(0, 10) as usize;
+                        let row = if channels == 1 { 1 } else { 0 };
+                        let bitrate_at = |index: usize| QUALITY_VBR_BITRATES[index].1[row][quality] as f64;
+                        let bitrate = match QUALITY_VBR_BITRATES.iter().position(|(rate, _)| *rate >= sample_rate) {
+                            Some(0) => bitrate_at(0) * sample_rate as f64 / QUALITY_VBR_BITRATES[0].0 as f64,
+                            Some(index) => {
+                                let lower_rate = QUALITY_VBR_BITRATES[index - 1].0 as f64;
+                                let upper_rate = QUALITY_VBR_BITRATES[index].0 as f64;
+                                let t = (sample_rate as f64 - lower_rate) / (upper_rate - lower_rate);
+                                bitrate_at(index - 1) + (bitrate_at(index) - bitrate_at(index - 1)) * t
                             }
-                            11025 => {
-                                Ok([
-                                    [36000, 44000, 50000,  52000,  56000,  64000,  80000,  96000, 112000, 144000, 168000],
-                                    [22000, 26000, 28000,  30000,  32000,  34000,  40000,  48000,  56000,  72000,  88000],
-                                ][channels as usize][quality as usize])
-                            }
-                            o => Err(AudioWriteError::InvalidArguments(format!("Invalid sample rate {o}. For Vorbis encoding, sample rate must be 48000, 44100, 22050, 11025."))),
+                            None => bitrate_at(QUALITY_VBR_BITRATES.len() - 1),
+                        };
+                        if channels > 2 {
+                            (bitrate * channels as f64 / 2.0) as u32
+                        } else {
+                            bitrate as u32
                         }
-                    },
-                    Self::Abr(bitrate) => Ok(*bitrate),
-                    Self::ConstrainedAbr(bitrate) => Ok(*bitrate),
+                    }
+                    Self::Abr(bitrate) => *bitrate,
+                    Self::ConstrainedAbr(bitrate) => *bitrate,
                 }
             }
         }
 
+        /// * The nominal bitrates of the quality VBR mode for the quality 0.0 to 1.0 at some sample rates, the first row is for the stereo audio, the second row is for the mono audio.
+        const QUALITY_VBR_BITRATES: [(u32, [[u32; 11]; 2]); 4] = [
+            (11025, [
+                [36000, 44000, 50000,  52000,  56000,  64000,  80000,  96000, 112000, 144000, 168000],
+                [22000, 26000, 28000,  30000,  32000,  34000,  40000,  48000,  56000,  72000,  88000],
+            ]),
+            (22050, [
+                [56000, 72000, 80000,  88000,  96000, 112000, 144000, 176000, 192000, 256000, 320000],
+                [36000, 42000, 48000,  52000,  56000,  64000,  80000,  88000,  96000, 128000, 168000],
+            ]),
+            (44100, [
+                [64000, 80000, 96000, 112000, 128000, 160000, 192000, 240000, 256000, 350000, 450000],
+                [48000, 64000, 72000,  80000,  88000,  96000, 112000, 128000, 144000, 192000, 256000],
+            ]),
+            (48000, [
+                [64000, 80000, 96000, 112000, 128000, 160000, 192000, 240000, 256000, 350000, 450000],
+                [48000, 64000, 72000,  80000,  88000,  96000, 112000, 128000, 144000, 192000, 256000],
+            ]),
+        ];
+
         impl From<OggVorbisBitrateStrategy> for VorbisBitrateManagementStrategy {
             /// * Convert to the `VorbisBitrateManagementStrategy` from `vorbis_rs` crate
             fn from(val: OggVorbisBitrateStrategy) -> Self {
@@ -2568,21 +4225,20 @@ pub mod oggvorbis_enc {
             pub fn create_vorbis_builder<W>(&self, writer: W) -> Result<VorbisEncoderBuilder<W>, AudioWriteError>
             where
                 W: Write {
-                let sample_rate = NonZero::new(self.sample_rate).unwrap();
+                // Any other sample rate that libvorbis refuses is reported by the builder.
+                let Some(sample_rate) = NonZero::new(self.sample_rate) else {
+                    return Err(AudioWriteError::InvalidArguments("The sample rate of the Vorbis encoder must not be zero.".to_owned()));
+                };
                 let channels = NonZero::new(self.channels as u8).unwrap();
 
                 let mut builder = VorbisEncoderBuilder::new(sample_rate, channels, writer)?;
 
-                match self.mode {
-                    OggVorbisMode::HaveNoCodebookHeader => (),
-                    _ => {
-                        if let Some(serial) = self.stream_serial {
-                            builder.stream_serial(serial);
-                        }
-                        if let Some(bitrate) = self.bitrate {
-                            builder.bitrate_management_strategy(bitrate.into());
-                        }
-                    }
+                // For `HaveNoCodebookHeader`, the decoder builds an encoder with the same parameters to regenerate the header, the bitrate strategy is recorded in the `fmt ` chunk for it.
+                if let Some(serial) = self.stream_serial {
+                    builder.stream_serial(serial);
+                }
+                if let Some(bitrate) = self.bitrate {
+                    builder.bitrate_management_strategy(bitrate.into());
                 }
 
                 builder.minimum_page_data_size(self.minimum_page_data_size);
@@ -2590,7 +4246,7 @@ pub mod oggvorbis_enc {
             }
 
             pub fn get_bitrate(&self) -> u32 {
-                self.bitrate.unwrap_or_default().get_bitrate(self.channels, self.sample_rate).unwrap()
+                self.bitrate.unwrap_or_default().get_bitrate(self.channels, self.sample_rate)
             }
         }
 
@@ -2638,14 +4294,21 @@ pub mod oggvorbis_enc {
                 writer: &'a mut dyn Writer,
                 params: &OggVorbisEncoderParams,
             ) -> Result<Self, AudioWriteError> {
+                if params.mode == OggVorbisMode::NakedVorbis && params.granule_offset != 0 {
+                    return Err(AudioWriteError::InvalidArguments("The naked Vorbis has no Ogg pages, its granule positions can't be offset.".to_string()));
+                }
                 let mut shared_writer = SharedAlterIO::default();
                 shared_writer.push_stream(SharedIO::Writer(writer));
                 shared_writer.push_stream(SharedIO::CursorU8(CursorVecU8::default()));
                 let data_offset = shared_writer.stream_position()?;
 
+                // Pick the serial here instead of letting `vorbis_rs` do it, so `get_stream_serial()` can tell which one is used.
+                let mut params = *params;
+                params.stream_serial.get_or_insert_with(random_stream_serial);
+
                 let mut ret = Self {
                     writer: shared_writer.clone(),
-                    params: *params,
+                    params,
                     encoder: OggVorbisEncoderOrBuilder::Builder {
                         builder: params.create_vorbis_builder(shared_writer.clone())?,
                         metadata: BTreeMap::new(),
@@ -2674,6 +4337,25 @@ pub mod oggvorbis_enc {
                 self.params.sample_rate
             }
 
+            /// Get the serial number of the Ogg stream, the one from the params, or the random one if the params didn't have it.
+            pub fn get_stream_serial(&self) -> i32 {
+                self.params.stream_serial.unwrap_or_default()
+            }
+
+            /// * Get the granule position of the last Ogg page, it's the `granule_offset` plus the number of frames encoded. Returns `None` before `finish()`.
+            /// * The next segment of an Ogg chain continues from here by using it as its `granule_offset`.
+            pub fn get_final_granule_position(&self) -> Option<u64> {
+                match self.encoder {
+                    OggVorbisEncoderOrBuilder::Finished => Some(self.params.granule_offset + self.frames_written),
+                    _ => None,
+                }
+            }
+
+            /// Does the encoder write to the `Cursor` for the Ogg pages to be processed before they go to the `Writer`.
+            fn uses_cursor(&self) -> bool {
+                self.params.mode == OggVorbisMode::NakedVorbis || self.params.granule_offset != 0
+            }
+
             /// Insert a comment to the metadata. NOTE: When the decoder was built, you can not add comments anymore.
             pub fn insert_comment(
                 &mut self,
@@ -2706,6 +4388,11 @@ pub mod oggvorbis_enc {
                             }
                         }
                         self.encoder = OggVorbisEncoderOrBuilder::Encoder(builder.build()?);
+
+                        // The header pages were written as they are, the audio pages go through the `Cursor` if they need to be processed.
+                        if self.params.mode == OggVorbisMode::OriginalStreamCompatible {
+                            self.writer.set_stream(if self.uses_cursor() { 1 } else { 0 });
+                        }
                         Ok(())
                     }
                     OggVorbisEncoderOrBuilder::Encoder(_) => Ok(()),
@@ -2716,32 +4403,67 @@ pub mod oggvorbis_enc {
                 }
             }
 
-            /// Peel off the Ogg skin from the `Cursor` data, and write the naked data to the `Writer`
+            /// * Move what the encoder wrote to the `Writer`: the naked Vorbis data for `NakedVorbis`, or the Ogg pages with the granule positions offset by `granule_offset`.
+            /// * Otherwise the encoder writes to the `Writer` directly, only the number of bytes written is updated.
+            fn flush_pages(&mut self) -> Result<(), AudioWriteError> {
+                if self.params.mode == OggVorbisMode::NakedVorbis {
+                    self.peel_ogg()
+                } else if self.params.granule_offset != 0 {
+                    self.offset_granules()
+                } else {
+                    self.bytes_written = self.writer.stream_position()? - self.data_offset;
+                    Ok(())
+                }
+            }
+
+            /// * Peel off the Ogg skin from the `Cursor` data, and write the naked data to the `Writer`.
+            /// * The incomplete page stays in the `Cursor` for the next time.
             fn peel_ogg(&mut self) -> Result<(), AudioWriteError> {
                 let mut cursor = 0usize;
                 let mut packet_length = 0usize;
-                let data = self.writer.get_stream_mut(1).as_cursor().get_ref().clone();
+                let data = self.writer.get_stream_mut(1).take_cursor_data();
+                self.writer.set_stream(0);
                 while cursor < data.len() {
                     match OggPacket::from_bytes(&data[cursor..], &mut packet_length) {
                         Ok(oggpacket) => {
-                            self.writer.set_stream(0);
                             self.writer.write_all(&oggpacket.get_inner_data())?;
-                            self.writer.set_stream(1);
                             cursor += packet_length;
                         }
-                        Err(ioerr) => {
-                            match ioerr.kind() {
-                                ErrorKind::UnexpectedEof => {
-                                    let remains = data[cursor..].to_vec();
-                                    let _data = self.writer.get_stream_mut(1).take_cursor_data();
-                                    self.writer.write_all(&remains)?;
-                                    break;
-                                }
-                                _ => return Err(ioerr.into()),
-                            }
-                        }
+                        Err(ioerr) if ioerr.kind() == ErrorKind::UnexpectedEof => break,
+                        Err(ioerr) => return Err(ioerr.into()),
+                    }
+                }
+                self.bytes_written = self.writer.stream_position()? - self.data_offset;
+                self.writer.set_stream(1);
+                self.writer.write_all(&data[cursor..])?;
+                Ok(())
+            }
+
+            /// * Add `granule_offset` to the granule positions of the complete Ogg pages in the `Cursor`, and write the pages to the `Writer`.
+            /// * The incomplete page stays in the `Cursor` for the next time.
+            fn offset_granules(&mut self) -> Result<(), AudioWriteError> {
+                let mut cursor = 0usize;
+                let mut data = self.writer.get_stream_mut(1).take_cursor_data();
+                while let Some(page_length) = ogg_page_length(&data[cursor..]) {
+                    let page = &mut data[cursor..cursor + page_length];
+                    if &page[0..4] != b"OggS" {
+                        return Err(AudioWriteError::InvalidData("The Vorbis encoder wrote something that isn't an Ogg page.".to_string()));
+                    }
+                    // All bits set means no packet ends on the page, there's no granule position to offset.
+                    let granule_position = u64::from_le_bytes(page[6..14].try_into().unwrap());
+                    if granule_position != u64::MAX {
+                        page[6..14].copy_from_slice(&(granule_position + self.params.granule_offset).to_le_bytes());
+                        page[22..26].fill(0);
+                        let crc = ogg_crc32(page);
+                        page[22..26].copy_from_slice(&crc.to_le_bytes());
                     }
+                    cursor += page_length;
                 }
+                self.writer.set_stream(0);
+                self.writer.write_all(&data[..cursor])?;
+                self.bytes_written = self.writer.stream_position()? - self.data_offset;
+                self.writer.set_stream(1);
+                self.writer.write_all(&data[cursor..])?;
                 Ok(())
             }
 
@@ -2756,14 +4478,11 @@ pub mod oggvorbis_enc {
                     } => Err(AudioWriteError::InvalidArguments(
                         "Must call `begin_to_encode()` before encoding.".to_string(),
                     )),
-                    OggVorbisEncoderOrBuilder::Encoder(ref mut encoder) => {
-                        let frames = audioutils::interleaved_samples_to_monos(samples, channels)?;
-                        encoder.encode_audio_block(&frames)?;
-                        if self.params.mode == OggVorbisMode::NakedVorbis {
-                            self.peel_ogg()?;
+                    OggVorbisEncoderOrBuilder::Encoder(_) => {
+                        for chunk in samples.chunks(self.params.max_frames_per_write.max(1) * channels as usize) {
+                            let monos = audioutils::interleaved_samples_to_monos(chunk, channels)?;
+                            self.encode_block(&monos)?;
                         }
-                        self.bytes_written = self.writer.stream_position()? - self.data_offset;
-                        self.frames_written += frames[0].len() as u64;
                         Ok(())
                     }
                     OggVorbisEncoderOrBuilder::Finished => Err(AudioWriteError::AlreadyFinished(
@@ -2782,13 +4501,17 @@ pub mod oggvorbis_enc {
                     } => Err(AudioWriteError::InvalidArguments(
                         "Must call `begin_to_encode()` before encoding.".to_string(),
                     )),
-                    OggVorbisEncoderOrBuilder::Encoder(ref mut encoder) => {
-                        encoder.encode_audio_block(monos)?;
-                        if self.params.mode == OggVorbisMode::NakedVorbis {
-                            self.peel_ogg()?;
+                    OggVorbisEncoderOrBuilder::Encoder(_) => {
+                        let max_frames = self.params.max_frames_per_write.max(1);
+                        let length = monos.first().map_or(0, |mono| mono.len());
+                        if length <= max_frames || monos.iter().any(|mono| mono.len() != length) {
+                            return self.encode_block(monos);
+                        }
+                        for begin in (0..length).step_by(max_frames) {
+                            let end = (begin + max_frames).min(length);
+                            let chunk: Vec<&[f32]> = monos.iter().map(|mono| &mono[begin..end]).collect();
+                            self.encode_block(&chunk)?;
                         }
-                        self.bytes_written = self.writer.stream_position()? - self.data_offset;
-                        self.frames_written += monos.len() as u64;
                         Ok(())
                     }
                     OggVorbisEncoderOrBuilder::Finished => Err(AudioWriteError::AlreadyFinished(
@@ -2798,6 +4521,18 @@ pub mod oggvorbis_enc {
                 }
             }
 
+            /// * Encode a block of the waveforms, then move the pages out of the `Cursor` right away, so the `Cursor` only holds the pages of a block.
+            fn encode_block<S>(&mut self, monos: &[S]) -> Result<(), AudioWriteError>
+            where
+                S: AsRef<[f32]>,
+            {
+                if let OggVorbisEncoderOrBuilder::Encoder(ref mut encoder) = self.encoder {
+                    encoder.encode_audio_block(monos)?;
+                    self.frames_written += monos.first().map_or(0, |mono| mono.as_ref().len()) as u64;
+                }
+                self.flush_pages()
+            }
+
             /// Finish encoding audio.
             pub fn finish(&mut self) -> Result<(), AudioWriteError> {
                 match self.encoder {
@@ -2807,9 +4542,12 @@ pub mod oggvorbis_enc {
                     } => Err(AudioWriteError::InvalidArguments(
                         "Must call `begin_to_encode()` before encoding.".to_string(),
                     )),
-                    OggVorbisEncoderOrBuilder::Encoder(ref mut _encoder) => {
-                        self.encoder = OggVorbisEncoderOrBuilder::Finished;
-                        Ok(())
+                    OggVorbisEncoderOrBuilder::Encoder(_) => {
+                        // The encoder writes the last pages when it finishes.
+                        if let OggVorbisEncoderOrBuilder::Encoder(encoder) = std::mem::replace(&mut self.encoder, OggVorbisEncoderOrBuilder::Finished) {
+                            encoder.finish()?;
+                        }
+                        self.flush_pages()
                     }
                     OggVorbisEncoderOrBuilder::Finished => Ok(()),
                 }
@@ -2825,6 +4563,14 @@ pub mod oggvorbis_enc {
                 255
             }
 
+            fn get_max_frames_per_write(&self) -> Option<usize> {
+                Some(self.params.max_frames_per_write)
+            }
+
+            fn native_sample_type(&self) -> WaveSampleType {
+                WaveSampleType::F32
+            }
+
             fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
                 match self.params.mode {
                     OggVorbisMode::OriginalStreamCompatible => self.begin_to_encode(),
@@ -2837,6 +4583,15 @@ pub mod oggvorbis_enc {
                 }
             }
 
+            fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<bool, AudioWriteError> {
+                if self.params.mode == OggVorbisMode::HaveNoCodebookHeader {
+                    // The comment header is discarded with the setup header, and the regenerated header must be the same as the discarded one.
+                    return Ok(false);
+                }
+                self.insert_comment(key.to_owned(), value.to_owned())?;
+                Ok(true)
+            }
+
             fn get_bitrate(&self) -> u32 {
                 if self.frames_written != 0 {
                     (self.bytes_written * 8 * self.get_sample_rate() as u64 / self.frames_written)
@@ -2854,14 +4609,14 @@ pub mod oggvorbis_enc {
                         self.writer.set_stream(1);
                         self.begin_to_encode()?;
                         self.vorbis_header = self.writer.get_cur_stream_mut().take_cursor_data();
-                        self.writer.set_stream(0);
+                        self.writer.set_stream(if self.uses_cursor() { 1 } else { 0 });
                     }
                     OggVorbisMode::HaveNoCodebookHeader => {
                         self.writer.set_stream(1);
                         self.begin_to_encode()?;
                         // Discard the header. When to decode, the decoder generates the header by using an encoder.
                         let _header = self.writer.get_cur_stream_mut().take_cursor_data();
-                        self.writer.set_stream(0);
+                        self.writer.set_stream(if self.uses_cursor() { 1 } else { 0 });
                     }
                     OggVorbisMode::NakedVorbis => {
                         // Save the header to `fmt ` chunk
@@ -2910,7 +4665,12 @@ pub mod oggvorbis_enc {
                     byte_rate: self.params.get_bitrate() / 8,
                     block_align: 4,
                     bits_per_sample: 16,
-                    extension: Some(if self.vorbis_header.is_empty() {
+                    extension: Some(if self.params.mode == OggVorbisMode::HaveNoCodebookHeader {
+                        FmtExtension::new_oggvorbis(OggVorbisData {
+                            bitrate: self.params.bitrate,
+                            ..OggVorbisData::new()
+                        })
+                    } else if self.vorbis_header.is_empty() {
                         FmtExtension::new_oggvorbis(OggVorbisData::new())
                     } else if self.params.mode != OggVorbisMode::NakedVorbis {
                         FmtExtension::new_oggvorbis_with_header(OggVorbisWithHeaderData::new(&self.vorbis_header))
@@ -2942,19 +4702,27 @@ pub mod oggvorbis_enc {
             fn write_interleaved_samples_u64(&mut self, samples: &[u64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
             fn write_interleaved_samples_f32(&mut self, samples: &[f32]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
             fn write_interleaved_samples_f64(&mut self, samples: &[f64]) -> Result<(), AudioWriteError> {self.write_interleaved_samples(&sample_conv(samples))}
+        }
 
-            fn write_monos__i8(&mut self, monos_array: &[Vec<i8 >]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_i16(&mut self, monos_array: &[Vec<i16>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_i24(&mut self, monos_array: &[Vec<i24>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_i32(&mut self, monos_array: &[Vec<i32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_i64(&mut self, monos_array: &[Vec<i64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos__u8(&mut self, monos_array: &[Vec<u8 >]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_u16(&mut self, monos_array: &[Vec<u16>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_u24(&mut self, monos_array: &[Vec<u24>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_u32(&mut self, monos_array: &[Vec<u32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_u64(&mut self, monos_array: &[Vec<u64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_f32(&mut self, monos_array: &[Vec<f32>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
-            fn write_monos_f64(&mut self, monos_array: &[Vec<f64>]) -> Result<(), AudioWriteError> {self.write_monos(&sample_conv_batch(monos_array))}
+        /// * The serial number for the Ogg stream when the params don't have one, the Ogg specification wants it to be random.
+        fn random_stream_serial() -> i32 {
+            use std::hash::BuildHasher;
+            std::collections::hash_map::RandomState::new().hash_one(std::time::SystemTime::now()) as i32
+        }
+
+        // The length of the Ogg page at the beginning of `data`, `None` if the page is incomplete.
+        fn ogg_page_length(data: &[u8]) -> Option<usize> {
+            let header_length = 27 + *data.get(26)? as usize;
+            let lacing_values = data.get(27..header_length)?;
+            let page_length = header_length + lacing_values.iter().map(|&length| length as usize).sum::<usize>();
+            (data.len() >= page_length).then_some(page_length)
+        }
+
+        // The CRC-32 of the Ogg page, polynomial `0x04C11DB7` without the reflection, computed with the CRC field zeroed.
+        fn ogg_crc32(data: &[u8]) -> u32 {
+            data.iter().fold(0u32, |crc, byte| {
+                (0..8).fold(crc ^ ((*byte as u32) << 24), |crc, _| if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 })
+            })
         }
 
         impl From<vorbis_rs::VorbisError> for AudioWriteError {
@@ -4,8 +4,11 @@ use std::{
     error,
     fmt::{Display, Formatter},
     io::{self, ErrorKind},
+    time::Duration,
 };
 
+use crate::wavcore::GUID;
+
 /// * The error info from `std::io::Error` but this must contains the message
 #[derive(Debug, Clone)]
 pub struct IOErrorInfo {
@@ -19,8 +22,147 @@ impl IOErrorInfo {
     }
 }
 
+/// * Which limit of the decode budget was exceeded, see `ReadOptions`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeBudgetLimit {
+    /// * Decoded more frames than `max_decoded_frames`
+    Frames(u64),
+
+    /// * Decoding took longer than `max_decode_wall_time`
+    WallTime(Duration),
+}
+
+impl Display for DecodeBudgetLimit {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Frames(frames) => write!(f, "decoded more than {frames} frames"),
+            Self::WallTime(duration) => write!(f, "decoding took longer than {:?}", duration),
+        }
+    }
+}
+
+/// * What a `DecodeWarning` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DecodeWarningKind {
+    /// * A corrupted packet was skipped, or the stream was truncated at it, see `DecodeErrorPolicy`
+    CorruptedPacket,
+
+    /// * The size of the RIFF chunk or a chunk in it disagrees with the file size
+    SizeMismatch,
+
+    /// * The layout of the chunks was worked around, e.g. an odd-sized chunk without the pad byte
+    ChunkLayout,
+
+    /// * Some fields of the `fmt ` chunk are missing or inconsistent, the standard values are used
+    FmtChunk,
+
+    /// * The `fmt ` chunk can't be used, the audio is read by the spec of `ReadOptions::assume_spec`
+    AssumedSpec,
+}
+
+/// * The warning recorded by a decoder when it recovered from a corrupted packet, see `DecodeErrorPolicy`
+/// * The `WaveReader` records them too for the problems of the file structure that it worked around, see `WaveReader::warnings()`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DecodeWarning {
+    pub kind: DecodeWarningKind,
+
+    /// * The byte offset of the corrupted packet in the data the codec decodes
+    pub offset: u64,
+
+    /// * The error message from the codec
+    pub message: String,
+}
+
+impl DecodeWarning {
+    pub fn new(kind: DecodeWarningKind, offset: u64, message: String) -> Self {
+        Self { kind, offset, message }
+    }
+}
+
+impl Display for DecodeWarning {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "at offset 0x{:x}: {}", self.offset, self.message)
+    }
+}
+
+/// * Where a parse error happened inside the file: the chunk being parsed, the absolute byte offset, and what was expected versus what was found.
+/// * Every field is optional, the parser fills in what it knows. Use `ChunkContext::new()` and the `with_*()` methods to build one.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ChunkContext {
+    /// * The FourCC of the chunk being parsed
+    pub flag: Option<[u8; 4]>,
+
+    /// * The absolute byte offset in the file, of the chunk or of the field that went wrong
+    pub offset: Option<u64>,
+
+    /// * The value that was expected
+    pub expected: Option<String>,
+
+    /// * The value that was found instead
+    pub found: Option<String>,
+}
+
+impl ChunkContext {
+    pub fn new(flag: Option<[u8; 4]>, offset: Option<u64>) -> Self {
+        Self {
+            flag,
+            offset,
+            expected: None,
+            found: None,
+        }
+    }
+
+    pub fn with_flag(self, flag: &[u8; 4]) -> Self {
+        Self { flag: Some(*flag), ..self }
+    }
+
+    pub fn with_offset(self, offset: u64) -> Self {
+        Self { offset: Some(offset), ..self }
+    }
+
+    pub fn with_expected_found(self, expected: &str, found: &str) -> Self {
+        Self {
+            expected: Some(expected.to_string()),
+            found: Some(found.to_string()),
+            ..self
+        }
+    }
+
+    /// * Fill in the fields that this context doesn't know from the other context, the fields this context knows are kept.
+    pub fn merge(self, other: ChunkContext) -> Self {
+        Self {
+            flag: self.flag.or(other.flag),
+            offset: self.offset.or(other.offset),
+            expected: self.expected.or(other.expected),
+            found: self.found.or(other.found),
+        }
+    }
+}
+
+impl Display for ChunkContext {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match (&self.flag, &self.offset) {
+            (Some(flag), Some(offset)) => write!(f, "in the \"{}\" chunk at 0x{offset:x}", String::from_utf8_lossy(flag))?,
+            (Some(flag), None) => write!(f, "in the \"{}\" chunk", String::from_utf8_lossy(flag))?,
+            (None, Some(offset)) => write!(f, "at 0x{offset:x}")?,
+            (None, None) => (),
+        }
+        if let (Some(expected), Some(found)) = (&self.expected, &self.found) {
+            if self.flag.is_some() || self.offset.is_some() {
+                write!(f, ", ")?;
+            }
+            write!(f, "expected {expected}, found {found}")?;
+        }
+        Ok(())
+    }
+}
+
 /// The error info for reading an audio file
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum AudioReadError {
     IncompleteFile(u64),
     IncompleteData(String),
@@ -35,6 +177,30 @@ pub enum AudioReadError {
     UnexpectedFlag(String, String),
     StringDecodeError(Vec<u8>),
     OtherReason(String),
+
+    /// * The format of the audio data can't be decoded: the `format_tag` and the sub-format GUID if the `fmt ` chunk has one.
+    UnsupportedFormat(u16, Option<GUID>),
+
+    /// * The decode budget set by `ReadOptions` was exceeded, the audio data claims to be longer than allowed.
+    BudgetExceeded(DecodeBudgetLimit),
+
+    /// * The scan was stopped by its `CancelToken`.
+    Cancelled,
+
+    /// * The error with the context of where it happened: the path of the file and the position of the chunk being parsed.
+    /// * The path is `None` if the WAV wasn't read from a file path, e.g. from a reader or a stream.
+    WithContext {
+        path: Option<String>,
+        offset: Option<u64>,
+        source: Box<AudioReadError>,
+    },
+
+    /// * The error with the context of the chunk it happened in: the FourCC, the absolute byte offset, and the expected and found values if known.
+    #[non_exhaustive]
+    InChunk {
+        context: ChunkContext,
+        source: Box<AudioReadError>,
+    },
 }
 
 impl AudioReadError {
@@ -42,9 +208,154 @@ impl AudioReadError {
     pub fn UnexpectedEof(message: String) -> Self {
         Self::IOError(IOErrorInfo::new(ErrorKind::UnexpectedEof, message))
     }
+
+    /// * Attach the path of the file and the chunk offset to the error, so the error can tell where it came from after it escaped far away from the call site.
+    /// * If the error already has a context, the context is replaced.
+    /// * The chunk context attached by `in_chunk()` is kept.
+    pub fn with_context(self, path: Option<&str>, offset: Option<u64>) -> Self {
+        Self::WithContext {
+            path: path.map(str::to_string),
+            offset,
+            source: Box::new(self.without_path()),
+        }
+    }
+
+    /// * Attach the chunk context to the error. If the error already has a chunk context, the fields it doesn't know are filled in from the given one.
+    pub fn in_chunk(self, context: ChunkContext) -> Self {
+        match self {
+            Self::WithContext { path, offset, source } => Self::WithContext {
+                path,
+                offset,
+                source: Box::new(source.in_chunk(context)),
+            },
+            Self::InChunk { context: inner, source } => Self::InChunk {
+                context: inner.merge(context),
+                source,
+            },
+            other => Self::InChunk {
+                context,
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// * Attach the expected and found values to the error, the parser of the chunk fills in the FourCC and the offset later.
+    pub fn expected_found(self, expected: &str, found: &str) -> Self {
+        self.in_chunk(ChunkContext::default().with_expected_found(expected, found))
+    }
+
+    fn without_path(self) -> Self {
+        match self {
+            Self::WithContext { source, .. } => source.without_path(),
+            other => other,
+        }
+    }
+
+    /// * Get rid of the context, retrieve the original error.
+    pub fn without_context(self) -> Self {
+        match self {
+            Self::WithContext { source, .. } => source.without_context(),
+            Self::InChunk { source, .. } => source.without_context(),
+            other => other,
+        }
+    }
+
+    /// * Get a reference to the original error.
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::WithContext { source, .. } => source.root(),
+            Self::InChunk { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// * The chunk context of the error, if the context was attached.
+    pub fn chunk_context(&self) -> Option<&ChunkContext> {
+        match self {
+            Self::WithContext { source, .. } => source.chunk_context(),
+            Self::InChunk { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// * The FourCC of the chunk that caused the error, if known.
+    pub fn chunk_flag(&self) -> Option<[u8; 4]> {
+        self.chunk_context().and_then(|context| context.flag)
+    }
+
+    /// * The expected and found values, if known.
+    pub fn expected_and_found(&self) -> Option<(&str, &str)> {
+        if let Some(ChunkContext { expected: Some(expected), found: Some(found), .. }) = self.chunk_context() {
+            return Some((expected, found));
+        }
+        match self.root() {
+            Self::UnexpectedFlag(expected, found) => Some((expected, found)),
+            _ => None,
+        }
+    }
+
+    /// * The path of the file that caused the error, if the context was attached and the WAV was read from a file path.
+    pub fn get_path(&self) -> Option<&str> {
+        match self {
+            Self::WithContext { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// * The position of the chunk that caused the error, if known.
+    /// * If only the chunk context knows it, this is the absolute byte offset of the chunk context, see `chunk_context()`.
+    pub fn get_offset(&self) -> Option<u64> {
+        match self {
+            Self::WithContext { offset: Some(offset), .. } => Some(*offset),
+            Self::WithContext { source, .. } => source.get_offset(),
+            Self::InChunk { context, source } => context.offset.or_else(|| source.get_offset()),
+            Self::IncompleteFile(offset) => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// * The `ErrorKind` of the IO error, returns `None` if it's not an IO error.
+    pub fn io_error_kind(&self) -> Option<ErrorKind> {
+        match self.root() {
+            Self::IOError(ioerr) => Some(ioerr.kind),
+            _ => None,
+        }
+    }
+
+    /// * Is the error caused by the IO operations, e.g. permission denied, file not found, etc. These errors are worth a retry.
+    pub fn is_io_error(&self) -> bool {
+        self.io_error_kind().is_some()
+    }
+
+    /// * Is the error caused by the content of the file, e.g. the file is truncated or structurally broken. Retrying won't help.
+    pub fn is_parse_error(&self) -> bool {
+        !self.is_io_error()
+    }
+
+    /// * While parsing a file, hitting the end of the file means the file is truncated, that's a parse error, not an IO error.
+    pub(crate) fn eof_as_incomplete_data(self) -> Self {
+        match self {
+            Self::IOError(ioerr) if ioerr.kind == ErrorKind::UnexpectedEof => {
+                Self::IncompleteData(format!("The file is truncated: {}", ioerr.message))
+            }
+            Self::InChunk { context, source } => Self::InChunk {
+                context,
+                source: Box::new(source.eof_as_incomplete_data()),
+            },
+            other => other,
+        }
+    }
 }
 
-impl error::Error for AudioReadError {}
+impl error::Error for AudioReadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::WithContext { source, .. } => Some(source.as_ref()),
+            Self::InChunk { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl Display for AudioReadError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
@@ -66,6 +377,19 @@ impl Display for AudioReadError {
             Self::UnexpectedFlag(expected, got) => write!(f, "Expect \"{expected}\", got \"{got}\"."),
             Self::StringDecodeError(bytes) => write!(f, "String decode error: {}", String::from_utf8_lossy(bytes)),
             Self::OtherReason(info) => write!(f, "Unknown error: {info}"),
+            Self::UnsupportedFormat(format_tag, sub_format) => match sub_format {
+                Some(guid) => write!(f, "Unsupported format: format_tag 0x{format_tag:04x}, sub-format {guid}"),
+                None => write!(f, "Unsupported format: format_tag 0x{format_tag:04x}"),
+            },
+            Self::BudgetExceeded(limit) => write!(f, "Decode budget exceeded: {limit}"),
+            Self::Cancelled => write!(f, "The operation was cancelled."),
+            Self::WithContext { path, offset, source } => match (path, offset) {
+                (Some(path), Some(offset)) => write!(f, "\"{path}\" (at chunk 0x{offset:x}): {source}"),
+                (Some(path), None) => write!(f, "\"{path}\": {source}"),
+                (None, Some(offset)) => write!(f, "At chunk 0x{offset:x}: {source}"),
+                (None, None) => write!(f, "{source}"),
+            },
+            Self::InChunk { context, source } => write!(f, "{source} ({context})"),
         }
     }
 }
@@ -81,8 +405,8 @@ impl From<io::Error> for AudioReadError {
 
 impl From<AudioReadError> for io::Error {
     fn from(err: AudioReadError) -> Self {
-        match err {
-            AudioReadError::IOError(ioerr) => io::Error::from(ioerr.kind),
+        match err.without_context() {
+            AudioReadError::IOError(ioerr) => io::Error::new(ioerr.kind, ioerr.message),
             other => panic!(
                 "When converting `AudioReadError` to `io::Error`, the given error is unrelated: {:?}",
                 other
@@ -111,6 +435,9 @@ pub enum AudioWriteError {
     TruncatedSamples,
     MissingData(String),
     OtherReason(String),
+
+    /// * The transfer was stopped by its `CancelToken`, the `WaveWriter` was finalized with the frames written so far.
+    Cancelled,
 }
 
 impl error::Error for AudioWriteError {}
@@ -135,6 +462,7 @@ impl Display for AudioWriteError {
             Self::TruncatedSamples => write!(f, "The samples seem truncated because they can not form an audio frame"),
             Self::MissingData(data) => write!(f, "Missing data: \"{data}\""),
             Self::OtherReason(info) => write!(f, "Unknown error: {info}"),
+            Self::Cancelled => write!(f, "The operation was cancelled, the audio written so far was finalized."),
         }
     }
 }
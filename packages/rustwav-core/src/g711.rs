@@ -0,0 +1,99 @@
+use xlaw::XLaw;
+
+/// * The 16-bit linear sample of every A-law code, the same values as the ITU-T G.711 tables.
+pub(crate) static ALAW_TO_I16: [i16; 256] = build_alaw_table();
+
+/// * The 16-bit linear sample of every μ-law code, the same values as the ITU-T G.711 tables.
+pub(crate) static ULAW_TO_I16: [i16; 256] = build_ulaw_table();
+
+/// * The ends of the A-law segments, for the 13-bit magnitude.
+const ALAW_SEGMENT_ENDS: [i32; 8] = [0x1F, 0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF];
+
+/// * The ends of the μ-law segments, for the biased 14-bit magnitude.
+const ULAW_SEGMENT_ENDS: [i32; 8] = [0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF, 0x1FFF];
+
+/// * The bias added to the μ-law magnitude before finding the segment.
+const ULAW_BIAS: i32 = 0x84;
+
+const fn alaw_to_linear(code: u8) -> i16 {
+    let code = code ^ 0x55;
+    let segment = ((code & 0x70) >> 4) as i32;
+    let mut magnitude = ((code & 0x0F) as i32) << 4;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+    (if code & 0x80 != 0 { magnitude } else { -magnitude }) as i16
+}
+
+const fn ulaw_to_linear(code: u8) -> i16 {
+    let code = !code;
+    let magnitude = ((((code & 0x0F) as i32) << 3) + ULAW_BIAS) << ((code & 0x70) >> 4);
+    (if code & 0x80 != 0 { ULAW_BIAS - magnitude } else { magnitude - ULAW_BIAS }) as i16
+}
+
+const fn build_alaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut code = 0;
+    while code < 256 {
+        table[code] = alaw_to_linear(code as u8);
+        code += 1;
+    }
+    table
+}
+
+const fn build_ulaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut code = 0;
+    while code < 256 {
+        table[code] = ulaw_to_linear(code as u8);
+        code += 1;
+    }
+    table
+}
+
+fn find_segment(magnitude: i32, segment_ends: &[i32; 8]) -> usize {
+    segment_ends.iter().position(|end| magnitude <= *end).unwrap_or(8)
+}
+
+/// * Encode the 16-bit linear sample into an A-law code. The decoded values of the codes encode back to the same codes.
+pub(crate) fn linear_to_alaw(sample: i16) -> u8 {
+    let sample = sample as i32 >> 3;
+    let (mask, magnitude) = if sample >= 0 { (0xD5, sample) } else { (0x55, -sample - 1) };
+    let segment = find_segment(magnitude, &ALAW_SEGMENT_ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let mantissa = (if segment < 2 { magnitude >> 1 } else { magnitude >> segment }) & 0x0F;
+    (((segment as i32) << 4) | mantissa) as u8 ^ mask
+}
+
+/// * Encode the 16-bit linear sample into a μ-law code. The decoded values of the codes encode back to the same codes,
+///   except that the negative zero `0x7F` becomes the positive zero `0xFF`.
+pub(crate) fn linear_to_ulaw(sample: i16) -> u8 {
+    let sample = sample as i32 >> 2;
+    let (mask, magnitude) = if sample < 0 { (0x7F, -sample) } else { (0xFF, sample) };
+    let magnitude = magnitude.min(8159) + (ULAW_BIAS >> 2);
+    let segment = find_segment(magnitude, &ULAW_SEGMENT_ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    (((segment as i32) << 4) | ((magnitude >> (segment + 1)) & 0x0F)) as u8 ^ mask
+}
+
+/// * The decoding table of the law.
+pub(crate) fn decode_table(which_law: &XLaw) -> &'static [i16; 256] {
+    match which_law {
+        XLaw::ALaw => &ALAW_TO_I16,
+        XLaw::MuLaw => &ULAW_TO_I16,
+    }
+}
+
+/// * The encoding function of the law.
+pub(crate) fn encoder(which_law: &XLaw) -> fn(i16) -> u8 {
+    match which_law {
+        XLaw::ALaw => linear_to_alaw,
+        XLaw::MuLaw => linear_to_ulaw,
+    }
+}
@@ -0,0 +1,312 @@
+//! * The GSM 06.10 full-rate RPE-LTP decoder for the `WAVE_FORMAT_GSM610` blocks.
+//! * The arithmetic follows the fixed-point reference of the ETSI specification bit by bit, so the decoded samples are the same as the other implementations.
+
+/// * The size of a block in the `data` chunk, two GSM frames packed together.
+pub(crate) const BLOCK_SIZE: usize = 65;
+
+/// * The samples decoded from a block.
+pub(crate) const SAMPLES_PER_BLOCK: usize = 320;
+
+/// * The samples decoded from a GSM frame.
+const SAMPLES_PER_FRAME: usize = 160;
+
+/// * The bit widths of the 8 coded log area ratios.
+const LARC_BITS: [u32; 8] = [6, 6, 5, 5, 4, 4, 3, 3];
+
+/// * The `B`, `MIC` and `INVA` constants to decode each log area ratio, the table 5.2 of the specification.
+const LAR_DECODING: [(i16, i16, i16); 8] = [
+    (0, -32, 13107),
+    (0, -32, 13107),
+    (2048, -16, 13107),
+    (-2560, -16, 13107),
+    (94, -8, 19223),
+    (-1792, -8, 17476),
+    (-341, -4, 31454),
+    (-1144, -4, 29708),
+];
+
+/// * The quantized long term prediction gains.
+const QLB: [i16; 4] = [3277, 11469, 21299, 32767];
+
+/// * The normalized mantissas of the RPE inverse quantization.
+const FAC: [i16; 8] = [18431, 20479, 22527, 24575, 26623, 28671, 30719, 32767];
+
+/// * The parameters of a 40-sample sub-frame.
+#[derive(Debug, Clone, Copy, Default)]
+struct SubFrameParams {
+    /// * The long term prediction lag.
+    nc: i16,
+
+    /// * The long term prediction gain.
+    bc: i16,
+
+    /// * The RPE grid position.
+    mc: i16,
+
+    /// * The block maximum of the RPE pulses.
+    xmaxc: i16,
+
+    /// * The RPE pulses.
+    xmc: [i16; 13],
+}
+
+/// * The parameters of a GSM frame.
+#[derive(Debug, Clone, Copy, Default)]
+struct FrameParams {
+    larc: [i16; 8],
+    sub_frames: [SubFrameParams; 4],
+}
+
+/// * Reads the bits of a block from the LSB of each byte, the two frames of a block are packed one after another without any padding.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    bit_pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, bit_pos: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> i16 {
+        let mut value = 0u16;
+        for i in 0..bits {
+            let byte = self.bytes[self.bit_pos >> 3];
+            value |= (((byte >> (self.bit_pos & 7)) & 1) as u16) << i;
+            self.bit_pos += 1;
+        }
+        value as i16
+    }
+
+    fn read_frame(&mut self) -> FrameParams {
+        let mut params = FrameParams::default();
+        for (larc, bits) in params.larc.iter_mut().zip(LARC_BITS) {
+            *larc = self.read(bits);
+        }
+        for sub_frame in params.sub_frames.iter_mut() {
+            sub_frame.nc = self.read(7);
+            sub_frame.bc = self.read(2);
+            sub_frame.mc = self.read(2);
+            sub_frame.xmaxc = self.read(6);
+            for xmc in sub_frame.xmc.iter_mut() {
+                *xmc = self.read(3);
+            }
+        }
+        params
+    }
+}
+
+fn add(a: i16, b: i16) -> i16 {
+    a.saturating_add(b)
+}
+
+fn sub(a: i16, b: i16) -> i16 {
+    a.saturating_sub(b)
+}
+
+fn mult_r(a: i16, b: i16) -> i16 {
+    if a == i16::MIN && b == i16::MIN {
+        i16::MAX
+    } else {
+        ((a as i32 * b as i32 + 16384) >> 15) as i16
+    }
+}
+
+fn asr(a: i16, n: i16) -> i16 {
+    if n >= 16 {
+        -((a < 0) as i16)
+    } else if n <= -16 {
+        0
+    } else if n < 0 {
+        ((a as i32) << -n) as i16
+    } else {
+        a >> n
+    }
+}
+
+fn asl(a: i16, n: i16) -> i16 {
+    if n >= 16 {
+        0
+    } else if n <= -16 {
+        -((a < 0) as i16)
+    } else if n < 0 {
+        asr(a, -n)
+    } else {
+        ((a as i32) << n) as i16
+    }
+}
+
+/// * The GSM 06.10 decoder, it keeps the states between the frames.
+#[derive(Debug, Clone)]
+pub(crate) struct Gsm610Decoder {
+    /// * The reconstructed short term residual signal, the 120 past samples and the current 40 samples of the sub-frame.
+    dp0: [i16; 160],
+
+    /// * The decoded log area ratios of the current frame and the previous frame.
+    larpp: [[i16; 8]; 2],
+    j: usize,
+
+    /// * The last valid long term prediction lag.
+    nrp: i16,
+
+    /// * The states of the short term synthesis filter.
+    v: [i16; 9],
+
+    /// * The state of the de-emphasis filter.
+    msr: i16,
+}
+
+impl Default for Gsm610Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gsm610Decoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            dp0: [0; 160],
+            larpp: [[0; 8]; 2],
+            j: 0,
+            nrp: 40,
+            v: [0; 9],
+            msr: 0,
+        }
+    }
+
+    /// * Forget the history, called before seeking to another block.
+    pub(crate) fn reset_states(&mut self) {
+        *self = Self::new();
+    }
+
+    /// * Decode a block of `BLOCK_SIZE` bytes to `SAMPLES_PER_BLOCK` samples.
+    pub(crate) fn decode_block(&mut self, block: &[u8], output: &mut [i16]) {
+        assert_eq!(block.len(), BLOCK_SIZE);
+        assert_eq!(output.len(), SAMPLES_PER_BLOCK);
+        let mut bits = BitReader::new(block);
+        let (first, second) = output.split_at_mut(SAMPLES_PER_FRAME);
+        let params = bits.read_frame();
+        self.decode_frame(&params, first);
+        let params = bits.read_frame();
+        self.decode_frame(&params, second);
+    }
+
+    fn decode_frame(&mut self, params: &FrameParams, output: &mut [i16]) {
+        let mut wt = [0i16; SAMPLES_PER_FRAME];
+        for (sub_frame, wt) in params.sub_frames.iter().zip(wt.chunks_mut(40)) {
+            let erp = Self::rpe_decoding(sub_frame.xmaxc, sub_frame.mc, &sub_frame.xmc);
+            self.long_term_synthesis(sub_frame.nc, sub_frame.bc, &erp);
+            wt.copy_from_slice(&self.dp0[120..160]);
+        }
+        self.short_term_synthesis(&params.larc, &wt, output);
+        self.postprocessing(output);
+    }
+
+    /// * The APCM inverse quantization and the RPE grid positioning, get the reconstructed long term residual of a sub-frame.
+    fn rpe_decoding(xmaxc: i16, mc: i16, xmc: &[i16; 13]) -> [i16; 40] {
+        // Compute the exponent and the mantissa of the decoded block maximum.
+        let mut exp = if xmaxc > 15 { (xmaxc >> 3) - 1 } else { 0 };
+        let mut mant = xmaxc - (exp << 3);
+        if mant == 0 {
+            exp = -4;
+            mant = 7;
+        } else {
+            while mant <= 7 {
+                mant = (mant << 1) | 1;
+                exp -= 1;
+            }
+            mant -= 8;
+        }
+
+        let temp1 = FAC[mant as usize];
+        let temp2 = sub(6, exp);
+        let temp3 = asl(1, sub(temp2, 1));
+        let mut erp = [0i16; 40];
+        for (i, &xmc) in xmc.iter().enumerate() {
+            // Restore the sign and scale it to the 16-bit signed value.
+            let temp = ((xmc << 1) - 7) << 12;
+            let temp = add(mult_r(temp1, temp), temp3);
+            erp[mc as usize + 3 * i] = asr(temp, temp2);
+        }
+        erp
+    }
+
+    fn long_term_synthesis(&mut self, ncr: i16, bcr: i16, erp: &[i16; 40]) {
+        // The lag out of the range means the frame was lost, the last valid lag is used.
+        let nr = if (40..=120).contains(&ncr) { ncr } else { self.nrp };
+        self.nrp = nr;
+        let brp = QLB[bcr as usize];
+        for (k, &erp) in erp.iter().enumerate() {
+            let drpp = mult_r(brp, self.dp0[120 + k - nr as usize]);
+            self.dp0[120 + k] = add(erp, drpp);
+        }
+        // The history is kept, the last sub-frame is still at `dp0[120..160]` for the caller.
+        self.dp0.copy_within(40..160, 0);
+    }
+
+    fn decode_lars(larc: &[i16; 8]) -> [i16; 8] {
+        let mut larpp = [0i16; 8];
+        for ((larpp, &larc), &(b, mic, inva)) in larpp.iter_mut().zip(larc).zip(&LAR_DECODING) {
+            let temp1 = ((add(larc, mic) as i32) << 10) as i16;
+            let temp1 = sub(temp1, b << 1);
+            let temp1 = mult_r(inva, temp1);
+            *larpp = add(temp1, temp1);
+        }
+        larpp
+    }
+
+    /// * Convert the interpolated log area ratios to the reflection coefficients.
+    fn larp_to_rp(larp: &mut [i16; 8]) {
+        for larp in larp.iter_mut() {
+            let temp = if *larp == i16::MIN { i16::MAX } else { larp.abs() };
+            let rp = if temp < 11059 {
+                temp << 1
+            } else if temp < 20070 {
+                temp + 11059
+            } else {
+                add(temp >> 2, 26112)
+            };
+            *larp = if *larp < 0 { -rp } else { rp };
+        }
+    }
+
+    fn short_term_synthesis(&mut self, larc: &[i16; 8], wt: &[i16; SAMPLES_PER_FRAME], output: &mut [i16]) {
+        let larpp_j = Self::decode_lars(larc);
+        let larpp_j_1 = self.larpp[self.j];
+        self.j ^= 1;
+        self.larpp[self.j] = larpp_j;
+
+        // The coefficients are interpolated between the previous frame and the current frame at the beginning of the frame.
+        let interpolate = |f: fn(i16, i16) -> i16| -> [i16; 8] {
+            let mut larp: [i16; 8] = std::array::from_fn(|i| f(larpp_j_1[i], larpp_j[i]));
+            Self::larp_to_rp(&mut larp);
+            larp
+        };
+        let segments: [([i16; 8], std::ops::Range<usize>); 4] = [
+            (interpolate(|p, c| add(add(p >> 2, c >> 2), p >> 1)), 0..13),
+            (interpolate(|p, c| add(p >> 1, c >> 1)), 13..27),
+            (interpolate(|p, c| add(add(p >> 2, c >> 2), c >> 1)), 27..40),
+            (interpolate(|_, c| c), 40..160),
+        ];
+        for (rrp, range) in segments {
+            for k in range {
+                let mut sri = wt[k];
+                for (i, &rp) in rrp.iter().enumerate().rev() {
+                    sri = sub(sri, mult_r(rp, self.v[i]));
+                    self.v[i + 1] = add(self.v[i], mult_r(rp, sri));
+                }
+                self.v[0] = sri;
+                output[k] = sri;
+            }
+        }
+    }
+
+    /// * The de-emphasis filter, the truncation and the upscaling.
+    fn postprocessing(&mut self, output: &mut [i16]) {
+        for sample in output.iter_mut() {
+            let tmp = mult_r(self.msr, 28180);
+            self.msr = add(*sample, tmp);
+            *sample = add(self.msr, self.msr) & !7;
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::f64::consts::PI;
+
+use crate::{CancelToken, WaveReader};
+use crate::errors::AudioReadError;
+
+/// * How many frames to decode at a time.
+const SCAN_BATCH_FRAMES: usize = 4096;
+
+/// * The gating block of the integrated loudness is 400 ms, it's made of four 100 ms sub-blocks, the blocks overlap by 75%.
+const SUB_BLOCKS_PER_BLOCK: usize = 4;
+
+/// * The blocks quieter than this are not counted in the integrated loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// * The blocks quieter than the ungated loudness minus this are not counted in the integrated loudness.
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// * The level statistics of the audio, see `WaveReader::scan_levels()`.
+/// * The levels are relative to the full scale of the decoded `f64` samples, so 0 dBFS is 1.0. The silent channel is `f64::NEG_INFINITY` dBFS.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelStats {
+    /// * How many frames were scanned.
+    pub num_frames: u64,
+
+    /// * The absolute peak of each channel, from 0.0 to 1.0.
+    pub peak: Vec<f64>,
+
+    /// * The peak of each channel in dBFS.
+    pub peak_dbfs: Vec<f64>,
+
+    /// * The RMS of each channel, from 0.0 to 1.0.
+    pub rms: Vec<f64>,
+
+    /// * The RMS of each channel in dBFS.
+    pub rms_dbfs: Vec<f64>,
+
+    /// * The frame index of the absolute peak over all the channels, the first one if there are more.
+    /// * It's counted from the beginning of the audio, the same as `WaveReader::seek_to_frame()`.
+    pub peak_frame: u64,
+
+    /// * The channel of the absolute peak.
+    pub peak_channel: u16,
+
+    /// * The integrated loudness in LUFS, measured the way of EBU R128: K-weighted, 400 ms blocks gated at -70 LUFS and 10 LU below the ungated loudness.
+    /// * The LFE channel of the 5.1 audio is excluded, the surround channels are weighted by +1.5 dB.
+    /// * `None` if the audio is shorter than a block or every block is below the absolute gate.
+    pub integrated_loudness: Option<f64>,
+}
+
+/// * Convert the linear level into dBFS.
+pub fn to_dbfs(level: f64) -> f64 {
+    if level > 0.0 {
+        20.0 * level.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+/// * A biquad filter of the K-weighting, in the direct form I.
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[1] * self.y[0] - self.a[2] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// * Measures the integrated loudness, only the mean square of each 100 ms sub-block is kept.
+#[derive(Debug, Clone)]
+struct LoudnessMeter {
+    filters: Vec<(Biquad, Biquad)>,
+    weights: Vec<f64>,
+    sub_block_frames: usize,
+    sub_block_pos: usize,
+    sub_block_energy: f64,
+    sub_blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    fn new(channels: u16, sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        // The high shelf of the head, see ITU-R BS.1770.
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        // The RLB high pass.
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        let weights = (0..channels).map(|channel| match (channels, channel) {
+            (6, 3) => 0.0,
+            (6, 4) | (6, 5) => 1.41,
+            _ => 1.0,
+        }).collect();
+        Self {
+            filters: vec![(shelf, highpass); channels as usize],
+            weights,
+            sub_block_frames: (sample_rate as usize / 10).max(1),
+            sub_block_pos: 0,
+            sub_block_energy: 0.0,
+            sub_blocks: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, frame: &[f64]) {
+        for ((sample, (shelf, highpass)), weight) in frame.iter().zip(self.filters.iter_mut()).zip(self.weights.iter()) {
+            let filtered = highpass.process(shelf.process(*sample));
+            self.sub_block_energy += weight * filtered * filtered;
+        }
+        self.sub_block_pos += 1;
+        if self.sub_block_pos == self.sub_block_frames {
+            self.sub_blocks.push(self.sub_block_energy / self.sub_block_frames as f64);
+            self.sub_block_pos = 0;
+            self.sub_block_energy = 0.0;
+        }
+    }
+
+    fn energy_to_lufs(energy: f64) -> f64 {
+        -0.691 + 10.0 * energy.log10()
+    }
+
+    fn integrated_loudness(&self) -> Option<f64> {
+        let blocks: Vec<f64> = self.sub_blocks
+            .windows(SUB_BLOCKS_PER_BLOCK)
+            .map(|sub_blocks| sub_blocks.iter().sum::<f64>() / SUB_BLOCKS_PER_BLOCK as f64)
+            .filter(|energy| Self::energy_to_lufs(*energy) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        let relative_gate = Self::energy_to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) - RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks.into_iter().filter(|energy| Self::energy_to_lufs(*energy) > relative_gate).collect();
+        if gated.is_empty() {
+            return None;
+        }
+        Some(Self::energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64))
+    }
+}
+
+/// * Decode the audio from the start frame of the reader to the end and measure the levels, see `WaveReader::scan_levels()`.
+/// * If the `cancel` token is cancelled, it stops before the next batch with `AudioReadError::Cancelled`.
+pub(crate) fn scan_levels(reader: &mut WaveReader, cancel: Option<&CancelToken>) -> Result<LevelStats, AudioReadError> {
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    let start_frame = reader.get_start_frame();
+    let mut meter = LoudnessMeter::new(spec.channels, spec.sample_rate);
+    let mut peak = vec![0.0f64; channels];
+    let mut sum_squares = vec![0.0f64; channels];
+    let mut stats = LevelStats {
+        peak_frame: start_frame,
+        ..Default::default()
+    };
+    let mut max_peak = 0.0f64;
+    let mut iter = reader.frame_iter::<f64>()?;
+    loop {
+        if cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+            return Err(AudioReadError::Cancelled);
+        }
+        let frames = iter.decode_frames(SCAN_BATCH_FRAMES)?;
+        if frames.is_empty() {
+            break;
+        }
+        for frame in frames.iter() {
+            for (channel, sample) in frame.iter().enumerate() {
+                let level = sample.abs();
+                peak[channel] = peak[channel].max(level);
+                sum_squares[channel] += sample * sample;
+                if level > max_peak {
+                    max_peak = level;
+                    stats.peak_frame = start_frame + stats.num_frames;
+                    stats.peak_channel = channel as u16;
+                }
+            }
+            meter.process(frame);
+            stats.num_frames += 1;
+        }
+    }
+    let rms: Vec<f64> = sum_squares.iter().map(|sum| {
+        if stats.num_frames > 0 { (sum / stats.num_frames as f64).sqrt() } else { 0.0 }
+    }).collect();
+    stats.peak_dbfs = peak.iter().map(|level| to_dbfs(*level)).collect();
+    stats.rms_dbfs = rms.iter().map(|level| to_dbfs(*level)).collect();
+    stats.peak = peak;
+    stats.rms = rms;
+    stats.integrated_loudness = meter.integrated_loudness();
+    Ok(stats)
+}
@@ -2,6 +2,21 @@ mod wavcore;
 mod wavreader;
 mod wavwriter;
 mod adpcm;
+mod aiff;
+mod levels;
+mod g711;
+mod gsm610;
+mod recommend;
+mod validate;
+
+#[cfg(feature = "async")]
+mod asyncwav;
+
+#[cfg(feature = "serde")]
+mod serialize;
+
+#[cfg(feature = "mmap")]
+mod mmap;
 
 #[macro_use]
 mod hacks;
@@ -12,6 +27,9 @@ pub mod encoders;
 /// * The decoders for the `WaveReader`, each of these provides the same API for it to use. You can use it too.
 pub mod decoders;
 
+/// * The generators of the deterministic test signals: the sine, the sweep and the white noise, written through a `WaveWriter`.
+pub mod testsig;
+
 /// * The resampler
 #[doc(inline)]
 pub use resampler;
@@ -20,8 +38,27 @@ pub use resampler;
 pub use sampletypes::{i24, u24};
 
 pub use sampletypes::{SampleFrom, SampleType};
-pub use wavreader::{WaveDataSource, WaveReader};
-pub use wavwriter::WaveWriter;
+pub use wavreader::{BitrateMode, ChunkAction, FileDescription, StorageInfo, WaveDataSource, WaveReader};
+pub use wavwriter::{ContainerKind, FinalizeReport, WaveWriter, WaveWriterBuilder, WriteStats, WriterPosition};
+pub use encoders::ClipStats;
+pub use levels::{to_dbfs, LevelStats};
+pub use recommend::{analyze_source, recommend_format, Recommendation, SourceAnalysis, UseCase};
+pub use validate::{ValidationIssue, ValidationIssueKind, ValidationReport, ValidationSeverity};
+
+#[cfg(feature = "fs")]
+pub use wavreader::SyncWaveReader;
+
+#[cfg(feature = "fs")]
+pub use wavwriter::{SplitWaveWriter, SyncWaveWriter};
+
+#[cfg(feature = "async")]
+pub use asyncwav::{AsyncWaveReader, AsyncWaveWriter};
+
+#[cfg(feature = "flac")]
+pub use encoders::flac_enc::FlacFileWriter;
+
+#[cfg(feature = "mmap")]
+pub use mmap::{PcmSample, PcmView, PcmWindows};
 
 /// * Errors returned from most of the function in this library.
 pub mod errors;
@@ -57,12 +94,12 @@ pub mod utils {
 
 /// * Iterators for `WaveReader` to decode audio samples.
 pub mod iterators {
-    pub use crate::wavreader::{FrameIntoIter, FrameIter, MonoIntoIter, MonoIter, StereoIntoIter, StereoIter};
+    pub use crate::wavreader::{BlockIter, FrameBlock, FrameIntoIter, FrameIter, LoopedFrameIter, MonoIntoIter, MonoIter, StereoIntoIter, StereoIter};
 }
 
 /// * WAV file format specs
 pub mod format_specs {
-    pub use crate::wavcore::{DataFormat, SampleFormat, Spec, WaveSampleType};
+    pub use crate::wavcore::{ChannelLayout, DataFormat, SampleFormat, Speaker, Spec, WaveSampleType, FORMATS};
 
     /// * All of the supported WAV format tags
     pub mod format_tags {
@@ -77,11 +114,20 @@ pub mod format_specs {
 
 /// * Encoder creation options
 pub mod options {
-    pub use crate::wavwriter::FileSizeOption;
+    pub use crate::wavwriter::{FileSizeOption, SplitPolicy};
+
+    #[doc(inline)]
+    pub use crate::wavreader::{DecodeErrorPolicy, LoopPolicy, PadMode, ReadOptions};
+
+    #[doc(inline)]
+    pub use crate::wavcore::{CuePositionPolicy, InfoTextPolicy};
 
     #[doc(inline)]
     pub use crate::wavcore::AdpcmSubFormat;
 
+    #[doc(inline)]
+    pub use crate::wavcore::pcm::{DitherMode, PcmEncoderOptions};
+
     #[doc(inline)]
     pub use crate::wavcore::flac::{FlacCompression, FlacEncoderParams};
 
@@ -98,25 +144,40 @@ pub mod options {
 /// * WAV chunks
 pub mod chunks {
     pub use crate::wavcore::{
+        ChunkInfo,
+        ChunkHeader,
         FmtChunk,
         SlntChunk,
         BextChunk,
+        AxmlChunk,
+        ChnaChunk,
+        ChnaAudioId,
         InstChunk,
         AcidChunk,
         TrknChunk,
         CueChunk,
+        CuePoint,
+        Cue64Chunk,
+        Cue64Entry,
         PlstChunk,
+        Plst,
         SmplChunk,
+        SmplSampleLoop,
         ListChunk,
         Id3,
         JunkChunk,
         FullInfoCuePoint,
+        CueBuilder,
         ListInfo,
+        InfoDict,
         AdtlChunk,
         LablChunk,
         NoteChunk,
         LtxtChunk,
         FileChunk,
+        Metadata,
+        MetadataSource,
+        METADATA_FIELDS,
     };
 
     /// * WAV `fmt ` chunk extension data
@@ -127,6 +188,8 @@ pub mod chunks {
             AdpcmMsData,
             AdpcmImaData,
             Mp3Data,
+            OpusData,
+            OpusHead,
             VorbisHeaderData,
             OggVorbisData,
             OggVorbisWithHeaderData,
@@ -135,11 +198,149 @@ pub mod chunks {
     }
 }
 
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
 use resampler::Resampler;
+use downmixer::{Downmixer, DownmixerParams};
+use errors::{AudioReadError, AudioWriteError};
+use wavcore::WaveSampleType;
 
 /// * Transfer audio from the decoder to the encoder with resampling.
 /// * This allows to transfer of audio from the decoder to a different sample rate encoder.
+/// * If the encoder has a different number of channels, the audio is converted, see `transfer_audio_with_gain()`.
+/// * If neither the sample rate nor the channels change, the samples go in the `native_sample_type()` of the encoder, e.g. the 24-bit PCM is copied bit-exact.
 pub fn transfer_audio_from_decoder_to_encoder(decoder: &mut WaveReader, encoder: &mut WaveWriter) {
+    let gains = vec![1.0; decoder.spec().channels as usize];
+    transfer_audio_with_gain(decoder, encoder, &gains).unwrap();
+}
+
+/// * Transfer audio from the decoder to the encoder with resampling, and normalize the peak to `target_dbfs`, e.g. `-1.0`.
+/// * It goes in two passes: `WaveReader::scan_levels()` finds the absolute peak of all the channels, then the audio is transferred by `transfer_audio_with_gain()`.
+/// * Every channel gets the same gain, so the balance between the channels stays. The silent audio is transferred as is.
+/// * Returns the gain applied in dB.
+/// * The target can't be above 0 dBFS, the integer sample formats can't hold it.
+/// * The streaming `WaveReader` can be decoded only once, then it can't be normalized. Use `transfer_audio_with_gain()` with a fixed gain instead.
+pub fn transfer_audio_normalized(decoder: &mut WaveReader, encoder: &mut WaveWriter, target_dbfs: f32) -> Result<f32, AudioWriteError> {
+    if target_dbfs.is_nan() || target_dbfs > 0.0 {
+        return Err(AudioWriteError::InvalidArguments(format!(
+            "The target peak must be at most 0 dBFS, got {target_dbfs} dBFS."
+        )));
+    }
+    if decoder.is_streaming() {
+        return Err(AudioWriteError::Unsupported(
+            "Normalizing needs to decode the audio twice, the streaming `WaveReader` can be decoded only once, use a fixed gain instead.".to_string()
+        ));
+    }
+    let stats = decoder.scan_levels().map_err(|e| AudioWriteError::OtherReason(format!("Failed to scan the audio levels: {e}")))?;
+    let peak = stats.peak.iter().copied().fold(0.0f64, f64::max);
+    let gain = if peak > 0.0 {
+        10f64.powf(target_dbfs as f64 / 20.0) / peak
+    } else {
+        1.0
+    };
+    let gains = vec![gain as f32; decoder.spec().channels as usize];
+    transfer_audio_with_gain(decoder, encoder, &gains)?;
+    Ok(to_dbfs(gain) as f32)
+}
+
+/// * Transfer audio from the decoder to the encoder with resampling, and multiply each channel by its gain factor.
+/// * The number of `gains` must match the number of channels of the decoder, e.g. `&[0.7079, 0.7079]` attenuates a stereo audio by 3 dB.
+/// * The gain is applied to the `f32` samples before resampling, the samples that go beyond `[-1.0, 1.0]` are clipped when the encoder converts them to its sample format.
+/// * If the encoder has fewer channels than the decoder, the channels are mixed down:
+///   * Stereo to mono: the average of the left and right channels.
+///   * More channels to mono or stereo: the `Downmixer` with the channel mask of the source, the LFE channel is mixed by the default `DownmixerParams`.
+///   * More channels to other numbers of channels is not supported.
+/// * If the encoder has more channels than the decoder, the channels are expanded:
+///   * Mono to more channels: the mono channel is copied to the first two channels (front left and front right), the other channels are silent.
+///   * Other numbers of channels: the source channels are copied to the first channels, the other channels are silent.
+pub fn transfer_audio_with_gain(decoder: &mut WaveReader, encoder: &mut WaveWriter, gains: &[f32]) -> Result<(), AudioWriteError> {
+    let options = TransferOptions {
+        gains: Some(gains.to_vec()),
+        ..Default::default()
+    };
+    transfer_audio_with_options(decoder, encoder, &options)
+}
+
+/// * The quality of the resampler used by the transfer functions, it's a trade-off between the quality and the speed.
+/// * The resampler processes the audio in blocks by FFT, the bigger block has a sharper cut at the Nyquist frequency, and fewer block edges to leak the energy,
+///   but it costs more time and memory for each block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    /// * A quarter of the default FFT size.
+    Fast,
+
+    /// * The FFT size is rounded up from the higher sample rate, about one second of audio for each block.
+    #[default]
+    Default,
+
+    /// * Four times the default FFT size, the best to downsample the audio that has a lot of high frequency content.
+    Best,
+}
+
+/// * The token to stop a long-running transfer or scan from another thread, e.g. the cancel button of a GUI.
+/// * The clones share the same flag, keep one and give the other to `TransferOptions::cancel` or `WaveReader::scan_levels_cancellable()`.
+/// * The flag is checked at each block boundary, the work stops at the next block after `cancel()` is called.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * Ask the operation that holds a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// * Whether `cancel()` was called on this token or any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// * Two tokens are equal if they're the clones of the same token.
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// * The options for `transfer_audio_with_options()`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TransferOptions {
+    /// * The resampler quality, it's only used when the sample rates are different.
+    pub quality: ResampleQuality,
+
+    /// * Override the FFT size of the resampler, then the `quality` is ignored. It must be an even number not less than 64.
+    pub fft_size: Option<usize>,
+
+    /// * The gain factor for each channel of the decoder, `None` for no gain. See `transfer_audio_with_gain()`.
+    pub gains: Option<Vec<f32>>,
+
+    /// * Stop the transfer when the token is cancelled. The encoder is finalized, so the output is still a valid WAV file with the frames written so far,
+    ///   then `AudioWriteError::Cancelled` is returned.
+    pub cancel: Option<CancelToken>,
+}
+
+impl TransferOptions {
+    /// * The FFT size to use for the resampler.
+    pub fn get_fft_size(&self, src_sample_rate: u32, dst_sample_rate: u32) -> usize {
+        if let Some(fft_size) = self.fft_size {
+            return fft_size;
+        }
+        let fft_size = Resampler::get_rounded_up_fft_size(std::cmp::max(src_sample_rate, dst_sample_rate));
+        match self.quality {
+            ResampleQuality::Fast => std::cmp::max(fft_size / 4, 64),
+            ResampleQuality::Default => fft_size,
+            ResampleQuality::Best => fft_size * 4,
+        }
+    }
+}
+
+/// * Transfer audio from the decoder to the encoder with the options for the resampler, the gains and the cancellation.
+/// * The channels are converted the same way as `transfer_audio_with_gain()`.
+pub fn transfer_audio_with_options(decoder: &mut WaveReader, encoder: &mut WaveWriter, options: &TransferOptions) -> Result<(), AudioWriteError> {
     // The decoding audio spec
     let decode_spec = decoder.spec();
 
@@ -148,72 +349,200 @@ pub fn transfer_audio_from_decoder_to_encoder(decoder: &mut WaveReader, encoder:
 
     let decode_channels = decode_spec.channels;
     let encode_channels = encode_spec.channels;
-    let decode_sample_rate = decode_spec.sample_rate;
-    let encode_sample_rate = encode_spec.sample_rate;
 
-    // Get the best FFT size for the resampler.
-    let fft_size = Resampler::get_rounded_up_fft_size(std::cmp::max(encode_sample_rate, decode_sample_rate));
+    if let Some(fft_size) = options.fft_size {
+        if fft_size < 64 || fft_size & 1 != 0 {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The FFT size of the resampler must be an even number not less than 64, got {fft_size}."
+            )));
+        }
+    }
+    let unity_gains = vec![1.0; decode_channels as usize];
+    let gains = options.gains.as_deref().unwrap_or(&unity_gains);
+    if gains.len() != decode_channels as usize {
+        return Err(AudioWriteError::InvalidArguments(format!(
+            "Got {} gains for {decode_channels} channels.",
+            gains.len()
+        )));
+    }
 
-    // This is the resampler, if the decoder's sample rate is different than the encode sample rate, use the resampler to help stretch or compress the waveform.
-    // Otherwise, it's not needed there.
-    let resampler = Resampler::new(fft_size);
+    // Let the encoder know how long the audio is, e.g. the FLAC encoder puts it into the `STREAMINFO`.
+    // It's only a hint, it can't be set if the encoder already got some samples, then the encoder goes without it.
+    if let Some(total_frames) = decoder.total_frames() {
+        let estimate = total_frames * encode_spec.sample_rate as u64 / decode_spec.sample_rate as u64;
+        let _ = encoder.set_total_frames_estimate(estimate);
+    }
 
-    // The number of channels must match
-    assert_eq!(encode_channels, decode_channels);
+    // Nothing to resample, mix or scale, let the encoder pick the sample type to avoid the conversions through `f32`.
+    let cancel = options.cancel.as_ref();
+    if decode_spec.sample_rate == encode_spec.sample_rate && decode_channels == encode_channels && gains.iter().all(|&gain| gain == 1.0) {
+        use WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64, Unknown};
+        return match encoder.native_sample_type() {
+            S8  => transfer_native::< i8>(decoder, encoder, cancel),
+            S16 => transfer_native::<i16>(decoder, encoder, cancel),
+            S24 => transfer_native::<i24>(decoder, encoder, cancel),
+            S32 => transfer_native::<i32>(decoder, encoder, cancel),
+            S64 => transfer_native::<i64>(decoder, encoder, cancel),
+            U8  => transfer_native::< u8>(decoder, encoder, cancel),
+            U16 => transfer_native::<u16>(decoder, encoder, cancel),
+            U24 => transfer_native::<u24>(decoder, encoder, cancel),
+            U32 => transfer_native::<u32>(decoder, encoder, cancel),
+            U64 => transfer_native::<u64>(decoder, encoder, cancel),
+            F32 | Unknown => transfer_native::<f32>(decoder, encoder, cancel),
+            F64 => transfer_native::<f64>(decoder, encoder, cancel),
+        };
+    }
 
-    // Process size is for the resampler to process the waveform, it is the length of the source waveform slice.
-    let process_size = resampler.get_process_size(fft_size, decode_sample_rate, encode_sample_rate);
+    let fft_size = options.get_fft_size(decode_spec.sample_rate, encode_spec.sample_rate);
+    let transfer = TransferResampler::new(fft_size, decode_spec.sample_rate, encode_spec.sample_rate, cancel.cloned());
+    let read_error = |e: AudioReadError| AudioWriteError::OtherReason(format!("Failed to decode the audio: {e}"));
+    let apply_gains = |frame: Vec<f32>| -> Vec<f32> { frame.iter().zip(gains).map(|(s, g)| s * g).collect() };
 
     // There are three types of iterators for three types of audio channels: mono, stereo, and more than 2 channels of audio.
     // Usually, the third iterator can handle all numbers of channels, but it's the slowest iterator.
-    match encode_channels {
-        1 => {
-            let mut iter = decoder.mono_iter::<f32>().unwrap();
-            loop {
-                let block: Vec<f32> = iter.by_ref().take(process_size).collect();
-                if block.is_empty() {
-                    break;
+    match (decode_channels, encode_channels) {
+        (1, 1) => {
+            let iter = decoder.mono_iter::<f32>().map_err(read_error)?;
+            transfer.monos(iter.map(|s| s * gains[0]), encoder)
+        }
+        (2, 2) => {
+            let iter = decoder.stereo_iter::<f32>().map_err(read_error)?;
+            transfer.stereos(iter.map(|(l, r)| (l * gains[0], r * gains[1])), encoder)
+        }
+        (d, e) if d == e => {
+            let iter = decoder.frame_iter::<f32>().map_err(read_error)?;
+            transfer.frames(iter.map(apply_gains), encoder)
+        }
+        (2, 1) => {
+            let iter = decoder.stereo_iter::<f32>().map_err(read_error)?;
+            transfer.monos(iter.map(|(l, r)| (l * gains[0] + r * gains[1]) * 0.5), encoder)
+        }
+        (_, 1) => {
+            let downmixer = Downmixer::new(decode_spec.effective_channel_mask(), DownmixerParams::new());
+            let iter = decoder.frame_iter::<f32>().map_err(read_error)?;
+            transfer.monos(iter.map(|frame| downmixer.downmix_frame_to_mono(&apply_gains(frame))), encoder)
+        }
+        (d, 2) if d > 2 => {
+            let downmixer = Downmixer::new(decode_spec.effective_channel_mask(), DownmixerParams::new());
+            let iter = decoder.frame_iter::<f32>().map_err(read_error)?;
+            transfer.stereos(iter.map(|frame| downmixer.downmix_frame_to_stereo(&apply_gains(frame))), encoder)
+        }
+        (1, 2) => {
+            let iter = decoder.mono_iter::<f32>().map_err(read_error)?;
+            transfer.stereos(iter.map(|s| (s * gains[0], s * gains[0])), encoder)
+        }
+        (d, e) if d < e => {
+            let iter = decoder.frame_iter::<f32>().map_err(read_error)?;
+            transfer.frames(iter.map(|frame| {
+                let mut frame = apply_gains(frame);
+                if d == 1 {
+                    frame.push(frame[0]);
                 }
-                let block = audioutils::do_resample_mono(
-                    &resampler,
-                    &block,
-                    decode_sample_rate,
-                    encode_sample_rate,
-                );
-                encoder.write_mono_channel(&block).unwrap();
+                frame.resize(e as usize, 0.0);
+                frame
+            }), encoder)
+        }
+        (d, e) => Err(AudioWriteError::WrongChannels(format!(
+            "Can't mix {d} channels down to {e} channels, only mixing down to mono or stereo is supported."
+        ))),
+    }
+}
+
+/// * Before each block of the transfer: if the token is cancelled, finalize the encoder with the frames written so far and stop with `AudioWriteError::Cancelled`.
+fn check_cancelled(cancel: Option<&CancelToken>, encoder: &mut WaveWriter) -> Result<(), AudioWriteError> {
+    match cancel {
+        Some(cancel) if cancel.is_cancelled() => {
+            encoder.finalize_in_place()?;
+            Err(AudioWriteError::Cancelled)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// * Transfer the audio as it is in the sample type `S`, the numbers of the channels must be the same.
+/// * The blocks are decoded by `BlockIter::next_block()` into the same buffer every time, nothing is allocated for each block.
+fn transfer_native<S>(decoder: &mut WaveReader, encoder: &mut WaveWriter, cancel: Option<&CancelToken>) -> Result<(), AudioWriteError>
+where
+    S: SampleType,
+{
+    const BLOCK_FRAMES: usize = 4096;
+    let read_error = |e: AudioReadError| AudioWriteError::OtherReason(format!("Failed to decode the audio: {e}"));
+    let mut blocks = decoder.block_iter::<S>(BLOCK_FRAMES, wavreader::PadMode::None).map_err(read_error)?;
+    loop {
+        check_cancelled(cancel, encoder)?;
+        let Some(block) = blocks.next_block().map_err(read_error)? else {
+            break Ok(());
+        };
+        encoder.write_interleaved_samples(&block.samples)?;
+    }
+}
+
+/// * Resamples the converted audio in blocks and sends them to the encoder.
+/// * The buffer of the source block is reused for every block, only the resampled block comes new from the resampler.
+struct TransferResampler {
+    resampler: Resampler,
+    process_size: usize,
+    src_sample_rate: u32,
+    dst_sample_rate: u32,
+    cancel: Option<CancelToken>,
+}
+
+impl TransferResampler {
+    fn new(fft_size: usize, src_sample_rate: u32, dst_sample_rate: u32, cancel: Option<CancelToken>) -> Self {
+        // This is the resampler, if the decoder's sample rate is different than the encode sample rate, use the resampler to help stretch or compress the waveform.
+        // Otherwise, it's not needed there.
+        let resampler = Resampler::new(fft_size);
+
+        // Process size is for the resampler to process the waveform, it is the length of the source waveform slice.
+        let process_size = resampler.get_process_size(fft_size, src_sample_rate, dst_sample_rate);
+        Self {
+            resampler,
+            process_size,
+            src_sample_rate,
+            dst_sample_rate,
+            cancel,
+        }
+    }
+
+    fn monos(&self, mut iter: impl Iterator<Item = f32>, encoder: &mut WaveWriter) -> Result<(), AudioWriteError> {
+        let mut block: Vec<f32> = Vec::with_capacity(self.process_size);
+        loop {
+            check_cancelled(self.cancel.as_ref(), encoder)?;
+            block.clear();
+            block.extend(iter.by_ref().take(self.process_size));
+            if block.is_empty() {
+                break Ok(());
             }
+            let resampled = audioutils::do_resample_mono(&self.resampler, &block, self.src_sample_rate, self.dst_sample_rate);
+            encoder.write_mono_channel(&resampled)?;
         }
-        2 => {
-            let mut iter = decoder.stereo_iter::<f32>().unwrap();
-            loop {
-                let block: Vec<(f32, f32)> = iter.by_ref().take(process_size).collect();
-                if block.is_empty() {
-                    break;
-                }
-                let block = audioutils::do_resample_stereo(
-                    &resampler,
-                    &block,
-                    decode_sample_rate,
-                    encode_sample_rate,
-                );
-                encoder.write_stereos(&block).unwrap();
+    }
+
+    fn stereos(&self, mut iter: impl Iterator<Item = (f32, f32)>, encoder: &mut WaveWriter) -> Result<(), AudioWriteError> {
+        let mut block: Vec<(f32, f32)> = Vec::with_capacity(self.process_size);
+        loop {
+            check_cancelled(self.cancel.as_ref(), encoder)?;
+            block.clear();
+            block.extend(iter.by_ref().take(self.process_size));
+            if block.is_empty() {
+                break Ok(());
             }
+            let resampled = audioutils::do_resample_stereo(&self.resampler, &block, self.src_sample_rate, self.dst_sample_rate);
+            encoder.write_stereos(&resampled)?;
         }
-        _ => {
-            let mut iter = decoder.frame_iter::<f32>().unwrap();
-            loop {
-                let block: Vec<Vec<f32>> = iter.by_ref().take(process_size).collect();
-                if block.is_empty() {
-                    break;
-                }
-                let block = audioutils::do_resample_frames(
-                    &resampler,
-                    &block,
-                    decode_sample_rate,
-                    encode_sample_rate,
-                );
-                encoder.write_frames(&block).unwrap();
+    }
+
+    fn frames(&self, mut iter: impl Iterator<Item = Vec<f32>>, encoder: &mut WaveWriter) -> Result<(), AudioWriteError> {
+        let mut block: Vec<Vec<f32>> = Vec::with_capacity(self.process_size);
+        loop {
+            check_cancelled(self.cancel.as_ref(), encoder)?;
+            block.clear();
+            block.extend(iter.by_ref().take(self.process_size));
+            if block.is_empty() {
+                break Ok(());
             }
+            let resampled = audioutils::do_resample_frames(&self.resampler, &block, self.src_sample_rate, self.dst_sample_rate);
+            encoder.write_frames(&resampled)?;
         }
     }
 }
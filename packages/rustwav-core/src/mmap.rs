@@ -0,0 +1,185 @@
+//! * The `mmap` feature: the `data` chunk of the PCM and IEEE float files mapped into the memory, see `WaveReader::map_pcm_data()`.
+//! * The samples are accessed as the slices of the mapped bytes directly, nothing is copied or decoded.
+
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+
+use memmap2::Mmap;
+
+use crate::errors::AudioReadError;
+use crate::wavcore::WaveSampleType;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// * The sample types that the mapped `data` chunk can be viewed as. Every bit pattern of these types is a valid value.
+pub trait PcmSample: private::Sealed + Copy {
+    /// * The sample type of the `data` chunk that this type matches.
+    const SAMPLE_TYPE: WaveSampleType;
+}
+
+macro_rules! impl_pcm_sample {
+    ($($t:ty => $sample_type:ident),+) => {
+        $(
+            impl private::Sealed for $t {}
+            impl PcmSample for $t {
+                const SAMPLE_TYPE: WaveSampleType = WaveSampleType::$sample_type;
+            }
+        )+
+    };
+}
+
+impl_pcm_sample!(u8 => U8, i16 => S16, i32 => S32, f32 => F32, f64 => F64);
+
+/// * The `data` chunk of a PCM or IEEE float file mapped into the memory, created by `WaveReader::map_pcm_data()`.
+/// * The samples are little-endian in the file, the typed slices are only available on the little-endian hosts.
+pub struct PcmView<'a> {
+    mmap: Mmap,
+    sample_type: WaveSampleType,
+    channels: u16,
+    num_frames: usize,
+    _reader: PhantomData<&'a ()>,
+}
+
+impl PcmView<'_> {
+    pub(crate) fn new(mmap: Mmap, sample_type: WaveSampleType, channels: u16, block_align: u16) -> Self {
+        // The partial frame at the end of a truncated `data` chunk isn't a part of the view.
+        let num_frames = mmap.len() / block_align as usize;
+        Self {
+            mmap,
+            sample_type,
+            channels,
+            num_frames,
+            _reader: PhantomData,
+        }
+    }
+
+    /// * The sample type of the `data` chunk, it decides which of the `as_*_slice()` works.
+    pub fn sample_type(&self) -> WaveSampleType {
+        self.sample_type
+    }
+
+    /// * The number of the interleaved channels.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// * How many whole audio frames are in the view.
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    /// * The raw bytes of the whole frames in the `data` chunk.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap[..self.num_frames * self.channels as usize * self.sample_type.sizeof() as usize]
+    }
+
+    /// * The interleaved samples as a slice of `T`.
+    /// * Fails if `T` doesn't match the sample type, if the host isn't little-endian, or if the `data` chunk isn't aligned for `T` in the file, e.g. the `f64` samples at an offset that isn't a multiple of 8.
+    pub fn as_slice<T: PcmSample>(&self) -> Result<&[T], AudioReadError> {
+        if T::SAMPLE_TYPE != self.sample_type {
+            return Err(AudioReadError::InvalidArguments(format!(
+                "the samples are {}, can't be viewed as {}",
+                self.sample_type,
+                T::SAMPLE_TYPE
+            )));
+        }
+        if size_of::<T>() > 1 && cfg!(target_endian = "big") {
+            return Err(AudioReadError::Unsupported(String::from(
+                "the samples are little-endian, they can't be viewed in place on a big-endian host",
+            )));
+        }
+        let bytes = self.as_bytes();
+        if bytes.as_ptr().align_offset(align_of::<T>()) != 0 {
+            return Err(AudioReadError::Unsupported(format!(
+                "the \"data\" chunk isn't aligned to {} bytes in the file for the {} samples",
+                align_of::<T>(),
+                self.sample_type
+            )));
+        }
+        // SAFETY: the pointer is aligned for `T`, the length is a multiple of `size_of::<T>()`, and every bit pattern is a valid `T`, see `PcmSample`.
+        Ok(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, bytes.len() / size_of::<T>()) })
+    }
+
+    /// * The unsigned 8-bit samples, see `as_slice()`.
+    pub fn as_u8_slice(&self) -> Result<&[u8], AudioReadError> {
+        self.as_slice::<u8>()
+    }
+
+    /// * The 16-bit samples, see `as_slice()`.
+    pub fn as_i16_slice(&self) -> Result<&[i16], AudioReadError> {
+        self.as_slice::<i16>()
+    }
+
+    /// * The 32-bit integer samples, see `as_slice()`.
+    pub fn as_i32_slice(&self) -> Result<&[i32], AudioReadError> {
+        self.as_slice::<i32>()
+    }
+
+    /// * The 32-bit float samples, see `as_slice()`.
+    pub fn as_f32_slice(&self) -> Result<&[f32], AudioReadError> {
+        self.as_slice::<f32>()
+    }
+
+    /// * The 64-bit float samples, see `as_slice()`.
+    pub fn as_f64_slice(&self) -> Result<&[f64], AudioReadError> {
+        self.as_slice::<f64>()
+    }
+
+    /// * Iterate through the windows of `window_frames` frames, each window is split into the channels: `window[channel][frame]`.
+    /// * The windows are deinterleaved one at a time while iterating, the last window may be shorter.
+    pub fn frames<T: PcmSample>(&self, window_frames: usize) -> Result<PcmWindows<'_, T>, AudioReadError> {
+        if window_frames == 0 {
+            return Err(AudioReadError::InvalidArguments(String::from("the window must have at least one frame")));
+        }
+        Ok(PcmWindows {
+            samples: self.as_slice::<T>()?,
+            channels: self.channels as usize,
+            window_frames,
+        })
+    }
+}
+
+impl std::fmt::Debug for PcmView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("PcmView")
+            .field("sample_type", &self.sample_type)
+            .field("channels", &self.channels)
+            .field("num_frames", &self.num_frames)
+            .finish_non_exhaustive()
+    }
+}
+
+/// * The iterator of the deinterleaved windows of a `PcmView`, see `PcmView::frames()`.
+#[derive(Debug, Clone)]
+pub struct PcmWindows<'a, T: PcmSample> {
+    samples: &'a [T],
+    channels: usize,
+    window_frames: usize,
+}
+
+impl<T: PcmSample> Iterator for PcmWindows<'_, T> {
+    type Item = Vec<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let length = self.samples.len().min(self.window_frames * self.channels);
+        let (window, rest) = self.samples.split_at(length);
+        self.samples = rest;
+        Some(
+            (0..self.channels)
+                .map(|channel| window.iter().skip(channel).step_by(self.channels).copied().collect())
+                .collect(),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let windows = (self.samples.len() / self.channels).div_ceil(self.window_frames);
+        (windows, Some(windows))
+    }
+}
+
+impl<T: PcmSample> ExactSizeIterator for PcmWindows<'_, T> {}
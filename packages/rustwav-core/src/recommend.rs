@@ -0,0 +1,314 @@
+use std::f64::consts::PI;
+
+use crate::WaveReader;
+use crate::errors::AudioReadError;
+use crate::format_specs::{DataFormat, SampleFormat, Spec};
+use crate::options::{
+    FlacCompression, FlacEncoderParams, Mp3Bitrate, Mp3Channels, Mp3EncoderOptions, OggVorbisBitrateStrategy,
+    OggVorbisEncoderParams, OggVorbisMode, OpusBitrate, OpusEncoderOptions, OpusEncoderSampleDuration,
+};
+
+/// * How many samples in a block to do the spectral analysis.
+const ANALYSIS_BLOCK_SIZE: usize = 512;
+
+/// * At most this number of seconds from the beginning of the source is analyzed.
+const MAX_ANALYSIS_SECONDS: u64 = 30;
+
+/// * At most this number of non-silent blocks go through the DFT, evenly picked from the analyzed audio.
+const MAX_SPECTRAL_BLOCKS: usize = 256;
+
+/// * The block which RMS is below -50 dBFS is silent.
+const SILENCE_RMS: f64 = 0.003_162;
+
+/// * The bandwidth is where this ratio of the spectral energy lies below.
+const BANDWIDTH_ENERGY_RATIO: f64 = 0.995;
+
+/// * The speech doesn't have much energy above this frequency.
+const SPEECH_MAX_BANDWIDTH: f64 = 8000.0;
+
+/// * The speech has pauses between the phrases.
+const SPEECH_MIN_SILENCE_RATIO: f64 = 0.1;
+
+/// * The speech changes its spectrum faster than the music does.
+const SPEECH_MIN_SPECTRAL_FLUX: f64 = 0.3;
+
+/// * What the exported audio is for, see `recommend_format()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UseCase {
+    /// * Keep everything of the source, lossless.
+    Archival,
+
+    /// * Good quality with a small bitrate.
+    Streaming,
+
+    /// * Speech only, the smallest bitrate that keeps the speech intelligible.
+    Voice,
+
+    /// * A small file that can be played everywhere.
+    Preview,
+}
+
+/// * What the cheap analysis found out about the source, see `analyze_source()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceAnalysis {
+    /// * The frequency in Hz where 99.5% of the spectral energy lies below.
+    pub bandwidth: f64,
+
+    /// * The ratio of the silent blocks in the analyzed audio, from 0.0 to 1.0.
+    pub silence_ratio: f64,
+
+    /// * The average spectral flux between the non-silent blocks, from 0.0 to 1.0. The speech changes its spectrum faster than the music.
+    pub spectral_flux: f64,
+
+    /// * Does the source look like the speech: narrowband, and has pauses or a fast-changing spectrum.
+    pub speech_like: bool,
+}
+
+/// * The recommended format and spec to create the `WaveWriter` with.
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+    /// * The format and the encoder options.
+    pub format: DataFormat,
+
+    /// * The spec for the encoder. The channels and the sample rate could differ from the source, the source needs to be downmixed or resampled.
+    pub spec: Spec,
+
+    /// * Why this format was chosen, one reason for each line.
+    pub rationale: Vec<String>,
+}
+
+/// * Decode the beginning of the source and measure its bandwidth, silence ratio, and spectral flux.
+/// * The channels are mixed into mono for the analysis, at most 30 seconds of the audio is decoded.
+pub fn analyze_source(reader: &mut WaveReader) -> Result<SourceAnalysis, AudioReadError> {
+    let sample_rate = reader.spec().sample_rate;
+    let max_frames = (sample_rate as u64 * MAX_ANALYSIS_SECONDS) as usize;
+    let monos = reader.mono_iter::<f32>()?.decode_monos(max_frames)?;
+
+    // Sort out the silent blocks, they don't go to the spectral analysis.
+    let blocks: Vec<&[f32]> = monos.chunks_exact(ANALYSIS_BLOCK_SIZE).collect();
+    let loud_blocks: Vec<&[f32]> = blocks
+        .iter()
+        .copied()
+        .filter(|block| {
+            let rms = (block.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>() / block.len() as f64).sqrt();
+            rms >= SILENCE_RMS
+        })
+        .collect();
+    let silence_ratio = if blocks.is_empty() {
+        0.0
+    } else {
+        1.0 - loud_blocks.len() as f64 / blocks.len() as f64
+    };
+
+    let dft = Dft::new(ANALYSIS_BLOCK_SIZE);
+    let step = loud_blocks.len().div_ceil(MAX_SPECTRAL_BLOCKS).max(1);
+    let mut total_power = vec![0.0; dft.num_bins()];
+    let mut flux_sum = 0.0;
+    let mut flux_count = 0usize;
+    let mut prev_spectrum: Option<Vec<f64>> = None;
+    for block in loud_blocks.iter().step_by(step) {
+        let power = dft.power_spectrum(block);
+        total_power.iter_mut().zip(power.iter()).for_each(|(t, p)| *t += p);
+
+        // The flux is measured on the normalized magnitude spectrum, so the loudness doesn't count.
+        let magnitude: Vec<f64> = power.iter().map(|p| p.sqrt()).collect();
+        let sum: f64 = magnitude.iter().sum();
+        if sum > 0.0 {
+            let spectrum: Vec<f64> = magnitude.iter().map(|m| m / sum).collect();
+            if let Some(prev_spectrum) = &prev_spectrum {
+                flux_sum += spectrum.iter().zip(prev_spectrum.iter()).map(|(c, p)| (c - p).abs()).sum::<f64>() / 2.0;
+                flux_count += 1;
+            }
+            prev_spectrum = Some(spectrum);
+        }
+    }
+    let spectral_flux = if flux_count > 0 { flux_sum / flux_count as f64 } else { 0.0 };
+
+    let energy: f64 = total_power.iter().sum();
+    let bandwidth = if energy > 0.0 {
+        let mut cumulated = 0.0;
+        let bin = total_power
+            .iter()
+            .position(|p| {
+                cumulated += p;
+                cumulated >= energy * BANDWIDTH_ENERGY_RATIO
+            })
+            .unwrap_or(total_power.len() - 1);
+        bin as f64 * sample_rate as f64 / ANALYSIS_BLOCK_SIZE as f64
+    } else {
+        0.0
+    };
+
+    let speech_like = bandwidth <= SPEECH_MAX_BANDWIDTH
+        && (silence_ratio >= SPEECH_MIN_SILENCE_RATIO || spectral_flux >= SPEECH_MIN_SPECTRAL_FLUX);
+
+    Ok(SourceAnalysis {
+        bandwidth,
+        silence_ratio,
+        spectral_flux,
+        speech_like,
+    })
+}
+
+/// * Analyze the source and recommend the format for the use case, e.g. the mono speech is recommended to use Opus of 24 kbps for `UseCase::Voice`,
+///   the music is recommended to use FLAC level 8 keeping the source bit depth for `UseCase::Archival`.
+/// * The `WaveReader` is still usable after this, the iterators created later decode from where they should.
+pub fn recommend_format(reader: &mut WaveReader, use_case: UseCase) -> Result<Recommendation, AudioReadError> {
+    let source = reader.spec();
+    let analysis = analyze_source(reader)?;
+    let mut rationale = vec![format!(
+        "The source has {} channel(s) at {} Hz, {:.0} Hz bandwidth, {:.0}% silence, spectral flux {:.2}.",
+        source.channels,
+        source.sample_rate,
+        analysis.bandwidth,
+        analysis.silence_ratio * 100.0,
+        analysis.spectral_flux
+    )];
+    if analysis.speech_like {
+        rationale.push("The source looks like speech.".to_string());
+    }
+    let lossy_spec = |channels: u16, sample_rate: u32| Spec {
+        channels,
+        channel_mask: None,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let opus = |bitrate: i32| {
+        DataFormat::Opus(OpusEncoderOptions {
+            bitrate: OpusBitrate::Bits(bitrate),
+            encode_vbr: true,
+            samples_cache_duration: OpusEncoderSampleDuration::MilliSec20,
+            ..OpusEncoderOptions::new()
+        })
+    };
+
+    let (format, spec) = match use_case {
+        UseCase::Archival => {
+            // FLAC can't store float samples, 24 bits keeps everything a float source could be heard with.
+            let bits_per_sample = match source.sample_format {
+                SampleFormat::Float => {
+                    rationale.push("The float samples are stored as 24-bit integers.".to_string());
+                    24
+                }
+                _ => source.bits_per_sample,
+            };
+            if source.channels > 8 {
+                rationale.push("FLAC supports at most 8 channels, use PCM to keep every channel.".to_string());
+                (DataFormat::Pcm, source)
+            } else {
+                rationale.push(format!("FLAC level 8 is lossless and keeps the {bits_per_sample}-bit depth and the sample rate of the source."));
+                let format = DataFormat::Flac(FlacEncoderParams {
+                    compression: FlacCompression::Level8,
+                    channels: source.channels,
+                    sample_rate: source.sample_rate,
+                    bits_per_sample: bits_per_sample as u32,
+                    ..FlacEncoderParams::new()
+                });
+                (format, Spec {
+                    bits_per_sample,
+                    sample_format: SampleFormat::Int,
+                    ..source
+                })
+            }
+        }
+        UseCase::Voice => {
+            let sample_rate = if analysis.bandwidth <= SPEECH_MAX_BANDWIDTH { 16000 } else { 48000 };
+            if source.channels > 1 {
+                rationale.push("The speech only needs one channel, downmix the source to mono.".to_string());
+            }
+            rationale.push(format!("Opus of 24 kbps VBR at {sample_rate} Hz is designed for speech."));
+            (opus(24000), lossy_spec(1, sample_rate))
+        }
+        UseCase::Streaming => {
+            if source.channels > 8 {
+                let bitrate = 64000 * source.channels as u32;
+                rationale.push(format!("Opus in WAV supports at most 8 channels, OggVorbis of {} kbps keeps all channels.", bitrate / 1000));
+                let format = DataFormat::OggVorbis(OggVorbisEncoderParams {
+                    mode: OggVorbisMode::OriginalStreamCompatible,
+                    channels: source.channels,
+                    sample_rate: source.sample_rate,
+                    bitrate: Some(OggVorbisBitrateStrategy::Vbr(bitrate)),
+                    ..OggVorbisEncoderParams::new()
+                });
+                (format, lossy_spec(source.channels, source.sample_rate))
+            } else {
+                let options = OpusEncoderOptions::new();
+                let (bitrate, sample_rate) = if analysis.speech_like {
+                    (32000 * source.channels.max(2) as i32 / 2, options.get_rounded_up_sample_rate(16000))
+                } else {
+                    (64000 * source.channels as i32, options.get_rounded_up_sample_rate(source.sample_rate))
+                };
+                if source.channels > 2 {
+                    rationale.push(format!("Multistream Opus keeps all {} channels.", source.channels));
+                }
+                rationale.push(format!("Opus of {} kbps VBR at {sample_rate} Hz.", bitrate / 1000));
+                (opus(bitrate), lossy_spec(source.channels, sample_rate))
+            }
+        }
+        UseCase::Preview => {
+            let channels = source.channels.min(2);
+            if source.channels > 2 {
+                rationale.push("MP3 supports at most 2 channels, downmix the source to stereo.".to_string());
+            }
+            let bitrate = if analysis.speech_like {
+                Mp3Bitrate::Kbps64
+            } else {
+                Mp3Bitrate::Kbps128
+            };
+            rationale.push(format!("MP3 of {} kbps can be played everywhere.", bitrate as u32));
+            let format = DataFormat::Mp3(Mp3EncoderOptions {
+                channels: if channels == 1 { Mp3Channels::Mono } else { Mp3Channels::JointStereo },
+                bitrate,
+                ..Mp3EncoderOptions::new()
+            });
+            (format, lossy_spec(channels, source.sample_rate))
+        }
+    };
+
+    Ok(Recommendation {
+        format,
+        spec,
+        rationale,
+    })
+}
+
+/// * A small DFT with the Hann window for the cheap spectral check, the block size is small enough that the FFT isn't needed.
+struct Dft {
+    window: Vec<f64>,
+    cos_table: Vec<f64>,
+    sin_table: Vec<f64>,
+}
+
+impl Dft {
+    fn new(size: usize) -> Self {
+        let window = (0..size).map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / size as f64).cos()).collect();
+        let cos_table = (0..size).map(|n| (2.0 * PI * n as f64 / size as f64).cos()).collect();
+        let sin_table = (0..size).map(|n| (2.0 * PI * n as f64 / size as f64).sin()).collect();
+        Self {
+            window,
+            cos_table,
+            sin_table,
+        }
+    }
+
+    fn num_bins(&self) -> usize {
+        self.window.len() / 2 + 1
+    }
+
+    fn power_spectrum(&self, block: &[f32]) -> Vec<f64> {
+        let size = self.window.len();
+        let windowed: Vec<f64> = block.iter().zip(self.window.iter()).map(|(s, w)| *s as f64 * w).collect();
+        (0..self.num_bins())
+            .map(|k| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (n, s) in windowed.iter().enumerate() {
+                    let i = (k * n) % size;
+                    re += s * self.cos_table[i];
+                    im -= s * self.sin_table[i];
+                }
+                re * re + im * im
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,75 @@
+//! * The helpers for the `serde` feature to serialize the headers and the chunks, see `WaveReader::describe()`.
+//! * The large binary data is serialized by its length and the short binary fields as base64 text, rather than the arrays of numbers.
+
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Serializer};
+
+use crate::wavcore::GUID;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// * Encode the bytes as the standard base64 text with the padding.
+pub(crate) fn to_base64(bytes: &[u8]) -> String {
+    let mut ret = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let value = group.iter().enumerate().fold(0u32, |value, (i, &byte)| value | ((byte as u32) << (16 - i * 8)));
+        for i in 0..4 {
+            if i <= group.len() {
+                ret.push(BASE64_ALPHABET[((value >> (18 - i * 6)) & 0x3F) as usize] as char);
+            } else {
+                ret.push('=');
+            }
+        }
+    }
+    ret
+}
+
+/// * Serialize the short binary field as base64 text, e.g. the UMID of the `bext` chunk.
+pub(crate) fn base64<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]> + ?Sized,
+    S: Serializer,
+{
+    serializer.serialize_str(&to_base64(bytes.as_ref()))
+}
+
+/// * Serialize the large binary data by its length, e.g. the Vorbis header, the embedded file of the `file` chunk.
+pub(crate) fn bytes_len<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]> + ?Sized,
+    S: Serializer,
+{
+    serializer.serialize_u64(bytes.as_ref().len() as u64)
+}
+
+/// * Serialize the 4-byte identifier as text, e.g. `"data"`.
+pub(crate) fn flag<S>(flag: &[u8; 4], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&String::from_utf8_lossy(flag))
+}
+
+/// * Serialize the map as a list of the key-value pairs, for the keys that can't be the keys of a JSON object.
+pub(crate) fn map_entries<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+/// * The GUID is serialized as the text in the registry format, e.g. `"00000001-0000-0010-8000-00aa00389b71"`.
+impl Serialize for GUID {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&format_args!(
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            self.0, self.1, self.2, self.3[0], self.3[1], self.3[2], self.3[3], self.3[4], self.3[5], self.3[6], self.3[7]
+        ))
+    }
+}
@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+
+use crate::WaveWriter;
+use crate::errors::AudioWriteError;
+
+/// * How many frames to generate at a time.
+const GENERATE_BATCH_FRAMES: usize = 4096;
+
+/// * The peak level of the generated signals, -6 dBFS. It leaves the headroom for the lossy encoders to overshoot without clipping.
+pub const AMPLITUDE: f64 = 0.5;
+
+/// * Count the frames of `duration` seconds, rounded to the nearest frame.
+fn duration_to_frames(writer: &WaveWriter, duration: f64) -> Result<u64, AudioWriteError> {
+    if !duration.is_finite() || duration < 0.0 {
+        return Err(AudioWriteError::InvalidArguments(format!(
+            "The duration of the signal must be a non-negative number of seconds, got {duration}."
+        )));
+    }
+    Ok((duration * writer.spec().sample_rate as f64).round() as u64)
+}
+
+/// * Check if the frequency can be represented by the sample rate of the writer.
+fn check_frequency(writer: &WaveWriter, freq: f64) -> Result<(), AudioWriteError> {
+    let nyquist = writer.spec().sample_rate as f64 / 2.0;
+    if !freq.is_finite() || freq <= 0.0 || freq >= nyquist {
+        return Err(AudioWriteError::InvalidArguments(format!(
+            "The frequency must be between 0 and the Nyquist frequency {nyquist} Hz, got {freq}."
+        )));
+    }
+    Ok(())
+}
+
+/// * Generate `num_frames` frames by calling `generate(frame_index, channel)` for each sample, and write them in batches.
+fn write_generated<F>(writer: &mut WaveWriter, num_frames: u64, mut generate: F) -> Result<(), AudioWriteError>
+where
+    F: FnMut(u64, u16) -> f64,
+{
+    let channels = writer.spec().channels;
+    let mut samples = Vec::<f32>::with_capacity(GENERATE_BATCH_FRAMES * channels as usize);
+    let mut frame_index = 0u64;
+    while frame_index < num_frames {
+        let length = (num_frames - frame_index).min(GENERATE_BATCH_FRAMES as u64);
+        samples.clear();
+        for i in frame_index..frame_index + length {
+            for channel in 0..channels {
+                samples.push(generate(i, channel) as f32);
+            }
+        }
+        writer.write_interleaved_samples(&samples)?;
+        frame_index += length;
+    }
+    Ok(())
+}
+
+/// * Write a sine wave of `freq` Hz for `duration` seconds at `AMPLITUDE`.
+/// * Each channel lags a quarter period behind the previous one, so the swapped channels can be noticed.
+pub fn write_sine(writer: &mut WaveWriter, freq: f64, duration: f64) -> Result<(), AudioWriteError> {
+    check_frequency(writer, freq)?;
+    let num_frames = duration_to_frames(writer, duration)?;
+    let sample_rate = writer.spec().sample_rate as f64;
+    write_generated(writer, num_frames, |i, channel| {
+        AMPLITUDE * (2.0 * PI * freq * i as f64 / sample_rate - channel as f64 * PI / 2.0).sin()
+    })
+}
+
+/// * Write a logarithmic sine sweep from `start_freq` Hz to `end_freq` Hz for `duration` seconds at `AMPLITUDE`.
+/// * The sweep goes downward if `end_freq` is lower than `start_freq`. All of the channels get the same sweep.
+pub fn write_sweep(writer: &mut WaveWriter, start_freq: f64, end_freq: f64, duration: f64) -> Result<(), AudioWriteError> {
+    check_frequency(writer, start_freq)?;
+    check_frequency(writer, end_freq)?;
+    let num_frames = duration_to_frames(writer, duration)?;
+    let sample_rate = writer.spec().sample_rate as f64;
+    let ratio = (end_freq / start_freq).ln();
+    write_generated(writer, num_frames, |i, _| {
+        let t = i as f64 / sample_rate;
+        let phase = if ratio.abs() < f64::EPSILON {
+            2.0 * PI * start_freq * t
+        } else {
+            // The phase is the integral of the frequency that grows exponentially over the duration.
+            2.0 * PI * start_freq * duration / ratio * ((t / duration * ratio).exp() - 1.0)
+        };
+        AMPLITUDE * phase.sin()
+    })
+}
+
+/// * Write the white noise for `duration` seconds, uniformly distributed within `AMPLITUDE`.
+/// * The noise comes from a xorshift generator seeded by `seed`, the same seed always produces the same samples. Each channel gets its own noise.
+pub fn write_white_noise(writer: &mut WaveWriter, seed: u32, duration: f64) -> Result<(), AudioWriteError> {
+    let num_frames = duration_to_frames(writer, duration)?;
+
+    // The xorshift generator is stuck at zero.
+    let mut state = if seed == 0 { 0x9E3779B9 } else { seed };
+    write_generated(writer, num_frames, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        AMPLITUDE * (state as f64 / 2147483648.0 - 1.0)
+    })
+}
@@ -0,0 +1,305 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::WaveReader;
+use crate::chunks::{BextChunk, FmtChunk};
+use crate::errors::DecodeWarningKind;
+use crate::format_specs::format_tags::*;
+use crate::wavcore::{ExtensionData, FmtExtension};
+use crate::wavwriter::is_pcm_fmt;
+
+/// * The top-level chunks that the `WaveReader` knows, the others are reported as unknown.
+const KNOWN_CHUNKS: [&[u8; 4]; 19] = [
+    b"fmt ", b"fact", b"data", b"ds64", b"JUNK", b"slnt", b"bext", b"smpl", b"inst", b"INST",
+    b"plst", b"cue ", b"axml", b"chna", b"ixml", b"acid", b"Trkn", b"id3 ", b"LIST",
+];
+
+/// * How bad a finding of `WaveReader::validate()` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    /// * Nothing wrong, but worth knowing, e.g. a chunk that isn't understood.
+    Info,
+
+    /// * The file is readable but some fields are wrong, other readers may play it differently.
+    Warning,
+
+    /// * The file is damaged, some of the audio is lost or can't be located correctly.
+    Error,
+}
+
+/// * What kind of problem a finding of `WaveReader::validate()` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValidationIssueKind {
+    /// * The RIFF size, the `data` chunk size or the `fact` chunk disagree with each other or with the file.
+    SizeMismatch,
+
+    /// * The `byte_rate` of the `fmt ` chunk isn't `sample_rate * block_align` for the uncompressed formats.
+    ByteRate,
+
+    /// * The `block_align` of the `fmt ` chunk doesn't match the channels and the bits per sample.
+    BlockAlign,
+
+    /// * The number of the speakers in the channel mask isn't the number of the channels.
+    ChannelMask,
+
+    /// * A top-level chunk that the `WaveReader` doesn't know.
+    UnknownChunk,
+
+    /// * The `data` chunk is cut off by the end of the file.
+    TruncatedData,
+
+    /// * The cue points aren't in the order of their positions, or point beyond the audio.
+    CuePoints,
+
+    /// * The fields of the `bext` chunk don't follow the EBU Tech 3285 rules for its version.
+    Bext,
+
+    /// * The layout of the chunks, e.g. an odd-sized chunk without the pad byte.
+    ChunkLayout,
+
+    /// * The `fmt ` chunk is missing or damaged, the audio is read by the spec of `ReadOptions::assume_spec`.
+    AssumedSpec,
+
+    /// * The audio data has corrupted packets that the decoder skipped or stopped at.
+    CorruptedData,
+}
+
+/// * A finding of `WaveReader::validate()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: ValidationSeverity,
+    pub kind: ValidationIssueKind,
+
+    /// * The byte offset in the file of the field or the chunk that has the problem.
+    pub offset: u64,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:?} {:?} at offset 0x{:x}: {}", self.severity, self.kind, self.offset, self.message)
+    }
+}
+
+/// * The findings of `WaveReader::validate()`, in the order of their offsets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// * No warnings and no errors, the findings of `ValidationSeverity::Info` don't count.
+    pub fn is_clean(&self) -> bool {
+        self.issues.iter().all(|issue| issue.severity == ValidationSeverity::Info)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == ValidationSeverity::Error)
+    }
+
+    /// * The worst severity of the findings, `None` if there's nothing found.
+    pub fn worst_severity(&self) -> Option<ValidationSeverity> {
+        self.issues.iter().map(|issue| issue.severity).max()
+    }
+
+    /// * The findings of the kind.
+    pub fn find(&self, kind: ValidationIssueKind) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(move |issue| issue.kind == kind)
+    }
+
+    fn push(&mut self, severity: ValidationSeverity, kind: ValidationIssueKind, offset: u64, message: String) {
+        self.issues.push(ValidationIssue { severity, kind, offset, message });
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for issue in self.issues.iter() {
+            writeln!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// * The `block_align` of the formats that store each sample in whole bytes, `None` for the compressed formats.
+pub(crate) fn fixed_block_align(fmt: &FmtChunk) -> Option<u16> {
+    match fmt.format_tag {
+        FORMAT_TAG_ALAW | FORMAT_TAG_MULAW => Some(fmt.channels),
+        _ if is_pcm_fmt(fmt) => Some(fmt.channels * fmt.bits_per_sample.div_ceil(8)),
+        _ => None,
+    }
+}
+
+/// * The channel mask of the extensible `fmt ` chunk.
+pub(crate) fn channel_mask_of(fmt: &FmtChunk) -> Option<u32> {
+    match &fmt.extension {
+        Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. }) => Some(extensible.channel_mask),
+        _ => None,
+    }
+}
+
+pub(crate) fn validate(reader: &WaveReader) -> ValidationReport {
+    use ValidationIssueKind::*;
+    use ValidationSeverity::*;
+    let mut report = ValidationReport::default();
+    let chunk_offset = |flag: &[u8; 4]| -> u64 {
+        reader.chunk_map().iter().find(|info| &info.flag == flag).map_or(0, |info| info.offset)
+    };
+
+    // The problems that the parser worked around.
+    for warning in reader.warnings() {
+        let (severity, kind) = match warning.kind {
+            DecodeWarningKind::CorruptedPacket => (Error, CorruptedData),
+            DecodeWarningKind::SizeMismatch => (Warning, SizeMismatch),
+            DecodeWarningKind::ChunkLayout => (Warning, ChunkLayout),
+            DecodeWarningKind::FmtChunk => (Warning, BlockAlign),
+            DecodeWarningKind::AssumedSpec => (Error, AssumedSpec),
+        };
+        report.push(severity, kind, warning.offset, warning.message.clone());
+    }
+
+    // The fields of the `fmt ` chunk, `byte_rate` is at 8 bytes of the chunk data, `block_align` is at 12 bytes.
+    let fmt = reader.get_fmt__chunk();
+    let fmt_offset = chunk_offset(b"fmt ") + 8;
+    if fmt.channels == 0 || fmt.block_align == 0 {
+        report.push(Error, BlockAlign, fmt_offset + 12, format!(
+            "The `fmt ` chunk has {} channels and the block size of {} bytes.",
+            fmt.channels, fmt.block_align
+        ));
+    }
+    if let Some(block_align) = fixed_block_align(fmt) {
+        if fmt.block_align != block_align {
+            report.push(Error, BlockAlign, fmt_offset + 12, format!(
+                "The block size is {} bytes, {} channels of {} bits are {} bytes.",
+                fmt.block_align, fmt.channels, fmt.bits_per_sample, block_align
+            ));
+        }
+        let byte_rate = fmt.sample_rate as u64 * block_align as u64;
+        if fmt.byte_rate as u64 != byte_rate {
+            report.push(Warning, ByteRate, fmt_offset + 8, format!(
+                "The byte rate is {}, {} frames per second of {} bytes are {} bytes per second.",
+                fmt.byte_rate, fmt.sample_rate, block_align, byte_rate
+            ));
+        }
+    }
+    if let Some(channel_mask) = channel_mask_of(fmt) {
+        if channel_mask != 0 && channel_mask.count_ones() != fmt.channels as u32 {
+            report.push(Warning, ChannelMask, fmt_offset + 20, format!(
+                "The channel mask 0x{:x} has {} speakers for {} channels.",
+                channel_mask, channel_mask.count_ones(), fmt.channels
+            ));
+        }
+    }
+
+    // The `data` chunk and the `fact` chunk.
+    let data_offset = chunk_offset(b"data");
+    if reader.is_truncated() {
+        report.push(Error, TruncatedData, data_offset, format!(
+            "The `data` chunk is cut off by the end of the file, only {} bytes are there.",
+            reader.get_data_length()
+        ));
+    }
+    if fmt.block_align > 0 && reader.get_data_length() % fmt.block_align as u64 != 0 {
+        report.push(Warning, SizeMismatch, data_offset + 4, format!(
+            "The `data` chunk of {} bytes isn't a multiple of the block size of {} bytes.",
+            reader.get_data_length(), fmt.block_align
+        ));
+    }
+    if fmt.channels > 0 && fmt.block_align > 0 && fixed_block_align(fmt).is_some() && reader.get_fact_data() > 0 {
+        let fact_frames = reader.get_fact_data() / fmt.channels as u64;
+        let data_frames = reader.get_data_length() / fmt.block_align as u64;
+        if fact_frames != data_frames {
+            report.push(Warning, SizeMismatch, chunk_offset(b"fact") + 8, format!(
+                "The `fact` chunk says {fact_frames} frames, the `data` chunk has {data_frames} frames."
+            ));
+        }
+    }
+
+    for info in reader.chunk_map().iter() {
+        if !KNOWN_CHUNKS.contains(&&info.flag) {
+            report.push(Info, UnknownChunk, info.offset, format!(
+                "The chunk \"{}\" of {} bytes isn't known.",
+                String::from_utf8_lossy(&info.flag), info.length
+            ));
+        }
+    }
+
+    // The cue points are expected in the order of their positions, and inside the audio.
+    if let Some(cue) = reader.get_cue__chunk() {
+        let cue_offset = chunk_offset(b"cue ") + 12;
+        let total_frames = reader.total_frames();
+        for (i, point) in cue.cue_points.iter().enumerate() {
+            let offset = cue_offset + i as u64 * 24;
+            if i > 0 && point.position < cue.cue_points[i - 1].position {
+                report.push(Warning, CuePoints, offset, format!(
+                    "The cue point {} at frame {} is before the previous cue point at frame {}.",
+                    point.cue_point_id, point.position, cue.cue_points[i - 1].position
+                ));
+            }
+            if let Some(total_frames) = total_frames.filter(|total_frames| point.position > *total_frames) {
+                report.push(Error, CuePoints, offset, format!(
+                    "The cue point {} at frame {} is beyond the {} frames of the audio.",
+                    point.cue_point_id, point.position, total_frames
+                ));
+            }
+        }
+    }
+
+    if let Some(bext) = reader.get_bext_chunk() {
+        validate_bext(bext, chunk_offset(b"bext") + 8, &mut report);
+    }
+
+    report.issues.sort_by_key(|issue| issue.offset);
+    report
+}
+
+/// * The `bext` chunk has no checksum, its fields are checked by the rules of its version instead.
+fn validate_bext(bext: &BextChunk, offset: u64, report: &mut ValidationReport) {
+    use ValidationIssueKind::Bext;
+    use ValidationSeverity::*;
+    if !is_bext_date(&bext.origination_date) {
+        report.push(Warning, Bext, offset + 320, format!("The origination date \"{}\" isn't `yyyy-mm-dd`.", bext.origination_date));
+    }
+    if !is_bext_time(&bext.origination_time) {
+        report.push(Warning, Bext, offset + 330, format!("The origination time \"{}\" isn't `hh:mm:ss`.", bext.origination_time));
+    }
+    if bext.version > 2 {
+        report.push(Warning, Bext, offset + 346, format!("The version {} of the `bext` chunk isn't known.", bext.version));
+    }
+    let loudness = [
+        bext.loudness_value,
+        bext.loudness_range,
+        bext.max_true_peak_level,
+        bext.max_momentary_loudness,
+        bext.max_short_term_loudness,
+    ];
+    if bext.version < 2 && loudness.iter().any(|value| *value != 0) {
+        report.push(Warning, Bext, offset + 412, format!(
+            "The loudness fields are set, but they are only in the version 2 of the `bext` chunk, the version is {}.",
+            bext.version
+        ));
+    }
+    if bext.version == 0 && bext.umid.iter().any(|byte| *byte != 0) {
+        report.push(Warning, Bext, offset + 348, String::from("The UMID is set, but it's only in the version 1 and later of the `bext` chunk."));
+    }
+    if bext.reserved.iter().any(|byte| *byte != 0) {
+        report.push(Warning, Bext, offset + 422, String::from("The reserved bytes of the `bext` chunk aren't zero."));
+    }
+}
+
+/// * The digits of the `bext` date and time can be separated by any of `-`, `_`, `:`, ` ` and `.`.
+fn matches_layout(text: &str, layout: &str) -> bool {
+    text.len() == layout.len() && text.bytes().zip(layout.bytes()).all(|(c, l)| match l {
+        b'd' => c.is_ascii_digit(),
+        _ => b"-_:. ".contains(&c),
+    })
+}
+
+/// * Is the origination date of the `bext` chunk `yyyy-mm-dd`, the empty date counts.
+pub(crate) fn is_bext_date(date: &str) -> bool {
+    date.is_empty() || matches_layout(date, "dddd-dd-dd")
+}
+
+/// * Is the origination time of the `bext` chunk `hh:mm:ss`, the empty time counts.
+pub(crate) fn is_bext_time(time: &str) -> bool {
+    time.is_empty() || matches_layout(time, "dd:dd:dd")
+}
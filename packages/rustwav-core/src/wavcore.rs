@@ -1,10 +1,12 @@
 #![allow(dead_code)]
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    any::TypeId,
+    collections::{BTreeMap, BTreeSet, HashMap},
     convert::From,
     fmt::{self, Debug, Display, Formatter},
     io::{self, Read, SeekFrom, Write},
+    time::Duration,
 };
 
 use sampletypes::SampleType;
@@ -13,6 +15,7 @@ use downmixer::*;
 use io_utils::{Reader, Writer, string_io::*};
 use crate::errors::{AudioError, AudioReadError, AudioWriteError};
 
+use pcm::*;
 use mp3::*;
 use opus::*;
 use flac::*;
@@ -28,6 +31,10 @@ pub enum DataFormat {
     /// * PCM format, supports `u8`, `i16`, `i24`, `i32`, `f32`, `f64` for WAV, supports channel number >= 2, no compresion, lossless.
     Pcm,
 
+    /// * PCM format with the extensible `fmt ` chunk, the `SubFormat` GUID could be specified by you.
+    /// * Some hardware requires a vendor-specific GUID e.g. the ambisonic B-format GUIDs while the payload is ordinary PCM.
+    PcmExtensible(PcmEncoderOptions),
+
     /// * ADPCM format, every sample stores as nibbles (One 4-bit nibble for a 16-bit sample), max channels is 2, lossy. Good for voice chatting, and very small memory usage.
     Adpcm(AdpcmSubFormat),
 
@@ -43,9 +50,8 @@ pub enum DataFormat {
     /// * The WAV file which encapsulates the MP3 file as its content, the size of the WAV file looks like an MP3 file size.
     Mp3(Mp3EncoderOptions),
 
-    /// * Naked opus stream, without the Ogg container. The encoded data is stored as blocks in the `data` chunk, the block size is stored in the `fmt ` chunk.
-    /// * Just take a look at the blocks, there are lots of zero bytes at the end, indicating that the Opus format is excellent at compressing the audio data.
-    /// * But WAV can't get rid of these zero bytes, resulting in the compression ratio just like encoding each sample into a byte.
+    /// * Naked opus stream, without the Ogg container. The encoded packets are stored in the `data` chunk, each packet is prefixed by its size.
+    /// * The number of audio frames of each packet is stored in the extension data of the `fmt ` chunk.
     /// * Opus was originally designed for low-lag digital audio transmission with good quality. Encapsulating this thing into a WAV file is very weird.
     Opus(OpusEncoderOptions),
 
@@ -57,26 +63,58 @@ pub enum DataFormat {
     /// * OggVorbis. Just a pure OggVorbis file encapsulated in the `data` chunk.
     /// * The WAV file which encapsulates the OggVorbis file as its content, the size of the WAV file looks like an OggVorbis file size.
     OggVorbis(OggVorbisEncoderParams),
+
+    /// * A third-party codec, the value is the `format_tag` of the `fmt ` chunk.
+    /// * The `WaveWriter` can't create the encoder for it, use `WaveWriter::create_with_encoder()` to provide your encoder, and `ReadOptions::custom_decoder` to read it back.
+    Custom(u16),
+
+    /// * Remux without re-encoding, e.g. to fix the header of a WAV file. See `WaveWriter::copy_data_from_reader()`.
+    /// * The `fmt ` chunk is cloned from the `WaveReader` and the `data` chunk is copied byte by byte, the samples can't be written.
+    PassThrough,
 }
 
 /// * When to encode audio to ADPCM format, choose one of the subformats.
 /// * The value of the subformat is the `format_tag` field of the `fmt ` chunk.
 #[derive(Debug, Clone, Copy, PartialEq)]
-#[repr(u16)]
 pub enum AdpcmSubFormat {
     /// * This is for ADPCM-MS
-    Ms = 0x0002,
+    Ms,
 
     /// * This is for ADPCM-IMA
-    Ima = 0x0011,
+    Ima,
+
+    /// * This is for ADPCM-IMA with 2 bits per sample, the low bit-rate variant.
+    Ima2,
+
+    /// * This is for ADPCM-IMA with 3 bits per sample, the low bit-rate variant.
+    Ima3,
+
+    /// * This is for ADPCM-IMA with 5 bits per sample.
+    Ima5,
 
     /// * This is for ADPCM-YAMAHA. The Yamaha ADPCM algorithm is the easiest one to implement.
-    Yamaha = 0x0020,
+    Yamaha,
+}
+
+impl AdpcmSubFormat {
+    /// * The `bits_per_sample` field of the `fmt ` chunk.
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            Self::Ima2 => 2,
+            Self::Ima3 => 3,
+            Self::Ima5 => 5,
+            Self::Ms | Self::Ima | Self::Yamaha => 4,
+        }
+    }
 }
 
 impl From<AdpcmSubFormat> for u16 {
     fn from(val: AdpcmSubFormat) -> Self {
-        val as u16
+        match val {
+            AdpcmSubFormat::Ms => 0x0002,
+            AdpcmSubFormat::Ima | AdpcmSubFormat::Ima2 | AdpcmSubFormat::Ima3 | AdpcmSubFormat::Ima5 => 0x0011,
+            AdpcmSubFormat::Yamaha => 0x0020,
+        }
     }
 }
 
@@ -85,6 +123,7 @@ impl Display for DataFormat {
         match self {
             Self::Unspecified => write!(f, "Unspecified"),
             Self::Pcm => write!(f, "PCM"),
+            Self::PcmExtensible(options) => write!(f, "PCM-Extensible({:?})", options),
             Self::Adpcm(subformat) => write!(f, "{:?}", subformat),
             Self::PcmALaw => write!(f, "PCM-ALaw"),
             Self::PcmMuLaw => write!(f, "PCM-MuLaw"),
@@ -92,6 +131,94 @@ impl Display for DataFormat {
             Self::Opus(options) => write!(f, "Opus({:?})", options),
             Self::Flac(options) => write!(f, "Flac({:?})", options),
             Self::OggVorbis(options) => write!(f, "OggVorbis({:?})", options),
+            Self::Custom(format_tag) => write!(f, "Custom(0x{:04x})", format_tag),
+            Self::PassThrough => write!(f, "PassThrough"),
+        }
+    }
+}
+
+/// * Declares the `FORMATS` table from a list of `name => DataFormat`, the length of the table is counted from the list.
+macro_rules! declare_formats {
+    ($($name:literal => $format:expr,)*) => {
+        /// * The names and the pre-filled encoder options for every `DataFormat`, used by the command line program and the tests.
+        /// * The options are the defaults of each option struct, with some intentional overrides for the best quality.
+        #[allow(clippy::large_const_arrays)]
+        pub const FORMATS: [(&str, DataFormat); [$($name),*].len()] = [$(($name, $format)),*];
+    };
+}
+
+declare_formats! {
+    "pcm" => DataFormat::Pcm,
+    "pcm-alaw" => DataFormat::PcmALaw,
+    "pcm-ulaw" => DataFormat::PcmMuLaw,
+    "adpcm-ms" => DataFormat::Adpcm(AdpcmSubFormat::Ms),
+    "adpcm-ima" => DataFormat::Adpcm(AdpcmSubFormat::Ima),
+    "adpcm-yamaha" => DataFormat::Adpcm(AdpcmSubFormat::Yamaha),
+    "mp3" => DataFormat::Mp3(Mp3EncoderOptions::new()),
+    "opus" => DataFormat::Opus(OpusEncoderOptions::new()),
+    "flac" => DataFormat::Flac(FlacEncoderParams {
+        compression: FlacCompression::Level8,
+        bits_per_sample: 32,
+        ..FlacEncoderParams::new()
+    }),
+    "vorbis" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::NakedVorbis,
+        bitrate: Some(OggVorbisBitrateStrategy::Vbr(160000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis1" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::OriginalStreamCompatible,
+        bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis2" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::HaveIndependentHeader,
+        bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis3" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::HaveNoCodebookHeader,
+        bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis1p" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::OriginalStreamCompatible,
+        bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis2p" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::HaveIndependentHeader,
+        bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "oggvorbis3p" => DataFormat::OggVorbis(OggVorbisEncoderParams {
+        mode: OggVorbisMode::HaveNoCodebookHeader,
+        bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
+        ..OggVorbisEncoderParams::new_stereo()
+    }),
+    "pcm-extensible" => DataFormat::PcmExtensible(PcmEncoderOptions::new()),
+    "adpcm-ima2" => DataFormat::Adpcm(AdpcmSubFormat::Ima2),
+    "adpcm-ima3" => DataFormat::Adpcm(AdpcmSubFormat::Ima3),
+    "adpcm-ima5" => DataFormat::Adpcm(AdpcmSubFormat::Ima5),
+}
+
+impl DataFormat {
+    /// * Find the `DataFormat` by its name in the `FORMATS` table, e.g. `"adpcm-ima"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        FORMATS
+            .iter()
+            .find(|(format_name, _)| *format_name == name)
+            .map(|(_, data_format)| data_format.clone())
+    }
+
+    /// * The maximum number of channels, the same as `get_max_channels()` of the encoder for the format.
+    pub fn get_max_channels(&self) -> u16 {
+        match self {
+            Self::Adpcm(_) | Self::PcmALaw | Self::PcmMuLaw | Self::Mp3(_) => 2,
+            Self::Opus(_) => OPUS_MAX_CHANNELS,
+            Self::Flac(_) => 8,
+            Self::OggVorbis(_) => 255,
+            Self::Unspecified | Self::Pcm | Self::PcmExtensible(_) | Self::Custom(_) | Self::PassThrough => u16::MAX,
         }
     }
 }
@@ -101,6 +228,9 @@ impl Display for AdpcmSubFormat {
         match self {
             Self::Ms => write!(f, "ADPCM-MS"),
             Self::Ima => write!(f, "ADPCM-IMA"),
+            Self::Ima2 => write!(f, "ADPCM-IMA-2bit"),
+            Self::Ima3 => write!(f, "ADPCM-IMA-3bit"),
+            Self::Ima5 => write!(f, "ADPCM-IMA-5bit"),
             Self::Yamaha => write!(f, "ADPCM-YAMAHA"),
         }
     }
@@ -115,6 +245,7 @@ pub mod format_tags {
     pub const FORMAT_TAG_ADPCM_IMA    : u16 = 0x0011;
     pub const FORMAT_TAG_ADPCM_IMA_   : u16 = 0x0067;
     pub const FORMAT_TAG_ADPCM_YAMAHA : u16 = 0x0020;
+    pub const FORMAT_TAG_GSM610       : u16 = 0x0031;
     pub const FORMAT_TAG_MP3          : u16 = 0x0055;
     pub const FORMAT_TAG_OPUS         : u16 = 0x704F;
     pub const FORMAT_TAG_OGG_VORBIS1  : u16 = ('O' as u16) | (('g' as u16) << 8);
@@ -126,13 +257,15 @@ pub mod format_tags {
     pub const FORMAT_TAG_VORBIS       : u16 = ('o' as u16) | (('V' as u16) << 8);
     pub const FORMAT_TAG_FLAC         : u16 = 0xF1AC;
     pub const FORMAT_TAG_EXTENSIBLE   : u16 = 0xFFFE;
+    pub const FORMAT_TAG_DEVELOPMENT  : u16 = 0xFFFF;
 }
 
 #[allow(unused_imports)]
 pub use format_tags::*;
 
 /// * The rough type of the sample format.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SampleFormat {
     Unknown,
 
@@ -215,6 +348,64 @@ impl WaveSampleType {
             Unknown => 0,
         }
     }
+
+    /// * The `WaveSampleType` of the Rust sample type `S`, e.g. `S24` for `i24`.
+    pub fn of<S>() -> Self
+    where
+        S: SampleType,
+    {
+        use WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64, Unknown};
+        match S::TYPE_NAME {
+            "i8" => S8,
+            "i16" => S16,
+            "i24" => S24,
+            "i32" => S32,
+            "i64" => S64,
+            "u8" => U8,
+            "u16" => U16,
+            "u24" => U24,
+            "u32" => U32,
+            "u64" => U64,
+            "f32" => F32,
+            "f64" => F64,
+            _ => Unknown,
+        }
+    }
+}
+
+/// * If the sample type `S` of the caller is the type `T` in the WAV file, and the memory is little-endian as the WAV file,
+///   the samples can be copied as bytes without converting them one by one.
+/// * Only the primitive number types are copiable, any bytes are valid for them. The 24-bit types are excluded, their memory layout isn't guaranteed to be the packed 3 bytes.
+pub(crate) fn is_sample_bytes_copiable<S, T>() -> bool
+where
+    S: SampleType + 'static,
+    T: SampleType + 'static,
+{
+    let primitives = [
+        TypeId::of::<i8>(), TypeId::of::<i16>(), TypeId::of::<i32>(), TypeId::of::<i64>(),
+        TypeId::of::<u8>(), TypeId::of::<u16>(), TypeId::of::<u32>(), TypeId::of::<u64>(),
+        TypeId::of::<f32>(), TypeId::of::<f64>(),
+    ];
+    cfg!(target_endian = "little") && TypeId::of::<S>() == TypeId::of::<T>() && primitives.contains(&TypeId::of::<S>())
+}
+
+/// * Both are the float types. The floats out of `[-1.0, 1.0]` are legit, e.g. the intermediate stems with headroom,
+///   the conversion between the float types keeps them as they are, only the conversion to the integer types clamps them.
+pub(crate) fn is_float_to_float<S, T>() -> bool
+where
+    S: SampleType,
+    T: SampleType,
+{
+    matches!(S::TYPE_NAME, "f32" | "f64") && matches!(T::TYPE_NAME, "f32" | "f64")
+}
+
+/// * Convert the float value to `S` which is `f32` or `f64` without scaling or clamping, see `is_float_to_float()`.
+/// * It's the `as` cast of `SampleType::cast_from()`, the floats out of `[-1.0, 1.0]` are kept.
+pub(crate) fn float_sample_from<S>(value: f64) -> S
+where
+    S: SampleType,
+{
+    S::cast_from(value)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -280,6 +471,16 @@ impl GUID {
         w.write_all(&self.3)?;
         Ok(())
     }
+
+    /// * Get the 16 bytes of the GUID as it's stored in the WAV file.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut ret = [0u8; 16];
+        ret[0..4].copy_from_slice(&self.0.to_le_bytes());
+        ret[4..6].copy_from_slice(&self.1.to_le_bytes());
+        ret[6..8].copy_from_slice(&self.2.to_le_bytes());
+        ret[8..16].copy_from_slice(&self.3);
+        ret
+    }
 }
 
 pub mod guids {
@@ -287,18 +488,237 @@ pub mod guids {
 
     pub const GUID_PCM_FORMAT: GUID =        GUID(0x00000001, 0x0000, 0x0010, [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71]);
     pub const GUID_IEEE_FLOAT_FORMAT: GUID = GUID(0x00000003, 0x0000, 0x0010, [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71]);
+
+    /// * The ambisonic B-format (AMB) GUIDs, the payload is ordinary PCM, the channel mask is normally zero.
+    pub const GUID_AMBISONIC_B_FORMAT_PCM: GUID =        GUID(0x00000001, 0x0721, 0x11d3, [0x86, 0x44, 0xc8, 0xc1, 0xca, 0x00, 0x00, 0x00]);
+    pub const GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT: GUID = GUID(0x00000003, 0x0721, 0x11d3, [0x86, 0x44, 0xc8, 0xc1, 0xca, 0x00, 0x00, 0x00]);
 }
 
 pub use guids::*;
 
+/// * A speaker position of the channel mask, the value is the bit of the speaker in the `WAVEFORMATEXTENSIBLE` channel mask.
+/// * The channels in the audio frames are in the order of the bits, from the lowest bit to the highest bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum Speaker {
+    FrontLeft = 0x1,
+    FrontRight = 0x2,
+    FrontCenter = 0x4,
+    LowFrequency = 0x8,
+    BackLeft = 0x10,
+    BackRight = 0x20,
+    FrontLeftOfCenter = 0x40,
+    FrontRightOfCenter = 0x80,
+    BackCenter = 0x100,
+    SideLeft = 0x200,
+    SideRight = 0x400,
+    TopCenter = 0x800,
+    TopFrontLeft = 0x1000,
+    TopFrontCenter = 0x2000,
+    TopFrontRight = 0x4000,
+    TopBackLeft = 0x8000,
+    TopBackCenter = 0x10000,
+    TopBackRight = 0x20000,
+}
+
+impl Speaker {
+    /// * All of the speaker positions in the order of the bits.
+    pub const ALL: [Speaker; 18] = [
+        Self::FrontLeft,
+        Self::FrontRight,
+        Self::FrontCenter,
+        Self::LowFrequency,
+        Self::BackLeft,
+        Self::BackRight,
+        Self::FrontLeftOfCenter,
+        Self::FrontRightOfCenter,
+        Self::BackCenter,
+        Self::SideLeft,
+        Self::SideRight,
+        Self::TopCenter,
+        Self::TopFrontLeft,
+        Self::TopFrontCenter,
+        Self::TopFrontRight,
+        Self::TopBackLeft,
+        Self::TopBackCenter,
+        Self::TopBackRight,
+    ];
+
+    /// * The bit of the speaker in the channel mask.
+    pub const fn bit(self) -> u32 {
+        self as u32
+    }
+
+    /// * The short name of the speaker, e.g. `FL` for `FrontLeft`.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Self::FrontLeft => "FL",
+            Self::FrontRight => "FR",
+            Self::FrontCenter => "FC",
+            Self::LowFrequency => "LFE",
+            Self::BackLeft => "BL",
+            Self::BackRight => "BR",
+            Self::FrontLeftOfCenter => "FLC",
+            Self::FrontRightOfCenter => "FRC",
+            Self::BackCenter => "BC",
+            Self::SideLeft => "SL",
+            Self::SideRight => "SR",
+            Self::TopCenter => "TC",
+            Self::TopFrontLeft => "TFL",
+            Self::TopFrontCenter => "TFC",
+            Self::TopFrontRight => "TFR",
+            Self::TopBackLeft => "TBL",
+            Self::TopBackCenter => "TBC",
+            Self::TopBackRight => "TBR",
+        }
+    }
+}
+
+impl Display for Speaker {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.abbreviation())
+    }
+}
+
+/// * The type-safe channel mask, a set of `Speaker`s. This is the preferred way to specify `Spec::channel_mask`, see `Spec::with_layout()`.
+/// * The bits of the raw mask that aren't speakers (e.g. `SPEAKER_ALL`) are kept as they are, they don't count as channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ChannelLayout(u32);
+
+impl ChannelLayout {
+    /// * The layout without speaker positions, the channel mask is zero. Some ambisonics files require this.
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    pub const fn mono() -> Self {
+        Self(Speaker::FrontCenter.bit())
+    }
+
+    pub const fn stereo() -> Self {
+        Self(Speaker::FrontLeft.bit() | Speaker::FrontRight.bit())
+    }
+
+    /// * 2.1: FL, FR, LFE
+    pub const fn surround_2_1() -> Self {
+        Self(Self::stereo().0 | Speaker::LowFrequency.bit())
+    }
+
+    /// * 3.0: FL, FR, FC
+    pub const fn surround_3_0() -> Self {
+        Self(Self::stereo().0 | Speaker::FrontCenter.bit())
+    }
+
+    /// * Quadraphonic: FL, FR, BL, BR
+    pub const fn quad() -> Self {
+        Self(Self::stereo().0 | Speaker::BackLeft.bit() | Speaker::BackRight.bit())
+    }
+
+    /// * 5.1 with the back speakers: FL, FR, FC, LFE, BL, BR
+    pub const fn surround_5_1() -> Self {
+        Self(Self::quad().0 | Speaker::FrontCenter.bit() | Speaker::LowFrequency.bit())
+    }
+
+    /// * 5.1 with the side speakers: FL, FR, FC, LFE, SL, SR
+    pub const fn surround_5_1_side() -> Self {
+        Self(Self::stereo().0 | Speaker::FrontCenter.bit() | Speaker::LowFrequency.bit() | Speaker::SideLeft.bit() | Speaker::SideRight.bit())
+    }
+
+    /// * 7.1: FL, FR, FC, LFE, BL, BR, SL, SR
+    pub const fn surround_7_1() -> Self {
+        Self(Self::surround_5_1().0 | Speaker::SideLeft.bit() | Speaker::SideRight.bit())
+    }
+
+    /// * Create the layout from the raw channel mask.
+    pub const fn from_mask(channel_mask: u32) -> Self {
+        Self(channel_mask)
+    }
+
+    /// * Create the layout from the speakers, the order of the speakers doesn't matter.
+    pub fn from_speakers(speakers: &[Speaker]) -> Self {
+        Self(speakers.iter().fold(0, |mask, speaker| mask | speaker.bit()))
+    }
+
+    /// * The raw channel mask for `Spec::channel_mask` and the `fmt ` chunk.
+    pub const fn mask(&self) -> u32 {
+        self.0
+    }
+
+    /// * Add a speaker to the layout.
+    pub const fn with(self, speaker: Speaker) -> Self {
+        Self(self.0 | speaker.bit())
+    }
+
+    pub const fn contains(&self, speaker: Speaker) -> bool {
+        self.0 & speaker.bit() != 0
+    }
+
+    /// * The speakers in the order of the channels.
+    pub fn speakers(&self) -> Vec<Speaker> {
+        Speaker::ALL.into_iter().filter(|speaker| self.contains(*speaker)).collect()
+    }
+
+    /// * The channel index of the speaker in the audio frames.
+    pub fn channel_index_of(&self, speaker: Speaker) -> Option<usize> {
+        self.contains(speaker).then(|| (self.0 & (speaker.bit() - 1) & Self::SPEAKER_BITS).count_ones() as usize)
+    }
+
+    /// * How many channels the speakers are for.
+    pub const fn num_channels(&self) -> u16 {
+        (self.0 & Self::SPEAKER_BITS).count_ones() as u16
+    }
+
+    /// * Check if the layout is good for the number of channels. The layout without speaker positions is good for any number of channels.
+    pub fn is_valid_for(&self, channels: u16) -> bool {
+        self.0 == 0 || speaker_positions::is_channel_mask_valid(channels, self.0)
+    }
+
+    /// * The bits of all of the `Speaker`s.
+    const SPEAKER_BITS: u32 = 0x3FFFF;
+}
+
+impl From<u32> for ChannelLayout {
+    fn from(channel_mask: u32) -> Self {
+        Self(channel_mask)
+    }
+}
+
+impl From<ChannelLayout> for u32 {
+    fn from(layout: ChannelLayout) -> Self {
+        layout.0
+    }
+}
+
+impl std::ops::BitOr for ChannelLayout {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl Display for ChannelLayout {
+    /// * The speakers joined by `+`, e.g. `FL+FR`, or `None` for the layout without speaker positions.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.0 & Self::SPEAKER_BITS == 0 {
+            return write!(f, "None");
+        }
+        let speakers: Vec<&str> = self.speakers().into_iter().map(Speaker::abbreviation).collect();
+        write!(f, "{}", speakers.join("+"))
+    }
+}
+
 /// * The spec info for a generic audio file.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Spec {
     /// * Num channels
     pub channels: u16,
 
     /// * The channel mask indicates the position of the speakers.
-    pub channel_mask: u32,
+    /// * `None`: there's no channel mask, the WAV file uses the plain `fmt ` chunk (`WAVEFORMATEX`).
+    /// * `Some(mask)`: the WAV file uses the extensible `fmt ` chunk with the mask. `Some(0)` means no speaker positions, some ambisonics files require this.
+    /// * The raw mask is kept for compatibility, `Spec::with_layout()` and `Spec::channel_layout()` are the preferred way to use it.
+    pub channel_mask: Option<u32>,
 
     /// * The sample rate. How many audio frames are to be played in a second.
     pub sample_rate: u32,
@@ -338,7 +758,7 @@ impl Spec {
     pub fn new() -> Self {
         Self {
             channels: 0,
-            channel_mask: 0,
+            channel_mask: None,
             sample_rate: 0,
             bits_per_sample: 0,
             sample_format: SampleFormat::Unknown,
@@ -350,24 +770,55 @@ impl Spec {
         get_sample_type(self.bits_per_sample, self.sample_format)
     }
 
+    /// * Set the channel layout, and the number of channels from it. The WAV file uses the extensible `fmt ` chunk with the layout.
+    /// * For `ChannelLayout::none()`, the number of channels is kept, set it before or after this.
+    pub fn with_layout(self, layout: ChannelLayout) -> Self {
+        Self {
+            channels: if layout.num_channels() > 0 { layout.num_channels() } else { self.channels },
+            channel_mask: Some(layout.mask()),
+            ..self
+        }
+    }
+
+    /// * The channel layout of the channel mask, `None` if there's no channel mask.
+    pub fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.channel_mask.map(ChannelLayout::from_mask)
+    }
+
     /// * Guess the channel mask
     pub fn guess_channel_mask(&self) -> Result<u32, AudioError> {
         Ok(speaker_positions::guess_channel_mask(self.channels)?)
     }
 
+    /// * Get the channel mask, if there's no channel mask, guess one by the number of channels.
+    /// * If the channel mask can't be guessed, returns zero.
+    pub fn effective_channel_mask(&self) -> u32 {
+        match self.channel_mask {
+            Some(channel_mask) => channel_mask,
+            None => self.guess_channel_mask().unwrap_or(0),
+        }
+    }
+
     /// * Break down a channel mask to the speaker positions.
     pub fn channel_mask_to_speaker_positions(&self) -> Vec<u32> {
-        speaker_positions::channel_mask_to_speaker_positions(self.channel_mask)
+        speaker_positions::channel_mask_to_speaker_positions(self.effective_channel_mask())
     }
 
     /// * Break down a channel mask to the speaker position description strings.
     pub fn channel_mask_to_speaker_positions_descs(&self) -> Vec<&'static str> {
-        speaker_positions::channel_mask_to_speaker_positions_descs(self.channel_mask)
+        speaker_positions::channel_mask_to_speaker_positions_descs(self.effective_channel_mask())
     }
 
     /// * Check if this spec is good for encoding PCM format.
+    /// * Without a channel mask, the speaker positions must be guessable from the number of channels.
+    ///   For the many-channel audio like the higher-order ambisonics, use `Some(0)` for the channel mask.
     pub fn verify_for_pcm(&self) -> Result<(), AudioError> {
-        self.guess_channel_mask()?;
+        if self.channels == 0 {
+            return Err(AudioError::InvalidArguments("PCM needs at least one channel".to_owned()));
+        }
+        if self.channel_mask.is_none() {
+            self.guess_channel_mask()?;
+        }
         if self.get_sample_type() == WaveSampleType::Unknown {
             Err(AudioError::InvalidArguments(format!(
                 "PCM doesn't support {} bits per sample {:?}",
@@ -378,9 +829,87 @@ impl Spec {
         }
     }
 
+    /// * Get the duration of `num_frames` audio frames at the sample rate.
+    pub fn duration(&self, num_frames: u64) -> Duration {
+        if self.sample_rate == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(num_frames as f64 / self.sample_rate as f64)
+        }
+    }
+
     /// * Check if the channel mask matches the channel number.
+    /// * No channel mask is always valid, and the zero channel mask (no speaker positions) is valid for any number of channels.
     pub fn is_channel_mask_valid(&self) -> bool {
-        speaker_positions::is_channel_mask_valid(self.channels, self.channel_mask)
+        self.channel_layout().is_none_or(|layout| layout.is_valid_for(self.channels))
+    }
+
+    /// * Check if this spec can be encoded in `format`, the `WaveWriter` checks it before creating the encoder.
+    /// * Checks the bit depth against the sample format, the channels against the maximum channels of the encoder,
+    ///   the sample rate for the codecs that only take some sample rates, and the channel mask against the channels.
+    pub fn validate(&self, format: &DataFormat) -> Result<(), AudioWriteError> {
+        let invalid = |message: String| Err(AudioWriteError::InvalidArguments(message));
+        let max_channels = format.get_max_channels();
+        if self.channels == 0 || self.channels > max_channels {
+            return invalid(format!("`channels` is {}, {format} supports 1 to {max_channels} channels.", self.channels));
+        }
+        if self.sample_rate == 0 {
+            return invalid(format!("`sample_rate` is 0, {format} needs a positive number of frames per second."));
+        }
+        match format {
+            DataFormat::Pcm | DataFormat::PcmExtensible(_) => {
+                if self.get_sample_type() == WaveSampleType::Unknown {
+                    return invalid(format!(
+                        "`bits_per_sample` is {} with `sample_format` {}, {format} supports 8 bits `UInt`, 16, 24, 32 or 64 bits `Int`, 32 or 64 bits `Float`.",
+                        self.bits_per_sample, self.sample_format
+                    ));
+                }
+            }
+            _ => {
+                if self.sample_format == SampleFormat::Float && !matches!(self.bits_per_sample, 32 | 64) {
+                    return invalid(format!(
+                        "`bits_per_sample` is {} with `sample_format` Float, the floating-point samples are 32 or 64 bits.",
+                        self.bits_per_sample
+                    ));
+                }
+            }
+        }
+        match format {
+            DataFormat::Opus(_) if !OPUS_ALLOWED_SAMPLE_RATES.contains(&self.sample_rate) => {
+                return invalid(format!("`sample_rate` is {}, {format} supports {OPUS_ALLOWED_SAMPLE_RATES:?} Hz.", self.sample_rate));
+            }
+            DataFormat::Mp3(_) if !MP3_ALLOWED_SAMPLE_RATES.contains(&self.sample_rate) => {
+                return invalid(format!("`sample_rate` is {}, {format} supports {MP3_ALLOWED_SAMPLE_RATES:?} Hz.", self.sample_rate));
+            }
+            DataFormat::Mp3(options) => {
+                let channels = match options.channels {
+                    Mp3Channels::Mono => 1,
+                    Mp3Channels::Stereo | Mp3Channels::JointStereo | Mp3Channels::DualChannel => 2,
+                    Mp3Channels::NotSet => self.channels,
+                };
+                if channels != self.channels {
+                    return invalid(format!(
+                        "`channels` is {}, but the MP3 options want {channels} channels for {:?}.",
+                        self.channels, options.channels
+                    ));
+                }
+            }
+            _ => (),
+        }
+        if !self.is_channel_mask_valid() {
+            let layout = self.channel_layout().unwrap_or_default();
+            return invalid(format!(
+                "`channel_mask` is 0x{:08x} of {} speakers, it doesn't match `channels` {}.",
+                layout.mask(), layout.num_channels(), self.channels
+            ));
+        }
+        if self.channel_mask.is_none() && matches!(format, DataFormat::Pcm | DataFormat::PcmExtensible(_)) && self.guess_channel_mask().is_err() {
+            return invalid(format!(
+                "`channel_mask` is None, the speaker positions of {} channels can't be guessed, give the channel mask, or `Some(0)` for no speaker positions.",
+                self.channels
+            ));
+        }
+        Ok(())
     }
 }
 
@@ -427,8 +956,25 @@ impl<'a> ChunkWriter<'a> {
         })
     }
 
-    /// * At the end of the chunk, the chunk size will be updated since the ownership of `self` moved there, and `drop()` will be called.
-    pub fn end(self) {}
+    /// * Continue a chunk that was written before, e.g. the `data` chunk of the WAV file to append to. The writer must be at the end of the chunk.
+    /// * Like the chunk from `begin()`, the chunk size is updated at the end.
+    pub fn resume(writer: &'a mut dyn Writer, flag: &[u8; 4], chunk_start: u64) -> Self {
+        Self {
+            writer,
+            flag: *flag,
+            pos_of_chunk_len: chunk_start - 4,
+            chunk_start,
+            ended: false,
+        }
+    }
+
+    /// * At the end of the chunk, the chunk size is updated, and the pad byte is written if the chunk size is odd.
+    /// * If the `ChunkWriter` is dropped without `end()`, the chunk size is updated too, but the errors are ignored.
+    pub fn end(mut self) -> Result<(), AudioWriteError> {
+        let result = self.on_drop();
+        self.ended = true;
+        result
+    }
 
     fn on_drop(&mut self) -> Result<(), AudioWriteError> {
         if self.ended {
@@ -472,9 +1018,10 @@ impl<'a> ChunkWriter<'a> {
         self.writer.seek(SeekFrom::Start(self.pos_of_chunk_len))?;
         chunk_size_to_write.write_le(self.writer)?;
         self.writer.seek(SeekFrom::Start(end_of_chunk))?;
-        if end_of_chunk & 1 > 0 {
+        // The pad byte follows the odd-sized chunk, even if the writer didn't start at an even position.
+        if (end_of_chunk - self.chunk_start) & 1 != 0 {
             0u8.write_le(self.writer)?;
-        } // Alignment
+        }
         self.ended = true;
         Ok(())
     }
@@ -492,7 +1039,33 @@ impl<'a> ChunkWriter<'a> {
 
 impl Drop for ChunkWriter<'_> {
     fn drop(&mut self) {
-        self.on_drop().unwrap()
+        // Best effort, call `end()` to get the error.
+        let _ = self.on_drop();
+    }
+}
+
+/// * Where a top-level chunk is in the WAV file, the `WaveWriter` reports the chunks it wrote and the `WaveReader` maps the chunks it found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChunkInfo {
+    /// * The 4-byte identifier of the chunk
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::flag"))]
+    pub flag: [u8; 4],
+
+    /// * File offset of the chunk header
+    pub offset: u64,
+
+    /// * The length of the payload, excludes the 8-byte header and the pad byte. For the `RF64` file, the length of the `data` chunk is from the `ds64` chunk.
+    pub length: u64,
+
+    /// * The chunk was written and then updated later, e.g. the `fmt ` chunk updated with the statistics data. It's always `false` from the `WaveReader`.
+    pub back_patched: bool,
+}
+
+impl ChunkInfo {
+    /// * Compare the place of the chunk, regardless of whether it was back-patched.
+    pub fn same_place(&self, other: &ChunkInfo) -> bool {
+        (self.flag, self.offset, self.length) == (other.flag, other.offset, other.length)
     }
 }
 
@@ -585,6 +1158,7 @@ impl Default for ChunkHeader {
 
 /// * The `fmt ` chunk for the WAV file.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FmtChunk {
     /// * See <https://github.com/tpn/winsdk-10/blob/master/Include/10.0.14393.0/shared/mmreg.h>
     pub format_tag: u16,
@@ -613,6 +1187,7 @@ pub struct FmtChunk {
 
 /// * The `fmt ` chunk extension block
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FmtExtension {
     /// * Extension block size
     pub ext_len: u16,
@@ -623,6 +1198,7 @@ pub struct FmtExtension {
 
 /// * Extension block data
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ExtensionData {
     /// * If the extension block size is zero, here we have `Nodata` for it.
     Nodata,
@@ -636,6 +1212,12 @@ pub enum ExtensionData {
     /// * MP3 specified extension data.
     Mp3(Mp3Data),
 
+    /// * Opus specified extension data, the encoder delay and the padding to trim.
+    Opus(OpusData),
+
+    /// * The `OpusHead` of the Ogg Opus, some other tools put it in the `fmt ` chunk for the Opus in WAV.
+    OpusHead(OpusHead),
+
     /// * Naked Vorbis header data
     Vorbis(VorbisHeaderData),
 
@@ -647,9 +1229,13 @@ pub enum ExtensionData {
 
     /// * Extensible data, it has channel mask, GUID for formats, etc, dedicated for multi-channel PCM format.
     Extensible(ExtensibleData),
+
+    /// * The extension data for the format we don't know, the bytes are kept as is for you to parse.
+    Raw(#[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::base64"))] Vec<u8>),
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AdpcmCoeffSet {
     pub coeff1: i16,
     pub coeff2: i16,
@@ -674,6 +1260,7 @@ impl AdpcmCoeffSet {
 
 /// * The extension data for ADPCM-MS
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AdpcmMsData {
     pub samples_per_block: u16,
     pub num_coeff: u16,
@@ -682,12 +1269,14 @@ pub struct AdpcmMsData {
 
 /// * The extension data for ADPCM-IMA
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AdpcmImaData {
     pub samples_per_block: u16,
 }
 
 /// * The extension data for MP3
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Mp3Data {
     pub id: u16,
     pub flags: u32,
@@ -696,10 +1285,157 @@ pub struct Mp3Data {
     pub codec_delay: u16,
 }
 
+/// * The extension data for Opus
+/// * The Opus encoder delays the audio by `pre_skip` frames, and the encoder pads silence at the end to fill up the last block and to flush the delayed audio out.
+/// * The decoder skips `pre_skip` frames at the beginning and drops `padding` frames at the end to get the original audio.
+/// * If `frames_per_packet` is not zero, every packet in the `data` chunk is prefixed by its size as a little-endian `u16`, the packets vary in size for VBR.
+///   Otherwise, this is the old layout that every packet is exactly `block_align` bytes.
+/// * For 3 to 8 channels, the packets are encoded by the Opus multistream encoder, the channel mapping is recorded like the `OpusHead` of the Ogg Opus.
+///   The mapping table is in the WAV channel order, so the decoder outputs the channels in the order of the channel mask.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OpusData {
+    /// * The encoder delay, in audio frames.
+    pub pre_skip: u16,
+
+    /// * The frames of silence appended at the end, including the frames to flush the encoder delay out.
+    pub padding: u32,
+
+    /// * How many audio frames are encoded in each packet, a.k.a. `dwSamplesPerBlock`. Zero for the old fixed-size block layout.
+    pub frames_per_packet: u32,
+
+    /// * The channel mapping family, zero for mono and stereo. 1 for the Vorbis channel order surround, 255 for the channels without the meanings.
+    pub mapping_family: u8,
+
+    /// * The number of Opus streams in each packet, only for the non-zero mapping family.
+    pub streams: u8,
+
+    /// * How many streams of them are the stereo streams, only for the non-zero mapping family.
+    pub coupled_streams: u8,
+
+    /// * Which decoded stream channel goes to each WAV channel, only for the non-zero mapping family.
+    pub mapping: [u8; 8],
+}
+
+/// * The identification header of the Ogg Opus, see RFC 7845 section 5.1.
+/// * The other tools write it into the `fmt ` chunk or as the first packet of the `data` chunk, followed by the `OpusTags` packet.
+/// * The `pre_skip` is always counted at 48 kHz, and the `mapping` is in the Vorbis channel order, use `to_opus_data()` to get them for the WAV file.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OpusHead {
+    pub version: u8,
+    pub channels: u8,
+
+    /// * The encoder delay in 48 kHz frames.
+    pub pre_skip: u16,
+
+    /// * The sample rate of the original audio, only for information, Opus always decodes at one of the Opus sample rates.
+    pub input_sample_rate: u32,
+
+    /// * The gain to apply in Q7.8 dB.
+    pub output_gain: i16,
+
+    pub mapping_family: u8,
+
+    /// * The streams and the channel mapping are only there for the non-zero mapping family.
+    pub streams: u8,
+    pub coupled_streams: u8,
+    pub mapping: Vec<u8>,
+}
+
+impl OpusHead {
+    pub const MAGIC: [u8; 8] = *b"OpusHead";
+
+    /// * The length of the header in bytes.
+    pub fn sizeof(&self) -> usize {
+        if self.mapping_family == 0 {
+            19
+        } else {
+            21 + self.channels as usize
+        }
+    }
+
+    /// * Parse the `OpusHead` from the bytes, the bytes after the header are ignored.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AudioReadError> {
+        let too_short = || AudioReadError::IncompleteData(format!("The `OpusHead` needs at least 19 bytes, got {}", bytes.len()));
+        if bytes.len() < 19 {
+            return Err(too_short());
+        }
+        if bytes[..8] != Self::MAGIC {
+            return Err(AudioReadError::FormatError(String::from("Not an `OpusHead`")));
+        }
+        let mut ret = Self {
+            version: bytes[8],
+            channels: bytes[9],
+            pre_skip: u16::from_le_bytes([bytes[10], bytes[11]]),
+            input_sample_rate: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            output_gain: i16::from_le_bytes([bytes[16], bytes[17]]),
+            mapping_family: bytes[18],
+            ..Default::default()
+        };
+        if ret.version >> 4 != 0 {
+            return Err(AudioReadError::Unsupported(format!("The `OpusHead` version {} is not supported", ret.version)));
+        }
+        if ret.mapping_family != 0 {
+            if bytes.len() < ret.sizeof() {
+                return Err(AudioReadError::IncompleteData(format!(
+                    "The `OpusHead` of the mapping family {} needs {} bytes, got {}",
+                    ret.mapping_family, ret.sizeof(), bytes.len()
+                )));
+            }
+            ret.streams = bytes[19];
+            ret.coupled_streams = bytes[20];
+            ret.mapping = bytes[21..ret.sizeof()].to_vec();
+        }
+        Ok(ret)
+    }
+
+    pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
+        writer.write_all(&Self::MAGIC)?;
+        self.version.write_le(writer)?;
+        self.channels.write_le(writer)?;
+        self.pre_skip.write_le(writer)?;
+        self.input_sample_rate.write_le(writer)?;
+        self.output_gain.write_le(writer)?;
+        self.mapping_family.write_le(writer)?;
+        if self.mapping_family != 0 {
+            self.streams.write_le(writer)?;
+            self.coupled_streams.write_le(writer)?;
+            writer.write_all(&self.mapping)?;
+        }
+        Ok(())
+    }
+
+    /// * Convert to the `OpusData` of the decoder at `sample_rate`, the padding is unknown and is zero.
+    /// * The `pre_skip` is scaled from 48 kHz, the mapping of the mapping family 1 is reordered from the Vorbis channel order to the order of the `channel_mask`.
+    pub fn to_opus_data(&self, sample_rate: u32, channel_mask: u32) -> OpusData {
+        let mut ret = OpusData {
+            pre_skip: (self.pre_skip as u64 * sample_rate as u64 / 48000) as u16,
+            mapping_family: self.mapping_family,
+            streams: self.streams,
+            coupled_streams: self.coupled_streams,
+            ..Default::default()
+        };
+        let order = match self.mapping_family {
+            1 => opus::get_vorbis_channel_order(self.channels as u16, channel_mask),
+            _ => None,
+        };
+        for (i, stream_channel) in self.mapping.iter().take(8).enumerate() {
+            match &order {
+                Some(order) => ret.mapping[order[i]] = *stream_channel,
+                None => ret.mapping[i] = *stream_channel,
+            }
+        }
+        ret
+    }
+}
+
 /// * The extension data for Naked vorbis audio without Ogg stream encapsulation
 #[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct VorbisHeaderData {
     /// The header for the Vorbis audio
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::bytes_len"))]
     pub header: Vec<u8>,
 }
 
@@ -736,6 +1472,7 @@ impl Debug for VorbisHeaderData {
 
 /// * The extension data for OggVorbis
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct OggVorbisData {
     /// * The codec version. I'm coding this thing at 2025/5/6, so this filed for our encoded WAV file should be 0x20250506
     pub codec_version: u32,
@@ -743,6 +1480,10 @@ pub struct OggVorbisData {
     /// * The `libvorbis` version, our `rustwav` depends on `vorbis_rs 0.5.5`, which uses `vorbis-sys`, which uses `libvorbis 1.3.7 20200704`
     /// * So this field must be 0x20200704 for our encoded WAV file.
     pub vorbis_version: u32,
+
+    /// * The bitrate strategy of the encoder. Without the header in the file (`OggVorbisMode::HaveNoCodebookHeader`), the decoder regenerates the header by an encoder with the same strategy.
+    /// * `None` for the files written before it was recorded, their encoder used the default strategy of `vorbis_rs`.
+    pub bitrate: Option<OggVorbisBitrateStrategy>,
 }
 
 impl Debug for OggVorbisData {
@@ -750,12 +1491,14 @@ impl Debug for OggVorbisData {
         f.debug_struct("OggVorbisWithHeaderData")
         .field("codec_version", &format_args!("{:x}/{:x}/{:x}", self.codec_version >> 16, (self.codec_version >> 8) & 0xFF, self.codec_version & 0xFF))
         .field("vorbis_version", &format_args!("{:x}/{:x}/{:x}", self.vorbis_version >> 16, (self.vorbis_version >> 8) & 0xFF, self.vorbis_version & 0xFF))
+        .field("bitrate", &self.bitrate)
         .finish()
     }
 }
 
 /// * The another extension data for OggVorbis
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct OggVorbisWithHeaderData {
     /// * The codec version. I'm coding this thing at 2025/5/6, so this filed for our encoded WAV file should be 0x20250506
     pub codec_version: u32,
@@ -765,6 +1508,7 @@ pub struct OggVorbisWithHeaderData {
     pub vorbis_version: u32,
 
     /// * The OggVorbis header data
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::bytes_len"))]
     pub header: Vec<u8>,
 }
 
@@ -780,6 +1524,7 @@ impl Debug for OggVorbisWithHeaderData {
 
 /// * The extension data for extensible.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ExtensibleData {
     /// * Valid bits per sample
     pub valid_bits_per_sample: u16,
@@ -805,6 +1550,10 @@ impl FmtChunk {
     }
 
     pub fn read(reader: &mut impl Reader, chunk_size: u32) -> Result<Self, AudioReadError> {
+        if chunk_size < 16 {
+            return Err(AudioReadError::IncompleteData(String::from("The `fmt ` chunk is too small"))
+                .expected_found("at least 16 bytes", &format!("{chunk_size} bytes")));
+        }
         let mut ret = FmtChunk {
             format_tag: u16::read_le(reader)?,
             channels: u16::read_le(reader)?,
@@ -849,8 +1598,8 @@ impl FmtChunk {
                     match &extension.data {
                         ExtensionData::Extensible(extensible) => {
                             match extensible.sub_format {
-                                GUID_PCM_FORMAT => Int,
-                                GUID_IEEE_FLOAT_FORMAT => Float,
+                                GUID_PCM_FORMAT | GUID_AMBISONIC_B_FORMAT_PCM => Int,
+                                GUID_IEEE_FLOAT_FORMAT | GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT => Float,
                                 _ => Unknown, // Let the decoders to decide
                             }
                         }
@@ -862,6 +1611,19 @@ impl FmtChunk {
                     Int
                 }
             }
+            (0xFFFF, bits_per_sample) => {
+                // The development format tag, the real format is identified by the extensible GUID if it has one.
+                if let Some(FmtExtension{data: ExtensionData::Extensible(extensible), ..}) = &self.extension {
+                    match (extensible.sub_format, bits_per_sample) {
+                        (GUID_PCM_FORMAT, 8) => UInt,
+                        (GUID_PCM_FORMAT, _) => Int,
+                        (GUID_IEEE_FLOAT_FORMAT, _) => Float,
+                        _ => Unknown,
+                    }
+                } else {
+                    Unknown
+                }
+            }
             (3, 32) => Float,
             (3, 64) => Float,
             (_, _) => Unknown, // Let the decoders to decide
@@ -871,6 +1633,13 @@ impl FmtChunk {
     pub fn get_sample_type(&self) -> WaveSampleType {
         get_sample_type(self.bits_per_sample, self.get_sample_format())
     }
+
+    /// * Get the full bytes of the `fmt ` chunk as it's stored in the file, for you to handle the format we don't know.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AudioWriteError> {
+        let mut cursor = io::Cursor::new(Vec::<u8>::new());
+        self.write(&mut cursor)?;
+        Ok(cursor.into_inner())
+    }
 }
 
 impl Default for FmtChunk {
@@ -901,6 +1670,17 @@ impl FmtExtension {
         }
     }
 
+    pub fn new_opus(opus: OpusData) -> Self {
+        Self {
+            ext_len: if opus.mapping_family == 0 {
+                OpusData::sizeof()
+            } else {
+                OpusData::sizeof_multistream()
+            } as u16,
+            data: ExtensionData::Opus(opus),
+        }
+    }
+
     pub fn new_vorbis(vorbis: VorbisHeaderData) -> Self {
         Self {
             ext_len: vorbis.sizeof() as u16,
@@ -910,7 +1690,7 @@ impl FmtExtension {
 
     pub fn new_oggvorbis(oggvorbis: OggVorbisData) -> Self {
         Self {
-            ext_len: OggVorbisData::sizeof() as u16,
+            ext_len: oggvorbis.sizeof() as u16,
             data: ExtensionData::OggVorbis(oggvorbis),
         }
     }
@@ -929,6 +1709,21 @@ impl FmtExtension {
         }
     }
 
+    /// * The zero `cbSize` of the non-PCM `WAVEFORMATEX` that has nothing to extend, e.g. the IEEE float format.
+    pub fn new_empty() -> Self {
+        Self {
+            ext_len: 0,
+            data: ExtensionData::Nodata,
+        }
+    }
+
+    pub fn new_raw(data: Vec<u8>) -> Self {
+        Self {
+            ext_len: data.len() as u16,
+            data: ExtensionData::Raw(data),
+        }
+    }
+
     pub fn get_length(&self) -> u16 {
         self.ext_len
     }
@@ -939,14 +1734,7 @@ impl FmtExtension {
             ext_len,
             data: match fmt_chunk.format_tag {
                 FORMAT_TAG_ADPCM_MS => {
-                    if ext_len as usize >= AdpcmMsData::sizeof() {
-                        Ok(ExtensionData::AdpcmMs(AdpcmMsData::read(reader)?))
-                    } else {
-                        Err(AudioReadError::IncompleteData(format!(
-                            "The extension data for ADPCM-MS should be bigger than {}, got {ext_len}",
-                            AdpcmMsData::sizeof()
-                        )))
-                    }
+                    Ok(ExtensionData::AdpcmMs(AdpcmMsData::read_partial(reader, ext_len)?))
                 }
                 FORMAT_TAG_ADPCM_IMA => {
                     if ext_len as usize >= AdpcmImaData::sizeof() {
@@ -955,7 +1743,7 @@ impl FmtExtension {
                         Err(AudioReadError::IncompleteData(format!(
                             "The extension data for ADPCM-IMA should be bigger than {}, got {ext_len}",
                             AdpcmImaData::sizeof()
-                        )))
+                        )).expected_found(&format!("at least {} bytes of extension data", AdpcmImaData::sizeof()), &format!("{ext_len} bytes")))
                     }
                 }
                 FORMAT_TAG_MP3 => {
@@ -965,7 +1753,17 @@ impl FmtExtension {
                         Err(AudioReadError::IncompleteData(format!(
                             "The extension data for Mpeg Layer III should be bigger than {}, got {ext_len}",
                             Mp3Data::sizeof()
-                        )))
+                        )).expected_found(&format!("at least {} bytes of extension data", Mp3Data::sizeof()), &format!("{ext_len} bytes")))
+                    }
+                }
+                FORMAT_TAG_OPUS if ext_len as usize >= OpusData::sizeof_min() => {
+                    // Our own extension data is never as long as the `OpusHead`, and it doesn't start with the magic.
+                    let mut buf = vec![0u8; ext_len as usize];
+                    reader.read_exact(&mut buf)?;
+                    if buf.starts_with(&OpusHead::MAGIC) {
+                        Ok(ExtensionData::OpusHead(OpusHead::parse(&buf)?))
+                    } else {
+                        Ok(ExtensionData::Opus(OpusData::read(&mut io_utils::CursorVecU8::new(buf), ext_len)?))
                     }
                 }
                 FORMAT_TAG_VORBIS => {
@@ -978,17 +1776,17 @@ impl FmtExtension {
                         Err(AudioReadError::IncompleteData(format!(
                             "The extension data for OggVorbis should be bigger than {}, got {ext_len}",
                             OggVorbisWithHeaderData::sizeof_min()
-                        )))
+                        )).expected_found(&format!("at least {} bytes of extension data", OggVorbisWithHeaderData::sizeof_min()), &format!("{ext_len} bytes")))
                     }
                 }
                 FORMAT_TAG_OGG_VORBIS1 | FORMAT_TAG_OGG_VORBIS3 | FORMAT_TAG_OGG_VORBIS1P | FORMAT_TAG_OGG_VORBIS3P => {
-                    if ext_len as usize >= OggVorbisData::sizeof() {
-                        Ok(ExtensionData::OggVorbis(OggVorbisData::read(reader)?))
+                    if ext_len as usize >= OggVorbisData::sizeof_min() {
+                        Ok(ExtensionData::OggVorbis(OggVorbisData::read(reader, ext_len)?))
                     } else {
                         Err(AudioReadError::IncompleteData(format!(
                             "The extension data for OggVorbis should be bigger than {}, got {ext_len}",
-                            OggVorbisData::sizeof()
-                        )))
+                            OggVorbisData::sizeof_min()
+                        )).expected_found(&format!("at least {} bytes of extension data", OggVorbisData::sizeof_min()), &format!("{ext_len} bytes")))
                     }
                 }
                 FORMAT_TAG_EXTENSIBLE => {
@@ -1000,17 +1798,34 @@ impl FmtExtension {
                         Err(AudioReadError::IncompleteData(format!(
                             "The extension data for EXTENSIBLE should be bigger than {}, got {ext_len}",
                             ExtensibleData::sizeof()
-                        )))
+                        )).expected_found(&format!("at least {} bytes of extension data", ExtensibleData::sizeof()), &format!("{ext_len} bytes")))
+                    }
+                }
+                FORMAT_TAG_DEVELOPMENT if ext_len as usize >= ExtensibleData::sizeof() => {
+                    // The experimental codecs often identify themselves by the GUID of the extensible data.
+                    Ok(ExtensionData::Extensible(ExtensibleData::read(reader)?))
+                }
+                _ => {
+                    if ext_len != 0 {
+                        let mut buf = vec![0u8; ext_len as usize];
+                        reader.read_exact(&mut buf)?;
+                        Ok(ExtensionData::Raw(buf))
+                    } else {
+                        Ok(ExtensionData::Nodata)
                     }
                 }
-                _ => Ok(ExtensionData::Nodata),
             }?,
         })
     }
 
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
-        self.ext_len.write_le(writer)?;
-        if self.ext_len != 0 {
+        // The ADPCM-MS extension data read from a short extension is completed by the standard coefficient table, it's always written in full.
+        let ext_len = match &self.data {
+            ExtensionData::AdpcmMs(_) => AdpcmMsData::sizeof() as u16,
+            _ => self.ext_len,
+        };
+        ext_len.write_le(writer)?;
+        if ext_len != 0 {
             match &self.data {
                 ExtensionData::Nodata => Err(AudioWriteError::InvalidArguments(format!(
                     "There should be data in {} bytes to be written, but the data is `Nodata`.",
@@ -1019,10 +1834,13 @@ impl FmtExtension {
                 ExtensionData::AdpcmMs(data) => Ok(data.write(writer)?),
                 ExtensionData::AdpcmIma(data) => Ok(data.write(writer)?),
                 ExtensionData::Mp3(data) => Ok(data.write(writer)?),
+                ExtensionData::Opus(data) => Ok(data.write(writer)?),
+                ExtensionData::OpusHead(data) => Ok(data.write(writer)?),
                 ExtensionData::Vorbis(data) => Ok(data.write(writer)?),
                 ExtensionData::OggVorbis(data) => Ok(data.write(writer)?),
                 ExtensionData::OggVorbisWithHeader(data) => Ok(data.write(writer)?),
                 ExtensionData::Extensible(data) => Ok(data.write(writer)?),
+                ExtensionData::Raw(data) => Ok(writer.write_all(data)?),
             }
         } else {
             Ok(())
@@ -1067,6 +1885,34 @@ impl AdpcmMsData {
         })
     }
 
+    /// * Read the extension data that could be shorter than it should be, some encoders don't write the coefficient table.
+    /// * The missing `samples_per_block` and `num_coeff` are zero, and the missing or incomplete coefficient table is replaced by the standard one.
+    /// * The bytes after the 7 coefficients are skipped.
+    pub fn read_partial(reader: &mut impl Reader, ext_len: u16) -> Result<Self, AudioReadError> {
+        let mut buf = vec![0u8; ext_len as usize];
+        reader.read_exact(&mut buf)?;
+        if buf.len() >= Self::sizeof() {
+            let mut ret = Self::read(&mut io_utils::CursorVecU8::new(buf))?;
+            if ret.num_coeff < 7 {
+                ret.coeffs = Self::new().coeffs;
+            }
+            Ok(ret)
+        } else {
+            let read_u16 = |offset: usize| -> u16 {
+                if buf.len() >= offset + 2 {
+                    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+                } else {
+                    0
+                }
+            };
+            Ok(Self {
+                samples_per_block: read_u16(0),
+                num_coeff: read_u16(2),
+                ..Self::new()
+            })
+        }
+    }
+
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
         self.samples_per_block.write_le(writer)?;
         self.num_coeff.write_le(writer)?;
@@ -1144,28 +1990,113 @@ impl Mp3Data {
     }
 }
 
-impl OggVorbisData {
-    pub fn new() -> Self {
+impl OpusData {
+    pub fn new(pre_skip: u16, frames_per_packet: u32) -> Self {
         Self {
-            codec_version: 0x20250506,
-            vorbis_version: 0x20110424,
+            pre_skip,
+            padding: 0,
+            frames_per_packet,
+            ..Default::default()
         }
     }
 
     pub fn sizeof() -> usize {
-        8
+        10
     }
 
-    pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
-        Ok(Self {
+    /// * The old layout doesn't have the `frames_per_packet` field.
+    pub fn sizeof_min() -> usize {
+        6
+    }
+
+    /// * With the channel mapping for the multistream packets.
+    pub fn sizeof_multistream() -> usize {
+        21
+    }
+
+    pub fn read(reader: &mut impl Reader, ext_len: u16) -> Result<Self, AudioReadError> {
+        let mut ret = Self {
+            pre_skip: u16::read_le(reader)?,
+            padding: u32::read_le(reader)?,
+            ..Default::default()
+        };
+        if ext_len as usize >= Self::sizeof() {
+            ret.frames_per_packet = u32::read_le(reader)?;
+        }
+        if ext_len as usize >= Self::sizeof_multistream() {
+            ret.mapping_family = u8::read_le(reader)?;
+            ret.streams = u8::read_le(reader)?;
+            ret.coupled_streams = u8::read_le(reader)?;
+            reader.read_exact(&mut ret.mapping)?;
+        }
+        Ok(ret)
+    }
+
+    pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
+        self.pre_skip.write_le(writer)?;
+        self.padding.write_le(writer)?;
+        self.frames_per_packet.write_le(writer)?;
+        if self.mapping_family != 0 {
+            self.mapping_family.write_le(writer)?;
+            self.streams.write_le(writer)?;
+            self.coupled_streams.write_le(writer)?;
+            writer.write_all(&self.mapping)?;
+        }
+        Ok(())
+    }
+}
+
+impl OggVorbisData {
+    pub fn new() -> Self {
+        Self {
+            codec_version: 0x20250506,
+            vorbis_version: 0x20110424,
+            bitrate: None,
+        }
+    }
+
+    pub fn sizeof_min() -> usize {
+        8
+    }
+
+    /// * The bitrate strategy takes 8 more bytes: the kind and the value.
+    pub fn sizeof(&self) -> usize {
+        if self.bitrate.is_some() { 16 } else { 8 }
+    }
+
+    pub fn read(reader: &mut impl Reader, ext_len: u16) -> Result<Self, AudioReadError> {
+        let mut ret = Self {
             codec_version: u32::read_le(reader)?,
             vorbis_version: u32::read_le(reader)?,
-        })
+            bitrate: None,
+        };
+        if ext_len >= 16 {
+            let kind = u32::read_le(reader)?;
+            let value = u32::read_le(reader)?;
+            ret.bitrate = match kind {
+                1 => Some(OggVorbisBitrateStrategy::Vbr(value)),
+                2 => Some(OggVorbisBitrateStrategy::QualityVbr(f32::from_bits(value))),
+                3 => Some(OggVorbisBitrateStrategy::Abr(value)),
+                4 => Some(OggVorbisBitrateStrategy::ConstrainedAbr(value)),
+                _ => None,
+            };
+        }
+        Ok(ret)
     }
 
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
         self.codec_version.write_le(writer)?;
         self.vorbis_version.write_le(writer)?;
+        if let Some(bitrate) = self.bitrate {
+            let (kind, value) = match bitrate {
+                OggVorbisBitrateStrategy::Vbr(bitrate) => (1u32, bitrate),
+                OggVorbisBitrateStrategy::QualityVbr(quality) => (2u32, quality.to_bits()),
+                OggVorbisBitrateStrategy::Abr(bitrate) => (3u32, bitrate),
+                OggVorbisBitrateStrategy::ConstrainedAbr(bitrate) => (4u32, bitrate),
+            };
+            kind.write_le(writer)?;
+            value.write_le(writer)?;
+        }
         Ok(())
     }
 }
@@ -1219,12 +2150,13 @@ impl ExtensibleData {
             channel_mask: {
                 let spec = Spec {
                     channels: fmt_chunk.channels,
-                    channel_mask: 0,
+                    channel_mask: None,
                     sample_rate: fmt_chunk.sample_rate,
                     bits_per_sample: fmt_chunk.bits_per_sample,
                     sample_format: SampleFormat::Unknown,
                 };
-                spec.guess_channel_mask()?
+                // The number of channels without speaker positions, e.g. more than 18 channels, gets the zero channel mask.
+                spec.guess_channel_mask().unwrap_or(0)
             },
             sub_format: GUID_PCM_FORMAT,
         })
@@ -1252,6 +2184,7 @@ impl ExtensibleData {
 
 /// See <https://www.recordingblogs.com/wiki/silent-chunk-of-a-wave-file>
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SlntChunk {
     /// * The number of samples through which playback should be silent
     data: u32,
@@ -1267,27 +2200,50 @@ impl SlntChunk {
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
         let cw = ChunkWriter::begin(writer, b"bext")?;
         self.data.write_le(cw.writer)?;
-        Ok(())
+        cw.end()
     }
 }
 
+/// * The `bext` chunk of the Broadcast WAV, see <https://tech.ebu.ch/docs/tech/tech3285.pdf>
+/// * The text fields are fixed-width ASCII, check them by `validate()` before writing, `WaveWriter::set_bext()` does it for you.
+/// * The loudness fields are from the version 2, they are in 1/100 of LUFS, LU or dBTP, e.g. `-2300` is -23 LUFS.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BextChunk {
     pub description: String,
     pub originator: String,
     pub originator_ref: String,
+
+    /// * `yyyy-mm-dd`
     pub origination_date: String,
+
+    /// * `hh:mm:ss`
     pub origination_time: String,
+
+    /// * The first sample count since midnight, see `set_time_reference_from_timecode()`.
     pub time_ref: u64,
     pub version: u16,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::base64"))]
     pub umid: [u8; 64],
-    pub reserved: [u8; 190],
-    pub coding_history: [u8; 1],
+    pub loudness_value: i16,
+    pub loudness_range: i16,
+    pub max_true_peak_level: i16,
+    pub max_momentary_loudness: i16,
+    pub max_short_term_loudness: i16,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserved: [u8; 180],
+
+    /// * The lines of the coding history, each line ends with CR/LF, see `append_coding_history()`.
+    pub coding_history: String,
 }
 
 impl BextChunk {
+    /// * The size of the fields before the coding history.
+    pub const FIXED_SIZE: usize = 602;
+
     pub fn read(
         reader: &mut impl Reader,
+        chunk_size: usize,
         text_encoding: &StringCodecMaps,
     ) -> Result<Self, AudioReadError> {
         let description = read_str(reader, 256, text_encoding)?;
@@ -1298,11 +2254,17 @@ impl BextChunk {
         let time_ref = u64::read_le(reader)?;
         let version = u16::read_le(reader)?;
         let mut umid = [0u8; 64];
-        let mut reserved = [0u8; 190];
-        let mut coding_history = [0u8; 1];
         reader.read_exact(&mut umid)?;
+        let loudness_value = i16::read_le(reader)?;
+        let loudness_range = i16::read_le(reader)?;
+        let max_true_peak_level = i16::read_le(reader)?;
+        let max_momentary_loudness = i16::read_le(reader)?;
+        let max_short_term_loudness = i16::read_le(reader)?;
+        let mut reserved = [0u8; 180];
         reader.read_exact(&mut reserved)?;
-        reader.read_exact(&mut coding_history)?;
+        let coding_history = read_str(reader, chunk_size.saturating_sub(Self::FIXED_SIZE), text_encoding)?
+            .trim_end_matches('\0')
+            .to_string();
         Ok(Self {
             description,
             originator,
@@ -1312,6 +2274,11 @@ impl BextChunk {
             time_ref,
             version,
             umid,
+            loudness_value,
+            loudness_range,
+            max_true_peak_level,
+            max_momentary_loudness,
+            max_short_term_loudness,
             reserved,
             coding_history,
         })
@@ -1331,8 +2298,85 @@ impl BextChunk {
         self.time_ref.write_le(cw.writer)?;
         self.version.write_le(cw.writer)?;
         cw.writer.write_all(&self.umid)?;
+        self.loudness_value.write_le(cw.writer)?;
+        self.loudness_range.write_le(cw.writer)?;
+        self.max_true_peak_level.write_le(cw.writer)?;
+        self.max_momentary_loudness.write_le(cw.writer)?;
+        self.max_short_term_loudness.write_le(cw.writer)?;
         cw.writer.write_all(&self.reserved)?;
-        cw.writer.write_all(&self.coding_history)?;
+        cw.writer.write_all(self.coding_history.as_bytes())?;
+        cw.end()
+    }
+
+    /// * Check the fields against the fixed widths, the text must be ASCII so that the text encoding won't change its length.
+    /// * The date and the time must be in `yyyy-mm-dd` (or `yyyy:mm:dd`) and `hh:mm:ss` if not empty, and every line of the coding history must end with CR/LF.
+    pub fn validate(&self) -> Result<(), AudioWriteError> {
+        let check_text = |name: &str, text: &str, max_len: usize| -> Result<(), AudioWriteError> {
+            if !text.is_ascii() {
+                Err(AudioWriteError::InvalidArguments(format!("The `bext` {name} \"{text}\" must be ASCII.")))
+            } else if text.len() > max_len {
+                Err(AudioWriteError::InvalidArguments(format!("The `bext` {name} \"{text}\" is longer than {max_len} characters.")))
+            } else {
+                Ok(())
+            }
+        };
+        let check_format = |name: &str, text: &str, separators: &[u8], separator_pos: [usize; 2]| -> Result<(), AudioWriteError> {
+            let valid = text.bytes().enumerate().all(|(i, c)| {
+                if separator_pos.contains(&i) {
+                    separators.contains(&c)
+                } else {
+                    c.is_ascii_digit()
+                }
+            });
+            if text.is_empty() || valid {
+                Ok(())
+            } else {
+                Err(AudioWriteError::InvalidArguments(format!("The `bext` {name} \"{text}\" is malformed.")))
+            }
+        };
+        check_text("description", &self.description, 256)?;
+        check_text("originator", &self.originator, 32)?;
+        check_text("originator reference", &self.originator_ref, 32)?;
+        check_text("origination date", &self.origination_date, 10)?;
+        check_text("origination time", &self.origination_time, 8)?;
+        if !self.origination_date.is_empty() && self.origination_date.len() != 10 {
+            return Err(AudioWriteError::InvalidArguments(format!("The `bext` origination date \"{}\" must be `yyyy-mm-dd`.", self.origination_date)));
+        }
+        if !self.origination_time.is_empty() && self.origination_time.len() != 8 {
+            return Err(AudioWriteError::InvalidArguments(format!("The `bext` origination time \"{}\" must be `hh:mm:ss`.", self.origination_time)));
+        }
+        check_format("origination date", &self.origination_date, b"-_:. ", [4, 7])?;
+        check_format("origination time", &self.origination_time, b"-_:. ", [2, 5])?;
+        check_text("coding history", &self.coding_history, u32::MAX as usize - Self::FIXED_SIZE)?;
+        if !self.coding_history.is_empty() && !self.coding_history.ends_with("\r\n") {
+            return Err(AudioWriteError::InvalidArguments("The `bext` coding history must end with CR/LF.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// * Set the `time_ref` from the SMPTE timecode `hh:mm:ss:ff` of the first sample.
+    /// * `fps` is the frame rate of the timecode, e.g. `25.0`, or `30000.0 / 1001.0` for the non-drop-frame 29.97 fps timecode that counts 30 frames per timecode second.
+    pub fn set_time_reference_from_timecode(&mut self, fps: f64, hh: u8, mm: u8, ss: u8, ff: u8, sample_rate: u32) -> Result<(), AudioWriteError> {
+        if !(fps.is_finite() && fps > 0.0) || sample_rate == 0 {
+            return Err(AudioWriteError::InvalidArguments(format!("Invalid frame rate {fps} or sample rate {sample_rate}.")));
+        }
+        let nominal_fps = fps.round().max(1.0) as u64;
+        if hh >= 24 || mm >= 60 || ss >= 60 || ff as u64 >= nominal_fps {
+            return Err(AudioWriteError::InvalidArguments(format!("Invalid timecode {hh:02}:{mm:02}:{ss:02}:{ff:02} at {fps} fps.")));
+        }
+        let frames = ((hh as u64 * 60 + mm as u64) * 60 + ss as u64) * nominal_fps + ff as u64;
+        self.time_ref = (frames as f64 * sample_rate as f64 / fps).round() as u64;
+        Ok(())
+    }
+
+    /// * Append a line to the coding history, e.g. `"A=PCM,F=48000,W=24,M=stereo,T=rustwav"`, the CR/LF is appended for you.
+    /// * The line must be ASCII and must not contain CR or LF itself.
+    pub fn append_coding_history(&mut self, line: &str) -> Result<(), AudioWriteError> {
+        if !line.is_ascii() || line.contains(['\r', '\n']) {
+            return Err(AudioWriteError::InvalidArguments(format!("The coding history line \"{line}\" must be a single line of ASCII.")));
+        }
+        self.coding_history.push_str(line);
+        self.coding_history.push_str("\r\n");
         Ok(())
     }
 }
@@ -1358,6 +2402,11 @@ impl Debug for BextChunk {
                         .join(",")
                 ),
             )
+            .field("loudness_value", &self.loudness_value)
+            .field("loudness_range", &self.loudness_range)
+            .field("max_true_peak_level", &self.max_true_peak_level)
+            .field("max_momentary_loudness", &self.max_momentary_loudness)
+            .field("max_short_term_loudness", &self.max_short_term_loudness)
             .field("reserved", &format_args!("[u8; {}]", self.reserved.len()))
             .field("coding_history", &self.coding_history)
             .finish()
@@ -1375,13 +2424,198 @@ impl Default for BextChunk {
             time_ref: 0,
             version: 0,
             umid: [0u8; 64],
-            reserved: [0u8; 190],
-            coding_history: [0u8; 1],
+            loudness_value: 0,
+            loudness_range: 0,
+            max_true_peak_level: 0,
+            max_momentary_loudness: 0,
+            max_short_term_loudness: 0,
+            reserved: [0u8; 180],
+            coding_history: String::new(),
         }
     }
 }
 
+/// * The `axml` chunk of the Broadcast WAV, it carries the XML metadata e.g. the ADM (Audio Definition Model) document.
+/// * See <https://tech.ebu.ch/docs/tech/tech3285s5.pdf>
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AxmlChunk {
+    pub xml: String,
+}
+
+impl AxmlChunk {
+    pub fn new(xml: &str) -> Self {
+        Self {
+            xml: xml.to_owned(),
+        }
+    }
+
+    pub fn read(
+        reader: &mut impl Reader,
+        chunk_size: usize,
+        text_encoding: &StringCodecMaps,
+    ) -> Result<Self, AudioReadError> {
+        Ok(Self {
+            xml: read_str(reader, chunk_size, text_encoding)?,
+        })
+    }
+
+    pub fn write(
+        &self,
+        writer: &mut dyn Writer,
+        text_encoding: &StringCodecMaps,
+    ) -> Result<(), AudioWriteError> {
+        let mut cw = ChunkWriter::begin(writer, b"axml")?;
+        write_str(&mut cw.writer, &self.xml, text_encoding)?;
+        cw.end()
+    }
+}
+
+/// * The `chna` chunk of the BW64, it allocates the tracks of the audio to the IDs of the ADM document in the `axml` chunk.
+/// * See <https://www.itu.int/rec/R-REC-BS.2088/en>
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChnaChunk {
+    /// * The number of the tracks used in the file
+    pub num_tracks: u16,
+
+    /// * The number of the track UIDs used in the file
+    pub num_uids: u16,
+
+    /// * The unused entries with the zero `track_index` are kept, they are reserved for adding more IDs later.
+    pub audio_ids: Vec<ChnaAudioId>,
+}
+
+/// * An entry of the track UID table of the `chna` chunk. The IDs are fixed-width ASCII strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChnaAudioId {
+    /// * The 1-based track index, zero for the unused entries.
+    pub track_index: u16,
+
+    /// * The `audioTrackUID` e.g. `ATU_00000001`, 12 characters.
+    pub uid: String,
+
+    /// * The `audioTrackFormatID` or the `audioChannelFormatID` e.g. `AT_00010001_01`, 14 characters.
+    pub track_ref: String,
+
+    /// * The `audioPackFormatID` e.g. `AP_00010002`, 11 characters.
+    pub pack_ref: String,
+}
+
+impl ChnaChunk {
+    pub fn new(audio_ids: Vec<ChnaAudioId>) -> Self {
+        let mut ret = Self {
+            num_tracks: 0,
+            num_uids: 0,
+            audio_ids,
+        };
+        ret.update_counts();
+        ret
+    }
+
+    /// * Recount `num_tracks` and `num_uids` from the used entries of `audio_ids`.
+    pub fn update_counts(&mut self) {
+        let used: Vec<&ChnaAudioId> = self.audio_ids.iter().filter(|id| id.track_index != 0).collect();
+        let tracks: BTreeSet<u16> = used.iter().map(|id| id.track_index).collect();
+        self.num_tracks = tracks.len() as u16;
+        self.num_uids = used.len() as u16;
+    }
+
+    pub fn read(reader: &mut impl Reader, chunk_size: usize) -> Result<Self, AudioReadError> {
+        let num_tracks = u16::read_le(reader)?;
+        let num_uids = u16::read_le(reader)?;
+        let num_entries = chunk_size.saturating_sub(4) / ChnaAudioId::sizeof();
+        if num_entries < num_uids as usize {
+            return Err(AudioReadError::IncompleteData(format!(
+                "The `chna` chunk claims {num_uids} UIDs, but has room for only {num_entries} entries."
+            )));
+        }
+        Ok(Self {
+            num_tracks,
+            num_uids,
+            audio_ids: (0..num_entries)
+                .map(|_| -> Result<ChnaAudioId, AudioReadError> { ChnaAudioId::read(reader) })
+                .collect::<Result<Vec<ChnaAudioId>, AudioReadError>>()?,
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
+        let cw = ChunkWriter::begin(writer, b"chna")?;
+        self.num_tracks.write_le(cw.writer)?;
+        self.num_uids.write_le(cw.writer)?;
+        for audio_id in self.audio_ids.iter() {
+            audio_id.write(cw.writer)?;
+        }
+        cw.end()
+    }
+}
+
+impl ChnaAudioId {
+    pub fn new(track_index: u16, uid: &str, track_ref: &str, pack_ref: &str) -> Self {
+        Self {
+            track_index,
+            uid: uid.to_owned(),
+            track_ref: track_ref.to_owned(),
+            pack_ref: pack_ref.to_owned(),
+        }
+    }
+
+    pub fn sizeof() -> usize {
+        40
+    }
+
+    fn read_ascii(reader: &mut impl Reader, width: usize) -> Result<String, AudioReadError> {
+        let mut buf = vec![0u8; width];
+        reader.read_exact(&mut buf)?;
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(width);
+        if !buf[..len].is_ascii() {
+            return Err(AudioReadError::InvalidData(format!(
+                "The ID in the `chna` chunk is not ASCII: {:?}", &buf[..len]
+            )));
+        }
+        Ok(String::from_utf8_lossy(&buf[..len]).to_string())
+    }
+
+    fn write_ascii(writer: &mut dyn Writer, string: &str, width: usize) -> Result<(), AudioWriteError> {
+        if !string.is_ascii() || string.len() > width {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The ID in the `chna` chunk must be ASCII and no longer than {width} characters, got \"{string}\"."
+            )));
+        }
+        let mut buf = vec![0u8; width];
+        buf[..string.len()].copy_from_slice(string.as_bytes());
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
+        let track_index = u16::read_le(reader)?;
+        let uid = Self::read_ascii(reader, 12)?;
+        let track_ref = Self::read_ascii(reader, 14)?;
+        let pack_ref = Self::read_ascii(reader, 11)?;
+        let mut pad = [0u8; 1];
+        reader.read_exact(&mut pad)?;
+        Ok(Self {
+            track_index,
+            uid,
+            track_ref,
+            pack_ref,
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
+        self.track_index.write_le(writer)?;
+        Self::write_ascii(writer, &self.uid, 12)?;
+        Self::write_ascii(writer, &self.track_ref, 14)?;
+        Self::write_ascii(writer, &self.pack_ref, 11)?;
+        writer.write_all(&[0u8])?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SmplChunk {
     pub manufacturer: u32,
     pub product: u32,
@@ -1396,6 +2630,7 @@ pub struct SmplChunk {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SmplSampleLoop {
     pub identifier: u32,
     pub type_: u32,
@@ -1406,6 +2641,46 @@ pub struct SmplSampleLoop {
 }
 
 impl SmplChunk {
+    /// * Create a `smpl` chunk for the sample rate, with the MIDI unity note and the fine tuning.
+    /// * `midi_pitch_fraction`: The fraction of a semitone up from the unity note, `0x80000000` means 1/2 semitone.
+    pub fn new(sample_rate: u32, midi_unity_note: u8, midi_pitch_fraction: u32) -> Self {
+        Self {
+            sample_period: if sample_rate != 0 { 1_000_000_000 / sample_rate } else { 0 },
+            midi_unity_note: midi_unity_note as u32,
+            midi_pitch_fraction,
+            ..Default::default()
+        }
+    }
+
+    /// * Add a sample loop, the frames from `start_frame` to `end_frame` (inclusive) are played repeatedly.
+    /// * `loop_type`: See `SmplSampleLoop::LOOP_FORWARD`, `SmplSampleLoop::LOOP_ALTERNATING`, `SmplSampleLoop::LOOP_BACKWARD`.
+    /// * `play_count`: How many times the loop plays, zero means infinite.
+    pub fn add_loop(&mut self, start_frame: u32, end_frame: u32, loop_type: u32, play_count: u32) -> &mut Self {
+        self.loops.push(SmplSampleLoop {
+            identifier: self.loops.len() as u32,
+            type_: loop_type,
+            start: start_frame,
+            end: end_frame,
+            fraction: 0,
+            play_count,
+        });
+        self.num_sample_loops = self.loops.len() as u32;
+        self
+    }
+
+    /// * Check if all of the loops are inside the audio data of `num_frames` frames.
+    pub fn validate(&self, num_frames: u64) -> Result<(), AudioWriteError> {
+        for l in self.loops.iter() {
+            if l.start > l.end || l.end as u64 >= num_frames {
+                return Err(AudioWriteError::InvalidData(format!(
+                    "The loop {} of the `smpl` chunk is from frame {} to frame {}, but there are {num_frames} frames written.",
+                    l.identifier, l.start, l.end
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
         let mut ret = Self {
             manufacturer: u32::read_le(reader)?,
@@ -1434,16 +2709,20 @@ impl SmplChunk {
         self.midi_pitch_fraction.write_le(cw.writer)?;
         self.smpte_format.write_le(cw.writer)?;
         self.smpte_offset.write_le(cw.writer)?;
-        self.num_sample_loops.write_le(cw.writer)?;
+        (self.loops.len() as u32).write_le(cw.writer)?;
         self.sampler_data.write_le(cw.writer)?;
         for l in self.loops.iter() {
             l.write(cw.writer)?;
         }
-        Ok(())
+        cw.end()
     }
 }
 
 impl SmplSampleLoop {
+    pub const LOOP_FORWARD: u32 = 0;
+    pub const LOOP_ALTERNATING: u32 = 1;
+    pub const LOOP_BACKWARD: u32 = 2;
+
     pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
         Ok(Self {
             identifier: u32::read_le(reader)?,
@@ -1467,6 +2746,7 @@ impl SmplSampleLoop {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InstChunk {
     pub base_note: u8,
     pub detune: u8,
@@ -1499,21 +2779,25 @@ impl InstChunk {
         self.high_note.write_le(cw.writer)?;
         self.low_velocity.write_le(cw.writer)?;
         self.high_velocity.write_le(cw.writer)?;
-        Ok(())
+        cw.end()
     }
 }
 
 /// * See <https://www.recordingblogs.com/wiki/playlist-chunk-of-a-wave-file>
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlstChunk {
     pub playlist_len: u32,
     pub data: Vec<Plst>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Plst {
     pub cue_point_id: u32,
-    pub num_samples: u32,
+
+    /// * Stored in 32 bits by the `plst` chunk, the `cu64` chunk has the value beyond it.
+    pub num_samples: u64,
     pub repeats: u32,
 }
 
@@ -1534,7 +2818,7 @@ impl PlstChunk {
         for data in self.data.iter() {
             data.write(cw.writer)?;
         }
-        Ok(())
+        cw.end()
     }
 
     pub fn build_map(&self) -> BTreeMap<u32, Plst> {
@@ -1549,14 +2833,14 @@ impl Plst {
     pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
         Ok(Self {
             cue_point_id: u32::read_le(reader)?,
-            num_samples: u32::read_le(reader)?,
+            num_samples: u32::read_le(reader)? as u64,
             repeats: u32::read_le(reader)?,
         })
     }
 
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
         self.cue_point_id.write_le(writer)?;
-        self.num_samples.write_le(writer)?;
+        saturate_to_u32(self.num_samples).write_le(writer)?;
         self.repeats.write_le(writer)?;
         Ok(())
     }
@@ -1565,19 +2849,26 @@ impl Plst {
 /// See <https://www.recordingblogs.com/wiki/cue-chunk-of-a-wave-file>
 /// See <https://wavref.til.cafe/chunk/cue/>
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CueChunk {
     pub num_cues: u32,
     pub cue_points: Vec<CuePoint>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CuePoint {
     pub cue_point_id: u32,
-    pub position: u32,
+
+    /// * Stored in 32 bits by the `cue ` chunk, the `cu64` chunk has the value beyond it.
+    pub position: u64,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::flag"))]
     pub data_chunk_id: [u8; 4],
     pub chunk_start: u32,
     pub block_start: u32,
-    pub offset: u32,
+
+    /// * Stored in 32 bits by the `cue ` chunk, the `cu64` chunk has the value beyond it.
+    pub offset: u64,
 }
 
 impl CueChunk {
@@ -1597,7 +2888,7 @@ impl CueChunk {
         for cue_point in self.cue_points.iter() {
             cue_point.write(cw.writer)?;
         }
-        Ok(())
+        cw.end()
     }
 
     pub fn build_map(&self) -> BTreeMap<u32, &CuePoint> {
@@ -1610,43 +2901,285 @@ impl CueChunk {
 
 impl CuePoint {
     pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
+        let cue_point_id = u32::read_le(reader)?;
+        let position = u32::read_le(reader)? as u64;
         let mut data_chunk_id = [0u8; 4];
         reader.read_exact(&mut data_chunk_id)?;
         Ok(Self {
-            cue_point_id: u32::read_le(reader)?,
-            position: u32::read_le(reader)?,
+            cue_point_id,
+            position,
             data_chunk_id,
             chunk_start: u32::read_le(reader)?,
             block_start: u32::read_le(reader)?,
-            offset: u32::read_le(reader)?,
+            offset: u32::read_le(reader)? as u64,
         })
     }
 
     pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
         self.cue_point_id.write_le(writer)?;
-        self.position.write_le(writer)?;
+        saturate_to_u32(self.position).write_le(writer)?;
         writer.write_all(&self.data_chunk_id)?;
         self.chunk_start.write_le(writer)?;
         self.block_start.write_le(writer)?;
-        self.offset.write_le(writer)?;
+        saturate_to_u32(self.offset).write_le(writer)?;
         Ok(())
     }
 }
 
+/// * The 32-bit field of the `cue `, `plst` and `ltxt` chunks for a 64-bit position, `0xFFFFFFFF` if it doesn't fit.
+fn saturate_to_u32(value: u64) -> u32 {
+    value.min(u32::MAX as u64) as u32
+}
+
+/// * The private `cu64` chunk, it has the 64-bit positions of the cue points for the files that have more than `0xFFFFFFFF` frames.
+/// * The `WaveWriter` writes it beside the standard chunks only if some of the positions don't fit in their 32-bit fields, the standard chunks have `0xFFFFFFFF` for them.
+/// * The `WaveReader` puts the positions back to the `cue `, `plst` and `LIST adtl` chunks, so the other readers see the standard chunks and this library sees the full positions.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Cue64Chunk {
+    pub entries: Vec<Cue64Entry>,
+}
+
+/// * The 64-bit positions of a cue point, 36 bytes in the `cu64` chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Cue64Entry {
+    pub cue_point_id: u32,
+
+    /// * The `position` of the `cue ` chunk
+    pub position: u64,
+
+    /// * The `offset` of the `cue ` chunk
+    pub offset: u64,
+
+    /// * The `sample_length` of the `ltxt` chunk, zero if there's no `ltxt` chunk.
+    pub sample_length: u64,
+
+    /// * The `num_samples` of the `plst` chunk, zero if there's no `plst` chunk.
+    pub num_samples: u64,
+}
+
+impl Cue64Chunk {
+    pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
+        let num_entries = u32::read_le(reader)?;
+        Ok(Self {
+            entries: (0..num_entries)
+                .map(|_| -> Result<Cue64Entry, AudioReadError> {
+                    Ok(Cue64Entry {
+                        cue_point_id: u32::read_le(reader)?,
+                        position: u64::read_le(reader)?,
+                        offset: u64::read_le(reader)?,
+                        sample_length: u64::read_le(reader)?,
+                        num_samples: u64::read_le(reader)?,
+                    })
+                })
+                .collect::<Result<Vec<Cue64Entry>, AudioReadError>>()?,
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn Writer) -> Result<(), AudioWriteError> {
+        let cw = ChunkWriter::begin(writer, b"cu64")?;
+        (self.entries.len() as u32).write_le(cw.writer)?;
+        for entry in self.entries.iter() {
+            entry.cue_point_id.write_le(cw.writer)?;
+            entry.position.write_le(cw.writer)?;
+            entry.offset.write_le(cw.writer)?;
+            entry.sample_length.write_le(cw.writer)?;
+            entry.num_samples.write_le(cw.writer)?;
+        }
+        cw.end()
+    }
+
+    /// * Collect the 64-bit positions of every cue point from the chunks.
+    /// * Returns `None` if all of them fit in the standard chunks, then the `cu64` chunk isn't needed.
+    pub fn collect(
+        cue_chunk: &CueChunk,
+        adtl_chunks: Option<&BTreeMap<u32, Vec<AdtlChunk>>>,
+        plst_chunk: Option<&PlstChunk>,
+    ) -> Option<Self> {
+        let plstmap = plst_chunk.map(|plst| plst.build_map()).unwrap_or_default();
+        let entries: Vec<Cue64Entry> = cue_chunk
+            .cue_points
+            .iter()
+            .map(|cue| Cue64Entry {
+                cue_point_id: cue.cue_point_id,
+                position: cue.position,
+                offset: cue.offset,
+                sample_length: adtl_chunks
+                    .and_then(|adtl| adtl.get(&cue.cue_point_id))
+                    .and_then(|adtls| adtls.iter().find_map(|adtl| match adtl {
+                        AdtlChunk::Ltxt(ltxt) => Some(ltxt.sample_length),
+                        _ => None,
+                    }))
+                    .unwrap_or(0),
+                num_samples: plstmap.get(&cue.cue_point_id).map(|plst| plst.num_samples).unwrap_or(0),
+            })
+            .collect();
+        let fits = |value: u64| value <= u32::MAX as u64;
+        entries
+            .iter()
+            .any(|entry| !(fits(entry.position) && fits(entry.offset) && fits(entry.sample_length) && fits(entry.num_samples)))
+            .then_some(Self { entries })
+    }
+
+    /// * Put the 64-bit positions back to the chunks that were read with the 32-bit fields.
+    /// * A field is restored only if the standard chunk still has the saturated value of it, the cue points edited by the software that doesn't know the `cu64` chunk are left as they are.
+    pub fn apply(
+        &self,
+        cue_chunk: &mut CueChunk,
+        adtl_chunks: Option<&mut BTreeMap<u32, Vec<AdtlChunk>>>,
+        plst_chunk: Option<&mut PlstChunk>,
+    ) {
+        fn restore(field: &mut u64, wide: u64) {
+            if *field == saturate_to_u32(wide) as u64 {
+                *field = wide;
+            }
+        }
+        let entries: BTreeMap<u32, &Cue64Entry> = self.entries.iter().map(|entry| (entry.cue_point_id, entry)).collect();
+        for cue in cue_chunk.cue_points.iter_mut() {
+            if let Some(entry) = entries.get(&cue.cue_point_id) {
+                restore(&mut cue.position, entry.position);
+                restore(&mut cue.offset, entry.offset);
+            }
+        }
+        if let Some(adtl_chunks) = adtl_chunks {
+            for (cue_point_id, adtls) in adtl_chunks.iter_mut() {
+                let Some(entry) = entries.get(cue_point_id) else {continue};
+                for adtl in adtls.iter_mut() {
+                    if let AdtlChunk::Ltxt(ltxt) = adtl {
+                        restore(&mut ltxt.sample_length, entry.sample_length);
+                    }
+                }
+            }
+        }
+        if let Some(plst_chunk) = plst_chunk {
+            for plst in plst_chunk.data.iter_mut() {
+                if let Some(entry) = entries.get(&plst.cue_point_id) {
+                    restore(&mut plst.num_samples, entry.num_samples);
+                }
+            }
+        }
+    }
+}
+
+/// * What the `WaveWriter` does if the positions of the cue points don't fit in the 32-bit fields of the `cue `, `plst` and `ltxt` chunks.
+/// * The positions that fit are always written as the standard chunks only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CuePositionPolicy {
+    /// * Write the standard chunks with `0xFFFFFFFF` for the positions that don't fit, and the `cu64` chunk with the full positions.
+    #[default]
+    WithCue64,
+
+    /// * Refuse to write the cue points, finalizing fails with `AudioWriteError::InvalidData`.
+    Refuse,
+}
+
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ListChunk {
-    Info(BTreeMap<String, String>),
-    Adtl(BTreeMap<u32, AdtlChunk>),
+    Info(InfoDict),
+    /// * The `labl`, `note`, `ltxt`, `file` chunks of each cue point ID.
+    Adtl(BTreeMap<u32, Vec<AdtlChunk>>),
 }
 
 impl Default for ListChunk {
     fn default() -> Self {
-        Self::Info(BTreeMap::<String, String>::new())
+        Self::Info(InfoDict::new())
+    }
+}
+
+/// * The key-value pairs of the `LIST INFO` chunk, in the order they were read or inserted.
+/// * Every key is kept verbatim, including the vendor-specific keys and the lowercase keys, so the chunk is written back as it was read unless it's edited.
+/// * `get()` falls back to the key in other cases, e.g. `get("IART")` finds the value of `iart`.
+#[derive(Debug, Clone, Default, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InfoDict {
+    entries: Vec<(String, String)>,
+}
+
+impl InfoDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * Get the value of the key, if there's no such key, the key in other cases, e.g. the lowercase one.
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.entries.iter().find(|(k, _)| k == key)
+            .or_else(|| self.entries.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)))
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// * Set the value of the key, an existing key keeps its position, a new key is appended. Returns the old value.
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, old_value)) => Some(std::mem::replace(old_value, value)),
+            None => {
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// * Remove the key and return its value, the order of the others stays.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+}
+
+impl FromIterator<(String, String)> for InfoDict {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        let mut dict = Self::new();
+        dict.extend(iter);
+        dict
+    }
+}
+
+impl Extend<(String, String)> for InfoDict {
+    fn extend<T: IntoIterator<Item = (String, String)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<const N: usize> From<[(String, String); N]> for InfoDict {
+    fn from(entries: [(String, String); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl IntoIterator for InfoDict {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
     }
 }
 
 /// See <https://wavref.til.cafe/chunk/adtl/>
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AdtlChunk {
     Labl(LablChunk),
     Note(NoteChunk),
@@ -1661,21 +3194,26 @@ impl Default for AdtlChunk {
 }
 
 #[derive(Debug, Clone, Default, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LablChunk {
     pub cue_point_id: u32,
     pub data: String,
 }
 
 #[derive(Debug, Clone, Default, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NoteChunk {
     pub cue_point_id: u32,
     pub data: String,
 }
 
 #[derive(Debug, Clone, Default, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LtxtChunk {
     pub cue_point_id: u32,
-    pub sample_length: u32,
+
+    /// * Stored in 32 bits by the `ltxt` chunk, the `cu64` chunk has the value beyond it.
+    pub sample_length: u64,
     pub purpose_id: String,
     pub country: u16,
     pub language: u16,
@@ -1685,9 +3223,11 @@ pub struct LtxtChunk {
 }
 
 #[derive(Clone, Default, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FileChunk {
     pub cue_point_id: u32,
     pub media_type: u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::bytes_len"))]
     pub file_data: Vec<u8>,
 }
 
@@ -1719,7 +3259,7 @@ impl AdtlChunk {
             b"ltxt" => {
                 let mut ltxt = LtxtChunk {
                     cue_point_id: u32::read_le(reader)?,
-                    sample_length: u32::read_le(reader)?,
+                    sample_length: u32::read_le(reader)? as u64,
                     purpose_id: read_str(reader, 4, text_encoding)?,
                     country: u16::read_le(reader)?,
                     language: u16::read_le(reader)?,
@@ -1772,28 +3312,32 @@ impl AdtlChunk {
                 let cw = ChunkWriter::begin(writer, b"labl")?;
                 labl.cue_point_id.write_le(cw.writer)?;
                 write_str(cw.writer, &to_sz(&labl.data), text_encoding)?;
+                cw.end()?;
             }
             Self::Note(note) => {
                 let cw = ChunkWriter::begin(writer, b"note")?;
                 note.cue_point_id.write_le(cw.writer)?;
                 write_str(cw.writer, &to_sz(&note.data), text_encoding)?;
+                cw.end()?;
             }
             Self::Ltxt(ltxt) => {
                 let cw = ChunkWriter::begin(writer, b"ltxt")?;
                 ltxt.cue_point_id.write_le(cw.writer)?;
-                ltxt.sample_length.write_le(cw.writer)?;
+                saturate_to_u32(ltxt.sample_length).write_le(cw.writer)?;
                 write_str_sized(cw.writer, &ltxt.purpose_id, 4, text_encoding)?;
                 ltxt.country.write_le(cw.writer)?;
                 ltxt.language.write_le(cw.writer)?;
                 ltxt.dialect.write_le(cw.writer)?;
                 ltxt.code_page.write_le(cw.writer)?;
                 write_str(cw.writer, &to_sz(&ltxt.data), text_encoding)?;
+                cw.end()?;
             }
             Self::File(file) => {
                 let cw = ChunkWriter::begin(writer, b"file")?;
                 file.cue_point_id.write_le(cw.writer)?;
                 file.media_type.write_le(cw.writer)?;
                 cw.writer.write_all(&file.file_data)?;
+                cw.end()?;
             }
         }
         Ok(())
@@ -1824,17 +3368,11 @@ impl ListChunk {
                 Ok(Self::Info(dict))
             }
             b"adtl" => {
-                let mut adtl_map = BTreeMap::<u32, AdtlChunk>::new();
+                let mut adtl_map = BTreeMap::<u32, Vec<AdtlChunk>>::new();
                 while reader.stream_position()? < end_of_chunk {
+                    // A cue point could have a label, a note, a text, etc. at the same time.
                     let adtl = AdtlChunk::read(reader, text_encoding)?;
-                    let cue_point_id = adtl.get_cue_point_id();
-                    if let Some(dup) = adtl_map.insert(cue_point_id, adtl.clone()) {
-                        // If the chunk point ID duplicates,  the new one will be used to overwrite the old one.
-                        eprintln!(
-                            "Duplicated chunk point ID {cue_point_id} for the `Adtl` data: old is {:?}, and will be overwritten by the new one: {:?}",
-                            dup, adtl
-                        );
-                    }
+                    adtl_map.entry(adtl.get_cue_point_id()).or_default().push(adtl);
                 }
                 Ok(Self::Adtl(adtl_map))
             }
@@ -1849,62 +3387,68 @@ impl ListChunk {
         &self,
         writer: &mut dyn Writer,
         text_encoding: &StringCodecMaps,
+    ) -> Result<(), AudioWriteError> {
+        self.write_with_bom(writer, text_encoding, false)
+    }
+
+    /// * Write the `LIST` chunk, if `utf8_with_bom` is true, the non-ASCII `INFO` values are written as UTF-8 text with a BOM.
+    /// * The ASCII values are the same in every code page, they're written without the BOM for the best compatibility.
+    /// * The `adtl` chunk is always written by `text_encoding`.
+    pub fn write_with_bom(
+        &self,
+        writer: &mut dyn Writer,
+        text_encoding: &StringCodecMaps,
+        utf8_with_bom: bool,
     ) -> Result<(), AudioWriteError> {
         let mut cw = ChunkWriter::begin(writer, b"LIST")?;
         match self {
             Self::Info(dict) => {
                 cw.writer.write_all(b"INFO")?;
-                Self::write_dict(&mut cw.writer, dict, text_encoding)?;
+                Self::write_dict(&mut cw.writer, dict, text_encoding, utf8_with_bom)?;
             }
             Self::Adtl(adtls) => {
                 cw.writer.write_all(b"adtl")?;
-                for (_cue_point_id, adtl) in adtls.iter() {
-                    adtl.write(&mut cw.writer, text_encoding)?;
+                for (_cue_point_id, adtls) in adtls.iter() {
+                    for adtl in adtls.iter() {
+                        adtl.write(&mut cw.writer, text_encoding)?;
+                    }
                 }
             }
         };
-        Ok(())
+        cw.end()
     }
 
     fn read_dict(
         reader: &mut impl Reader,
         end_of_chunk: u64,
         text_encoding: &StringCodecMaps,
-    ) -> Result<BTreeMap<String, String>, AudioReadError> {
+    ) -> Result<InfoDict, AudioReadError> {
         // The INFO chunk consists of multiple key-value pairs for song metadata.
-        // Within its byte size constraints, read all key-value entries.
-        let mut dict = BTreeMap::<String, String>::new();
+        // Within its byte size constraints, read all key-value entries in their order, the repeated keys are kept too.
+        let mut dict = InfoDict::new();
         while reader.stream_position()? < end_of_chunk {
             let key_chunk = ChunkHeader::read(reader)?; // Every chunk's name is a key, its content is the value.
-            let value_str = read_str(reader, key_chunk.size as usize, text_encoding)?;
+            let mut value_bytes = vec![0u8; key_chunk.size as usize];
+            reader.read_exact(&mut value_bytes)?;
+            let value_str = if let Some(utf8_bytes) = value_bytes.strip_prefix(&UTF8_BOM) {
+                // The value was written as UTF-8 with a BOM, decode it as UTF-8 regardless of the code page.
+                String::from_utf8_lossy(utf8_bytes).trim_end_matches('\0').to_string()
+            } else {
+                read_str(&mut io::Cursor::new(value_bytes), key_chunk.size as usize, text_encoding)?
+            };
             let key_str = text_encoding.decode(&key_chunk.flag);
-            dict.insert(key_str, value_str);
+            dict.entries.push((key_str, value_str));
             key_chunk.seek_to_next_chunk(reader)?;
         }
-        // Let's try to store the key in uppercase form, if the INFO chunk provides both uppercase or lowercase, we store both of them.
-        let mut to_be_added = Vec::<(String, String)>::new();
-        for (key, val) in dict.iter() {
-            let key_uppercase = key.to_uppercase();
-            if key_uppercase == *key {
-                // It is uppercase originally.
-                continue;
-            }
-            if dict.contains_key(&key_uppercase) {
-                // The LIST INFO chunk provided the uppercase key, and its value may be different from the lowercase key value, better not to overwrite it.
-                continue;
-            }
-            to_be_added.push((key_uppercase, val.clone()));
-        }
-        for (key_uppercase, val) in to_be_added.into_iter() {
-            dict.insert(key_uppercase, val);
-        }
+        // The lowercase keys are kept as they are, `InfoDict::get()` finds them by the uppercase keys.
         Ok(dict)
     }
 
     fn write_dict(
         writer: &mut dyn Writer,
-        dict: &BTreeMap<String, String>,
+        dict: &InfoDict,
         text_encoding: &StringCodecMaps,
+        utf8_with_bom: bool,
     ) -> Result<(), AudioWriteError> {
         for (key, val) in dict.iter() {
             if key.len() != 4 {
@@ -1917,12 +3461,52 @@ impl ListChunk {
             let cw = ChunkWriter::begin(writer, &flag)?;
             let mut val = val.clone();
             val.push('\0');
-            write_str(cw.writer, &val, text_encoding)?;
+            if utf8_with_bom && !val.is_ascii() {
+                cw.writer.write_all(&UTF8_BOM)?;
+                cw.writer.write_all(val.as_bytes())?;
+            } else {
+                write_str(cw.writer, &val, text_encoding)?;
+            }
+            cw.end()?;
         }
         Ok(())
     }
 }
 
+/// * The BOM of the UTF-8 text, a value in the `LIST INFO` chunk begins with it is decoded as UTF-8 regardless of the code page.
+pub const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// * How the `WaveWriter` writes the text of the `LIST INFO` chunk.
+/// * The policies are combinable, e.g. `[CodePage(maps), MirrorToId3]` writes the `INFO` chunk for the legacy readers, while the `id3 ` chunk keeps the full fidelity text.
+#[derive(Debug)]
+pub enum InfoTextPolicy {
+    /// * Write the non-ASCII values as UTF-8 with a BOM, the readers who recognize the BOM will get the exact text.
+    Utf8WithBom,
+
+    /// * Write the values by the code page of the string codec maps, the characters not in the code page may be lost.
+    CodePage(StringCodecMaps),
+
+    /// * Also write the values into the `id3 ` chunk, the ID3 text frames are Unicode so nothing is lost.
+    /// * Requires the `id3` feature.
+    MirrorToId3,
+}
+
+/// * The `LIST INFO` keys and their corresponding ID3 text frames, used by `InfoTextPolicy::MirrorToId3` and by the metadata lookups.
+/// * The keys not in this table are mirrored as the `TXXX` frames with the key as the description.
+pub const INFO_TO_ID3_FRAMES: [(&str, &str); 11] = [
+    ("IART", "TPE1"),
+    ("ICMT", "COMM"),
+    ("ICOP", "TCOP"),
+    ("ICRD", "TDRC"),
+    ("IENG", "TENC"),
+    ("IGNR", "TCON"),
+    ("INAM", "TIT2"),
+    ("IPRD", "TALB"),
+    ("ISFT", "TSSE"),
+    ("ISRC", "TSRC"),
+    ("ITRK", "TRCK"),
+];
+
 /// See <https://www.recordingblogs.com/wiki/list-chunk-of-a-wave-file>
 pub fn get_list_info_map() -> BTreeMap<&'static str, &'static str> {
     [
@@ -2047,6 +3631,120 @@ impl ListInfo for ListChunk {
     }
 }
 
+/// * The chunk which a value of `Metadata` came from.
+/// * The order of the variants is the precedence: when the sources disagree on a common field, the former wins.
+/// * The ID3 tag comes first as `WaveReader::get_metadata_text()` does, its text is Unicode while the `LIST INFO` text may be decoded from a code page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum MetadataSource {
+    Id3,
+    ListInfo,
+    Bext,
+    Trkn,
+    Acid,
+}
+
+/// * The common fields of `Metadata` and their `LIST INFO` keys.
+pub const METADATA_FIELDS: [(&str, &str); 6] = [
+    ("title", "INAM"),
+    ("artist", "IART"),
+    ("album", "IPRD"),
+    ("date", "ICRD"),
+    ("comment", "ICMT"),
+    ("track", "ITRK"),
+];
+
+/// * The aggregate view of the metadata from the `LIST INFO`, `id3 `, `bext`, `trkn` and `acid` chunks.
+/// * The common fields take the value of the source with the highest precedence, see `MetadataSource`.
+/// * The values that disagree with the common fields are kept in `other` by their source and the field name e.g. `(MetadataSource::Id3, "title")`,
+///   so are the values that don't have a common field, keyed by the `LIST INFO` key, the ID3 frame ID (`TXXX:description` for the user texts) or the chunk field name.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    pub comment: Option<String>,
+    pub track: Option<String>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::map_entries"))]
+    pub other: BTreeMap<(MetadataSource, String), String>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * Get the common field by the name in `METADATA_FIELDS`
+    pub fn get_field(&self, field: &str) -> Option<&String> {
+        match field {
+            "title" => self.title.as_ref(),
+            "artist" => self.artist.as_ref(),
+            "album" => self.album.as_ref(),
+            "date" => self.date.as_ref(),
+            "comment" => self.comment.as_ref(),
+            "track" => self.track.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, field: &str) -> Option<&mut Option<String>> {
+        match field {
+            "title" => Some(&mut self.title),
+            "artist" => Some(&mut self.artist),
+            "album" => Some(&mut self.album),
+            "date" => Some(&mut self.date),
+            "comment" => Some(&mut self.comment),
+            "track" => Some(&mut self.track),
+            _ => None,
+        }
+    }
+
+    /// * Add a value from the source, the sources must be added from the highest precedence to the lowest.
+    /// * If the common field already has a different value, the value goes to `other`. Empty values are ignored.
+    pub fn add(&mut self, source: MetadataSource, field: &str, value: &str) {
+        let value = value.trim_end_matches('\0');
+        if value.is_empty() {
+            return;
+        }
+        match self.field_mut(field) {
+            Some(common @ None) => *common = Some(value.to_owned()),
+            Some(Some(common)) if *common == value => (),
+            _ => {
+                self.other.entry((source, field.to_owned())).or_insert_with(|| value.to_owned());
+            }
+        }
+    }
+
+    /// * Get the value to write to the source: the value kept in `other` for the source, or the common field.
+    pub fn get_for(&self, source: MetadataSource, field: &str) -> Option<&String> {
+        self.other.get(&(source, field.to_owned())).or_else(|| self.get_field(field))
+    }
+
+    /// * Iterate through the values in `other` of the source that don't belong to a common field.
+    pub fn iter_other(&self, source: MetadataSource) -> impl Iterator<Item = (&String, &String)> {
+        self.other.iter().filter_map(move |((src, field), value)| {
+            if *src == source && !METADATA_FIELDS.iter().any(|(common, _)| *common == field.as_str()) {
+                Some((field, value))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// * Build the `LIST INFO` dictionary of the source, the common fields are stored by their `LIST INFO` keys.
+    pub fn to_info_dict(&self, source: MetadataSource) -> InfoDict {
+        let mut dict: InfoDict = self.iter_other(source).map(|(key, value)| (key.clone(), value.clone())).collect();
+        for (field, info_key) in METADATA_FIELDS.iter() {
+            if let Some(value) = self.get_for(source, field) {
+                dict.insert(info_key.to_string(), value.clone());
+            }
+        }
+        dict
+    }
+}
+
 /// See <https://wavref.til.cafe/chunk/cset/>
 #[allow(clippy::zero_prefixed_literal)]
 pub fn get_country_code_map() -> HashMap<u16, &'static str> {
@@ -2163,7 +3861,7 @@ pub struct FullInfoCuePoint {
     pub note: String,
 
     /// * How many samples in the cue point
-    pub sample_length: u32,
+    pub sample_length: u64,
 
     /// * What is the purpose of the cue point
     pub purpose_id: String,
@@ -2184,10 +3882,10 @@ pub struct FullInfoCuePoint {
     pub file_data: Vec<u8>,
 
     /// * Start sample
-    pub start_sample: u32,
+    pub start_sample: u64,
 
     /// * Num samples
-    pub num_samples: u32,
+    pub num_samples: u64,
 
     /// * repeats for playback
     pub repeats: u32,
@@ -2197,7 +3895,7 @@ impl FullInfoCuePoint {
     pub fn new(
         cue_point_id: u32,
         cue_point: &CuePoint,
-        adtl_chunks: &BTreeMap<u32, AdtlChunk>,
+        adtl_chunks: &BTreeMap<u32, Vec<AdtlChunk>>,
         plst: &Option<&Plst>,
         country_code_map: &HashMap<u16, &'static str>,
         dialect_code_map: &HashMap<LanguageDialect, LanguageSpecification>,
@@ -2225,7 +3923,13 @@ impl FullInfoCuePoint {
                 "Lack of `plst` chunk, `num_samples` should be calculated by yourself, and `repeats` remains zero."
             );
         }
-        if let Some(adtl) = adtl_chunks.get(&cue_point_id) {
+        let adtls = adtl_chunks.get(&cue_point_id).map(|adtls| adtls.as_slice()).unwrap_or_default();
+        if adtls.is_empty() {
+            return Err(AudioError::NoSuchData(format!(
+                "ADTL data for cue point ID: {cue_point_id}"
+            )));
+        }
+        for adtl in adtls.iter() {
             match adtl {
                 AdtlChunk::Labl(labl) => ret.label = labl.data.clone(),
                 AdtlChunk::Note(note) => ret.note = note.data.clone(),
@@ -2261,19 +3965,15 @@ impl FullInfoCuePoint {
                     ret.file_data = file.file_data.clone();
                 }
             }
-            Ok(ret)
-        } else {
-            Err(AudioError::NoSuchData(format!(
-                "ADTL data for cue point ID: {cue_point_id}"
-            )))
         }
+        Ok(ret)
     }
 }
 
 /// * Create a fully assembled cue point data from various of chunks in the WAV file.
 pub fn create_full_info_cue_data(
     cue_chunk: &CueChunk,
-    adtl_chunks: &BTreeMap<u32, AdtlChunk>,
+    adtl_chunks: &BTreeMap<u32, Vec<AdtlChunk>>,
     plstchunk: &Option<PlstChunk>,
 ) -> Result<BTreeMap<u32, FullInfoCuePoint>, AudioError> {
     let country_code_map = get_country_code_map();
@@ -2302,19 +4002,210 @@ pub fn create_full_info_cue_data(
         .collect::<Result<BTreeMap<u32, FullInfoCuePoint>, AudioError>>()
 }
 
+/// * Author the cue points of a WAV file, then give it to `WaveWriter::set_cues()`.
+/// * Every cue point gets an ID from `add_cue()`, the ID is shared by its entries in the `cue `, `LIST adtl` and `plst` chunks.
+#[derive(Debug, Clone, Default)]
+pub struct CueBuilder {
+    /// * Whether to write the `plst` chunk for the cue points, the cue points are played in the order of adding them.
+    pub with_playlist: bool,
+    cues: Vec<CueBuilderPoint>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CueBuilderPoint {
+    position: u64,
+    label: String,
+    note: Option<String>,
+    region: Option<(u64, String)>,
+    repeats: u32,
+}
+
+impl CueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * Add a cue point at the frame position with a label, returns the cue point ID.
+    /// * The position beyond `0xFFFFFFFF` is written as the `CuePositionPolicy` of the `WaveWriter` tells.
+    pub fn add_cue(&mut self, position: u64, label: &str) -> u32 {
+        self.cues.push(CueBuilderPoint {
+            position,
+            label: label.to_owned(),
+            repeats: 1,
+            ..Default::default()
+        });
+        self.cues.len() as u32
+    }
+
+    /// * Set the note text of the cue point, it's written as the `note` chunk.
+    pub fn set_note(&mut self, cue_point_id: u32, note: &str) -> Result<(), AudioWriteError> {
+        self.get_cue_mut(cue_point_id)?.note = Some(note.to_owned());
+        Ok(())
+    }
+
+    /// * Make the cue point a region of `sample_length` frames with a text, it's written as the `ltxt` chunk.
+    pub fn set_region(&mut self, cue_point_id: u32, sample_length: u64, text: &str) -> Result<(), AudioWriteError> {
+        self.get_cue_mut(cue_point_id)?.region = Some((sample_length, text.to_owned()));
+        Ok(())
+    }
+
+    /// * How many times the cue point plays in the `plst` chunk, the default is 1.
+    pub fn set_repeats(&mut self, cue_point_id: u32, repeats: u32) -> Result<(), AudioWriteError> {
+        self.get_cue_mut(cue_point_id)?.repeats = repeats;
+        Ok(())
+    }
+
+    fn get_cue_mut(&mut self, cue_point_id: u32) -> Result<&mut CueBuilderPoint, AudioWriteError> {
+        let index = (cue_point_id as usize).wrapping_sub(1);
+        self.cues.get_mut(index).ok_or(AudioWriteError::InvalidArguments(format!("No such cue point ID: {cue_point_id}")))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    /// * Check if all of the cue points and their regions are inside the audio data of `num_frames` frames.
+    pub fn validate(&self, num_frames: u64) -> Result<(), AudioWriteError> {
+        for (i, cue) in self.cues.iter().enumerate() {
+            let length = cue.region.as_ref().map(|(length, _)| *length).unwrap_or(0);
+            if cue.position.saturating_add(length) > num_frames {
+                return Err(AudioWriteError::InvalidData(format!(
+                    "The cue point {} is from frame {} with {length} frames, but there are {num_frames} frames written.",
+                    i + 1, cue.position
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// * Build the `cue ` chunk, the `LIST adtl` chunk, and the `plst` chunk if `with_playlist` is true.
+    pub fn build(&self) -> (CueChunk, ListChunk, Option<PlstChunk>) {
+        let mut cue_points = Vec::<CuePoint>::with_capacity(self.cues.len());
+        let mut adtl_map = BTreeMap::<u32, Vec<AdtlChunk>>::new();
+        let mut playlist = Vec::<Plst>::with_capacity(self.cues.len());
+        for (i, cue) in self.cues.iter().enumerate() {
+            let cue_point_id = i as u32 + 1;
+            cue_points.push(CuePoint {
+                cue_point_id,
+                position: cue.position,
+                data_chunk_id: *b"data",
+                chunk_start: 0,
+                block_start: 0,
+                offset: cue.position,
+            });
+            let adtls = adtl_map.entry(cue_point_id).or_default();
+            adtls.push(AdtlChunk::Labl(LablChunk {
+                cue_point_id,
+                data: cue.label.clone(),
+            }));
+            if let Some(note) = &cue.note {
+                adtls.push(AdtlChunk::Note(NoteChunk {
+                    cue_point_id,
+                    data: note.clone(),
+                }));
+            }
+            if let Some((sample_length, text)) = &cue.region {
+                adtls.push(AdtlChunk::Ltxt(LtxtChunk {
+                    cue_point_id,
+                    sample_length: *sample_length,
+                    purpose_id: "rgn ".to_owned(),
+                    data: text.clone(),
+                    ..Default::default()
+                }));
+            }
+            playlist.push(Plst {
+                cue_point_id,
+                num_samples: cue.region.as_ref().map(|(length, _)| *length).unwrap_or(0),
+                repeats: cue.repeats,
+            });
+        }
+        (
+            CueChunk {
+                num_cues: cue_points.len() as u32,
+                cue_points,
+            },
+            ListChunk::Adtl(adtl_map),
+            self.with_playlist.then(|| PlstChunk {
+                playlist_len: playlist.len() as u32,
+                data: playlist,
+            }),
+        )
+    }
+}
+
+/// * The `acid` chunk of the ACIDized WAV file, it tells the loop-based music software how to play the audio in the tempo of the song.
+/// * The chunk is 24 bytes long.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AcidChunk {
+    /// * The combination of `AcidChunk::FLAG_*`
     pub flags: u32,
+
+    /// * The MIDI note number of the root note, `60` is the middle C. Used only if `FLAG_ROOT_NOTE` is set.
     pub root_node: u16,
     pub reserved1: u16,
     pub reserved2: f32,
     pub num_beats: u32,
     pub meter_denominator: u16,
     pub meter_numerator: u16,
+
+    /// * Beats per minute
     pub tempo: f32,
 }
 
 impl AcidChunk {
+    /// * The audio is a one-shot sample, it's not stretched to the tempo or looped.
+    pub const FLAG_ONE_SHOT: u32 = 0x01;
+
+    /// * The `root_node` field is valid.
+    pub const FLAG_ROOT_NOTE: u32 = 0x02;
+
+    /// * The audio is stretched to the tempo of the song.
+    pub const FLAG_STRETCH: u32 = 0x04;
+
+    /// * The audio is played from the disk rather than loaded into the memory.
+    pub const FLAG_DISK_BASED: u32 = 0x08;
+
+    /// * How far the duration of the beats may be from the duration of the audio before `check_duration()` complains, in proportion to the audio.
+    pub const DURATION_TOLERANCE: f64 = 0.25;
+
+    /// * Create the `acid` chunk for a loop of `num_beats` beats at `tempo` beats per minute in 4/4, stretched to the tempo of the song.
+    pub fn for_loop(root_note: u16, num_beats: u32, tempo: f32) -> Self {
+        Self {
+            flags: Self::FLAG_ROOT_NOTE | Self::FLAG_STRETCH,
+            root_node: root_note,
+            num_beats,
+            meter_denominator: 4,
+            meter_numerator: 4,
+            tempo,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        self.flags & Self::FLAG_ONE_SHOT != 0
+    }
+
+    /// * The duration of the beats at the tempo, `None` for the one-shot sample or if the beats or the tempo is unknown.
+    pub fn beats_duration(&self) -> Option<Duration> {
+        (!self.is_one_shot() && self.num_beats > 0 && self.tempo.is_finite() && self.tempo > 0.0)
+            .then(|| Duration::from_secs_f64(self.num_beats as f64 * 60.0 / self.tempo as f64))
+    }
+
+    /// * Check if the beats and the tempo describe the audio of `num_frames` frames at `sample_rate`.
+    /// * Returns the warning message if the durations differ by more than `DURATION_TOLERANCE`.
+    pub fn check_duration(&self, num_frames: u64, sample_rate: u32) -> Option<String> {
+        let beats_duration = self.beats_duration()?.as_secs_f64();
+        if sample_rate == 0 {
+            return None;
+        }
+        let audio_duration = num_frames as f64 / sample_rate as f64;
+        ((beats_duration - audio_duration).abs() > audio_duration * Self::DURATION_TOLERANCE).then(|| format!(
+            "The `acid` chunk says {} beats at {} BPM, which is {beats_duration:.3} seconds, but the audio is {audio_duration:.3} seconds.",
+            self.num_beats, self.tempo
+        ))
+    }
+
     pub fn read(reader: &mut impl Reader) -> Result<Self, AudioReadError> {
         Ok(Self {
             flags: u32::read_le(reader)?,
@@ -2338,11 +4229,12 @@ impl AcidChunk {
         self.meter_denominator.write_le(cw.writer)?;
         self.meter_numerator.write_le(cw.writer)?;
         self.tempo.write_le(cw.writer)?;
-        Ok(())
+        cw.end()
     }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TrknChunk {
     pub track_no: u16,
     pub total_tracks: u16,
@@ -2360,14 +4252,15 @@ impl TrknChunk {
         let cw = ChunkWriter::begin(writer, b"Trkn")?;
         self.track_no.write_le(cw.writer)?;
         self.total_tracks.write_le(cw.writer)?;
-        Ok(())
+        cw.end()
     }
 }
 
 #[derive(Clone, PartialOrd, PartialEq, Ord, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum JunkChunk {
     FullZero(u64),
-    SomeData(Vec<u8>),
+    SomeData(#[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::bytes_len"))] Vec<u8>),
 }
 
 impl JunkChunk {
@@ -2392,7 +4285,7 @@ impl JunkChunk {
             Self::FullZero(size) => cw.writer.write_all(&vec![0u8; *size as usize])?,
             Self::SomeData(data) => cw.writer.write_all(data)?,
         }
-        Ok(())
+        cw.end()
     }
 }
 
@@ -2422,8 +4315,11 @@ impl Default for JunkChunk {
 #[cfg(feature = "id3")]
 #[allow(non_snake_case)]
 pub mod Id3 {
+    use super::{InfoDict, INFO_TO_ID3_FRAMES};
     use crate::errors::{AudioReadError, AudioWriteError, IOErrorInfo};
     use std::io::{Read, Seek, Write};
+    use id3::TagLike;
+    use id3::frame::{Comment, ExtendedText};
     pub type Tag = id3::Tag;
 
     pub fn id3_read<R>(reader: &mut R, _size: usize) -> Result<Tag, AudioReadError>
@@ -2440,6 +4336,67 @@ pub mod Id3 {
         Ok(tag.write_to(writer, tag.version())?)
     }
 
+    /// * Copy the `LIST INFO` values into the ID3 tag, the existing frames of the same keys are replaced.
+    pub fn mirror_info(tag: Option<Tag>, dict: &InfoDict) -> Result<Tag, AudioWriteError> {
+        let mut tag = tag.unwrap_or_default();
+        for (key, val) in dict.iter() {
+            match INFO_TO_ID3_FRAMES.iter().find(|(info_key, _)| info_key == key) {
+                Some((_, "COMM")) => {
+                    tag.remove_comment(Some(""), None);
+                    tag.add_frame(Comment {
+                        lang: "und".to_owned(),
+                        description: String::new(),
+                        text: val.clone(),
+                    });
+                }
+                Some((_, frame_id)) => tag.set_text(*frame_id, val.clone()),
+                None => {
+                    tag.remove_extended_text(Some(key.as_str()), None);
+                    tag.add_frame(ExtendedText {
+                        description: key.clone(),
+                        value: val.clone(),
+                    });
+                }
+            }
+        }
+        Ok(tag)
+    }
+
+    /// * Get the text of the ID3 frame that corresponds to the `LIST INFO` key.
+    pub fn get_info_text(tag: &Tag, key: &str) -> Option<String> {
+        match INFO_TO_ID3_FRAMES.iter().find(|(info_key, _)| *info_key == key) {
+            Some((_, "COMM")) => tag.comments().next().map(|comment| comment.text.clone()),
+            Some((_, frame_id)) => tag.get(frame_id).and_then(|frame| frame.content().text()).map(|text| text.to_owned()),
+            None => tag.extended_texts().find(|ext| ext.description == key).map(|ext| ext.value.clone()),
+        }
+    }
+
+    /// * List the text frames by the frame ID, the user text frames are listed as `TXXX:description`.
+    pub fn get_text_frames(tag: &Tag) -> Vec<(String, String)> {
+        let mut frames: Vec<(String, String)> = tag.frames().filter_map(|frame| {
+            frame.content().text().map(|text| (frame.id().to_owned(), text.to_owned()))
+        }).collect();
+        frames.extend(tag.extended_texts().map(|ext| (format!("TXXX:{}", ext.description), ext.value.clone())));
+        frames
+    }
+
+    /// * Set the text frame by the frame ID, the user text frames are set by `TXXX:description`.
+    pub fn set_text_frame(tag: &mut Tag, frame_id: &str, text: &str) -> Result<(), AudioWriteError> {
+        if let Some(description) = frame_id.strip_prefix("TXXX:") {
+            tag.remove_extended_text(Some(description), None);
+            tag.add_frame(ExtendedText {
+                description: description.to_owned(),
+                value: text.to_owned(),
+            });
+            Ok(())
+        } else if frame_id.len() == 4 && frame_id.starts_with('T') && frame_id.is_ascii() {
+            tag.set_text(frame_id, text);
+            Ok(())
+        } else {
+            Err(AudioWriteError::InvalidArguments(format!("`{frame_id}` is not an ID3 text frame.")))
+        }
+    }
+
     impl From<id3::Error> for AudioReadError {
         fn from(err: id3::Error) -> Self {
             match err.kind {
@@ -2477,6 +4434,7 @@ pub mod Id3 {
 #[cfg(not(feature = "id3"))]
 #[allow(non_snake_case)]
 pub mod Id3 {
+    use super::InfoDict;
     use std::error::Error;
     use std::io::Read;
     use std::vec::Vec;
@@ -2512,6 +4470,30 @@ pub mod Id3 {
         Ok(writer.write_all(&tag.data))
     }
 
+    /// * Can't mirror the `LIST INFO` values without the `id3` feature.
+    pub fn mirror_info(_tag: Option<Tag>, _dict: &InfoDict) -> Result<Tag, AudioWriteError> {
+        Err(AudioWriteError::Unsupported(
+            "Feature \"id3\" was not enabled, can not mirror the `LIST INFO` chunk to the `id3 ` chunk".to_owned(),
+        ))
+    }
+
+    /// * The raw bytes can't be parsed without the `id3` feature.
+    pub fn get_info_text(_tag: &Tag, _key: &str) -> Option<String> {
+        None
+    }
+
+    /// * The raw bytes can't be parsed without the `id3` feature.
+    pub fn get_text_frames(_tag: &Tag) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// * Can't set the frames of the raw bytes without the `id3` feature.
+    pub fn set_text_frame(_tag: &mut Tag, _frame_id: &str, _text: &str) -> Result<(), AudioWriteError> {
+        Err(AudioWriteError::Unsupported(
+            "Feature \"id3\" was not enabled, can not set the frames of the `id3 ` chunk".to_owned(),
+        ))
+    }
+
     impl std::fmt::Debug for Tag {
         fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
             fmt.debug_struct("Tag").finish_non_exhaustive()
@@ -2527,7 +4509,52 @@ impl From<DownmixerError> for AudioError {
     }
 }
 
+pub mod pcm {
+    use super::GUID;
+
+    /// * The encoder options for PCM with the extensible `fmt ` chunk
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub struct PcmEncoderOptions {
+        /// * The `SubFormat` GUID to be written into the extensible data verbatim.
+        /// * If it's `None`, `GUID_PCM_FORMAT` or `GUID_IEEE_FLOAT_FORMAT` is used depending on the sample format.
+        /// * If the first field of the GUID is a format tag of PCM (1) or IEEE float (3), it must match the sample format of the spec.
+        pub sub_format_guid: Option<GUID>,
+    }
+
+    impl PcmEncoderOptions {
+        pub const fn new() -> Self {
+            Self {
+                sub_format_guid: None,
+            }
+        }
+    }
+
+    /// * How the PCM encoder dithers the samples when the sample type of the WAV file has fewer bits than the samples you write,
+    ///   e.g. the `f32` samples or the 24-bit samples to the 16-bit PCM file.
+    /// * Without the dither, the quantization error of the quiet audio follows the signal and sounds like distortion, e.g. on the fading out.
+    ///   The dither adds a little noise before rounding, then the error becomes a steady noise floor.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum DitherMode {
+        /// * Round the samples to the nearest value, the default.
+        #[default]
+        None,
+
+        /// * Add the uniform noise of 1 LSB peak to peak, it removes the distortion but the noise floor still changes with the signal.
+        Rectangular,
+
+        /// * Add the triangular noise of 2 LSB peak to peak (TPDF), the noise floor is steady and doesn't change with the signal.
+        Triangular,
+
+        /// * The triangular noise with the quantization error fed back through a second-order filter,
+        ///   the noise moves to the high frequencies where the ears are less sensitive. The total noise is louder.
+        NoiseShaped,
+    }
+}
+
 pub mod mp3 {
+    /// * The sample rates of MPEG-1, MPEG-2 and MPEG-2.5 layer III.
+    pub const MP3_ALLOWED_SAMPLE_RATES: [u32; 9] = [8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000];
+
     /// * MP3 supports two channels in multiple ways.
     #[derive(Debug, Clone, Copy, PartialEq)]
     #[repr(u8)]
@@ -2642,7 +4669,7 @@ pub mod mp3 {
     }
 
     impl Mp3EncoderOptions {
-        pub fn new() -> Self {
+        pub const fn new() -> Self {
             Self {
                 channels: Mp3Channels::NotSet,
                 quality: Mp3Quality::Best,
@@ -2652,7 +4679,7 @@ pub mod mp3 {
             }
         }
 
-        pub fn new_mono() -> Self {
+        pub const fn new_mono() -> Self {
             Self {
                 channels: Mp3Channels::Mono,
                 quality: Mp3Quality::Best,
@@ -2662,7 +4689,7 @@ pub mod mp3 {
             }
         }
 
-        pub fn new_stereo() -> Self {
+        pub const fn new_stereo() -> Self {
             Self {
                 channels: Mp3Channels::JointStereo,
                 quality: Mp3Quality::Best,
@@ -2690,6 +4717,286 @@ pub mod mp3 {
             Self::new()
         }
     }
+
+    /// * The number of the samples that LAME puts before the audio.
+    pub(crate) const LAME_ENCODER_DELAY: u32 = 576;
+
+    /// * The delay of the MP3 decoder filterbank, the decoders that honor the LAME tag skip these samples too.
+    pub(crate) const MP3_DECODER_DELAY: u32 = 529;
+
+    const MPEG1_BITRATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    /// * The size of the Xing/Info fields we write: the tag, the flags, the number of frames and the number of bytes.
+    const XING_SIZE: usize = 16;
+
+    /// * The size of the LAME tag after the Xing/Info fields.
+    const LAME_TAG_SIZE: usize = 36;
+
+    /// * The header of a MPEG audio layer III frame.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) struct Mp3FrameHeader {
+        pub header: [u8; 4],
+        pub sample_rate: u32,
+        pub bitrate: u32,
+    }
+
+    impl Mp3FrameHeader {
+        /// * Parse the header at the start of `data`, returns `None` if it isn't a layer III frame.
+        pub fn parse(data: &[u8]) -> Option<Self> {
+            if data.len() < 4 || data[0] != 0xFF || data[1] & 0xE0 != 0xE0 || data[1] & 0x06 != 0x02 {
+                return None;
+            }
+            let header = [data[0], data[1], data[2], data[3]];
+            let sample_rates = match (header[1] >> 3) & 3 {
+                0 => [11025, 12000, 8000],
+                2 => [22050, 24000, 16000],
+                3 => [44100, 48000, 32000],
+                _ => return None,
+            };
+            let bitrate_index = (header[2] >> 4) as usize;
+            let sample_rate_index = ((header[2] >> 2) & 3) as usize;
+            if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+                return None;
+            }
+            let mut ret = Self {
+                header,
+                sample_rate: sample_rates[sample_rate_index],
+                bitrate: 0,
+            };
+            ret.bitrate = ret.bitrates()[bitrate_index];
+            Some(ret)
+        }
+
+        pub fn is_mpeg1(&self) -> bool {
+            (self.header[1] >> 3) & 3 == 3
+        }
+
+        pub fn is_mono(&self) -> bool {
+            self.header[3] >> 6 == 3
+        }
+
+        fn bitrates(&self) -> &'static [u32; 15] {
+            if self.is_mpeg1() { &MPEG1_BITRATES } else { &MPEG2_BITRATES }
+        }
+
+        pub fn samples_per_frame(&self) -> u32 {
+            if self.is_mpeg1() { 1152 } else { 576 }
+        }
+
+        pub fn frame_size(&self) -> usize {
+            let padding = ((self.header[2] >> 1) & 1) as usize;
+            (self.samples_per_frame() / 8 * self.bitrate * 1000 / self.sample_rate) as usize + padding
+        }
+
+        /// * The Xing/Info tag is after the side info, and the CRC if the frame has it.
+        fn xing_offset(&self) -> usize {
+            let crc = if self.header[1] & 1 == 0 { 2 } else { 0 };
+            let side_info = match (self.is_mpeg1(), self.is_mono()) {
+                (true, true) => 17,
+                (true, false) => 32,
+                (false, true) => 9,
+                (false, false) => 17,
+            };
+            4 + crc + side_info
+        }
+    }
+
+    /// * The size of the ID3v2 tag at the start of `data`, `None` if there isn't one.
+    pub(crate) fn id3v2_tag_size(data: &[u8]) -> Option<usize> {
+        if data.len() < 10 || &data[0..3] != b"ID3" {
+            return None;
+        }
+        let size = data[6..10].iter().fold(0usize, |size, byte| (size << 7) | (byte & 0x7F) as usize);
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        Some(10 + size + footer)
+    }
+
+    /// * The VBR header in the first frame of the MP3 stream: the Xing/Info header written by LAME and most encoders, or the VBRI header written by the Fraunhofer encoder.
+    /// * The frame isn't audio. It tells the number of the frames, and the seek table to find a position without decoding the frames before it.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct Mp3VbrHeader {
+        /// * The number of the audio frames after this frame, `None` if it isn't in the header.
+        pub num_frames: Option<u32>,
+        pub samples_per_frame: u32,
+        pub sample_rate: u32,
+
+        /// * Is it the Xing or the VBRI header of the VBR stream. The Info header is the same as the Xing header, but for the CBR stream.
+        pub vbr: bool,
+
+        /// * The MP3 frame indices and the byte positions in the stream where they are, in ascending order. It's approximate for the Xing TOC.
+        pub seek_points: Vec<(u64, usize)>,
+
+        /// * The gapless playback info from the LAME tag after the Xing/Info header.
+        pub gapless: Option<Mp3GaplessInfo>,
+    }
+
+    impl Mp3VbrHeader {
+        /// * The number of the audio samples at `sample_rate`, without the delay and the padding if the LAME tag tells them.
+        pub fn total_samples_at(&self, sample_rate: u32) -> Option<u64> {
+            let total_samples = match self.gapless {
+                Some(info) => info.total_samples(),
+                None => self.num_frames.map(|num_frames| num_frames as u64 * self.samples_per_frame as u64),
+            };
+            total_samples.map(|total_samples| total_samples * sample_rate as u64 / self.sample_rate as u64)
+        }
+
+        /// * Find the VBR header in the first frame at the start of `data`, after the ID3v2 tag if there is one.
+        /// * Returns the header and where its frame ends, the audio frames are from there.
+        pub fn parse(data: &[u8]) -> Option<(Self, usize)> {
+            let start = id3v2_tag_size(data).unwrap_or(0);
+            let header = Mp3FrameHeader::parse(data.get(start..)?)?;
+            let frame = data.get(start..start + header.frame_size())?;
+            let end = start + frame.len();
+            let read_be = |pos: usize, size: usize| -> Option<u64> {
+                frame.get(pos..pos + size).map(|bytes| bytes.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64))
+            };
+            let mut ret = Self {
+                num_frames: None,
+                samples_per_frame: header.samples_per_frame(),
+                sample_rate: header.sample_rate,
+                vbr: true,
+                seek_points: Vec::new(),
+                gapless: None,
+            };
+            let mut pos = header.xing_offset();
+            if matches!(frame.get(pos..pos + 4), Some(b"Xing" | b"Info")) {
+                ret.vbr = frame.get(pos..pos + 4) == Some(b"Xing");
+                let flags = read_be(pos + 4, 4)?;
+                pos += 8;
+                let mut num_bytes = None;
+                if flags & 1 != 0 {
+                    ret.num_frames = Some(read_be(pos, 4)? as u32);
+                    pos += 4;
+                }
+                if flags & 2 != 0 {
+                    num_bytes = Some(read_be(pos, 4)?);
+                    pos += 4;
+                }
+
+                // The TOC tells the byte position of each percent of the audio, in 1/256 of the stream.
+                if flags & 4 != 0 {
+                    let toc = frame.get(pos..pos + 100)?;
+                    if let (Some(num_frames), Some(num_bytes)) = (ret.num_frames, num_bytes) {
+                        ret.seek_points = toc.iter().enumerate().map(|(percent, byte)| {
+                            (percent as u64 * num_frames as u64 / 100, start + (*byte as u64 * num_bytes / 256) as usize)
+                        }).collect();
+                    }
+                    pos += 100;
+                }
+
+                // The quality
+                if flags & 8 != 0 {
+                    pos += 4;
+                }
+                if let Some(lame_tag) = frame.get(pos..pos + 24) {
+                    if lame_tag[0] != 0 {
+                        ret.gapless = Some(Mp3GaplessInfo {
+                            num_frames: ret.num_frames,
+                            encoder_delay: ((lame_tag[21] as u32) << 4) | (lame_tag[22] as u32 >> 4),
+                            padding: ((lame_tag[22] as u32 & 0x0F) << 8) | lame_tag[23] as u32,
+                            samples_per_frame: ret.samples_per_frame,
+                            sample_rate: ret.sample_rate,
+                        });
+                    }
+                }
+            } else if frame.get(36..40) == Some(b"VBRI") {
+                // The VBRI header is always 32 bytes after the frame header.
+                ret.num_frames = Some(read_be(50, 4)? as u32);
+                let toc_entries = read_be(54, 2)? as usize;
+                let toc_scale = read_be(56, 2)?;
+                let entry_size = read_be(58, 2)? as usize;
+                let frames_per_entry = read_be(60, 2)?;
+
+                // Each entry of the TOC is the number of bytes of the frames it covers, from the first audio frame.
+                if (1..=4).contains(&entry_size) && frames_per_entry > 0 {
+                    let mut position = end;
+                    ret.seek_points.push((0, position));
+                    for i in 0..toc_entries {
+                        position += (read_be(62 + i * entry_size, entry_size)? * toc_scale) as usize;
+                        ret.seek_points.push(((i as u64 + 1) * frames_per_entry, position));
+                    }
+                }
+            } else {
+                return None;
+            }
+            Some((ret, end))
+        }
+    }
+
+    /// * The gapless playback info from the Xing/Info frame with the LAME tag, the first frame of the MP3 stream.
+    /// * The stream is `encoder_delay` samples of silence, then the audio, then `padding` samples of silence to fill the last frame.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) struct Mp3GaplessInfo {
+        /// * The number of the audio frames after the Xing/Info frame, `None` if it isn't in the tag.
+        pub num_frames: Option<u32>,
+        pub encoder_delay: u32,
+        pub padding: u32,
+        pub samples_per_frame: u32,
+        pub sample_rate: u32,
+    }
+
+    impl Mp3GaplessInfo {
+        /// * The number of the samples of the audio without the delay and the padding.
+        pub fn total_samples(&self) -> Option<u64> {
+            self.num_frames.map(|num_frames| {
+                (num_frames as u64 * self.samples_per_frame as u64).saturating_sub(self.encoder_delay as u64 + self.padding as u64)
+            })
+        }
+
+        /// * Create the Xing/Info frame with the LAME tag. The frame header is from `first_frame`, the first audio frame, with a bitrate big enough for the tag.
+        /// * `num_bytes` is the size of the MP3 stream including this frame, without the ID3 tags.
+        /// * The size of the frame only depends on `first_frame`, so it can be reserved first and rewritten later.
+        pub fn build_frame(&self, first_frame: &Mp3FrameHeader, is_vbr: bool, num_bytes: u32) -> Vec<u8> {
+            let mut header = *first_frame;
+            // No CRC, no padding
+            header.header[1] |= 1;
+            header.header[2] &= !0x02;
+            let xing_offset = header.xing_offset();
+            let min_size = xing_offset + XING_SIZE + LAME_TAG_SIZE;
+            if header.frame_size() < min_size {
+                let bitrates = header.bitrates();
+                let index = (1..bitrates.len()).find(|&i| {
+                    (header.samples_per_frame() / 8 * bitrates[i] * 1000 / header.sample_rate) as usize >= min_size
+                }).unwrap_or(bitrates.len() - 1);
+                header.header[2] = (header.header[2] & 0x0F) | ((index as u8) << 4);
+                header.bitrate = bitrates[index];
+            }
+
+            let mut frame = vec![0u8; header.frame_size()];
+            frame[0..4].copy_from_slice(&header.header);
+            let mut xing = Vec::<u8>::with_capacity(XING_SIZE + LAME_TAG_SIZE);
+            xing.extend(if is_vbr { b"Xing" } else { b"Info" });
+            xing.extend(3u32.to_be_bytes());
+            xing.extend(self.num_frames.unwrap_or(0).to_be_bytes());
+            xing.extend(num_bytes.to_be_bytes());
+
+            // The LAME tag: the version string, the VBR method, the lowpass, the ReplayGain and the encoding flags are left blank.
+            xing.extend(b"LAME     ");
+            xing.push(if is_vbr { 4 } else { 1 });
+            xing.extend([0u8; 10]);
+            xing.push(first_frame.bitrate.min(255) as u8);
+            let encoder_delay = self.encoder_delay.min(0xFFF);
+            let padding = self.padding.min(0xFFF);
+            xing.extend([(encoder_delay >> 4) as u8, (((encoder_delay & 0x0F) << 4) | (padding >> 8)) as u8, padding as u8]);
+            xing.extend([0u8; 4]);
+            xing.extend(num_bytes.to_be_bytes());
+            xing.extend([0u8; 2]);
+            frame[xing_offset..xing_offset + xing.len()].copy_from_slice(&xing);
+
+            // The CRC-16 of the frame up to the LAME tag CRC
+            let crc_pos = xing_offset + xing.len();
+            let crc = frame[..crc_pos].iter().fold(0u16, |mut crc, byte| {
+                crc ^= *byte as u16;
+                for _ in 0..8 {
+                    crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+                }
+                crc
+            });
+            frame[crc_pos..crc_pos + 2].copy_from_slice(&crc.to_be_bytes());
+            frame
+        }
+    }
 }
 
 pub mod opus {
@@ -2745,14 +5052,19 @@ pub mod opus {
         /// * The opus encoder only eats these durations of the samples to encode.
         /// * Longer duration means better quality and compression.
         pub samples_cache_duration: OpusEncoderSampleDuration,
+
+        /// * The encoder takes at most this many frames of the written samples at a time, a huge slice is encoded piece by piece.
+        /// * It caps the memory used to convert and cache the samples, no matter how many samples are written in one call. The default is 16384.
+        pub max_frames_per_write: usize,
     }
 
     impl OpusEncoderOptions {
-        pub fn new() -> Self {
+        pub const fn new() -> Self {
             Self {
                 bitrate: OpusBitrate::Max,
                 encode_vbr: false,
                 samples_cache_duration: OpusEncoderSampleDuration::MilliSec60,
+                max_frames_per_write: 16384,
             }
         }
 
@@ -2784,10 +5096,51 @@ pub mod opus {
             Self::new()
         }
     }
+
+    /// * The most channels that the Opus multistream packets in WAV can have.
+    pub const OPUS_MAX_CHANNELS: u16 = 8;
+
+    const FL: u32 = 0x1;
+    const FR: u32 = 0x2;
+    const FC: u32 = 0x4;
+    const LFE: u32 = 0x8;
+    const BL: u32 = 0x10;
+    const BR: u32 = 0x20;
+    const BC: u32 = 0x100;
+    const SL: u32 = 0x200;
+    const SR: u32 = 0x400;
+
+    /// * The channel order of the Opus channel mapping family 1 is the Vorbis channel order, see RFC 7845 section 5.1.1.2.
+    /// * Find out which WAV channel goes to each Opus channel by the speaker positions of the channel mask, for 3 to 8 channels.
+    /// * Returns `None` if the channel mask doesn't fit the Vorbis channel order, then the channels should go with the mapping family 255.
+    pub fn get_vorbis_channel_order(channels: u16, channel_mask: u32) -> Option<Vec<usize>> {
+        let slots: &[u32] = match channels {
+            3 => &[FL, FC, FR],
+            4 => &[FL, FR, BL | SL, BR | SR],
+            5 => &[FL, FC, FR, BL | SL, BR | SR],
+            6 => &[FL, FC, FR, BL | SL, BR | SR, LFE],
+            7 => &[FL, FC, FR, SL | BL, SR | BR, BC, LFE],
+            8 => &[FL, FC, FR, SL, SR, BL, BR, LFE],
+            _ => return None,
+        };
+
+        // The WAV channels are in the order of the bits of the channel mask.
+        let positions: Vec<u32> = (0..32).map(|i| 1u32 << i).filter(|bit| channel_mask & bit != 0).collect();
+        if positions.len() != channels as usize {
+            return None;
+        }
+        let mut order = Vec::<usize>::with_capacity(channels as usize);
+        for slot in slots.iter() {
+            let index = (0..positions.len()).find(|i| positions[*i] & slot != 0 && !order.contains(i))?;
+            order.push(index);
+        }
+        Some(order)
+    }
 }
 
 pub mod flac {
     use std::collections::BTreeMap;
+    use super::Speaker;
 
     /// * The compression level of the FLAC file
     /// A higher number means less file size. Default compression level is 5
@@ -2828,8 +5181,92 @@ pub mod flac {
 
         /// * How many samples you will put into the encoder, set to zero if you don't know.
         pub total_samples_estimate: u64,
+
+        /// * How many threads to encode the FLAC frames in parallel, `0` or `1` means single-threaded, which is the default.
+        ///   The audio is split into segments of whole FLAC blocks, each segment is encoded by a worker thread, and the frames are written in order.
+        ///   The output is identical to the output of the single-threaded encoding.
+        pub worker_threads: usize,
+    }
+
+    impl FlacEncoderParams {
+        /// * 16-bit stereo 44100 Hz audio with the default compression level 5
+        pub const fn new() -> Self {
+            Self {
+                verify_decoded: false,
+                compression: FlacCompression::Level5,
+                channels: 2,
+                sample_rate: 44100,
+                bits_per_sample: 16,
+                total_samples_estimate: 0,
+                worker_threads: 1,
+            }
+        }
     }
 
+    impl Default for FlacEncoderParams {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// * The Vorbis comment of the channel mask for the channels that are not in the FLAC channel order, the same as the `flac` tool writes.
+    pub const FLAC_CHANNEL_MASK_TAG: &str = "WAVEFORMATEXTENSIBLE_CHANNEL_MASK";
+
+    const FL: u32 = Speaker::FrontLeft.bit();
+    const FR: u32 = Speaker::FrontRight.bit();
+    const FC: u32 = Speaker::FrontCenter.bit();
+    const LFE: u32 = Speaker::LowFrequency.bit();
+    const BL: u32 = Speaker::BackLeft.bit();
+    const BR: u32 = Speaker::BackRight.bit();
+    const BC: u32 = Speaker::BackCenter.bit();
+    const SL: u32 = Speaker::SideLeft.bit();
+    const SR: u32 = Speaker::SideRight.bit();
+
+    /// * The channel mask of the FLAC channel order for the number of channels, see the FLAC format specification, section "Channels bits".
+    /// * The FLAC channel orders are in the order of the bits of these masks, so they are the same as the WAV channel orders of these masks.
+    pub fn get_flac_channel_mask(channels: u16) -> u32 {
+        match channels {
+            1 => FC,
+            2 => FL | FR,
+            3 => FL | FR | FC,
+            4 => FL | FR | BL | BR,
+            5 => FL | FR | FC | BL | BR,
+            6 => FL | FR | FC | LFE | BL | BR,
+            7 => FL | FR | FC | LFE | BC | SL | SR,
+            8 => FL | FR | FC | LFE | BL | BR | SL | SR,
+            _ => 0,
+        }
+    }
+
+    /// * Find out which WAV channel goes to each FLAC channel by the speaker positions of the channel mask, for 3 to 8 channels.
+    /// * The side and the back surround speakers are interchangeable, e.g. the 5.1 with the side surrounds goes to the FLAC 5.1 channel order.
+    /// * Returns `None` if the channel mask doesn't fit the FLAC channel order, then the channels are kept in the WAV channel order with the `FLAC_CHANNEL_MASK_TAG`.
+    pub fn get_flac_channel_order(channels: u16, channel_mask: u32) -> Option<Vec<usize>> {
+        let slots: &[u32] = match channels {
+            3 => &[FL, FR, FC],
+            4 => &[FL, FR, BL | SL, BR | SR],
+            5 => &[FL, FR, FC, BL | SL, BR | SR],
+            6 => &[FL, FR, FC, LFE, BL | SL, BR | SR],
+            7 => &[FL, FR, FC, LFE, BC, SL | BL, SR | BR],
+            8 => &[FL, FR, FC, LFE, BL, BR, SL, SR],
+            _ => return None,
+        };
+
+        // The WAV channels are in the order of the bits of the channel mask.
+        let positions: Vec<u32> = (0..32).map(|i| 1u32 << i).filter(|bit| channel_mask & bit != 0).collect();
+        if positions.len() != channels as usize {
+            return None;
+        }
+        let mut order = Vec::<usize>::with_capacity(channels as usize);
+        for slot in slots.iter() {
+            let index = (0..positions.len()).find(|i| positions[*i] & slot != 0 && !order.contains(i))?;
+            order.push(index);
+        }
+        Some(order)
+    }
+
+    /// * The `LIST INFO` keys that have a FLAC comment, and the FLAC comment names.
+    /// * The mapping goes by this table only, the `LIST INFO` keys not in it, e.g. the vendor-specific ones, are skipped, they stay in the `LIST INFO` chunk.
     pub fn get_listinfo_flacmeta() -> &'static BTreeMap<&'static str, &'static str> {
         use std::sync::OnceLock;
         static LISTINFO_FLACMETA: OnceLock<BTreeMap<&'static str, &'static str>> = OnceLock::new();
@@ -2883,7 +5320,7 @@ pub mod oggvorbis {
     }
 
     /// * OggVorbis encoder parameters, NOTE: Most of the comments or documents were copied from `vorbis_rs`
-    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct OggVorbisEncoderParams {
         /// OggVorbis encoder mode
         pub mode: OggVorbisMode,
@@ -2905,10 +5342,51 @@ pub mod oggvorbis {
         /// * This value controls the tradeoff between Ogg encapsulation overhead and ease of seeking and packet loss concealment.
         /// * By default, it is set to None, which lets the encoder decide.
         pub minimum_page_data_size: Option<u16>,
+
+        /// * The granule position that the audio starts from. The granule position of every audio page is offset by it, the header pages stay at zero.
+        /// * To continue the audio of a previous segment in an Ogg chain, set it to `OggVorbisEncoderWrap::get_final_granule_position()` of the previous segment.
+        /// * It has no effect on `OggVorbisMode::NakedVorbis`, which has no Ogg pages, a nonzero offset is refused there.
+        pub granule_offset: u64,
+
+        /// * The encoder takes at most this many frames of the written samples at a time, a huge slice is encoded piece by piece.
+        /// * It caps the memory used to convert the samples to the waveforms and to hold the pages, no matter how many samples are written in one call. The default is 16384.
+        pub max_frames_per_write: usize,
+    }
+
+    impl OggVorbisEncoderParams {
+        /// * The channels and the sample rate are zero, fill them before use. The bitrate is decided by the encoder.
+        pub const fn new() -> Self {
+            Self {
+                mode: OggVorbisMode::OriginalStreamCompatible,
+                channels: 0,
+                sample_rate: 0,
+                stream_serial: None,
+                bitrate: None,
+                minimum_page_data_size: None,
+                granule_offset: 0,
+                max_frames_per_write: 16384,
+            }
+        }
+
+        /// * Stereo 44100 Hz audio
+        pub const fn new_stereo() -> Self {
+            Self {
+                channels: 2,
+                sample_rate: 44100,
+                ..Self::new()
+            }
+        }
+    }
+
+    impl Default for OggVorbisEncoderParams {
+        fn default() -> Self {
+            Self::new()
+        }
     }
 
     /// * OggVorbis bitrate strategy represents a bitrate management strategy that a OggVorbis encoder can use.
     #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub enum OggVorbisBitrateStrategy {
         /// * Pure VBR quality mode, selected by a target bitrate (in bit/s).
         /// * The bitrate management engine is not enabled.
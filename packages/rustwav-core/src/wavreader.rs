@@ -2,28 +2,44 @@
 #![allow(dead_code)]
 
 use std::{
-    cmp::Ordering,
-    collections::{BTreeMap, BTreeSet},
-    fs::File,
-    io::{BufReader, BufWriter, Read, Seek, SeekFrom},
+    any::{Any, TypeId},
+    cell::{OnceCell, RefCell},
+    cmp::{min, Ordering},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::{self, Debug, Formatter},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
     mem,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufWriter, sync::{Mutex, MutexGuard}};
+
+#[cfg(not(feature = "fs"))]
+use std::sync::Arc;
+
 use xlaw::XLaw;
-use io_utils::{Reader, string_io::*};
+use io_utils::{Reader, CursorVecU8, string_io::*};
 use savagestr::{SavageStringCodecs, StringCodecMaps};
 use filehasher::FileHasher;
 use copiablebuf::CopiableBuffer;
-use crate::SampleType;
-use crate::adpcm::{DecIMA, DecMS, DecYAMAHA};
-use crate::decoders::{AdpcmDecoderWrap, Decoder, ExtensibleDecoder, PcmDecoder, PcmXLawDecoderWrap};
+use crate::{CancelToken, SampleType};
+use crate::adpcm::{AdpcmDecoder, DecIMA, DecIMABits, DecMS, DecYAMAHA};
+use crate::decoders::{AdpcmDecoderWrap, BudgetedDecoder, CustomDecoder, CustomDecoderWrap, Decoder, ExtensibleDecoder, Gsm610DecoderWrap, PcmDecoder, PcmXLawDecoderWrap};
 use crate::wavcore;
+use crate::gsm610;
 use crate::wavcore::ChunkHeader;
-use crate::wavcore::Spec;
+use crate::wavcore::{ChannelLayout, Spec};
 use crate::chunks::*;
-use crate::wavcore::{ExtensionData};
-use crate::errors::{AudioError, AudioReadError};
+use crate::wavcore::{ExtensionData, FmtExtension};
+use crate::levels::{self, LevelStats};
+use crate::validate::{self, ValidationReport};
+use crate::aiff::{AiffChunkHeader, AiffCommChunk, AiffInstChunk, AiffMarker, SampleConversion, read_u32_be};
+use crate::errors::{AudioError, AudioReadError, ChunkContext, DecodeWarning, DecodeWarningKind};
+
+#[cfg(feature = "mmap")]
+use crate::mmap::PcmView;
 
 #[cfg(feature = "mp3dec")]
 use crate::decoders::mp3::Mp3Decoder;
@@ -38,23 +54,383 @@ use crate::decoders::flac_dec::FlacDecoderWrap;
 use crate::decoders::oggvorbis_dec::OggVorbisDecoderWrap;
 
 /// * The data source for the `WaveReader`, currently we have a file reader or a file path.
+/// * The file path needs the `fs` feature, without it, the `WaveReader` reads from the `Reader` over the memory, e.g. a `CursorVecU8`.
 #[derive(Debug)]
 pub enum WaveDataSource {
     Reader(Box<dyn Reader>),
+
+    #[cfg(feature = "fs")]
     Filename(String),
     Unknown,
 }
 
+/// * What the compressed decoders (MP3, Opus, Vorbis) do when the codec fails to decode a packet in the middle of the stream.
+/// * The recovered decoders record a `DecodeWarning` with the byte offset and the codec error, get them by calling `get_warnings()` from the iterators.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeErrorPolicy {
+    /// * Return the codec error, the decoding stops. The MP3 decoder always resyncs after the bad data and doesn't record the warning.
+    #[default]
+    Abort,
+
+    /// * Drop the bad packet and excrete silence of the packet's nominal duration to keep the timing.
+    SkipPacket,
+
+    /// * End the stream cleanly at the last good frame.
+    TruncateStream,
+}
+
+/// * How `WaveReader::looped_frame_iter()` plays the first loop of the `smpl` chunk, as a sampler would play it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopPolicy {
+    /// * Play the loop once, the same as the normal iterator.
+    #[default]
+    Once,
+
+    /// * Play the loop `n` times in total, then play on after the loop. `Repeat(0)` and `Repeat(1)` are the same as `Once`.
+    Repeat(u64),
+
+    /// * Play the loop again and again, stop after the duration. The audio after the loop is never reached.
+    /// * Without a loop, the audio plays once and still stops after the duration if it's longer.
+    ForDuration(Duration),
+}
+
+/// * How `WaveReader::block_iter()` fills the last block when the audio doesn't end at a block boundary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PadMode {
+    /// * The last block is shorter, it only has the valid frames.
+    #[default]
+    None,
+
+    /// * The last block is filled up with silence, which is the midpoint for the unsigned sample types.
+    Zero,
+
+    /// * The last block is filled up with the valid frames reflected about the last frame, e.g. `a b c d` becomes `a b c d c b a b` for 8 frames.
+    /// * It keeps the waveform continuous for the FFT-based processing. A single valid frame is repeated.
+    ReflectLast,
+}
+
+/// * What the `WaveReader` does with a chunk, returned by the visitor of `WaveReader::open_with_visitor()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkAction {
+    /// * Parse the chunk as usual.
+    #[default]
+    Parse,
+
+    /// * Don't parse the chunk, it's still in the `chunk_map()`. The `fmt `, `ds64` and `data` chunks are always parsed.
+    Skip,
+
+    /// * Stop walking the chunks before this chunk, the chunks after it are not read.
+    /// * The reader is usable if the `fmt ` chunk and the `data` chunk were seen, otherwise opening fails.
+    StopEarly,
+}
+
+/// * The options for the `WaveReader` to create the iterators.
+/// * The decode budget is counted for each iterator, protects you from the files that claim to contain hours of audio in a few bytes of highly compressed data.
+/// * When the budget was exceeded, the batch decode methods return `AudioReadError::BudgetExceeded`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReadOptions {
+    /// * Maximum number of frames an iterator is allowed to decode.
+    pub max_decoded_frames: Option<u64>,
+
+    /// * Maximum time an iterator is allowed to spend on decoding, counted from its first decoded frame.
+    pub max_decode_wall_time: Option<Duration>,
+
+    /// * What to do when the codec of the compressed audio fails in the middle of the stream.
+    pub on_decode_error: DecodeErrorPolicy,
+
+    /// * Read the file whose `data` chunk is cut off, e.g. the recording was interrupted.
+    ///   The size of the `data` chunk is clamped to the end of the file and rounded down to whole blocks, check it by `WaveReader::is_truncated()`.
+    /// * If false, opening such a file returns `AudioReadError::IncompleteData`.
+    pub allow_truncated: bool,
+
+    /// * The decoder for a third-party codec, it's used when its format tag and its `SubFormat` GUID if any match the `fmt ` chunk, see `CustomDecoder::matches()`.
+    pub custom_decoder: Option<CustomDecoder>,
+
+    /// * Salvage the file whose `fmt ` chunk is missing or damaged, the `data` chunk is read as the PCM samples of this spec.
+    /// * The `fmt ` chunk of the file is still used if it's readable and describes a format that can be decoded. Otherwise the assumed spec replaces it and `WaveReader::warnings()` notes it.
+    /// * If `None`, opening such a file fails, the default.
+    pub assume_spec: Option<Spec>,
+}
+
+impl ReadOptions {
+    /// * No limits, the default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * The sane defaults to decode files from untrusted input: at most 3 hours of 48 kHz audio, at most 60 seconds of decoding.
+    pub fn untrusted() -> Self {
+        Self {
+            max_decoded_frames: Some(48000 * 60 * 60 * 3),
+            max_decode_wall_time: Some(Duration::from_secs(60)),
+            on_decode_error: DecodeErrorPolicy::Abort,
+            allow_truncated: false,
+            custom_decoder: None,
+            assume_spec: None,
+        }
+    }
+
+    /// * Is there any limit set
+    pub fn has_decode_budget(&self) -> bool {
+        self.max_decoded_frames.is_some() || self.max_decode_wall_time.is_some()
+    }
+}
+
+/// * Whether the compressed audio has the same size for every block or frame, see `StorageInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum BitrateMode {
+    /// * CBR, every block or frame has the same size.
+    Constant,
+
+    /// * VBR, the size of each frame varies with the content.
+    Variable,
+}
+
+/// * How the audio is actually stored in the `data` chunk, see `WaveReader::storage_info()`.
+/// * `spec()` describes what the decoders give you, e.g. 16-bit PCM for an MP3 file. This one describes what's in the file, e.g. "MP3 320 kbps CBR, 44.1 kHz, 2 channels".
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StorageInfo {
+    /// * The `format_tag` of the `fmt ` chunk.
+    pub format_tag: u16,
+
+    /// * The `channels` of the `fmt ` chunk.
+    pub channels: u16,
+
+    /// * The `sample_rate` of the `fmt ` chunk.
+    pub sample_rate: u32,
+
+    /// * The `byte_rate` of the `fmt ` chunk, it's the average for the VBR formats, some encoders leave it zero.
+    pub byte_rate: u32,
+
+    /// * The `block_align` of the `fmt ` chunk.
+    pub block_align: u16,
+
+    /// * The `bits_per_sample` of the `fmt ` chunk. It's the bit depth of the source for FLAC, and often zero for the other compressed formats.
+    pub bits_per_sample: u16,
+
+    /// * The parsed extension data of the `fmt ` chunk, `None` if the `fmt ` chunk has no extension.
+    pub extension: Option<ExtensionData>,
+
+    /// * The name of the codec, e.g. "PCM", "ADPCM-MS", "MP3".
+    pub codec: &'static str,
+
+    /// * The bit depth of the stored samples for PCM, IEEE float and FLAC. `None` for the lossy formats, they don't store the samples by the bits.
+    pub bit_depth: Option<u16>,
+
+    /// * The size of each block in bytes for the formats that store the audio in the fixed-size blocks: PCM, A-law, µ-law, ADPCM and the old Opus layout.
+    pub block_size: Option<u32>,
+
+    /// * How many audio frames each block or each MP3 frame or each Opus packet holds.
+    pub samples_per_block: Option<u32>,
+
+    /// * The average bitrate in bits per second. From the size of the `data` chunk and the duration if the duration is known, otherwise from `byte_rate`.
+    /// * For the CBR MP3, it's the bitrate of the MP3 frames.
+    pub bitrate: Option<u32>,
+
+    /// * `None` if it can't be known without scanning the whole `data` chunk.
+    pub bitrate_mode: Option<BitrateMode>,
+
+    /// * The size of the `data` chunk content in bytes.
+    pub data_length: u64,
+}
+
+impl StorageInfo {
+    fn new(fmt: &FmtChunk, data_chunk: &FileDataSource, fact_data: u64) -> Self {
+        use wavcore::format_tags::*;
+        let (codec, has_bit_depth) = Self::codec_of(fmt);
+        let mut ret = Self {
+            format_tag: fmt.format_tag,
+            channels: fmt.channels,
+            sample_rate: fmt.sample_rate,
+            byte_rate: fmt.byte_rate,
+            block_align: fmt.block_align,
+            bits_per_sample: fmt.bits_per_sample,
+            extension: fmt.extension.as_ref().map(|extension| extension.data.clone()),
+            codec,
+            bit_depth: if has_bit_depth { Some(fmt.bits_per_sample) } else { None },
+            block_size: None,
+            samples_per_block: None,
+            bitrate: None,
+            bitrate_mode: None,
+            data_length: data_chunk.length,
+        };
+        ret.bitrate = match get_total_frames(fmt, data_chunk.length, fact_data) {
+            Some(total_frames) if total_frames > 0 => Some((data_chunk.length as u128 * 8 * fmt.sample_rate as u128 / total_frames as u128) as u32),
+            _ if fmt.byte_rate > 0 => Some(fmt.byte_rate * 8),
+            _ => None,
+        };
+        let block_frames = |frames_per_block: io::Result<usize>| frames_per_block.ok().map(|frames| frames as u32);
+        let block_size = Some(fmt.block_align as u32);
+        match fmt.format_tag {
+            FORMAT_TAG_ADPCM_MS => {
+                ret.block_size = block_size;
+                ret.samples_per_block = block_frames(DecMS::new(fmt).map(|dec| dec.frames_per_block()));
+            }
+            FORMAT_TAG_ADPCM_IMA | FORMAT_TAG_ADPCM_IMA_ => {
+                ret.block_size = block_size;
+                ret.samples_per_block = match fmt.bits_per_sample {
+                    4 => block_frames(DecIMA::new(fmt).map(|dec| dec.frames_per_block())),
+                    _ => block_frames(DecIMABits::new(fmt).map(|dec| dec.frames_per_block())),
+                };
+            }
+            FORMAT_TAG_ADPCM_YAMAHA => {
+                ret.block_size = block_size;
+                ret.samples_per_block = block_frames(DecYAMAHA::new(fmt).map(|dec| dec.frames_per_block()));
+            }
+            FORMAT_TAG_GSM610 => {
+                ret.block_size = block_size;
+                ret.samples_per_block = Some(gsm610::SAMPLES_PER_BLOCK as u32);
+                ret.bitrate_mode = Some(BitrateMode::Constant);
+            }
+            FORMAT_TAG_MP3 => {
+                use wavcore::mp3::{id3v2_tag_size, Mp3FrameHeader, Mp3VbrHeader};
+                if !data_chunk.is_stream() {
+                    if let Ok(head) = peek_mp3_head(data_chunk) {
+                        let frame = id3v2_tag_size(&head).and_then(|tag_size| head.get(tag_size..)).unwrap_or(&head[..]);
+                        if let Some(header) = Mp3FrameHeader::parse(frame) {
+                            ret.samples_per_block = Some(header.samples_per_frame());
+                            match Mp3VbrHeader::parse(&head) {
+                                Some((vbr_header, _)) if vbr_header.vbr => ret.bitrate_mode = Some(BitrateMode::Variable),
+                                Some(_) => {
+                                    ret.bitrate_mode = Some(BitrateMode::Constant);
+                                    ret.bitrate = Some(header.bitrate * 1000);
+                                }
+                                None => ret.bitrate = Some(header.bitrate * 1000),
+                            }
+                        }
+                    }
+                }
+            }
+            FORMAT_TAG_OPUS => match &fmt.extension {
+                Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. }) if opus_data.frames_per_packet > 0 => {
+                    ret.samples_per_block = Some(opus_data.frames_per_packet);
+                }
+                Some(FmtExtension { data: ExtensionData::Opus(_), .. }) if fmt.channels > 0 => {
+                    ret.block_size = block_size;
+                    ret.samples_per_block = Some(fmt.block_align as u32 / fmt.channels as u32);
+                    ret.bitrate_mode = Some(BitrateMode::Constant);
+                }
+                _ => (),
+            },
+            FORMAT_TAG_FLAC => (),
+            _ => {
+                // The uncompressed formats and the companded formats are stored frame by frame.
+                if has_bit_depth || matches!(fmt.format_tag, FORMAT_TAG_ALAW | FORMAT_TAG_MULAW) {
+                    ret.block_size = block_size;
+                    ret.samples_per_block = Some(1);
+                    ret.bitrate_mode = Some(BitrateMode::Constant);
+                }
+            }
+        }
+        ret
+    }
+
+    /// * The name of the codec, and whether `bits_per_sample` is the bit depth of the stored samples.
+    fn codec_of(fmt: &FmtChunk) -> (&'static str, bool) {
+        use wavcore::format_tags::*;
+        use wavcore::guids::*;
+        match fmt.format_tag {
+            FORMAT_TAG_PCM => ("PCM", true),
+            FORMAT_TAG_PCM_IEEE => ("IEEE float", true),
+            FORMAT_TAG_ALAW => ("A-law", false),
+            FORMAT_TAG_MULAW => ("µ-law", false),
+            FORMAT_TAG_ADPCM_MS => ("ADPCM-MS", false),
+            FORMAT_TAG_ADPCM_IMA | FORMAT_TAG_ADPCM_IMA_ => ("ADPCM-IMA", false),
+            FORMAT_TAG_ADPCM_YAMAHA => ("ADPCM-Yamaha", false),
+            FORMAT_TAG_GSM610 => ("GSM 6.10", false),
+            FORMAT_TAG_MP3 => ("MP3", false),
+            FORMAT_TAG_OPUS => ("Opus", false),
+            FORMAT_TAG_VORBIS => ("Vorbis", false),
+            FORMAT_TAG_OGG_VORBIS1 | FORMAT_TAG_OGG_VORBIS2 | FORMAT_TAG_OGG_VORBIS3 |
+            FORMAT_TAG_OGG_VORBIS1P | FORMAT_TAG_OGG_VORBIS2P | FORMAT_TAG_OGG_VORBIS3P => ("OggVorbis", false),
+            FORMAT_TAG_FLAC => ("FLAC", true),
+            FORMAT_TAG_EXTENSIBLE | FORMAT_TAG_DEVELOPMENT => match &fmt.extension {
+                Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. }) => match extensible.sub_format {
+                    GUID_PCM_FORMAT => ("PCM", true),
+                    GUID_IEEE_FLOAT_FORMAT => ("IEEE float", true),
+                    GUID_AMBISONIC_B_FORMAT_PCM => ("Ambisonic B-format PCM", true),
+                    GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT => ("Ambisonic B-format IEEE float", true),
+                    _ => ("Unknown", false),
+                },
+                _ if fmt.format_tag == FORMAT_TAG_EXTENSIBLE => ("PCM", true),
+                _ => ("Unknown", false),
+            },
+            _ => ("Unknown", false),
+        }
+    }
+}
+
+impl fmt::Display for StorageInfo {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.codec)?;
+        if let Some(bit_depth) = self.bit_depth {
+            write!(f, " {bit_depth}-bit")?;
+        }
+        if let Some(bitrate) = self.bitrate {
+            write!(f, " {} kbps", (bitrate + 500) / 1000)?;
+        }
+        match self.bitrate_mode {
+            Some(BitrateMode::Constant) => write!(f, " CBR")?,
+            Some(BitrateMode::Variable) => write!(f, " VBR")?,
+            None => (),
+        }
+        write!(f, ", {} kHz", self.sample_rate as f64 / 1000.0)?;
+        match self.channels {
+            1 => write!(f, ", 1 channel"),
+            channels => write!(f, ", {channels} channels"),
+        }
+    }
+}
+
+/// * The headers and the parsed chunks of a WAV file in one place, see `WaveReader::describe()`.
+/// * With the `serde` feature it serializes, e.g. to JSON for a tool to describe the WAV files. The binary data is serialized by its length or as base64 text.
+/// * The `id3 ` chunk isn't parsed for it, its text goes to `metadata` and the tag with its pictures is described by its size.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FileDescription {
+    pub spec: Spec,
+    pub fmt__chunk: FmtChunk,
+    pub storage_info: StorageInfo,
+    pub fact_data: u64,
+    pub start_frame: u64,
+    pub truncated: bool,
+    pub chunk_map: Vec<ChunkInfo>,
+    pub slnt_chunk: Option<SlntChunk>,
+    pub bext_chunk: Option<BextChunk>,
+    pub smpl_chunk: Option<SmplChunk>,
+    pub inst_chunk: Option<InstChunk>,
+    pub plst_chunk: Option<PlstChunk>,
+    pub trkn_chunk: Option<TrknChunk>,
+    pub cue__chunk: Option<CueChunk>,
+    pub axml_chunk: Option<AxmlChunk>,
+    pub chna_chunk: Option<ChnaChunk>,
+    pub ixml_chunk: Option<String>,
+    pub list_chunk: Vec<ListChunk>,
+    pub acid_chunk: Option<AcidChunk>,
+
+    /// * The size of the `id3 ` chunk, `None` if there's no `id3 ` chunk.
+    pub id3__length: Option<u64>,
+    pub junk_chunks: Vec<JunkChunk>,
+
+    /// * The aggregate view of the metadata, see `WaveReader::metadata()`.
+    pub metadata: Metadata,
+    pub warnings: Vec<DecodeWarning>,
+}
+
 /// * The `WaveReader` is dedicated to reading a WAV file and provides you with samples as you want.
 ///   Usage:
 /// * Open a WAV file
 /// * Get the iterator
 /// * The iterator excretes the PCM samples with the format you specified.
+/// * The AIFF and AIFF-C files are read too, their chunks are presented as the WAV chunks, e.g. the `COMM` chunk is the `fmt ` chunk.
 #[derive(Debug)]
 pub struct WaveReader {
     spec: Spec,
     fmt__chunk: FmtChunk, // fmt chunk must exists
-    fact_data: u64,       // Total samples in the data chunk
+    storage_info: StorageInfo,
+    fact_data: u64,       // Total samples in the data chunk, the `fact` chunk multiplied by the channels
     data_chunk: FileDataSource,
     text_encoding: StringCodecMaps,
     slnt_chunk: Option<SlntChunk>,
@@ -64,12 +440,158 @@ pub struct WaveReader {
     plst_chunk: Option<PlstChunk>,
     trkn_chunk: Option<TrknChunk>,
     cue__chunk: Option<CueChunk>,
-    axml_chunk: Option<String>,
+    axml_chunk: Option<AxmlChunk>,
+    chna_chunk: Option<ChnaChunk>,
     ixml_chunk: Option<String>,
     list_chunk: BTreeSet<ListChunk>,
     acid_chunk: Option<AcidChunk>,
-    id3__chunk: Option<Id3::Tag>,
+    id3__source: Option<FileDataSource>, // Where the bytes of the `id3 ` chunk are, it's parsed on demand
+    id3__chunk: OnceCell<Option<Id3::Tag>>,
     junk_chunks: BTreeSet<JunkChunk>,
+    unknown_chunks: BTreeMap<u64, FileDataSource>, // The unknown chunks copied out of the reader by their offsets, see `read_raw_chunk()`
+    source_path: Option<PathBuf>, // If the WAV file was opened from a file path, the raw chunks are read from the file on demand
+    chunk_map: Vec<ChunkInfo>,
+    warnings: Vec<DecodeWarning>,
+    read_options: ReadOptions,
+    start_frame: u64,
+    truncated: bool,
+    frame_cache: FrameRangeCache,
+    pull_cursor: Option<(u64, Box<dyn Any>)>, // The decoder of `decode_into()` and the frame index it decodes next
+}
+
+/// * The `WaveReader` guarded by a mutex, it can be moved to and shared between the threads, e.g. to decode the parts of a file on a thread pool.
+/// * The `WaveReader` itself isn't `Send`: the streaming `WaveReader` keeps the `Read` you gave it, and the decoders share their readers by `Rc` and raw pointers.
+///   The `SyncWaveReader` can only be opened from a file path, then everything inside the `WaveReader` is owned by it and nothing is shared with the outside.
+/// * The `WaveReader` is never handed out while it's shared, only the methods here can use it, each of them holds the lock while decoding.
+///   Use `read_frames_range()` to decode the parts of the file, the decoded blocks are cached and shared by all of the threads.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct SyncWaveReader(Mutex<OwnedWaveReader>);
+
+/// * The `WaveReader` opened from a file path, the mutex of it is `Sync` because this is `Send`.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+struct OwnedWaveReader(WaveReader);
+
+// The `WaveReader` opened from a file path without a custom decoder owns all of its `Rc`s and raw pointers, the methods of `SyncWaveReader` never hand them out,
+// and they never give out a `&mut WaveReader` that could replace it with a `WaveReader` that shares its state with the outside.
+// The codec libraries don't bind their states to a thread.
+#[cfg(feature = "fs")]
+unsafe impl Send for OwnedWaveReader {}
+
+#[cfg(feature = "fs")]
+impl SyncWaveReader {
+    /// * Open the WAV file from a file path, see `WaveReader::open()`.
+    pub fn open(file_source: &str) -> Result<Self, AudioReadError> {
+        Ok(Self(Mutex::new(OwnedWaveReader(WaveReader::open(file_source)?))))
+    }
+
+    /// * Open the WAV file from a file path with the `ReadOptions`, see `WaveReader::open_with_options()`.
+    /// * The `ReadOptions::custom_decoder` isn't allowed, the decoder it creates may not be `Send`.
+    pub fn open_with_options(file_source: &str, read_options: ReadOptions) -> Result<Self, AudioReadError> {
+        if read_options.custom_decoder.is_some() {
+            return Err(AudioReadError::InvalidArguments(
+                "The `SyncWaveReader` can't use a custom decoder, it may not be `Send`.".to_owned(),
+            ));
+        }
+        Ok(Self(Mutex::new(OwnedWaveReader(WaveReader::open_with_options(file_source, read_options)?))))
+    }
+
+    /// * If a thread panicked while holding the lock, the `WaveReader` is still usable.
+    fn lock(&self) -> MutexGuard<'_, OwnedWaveReader> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// * The spec of the WAV file, see `WaveReader::spec()`.
+    pub fn spec(&self) -> Spec {
+        self.lock().0.spec()
+    }
+
+    /// * How many audio frames are in the file, see `WaveReader::total_frames()`.
+    pub fn total_frames(&self) -> Option<u64> {
+        self.lock().0.total_frames()
+    }
+
+    /// * Read `count` frames from the frame index `start`, see `WaveReader::read_frames_range()`.
+    pub fn read_frames_range<S>(&self, start: u64, count: usize) -> Result<Vec<Vec<S>>, AudioReadError>
+    where
+        S: SampleType + 'static,
+    {
+        self.lock().0.read_frames_range(start, count)
+    }
+
+    /// * Get the `WaveReader` back, it can't leave the thread anymore.
+    pub fn into_inner(self) -> WaveReader {
+        self.0.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()).0
+    }
+}
+
+/// * How many frames are in a block of the `FrameRangeCache`.
+const RANGE_CACHE_BLOCK_FRAMES: u64 = 4096;
+
+/// * How many blocks the `FrameRangeCache` keeps, the least recently used block is dropped first.
+const RANGE_CACHE_MAX_BLOCKS: usize = 64;
+
+/// * The cursor decoder decodes forward to the block if it's no further than this, otherwise a new decoder is opened and seeked.
+const RANGE_CACHE_MAX_SKIP_FRAMES: u64 = RANGE_CACHE_BLOCK_FRAMES * 16;
+
+/// * The decoded blocks of `WaveReader::read_frames_range()` keyed by the block index, each block is a `Vec<Vec<S>>` of the sample type last read.
+/// * The decoder that decoded the last block is kept as the cursor, reading on from there doesn't need to seek.
+#[derive(Default)]
+struct FrameRangeCache {
+    sample_type: Option<TypeId>,
+    blocks: BTreeMap<u64, Box<dyn Any>>,
+    recent: VecDeque<u64>,
+    cursor: Option<(u64, Box<dyn Any>)>,
+    end_frame: Option<u64>,
+}
+
+impl FrameRangeCache {
+    /// * The blocks of the other sample type are dropped, the known end of the audio is kept.
+    fn switch_type<S: 'static>(&mut self) {
+        let sample_type = TypeId::of::<S>();
+        if self.sample_type != Some(sample_type) {
+            *self = Self {
+                sample_type: Some(sample_type),
+                end_frame: self.end_frame,
+                ..Default::default()
+            };
+        }
+    }
+
+    fn get<S: 'static>(&mut self, block: u64) -> Option<&Vec<Vec<S>>> {
+        if self.blocks.contains_key(&block) {
+            self.recent.retain(|b| *b != block);
+            self.recent.push_back(block);
+        }
+        self.blocks.get(&block).and_then(|frames| frames.downcast_ref())
+    }
+
+    fn insert<S: 'static>(&mut self, block: u64, frames: Vec<Vec<S>>) {
+        self.blocks.insert(block, Box::new(frames));
+        self.recent.retain(|b| *b != block);
+        self.recent.push_back(block);
+        while self.recent.len() > RANGE_CACHE_MAX_BLOCKS {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.blocks.remove(&oldest);
+            }
+        }
+    }
+
+    fn take_cursor<S: 'static>(&mut self) -> Option<(u64, Box<dyn Decoder<S>>)> {
+        let (next_frame, decoder) = self.cursor.take()?;
+        decoder.downcast::<Box<dyn Decoder<S>>>().ok().map(|decoder| (next_frame, *decoder))
+    }
+}
+
+impl Debug for FrameRangeCache {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("FrameRangeCache")
+            .field("blocks", &self.recent)
+            .field("cursor", &self.cursor.as_ref().map(|(next_frame, _)| next_frame))
+            .field("end_frame", &self.end_frame)
+            .finish()
+    }
 }
 
 /// Accepts a result, if it is `Ok`, return a `Some`; otherwise print the error message and return `None`
@@ -91,19 +613,92 @@ where
 }
 
 impl WaveReader {
-    /// * Open the WAV file from a file path. No temporary files will be created.
+    /// * Open the WAV file from a file path. No temporary files will be created, except for the AIFF file whose samples need byte-swapping.
+    #[cfg(feature = "fs")]
     pub fn open(file_source: &str) -> Result<Self, AudioReadError> {
         Self::new(WaveDataSource::Filename(file_source.to_string()))
     }
 
+    /// * Open the WAV file from a file path with the `ReadOptions`, use `ReadOptions::untrusted()` for the files from untrusted sources.
+    #[cfg(feature = "fs")]
+    pub fn open_with_options(file_source: &str, read_options: ReadOptions) -> Result<Self, AudioReadError> {
+        Self::new_with_options(WaveDataSource::Filename(file_source.to_string()), read_options)
+    }
+
+    /// * Open the WAV file from a file path, the text of the metadata chunks is decoded by `text_encoding`, see `new_with_text_codec()`.
+    #[cfg(feature = "fs")]
+    pub fn open_with_text_codec(file_source: &str, read_options: ReadOptions, text_encoding: StringCodecMaps) -> Result<Self, AudioReadError> {
+        Self::new_with_text_codec(WaveDataSource::Filename(file_source.to_string()), read_options, text_encoding)
+    }
+
+    /// * Open the WAV file from a file path, `visitor` sees the header of each chunk as it's encountered, see `new_with_visitor()`.
+    #[cfg(feature = "fs")]
+    pub fn open_with_visitor(file_source: &str, visitor: impl FnMut(&ChunkHeader) -> ChunkAction) -> Result<Self, AudioReadError> {
+        Self::new_with_visitor(WaveDataSource::Filename(file_source.to_string()), ReadOptions::default(), visitor)
+    }
+
+    /// * Set the `ReadOptions`, the iterators created after this call use the new options.
+    pub fn set_read_options(&mut self, read_options: ReadOptions) {
+        self.read_options = read_options;
+    }
+
+    /// * Get the `ReadOptions`
+    pub fn get_read_options(&self) -> ReadOptions {
+        self.read_options
+    }
+
+    /// * Get the string codec maps that decoded the text of the metadata chunks.
+    pub fn get_text_codec(&self) -> &StringCodecMaps {
+        &self.text_encoding
+    }
+
     /// * Open the WAV file from a `WaveDataSource`, if the `WaveDataSource` is `Reader`, the `WaveReader` will create an auto-delete temporary file for the `data` chunk.
+    /// * The returned error carries the path of the file and the position of the chunk being parsed, use `is_io_error()` or `is_parse_error()` to classify it.
     pub fn new(file_source: WaveDataSource) -> Result<Self, AudioReadError> {
+        Self::new_with_options(file_source, ReadOptions::default())
+    }
+
+    /// * Open the WAV file from a `WaveDataSource` with the `ReadOptions`, see `new()`.
+    pub fn new_with_options(file_source: WaveDataSource, read_options: ReadOptions) -> Result<Self, AudioReadError> {
+        Self::new_with_text_codec(file_source, read_options, StringCodecMaps::new())
+    }
+
+    /// * Open the WAV file from a `WaveDataSource` with the `ReadOptions`, and decode the text by `text_encoding`.
+    /// * The text of the `LIST INFO` values, the `labl`, `note` and `ltxt` labels, the `bext` description and the `iXML` and `axml` chunks are decoded by it.
+    ///   The files from the Japanese or Chinese DAWs often have them in Shift-JIS or GBK, give the string codec maps of that code page to read them right.
+    /// * The `LIST INFO` values that begin with the UTF-8 BOM are always decoded as UTF-8.
+    /// * To write the text back with the same bytes, give the same string codec maps to `WaveWriter::set_text_codec()`.
+    pub fn new_with_text_codec(file_source: WaveDataSource, read_options: ReadOptions, text_encoding: StringCodecMaps) -> Result<Self, AudioReadError> {
+        Self::open_source(file_source, read_options, text_encoding, &mut |_| ChunkAction::Parse)
+    }
+
+    /// * Open the WAV file from a `WaveDataSource`, `visitor` sees the FourCC, the size and the position of each chunk before it's parsed.
+    /// * The visitor decides what to do with the chunk by returning a `ChunkAction`, e.g. to show the progress of opening a huge file on a slow disk,
+    ///   or to stop after the `fmt ` chunk and the `data` chunk when the metadata isn't needed.
+    /// * The `chunk_start_pos` of the `ChunkHeader` is the position of the chunk data, the 8-byte header is before it.
+    ///   The `size` is the one in the header, for the huge chunks of the `RF64` file it's `0xFFFFFFFF`.
+    /// * The chunks of the AIFF file are not visited.
+    pub fn new_with_visitor(file_source: WaveDataSource, read_options: ReadOptions, mut visitor: impl FnMut(&ChunkHeader) -> ChunkAction) -> Result<Self, AudioReadError> {
+        Self::open_source(file_source, read_options, StringCodecMaps::new(), &mut visitor)
+    }
+
+    fn open_source(
+        file_source: WaveDataSource,
+        read_options: ReadOptions,
+        text_encoding: StringCodecMaps,
+        visitor: &mut dyn FnMut(&ChunkHeader) -> ChunkAction,
+    ) -> Result<Self, AudioReadError> {
+        #[cfg_attr(not(feature = "fs"), allow(unused_mut))]
         let mut filesrc: Option<String> = None;
-        let mut reader = match file_source {
+        let reader: Box<dyn Reader> = match file_source {
             WaveDataSource::Reader(reader) => reader,
+            #[cfg(feature = "fs")]
             WaveDataSource::Filename(filename) => {
                 filesrc = Some(filename.clone());
-                Box::new(BufReader::new(File::open(&filename)?))
+                match File::open(&filename) {
+                    Ok(file) => Box::new(BufReader::new(file)),
+                    Err(ioerr) => return Err(AudioReadError::from(ioerr).with_context(Some(&filename), None)),
+                }
             }
             WaveDataSource::Unknown => {
                 return Err(AudioReadError::InvalidArguments(String::from(
@@ -111,30 +706,64 @@ impl WaveReader {
                 )));
             }
         };
+        let source_name = filesrc.clone();
+        let mut last_chunk = ChunkContext::new(None, Some(0));
+        Self::parse(reader, filesrc, false, read_options, text_encoding, visitor, &mut last_chunk).map_err(|err| {
+            err.eof_as_incomplete_data()
+                .in_chunk(last_chunk.clone())
+                .with_context(source_name.as_deref(), last_chunk.offset)
+        })
+    }
 
-        let text_encoding = StringCodecMaps::new();
+    /// * Read the WAV from a non-seekable stream, e.g. the stdin or a network socket. No temporary files will be created.
+    /// * The chunks are parsed until the `data` chunk, then the audio data is decoded directly from the stream.
+    ///   The chunks after the `data` chunk (e.g. a trailing `LIST` chunk) are not available, they require seeking back.
+    /// * The `data` chunk can only be iterated once by one iterator, and the iterator can't seek backward.
+    ///   The operations that require seeking return an `AudioReadError` that describes it.
+    /// * Streaming decoding works for PCM, ADPCM, A-law, µ-law and MP3 audio data.
+    pub fn from_reader(reader: impl Read + 'static) -> Result<Self, AudioReadError> {
+        let mut last_chunk = ChunkContext::new(None, Some(0));
+        Self::parse(Box::new(StreamReader::new(Box::new(reader))), None, true, ReadOptions::default(), StringCodecMaps::new(), &mut |_| ChunkAction::Parse, &mut last_chunk).map_err(|err| {
+            err.eof_as_incomplete_data()
+                .in_chunk(last_chunk.clone())
+                .with_context(None, last_chunk.offset)
+        })
+    }
 
-        let (filestart, reader_seekable) = match reader.stream_position() {
-            Ok(position) => (position, true),
+    /// * Parse the chunks from the reader, `last_chunk` keeps the FourCC and the position of the chunk being parsed for the error context.
+    /// * If `streaming` is true, stop parsing at the `data` chunk and decode the audio data directly from the reader.
+    /// * `visitor` decides what to do with each chunk inside the `RIFF` chunk, see `new_with_visitor()`.
+    fn parse(
+        mut reader: Box<dyn Reader>,
+        filesrc: Option<String>,
+        streaming: bool,
+        read_options: ReadOptions,
+        text_encoding: StringCodecMaps,
+        visitor: &mut dyn FnMut(&ChunkHeader) -> ChunkAction,
+        last_chunk: &mut ChunkContext,
+    ) -> Result<Self, AudioReadError> {
+        // A stream may tell its position but can't seek to its end.
+        let filestart = reader.stream_position().unwrap_or(0);
+        let (mut filelen, reader_seekable) = match reader.seek(SeekFrom::End(0)) {
+            Ok(filelen) => {
+                reader.seek(SeekFrom::Start(filestart))?;
+                (filelen, true)
+            }
             Err(_) => (0u64, false),
         };
-
-        let mut filelen = if reader_seekable {
-            let filelen = reader.seek(SeekFrom::End(0))?;
-            reader.seek(SeekFrom::Start(filestart))?;
-            filelen
-        } else {
-            0
-        };
         let mut cur_pos = filestart;
+        *last_chunk = ChunkContext::new(None, Some(filestart));
 
         let mut riff_end = 0xFFFFFFFFu64;
         let mut isRF64 = false;
         let mut ds64_read = false;
         let mut data_size = 0u64;
+        let mut ds64_sample_count = 0u64;
+        let mut ds64_table = BTreeMap::<[u8; 4], u64>::new();
 
-        // The whole file should be a `RIFF` chunk or a `RF64` chunk.
+        // The whole file should be a `RIFF` chunk or a `RF64` chunk, the `BW64` chunk of the ITU-R BS.2088 is the same as the `RF64` chunk.
         let chunk = ChunkHeader::read_unseekable(&mut reader, &mut cur_pos)?;
+        last_chunk.flag = Some(chunk.flag);
         match &chunk.flag {
             b"RIFF" => {
                 let riff_len = chunk.size as u64;
@@ -143,10 +772,18 @@ impl WaveReader {
                     filelen = riff_end;
                 }
             }
-            b"RF64" => {
+            b"RF64" | b"BW64" => {
                 isRF64 = true;
             }
-            _ => return Err(AudioReadError::FormatError(String::from("Not a WAV file"))), // Not WAV
+            b"FORM" => {
+                // The AIFF file has the same layout of the chunks but big-endian.
+                return Self::parse_aiff(reader, chunk, filesrc, reader_seekable, filelen, read_options, text_encoding, last_chunk);
+            }
+            other => {
+                // Not WAV
+                return Err(AudioReadError::FormatError(String::from("Not a WAV file"))
+                    .expected_found("\"RIFF\", \"RF64\" or \"BW64\"", &format!("{:?}", String::from_utf8_lossy(other))));
+            }
         }
 
         let start_of_riff = cur_pos;
@@ -155,6 +792,7 @@ impl WaveReader {
         expect_flag(&mut reader, b"WAVE", &mut cur_pos)?;
 
         let mut fmt__chunk: Option<FmtChunk> = None;
+        let mut fmt__error: Option<String> = None; // Why the `fmt ` chunk couldn't be read, for `ReadOptions::assume_spec`
         let mut data_offset = 0u64;
         let mut fact_data = 0u64;
         let mut slnt_chunk: Option<SlntChunk> = None;
@@ -164,13 +802,21 @@ impl WaveReader {
         let mut plst_chunk: Option<PlstChunk> = None;
         let mut trkn_chunk: Option<TrknChunk> = None;
         let mut cue__chunk: Option<CueChunk> = None;
-        let mut axml_chunk: Option<String> = None;
+        let mut cu64_chunk: Option<Cue64Chunk> = None;
+        let mut axml_chunk: Option<AxmlChunk> = None;
+        let mut chna_chunk: Option<ChnaChunk> = None;
         let mut ixml_chunk: Option<String> = None;
         let mut list_chunk = BTreeSet::<ListChunk>::new();
         let mut acid_chunk: Option<AcidChunk> = None;
-        let mut id3__chunk: Option<Id3::Tag> = None;
+        let mut id3__source: Option<FileDataSource> = None;
         let mut junk_chunks = BTreeSet::<JunkChunk>::new();
+        let mut unknown_chunks = BTreeMap::<u64, FileDataSource>::new();
         let mut data_chunk = FileDataSource::default();
+        let mut chunk_map = Vec::<ChunkInfo>::new();
+        let mut warnings = Vec::<DecodeWarning>::new();
+        let mut data_until_eof = false;
+        let mut truncated = false;
+        let mut stopped_early = false;
 
         // Read each chunks from the WAV file
         let mut last_flag: [u8; 4];
@@ -184,30 +830,69 @@ impl WaveReader {
             } else {
                 cur_pos
             };
+            *last_chunk = ChunkContext::new(None, Some(chunk_position));
             if chunk_position == riff_end {
                 // Normally hit the end of the WAV file.
+                if reader_seekable && filelen > riff_end {
+                    warnings.push(DecodeWarning::new(DecodeWarningKind::SizeMismatch, riff_end, format!("There are {} extra bytes after the end of the RIFF chunk.", filelen - riff_end)));
+                }
                 break;
             } else if chunk_position + 4 >= riff_end {
                 // Hit the end but not good.
-                match riff_end.cmp(&filelen) {
-                    Ordering::Greater => eprintln!(
+                let message = match riff_end.cmp(&filelen) {
+                    Ordering::Greater => format!(
                         "There end of the RIFF chunk exceeded the file size of {} bytes.",
                         riff_end - filelen
                     ),
-                    Ordering::Equal => eprintln!(
+                    Ordering::Equal => format!(
                         "There are some chunk sizes wrong, probably the \"{}\" chunk.",
                         text_encoding.decode_flags(&chunk.flag)
                     ),
-                    Ordering::Less => eprintln!(
+                    Ordering::Less => format!(
                         "There are {} extra bytes at the end of the RIFF chunk.",
                         filelen - riff_end
                     ),
-                }
+                };
+                eprintln!("{message}");
+                warnings.push(DecodeWarning::new(DecodeWarningKind::SizeMismatch, chunk_position, message));
                 break;
             }
             last_flag = chunk.flag;
             chunk = ChunkHeader::read_unseekable(&mut reader, &mut cur_pos)?;
+            last_chunk.flag = Some(chunk.flag);
+
+            // The reader can't do without the `fmt `, `ds64` and `data` chunks, they are parsed even if the visitor wants to skip them.
+            let skip = match visitor(&chunk) {
+                ChunkAction::Parse => false,
+                ChunkAction::Skip => !matches!(&chunk.flag, b"fmt " | b"ds64" | b"data"),
+                ChunkAction::StopEarly => {
+                    stopped_early = true;
+                    break;
+                }
+            };
+
+            // In the RF64 file, the chunk with the size of `0xFFFFFFFF` has its 64-bit size in the table of the `ds64` chunk.
+            // Such a huge chunk can't be read into the memory, skip it.
+            if isRF64 && chunk.size == u32::MAX && &chunk.flag != b"data" {
+                if let Some(&chunk_size) = ds64_table.get(&chunk.flag) {
+                    chunk_map.push(ChunkInfo {
+                        flag: chunk.flag,
+                        offset: chunk_position,
+                        length: chunk_size,
+                        back_patched: false,
+                    });
+                    let chunk_end = ChunkHeader::align(chunk.chunk_start_pos + chunk_size);
+                    if reader_seekable {
+                        cur_pos = reader.seek(SeekFrom::Start(chunk_end))?;
+                    } else {
+                        io_utils::goto_offset_without_seek(&mut reader, &mut cur_pos, chunk_end)?;
+                    }
+                    continue;
+                }
+            }
+
             match &chunk.flag {
+                _ if skip => (),
                 b"JUNK" => {
                     let mut junk = vec![0u8; chunk.size as usize];
                     reader.read_exact(&mut junk)?;
@@ -215,7 +900,19 @@ impl WaveReader {
                 }
                 b"fmt " => {
                     Self::no_duplication(&fmt__chunk, &chunk.flag)?;
-                    fmt__chunk = Some(FmtChunk::read(&mut reader, chunk.size)?);
+                    match FmtChunk::read(&mut reader, chunk.size) {
+                        Ok(fmt) => {
+                            if fmt.format_tag == wavcore::format_tags::FORMAT_TAG_ADPCM_MS {
+                                for message in crate::adpcm::ms::check_fmt_chunk(&fmt) {
+                                    warnings.push(DecodeWarning::new(DecodeWarningKind::FmtChunk, chunk_position, message));
+                                }
+                            }
+                            fmt__chunk = Some(fmt);
+                        }
+                        // The damaged `fmt ` chunk is replaced by the assumed spec after all of the chunks were parsed.
+                        Err(err) if read_options.assume_spec.is_some() => fmt__error = Some(err.to_string()),
+                        Err(err) => return Err(err),
+                    }
                 }
                 b"fact" => {
                     let mut buf = vec![0u8; chunk.size as usize];
@@ -250,8 +947,21 @@ impl WaveReader {
                     }
                     let riff_len = u64::read_le(&mut reader)?;
                     data_size = u64::read_le(&mut reader)?;
-                    let _sample_count = u64::read_le(&mut reader)?;
-                    // After these fields, there are tables for each chunk's size in 64 bits. Normally it's not needed to read this, except for huge > 4GB JUNK chunks.
+                    ds64_sample_count = u64::read_le(&mut reader)?;
+
+                    // After these fields, there is a table for each chunk's size in 64 bits, e.g. for the huge > 4GB JUNK chunks.
+                    let table_length = u32::read_le(&mut reader)? as usize;
+                    let table_capacity = (chunk.size as usize - 28) / 12;
+                    if table_length > table_capacity {
+                        return Err(AudioReadError::InvalidData(format!(
+                            "the table of the \"ds64\" chunk has {table_length} entries, but the chunk has room for {table_capacity} entries"
+                        )));
+                    }
+                    for _ in 0..table_length {
+                        let mut flag = [0u8; 4];
+                        reader.read_exact(&mut flag)?;
+                        ds64_table.insert(flag, u64::read_le(&mut reader)?);
+                    }
                     riff_end = ChunkHeader::align(start_of_riff + riff_len);
                     if filelen == 0 {
                         filelen = riff_end;
@@ -269,6 +979,39 @@ impl WaveReader {
                     if !isRF64 {
                         data_size = chunk.size as u64;
                     }
+                    if reader_seekable && data_offset + data_size > filelen {
+                        let remaining = filelen.saturating_sub(data_offset);
+                        if !isRF64 && chunk.size == u32::MAX {
+                            // The recording software writes `0xFFFFFFFF` while it's still recording, the audio data lasts until the end of the file.
+                            data_until_eof = true;
+                        } else if read_options.allow_truncated {
+                            eprintln!("The \"data\" chunk is truncated, it claims {data_size} bytes, but only {remaining} bytes are in the file.");
+                            truncated = true;
+                        } else {
+                            return Err(AudioReadError::IncompleteData(format!(
+                                "the \"data\" chunk claims {data_size} bytes, but only {remaining} bytes are in the file, set `allow_truncated` of the `ReadOptions` to read it anyway"
+                            )));
+                        }
+                        data_size = remaining;
+                        // Nothing after the `data` chunk.
+                        riff_end = ChunkHeader::align(filelen);
+                    }
+                    if streaming {
+                        // Everything needed for decoding must come before the audio data, we can't go back for it.
+                        if fmt__chunk.is_none() && read_options.assume_spec.is_none() {
+                            return Err(AudioReadError::Unsupported(String::from(
+                                "the \"fmt \" chunk is after the \"data\" chunk, reading it from a stream requires a seekable reader",
+                            )));
+                        }
+                        chunk_map.push(ChunkInfo {
+                            flag: chunk.flag,
+                            offset: chunk_position,
+                            length: data_size,
+                            back_patched: false,
+                        });
+                        data_chunk = FileDataSource::from_stream(reader, data_offset, data_size);
+                        break;
+                    }
                     if let Some(ref filename) = filesrc {
                         data_chunk = FileDataSource::new(
                             None,
@@ -300,7 +1043,7 @@ impl WaveReader {
                     Self::ignore_laters(&mut slnt_chunk, &chunk.flag, ||optional(SlntChunk::read(&mut reader)));
                 }
                 b"bext" => {
-                    Self::ignore_laters(&mut bext_chunk, &chunk.flag, ||optional(BextChunk::read(&mut reader, &text_encoding)));
+                    Self::ignore_laters(&mut bext_chunk, &chunk.flag, ||optional(BextChunk::read(&mut reader, chunk.size as usize, &text_encoding)));
                 },
                 b"smpl" => {
                     Self::ignore_laters(&mut smpl_chunk, &chunk.flag, ||optional(SmplChunk::read(&mut reader)));
@@ -314,22 +1057,59 @@ impl WaveReader {
                 b"cue " => {
                     Self::ignore_laters(&mut cue__chunk, &chunk.flag, ||optional(CueChunk::read(&mut reader)));
                 },
+                b"cu64" => {
+                    Self::ignore_laters(&mut cu64_chunk, &chunk.flag, ||optional(Cue64Chunk::read(&mut reader)));
+                },
                 b"axml" => {
-                    Self::ignore_laters(&mut axml_chunk, &chunk.flag, ||optional(read_str(&mut reader, chunk.size as usize, &text_encoding)));
+                    Self::ignore_laters(&mut axml_chunk, &chunk.flag, ||optional(AxmlChunk::read(&mut reader, chunk.size as usize, &text_encoding)));
+                },
+                b"chna" => {
+                    Self::ignore_laters(&mut chna_chunk, &chunk.flag, ||optional(ChnaChunk::read(&mut reader, chunk.size as usize)));
                 },
                 b"ixml" => {
                     Self::ignore_laters(&mut ixml_chunk, &chunk.flag, ||optional(read_str(&mut reader, chunk.size as usize, &text_encoding)));
                 },
-                b"LIST" => {
-                    list_chunk.append(
-                        &mut optional(ListChunk::read(
-                            &mut reader,
-                            chunk.size as u64,
-                            &text_encoding,
-                        ))
-                        .into_iter()
-                        .collect::<BTreeSet<ListChunk>>(),
-                    );
+                b"LIST" if chunk.size >= 4 => {
+                    let mut list_type = [0u8; 4];
+                    reader.read_exact(&mut list_type)?;
+                    if &list_type == b"wavl" {
+                        // The old form of the audio data, the `data` chunks alternate with the `slnt` chunks in the `LIST wavl` chunk.
+                        if data_offset != 0 {
+                            return Err(AudioReadError::InvalidData(String::from(
+                                "the WAV file has both the \"data\" chunk and the \"LIST wavl\" chunk",
+                            )));
+                        }
+                        let Some(fmt) = &fmt__chunk else {
+                            return Err(AudioReadError::Unsupported(String::from(
+                                "the \"fmt \" chunk is after the \"LIST wavl\" chunk, the length of the silence is unknown",
+                            )));
+                        };
+                        if streaming || !reader_seekable {
+                            return Err(AudioReadError::Unsupported(String::from(
+                                "reading the \"LIST wavl\" chunk requires a seekable reader",
+                            )));
+                        }
+                        let segments = Self::read_wavl(&mut reader, chunk.chunk_start_pos + 4, chunk.next_chunk_pos().min(riff_end), fmt)?;
+                        data_offset = chunk.chunk_start_pos;
+                        let silence_byte = if fmt.bits_per_sample == 8 { 0x80 } else { 0 };
+                        data_chunk = match filesrc {
+                            Some(ref filename) => FileDataSource::new_wavl(None, Some(filename.clone()), segments, silence_byte)?,
+                            None => FileDataSource::new_wavl(Some(&mut *reader), None, segments, silence_byte)?,
+                        };
+                    } else {
+                        // The other `LIST` chunks are small, parse it in the memory, the list type was read already.
+                        let mut buf = list_type.to_vec();
+                        (&mut reader).take(chunk.size as u64 - 4).read_to_end(&mut buf)?;
+                        list_chunk.append(
+                            &mut optional(ListChunk::read(
+                                &mut CursorVecU8::new(buf),
+                                chunk.size as u64,
+                                &text_encoding,
+                            ))
+                            .into_iter()
+                            .collect::<BTreeSet<ListChunk>>(),
+                        );
+                    }
                 }
                 b"acid" => {
                     Self::ignore_laters(&mut acid_chunk, &chunk.flag, ||optional(AcidChunk::read(&mut reader)));
@@ -338,7 +1118,19 @@ impl WaveReader {
                     Self::ignore_laters(&mut trkn_chunk, &chunk.flag, ||optional(TrknChunk::read(&mut reader)));
                 }
                 b"id3 " => {
-                    Self::ignore_laters(&mut id3__chunk, &chunk.flag, ||optional(Id3::id3_read(&mut reader, chunk.size as usize)));
+                    // The ID3 tag may carry megabytes of pictures, only remember where it is, see `id3()`.
+                    if id3__source.is_some() {
+                        eprintln!("Duplicated chunk 'id3 ' in the WAV file");
+                    } else {
+                        id3__source = Some(Self::chunk_source(&mut reader, &filesrc, chunk.chunk_start_pos, chunk.size as u64, reader_seekable, &mut cur_pos)?);
+                        let chunk_end = ChunkHeader::align(chunk.chunk_start_pos + chunk.size as u64);
+                        if reader_seekable {
+                            reader.seek(SeekFrom::Start(chunk_end))?;
+                        } else {
+                            io_utils::goto_offset_without_seek(&mut reader, &mut cur_pos, chunk_end)?;
+                        }
+                        manually_skipped = true;
+                    }
                 },
                 b"\0\0\0\0" => {
                     // empty flag
@@ -360,60 +1152,130 @@ impl WaveReader {
                     eprintln!(
                         "The previous chunk is '{}'",
                         text_encoding.decode_flags(&last_flag)
-                    )
+                    );
+                    if filesrc.is_none() {
+                        // The reader is gone after parsing, keep the chunk for `read_raw_chunk()`.
+                        unknown_chunks.insert(chunk_position, Self::chunk_source(&mut reader, &filesrc, chunk.chunk_start_pos, chunk.size as u64, reader_seekable, &mut cur_pos)?);
+                        let chunk_end = ChunkHeader::align(chunk.chunk_start_pos + chunk.size as u64);
+                        if reader_seekable {
+                            reader.seek(SeekFrom::Start(chunk_end))?;
+                        } else {
+                            io_utils::goto_offset_without_seek(&mut reader, &mut cur_pos, chunk_end)?;
+                        }
+                        manually_skipped = true;
+                    }
                 }
             }
-            if !manually_skipped {
-                if reader_seekable {
-                    cur_pos = chunk.next_chunk_pos();
-                    chunk.seek_to_next_chunk(&mut reader)?;
-                } else {
+            chunk_map.push(ChunkInfo {
+                flag: chunk.flag,
+                offset: chunk_position,
+                length: if &chunk.flag == b"data" { data_size } else { chunk.size as u64 },
+                back_patched: false,
+            });
+            if reader_seekable {
+                // The `FileDataSource` updated `cur_pos` to the end of the `data` chunk but not align it to 16-bit position.
+                let chunk_end = if manually_skipped { cur_pos } else { chunk.chunk_start_pos + chunk.size as u64 };
+                cur_pos = Self::next_chunk_position(&mut reader, chunk_end, riff_end, &mut warnings)?;
+                reader.seek(SeekFrom::Start(cur_pos))?;
+            } else {
+                if !manually_skipped {
                     cur_pos += chunk.size as u64;
-                    cur_pos = ChunkHeader::align(cur_pos);
-                    chunk.goto_next_chunk_unseekable(&mut reader, &mut cur_pos)?;
                 }
-            } else {
-                // The `FileDataSource` updated `cur_pos` to the end of the `data` chunk but not align it to 16-bit position.
                 cur_pos = ChunkHeader::align(cur_pos);
-                if reader_seekable {
-                    reader.seek(SeekFrom::Start(cur_pos))?;
-                } else {
-                    chunk.goto_next_chunk_unseekable(&mut reader, &mut cur_pos)?;
-                }
+                chunk.goto_next_chunk_unseekable(&mut reader, &mut cur_pos)?;
             }
         }
 
+        if stopped_early && ((fmt__chunk.is_none() && read_options.assume_spec.is_none()) || data_offset == 0) {
+            return Err(AudioReadError::InvalidArguments(String::from(
+                "the chunk visitor stopped before both the \"fmt \" chunk and the \"data\" chunk were seen",
+            )));
+        }
+
         if isRF64 && !ds64_read {
             return Err(AudioReadError::InvalidData(String::from(
                 "the WAV file is a RF64 file but doesn't provide the \"ds64\" chunk",
             )));
         }
 
-        let fmt__chunk = match fmt__chunk {
-            Some(fmt__chunk) => fmt__chunk,
-            None => {
+        // The `fact` chunk of the RF64 file can't hold the 64-bit sample count, it's in the `ds64` chunk.
+        if isRF64 && (fact_data == 0 || fact_data == u32::MAX as u64) && ds64_sample_count != 0 {
+            fact_data = ds64_sample_count;
+        }
+
+        // The `fmt ` chunk that was read but describes nothing decodable is damaged too.
+        if let (Some(fmt), Some(_)) = (&fmt__chunk, read_options.assume_spec) {
+            if !Self::is_fmt_usable(fmt, &read_options) {
+                fmt__error = Some(format!(
+                    "the format tag is 0x{:04x}, {} channels, {} Hz, {} bytes per block",
+                    fmt.format_tag, fmt.channels, fmt.sample_rate, fmt.block_align
+                ));
+                fmt__chunk = None;
+            }
+        }
+        let fmt__chunk = match (fmt__chunk, read_options.assume_spec) {
+            (Some(fmt__chunk), _) => fmt__chunk,
+            (None, Some(spec)) => {
+                let fmt_offset = chunk_map.iter().find(|info| &info.flag == b"fmt ").map_or(start_of_riff + 4, |info| info.offset);
+                let reason = fmt__error.unwrap_or_else(|| String::from("there's no \"fmt \" chunk"));
+                warnings.push(DecodeWarning::new(DecodeWarningKind::AssumedSpec, fmt_offset, format!("The spec is assumed: {spec:?}, the \"fmt \" chunk can't be used: {reason}.")));
+                Self::assumed_fmt_chunk(&spec)?
+            }
+            (None, None) => {
                 return Err(AudioReadError::InvalidData(String::from(
-                    "the whole WAV file doesn't provide the \"data\" chunk",
+                    "the whole WAV file doesn't provide the \"fmt \" chunk",
                 )));
             }
         };
 
+        // The `fact` chunk is the number of the sample frames, the decoders take the number of the total samples.
+        fact_data *= fmt__chunk.channels as u64;
+
+        // The MP3 without the `fact` chunk may have the Xing/Info or VBRI header that tells the length.
+        #[cfg(feature = "mp3dec")]
+        if fact_data == 0 && fmt__chunk.format_tag == FORMAT_TAG_MP3 && !data_chunk.is_stream() {
+            fact_data = peek_mp3_total_samples(&data_chunk, &fmt__chunk)? * fmt__chunk.channels as u64;
+        }
+
+        // The `cu64` chunk has the cue positions that don't fit in the 32-bit fields of the `cue `, `plst` and `ltxt` chunks.
+        if let (Some(cu64_chunk), Some(cue__chunk)) = (&cu64_chunk, cue__chunk.as_mut()) {
+            let mut adtl = list_chunk.iter().find_map(|list_chunk| match list_chunk {
+                ListChunk::Adtl(adtl) => Some(adtl.clone()),
+                _ => None,
+            });
+            cu64_chunk.apply(cue__chunk, adtl.as_mut(), plst_chunk.as_mut());
+            if let Some(adtl) = adtl {
+                list_chunk.retain(|list_chunk| !matches!(list_chunk, ListChunk::Adtl(_)));
+                list_chunk.insert(ListChunk::Adtl(adtl));
+            }
+        }
+
+        // The data cut off at the end of the file may end in the middle of a block, let the iterators end at the last whole block.
+        if (truncated || data_until_eof) && fmt__chunk.block_align > 1 {
+            data_chunk.length -= data_chunk.length % fmt__chunk.block_align as u64;
+            if let Some(info) = chunk_map.iter_mut().find(|info| &info.flag == b"data") {
+                info.length = data_chunk.length;
+            }
+        }
+
         let mut spec = Spec {
             channels: fmt__chunk.channels,
-            channel_mask: 0,
+            channel_mask: None,
             sample_rate: fmt__chunk.sample_rate,
             bits_per_sample: fmt__chunk.bits_per_sample,
             sample_format: fmt__chunk.get_sample_format(),
         };
-        spec.channel_mask = spec.guess_channel_mask()?;
+        // Only the extensible `fmt ` chunk has the channel mask, even if it's zero.
         if let Some(extension) = &fmt__chunk.extension {
             if let ExtensionData::Extensible(extensible) = &extension.data {
-                spec.channel_mask = extensible.channel_mask;
+                spec.channel_mask = Some(extensible.channel_mask);
             }
         }
+        let storage_info = StorageInfo::new(&fmt__chunk, &data_chunk, fact_data);
         Ok(Self {
             spec,
             fmt__chunk,
+            storage_info,
             fact_data,
             data_chunk,
             text_encoding,
@@ -425,11 +1287,247 @@ impl WaveReader {
             trkn_chunk,
             cue__chunk,
             axml_chunk,
+            chna_chunk,
             ixml_chunk,
             list_chunk,
             acid_chunk,
-            id3__chunk,
+            id3__source,
+            id3__chunk: OnceCell::new(),
             junk_chunks,
+            unknown_chunks,
+            source_path: filesrc.map(PathBuf::from),
+            chunk_map,
+            warnings,
+            read_options,
+            start_frame: 0,
+            truncated,
+            frame_cache: FrameRangeCache::default(),
+            pull_cursor: None,
+        })
+    }
+
+    /// * Parse the chunks of the AIFF or AIFF-C file, `form` is the header of the `FORM` chunk. The chunks are presented as the WAV chunks:
+    /// * The `COMM` chunk becomes the `fmt ` chunk. The samples of the `SSND` chunk are copied into a temporary file as the `data` chunk,
+    ///   the big-endian samples are byte-swapped and the signed 8-bit samples become unsigned.
+    /// * The `MARK` chunk becomes the `cue ` chunk with the labels in the `LIST adtl` chunk, the cue point IDs are the marker IDs.
+    /// * The `INST` chunk becomes the `inst` chunk, its sustain loop and release loop become the loops of the `smpl` chunk.
+    /// * The `NAME`, `AUTH`, `(c) ` and `ANNO` chunks become the `INAM`, `IART`, `ICOP` and `ICMT` of the `LIST INFO` chunk.
+    #[allow(clippy::too_many_arguments)]
+    fn parse_aiff(
+        mut reader: Box<dyn Reader>,
+        form: ChunkHeader,
+        filesrc: Option<String>,
+        reader_seekable: bool,
+        filelen: u64,
+        read_options: ReadOptions,
+        text_encoding: StringCodecMaps,
+        last_chunk: &mut ChunkContext,
+    ) -> Result<Self, AudioReadError> {
+        // The size of the `FORM` chunk was read as little-endian.
+        let form_end = ChunkHeader::align(form.chunk_start_pos + form.size.swap_bytes() as u64);
+        let mut cur_pos = form.chunk_start_pos;
+        let mut form_type = [0u8; 4];
+        reader.read_exact(&mut form_type)?;
+        cur_pos += 4;
+        let is_aifc = match &form_type {
+            b"AIFF" => false,
+            b"AIFC" => true,
+            other => {
+                return Err(AudioReadError::FormatError(String::from("Not an AIFF file"))
+                    .expected_found("\"AIFF\" or \"AIFC\"", &format!("{:?}", String::from_utf8_lossy(other))));
+            }
+        };
+
+        let mut comm_chunk: Option<AiffCommChunk> = None;
+        let mut ssnd: Option<(u64, u64)> = None;
+        let mut markers = Vec::<AiffMarker>::new();
+        let mut aiff_inst_chunk: Option<AiffInstChunk> = None;
+        let mut info = InfoDict::new();
+        let mut id3__source: Option<FileDataSource> = None;
+        let mut data_chunk: Option<FileDataSource> = None;
+        let mut chunk_map = Vec::<ChunkInfo>::new();
+        let mut truncated = false;
+
+        while cur_pos + 8 <= form_end {
+            let chunk_position = cur_pos;
+            *last_chunk = ChunkContext::new(None, Some(chunk_position));
+            let chunk = AiffChunkHeader::read_unseekable(&mut reader, &mut cur_pos)?;
+            last_chunk.flag = Some(chunk.flag);
+            match &chunk.flag {
+                b"COMM" => {
+                    Self::no_duplication(&comm_chunk, &chunk.flag)?;
+                    comm_chunk = Some(AiffCommChunk::read(&mut reader, is_aifc)?);
+                }
+                b"SSND" => {
+                    if ssnd.is_some() {
+                        return Err(AudioReadError::InvalidData(String::from("Duplicated chunk 'SSND' in the AIFF file")));
+                    }
+                    let offset = read_u32_be(&mut reader)? as u64;
+                    let _block_size = read_u32_be(&mut reader)?;
+                    cur_pos += 8;
+                    let data_offset = chunk.chunk_start_pos + 8 + offset;
+                    let mut data_size = (chunk.size as u64).saturating_sub(8 + offset);
+                    if reader_seekable && data_offset + data_size > filelen {
+                        let remaining = filelen.saturating_sub(data_offset);
+                        if !read_options.allow_truncated {
+                            return Err(AudioReadError::IncompleteData(format!(
+                                "the \"SSND\" chunk claims {data_size} bytes, but only {remaining} bytes are in the file, set `allow_truncated` of the `ReadOptions` to read it anyway"
+                            )));
+                        }
+                        eprintln!("The \"SSND\" chunk is truncated, it claims {data_size} bytes, but only {remaining} bytes are in the file.");
+                        truncated = true;
+                        data_size = remaining;
+                    }
+                    ssnd = Some((data_offset, data_size));
+
+                    // Without seeking, the samples must be copied now, so the format must be known before them.
+                    if !reader_seekable {
+                        let Some(comm_chunk) = &comm_chunk else {
+                            return Err(AudioReadError::Unsupported(String::from(
+                                "the \"COMM\" chunk is after the \"SSND\" chunk, reading it from a stream requires a seekable reader",
+                            )));
+                        };
+                        let (_, conversion) = comm_chunk.to_fmt_chunk()?;
+                        data_chunk = Some(FileDataSource::new_converted(&mut *reader, data_offset, data_size, false, &mut cur_pos, conversion)?);
+                    }
+                }
+                b"MARK" => {
+                    markers = AiffMarker::read_markers(&mut reader)?;
+                }
+                b"INST" => {
+                    Self::ignore_laters(&mut aiff_inst_chunk, &chunk.flag, ||optional(AiffInstChunk::read(&mut reader)));
+                }
+                b"NAME" | b"AUTH" | b"(c) " | b"ANNO" => {
+                    let key = match &chunk.flag {
+                        b"NAME" => "INAM",
+                        b"AUTH" => "IART",
+                        b"(c) " => "ICOP",
+                        _ => "ICMT",
+                    };
+                    let text = read_str(&mut reader, chunk.size as usize, &text_encoding)?;
+                    let text = text.trim_end_matches('\0');
+                    info.entry(key.to_owned())
+                        .and_modify(|value| {
+                            value.push('\n');
+                            value.push_str(text);
+                        })
+                        .or_insert_with(|| text.to_owned());
+                }
+                b"ID3 " | b"id3 " => {
+                    if id3__source.is_some() {
+                        eprintln!("Duplicated chunk '{}' in the AIFF file", text_encoding.decode_flags(&chunk.flag));
+                    } else {
+                        id3__source = Some(Self::chunk_source(&mut reader, &filesrc, chunk.chunk_start_pos, chunk.size as u64, reader_seekable, &mut cur_pos)?);
+                    }
+                }
+                other => {
+                    eprintln!(
+                        "Skipped an unknown chunk in FORM chunk: '{}', Position: 0x{:x}, Size: 0x{:x}",
+                        text_encoding.decode_flags(other),
+                        chunk_position,
+                        chunk.size
+                    );
+                }
+            }
+            chunk_map.push(ChunkInfo {
+                flag: chunk.flag,
+                offset: chunk_position,
+                length: chunk.size as u64,
+                back_patched: false,
+            });
+            let next_chunk_pos = min(chunk.next_chunk_pos(), form_end);
+            if reader_seekable {
+                cur_pos = reader.seek(SeekFrom::Start(next_chunk_pos))?;
+            } else if cur_pos < next_chunk_pos {
+                io_utils::goto_offset_without_seek(&mut reader, &mut cur_pos, next_chunk_pos)?;
+            }
+        }
+
+        let Some(comm_chunk) = comm_chunk else {
+            return Err(AudioReadError::InvalidData(String::from(
+                "the whole AIFF file doesn't provide the \"COMM\" chunk",
+            )));
+        };
+        let Some((data_offset, data_size)) = ssnd else {
+            return Err(AudioReadError::InvalidData(String::from(
+                "the whole AIFF file doesn't provide the \"SSND\" chunk",
+            )));
+        };
+        let (fmt__chunk, conversion) = comm_chunk.to_fmt_chunk()?;
+
+        // The `SSND` chunk may have some padding after the samples.
+        let mut data_size = min(data_size, comm_chunk.num_frames as u64 * fmt__chunk.block_align as u64);
+        if truncated {
+            data_size -= data_size % fmt__chunk.block_align as u64;
+        }
+        let source_path = filesrc.as_ref().map(PathBuf::from);
+        let data_chunk = match (data_chunk, filesrc) {
+            (Some(mut data_chunk), _) => {
+                data_chunk.length = data_size;
+                data_chunk
+            }
+            (None, Some(filename)) if conversion == SampleConversion::Keep => {
+                FileDataSource::new(None, Some(filename), data_offset, data_size, true, &mut cur_pos)?
+            }
+            (None, _) => FileDataSource::new_converted(&mut *reader, data_offset, data_size, true, &mut cur_pos, conversion)?,
+        };
+        if let Some(info) = chunk_map.iter_mut().find(|info| &info.flag == b"SSND") {
+            info.length = data_size;
+        }
+
+        let mut list_chunk = BTreeSet::<ListChunk>::new();
+        let mut cue__chunk: Option<CueChunk> = None;
+        if !markers.is_empty() {
+            let (cue_chunk, adtl_chunk) = AiffMarker::to_cue_chunks(&markers);
+            cue__chunk = Some(cue_chunk);
+            list_chunk.insert(adtl_chunk);
+        }
+        if !info.is_empty() {
+            list_chunk.insert(ListChunk::Info(info));
+        }
+
+        let smpl_chunk = aiff_inst_chunk.and_then(|inst| inst.to_smpl_chunk(&markers, fmt__chunk.sample_rate));
+        let inst_chunk = aiff_inst_chunk.map(|inst| inst.to_inst_chunk());
+
+        let spec = Spec {
+            channels: fmt__chunk.channels,
+            channel_mask: None,
+            sample_rate: fmt__chunk.sample_rate,
+            bits_per_sample: fmt__chunk.bits_per_sample,
+            sample_format: fmt__chunk.get_sample_format(),
+        };
+        let storage_info = StorageInfo::new(&fmt__chunk, &data_chunk, 0);
+        Ok(Self {
+            spec,
+            fmt__chunk,
+            storage_info,
+            fact_data: 0,
+            data_chunk,
+            text_encoding,
+            slnt_chunk: None,
+            bext_chunk: None,
+            smpl_chunk,
+            inst_chunk,
+            plst_chunk: None,
+            trkn_chunk: None,
+            cue__chunk,
+            axml_chunk: None,
+            chna_chunk: None,
+            ixml_chunk: None,
+            list_chunk,
+            acid_chunk: None,
+            id3__source,
+            id3__chunk: OnceCell::new(),
+            junk_chunks: BTreeSet::new(),
+            unknown_chunks: BTreeMap::new(),
+            source_path,
+            chunk_map,
+            warnings: Vec::new(),
+            read_options,
+            start_frame: 0,
+            truncated,
+            frame_cache: FrameRangeCache::default(),
+            pull_cursor: None,
         })
     }
 
@@ -438,16 +1536,114 @@ impl WaveReader {
         self.spec
     }
 
+    /// * The channel layout parsed from the channel mask of the extensible `fmt ` chunk, `None` if the `fmt ` chunk has no channel mask.
+    pub fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.spec.channel_layout()
+    }
+
     /// * The `fact` data is the number of the total samples in the `data` chunk.
     pub fn get_fact_data(&self) -> u64 {
         self.fact_data
     }
 
+    /// * How many audio frames are in the file, without decoding. `None` if the length can't be known without decoding the whole audio.
+    pub fn total_frames(&self) -> Option<u64> {
+        get_total_frames(&self.fmt__chunk, self.data_chunk.length, self.fact_data)
+    }
+
+    /// * How long is the audio, see `total_frames()`.
+    pub fn duration(&self) -> Option<Duration> {
+        self.total_frames().map(|total_frames| self.spec.duration(total_frames))
+    }
+
     /// * The `fmt ` chunk is to specify the detailed audio file format.
     pub fn get_fmt__chunk(&self) -> &FmtChunk {
         &self.fmt__chunk
     }
 
+    /// * How the audio is actually stored in the `data` chunk: the codec, the block size, the bitrate, see `StorageInfo`. The `spec()` is what the decoders give you.
+    pub fn storage_info(&self) -> &StorageInfo {
+        &self.storage_info
+    }
+
+    /// * The `SubFormat` GUID from the extensible `fmt ` chunk, e.g. the ambisonic B-format GUIDs. Returns `None` if the `fmt ` chunk isn't extensible.
+    pub fn get_sub_format_guid(&self) -> Option<wavcore::GUID> {
+        match &self.fmt__chunk.extension {
+            Some(extension) => match &extension.data {
+                ExtensionData::Extensible(extensible) => Some(extensible.sub_format),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    /// * Get the raw reader of the `data` chunk content, positioned at the beginning of the audio data.
+    /// * With `get_fmt__chunk().to_bytes()`, this is for you to handle the format that we can't decode.
+    pub fn open_raw_data(&self) -> Result<Box<dyn Reader>, AudioReadError> {
+        self.data_chunk.open()
+    }
+
+    /// * Map the `data` chunk into the memory to access the samples without copying or decoding, see `PcmView`.
+    /// * Only the PCM and IEEE float samples of 8, 16, 32 or 64 bits are mapped, the extensible `fmt ` chunk must have its valid bits the same as the container bits.
+    ///   The 24-bit samples, the compressed formats, the `LIST wavl` chunk and the streaming `WaveReader` are refused.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified, truncated or replaced in place by this or any other process while the `PcmView` is alive.
+    /// The view is a plain slice of the file, the changes would show through it behind the back of the borrow checker,
+    /// and accessing the pages that were cut off by shrinking the file is undefined behavior, e.g. the process gets killed by `SIGBUS`.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn map_pcm_data(&self) -> Result<PcmView<'_>, AudioReadError> {
+        use wavcore::format_tags::*;
+        use wavcore::guids::*;
+        use wavcore::WaveSampleType::{F32, F64, S16, S32, U8};
+        let fmt = &self.fmt__chunk;
+        let is_pcm = match (fmt.format_tag, &fmt.extension) {
+            (FORMAT_TAG_PCM | FORMAT_TAG_PCM_IEEE, _) => true,
+            (FORMAT_TAG_EXTENSIBLE, Some(FmtExtension{data: ExtensionData::Extensible(extensible), ..})) => {
+                if extensible.valid_bits_per_sample != fmt.bits_per_sample {
+                    return Err(AudioReadError::Unsupported(format!(
+                        "the extensible \"fmt \" chunk has {} valid bits in the {}-bit samples, they can't be viewed as they are",
+                        extensible.valid_bits_per_sample, fmt.bits_per_sample
+                    )));
+                }
+                matches!(extensible.sub_format, GUID_PCM_FORMAT | GUID_IEEE_FLOAT_FORMAT)
+            }
+            _ => false,
+        };
+        if !is_pcm {
+            return Err(AudioReadError::UnsupportedFormat(fmt.format_tag, self.get_sub_format_guid()));
+        }
+        let sample_type = fmt.get_sample_type();
+        if !matches!(sample_type, U8 | S16 | S32 | F32 | F64) {
+            return Err(AudioReadError::Unsupported(format!("the {sample_type} samples can't be mapped as a slice")));
+        }
+        if fmt.channels == 0 || fmt.block_align != fmt.channels * sample_type.sizeof() {
+            return Err(AudioReadError::InvalidData(format!(
+                "the block align {} doesn't fit {} channels of the {sample_type} samples",
+                fmt.block_align, fmt.channels
+            )));
+        }
+        // SAFETY: the caller promises that the file isn't modified while the view is alive.
+        let mmap = unsafe { self.data_chunk.map()? };
+        Ok(PcmView::new(mmap, sample_type, fmt.channels, fmt.block_align))
+    }
+
+    /// * The size of the `data` chunk content in bytes.
+    pub fn get_data_length(&self) -> u64 {
+        self.data_chunk.length
+    }
+
+    /// * Is the `data` chunk cut off by the end of the file, see `ReadOptions::allow_truncated`.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// * Is the `WaveReader` created by `from_reader()` that reads the audio from a stream, the audio can be decoded only once.
+    pub fn is_streaming(&self) -> bool {
+        self.data_chunk.is_stream()
+    }
+
     /// * The `slnt` chunk indicates how long to stay silent.
     pub fn get_slnt_chunk(&self) -> &Option<SlntChunk> {
         &self.slnt_chunk
@@ -483,11 +1679,16 @@ impl WaveReader {
         &self.cue__chunk
     }
 
-    /// * The `axml` chunk. I personally don't know what it is, by the name it looks like some kind of `audio XML`. It's a pure string chunk.
-    pub fn get_axml_chunk(&self) -> &Option<String> {
+    /// * The `axml` chunk of the Broadcast WAV, it's the XML metadata e.g. the ADM document written by the ADM tools.
+    pub fn get_axml_chunk(&self) -> &Option<AxmlChunk> {
         &self.axml_chunk
     }
 
+    /// * The `chna` chunk of the BW64, it maps the tracks to the track UIDs of the ADM document in the `axml` chunk.
+    pub fn get_chna_chunk(&self) -> &Option<ChnaChunk> {
+        &self.chna_chunk
+    }
+
     /// * The `ixml` chunk. I personally don't know what it is, by the name it looks like some kind of `info XML`. It's a pure string chunk.
     pub fn get_ixml_chunk(&self) -> &Option<String> {
         &self.ixml_chunk
@@ -500,19 +1701,208 @@ impl WaveReader {
         &self.list_chunk
     }
 
-    /// * The `acid` chunk, contains some pieces of information about the rhythm, e.g. `num_beats`, `tempo`, etc.
-    pub fn get_acid_chunk(&self) -> &Option<AcidChunk> {
-        &self.acid_chunk
+    /// * The `acid` chunk, contains some pieces of information about the rhythm, e.g. `num_beats`, `tempo`, etc.
+    pub fn get_acid_chunk(&self) -> &Option<AcidChunk> {
+        &self.acid_chunk
+    }
+
+    /// * Another metadata chunk for the audio file. This covers more metadata than the `LIST INFO` chunk.
+    /// * The `id3 ` chunk is parsed at the first call, the parse errors are printed and give `None`, use `id3()` to get the errors.
+    pub fn get_id3__chunk(&self) -> &Option<Id3::Tag> {
+        self.id3__chunk.get_or_init(|| self.id3__source.as_ref().and_then(|source| optional(Self::read_id3(source))))
+    }
+
+    /// * Parse the `id3 ` chunk on demand. Opening the WAV file only remembers where the chunk is, the tag and its pictures aren't loaded until this is called.
+    /// * The parsed tag is kept, the later calls and `get_id3__chunk()` don't parse it again.
+    pub fn id3(&mut self) -> Result<Option<Id3::Tag>, AudioReadError> {
+        if let Some(tag) = self.id3__chunk.get() {
+            return Ok(tag.clone());
+        }
+        let tag = match &self.id3__source {
+            Some(source) => Some(Self::read_id3(source)?),
+            None => None,
+        };
+        Ok(self.id3__chunk.get_or_init(|| tag).clone())
+    }
+
+    /// * Get the raw bytes of the `id3 ` chunk as a reader, to stream out the frames (e.g. a large `APIC` picture) without parsing the whole tag.
+    /// * Returns `None` if there's no `id3 ` chunk.
+    pub fn id3_raw_reader(&self) -> Result<Option<Box<dyn Read>>, AudioReadError> {
+        match &self.id3__source {
+            Some(source) => Ok(Some(Box::new(source.open()?.take(source.length)))),
+            None => Ok(None),
+        }
+    }
+
+    fn read_id3(source: &FileDataSource) -> Result<Id3::Tag, AudioReadError> {
+        Id3::id3_read(&mut source.open()?, source.length as usize)
+    }
+
+    /// * Remember where the bytes of a chunk are to read it later, the chunk is copied into a temporary file if the WAV file isn't opened from a file path.
+    fn chunk_source(
+        reader: &mut Box<dyn Reader>,
+        filesrc: &Option<String>,
+        chunk_offset: u64,
+        chunk_size: u64,
+        reader_seekable: bool,
+        cur_pos: &mut u64,
+    ) -> Result<FileDataSource, AudioReadError> {
+        match filesrc {
+            Some(filename) => FileDataSource::new(None, Some(filename.clone()), chunk_offset, chunk_size, reader_seekable, cur_pos),
+            None => FileDataSource::new(Some(&mut **reader), None, chunk_offset, chunk_size, reader_seekable, cur_pos),
+        }
+    }
+
+    /// * Get a metadata text by the `LIST INFO` key, e.g. `INAM` for the title.
+    /// * If both the `id3 ` chunk and the `LIST INFO` chunk have it, the ID3 one is preferred because its text is Unicode,
+    ///   while the `LIST INFO` text may be decoded from a code page and lose some characters.
+    pub fn get_metadata_text(&self, key: &str) -> Option<String> {
+        if let Some(tag) = self.get_id3__chunk() {
+            if let Some(text) = Id3::get_info_text(tag, key) {
+                return Some(text);
+            }
+        }
+        self.list_chunk.iter().find_map(|chunk| chunk.get(key).cloned())
+    }
+
+    /// * Get the aggregate view of the `id3 `, `LIST INFO`, `bext`, `trkn` and `acid` chunks.
+    /// * The common fields are taken by the precedence of `MetadataSource`, the disagreeing values are kept in `Metadata::other`.
+    /// * The `bext` description and origination date fill the comment and the date, the `trkn` track number fills the track.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+        if let Some(tag) = self.get_id3__chunk() {
+            let mut common_frames = Vec::<&str>::new();
+            for (field, info_key) in METADATA_FIELDS.iter() {
+                if let Some(text) = Id3::get_info_text(tag, info_key) {
+                    metadata.add(MetadataSource::Id3, field, &text);
+                }
+                if let Some((_, frame_id)) = wavcore::INFO_TO_ID3_FRAMES.iter().find(|(key, _)| key == info_key) {
+                    common_frames.push(*frame_id);
+                }
+            }
+            for (frame_id, text) in Id3::get_text_frames(tag) {
+                if !common_frames.contains(&frame_id.as_str()) {
+                    metadata.add(MetadataSource::Id3, &frame_id, &text);
+                }
+            }
+        }
+        for chunk in self.list_chunk.iter() {
+            if let ListChunk::Info(dict) = chunk {
+                for (key, value) in dict.iter() {
+                    match METADATA_FIELDS.iter().find(|(_, info_key)| *info_key == key.as_str()) {
+                        Some((field, _)) => metadata.add(MetadataSource::ListInfo, field, value),
+                        None => metadata.add(MetadataSource::ListInfo, key, value),
+                    }
+                }
+            }
+        }
+        if let Some(bext) = &self.bext_chunk {
+            metadata.add(MetadataSource::Bext, "comment", &bext.description);
+            metadata.add(MetadataSource::Bext, "date", &bext.origination_date);
+            metadata.add(MetadataSource::Bext, "originator", &bext.originator);
+            metadata.add(MetadataSource::Bext, "originator_ref", &bext.originator_ref);
+            metadata.add(MetadataSource::Bext, "origination_time", &bext.origination_time);
+        }
+        if let Some(trkn) = &self.trkn_chunk {
+            metadata.add(MetadataSource::Trkn, "track", &format!("{}", trkn.track_no));
+            metadata.add(MetadataSource::Trkn, "total_tracks", &format!("{}", trkn.total_tracks));
+        }
+        if let Some(acid) = &self.acid_chunk {
+            metadata.add(MetadataSource::Acid, "tempo", &format!("{}", acid.tempo));
+            metadata.add(MetadataSource::Acid, "num_beats", &format!("{}", acid.num_beats));
+            metadata.add(MetadataSource::Acid, "meter", &format!("{}/{}", acid.meter_numerator, acid.meter_denominator));
+            metadata.add(MetadataSource::Acid, "root_node", &format!("{}", acid.root_node));
+        }
+        metadata
+    }
+
+    /// * The `JUNK` chunk, sometimes it's used for placeholder, sometimes it contains some random data for some random music software to show off.
+    pub fn get_junk_chunks(&self) -> &BTreeSet<JunkChunk> {
+        &self.junk_chunks
+    }
+
+    /// * The problems found in the file structure that were worked around when parsing it, e.g. the missing pad byte after an odd-sized chunk.
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// * Check the file for the problems that other readers may trip over, e.g. the wrong `byte_rate` and `block_align`, the `fact` chunk that disagrees with the `data` chunk, the truncated `data` chunk.
+    /// * It only checks what was parsed, nothing is decoded. Fix the header fields by `WaveWriter::repair_from_reader()`.
+    /// * Open the file with `ReadOptions::allow_truncated` to validate the truncated files.
+    pub fn validate(&self) -> ValidationReport {
+        validate::validate(self)
+    }
+
+    /// * Gather the headers and the parsed chunks into a `FileDescription`, e.g. for a tool to print them as JSON with the `serde` feature.
+    /// * Nothing is decoded, the `id3 ` chunk is parsed for the metadata as `metadata()` does.
+    pub fn describe(&self) -> FileDescription {
+        FileDescription {
+            spec: self.spec,
+            fmt__chunk: self.fmt__chunk.clone(),
+            storage_info: self.storage_info.clone(),
+            fact_data: self.fact_data,
+            start_frame: self.start_frame,
+            truncated: self.truncated,
+            chunk_map: self.chunk_map.clone(),
+            slnt_chunk: self.slnt_chunk,
+            bext_chunk: self.bext_chunk.clone(),
+            smpl_chunk: self.smpl_chunk.clone(),
+            inst_chunk: self.inst_chunk,
+            plst_chunk: self.plst_chunk.clone(),
+            trkn_chunk: self.trkn_chunk,
+            cue__chunk: self.cue__chunk.clone(),
+            axml_chunk: self.axml_chunk.clone(),
+            chna_chunk: self.chna_chunk.clone(),
+            ixml_chunk: self.ixml_chunk.clone(),
+            list_chunk: self.list_chunk.iter().cloned().collect(),
+            acid_chunk: self.acid_chunk.clone(),
+            id3__length: self.id3__source.as_ref().map(|source| source.length),
+            junk_chunks: self.junk_chunks.iter().cloned().collect(),
+            metadata: self.metadata(),
+            warnings: self.warnings.clone(),
+        }
     }
 
-    /// * Another metadata chunk for the audio file. This covers more metadata than the `LIST INFO` chunk.
-    pub fn get_id3__chunk(&self) -> &Option<Id3::Tag> {
-        &self.id3__chunk
+    /// * All of the top-level chunks found in the file, in the order of their positions.
+    /// * Compare it with the `FinalizeReport` from the `WaveWriter` to verify the file layout.
+    pub fn chunk_map(&self) -> &[ChunkInfo] {
+        &self.chunk_map
     }
 
-    /// * The `JUNK` chunk, sometimes it's used for placeholder, sometimes it contains some random data for some random music software to show off.
-    pub fn get_junk_chunks(&self) -> &BTreeSet<JunkChunk> {
-        &self.junk_chunks
+    /// * Read the payload of a top-level chunk in the `chunk_map()`, e.g. the proprietary chunks that the `WaveReader` doesn't parse. Preserve them by `WaveWriter::write_raw_chunk()`.
+    /// * The `index` counts the chunks with the same `flag`, `0` for the first one.
+    /// * If the file was opened from a file path, any chunk is read from the file on demand.
+    ///   Otherwise, only the unknown chunks were kept when parsing, the other chunks return `Unsupported`.
+    pub fn read_raw_chunk(&self, flag: &[u8; 4], index: usize) -> Result<Vec<u8>, AudioReadError> {
+        let Some(info) = self.chunk_map.iter().filter(|info| &info.flag == flag).nth(index) else {
+            return Err(AudioReadError::MissingData(format!(
+                "there's no \"{}\" chunk #{index} in the file",
+                String::from_utf8_lossy(flag)
+            )));
+        };
+        let reader: Box<dyn Read> = if let Some(path) = &self.source_path {
+            let mut file = open_spool_file(path)?;
+            file.seek(SeekFrom::Start(info.offset + 8))?;
+            Box::new(file)
+        } else if let Some(source) = self.unknown_chunks.get(&info.offset) {
+            Box::new(source.open()?)
+        } else {
+            return Err(AudioReadError::Unsupported(format!(
+                "the \"{}\" chunk wasn't kept, reading it requires the file path",
+                String::from_utf8_lossy(flag)
+            )));
+        };
+        let mut buf = Vec::<u8>::new();
+        BufReader::new(reader).take(info.length).read_to_end(&mut buf)?;
+        if buf.len() as u64 != info.length {
+            return Err(AudioReadError::IncompleteData(format!(
+                "the \"{}\" chunk claims {} bytes, but only {} bytes are in the file",
+                String::from_utf8_lossy(flag),
+                info.length,
+                buf.len()
+            )));
+        }
+        Ok(buf)
     }
 
     /// * If your audio file has `plst`, `cue `, and `LIST adtl` chunks, then BAM you can call this function for full playlist info.
@@ -545,6 +1935,124 @@ impl WaveReader {
         )))
     }
 
+    /// * Walk through the `data` chunks and the `slnt` chunks inside the `LIST wavl` chunk from `start` to `end`.
+    /// * The `slnt` chunk has the number of the silent sample frames, only PCM audio data can be silent by filling the bytes.
+    fn read_wavl(reader: &mut impl Reader, start: u64, end: u64, fmt: &FmtChunk) -> Result<Vec<DataSegment>, AudioReadError> {
+        use wavcore::format_tags::*;
+        let mut segments = Vec::<DataSegment>::new();
+        let mut position = start;
+        while position + 8 <= end {
+            reader.seek(SeekFrom::Start(position))?;
+            let chunk = ChunkHeader::read_unseekable(reader, &mut position)?;
+            match &chunk.flag {
+                b"data" => segments.push(DataSegment {
+                    offset: Some(chunk.chunk_start_pos),
+                    length: min(chunk.size as u64, end - chunk.chunk_start_pos),
+                }),
+                b"slnt" => {
+                    if !matches!(fmt.format_tag, FORMAT_TAG_PCM | FORMAT_TAG_PCM_IEEE | FORMAT_TAG_EXTENSIBLE) {
+                        return Err(AudioReadError::Unsupported(format!(
+                            "the \"slnt\" chunk in the \"LIST wavl\" chunk with the format tag 0x{:04x}, only PCM audio data can be silent",
+                            fmt.format_tag
+                        )));
+                    }
+                    let num_frames = u32::read_le(reader)?;
+                    segments.push(DataSegment {
+                        offset: None,
+                        length: num_frames as u64 * fmt.block_align as u64,
+                    });
+                }
+                other => eprintln!(
+                    "Skipped an unknown chunk in the \"LIST wavl\" chunk: '{}', Position: 0x{:x}",
+                    String::from_utf8_lossy(other),
+                    chunk.chunk_start_pos - 8
+                ),
+            }
+            position = chunk.next_chunk_pos();
+        }
+        Ok(segments)
+    }
+
+    /// * Find where the next chunk is after the chunk that ends at `chunk_end`.
+    /// * The chunk of an odd size should be followed by a pad byte, but some tools don't write it.
+    ///   If the FourCC isn't after the pad byte but right after the chunk, the next chunk is taken from there, and a warning is recorded.
+    fn next_chunk_position(reader: &mut impl Reader, chunk_end: u64, riff_end: u64, warnings: &mut Vec<DecodeWarning>) -> Result<u64, AudioReadError> {
+        let aligned = ChunkHeader::align(chunk_end);
+        if aligned == chunk_end {
+            return Ok(aligned);
+        }
+        // The RIFF size without the last pad byte, or the truncated file, ends right after the chunk.
+        if chunk_end >= riff_end {
+            return Ok(chunk_end);
+        }
+        if chunk_end + 8 > riff_end {
+            return Ok(aligned);
+        }
+
+        // Check the FourCC and the chunk size at both positions, the padded position wins if both look like a chunk.
+        let mut peek = [0u8; 9];
+        reader.seek(SeekFrom::Start(chunk_end))?;
+        let peeked = reader.read(&mut peek)?;
+        let looks_like_chunk = |offset: usize| -> bool {
+            if offset + 8 > peeked {
+                return false;
+            }
+            let flag = &peek[offset..offset + 4];
+            let size = u32::from_le_bytes([peek[offset + 4], peek[offset + 5], peek[offset + 6], peek[offset + 7]]) as u64;
+            flag[0] != b' ' && flag.iter().all(|c| (0x20..=0x7E).contains(c)) && chunk_end + offset as u64 + 8 + size <= riff_end
+        };
+        if !looks_like_chunk(1) && looks_like_chunk(0) {
+            warnings.push(DecodeWarning::new(DecodeWarningKind::ChunkLayout, chunk_end, format!(
+                "The pad byte after the odd-sized chunk is missing, the \"{}\" chunk is right after it",
+                String::from_utf8_lossy(&peek[0..4])
+            )));
+            return Ok(chunk_end);
+        }
+        Ok(aligned)
+    }
+
+    /// * Can the `data` chunk be decoded by the `fmt ` chunk, it tells the damaged one for `ReadOptions::assume_spec`.
+    fn is_fmt_usable(fmt: &FmtChunk, read_options: &ReadOptions) -> bool {
+        let known = StorageInfo::codec_of(fmt).0 != "Unknown"
+            || read_options.custom_decoder.is_some_and(|custom_decoder| custom_decoder.matches(fmt));
+        known
+            && fmt.channels > 0
+            && fmt.sample_rate > 0
+            && fmt.block_align > 0
+            && validate::fixed_block_align(fmt).is_none_or(|block_align| block_align == fmt.block_align)
+    }
+
+    /// * The `fmt ` chunk of the PCM samples of the spec for `ReadOptions::assume_spec`, extensible if the spec has a channel mask.
+    fn assumed_fmt_chunk(spec: &Spec) -> Result<FmtChunk, AudioReadError> {
+        use wavcore::format_tags::*;
+        use wavcore::guids::*;
+        use wavcore::WaveSampleType::{F32, F64, S16, S24, S32, S64, U8};
+        let sample_type = spec.get_sample_type();
+        if spec.channels == 0 || spec.sample_rate == 0 || !matches!(sample_type, U8 | S16 | S24 | S32 | S64 | F32 | F64) {
+            return Err(AudioReadError::InvalidArguments(format!("The assumed spec isn't PCM that can be decoded: {spec:?}")));
+        }
+        let is_float = matches!(sample_type, F32 | F64);
+        let block_align = spec.channels * sample_type.sizeof();
+        let extension = spec.channel_mask.map(|channel_mask| FmtExtension::new_extensible(wavcore::ExtensibleData {
+            valid_bits_per_sample: spec.bits_per_sample,
+            channel_mask,
+            sub_format: if is_float { GUID_IEEE_FLOAT_FORMAT } else { GUID_PCM_FORMAT },
+        }));
+        Ok(FmtChunk {
+            format_tag: match (&extension, is_float) {
+                (Some(_), _) => FORMAT_TAG_EXTENSIBLE,
+                (None, true) => FORMAT_TAG_PCM_IEEE,
+                (None, false) => FORMAT_TAG_PCM,
+            },
+            channels: spec.channels,
+            sample_rate: spec.sample_rate,
+            byte_rate: spec.sample_rate * block_align as u32,
+            block_align,
+            bits_per_sample: spec.bits_per_sample,
+            extension,
+        })
+    }
+
     /// * To verify if a chunk had not read. Some chunks should not be duplicated.
     fn no_duplication<T>(o: &Option<T>, flag: &[u8; 4]) -> Result<(), AudioReadError> {
         if o.is_some() {
@@ -572,6 +2080,237 @@ impl WaveReader {
         }
     }
 
+    /// * Seek to the audio frame at `frame`, the iterators created afterwards start decoding from there.
+    /// * Returns the frame index actually landed on, seeking beyond the end lands on the end of the audio data.
+    /// * For the streaming `WaveReader` created by `from_reader()`, the position can't be verified without consuming the stream,
+    ///   so `frame` is returned as is, and the iterator skips forward to it when created.
+    pub fn seek_to_frame(&mut self, frame: u64) -> Result<u64, AudioReadError> {
+        self.pull_cursor = None;
+        if self.data_chunk.is_stream() {
+            self.start_frame = frame;
+            return Ok(frame);
+        }
+        let mut reader = self.data_chunk.open()?;
+        reader.seek(SeekFrom::Start(self.data_chunk.offset))?;
+        let mut decoder = create_format_decoder::<i16>(
+            reader,
+            self.data_chunk.offset,
+            self.data_chunk.length,
+            self.spec,
+            &self.fmt__chunk,
+            self.fact_data,
+            self.read_options.on_decode_error,
+        )?;
+        decoder.seek(SeekFrom::Start(frame))?;
+        self.start_frame = decoder.get_cur_frame_index()?;
+        Ok(self.start_frame)
+    }
+
+    /// * Get the frame index that the iterators start decoding from, see `seek_to_frame()`.
+    pub fn get_start_frame(&self) -> u64 {
+        self.start_frame
+    }
+
+    /// * Decode the audio from the start frame to the end, measure the peak, the RMS and the integrated loudness of each channel, see `LevelStats`.
+    /// * It decodes in batches, the memory used doesn't grow with the audio, except the integrated loudness keeps one number for each 100 ms.
+    /// * It works with any format that the iterators can decode. The start frame isn't changed, the iterators created afterwards still start from it.
+    /// * For the streaming `WaveReader` created by `from_reader()`, it consumes the stream, the audio can't be decoded again.
+    pub fn scan_levels(&mut self) -> Result<LevelStats, AudioReadError> {
+        levels::scan_levels(self, None)
+    }
+
+    /// * The same as `scan_levels()`, but it stops with `AudioReadError::Cancelled` at the next batch after the `cancel` token is cancelled.
+    pub fn scan_levels_cancellable(&mut self, cancel: &CancelToken) -> Result<LevelStats, AudioReadError> {
+        levels::scan_levels(self, Some(cancel))
+    }
+
+    /// * Check the MD5 in the `hash` chunk written by `WaveWriter::enable_audio_hash()` by decoding all of the audio again, returns `false` if the audio was changed.
+    /// * The files without the `hash` chunk return `MissingData`. The streaming `WaveReader` can't decode the audio again, it returns `Unsupported`.
+    pub fn verify_embedded_hash(&mut self) -> Result<bool, AudioReadError> {
+        let chunk = self.read_raw_chunk(b"hash", 0)?;
+        if chunk.len() != 20 || &chunk[0..4] != b"MD5 " {
+            return Err(AudioReadError::Unsupported(format!(
+                "Unknown audio hash \"{}\" of {} bytes.",
+                String::from_utf8_lossy(&chunk[..chunk.len().min(4)]),
+                chunk.len()
+            )));
+        }
+        if self.data_chunk.is_stream() {
+            return Err(AudioReadError::Unsupported(
+                "The streaming `WaveReader` can't decode the audio again to verify the hash.".to_owned(),
+            ));
+        }
+        use crate::wavcore::WaveSampleType::{F32, F64, S8, S16, S24, S32, S64, U8, U16, U24, U32, U64, Unknown};
+        use crate::{i24, u24};
+        let digest = match self.spec.get_sample_type() {
+            S8  => self.hash_decoded::< i8>()?,
+            S16 => self.hash_decoded::<i16>()?,
+            S24 => self.hash_decoded::<i24>()?,
+            S32 => self.hash_decoded::<i32>()?,
+            S64 => self.hash_decoded::<i64>()?,
+            U8  => self.hash_decoded::< u8>()?,
+            U16 => self.hash_decoded::<u16>()?,
+            U24 => self.hash_decoded::<u24>()?,
+            U32 => self.hash_decoded::<u32>()?,
+            U64 => self.hash_decoded::<u64>()?,
+            F32 => self.hash_decoded::<f32>()?,
+            F64 => self.hash_decoded::<f64>()?,
+            Unknown => {
+                return Err(AudioReadError::Unsupported(format!(
+                    "Can't verify the audio hash of the {}-bit {:?} samples.",
+                    self.spec.bits_per_sample, self.spec.sample_format
+                )));
+            }
+        };
+        Ok(digest.0[..] == chunk[4..20])
+    }
+
+    /// * The MD5 of all of the decoded samples in the little-endian bytes, from the beginning of the audio regardless of `seek_to_frame()`.
+    fn hash_decoded<S>(&self) -> Result<md5::Digest, AudioReadError>
+    where
+        S: SampleType + 'static,
+    {
+        const HASH_BLOCK_SAMPLES: usize = 65536;
+        let mut reader = self.data_chunk.open()?;
+        reader.seek(SeekFrom::Start(self.data_chunk.offset))?;
+        let mut decoder = create_decoder::<S>(
+            reader,
+            self.data_chunk.offset,
+            self.data_chunk.length,
+            self.spec,
+            &self.fmt__chunk,
+            self.fact_data,
+            0,
+            &self.read_options,
+        )?;
+        let mut context = md5::Context::new();
+        let mut buf = vec![S::new(); HASH_BLOCK_SAMPLES];
+        loop {
+            let decoded = decoder.decode_interleaved(&mut buf)?;
+            if decoded == 0 {
+                break;
+            }
+            for sample in buf[..decoded].iter() {
+                sample.write_le(&mut context)?;
+            }
+        }
+        Ok(context.compute())
+    }
+
+    /// * Read `count` frames from the frame index `start`, which is counted from the beginning of the audio regardless of `seek_to_frame()`.
+    /// * Returns fewer frames only at the end of the audio, and an empty `Vec` if `start` is beyond the end.
+    /// * The decoded frames are cached by blocks of 4096 frames, so the repeated and nearby reads e.g. repainting a waveform view don't decode again.
+    ///   Reading on from the last read continues with the same decoder, otherwise the decoder seeks, or decodes forward if the format can't seek.
+    /// * For the streaming `WaveReader` created by `from_reader()`, the audio can only be read forward, except for the cached blocks.
+    pub fn read_frames_range<S>(&mut self, start: u64, count: usize) -> Result<Vec<Vec<S>>, AudioReadError>
+    where
+        S: SampleType + 'static,
+    {
+        let end = start.saturating_add(count as u64);
+        self.frame_cache.switch_type::<S>();
+        if self.frame_cache.end_frame.is_none() {
+            self.frame_cache.end_frame = self.total_frames();
+        }
+        let mut frames = Vec::new();
+        let mut frame = start;
+        while frame < end {
+            if self.frame_cache.end_frame.is_some_and(|end_frame| frame >= end_frame) {
+                break;
+            }
+            let block = frame / RANGE_CACHE_BLOCK_FRAMES;
+            if self.frame_cache.get::<S>(block).is_none() {
+                let decoded = self.decode_range_block::<S>(block)?;
+                self.frame_cache.insert(block, decoded);
+            }
+            let Some(decoded) = self.frame_cache.get::<S>(block) else {
+                break;
+            };
+            let offset = (frame - block * RANGE_CACHE_BLOCK_FRAMES) as usize;
+            if offset >= decoded.len() {
+                break;
+            }
+            let num_frames = (decoded.len() - offset).min((end - frame) as usize);
+            frames.extend_from_slice(&decoded[offset..offset + num_frames]);
+            frame += num_frames as u64;
+        }
+        Ok(frames)
+    }
+
+    /// * Decode the interleaved samples into `buf`, like `std::io::Read::read()` for the audio. It's for the real-time audio callback that has its own buffer.
+    /// * Decodes exactly `buf.len() / channels` frames, fewer only at the end of the audio. Returns the number of the samples written, `0` at the end.
+    /// * The first call starts from the start frame, the following calls continue from where the last call stopped, until `seek_to_frame()` is called.
+    ///   It keeps its own decoder, the iterators are not affected by it, and vice versa.
+    /// * The PCM, aLaw/MuLaw and ADPCM audio is decoded without allocating, except for the first call. The other formats go through their per-frame decoding.
+    /// * Calling it with a different sample type continues from the same frame with a new decoder. After an error, the next call starts over from the start frame.
+    pub fn decode_into<S>(&mut self, buf: &mut [S]) -> Result<usize, AudioReadError>
+    where
+        S: SampleType + 'static,
+    {
+        let (next_frame, decoder) = match self.pull_cursor.take() {
+            Some((next_frame, decoder)) => (next_frame, decoder.downcast::<Box<dyn Decoder<S>>>().ok().map(|decoder| *decoder)),
+            None => (self.start_frame, None),
+        };
+        let mut decoder = match decoder {
+            Some(decoder) => decoder,
+            None => {
+                let mut reader = self.data_chunk.open()?;
+                reader.seek(SeekFrom::Start(self.data_chunk.offset))?;
+                create_decoder::<S>(
+                    reader,
+                    self.data_chunk.offset,
+                    self.data_chunk.length,
+                    self.spec,
+                    &self.fmt__chunk,
+                    self.fact_data,
+                    next_frame,
+                    &self.read_options,
+                )?
+            }
+        };
+        let written = decoder.decode_interleaved(buf)?;
+        let next_frame = next_frame + (written / self.spec.channels.max(1) as usize) as u64;
+        self.pull_cursor = Some((next_frame, Box::new(decoder)));
+        Ok(written)
+    }
+
+    /// * Decode a block for `read_frames_range()`, by the cursor decoder if it's not far behind, otherwise by a new decoder.
+    fn decode_range_block<S>(&mut self, block: u64) -> Result<Vec<Vec<S>>, AudioReadError>
+    where
+        S: SampleType + 'static,
+    {
+        let block_start = block * RANGE_CACHE_BLOCK_FRAMES;
+        let is_stream = self.data_chunk.is_stream();
+        let mut decoder = match self.frame_cache.take_cursor::<S>() {
+            Some((next_frame, mut decoder)) if next_frame <= block_start && (is_stream || block_start - next_frame <= RANGE_CACHE_MAX_SKIP_FRAMES) => {
+                decoder.skip_frames(block_start - next_frame)?;
+                decoder
+            }
+            _ => {
+                let mut reader = self.data_chunk.open()?;
+                reader.seek(SeekFrom::Start(self.data_chunk.offset))?;
+                let mut decoder = create_decoder::<S>(
+                    reader,
+                    self.data_chunk.offset,
+                    self.data_chunk.length,
+                    self.spec,
+                    &self.fmt__chunk,
+                    self.fact_data,
+                    0,
+                    &self.read_options,
+                )?;
+                decoder.skip_frames(block_start)?;
+                decoder
+            }
+        };
+        let decoded = decoder.decode_frames(RANGE_CACHE_BLOCK_FRAMES as usize)?;
+        let next_frame = block_start + decoded.len() as u64;
+        if decoded.len() < RANGE_CACHE_BLOCK_FRAMES as usize {
+            self.frame_cache.end_frame = Some(self.frame_cache.end_frame.map_or(next_frame, |end_frame| end_frame.min(next_frame)));
+        }
+        self.frame_cache.cursor = Some((next_frame, Box::new(decoder)));
+        Ok(decoded)
+    }
+
     /// * Create an iterator for iterating through each audio frame, excretes multi-channel audio frames.
     /// * Every audio frame is an array that includes one sample for every channel.
     /// * This iterator supports multi-channel audio files e.g. 5.1 stereo or 7.1 stereo audio files.
@@ -588,9 +2327,49 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 
+    /// * Create an iterator like `frame_iter()`, but it jumps back to the start of the first loop of the `smpl` chunk at the end of the loop, see `LoopPolicy`.
+    /// * The loop boundaries are frame-exact, the last frame of the loop is followed by the first frame of the loop. There's no crossfading.
+    /// * Without the `smpl` chunk or the loop, or if the `data` chunk is from a stream that can't be read again, it's the same as `frame_iter()`.
+    pub fn looped_frame_iter<S>(&mut self, loop_policy: LoopPolicy) -> Result<LoopedFrameIter<S>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let total_frames = self.total_frames();
+        let loop_range = self.smpl_chunk.as_ref().and_then(|smpl| smpl.loops.first()).and_then(|sample_loop| {
+            // The end of the loop in the `smpl` chunk is the last frame to play.
+            let (start, end) = (sample_loop.start as u64, sample_loop.end as u64 + 1);
+            (start < end && total_frames.is_none_or(|total_frames| end <= total_frames)).then_some((start, end))
+        }).filter(|_| !self.data_chunk.is_stream());
+        LoopedFrameIter::<S>::new(
+            &self.data_chunk,
+            self.data_chunk.offset,
+            self.data_chunk.length,
+            self.spec,
+            &self.fmt__chunk,
+            self.fact_data,
+            self.start_frame,
+            &self.read_options,
+            loop_range,
+            loop_policy,
+        )
+    }
+
+    /// * Create an iterator that excretes the blocks of `frames_per_block` interleaved audio frames, e.g. for the resampler and the FFT-based processing that want the constant-size blocks.
+    /// * The last block is padded as `pad_mode` says, and `FrameBlock::valid_frames` tells how many frames of the block are from the audio.
+    /// * `BlockIter::next_block()` decodes into the same buffer every time, the `Iterator` clones the block out of it.
+    pub fn block_iter<S>(&mut self, frames_per_block: usize, pad_mode: PadMode) -> Result<BlockIter<S>, AudioReadError>
+    where
+        S: SampleType,
+    {
+        let channels = self.spec.channels;
+        BlockIter::<S>::new(self.frame_iter::<S>()?, channels, frames_per_block, pad_mode)
+    }
+
     /// * Create an iterator for iterating through each audio frame, excretes mono-channel samples.
     /// * This iterator is dedicated to mono audio, it combines every channel into one channel and excretes every single sample as an audio frame.
     /// * Besides it's an iterator, the struct itself provides `decode_frames()` for batch decode multiple samples.
@@ -605,6 +2384,8 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 
@@ -622,6 +2403,8 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 
@@ -641,6 +2424,8 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 
@@ -658,6 +2443,8 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 
@@ -675,6 +2462,8 @@ impl WaveReader {
             self.spec,
             &self.fmt__chunk,
             self.fact_data,
+            self.start_frame,
+            &self.read_options,
         )
     }
 }
@@ -694,6 +2483,7 @@ fn expect_flag<T: Read>(
     flag: &[u8; 4],
     cur_pos: &mut u64,
 ) -> Result<(), AudioReadError> {
+    let flag_pos = *cur_pos;
     let mut buf = [0u8; 4];
     r.read_exact(&mut buf)?;
     *cur_pos += 4;
@@ -701,13 +2491,14 @@ fn expect_flag<T: Read>(
         Err(AudioReadError::UnexpectedFlag(
             String::from_utf8_lossy(flag).to_string(),
             String::from_utf8_lossy(&buf).to_string(),
-        ))
+        ).in_chunk(ChunkContext::new(None, Some(flag_pos))))
     } else {
         Ok(())
     }
 }
 
-/// * Create the decoder for each specific `format_tag` in the `fmt` chunk.
+/// * Create the decoder for the `fmt` chunk, if the `ReadOptions` has a decode budget, the decoder is wrapped to enforce it.
+/// * The decoder starts from `start_frame`, which was set by `WaveReader::seek_to_frame()`.
 fn create_decoder<S>(
     reader: Box<dyn Reader>,
     data_offset: u64,
@@ -715,6 +2506,90 @@ fn create_decoder<S>(
     spec: Spec,
     fmt: &FmtChunk,
     fact_data: u64,
+    start_frame: u64,
+    read_options: &ReadOptions,
+) -> Result<Box<dyn Decoder<S>>, AudioReadError>
+where
+    S: SampleType,
+{
+    let mut decoder: Box<dyn Decoder<S>> = match read_options.custom_decoder {
+        Some(custom_decoder) if custom_decoder.matches(fmt) => {
+            Box::new(CustomDecoderWrap::<S>::new((custom_decoder.create)(reader, data_offset, data_length, fmt, fact_data)?))
+        }
+        _ => create_format_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, read_options.on_decode_error)?,
+    };
+    if start_frame > 0 {
+        decoder.seek(SeekFrom::Start(start_frame))?;
+    }
+    if read_options.has_decode_budget() {
+        Ok(Box::new(BudgetedDecoder::new(
+            decoder,
+            read_options.max_decoded_frames,
+            read_options.max_decode_wall_time,
+        )))
+    } else {
+        Ok(decoder)
+    }
+}
+
+/// * Create the decoder for each specific `format_tag` in the `fmt` chunk.
+/// * Get the number of the audio frames without decoding. For PCM it's from the size of the `data` chunk.
+/// * For the other formats it's from the `fact` chunk, without the `fact` chunk it's calculated from the blocks if possible.
+/// * For the formats that the blocks don't tell the length, e.g. MP3, FLAC and Vorbis without the `fact` chunk, it's `None`.
+fn get_total_frames(fmt: &FmtChunk, data_length: u64, fact_data: u64) -> Option<u64> {
+    use wavcore::format_tags::*;
+    use wavcore::guids::*;
+    if fmt.channels == 0 || fmt.block_align == 0 {
+        return None;
+    }
+    let block_align = fmt.block_align as u64;
+    let num_blocks = data_length / block_align;
+    let adpcm_frames = |frames_per_block: io::Result<usize>| -> Option<u64> {
+        frames_per_block.ok().map(|frames_per_block| num_blocks * frames_per_block as u64)
+    };
+    match fmt.format_tag {
+        FORMAT_TAG_PCM | FORMAT_TAG_PCM_IEEE => Some(num_blocks),
+        FORMAT_TAG_EXTENSIBLE | FORMAT_TAG_DEVELOPMENT => match &fmt.extension {
+            Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. }) => match extensible.sub_format {
+                GUID_PCM_FORMAT |
+                GUID_IEEE_FLOAT_FORMAT |
+                GUID_AMBISONIC_B_FORMAT_PCM |
+                GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT => Some(num_blocks),
+                _ => None,
+            },
+            _ => Some(num_blocks),
+        },
+        _ if fact_data > 0 => Some(fact_data / fmt.channels as u64),
+        FORMAT_TAG_ALAW | FORMAT_TAG_MULAW => Some(num_blocks),
+        FORMAT_TAG_ADPCM_MS => adpcm_frames(DecMS::new(fmt).map(|dec| dec.frames_per_block())),
+        FORMAT_TAG_ADPCM_IMA | FORMAT_TAG_ADPCM_IMA_ => match fmt.bits_per_sample {
+            4 => adpcm_frames(DecIMA::new(fmt).map(|dec| dec.frames_per_block())),
+            _ => adpcm_frames(DecIMABits::new(fmt).map(|dec| dec.frames_per_block())),
+        },
+        FORMAT_TAG_ADPCM_YAMAHA => adpcm_frames(DecYAMAHA::new(fmt).map(|dec| dec.frames_per_block())),
+        FORMAT_TAG_GSM610 => Some(num_blocks * gsm610::SAMPLES_PER_BLOCK as u64),
+        FORMAT_TAG_OPUS => match &fmt.extension {
+            // The size-prefixed packets have to be scanned to count them.
+            Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. }) if opus_data.frames_per_packet > 0 => None,
+            Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. }) => {
+                let frames_per_block = block_align / fmt.channels as u64;
+                Some((num_blocks * frames_per_block).saturating_sub(opus_data.padding as u64))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// * The `on_decode_error` policy is for the compressed decoders, the other decoders don't use it.
+fn create_format_decoder<S>(
+    reader: Box<dyn Reader>,
+    data_offset: u64,
+    data_length: u64,
+    spec: Spec,
+    fmt: &FmtChunk,
+    fact_data: u64,
+    on_decode_error: DecodeErrorPolicy,
 ) -> Result<Box<dyn Decoder<S>>, AudioReadError>
 where
     S: SampleType,
@@ -752,14 +2627,30 @@ where
             fmt,
             fact_data,
         )?)),
-        FORMAT_TAG_ADPCM_IMA | FORMAT_TAG_ADPCM_IMA_ => Ok(Box::new(AdpcmDecoderWrap::<DecIMA>::new(
+        FORMAT_TAG_ADPCM_IMA | FORMAT_TAG_ADPCM_IMA_ => match fmt.bits_per_sample {
+            4 => Ok(Box::new(AdpcmDecoderWrap::<DecIMA>::new(
+                reader,
+                data_offset,
+                data_length,
+                fmt,
+                fact_data
+            )?)),
+            _ => Ok(Box::new(AdpcmDecoderWrap::<DecIMABits>::new(
+                reader,
+                data_offset,
+                data_length,
+                fmt,
+                fact_data
+            )?)),
+        },
+        FORMAT_TAG_ADPCM_YAMAHA => Ok(Box::new(AdpcmDecoderWrap::<DecYAMAHA>::new(
             reader,
             data_offset,
             data_length,
             fmt,
-            fact_data
+            fact_data,
         )?)),
-        FORMAT_TAG_ADPCM_YAMAHA => Ok(Box::new(AdpcmDecoderWrap::<DecYAMAHA>::new(
+        FORMAT_TAG_GSM610 => Ok(Box::new(Gsm610DecoderWrap::new(
             reader,
             data_offset,
             data_length,
@@ -774,6 +2665,7 @@ where
                 data_length,
                 fmt,
                 fact_data,
+                on_decode_error,
             )?));
             #[cfg(not(feature = "mp3dec"))]
             return Err(AudioReadError::Unimplemented(String::from(
@@ -788,6 +2680,7 @@ where
                 data_length,
                 fmt,
                 fact_data,
+                on_decode_error,
             )?));
             #[cfg(not(feature = "opus"))]
             return Err(AudioReadError::Unimplemented(String::from(
@@ -826,34 +2719,130 @@ where
                 fmt,
                 fact_data,
                 None,
+                on_decode_error,
             )?));
             #[cfg(not(any(feature = "vorbis", feature = "oggvorbis")))]
             return Err(AudioReadError::Unimplemented(String::from(
                 "not implemented for decoding ogg vorbis audio data inside the WAV file",
             )));
         }
-        FORMAT_TAG_EXTENSIBLE => Ok(ExtensibleDecoder::<S>::new(
-            reader,
-            data_offset,
-            data_length,
-            spec,
-            fmt,
-            None,
-        )?),
-        other => Err(AudioReadError::Unimplemented(format!(
-            "Not implemented for format_tag 0x{:x}",
-            other
-        ))),
+        FORMAT_TAG_EXTENSIBLE | FORMAT_TAG_DEVELOPMENT => {
+            use wavcore::guids::*;
+            match &fmt.extension {
+                Some(FmtExtension{data: ExtensionData::Extensible(extensible), ..}) => match extensible.sub_format {
+                    GUID_PCM_FORMAT |
+                    GUID_IEEE_FLOAT_FORMAT |
+                    GUID_AMBISONIC_B_FORMAT_PCM |
+                    GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT => (),
+                    other => return Err(AudioReadError::UnsupportedFormat(fmt.format_tag, Some(other))),
+                },
+                _ => {
+                    if fmt.format_tag == FORMAT_TAG_DEVELOPMENT {
+                        // No GUID to identify the codec, use `open_raw_data()` to handle it yourself.
+                        return Err(AudioReadError::UnsupportedFormat(fmt.format_tag, None));
+                    }
+                }
+            }
+            Ok(ExtensibleDecoder::<S>::new(
+                reader,
+                data_offset,
+                data_length,
+                spec,
+                fmt,
+                None,
+            )?)
+        }
+        other => Err(AudioReadError::UnsupportedFormat(other, None)),
+    }
+}
+
+/// * The file that the `FileDataSource` reads the `data` chunk from: the source file, or the temporary file that the `data` chunk was copied into.
+#[cfg(feature = "fs")]
+type SpoolFile = File;
+
+/// * Without the `fs` feature, there's no file to open, the `data` chunk is copied into the memory, e.g. for the `wasm32-unknown-unknown` target.
+#[cfg(not(feature = "fs"))]
+type SpoolFile = MemorySpool;
+
+/// * The `data` chunk copied into the memory, the clones share the bytes and have their own positions.
+#[cfg(not(feature = "fs"))]
+#[derive(Clone)]
+struct MemorySpool(io::Cursor<Arc<[u8]>>);
+
+#[cfg(not(feature = "fs"))]
+impl Debug for MemorySpool {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("MemorySpool")
+            .field(&format_args!("[u8; {}]", self.0.get_ref().len()))
+            .finish()
+    }
+}
+
+#[cfg(not(feature = "fs"))]
+impl Read for MemorySpool {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
     }
 }
 
+#[cfg(not(feature = "fs"))]
+impl Seek for MemorySpool {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[cfg(feature = "fs")]
+fn open_spool_file(path: &Path) -> io::Result<SpoolFile> {
+    File::open(path)
+}
+
+#[cfg(not(feature = "fs"))]
+fn open_spool_file(path: &Path) -> io::Result<SpoolFile> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Can't open {}, the file system needs the `fs` feature", path.display()),
+    ))
+}
+
+/// * Create the temporary file to copy the `data` chunk into. This kind of temp file (in Windows) can delete itself without its `drop()` being called if we don't want it.
+#[cfg(feature = "fs")]
+fn new_spool() -> io::Result<BufWriter<File>> {
+    Ok(BufWriter::new(tempfile::tempfile()?))
+}
+
+#[cfg(not(feature = "fs"))]
+fn new_spool() -> io::Result<Vec<u8>> {
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "fs")]
+fn finish_spool(writer: BufWriter<File>) -> io::Result<SpoolFile> {
+    writer.into_inner().map_err(|err| err.into_error())
+}
+
+#[cfg(not(feature = "fs"))]
+fn finish_spool(writer: Vec<u8>) -> io::Result<SpoolFile> {
+    Ok(MemorySpool(io::Cursor::new(Arc::from(writer))))
+}
+
+#[cfg(feature = "fs")]
+fn clone_spool(file: &SpoolFile) -> io::Result<SpoolFile> {
+    file.try_clone()
+}
+
+#[cfg(not(feature = "fs"))]
+fn clone_spool(file: &SpoolFile) -> io::Result<SpoolFile> {
+    Ok(file.clone())
+}
+
 /// * The `FileDataSource` provides the way to access the audio data, by a `Reader` or a file.
 /// * This is for creating the iterators. Each iterator uses this to read bytes, seek an offset, and convert data into samples.
 /// * By using this, every individual iterator can have its iterating position.
 #[derive(Debug)]
 pub struct FileDataSource {
     /// Do not wrap the file into a `BufReader`, we only wrap it if we have to read data from it.
-    file: Option<File>,
+    file: Option<SpoolFile>,
 
     /// Because the `WaveReader` could be created from just a `Reader`, it does not always come from a file, so there could be no file path.
     filepath: Option<PathBuf>,
@@ -866,6 +2855,25 @@ pub struct FileDataSource {
 
     /// The hash of the `data` chunk. It's actually useless.
     datahash: u64,
+
+    /// The stream positioned at the `data` chunk for the streaming `WaveReader`, it can be taken only once.
+    stream: RefCell<Option<Box<dyn Reader>>>,
+
+    /// The `data` chunks and the `slnt` chunks of the `LIST wavl` chunk, empty for the single `data` chunk.
+    segments: Vec<DataSegment>,
+
+    /// The byte to fill the silence of the `slnt` chunks.
+    silence_byte: u8,
+}
+
+/// * A piece of the audio data in the `LIST wavl` chunk.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DataSegment {
+    /// Where the bytes of the `data` chunk are in the file, `None` for the silence of the `slnt` chunk.
+    offset: Option<u64>,
+
+    /// The length in bytes.
+    length: u64,
 }
 
 impl FileDataSource {
@@ -881,24 +2889,23 @@ impl FileDataSource {
             // If we have the file path, we can open the file anytime anyway as we want.
             let path = PathBuf::from(filepath);
             let filepath = Some(path.clone());
-            let file = File::open(&path)?;
+            let file = open_spool_file(&path)?;
             (file, data_offset, filepath)
         } else if let Some(ref mut reader) = reader {
             // If we only get the reader, regardless of whether seekable or not, we have to create a temporary file to store the `data` chunk data.
             // Because the reader can be anything that some can do `try_clone()`, some can not do this, we need the one that can do `try_clone()`.
-            let file = tempfile::tempfile()?; // This kind of temp file (in Windows) can delete itself without its `drop()` being called if we don't want it.
             let filepath = Option::<PathBuf>::None;
             let offset = 0u64;
 
             // Let's create the temp file and copy the `data` chunk data into it.
-            let mut writer = BufWriter::new(file);
+            let mut writer = new_spool()?;
             if reader_seekable {
                 reader.seek(SeekFrom::Start(data_offset))?;
             } else {
                 io_utils::goto_offset_without_seek(&mut *reader, reader_cur_pos, data_offset)?;
             }
             io_utils::copy(&mut *reader, &mut writer, data_size)?;
-            let file: File = writer.into_inner().unwrap();
+            let file = finish_spool(writer)?;
 
             #[cfg(debug_assertions)]
             println!("Using tempfile to store \"data\" chunk: {:?}", file); // We can't get the path of the temp file directly, but it can be printed out anyway.
@@ -921,23 +2928,260 @@ impl FileDataSource {
         Ok(Self {
             file: Some(file), // Do not wrap the file into a `BufReader`, we only wrap it if we have to read data from it.
             filepath,
-            offset,
-            length: data_size,
-            datahash,
+            offset,
+            length: data_size,
+            datahash,
+            stream: RefCell::new(None),
+            segments: Vec::new(),
+            silence_byte: 0,
+        })
+    }
+
+    /// Copy the `data` chunk data into a temporary file with the conversion, e.g. byte-swap the big-endian samples of the AIFF file.
+    pub fn new_converted(
+        reader: &mut dyn Reader,
+        data_offset: u64,
+        data_size: u64,
+        reader_seekable: bool,
+        reader_cur_pos: &mut u64,
+        conversion: SampleConversion,
+    ) -> Result<Self, AudioReadError> {
+        let mut writer = new_spool()?;
+        if reader_seekable {
+            reader.seek(SeekFrom::Start(data_offset))?;
+            *reader_cur_pos = data_offset;
+        } else {
+            io_utils::goto_offset_without_seek(&mut *reader, reader_cur_pos, data_offset)?;
+        }
+
+        // The buffer holds whole samples, so the samples are never split between two reads.
+        let mut buf = vec![0u8; conversion.unit_size() * 16384];
+        let mut remaining = data_size;
+        while remaining > 0 {
+            let to_read = min(remaining, buf.len() as u64) as usize;
+            reader.read_exact(&mut buf[..to_read])?;
+            conversion.convert(&mut buf[..to_read]);
+            writer.write_all(&buf[..to_read])?;
+            remaining -= to_read as u64;
+        }
+        let file = finish_spool(writer)?;
+
+        let mut hasher = FileHasher::new();
+        let mut reader = BufReader::new(file);
+        let datahash = hasher.hash(&mut reader, 0, data_size)?;
+        let mut file = reader.into_inner();
+        file.seek(SeekFrom::Start(0))?;
+
+        *reader_cur_pos += data_size;
+
+        Ok(Self {
+            file: Some(file),
+            filepath: None,
+            offset: 0,
+            length: data_size,
+            datahash,
+            stream: RefCell::new(None),
+            segments: Vec::new(),
+            silence_byte: 0,
+        })
+    }
+
+    /// Gather the `data` chunks and the silence of the `LIST wavl` chunk as one piece of audio data, the silence takes no room.
+    /// If we only get the reader, the `data` chunks are copied into a temporary file.
+    pub(crate) fn new_wavl(
+        reader: Option<&mut dyn Reader>,
+        filepath: Option<String>,
+        mut segments: Vec<DataSegment>,
+        silence_byte: u8,
+    ) -> Result<Self, AudioReadError> {
+        let (file, filepath) = if let Some(filepath) = filepath {
+            let path = PathBuf::from(filepath);
+            (open_spool_file(&path)?, Some(path))
+        } else if let Some(reader) = reader {
+            let mut writer = new_spool()?;
+            let mut temp_offset = 0u64;
+            for segment in segments.iter_mut() {
+                if let Some(offset) = segment.offset {
+                    reader.seek(SeekFrom::Start(offset))?;
+                    io_utils::copy(&mut *reader, &mut writer, segment.length)?;
+                    segment.offset = Some(temp_offset);
+                    temp_offset += segment.length;
+                }
+            }
+            (finish_spool(writer)?, None)
+        } else {
+            return Err(AudioReadError::InvalidArguments(
+                "Must provide a `reader` or a `filepath`".to_string(),
+            ));
+        };
+        Ok(Self {
+            file: Some(file),
+            filepath,
+            offset: 0,
+            length: segments.iter().map(|segment| segment.length).sum(),
+            datahash: 0,
+            stream: RefCell::new(None),
+            segments,
+            silence_byte,
         })
     }
 
+    /// Use the stream as the source of the `data` chunk, the stream must be positioned at the `data` chunk inner data offset.
+    pub fn from_stream(stream: Box<dyn Reader>, data_offset: u64, data_size: u64) -> Self {
+        Self {
+            file: None,
+            filepath: None,
+            offset: data_offset,
+            length: data_size,
+            datahash: 0,
+            stream: RefCell::new(Some(stream)),
+            segments: Vec::new(),
+            silence_byte: 0,
+        }
+    }
+
+    /// Map the bytes of the `data` chunk into the memory, from the source file or the temporary file.
+    /// Safety: the file must not be modified while the map is alive, see `WaveReader::map_pcm_data()`.
+    #[cfg(feature = "mmap")]
+    pub(crate) unsafe fn map(&self) -> Result<memmap2::Mmap, AudioReadError> {
+        if self.is_stream() {
+            return Err(AudioReadError::Unsupported(String::from(
+                "the \"data\" chunk of the stream isn't in a file, it can't be mapped",
+            )));
+        }
+        if !self.segments.is_empty() {
+            return Err(AudioReadError::Unsupported(String::from(
+                "the \"data\" chunks of the \"LIST wavl\" chunk aren't contiguous, they can't be mapped",
+            )));
+        }
+        let file = match self.file.as_ref().map(clone_spool) {
+            Some(Ok(file)) => file,
+            _ => open_spool_file(self.filepath.as_ref().unwrap())?,
+        };
+        // SAFETY: the map is read-only, and the caller promises that the file isn't modified while the map is alive.
+        Ok(unsafe { memmap2::MmapOptions::new().offset(self.offset).len(self.length as usize).map(&file)? })
+    }
+
+    /// Is the `data` chunk from a stream that can be read only once.
+    pub fn is_stream(&self) -> bool {
+        self.file.is_none() && self.filepath.is_none()
+    }
+
     /// Open the source file or the temporary file or clone the file, and seek the `data` chunk inner data offset.
+    /// For the stream, hand it out once, it can't be read from the beginning again.
     pub fn open(&self) -> Result<Box<dyn Reader>, AudioReadError> {
-        let mut file = BufReader::new(match self.file.as_ref().unwrap().try_clone() {
+        if self.is_stream() {
+            return self.stream.borrow_mut().take().ok_or(AudioReadError::Unsupported(String::from(
+                "the \"data\" chunk of the stream was already being read, reading it again requires a seekable reader",
+            )));
+        }
+        let mut file = BufReader::new(match clone_spool(self.file.as_ref().unwrap()) {
             Ok(file) => file,
-            Err(_) => File::open(self.filepath.as_ref().unwrap())?,
+            Err(_) => open_spool_file(self.filepath.as_ref().unwrap())?,
         });
+        if !self.segments.is_empty() {
+            return Ok(Box::new(WavlReader {
+                file,
+                file_position: None,
+                segments: self.segments.clone(),
+                silence_byte: self.silence_byte,
+                position: 0,
+                length: self.length,
+            }));
+        }
         file.seek(SeekFrom::Start(self.offset))?;
         Ok(Box::new(file))
     }
 }
 
+/// * Read the beginning of the MP3 `data` chunk: the ID3v2 tag if there is one, and the first MP3 frame after it.
+fn peek_mp3_head(data_chunk: &FileDataSource) -> Result<Vec<u8>, AudioReadError> {
+    use crate::wavcore::mp3::id3v2_tag_size;
+
+    // The largest MP3 frame is 2881 bytes, it's after the ID3v2 tag if there is one.
+    const PEEK_SIZE: u64 = 4096;
+    let mut reader = data_chunk.open()?;
+    let mut head = Vec::new();
+    (&mut reader).take(PEEK_SIZE.min(data_chunk.length)).read_to_end(&mut head)?;
+    let tag_size = id3v2_tag_size(&head).unwrap_or(0) as u64;
+    if tag_size > 0 {
+        (&mut reader).take((tag_size + PEEK_SIZE).min(data_chunk.length) - head.len() as u64).read_to_end(&mut head)?;
+    }
+    Ok(head)
+}
+
+/// * Read the first MP3 frame of the `data` chunk for the number of the samples from its Xing/Info or VBRI header, zero if there isn't one.
+#[cfg(feature = "mp3dec")]
+fn peek_mp3_total_samples(data_chunk: &FileDataSource, fmt: &FmtChunk) -> Result<u64, AudioReadError> {
+    use crate::wavcore::mp3::Mp3VbrHeader;
+    let head = peek_mp3_head(data_chunk)?;
+    Ok(Mp3VbrHeader::parse(&head).and_then(|(header, _)| header.total_samples_at(fmt.sample_rate)).unwrap_or(0))
+}
+
+/// * Reads the `data` chunks of the `LIST wavl` chunk as one piece of audio data, and fills the silence of the `slnt` chunks without a buffer for it.
+#[derive(Debug)]
+struct WavlReader {
+    file: BufReader<SpoolFile>,
+
+    /// The position of the file, to skip seeking it when reading on.
+    file_position: Option<u64>,
+    segments: Vec<DataSegment>,
+    silence_byte: u8,
+    position: u64,
+    length: u64,
+}
+
+impl Read for WavlReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut segment_start = 0u64;
+        for segment in self.segments.iter() {
+            let segment_end = segment_start + segment.length;
+            if self.position < segment_end {
+                let to_read = min(buf.len() as u64, segment_end - self.position) as usize;
+                let size = match segment.offset {
+                    Some(offset) => {
+                        let file_position = offset + self.position - segment_start;
+                        if self.file_position != Some(file_position) {
+                            self.file.seek(SeekFrom::Start(file_position))?;
+                        }
+                        let size = self.file.read(&mut buf[..to_read])?;
+                        self.file_position = Some(file_position + size as u64);
+                        size
+                    }
+                    None => {
+                        buf[..to_read].fill(self.silence_byte);
+                        to_read
+                    }
+                };
+                self.position += size as u64;
+                return Ok(size);
+            }
+            segment_start = segment_end;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for WavlReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(target) => Some(target),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+        };
+        match target {
+            Some(target) => {
+                self.position = target;
+                Ok(target)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't seek to a negative position",
+            )),
+        }
+    }
+}
+
 /// For `mem::take()`, used by the `IntoIter` iterators.
 impl Default for FileDataSource {
     fn default() -> Self {
@@ -947,10 +3191,78 @@ impl Default for FileDataSource {
             offset: 0,
             length: 0,
             datahash: 0,
+            stream: RefCell::new(None),
+            segments: Vec::new(),
+            silence_byte: 0,
+        }
+    }
+}
+
+/// * Wraps a `Read` as a `Reader` for the streaming `WaveReader`.
+/// * It tracks the position, and it can only seek forward by skipping the bytes, seeking backward or from the end returns an error.
+pub struct StreamReader {
+    reader: Box<dyn Read>,
+    position: u64,
+}
+
+impl StreamReader {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        Self {
+            reader,
+            position: 0,
+        }
+    }
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.reader.read(buf)?;
+        self.position += size as u64;
+        Ok(size)
+    }
+}
+
+impl Seek for StreamReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(target) => Some(target),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "can't seek from the end of a stream, it requires a seekable reader",
+                ));
+            }
+        };
+        match target {
+            Some(target) if target >= self.position => {
+                let to_skip = target - self.position;
+                let skipped = io::copy(&mut (&mut self.reader).take(to_skip), &mut io::sink())?;
+                self.position += skipped;
+                if skipped < to_skip {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("the stream ended at {} while skipping to {target}", self.position),
+                    ));
+                }
+                Ok(self.position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("can't seek backward from {} on a stream, it requires a seekable reader", self.position),
+            )),
         }
     }
 }
 
+impl Debug for StreamReader {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("StreamReader")
+            .field("position", &self.position)
+            .finish_non_exhaustive()
+    }
+}
+
 /// * The audio frame iterator was created from the `WaveReader` to decode the audio frames.
 /// * Every audio frame is an array that includes one sample for every channel.
 /// * This iterator supports multi-channel audio files e.g. 5.1 stereo or 7.1 stereo audio files.
@@ -978,6 +3290,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<'a, S> FrameIter<'a, S>
@@ -991,6 +3306,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1000,13 +3317,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_frames(&mut self, num_frames: usize) -> Result<Vec<Vec<S>>, AudioReadError> {
-        self.decoder.decode_frames(num_frames)
+        let ret = self.decoder.decode_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1018,14 +3356,269 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_frame().unwrap()
+        let ret = self.decoder.decode_frame().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
+}
+
+/// * The audio frame iterator was created by `WaveReader::looped_frame_iter()`, it plays the loop of the `smpl` chunk as the `LoopPolicy` says.
+/// * Jumping back to the start of the loop creates the decoder at the start of the loop, it's as fast as `WaveReader::seek_to_frame()`.
+#[derive(Debug)]
+pub struct LoopedFrameIter<'a, S>
+where
+    S: SampleType,
+{
+    /// * The iterator of the current pass
+    iter: FrameIter<'a, S>,
+
+    /// * The borrowed data reader from the `WaveReader`, and what's needed to create the iterator again at the start of the loop.
+    data_reader: &'a FileDataSource,
+    data_offset: u64,
+    data_length: u64,
+    spec: Spec,
+    fmt: FmtChunk,
+    fact_data: u64,
+    read_options: ReadOptions,
+
+    /// * The first frame of the loop and the frame after the loop, `None` if there's no loop to play.
+    loop_range: Option<(u64, u64)>,
+
+    /// * The index of the next frame to decode.
+    position: u64,
+
+    /// * How many more times to jump back, `None` for no limit.
+    jumps_remaining: Option<u64>,
+
+    /// * How many more frames to excrete, `None` for no limit.
+    frames_remaining: Option<u64>,
+}
+
+impl<'a, S> LoopedFrameIter<'a, S>
+where
+    S: SampleType,
+{
+    fn new(
+        data_reader: &'a FileDataSource,
+        data_offset: u64,
+        data_length: u64,
+        spec: Spec,
+        fmt: &FmtChunk,
+        fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
+        loop_range: Option<(u64, u64)>,
+        loop_policy: LoopPolicy,
+    ) -> Result<Self, AudioReadError> {
+        let (jumps_remaining, frames_remaining) = match loop_policy {
+            LoopPolicy::Once => (Some(0), None),
+            LoopPolicy::Repeat(n) => (Some(n.saturating_sub(1)), None),
+            LoopPolicy::ForDuration(duration) => (None, Some((duration.as_secs_f64() * spec.sample_rate as f64).round() as u64)),
+        };
+        Ok(Self {
+            iter: FrameIter::<S>::new(data_reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            data_reader,
+            data_offset,
+            data_length,
+            spec,
+            fmt: fmt.clone(),
+            fact_data,
+            read_options: *read_options,
+            loop_range,
+            position: start_frame,
+            jumps_remaining,
+            frames_remaining,
+        })
+    }
+
+    /// * Decode the next audio frame, jump back to the start of the loop first if the loop ended.
+    pub fn decode_frame(&mut self) -> Result<Option<Vec<S>>, AudioReadError> {
+        if self.frames_remaining == Some(0) {
+            return Ok(None);
+        }
+        if let Some((start, end)) = self.loop_range {
+            if self.position == end && self.jumps_remaining != Some(0) {
+                self.iter = FrameIter::<S>::new(self.data_reader, self.data_offset, self.data_length, self.spec, &self.fmt, self.fact_data, start, &self.read_options)?;
+                self.position = start;
+                if let Some(jumps_remaining) = self.jumps_remaining.as_mut() {
+                    *jumps_remaining -= 1;
+                }
+            }
+        }
+        let ret = self.iter.decoder.decode_frame()?;
+        if ret.is_some() {
+            self.position += 1;
+            if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+                *frames_remaining -= 1;
+            }
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder of the current pass recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.iter.get_warnings()
+    }
+}
+
+impl<S> Iterator for LoopedFrameIter<'_, S>
+where
+    S: SampleType,
+{
+    type Item = Vec<S>;
+
+    /// * This method is for decoding each audio frame, it panics on errors, use `decode_frame()` to get the errors.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decode_frame().unwrap()
+    }
+}
+
+/// * A block of the interleaved audio frames from `BlockIter`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameBlock<S>
+where
+    S: SampleType,
+{
+    /// * The interleaved samples, `frames_per_block` frames unless it's the last block with `PadMode::None`.
+    pub samples: Vec<S>,
+
+    /// * How many frames from the beginning of the block are decoded from the audio, the rest are the padding.
+    pub valid_frames: usize,
+
+    /// * Num channels of each frame
+    pub channels: u16,
+}
+
+impl<S> FrameBlock<S>
+where
+    S: SampleType,
+{
+    /// * Iterate through the frames of the block including the padding, each frame has one sample for every channel.
+    pub fn frames(&self) -> std::slice::ChunksExact<'_, S> {
+        self.samples.chunks_exact(self.channels as usize)
+    }
+
+    /// * The number of the frames of the block including the padding.
+    pub fn num_frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn is_padded(&self) -> bool {
+        self.num_frames() > self.valid_frames
+    }
+}
+
+/// * The block iterator was created by `WaveReader::block_iter()`, it excretes the blocks of the same number of the frames, the last block is padded as `PadMode` says.
+/// * The samples are decoded by the decoder of the frame iterator directly into the buffer of the block, the buffer is reused for every block.
+#[derive(Debug)]
+pub struct BlockIter<'a, S>
+where
+    S: SampleType,
+{
+    iter: FrameIter<'a, S>,
+    frames_per_block: usize,
+    pad_mode: PadMode,
+    block: FrameBlock<S>,
+
+    /// * The last block was excreted.
+    ended: bool,
+}
+
+impl<'a, S> BlockIter<'a, S>
+where
+    S: SampleType,
+{
+    fn new(iter: FrameIter<'a, S>, channels: u16, frames_per_block: usize, pad_mode: PadMode) -> Result<Self, AudioReadError> {
+        if frames_per_block == 0 || channels == 0 {
+            return Err(AudioReadError::InvalidArguments(format!(
+                "The blocks need at least 1 frame of at least 1 channel, got {frames_per_block} frames of {channels} channels."
+            )));
+        }
+        Ok(Self {
+            iter,
+            frames_per_block,
+            pad_mode,
+            block: FrameBlock {
+                samples: Vec::with_capacity(frames_per_block * channels as usize),
+                valid_frames: 0,
+                channels,
+            },
+            ended: false,
+        })
+    }
+
+    /// * Decode the next block into the buffer of the iterator. Returns `None` after the last block, there's no block of only the padding.
+    pub fn next_block(&mut self) -> Result<Option<&FrameBlock<S>>, AudioReadError> {
+        if self.ended {
+            return Ok(None);
+        }
+        let channels = self.block.channels as usize;
+        let block_length = self.frames_per_block * channels;
+        self.block.samples.resize(block_length, S::new());
+        let mut filled = 0;
+        while filled < block_length {
+            let decoded = self.iter.decoder.decode_interleaved(&mut self.block.samples[filled..])?;
+            if decoded == 0 {
+                break;
+            }
+            filled += decoded;
+        }
+        let valid_frames = filled / channels;
+        if valid_frames < self.frames_per_block {
+            self.ended = true;
+            if valid_frames == 0 {
+                return Ok(None);
+            }
+        }
+        self.block.valid_frames = valid_frames;
+        match self.pad_mode {
+            PadMode::None => self.block.samples.truncate(filled),
+            PadMode::Zero => self.block.samples[filled..].fill(S::scale_from(0i16)),
+            PadMode::ReflectLast => {
+                // The valid frames and their reflection repeat every `2 * (valid_frames - 1)` frames.
+                let period = (2 * (valid_frames - 1)).max(1);
+                for i in valid_frames..self.frames_per_block {
+                    let phase = i % period;
+                    let source = if phase < valid_frames { phase } else { period - phase };
+                    self.block.samples.copy_within(source * channels..(source + 1) * channels, i * channels);
+                }
+            }
+        }
+        Ok(Some(&self.block))
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.iter.get_warnings()
+    }
+}
+
+impl<S> Iterator for BlockIter<'_, S>
+where
+    S: SampleType,
+{
+    type Item = FrameBlock<S>;
+
+    /// * This method clones every block, it panics on errors, use `next_block()` to avoid both.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().unwrap().cloned()
+    }
 }
 
 /// * The audio frame iterator was created from the `WaveReader` to decode the mono audio.
@@ -1053,6 +3646,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<'a, S> MonoIter<'a, S>
@@ -1066,6 +3662,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1075,13 +3673,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_monos(&mut self, num_monos: usize) -> Result<Vec<S>, AudioReadError> {
-        self.decoder.decode_monos(num_monos)
+        let ret = self.decoder.decode_monos(num_monos)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1093,14 +3712,26 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_mono().unwrap()
+        let ret = self.decoder.decode_mono().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
 }
 
 /// * The audio frame iterator was created from the `WaveReader` to decode the stereo audio.
@@ -1128,6 +3759,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<'a, S> StereoIter<'a, S>
@@ -1141,6 +3775,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1150,13 +3786,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_stereos(&mut self, num_stereos: usize) -> Result<Vec<(S, S)>, AudioReadError> {
-        self.decoder.decode_stereos(num_stereos)
+        let ret = self.decoder.decode_stereos(num_stereos)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1168,14 +3825,26 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_stereo().unwrap()
+        let ret = self.decoder.decode_stereo().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
 }
 
 /// * The audio frame iterator was created from the `WaveReader` to decode the audio frames.
@@ -1206,6 +3875,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<S> FrameIntoIter<S>
@@ -1219,6 +3891,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1228,13 +3902,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_frames(&mut self, num_frames: usize) -> Result<Vec<Vec<S>>, AudioReadError> {
-        self.decoder.decode_frames(num_frames)
+        let ret = self.decoder.decode_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1246,14 +3941,26 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_frame().unwrap()
+        let ret = self.decoder.decode_frame().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
 }
 
 /// * The audio frame iterator was created from the `WaveReader` to decode the mono audio.
@@ -1282,6 +3989,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<S> MonoIntoIter<S>
@@ -1295,6 +4005,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1304,13 +4016,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_monos(&mut self, num_monos: usize) -> Result<Vec<S>, AudioReadError> {
-        self.decoder.decode_monos(num_monos)
+        let ret = self.decoder.decode_monos(num_monos)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1322,14 +4055,26 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_mono().unwrap()
+        let ret = self.decoder.decode_mono().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
 }
 
 /// * The audio frame iterator was created from the `WaveReader` to decode the stereo audio.
@@ -1358,6 +4103,9 @@ where
 
     /// * The decoder dedicated for the format of the audio data, excretes the `<S>` format of the PCM samples for you.
     decoder: Box<dyn Decoder<S>>,
+
+    /// * How many frames are left to decode, for `size_hint()`. `None` if it's unknown.
+    frames_remaining: Option<u64>,
 }
 
 impl<S> StereoIntoIter<S>
@@ -1371,6 +4119,8 @@ where
         spec: Spec,
         fmt: &FmtChunk,
         fact_data: u64,
+        start_frame: u64,
+        read_options: &ReadOptions,
     ) -> Result<Self, AudioReadError> {
         let mut reader = data_reader.open()?;
         reader.seek(SeekFrom::Start(data_offset))?;
@@ -1380,13 +4130,34 @@ where
             data_length,
             spec,
             fact_data,
-            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data)?,
+            decoder: create_decoder::<S>(reader, data_offset, data_length, spec, fmt, fact_data, start_frame, read_options)?,
+            frames_remaining: get_total_frames(fmt, data_length, fact_data).map(|total_frames| total_frames.saturating_sub(start_frame)),
         })
     }
 
     /// * Batch decodes multiple frames. For some types of audio formats, this method is faster than decoding every frame one by one.
     pub fn decode_stereos(&mut self, num_stereos: usize) -> Result<Vec<(S, S)>, AudioReadError> {
-        self.decoder.decode_stereos(num_stereos)
+        let ret = self.decoder.decode_stereos(num_stereos)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(ret.len() as u64);
+        }
+        Ok(ret)
+    }
+
+    /// * The warnings recorded when the decoder recovered from corrupted packets, see `DecodeErrorPolicy`.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        self.decoder.get_warnings()
+    }
+
+    /// * Skip `num_frames` audio frames, the next decoded frame is the one after them. `nth()` does this too, but panics on errors.
+    /// * The PCM audio is simply seeked, and the block-based formats e.g. ADPCM jump over the whole blocks and only decode the remainder of the last block.
+    /// * For the decoders that can't seek, the frames are decoded and discarded.
+    pub fn skip_frames(&mut self, num_frames: u64) -> Result<(), AudioReadError> {
+        self.decoder.skip_frames(num_frames)?;
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(num_frames);
+        }
+        Ok(())
     }
 }
 
@@ -1398,12 +4169,24 @@ where
 
     /// * This method is for decoding each audio frame.
     fn next(&mut self) -> Option<Self::Item> {
-        self.decoder.decode_stereo().unwrap()
+        let ret = self.decoder.decode_stereo().unwrap();
+        if let Some(frames_remaining) = self.frames_remaining.as_mut() {
+            *frames_remaining = frames_remaining.saturating_sub(1);
+        }
+        ret
     }
 
     /// * This method is for seeking.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        self.decoder.seek(SeekFrom::Current(n as i64)).unwrap();
+        self.skip_frames(n as u64).unwrap();
         self.next()
     }
+
+    /// * The number of the remaining frames, known without decoding for most of the formats, see `WaveReader::total_frames()`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.frames_remaining {
+            Some(frames_remaining) => (frames_remaining as usize, Some(frames_remaining as usize)),
+            None => (0, None),
+        }
+    }
 }
@@ -3,24 +3,33 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet},
+    fmt::{self, Debug},
     fs::File,
-    io::{BufWriter, SeekFrom},
-    path::Path,
+    io::{self, BufWriter, Read, Seek, SeekFrom},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
 };
 
 use xlaw::XLaw;
-use io_utils::{Writer, string_io::*};
+use io_utils::{Writer, CursorVecU8, string_io::*};
 use sampletypes::SampleType;
 use crate::WaveReader;
-use crate::adpcm::{EncIMA, EncMS, EncYAMAHA};
-use crate::encoders::{AdpcmEncoderWrap, Encoder, PcmEncoder, PcmXLawEncoderWrap};
+use crate::adpcm::{EncIMA, EncIMA2, EncIMA3, EncIMA5, EncMS, EncYAMAHA};
+use crate::encoders::{AdpcmEncoderWrap, ClipStats, Encoder, EncoderToImpl, PassThroughEncoder, PcmEncoder, PcmXLawEncoderWrap};
 use crate::hacks;
 use crate::utils::{SavageStringCodecs, StringCodecMaps};
-use crate::wavcore::ChunkWriter;
+use crate::wavcore::{ChunkWriter, ExtensibleData, ExtensionData, FmtExtension};
 use crate::chunks::*;
 use crate::format_specs::*;
-use crate::wavcore::{AdpcmSubFormat, DataFormat, SampleFormat, Spec};
-use crate::errors::{AudioError, AudioWriteError};
+use crate::wavcore::{AdpcmSubFormat, DataFormat, SampleFormat, Spec, WaveSampleType};
+use crate::wavcore::{CuePositionPolicy, InfoTextPolicy};
+use crate::wavcore::pcm::{DitherMode, PcmEncoderOptions};
+use crate::wavcore::{flac::FlacCompression, mp3::{Mp3Bitrate, Mp3Channels}, oggvorbis::OggVorbisBitrateStrategy};
+use crate::validate;
+use crate::wavcore::flac::get_listinfo_flacmeta;
+use crate::errors::{AudioError, AudioReadError, AudioWriteError};
 
 #[cfg(feature = "mp3enc")]
 use crate::encoders::mp3::Mp3Encoder;
@@ -35,7 +44,7 @@ use crate::encoders::flac_enc::FlacEncoderWrap;
 use crate::encoders::oggvorbis_enc::OggVorbisEncoderWrap;
 
 /// * These options are used to specify what type of WAV file you want to create.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileSizeOption {
     /// * You specify the WAV file will never be larger than 4 GB. If the WAV file is about to exceed 4 GB and you continue to write data into it, errors occur.
     /// * This kind of WAV file is the most common one, most of the WAV parser supports this format.
@@ -50,6 +59,81 @@ pub enum FileSizeOption {
     ForceUse4GBFormat,
 }
 
+/// * How `WaveWriter::create_split()` splits the audio into the numbered WAV files, e.g. for the tools that can't read the `RF64` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitPolicy {
+    /// * Start the next file before the `data` chunk of the current file exceeds the bytes. The header and the metadata chunks aren't counted, leave some room for them.
+    /// * Only for the formats of the fixed size blocks: PCM, PCM-aLaw, PCM-MuLaw and ADPCM.
+    MaxBytes(u64),
+
+    /// * Start the next file when the current file reaches the duration.
+    MaxDuration(Duration),
+}
+
+/// * The container of the WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Riff,
+    Rf64,
+}
+
+/// * What the `WaveWriter` wrote into the WAV file, returned by `finalize()`.
+/// * It's a cheap oracle to verify the file layout without re-opening and re-parsing the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinalizeReport {
+    /// * Every top-level chunk written, in the order of their positions.
+    pub chunks: Vec<ChunkInfo>,
+
+    /// * The size of the `RIFF` or `RF64` chunk, for the `RF64` file it's the size stored in the `ds64` chunk.
+    pub riff_size: u64,
+
+    /// * Is the file a `RIFF` file or a `RF64` file
+    pub container: ContainerKind,
+
+    /// * The final position of the writer, all of the accepted frames were committed.
+    pub position: WriterPosition,
+
+    /// * The problems of the metadata that didn't stop the file from being written, e.g. the `acid` chunk doesn't fit the audio.
+    pub warnings: Vec<String>,
+}
+
+/// * The progress of the `WaveWriter`, returned by `position()`.
+/// * The encoders may keep some frames in their buffers, these frames are accepted but not yet committed into the `data` chunk.
+/// * Only the buffers in this crate are counted, the frames inside the codec libraries (e.g. LAME, libFLAC, libvorbis) count as committed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriterPosition {
+    /// * How many audio frames were given to the `WaveWriter`
+    pub frames_accepted: u64,
+
+    /// * How many of the accepted audio frames were encoded and written into the `data` chunk
+    pub frames_committed: u64,
+
+    /// * How many bytes were written into the `data` chunk
+    pub bytes_committed: u64,
+}
+
+/// * The statistics of the encoding, returned by `stats()` and given to the progress callback.
+/// * They're from the `WaveWriter` rather than from each encoder, so they mean the same for every format.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WriteStats {
+    /// * How many audio frames were given to the `WaveWriter`
+    pub frames_written: u64,
+
+    /// * How many bytes were written into the `data` chunk
+    pub bytes_written: u64,
+
+    /// * The average bitrate in bits per second of the frames committed into the `data` chunk, `0.0` before any frame is committed.
+    pub current_bitrate: f64,
+}
+
+impl FinalizeReport {
+    /// * Check if the chunks are at the same place as the `chunk_map()` of a `WaveReader` that re-opened the file.
+    pub fn matches_chunk_map(&self, chunk_map: &[ChunkInfo]) -> bool {
+        self.chunks.len() == chunk_map.len()
+            && self.chunks.iter().zip(chunk_map.iter()).all(|(a, b)| a.same_place(b))
+    }
+}
+
 /// * The `WaveWriter` is dedicated to creating a WAV file.
 ///   Usage:
 /// * Choose one of the internal formats by specifying `DataFormat` and use the `WaveWriter` to create the WAV file.
@@ -59,18 +143,29 @@ pub enum FileSizeOption {
 /// Then BAM. The WAV file was created successfully with the audio sound as you provided.
 #[derive(Debug)]
 pub struct WaveWriter<'a> {
-    writer: Box<dyn Writer + 'a>,
+    writer: Box<WriteBuffer<'a>>,
     spec: Spec,
     data_format: DataFormat,
     file_size_option: FileSizeOption,
+    riff_offset: u64,
     fmt_chunk_offset: u64,
     fact_chunk_offset: u64,
     num_frames_written: u64,
     data_offset: u64,
     encoder: Encoder<'a>,
     text_encoding: StringCodecMaps,
+    info_text_policies: Vec<InfoTextPolicy>,
     riff_chunk: Option<ChunkWriter<'a>>,
     data_chunk: Option<ChunkWriter<'a>>,
+    written_chunks: Vec<ChunkInfo>,
+    encoding_begun: bool,
+    finished: bool,
+    cue_builder: Option<CueBuilder>,
+    cue_position_policy: CuePositionPolicy,
+    progress_callback: Option<ProgressCallback<'a>>,
+    acid_chunk_is_set: bool,
+    relocated_chunks: Vec<([u8; 4], Vec<u8>)>,
+    raw_chunks: Vec<([u8; 4], Vec<u8>)>,
     pub fmt__chunk: FmtChunk,
     pub slnt_chunk: Option<SlntChunk>,
     pub bext_chunk: Option<BextChunk>,
@@ -79,7 +174,8 @@ pub struct WaveWriter<'a> {
     pub plst_chunk: Option<PlstChunk>,
     pub trkn_chunk: Option<TrknChunk>,
     pub cue__chunk: Option<CueChunk>,
-    pub axml_chunk: Option<String>,
+    pub axml_chunk: Option<AxmlChunk>,
+    pub chna_chunk: Option<ChnaChunk>,
     pub ixml_chunk: Option<String>,
     pub list_chunk: BTreeSet<ListChunk>,
     pub acid_chunk: Option<AcidChunk>,
@@ -87,8 +183,317 @@ pub struct WaveWriter<'a> {
     pub junk_chunks: BTreeSet<JunkChunk>,
 }
 
+/// * The `WaveWriter` guarded by a mutex, it can be moved to and shared between the threads.
+/// * The `WaveWriter` itself isn't `Send`: it may write to any writer you gave it, and the encoders keep raw pointers to the writer and share their outputs by `Rc`.
+///   The `SyncWaveWriter` can only be created to a file path with the built-in encoders, then everything inside the `WaveWriter` is owned by it.
+/// * The `WaveWriter` is never handed out while it's shared, only the methods here can use it, each of them holds the lock while encoding.
+/// * The WAV file is finalized when the `SyncWaveWriter` is dropped, or call `finalize()` on `into_inner()` to get the errors.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct SyncWaveWriter(Mutex<OwnedWaveWriter>);
+
+/// * The `WaveWriter` created to a file path, the mutex of it is `Sync` because this is `Send`.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+struct OwnedWaveWriter(WaveWriter<'static>);
+
+// Only `SyncWaveWriter::create()` makes it, the file and the encoders writing to it belong to this `WaveWriter` alone.
+// No `&mut WaveWriter` is given out to swap in one that writes to a writer shared with the outside, and the encoders don't bind their states to a thread.
+#[cfg(feature = "fs")]
+unsafe impl Send for OwnedWaveWriter {}
+
+#[cfg(feature = "fs")]
+impl SyncWaveWriter {
+    /// * Create WAV file through a file path, see `WaveWriter::create()`.
+    pub fn create<P: AsRef<Path>>(
+        filename: P,
+        spec: Spec,
+        data_format: DataFormat,
+        file_size_option: FileSizeOption,
+    ) -> Result<Self, AudioWriteError> {
+        Ok(Self(Mutex::new(OwnedWaveWriter(WaveWriter::create(filename, spec, data_format, file_size_option)?))))
+    }
+
+    /// * If a thread panicked while holding the lock, the `WaveWriter` is still usable.
+    fn lock(&self) -> MutexGuard<'_, OwnedWaveWriter> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// * Write the interleaved samples, see `WaveWriter::write_interleaved_samples()`.
+    pub fn write_interleaved_samples<S>(&self, samples: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.lock().0.write_interleaved_samples(samples)
+    }
+
+    /// * Write the audio frames, see `WaveWriter::write_frames()`.
+    pub fn write_frames<S>(&self, frames: &[Vec<S>]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.lock().0.write_frames(frames)
+    }
+
+    /// * Write the stereo samples, see `WaveWriter::write_stereos()`.
+    pub fn write_stereos<S>(&self, stereos: &[(S, S)]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.lock().0.write_stereos(stereos)
+    }
+
+    /// * Write the mono samples, see `WaveWriter::write_mono_channel()`.
+    pub fn write_mono_channel<S>(&self, monos: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.lock().0.write_mono_channel(monos)
+    }
+
+    /// * How many frames were accepted and committed, see `WaveWriter::position()`.
+    pub fn position(&self) -> Result<WriterPosition, AudioWriteError> {
+        self.lock().0.position()
+    }
+
+    /// * Get the `WaveWriter` back, it can't leave the thread anymore.
+    pub fn into_inner(self) -> WaveWriter<'static> {
+        self.0.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()).0
+    }
+}
+
+/// * The `WaveWriter` that splits the audio into the numbered WAV files, created by `WaveWriter::create_split()`.
+/// * Every part is a complete WAV file with the same spec, format and metadata. The splits are on the frame boundaries, and on the block boundaries for ADPCM,
+///   so the decoded parts concatenate seamlessly.
+/// * The next part is created when more samples come after the current part is full, there's no empty part at the end.
+/// * The current part is finalized when the `SplitWaveWriter` is dropped, or call `finalize()` to get the errors.
+#[cfg(feature = "fs")]
+pub struct SplitWaveWriter {
+    path_pattern: PathBuf,
+    spec: Spec,
+    data_format: DataFormat,
+    frames_per_part: u64,
+    metadata_tags: Vec<(String, String)>,
+    part_setup: Option<Box<dyn FnMut(&mut WaveWriter<'static>) -> Result<(), AudioWriteError>>>,
+    current: Option<WaveWriter<'static>>,
+    frames_in_part: u64,
+    num_frames_written: u64,
+    paths: Vec<PathBuf>,
+    reports: Vec<FinalizeReport>,
+}
+
+#[cfg(feature = "fs")]
+impl SplitWaveWriter {
+    fn create(path_pattern: &Path, spec: Spec, data_format: DataFormat, split_policy: SplitPolicy) -> Result<Self, AudioWriteError> {
+        let fixed_size_blocks = matches!(
+            data_format,
+            DataFormat::Pcm | DataFormat::PcmExtensible(_) | DataFormat::PcmALaw | DataFormat::PcmMuLaw | DataFormat::Adpcm(_)
+        );
+        if matches!(split_policy, SplitPolicy::MaxBytes(_)) && !fixed_size_blocks {
+            return Err(AudioWriteError::Unsupported(format!(
+                "The size of the {data_format} audio data can't be known before encoding, split it by `SplitPolicy::MaxDuration`."
+            )));
+        }
+
+        // The splits must be on the block boundaries, the parts end with the whole blocks. Get the block size from the encoder before creating any file.
+        spec.validate(&data_format)?;
+        let mut probe = WaveWriter::new_unstarted(Box::new(CursorVecU8::default()), spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB);
+        probe.create_encoder()?;
+        let fmt = probe.encoder.new_fmt_chunk()?;
+        drop(probe);
+        let frames_per_block = match &fmt.extension {
+            Some(FmtExtension { data: ExtensionData::AdpcmMs(adpcm_ms), .. }) => adpcm_ms.samples_per_block as u64,
+            Some(FmtExtension { data: ExtensionData::AdpcmIma(adpcm_ima), .. }) => adpcm_ima.samples_per_block as u64,
+            _ => (fmt.block_align as u64 * 8 / (fmt.bits_per_sample as u64 * fmt.channels as u64).max(1)).max(1),
+        };
+        let frames_per_part = match split_policy {
+            SplitPolicy::MaxBytes(max_bytes) => max_bytes / fmt.block_align.max(1) as u64 * frames_per_block,
+            SplitPolicy::MaxDuration(duration) => {
+                let frames = (duration.as_secs_f64() * spec.sample_rate as f64) as u64;
+                frames - frames % frames_per_block
+            }
+        };
+        if frames_per_part == 0 {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The {split_policy:?} is smaller than a block of {frames_per_block} frames."
+            )));
+        }
+        let mut ret = Self {
+            path_pattern: path_pattern.to_path_buf(),
+            spec,
+            data_format,
+            frames_per_part,
+            metadata_tags: Vec::new(),
+            part_setup: None,
+            current: None,
+            frames_in_part: 0,
+            num_frames_written: 0,
+            paths: Vec::new(),
+            reports: Vec::new(),
+        };
+        ret.open_part()?;
+        Ok(ret)
+    }
+
+    /// * The path of the part, e.g. `name_001.wav` for the path pattern `name.wav`, the index starts from 1.
+    fn part_path(&self, index: usize) -> PathBuf {
+        let stem = self.path_pattern.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = self.path_pattern.extension().map_or("wav".into(), |extension| extension.to_string_lossy());
+        self.path_pattern.with_file_name(format!("{stem}_{index:03}.{extension}"))
+    }
+
+    fn open_part(&mut self) -> Result<(), AudioWriteError> {
+        let path = self.part_path(self.paths.len() + 1);
+        let mut writer = WaveWriter::create(&path, self.spec, self.data_format.clone(), FileSizeOption::NeverLargerThan4GB)?;
+        for (key, value) in self.metadata_tags.iter() {
+            writer.set_metadata_tag(key, value)?;
+        }
+        if let Some(part_setup) = self.part_setup.as_mut() {
+            part_setup(&mut writer)?;
+        }
+        self.paths.push(path);
+        self.current = Some(writer);
+        self.frames_in_part = 0;
+        Ok(())
+    }
+
+    fn close_part(&mut self) -> Result<(), AudioWriteError> {
+        if let Some(writer) = self.current.take() {
+            self.reports.push(writer.finalize()?);
+        }
+        Ok(())
+    }
+
+    /// * Set a metadata tag for every part, see `WaveWriter::set_metadata_tag()`. It must be called before writing any samples.
+    pub fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<(), AudioWriteError> {
+        if let Some(writer) = self.current.as_mut() {
+            writer.set_metadata_tag(key, value)?;
+        }
+        self.metadata_tags.push((key.to_owned(), value.to_owned()));
+        Ok(())
+    }
+
+    /// * Set up every part by the closure, e.g. to set the `bext` chunk or `inherit_metadata_from_reader()`. The current part is set up right away.
+    /// * The closure is called before any samples of the part are written.
+    pub fn set_part_setup(&mut self, mut part_setup: impl FnMut(&mut WaveWriter<'static>) -> Result<(), AudioWriteError> + 'static) -> Result<(), AudioWriteError> {
+        if let Some(writer) = self.current.as_mut() {
+            if self.frames_in_part > 0 {
+                return Err(AudioWriteError::InvalidArguments(
+                    "The current part has samples, the part setup must be set before writing any samples.".to_owned(),
+                ));
+            }
+            part_setup(writer)?;
+        }
+        self.part_setup = Some(Box::new(part_setup));
+        Ok(())
+    }
+
+    /// * Write the frames into the parts, `items_per_frame` items form a frame, a new part is started when the current part is full.
+    fn write_parts<T>(
+        &mut self,
+        mut items: &[T],
+        items_per_frame: usize,
+        mut write: impl FnMut(&mut WaveWriter<'static>, &[T]) -> Result<(), AudioWriteError>,
+    ) -> Result<(), AudioWriteError> {
+        if items.len() % items_per_frame != 0 {
+            return Err(AudioWriteError::TruncatedSamples);
+        }
+        while !items.is_empty() {
+            if self.frames_in_part == self.frames_per_part {
+                self.close_part()?;
+            }
+            if self.current.is_none() {
+                self.open_part()?;
+            }
+            let frames = ((self.frames_per_part - self.frames_in_part) as usize).min(items.len() / items_per_frame);
+            let (part, rest) = items.split_at(frames * items_per_frame);
+            write(self.current.as_mut().unwrap(), part)?;
+            self.frames_in_part += frames as u64;
+            self.num_frames_written += frames as u64;
+            items = rest;
+        }
+        Ok(())
+    }
+
+    /// * Write the interleaved samples, see `WaveWriter::write_interleaved_samples()`.
+    pub fn write_interleaved_samples<S>(&mut self, samples: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.write_parts(samples, self.spec.channels as usize, |writer, samples| writer.write_interleaved_samples(samples))
+    }
+
+    /// * Write the audio frames, see `WaveWriter::write_frames()`.
+    pub fn write_frames<S>(&mut self, frames: &[Vec<S>]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.write_parts(frames, 1, |writer, frames| writer.write_frames(frames))
+    }
+
+    /// * Write the stereo samples, see `WaveWriter::write_stereos()`.
+    pub fn write_stereos<S>(&mut self, stereos: &[(S, S)]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.write_parts(stereos, 1, |writer, stereos| writer.write_stereos(stereos))
+    }
+
+    /// * Write the mono samples, see `WaveWriter::write_mono_channel()`.
+    pub fn write_mono_channel<S>(&mut self, monos: &[S]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        self.write_parts(monos, 1, |writer, monos| writer.write_mono_channel(monos))
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// * The number of frames written into all of the parts
+    pub fn get_num_frames_written(&self) -> u64 {
+        self.num_frames_written
+    }
+
+    /// * The number of frames of each part, the last part may have fewer.
+    pub fn get_frames_per_part(&self) -> u64 {
+        self.frames_per_part
+    }
+
+    /// * The paths of the parts created so far
+    pub fn get_paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// * Finalize the current part, and get the reports of all of the parts.
+    pub fn finalize(mut self) -> Result<Vec<FinalizeReport>, AudioWriteError> {
+        self.close_part()?;
+        Ok(mem::take(&mut self.reports))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Debug for SplitWaveWriter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SplitWaveWriter")
+            .field("path_pattern", &self.path_pattern)
+            .field("spec", &self.spec)
+            .field("data_format", &self.data_format)
+            .field("frames_per_part", &self.frames_per_part)
+            .field("metadata_tags", &self.metadata_tags)
+            .field("current", &self.current)
+            .field("frames_in_part", &self.frames_in_part)
+            .field("num_frames_written", &self.num_frames_written)
+            .field("paths", &self.paths)
+            .finish_non_exhaustive()
+    }
+}
+
 impl<'a> WaveWriter<'a> {
     /// * Create WAV file through a file path.
+    /// * Without the `fs` feature, use `from()` with a writer over the memory, e.g. a `CursorVecU8`.
+    #[cfg(feature = "fs")]
     pub fn create<P: AsRef<Path>>(
         filename: P,
         spec: Spec,
@@ -101,27 +506,235 @@ impl<'a> WaveWriter<'a> {
         Ok(wave_writer)
     }
 
-    /// * Write the WAV file to the writer.
+    /// * Create the numbered WAV files from the path pattern, e.g. `name_001.wav`, `name_002.wav` from `name.wav`, the next file begins when the current one reaches the `SplitPolicy`.
+    /// * Every part is a common `RIFF` WAV file, see `SplitWaveWriter`.
+    #[cfg(feature = "fs")]
+    pub fn create_split<P: AsRef<Path>>(
+        path_pattern: P,
+        spec: Spec,
+        data_format: DataFormat,
+        split_policy: SplitPolicy,
+    ) -> Result<SplitWaveWriter, AudioWriteError> {
+        SplitWaveWriter::create(path_pattern.as_ref(), spec, data_format, split_policy)
+    }
+
+    /// * Open an existing WAV file to append audio frames to it, e.g. to continue a long recording after the program restarted.
+    /// * Only PCM, PCM-aLaw and PCM-MuLaw can be appended to, the compressed formats can't continue in the middle of their bitstreams.
+    /// * The spec and the data format are from the `fmt ` chunk of the file, see `spec()` and `get_data_format()`. The frames in the file are counted by `get_num_frames_written()`.
+    /// * The chunks before the `data` chunk stay where they are. The chunks after the `data` chunk are moved to after the appended audio as they are.
+    ///   The metadata fields of the `WaveWriter` start empty, what you set is written in addition to the moved chunks.
+    /// * When finalized, the sizes of the `RIFF` and `data` chunks and the `fact` chunk are updated. Until then, the file ends at the audio.
+    #[cfg(feature = "fs")]
+    pub fn append<P: AsRef<Path>>(filename: P) -> Result<WaveWriter<'a>, AudioWriteError> {
+        let path = filename.as_ref();
+        let read_error = |e: AudioReadError| AudioWriteError::OtherReason(format!("Failed to read the WAV file to append to: {e}"));
+        let reader = WaveReader::open(&path.to_string_lossy()).map_err(read_error)?;
+        if reader.is_truncated() {
+            return Err(AudioWriteError::InvalidData(
+                "The `data` chunk of the WAV file is cut off, repair it before appending to it.".to_owned(),
+            ));
+        }
+        let spec = reader.spec();
+        let fmt = reader.get_fmt__chunk().clone();
+        let data_format = match fmt.format_tag {
+            format_tags::FORMAT_TAG_ALAW => DataFormat::PcmALaw,
+            format_tags::FORMAT_TAG_MULAW => DataFormat::PcmMuLaw,
+            _ if is_pcm_fmt(&fmt) => match &fmt.extension {
+                Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. })
+                    if ![guids::GUID_PCM_FORMAT, guids::GUID_IEEE_FLOAT_FORMAT].contains(&extensible.sub_format) =>
+                {
+                    DataFormat::PcmExtensible(PcmEncoderOptions {
+                        sub_format_guid: Some(extensible.sub_format),
+                    })
+                }
+                _ => DataFormat::Pcm,
+            },
+            other => {
+                return Err(AudioWriteError::Unsupported(format!(
+                    "Can't append to the format 0x{other:04x}, only PCM, PCM-aLaw and PCM-MuLaw can continue after the audio in the file."
+                )));
+            }
+        };
+
+        // The encoder must write the same blocks as the file has, try it before touching the file.
+        let mut probe = Self::new_unstarted(Box::new(CursorVecU8::default()), spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB);
+        probe.create_encoder()?;
+        let probe_fmt = probe.encoder.new_fmt_chunk()?;
+        if (probe_fmt.channels, probe_fmt.sample_rate, probe_fmt.block_align, probe_fmt.bits_per_sample, probe_fmt.get_sample_format())
+            != (fmt.channels, fmt.sample_rate, fmt.block_align, fmt.bits_per_sample, fmt.get_sample_format())
+        {
+            return Err(AudioWriteError::Unsupported(format!(
+                "The `fmt ` chunk of the WAV file doesn't fit the {data_format} encoder: {} channels, {} Hz, {} bits, {} bytes per block.",
+                fmt.channels, fmt.sample_rate, fmt.bits_per_sample, fmt.block_align
+            )));
+        }
+        drop(probe);
+
+        let chunk_map = reader.chunk_map().to_vec();
+        let Some(data_index) = chunk_map.iter().position(|info| &info.flag == b"data") else {
+            return Err(AudioWriteError::InvalidData("The WAV file has no `data` chunk.".to_owned()));
+        };
+        let Some(fmt_info) = chunk_map[..data_index].iter().find(|info| &info.flag == b"fmt ") else {
+            return Err(AudioWriteError::InvalidData("The `fmt ` chunk isn't before the `data` chunk.".to_owned()));
+        };
+        let fmt_chunk_offset = fmt_info.offset + 8;
+        let fact_chunk_offset = chunk_map[..data_index].iter().find(|info| &info.flag == b"fact").map_or(0, |info| info.offset + 8);
+        let data_offset = chunk_map[data_index].offset + 8;
+        let data_length = chunk_map[data_index].length - chunk_map[data_index].length % (fmt.block_align.max(1) as u64);
+
+        // The `RF64` file has the `ds64` chunk first, the file that may grow into `RF64` has the `JUNK` chunk as its placeholder.
+        let file_size_option = match chunk_map.first() {
+            Some(info) if &info.flag == b"ds64" && info.offset == 12 => FileSizeOption::ForceUse4GBFormat,
+            Some(info) if &info.flag == b"JUNK" && info.offset == 12 && info.length == 28 => FileSizeOption::AllowLargerThan4GB,
+            _ => FileSizeOption::NeverLargerThan4GB,
+        };
+
+        // Keep the chunks after the `data` chunk to write them again after the appended audio.
+        let mut relocated_chunks = Vec::<([u8; 4], Vec<u8>)>::new();
+        let mut file = File::open(path)?;
+        for info in chunk_map[data_index + 1..].iter() {
+            let mut data = vec![0u8; info.length as usize];
+            file.seek(SeekFrom::Start(info.offset + 8))?;
+            file.read_exact(&mut data)?;
+            relocated_chunks.push((info.flag, data));
+        }
+        drop(file);
+        drop(reader);
+
+        let file = File::options().write(true).open(path)?;
+        file.set_len(data_offset + data_length)?;
+        let mut ret = Self::new_unstarted(Box::new(BufWriter::new(file)), spec, data_format, file_size_option);
+        ret.create_encoder()?;
+        ret.fmt__chunk = fmt;
+        ret.fmt_chunk_offset = fmt_chunk_offset;
+        ret.fact_chunk_offset = fact_chunk_offset;
+        ret.data_offset = data_offset;
+        ret.num_frames_written = data_length / ret.fmt__chunk.block_align.max(1) as u64;
+        ret.relocated_chunks = relocated_chunks;
+        ret.written_chunks = chunk_map[..data_index]
+            .iter()
+            .map(|info| ChunkInfo {
+                back_patched: matches!(&info.flag, b"fmt " | b"fact" | b"ds64"),
+                ..*info
+            })
+            .collect();
+        ret.written_chunks.push(ChunkInfo {
+            flag: *b"data",
+            offset: data_offset - 8,
+            length: 0,
+            back_patched: true,
+        });
+        ret.writer.seek(SeekFrom::Start(data_offset + data_length))?;
+        ret.riff_chunk = Some(ChunkWriter::resume(
+            hacks::force_borrow_mut!(*ret.writer, WriteBuffer<'a>),
+            b"RIFF",
+            8,
+        ));
+        ret.data_chunk = Some(ChunkWriter::resume(
+            hacks::force_borrow_mut!(*ret.writer, WriteBuffer<'a>),
+            b"data",
+            data_offset,
+        ));
+        Ok(ret)
+    }
+
+    /// * Write the WAV file to the writer, e.g. a `CursorVecU8` for encoding the WAV file in memory, or any custom sink.
+    /// * The WAV file starts at the current position of the writer, the writer must be seekable for the header to be updated when finished.
+    /// * Use `into_inner()` to finalize the WAV file and get the writer back.
     pub fn from(
         writer: Box<dyn Writer + 'a>,
         spec: Spec,
         data_format: DataFormat,
         file_size_option: FileSizeOption,
     ) -> Result<WaveWriter<'a>, AudioWriteError> {
-        let mut ret = Self {
-            writer,
+        // The pass-through `WaveWriter` takes the spec from the `WaveReader` later.
+        if data_format != DataFormat::PassThrough {
+            spec.validate(&data_format)?;
+        }
+        let mut ret = Self::new_unstarted(writer, spec, data_format, file_size_option);
+
+        // The pass-through `WaveWriter` writes nothing until `copy_data_from_reader()` gives it the `fmt ` chunk.
+        if ret.data_format != DataFormat::PassThrough {
+            ret.create_encoder()?;
+            ret.write_header()?;
+        }
+        Ok(ret)
+    }
+
+    /// * Create WAV file through a file path, with your own encoder for a codec that the `DataFormat` doesn't have.
+    /// * See `from_with_encoder()`.
+    #[cfg(feature = "fs")]
+    pub fn create_with_encoder<P, F>(
+        filename: P,
+        spec: Spec,
+        create_encoder: F,
+        file_size_option: FileSizeOption,
+    ) -> Result<WaveWriter<'a>, AudioWriteError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&'a mut dyn Writer, Spec) -> Result<Box<dyn EncoderToImpl + 'a>, AudioWriteError>,
+    {
+        let file_writer = BufWriter::new(File::create(filename)?);
+        let wave_writer =
+            WaveWriter::from_with_encoder(Box::new(file_writer), spec, create_encoder, file_size_option)?;
+        Ok(wave_writer)
+    }
+
+    /// * Write the WAV file to the writer with your own encoder.
+    /// * The `create_encoder` closure gets the writer to write the encoded `data` chunk into, and the spec.
+    /// * The encoder is used exactly like the built-in encoders: `new_fmt_chunk()` for the `fmt ` chunk, then the samples, then `finish()` and `update_fmt_chunk()` when finalizing.
+    /// * The data format becomes `DataFormat::Custom` with the `format_tag` of the `fmt ` chunk from the encoder.
+    pub fn from_with_encoder<F>(
+        writer: Box<dyn Writer + 'a>,
+        spec: Spec,
+        create_encoder: F,
+        file_size_option: FileSizeOption,
+    ) -> Result<WaveWriter<'a>, AudioWriteError>
+    where
+        F: FnOnce(&'a mut dyn Writer, Spec) -> Result<Box<dyn EncoderToImpl + 'a>, AudioWriteError>,
+    {
+        let mut ret = Self::new_unstarted(writer, spec, DataFormat::Custom(0), file_size_option);
+        ret.encoder = Encoder::from_boxed(create_encoder(
+            hacks::force_borrow_mut!(*ret.writer, WriteBuffer<'a>),
+            spec,
+        )?);
+        ret.write_header()?;
+        ret.data_format = DataFormat::Custom(ret.fmt__chunk.format_tag);
+        Ok(ret)
+    }
+
+    /// * The `WaveWriter` without the encoder and the header.
+    fn new_unstarted(
+        writer: Box<dyn Writer + 'a>,
+        spec: Spec,
+        data_format: DataFormat,
+        file_size_option: FileSizeOption,
+    ) -> Self {
+        Self {
+            writer: Box::new(WriteBuffer::new(writer)),
             spec,
             data_format,
             file_size_option,
+            riff_offset: 0,
             fmt_chunk_offset: 0,
             fact_chunk_offset: 0,
             num_frames_written: 0,
             data_offset: 0,
             encoder: Encoder::default(),
             text_encoding: StringCodecMaps::new(),
+            info_text_policies: Vec::<InfoTextPolicy>::new(),
             fmt__chunk: FmtChunk::new(),
             riff_chunk: None,
             data_chunk: None,
+            written_chunks: Vec::<ChunkInfo>::new(),
+            encoding_begun: false,
+            finished: false,
+            cue_builder: None,
+            cue_position_policy: CuePositionPolicy::default(),
+            progress_callback: None,
+            acid_chunk_is_set: false,
+            relocated_chunks: Vec::new(),
+            raw_chunks: Vec::new(),
             slnt_chunk: None,
             bext_chunk: None,
             smpl_chunk: None,
@@ -130,15 +743,13 @@ impl<'a> WaveWriter<'a> {
             trkn_chunk: None,
             cue__chunk: None,
             axml_chunk: None,
+            chna_chunk: None,
             ixml_chunk: None,
             list_chunk: BTreeSet::<ListChunk>::new(),
             acid_chunk: None,
             id3__chunk: None,
             junk_chunks: BTreeSet::<JunkChunk>::new(),
-        };
-        ret.create_encoder()?;
-        ret.write_header()?;
-        Ok(ret)
+        }
     }
 
     fn create_encoder(&mut self) -> Result<(), AudioWriteError> {
@@ -147,54 +758,80 @@ impl<'a> WaveWriter<'a> {
             DataFormat::Pcm => {
                 spec.verify_for_pcm()?;
                 Encoder::new(PcmEncoder::new(
-                    hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
+                    spec,
+                )?)
+            }
+            DataFormat::PcmExtensible(pcm_options) => {
+                spec.verify_for_pcm()?;
+                Encoder::new(PcmEncoder::new_with_options(
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                     spec,
+                    pcm_options,
                 )?)
             }
             DataFormat::Adpcm(sub_format) => match sub_format {
                 AdpcmSubFormat::Ima => Encoder::new(AdpcmEncoderWrap::<EncIMA>::new(
-                    hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
+                    spec,
+                )?),
+                AdpcmSubFormat::Ima2 => Encoder::new(AdpcmEncoderWrap::<EncIMA2>::new(
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
+                    spec,
+                )?),
+                AdpcmSubFormat::Ima3 => Encoder::new(AdpcmEncoderWrap::<EncIMA3>::new(
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
+                    spec,
+                )?),
+                AdpcmSubFormat::Ima5 => Encoder::new(AdpcmEncoderWrap::<EncIMA5>::new(
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                     spec,
                 )?),
                 AdpcmSubFormat::Ms => Encoder::new(AdpcmEncoderWrap::<EncMS>::new(
-                    hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                     spec,
                 )?),
                 AdpcmSubFormat::Yamaha => Encoder::new(AdpcmEncoderWrap::<EncYAMAHA>::new(
-                    hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                     spec,
                 )?),
             },
             DataFormat::PcmALaw => Encoder::new(PcmXLawEncoderWrap::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                 spec,
                 XLaw::ALaw,
             )),
             DataFormat::PcmMuLaw => Encoder::new(PcmXLawEncoderWrap::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                 spec,
                 XLaw::MuLaw,
             )),
             #[cfg(feature = "mp3enc")]
             DataFormat::Mp3(mp3_options) => Encoder::new(Mp3Encoder::<f32>::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                 spec,
                 mp3_options,
             )?),
             #[cfg(feature = "opus")]
             DataFormat::Opus(opus_options) => Encoder::new(OpusEncoder::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                 spec,
                 opus_options,
             )?),
             #[cfg(feature = "flac")]
-            DataFormat::Flac(flac_options) => Encoder::new(FlacEncoderWrap::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
-                flac_options,
-            )?),
+            DataFormat::Flac(flac_options) => {
+                let mut encoder = FlacEncoderWrap::new(
+                    hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
+                    flac_options,
+                )?;
+                if let Some(channel_mask) = spec.channel_mask {
+                    encoder.set_channel_mask(channel_mask)?;
+                }
+                Encoder::new(encoder)
+            }
             #[cfg(any(feature = "vorbis", feature = "oggvorbis"))]
             DataFormat::OggVorbis(vorbis_options) => Encoder::new(OggVorbisEncoderWrap::new(
-                hacks::force_borrow_mut!(*self.writer, dyn Writer),
+                hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
                 vorbis_options,
             )?),
             DataFormat::Unspecified => {
@@ -203,6 +840,12 @@ impl<'a> WaveWriter<'a> {
                     self.data_format
                 )));
             }
+            DataFormat::Custom(_) => {
+                return Err(AudioWriteError::InvalidArguments(format!(
+                    "`data_format` is {}, use `WaveWriter::create_with_encoder()` to provide the encoder.",
+                    self.data_format
+                )));
+            }
             #[allow(unreachable_patterns)]
             other => {
                 return Err(AudioWriteError::InvalidArguments(format!(
@@ -216,8 +859,11 @@ impl<'a> WaveWriter<'a> {
     fn write_header(&mut self) -> Result<(), AudioWriteError> {
         use SampleFormat::{Float, Int, UInt};
 
+        // The writer may already have something before the WAV file.
+        self.riff_offset = self.writer.stream_position()?;
+
         self.riff_chunk = Some(ChunkWriter::begin(
-            hacks::force_borrow_mut!(*self.writer, dyn Writer),
+            hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
             b"RIFF",
         )?);
 
@@ -231,6 +877,13 @@ impl<'a> WaveWriter<'a> {
             FileSizeOption::AllowLargerThan4GB | FileSizeOption::ForceUse4GBFormat => {
                 let cw = ChunkWriter::begin(&mut self.writer, b"JUNK")?;
                 cw.writer.write_all(&[0u8; 28])?;
+                self.written_chunks.push(ChunkInfo {
+                    flag: *b"JUNK",
+                    offset: cw.get_chunk_start_pos() - 8,
+                    length: 28,
+                    back_patched: false,
+                });
+                cw.end()?;
             }
         }
 
@@ -240,42 +893,78 @@ impl<'a> WaveWriter<'a> {
         let mut cw = ChunkWriter::begin(&mut self.writer, b"fmt ")?;
         self.fmt_chunk_offset = cw.writer.stream_position()?;
         self.fmt__chunk.write(&mut cw.writer)?;
-        cw.end();
+        self.written_chunks.push(ChunkInfo {
+            flag: *b"fmt ",
+            offset: cw.get_chunk_start_pos() - 8,
+            length: cw.get_chunk_data_size()?,
+            back_patched: true,
+        });
+        cw.end()?;
 
         // Reserves space here for the fact chunk, to be updated later.
-        let mut cw = ChunkWriter::begin(&mut self.writer, b"fact")?;
-        self.fact_chunk_offset = cw.writer.stream_position()?;
-        match self.file_size_option {
-            FileSizeOption::NeverLargerThan4GB => {
+        // The `fact` chunk is required for every non-PCM format, it's the number of the sample frames to decode.
+        // It's always 32-bit, for the RF64 file the 64-bit sample count is in the `ds64` chunk.
+        // The plain IEEE float format isn't PCM for the `fact` chunk, the extensible one is.
+        let is_ieee_float = self.fmt__chunk.format_tag == format_tags::FORMAT_TAG_PCM_IEEE;
+        match self.data_format {
+            DataFormat::Pcm | DataFormat::PcmExtensible(_) if !is_ieee_float => self.fact_chunk_offset = 0,
+            DataFormat::PassThrough if is_pcm_fmt(&self.fmt__chunk) => self.fact_chunk_offset = 0,
+            _ => {
+                let mut cw = ChunkWriter::begin(&mut self.writer, b"fact")?;
+                self.fact_chunk_offset = cw.writer.stream_position()?;
                 0u32.write_le(&mut cw.writer)?;
-            }
-            FileSizeOption::AllowLargerThan4GB | FileSizeOption::ForceUse4GBFormat => {
-                0u64.write_le(&mut cw.writer)?;
+                self.written_chunks.push(ChunkInfo {
+                    flag: *b"fact",
+                    offset: cw.get_chunk_start_pos() - 8,
+                    length: cw.get_chunk_data_size()?,
+                    back_patched: true,
+                });
+                cw.end()?;
             }
         }
-        cw.end();
 
         self.data_chunk = Some(ChunkWriter::begin(
-            hacks::force_borrow_mut!(*self.writer, dyn Writer),
+            hacks::force_borrow_mut!(*self.writer, WriteBuffer<'a>),
             b"data",
         )?);
         self.data_offset = self.data_chunk.as_ref().unwrap().get_chunk_start_pos();
 
-        self.encoder.begin_encoding()?;
+        // The length of the `data` chunk is updated when finished.
+        self.written_chunks.push(ChunkInfo {
+            flag: *b"data",
+            offset: self.data_offset - 8,
+            length: 0,
+            back_patched: true,
+        });
+
+        // The encoding begins when the first samples come, before that, the metadata tags could be set to the encoder.
+        Ok(())
+    }
 
+    /// * Let the encoder begin encoding if it didn't, after this, the metadata tags of the encoder can't be set.
+    fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
+        if !self.encoding_begun {
+            self.encoding_begun = true;
+            self.encoder.begin_encoding()?;
+        }
         Ok(())
     }
 
     /// Stores audio samples. The generic parameter `S` represents the user-provided input format.
     /// The encoder converts samples to the internal target format before encoding them into the WAV file.
+    /// The samples are interleaved by channels, the length must be a multiple of the channel count.
     pub fn write_interleaved_samples<S>(&mut self, samples: &[S]) -> Result<(), AudioWriteError>
     where
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
+            if samples.len() % self.spec.channels as usize != 0 {
+                return Err(AudioWriteError::TruncatedSamples);
+            }
             self.encoder.write_interleaved_samples(samples)?;
             self.num_frames_written += (samples.len() / self.spec.channels as usize) as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -290,9 +979,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_mono(mono)?;
             self.num_frames_written += 1;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -307,9 +997,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_mono_channel(monos)?;
             self.num_frames_written += monos.len() as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -324,9 +1015,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_monos(monos)?;
             self.num_frames_written += monos[0].len() as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -335,15 +1027,34 @@ impl<'a> WaveWriter<'a> {
         }
     }
 
-    /// Saves a single stereo sample (left + right). Avoid frequent calls due to inefficiency.
-    pub fn write_stereo<S>(&mut self, stereo: (S, S)) -> Result<(), AudioWriteError>
+    /// Batch-saves multiple mono channels from borrowed planar buffers, without allocating a `Vec` for each channel.
+    pub fn write_monos_planar<S>(&mut self, monos: &[&[S]]) -> Result<(), AudioWriteError>
     where
         S: SampleType,
     {
         if self.data_chunk.is_some() {
-            self.encoder.write_stereo(stereo)?;
-            self.num_frames_written += 1;
-            Ok(())
+            self.begin_encoding()?;
+            self.encoder.write_monos_planar(monos)?;
+            self.num_frames_written += monos.first().map_or(0, |monos| monos.len()) as u64;
+            self.report_progress()
+        } else {
+            Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
+                    .to_owned(),
+            ))
+        }
+    }
+
+    /// Saves a single stereo sample (left + right). Avoid frequent calls due to inefficiency.
+    pub fn write_stereo<S>(&mut self, stereo: (S, S)) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        if self.data_chunk.is_some() {
+            self.begin_encoding()?;
+            self.encoder.write_stereo(stereo)?;
+            self.num_frames_written += 1;
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -358,6 +1069,7 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             if self.spec.channels != 2 {
                 return Err(AudioWriteError::WrongChannels(format!(
                     "Can't write stereo audio to {} channels audio file.",
@@ -366,7 +1078,7 @@ impl<'a> WaveWriter<'a> {
             }
             self.encoder.write_stereos(stereos)?;
             self.num_frames_written += stereos.len() as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -381,9 +1093,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_dual_mono(mono1, mono2)?;
             self.num_frames_written += 1;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -398,9 +1111,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_dual_monos(mono1, mono2)?;
             self.num_frames_written += mono1.len() as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -415,9 +1129,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_frame(frame)?;
             self.num_frames_written += 1;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -432,9 +1147,10 @@ impl<'a> WaveWriter<'a> {
         S: SampleType,
     {
         if self.data_chunk.is_some() {
+            self.begin_encoding()?;
             self.encoder.write_frames(frames)?;
             self.num_frames_written += frames.len() as u64;
-            Ok(())
+            self.report_progress()
         } else {
             Err(AudioWriteError::AlreadyFinished(
                 "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
@@ -443,6 +1159,112 @@ impl<'a> WaveWriter<'a> {
         }
     }
 
+    /// Batch-saves audio frames from borrowed slices, without allocating a `Vec` for each frame. Supports multi-channel layouts.
+    pub fn write_frames_planar<S>(&mut self, frames: &[&[S]]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        if self.data_chunk.is_some() {
+            self.begin_encoding()?;
+            self.encoder.write_frames_planar(frames)?;
+            self.num_frames_written += frames.len() as u64;
+            self.report_progress()
+        } else {
+            Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
+                    .to_owned(),
+            ))
+        }
+    }
+
+    /// * Write the audio frames at `frame_index` rather than appending them, for rendering the regions out of order.
+    /// * Only for the constant bitrate uncompressed formats: PCM, PCM-aLaw and PCM-MuLaw, other formats return `Unsupported`.
+    /// * Writing past the end fills the gap with silence. Writing inside the written frames overwrites them.
+    pub fn write_frames_at<S>(&mut self, frame_index: u64, frames: &[Vec<S>]) -> Result<(), AudioWriteError>
+    where
+        S: SampleType,
+    {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
+                    .to_owned(),
+            ));
+        }
+        match self.data_format {
+            DataFormat::Pcm | DataFormat::PcmExtensible(_) | DataFormat::PcmALaw | DataFormat::PcmMuLaw => (),
+            ref other => {
+                return Err(AudioWriteError::Unsupported(format!(
+                    "Can't write frames at a position for the format {other}, only PCM, PCM-aLaw and PCM-MuLaw are seekable."
+                )));
+            }
+        }
+        if self.writer.hasher.is_some() {
+            return Err(AudioWriteError::Unsupported(
+                "Can't write frames at a position with the audio hash enabled, the hash is computed in the order the samples are written.".to_owned(),
+            ));
+        }
+        self.begin_encoding()?;
+        let channels = self.spec.channels as usize;
+        if frames.iter().any(|frame| frame.len() != channels) {
+            return Err(AudioWriteError::WrongChannels(format!(
+                "Every frame must have {channels} samples."
+            )));
+        }
+        let block_align = self.fmt__chunk.block_align as u64;
+
+        // Fill the gap with silence, the encoder converts zeros into the midpoint value of the target format.
+        if frame_index > self.num_frames_written {
+            const SILENCE_FRAMES: u64 = 4096;
+            let silence = vec![vec![0i16; channels]; SILENCE_FRAMES as usize];
+            self.writer.seek(SeekFrom::Start(self.data_offset + self.num_frames_written * block_align))?;
+            let mut gap = frame_index - self.num_frames_written;
+            while gap > 0 {
+                let length = gap.min(SILENCE_FRAMES);
+                self.encoder.write_frames(&silence[..length as usize])?;
+                gap -= length;
+            }
+        }
+
+        self.writer.seek(SeekFrom::Start(self.data_offset + frame_index * block_align))?;
+        self.encoder.write_frames(frames)?;
+
+        // Move the high-water mark and go back to the end, so the appending writes and `finalize()` work as usual.
+        self.num_frames_written = self.num_frames_written.max(frame_index + frames.len() as u64);
+        self.writer.seek(SeekFrom::Start(self.data_offset + self.num_frames_written * block_align))?;
+        self.report_progress()
+    }
+
+    /// * Append `frames` frames of silence, for inserting exact gaps between the program segments.
+    /// * The silence goes through the encoder, it's converted into the silence of the target format, e.g. `0x80` for 8-bit PCM.
+    /// * The silence is written in bounded chunks, so the memory usage stays flat even for a long gap.
+    pub fn write_silence_frames(&mut self, frames: u64) -> Result<(), AudioWriteError> {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, and no longer accepts new samples to be encoded."
+                    .to_owned(),
+            ));
+        }
+        const SILENCE_FRAMES: u64 = 4096;
+        let silence = vec![vec![0i16; self.spec.channels as usize]; frames.min(SILENCE_FRAMES) as usize];
+        let mut remaining = frames;
+        while remaining > 0 {
+            let length = remaining.min(SILENCE_FRAMES);
+            self.write_frames(&silence[..length as usize])?;
+            remaining -= length;
+        }
+        Ok(())
+    }
+
+    /// * Append silence of `seconds` seconds, rounded to the nearest frame. See `write_silence_frames()`.
+    pub fn write_silence_duration(&mut self, seconds: f64) -> Result<(), AudioWriteError> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The duration of the silence must be a non-negative number of seconds, got {seconds}."
+            )));
+        }
+        self.write_silence_frames((seconds * self.spec.sample_rate as f64).round() as u64)
+    }
+
     /// * Get the spec for the `WaveWriter`
     pub fn spec(&self) -> Spec {
         self.spec
@@ -451,10 +1273,112 @@ impl<'a> WaveWriter<'a> {
     pub fn get_data_format(&self) -> DataFormat {
         self.data_format.clone()
     }
+
+    /// * The sample type that the encoder takes without converting it, e.g. `S16` for ADPCM, `S32` for FLAC, the sample type of the WAV file for PCM.
+    /// * Write the samples in this type to avoid the conversions through the other types.
+    pub fn native_sample_type(&self) -> WaveSampleType {
+        self.encoder.native_sample_type()
+    }
     /// * Get how many audio frames were written
     pub fn get_num_frames_written(&self) -> u64 {
         self.num_frames_written
     }
+
+    /// * Count the float samples that clip when they're converted to the integer samples, for every `write_*()` function.
+    /// * The float PCM file stores the samples as is, nothing clips, so nothing is counted for it.
+    /// * It's disabled by default, then it costs nothing but a flag check for each write.
+    pub fn enable_clip_detection(&mut self, enabled: bool) {
+        let stores_float = matches!(self.data_format, DataFormat::Pcm | DataFormat::PcmExtensible(_))
+            && matches!(self.spec.sample_format, SampleFormat::Float);
+        self.encoder.enable_clip_detection(enabled && !stores_float);
+    }
+
+    /// * Get how many samples were clipped and how far they went, see `enable_clip_detection()`.
+    pub fn clip_stats(&self) -> ClipStats {
+        self.encoder.clip_stats()
+    }
+
+    /// * Buffer the bytes written to the writer, so the small writes, e.g. writing frame by frame, don't go to the writer one by one.
+    /// * The buffered bytes are flushed before the buffer is resized, and whenever the `WaveWriter` seeks the writer, e.g. when finalizing.
+    /// * It's `0` by default, then every write goes straight to the writer. The output is the same with or without the buffer.
+    pub fn set_write_buffer_size(&mut self, bytes: usize) -> Result<(), AudioWriteError> {
+        self.writer.flush_buffer()?;
+        self.writer.capacity = bytes;
+        self.writer.buffer = Vec::with_capacity(bytes);
+        Ok(())
+    }
+
+    /// * Get the size of the write buffer, see `set_write_buffer_size()`.
+    pub fn get_write_buffer_size(&self) -> usize {
+        self.writer.capacity
+    }
+
+    /// * Flush the write buffer and the writer. The bytes that the encoders keep for their unfinished blocks are not flushed until finalizing.
+    pub fn flush(&mut self) -> Result<(), AudioWriteError> {
+        io::Write::flush(&mut *self.writer)?;
+        Ok(())
+    }
+
+    /// * Get how many frames were accepted, and how many frames and bytes were committed into the `data` chunk.
+    /// * Use `frames_committed` for a progress display, the frames still in the encoder buffers are not in the file yet.
+    pub fn position(&mut self) -> Result<WriterPosition, AudioWriteError> {
+        let frames_accepted = self.num_frames_written;
+        let bytes_committed = if self.data_chunk.is_some() {
+            self.writer.stream_position()? - self.data_offset
+        } else {
+            0
+        };
+        Ok(WriterPosition {
+            frames_accepted,
+            frames_committed: frames_accepted - self.encoder.get_frames_buffered().min(frames_accepted),
+            bytes_committed,
+        })
+    }
+
+    /// * Get the number of the frames and the bytes written and the average bitrate so far, for a progress display.
+    /// * The bitrate is of the committed frames, the frames in the encoder buffers have no bytes in the file yet.
+    pub fn stats(&mut self) -> Result<WriteStats, AudioWriteError> {
+        let position = self.position()?;
+        let current_bitrate = if position.frames_committed > 0 {
+            position.bytes_committed as f64 * 8.0 * self.spec.sample_rate as f64 / position.frames_committed as f64
+        } else {
+            0.0
+        };
+        Ok(WriteStats {
+            frames_written: position.frames_accepted,
+            bytes_written: position.bytes_committed,
+            current_bitrate,
+        })
+    }
+
+    /// * Call `callback` with the `stats()` after a `write_*()` function, at most once every `interval_frames` frames.
+    /// * The frames are counted by the `write_*()` calls, so a call that writes many frames reports once.
+    /// * The callback is called on the thread that writes, it must be `Send` as the `SyncWaveWriter` may move the `WaveWriter` between the threads.
+    pub fn set_progress_callback(&mut self, interval_frames: u64, callback: Box<dyn FnMut(&WriteStats) + Send + 'a>) {
+        self.progress_callback = Some(ProgressCallback {
+            callback,
+            interval_frames: interval_frames.max(1),
+            next_report: self.num_frames_written + interval_frames.max(1),
+        });
+    }
+
+    /// * Remove the progress callback, see `set_progress_callback()`.
+    pub fn clear_progress_callback(&mut self) {
+        self.progress_callback = None;
+    }
+
+    fn report_progress(&mut self) -> Result<(), AudioWriteError> {
+        match &self.progress_callback {
+            Some(progress) if self.num_frames_written >= progress.next_report => (),
+            _ => return Ok(()),
+        }
+        let stats = self.stats()?;
+        if let Some(progress) = self.progress_callback.as_mut() {
+            progress.next_report = (stats.frames_written / progress.interval_frames + 1) * progress.interval_frames;
+            (progress.callback)(&stats);
+        }
+        Ok(())
+    }
     /// * See `WaveReader`
     pub fn set_slnt_chunk(&mut self, chunk: &SlntChunk) {
         self.slnt_chunk = Some(*chunk);
@@ -463,7 +1387,25 @@ impl<'a> WaveWriter<'a> {
     pub fn set_bext_chunk(&mut self, chunk: &BextChunk) {
         self.bext_chunk = Some(chunk.clone());
     }
+    /// * Set the `bext` chunk of the Broadcast WAV, the fields are checked by `BextChunk::validate()` here rather than when finalizing.
+    /// * If any of the loudness fields is set, the version is raised to 2.
+    pub fn set_bext(&mut self, mut chunk: BextChunk) -> Result<(), AudioWriteError> {
+        chunk.validate()?;
+        let loudness = [
+            chunk.loudness_value,
+            chunk.loudness_range,
+            chunk.max_true_peak_level,
+            chunk.max_momentary_loudness,
+            chunk.max_short_term_loudness,
+        ];
+        if loudness.iter().any(|value| *value != 0) {
+            chunk.version = chunk.version.max(2);
+        }
+        self.bext_chunk = Some(chunk);
+        Ok(())
+    }
     /// * See `WaveReader`
+    /// * The loops are checked against the number of frames written when finalizing.
     pub fn set_smpl_chunk(&mut self, chunk: &SmplChunk) {
         self.smpl_chunk = Some(chunk.clone());
     }
@@ -471,6 +1413,20 @@ impl<'a> WaveWriter<'a> {
     pub fn set_inst_chunk(&mut self, chunk: &InstChunk) {
         self.inst_chunk = Some(*chunk);
     }
+    /// * Set the cue points authored by the `CueBuilder`.
+    /// * When finalizing, the cue points are checked against the number of frames written, then they replace the `cue ` chunk, the `LIST adtl` chunk, and the `plst` chunk if `with_playlist` is true.
+    pub fn set_cues(&mut self, cues: &CueBuilder) {
+        self.cue_builder = Some(cues.clone());
+    }
+
+    /// * Set what to do if the positions of the cue points don't fit in the 32-bit fields, the default is to write the `cu64` chunk with them.
+    pub fn set_cue_position_policy(&mut self, policy: CuePositionPolicy) {
+        self.cue_position_policy = policy;
+    }
+    /// * See `set_cue_position_policy()`
+    pub fn get_cue_position_policy(&self) -> CuePositionPolicy {
+        self.cue_position_policy
+    }
     /// * See `WaveReader`
     pub fn set_plst_chunk(&mut self, chunk: &PlstChunk) {
         self.plst_chunk = Some(chunk.clone());
@@ -484,8 +1440,12 @@ impl<'a> WaveWriter<'a> {
         self.cue__chunk = Some(chunk.clone());
     }
     /// * See `WaveReader`
-    pub fn set_axml_chunk(&mut self, chunk: &String) {
-        self.axml_chunk = Some(chunk.to_owned());
+    pub fn set_axml_chunk(&mut self, chunk: &AxmlChunk) {
+        self.axml_chunk = Some(chunk.clone());
+    }
+
+    pub fn set_chna_chunk(&mut self, chunk: &ChnaChunk) {
+        self.chna_chunk = Some(chunk.clone());
     }
     /// * See `WaveReader`
     pub fn set_ixml_chunk(&mut self, chunk: &String) {
@@ -495,15 +1455,227 @@ impl<'a> WaveWriter<'a> {
     pub fn set_list_chunk(&mut self, chunk: ListChunk) {
         self.list_chunk.insert(chunk);
     }
-    /// * See `WaveReader`
+
+    /// * Set a metadata tag, the key is the Vorbis comment field name e.g. `TITLE`, `ARTIST`, `ALBUM`, or the `LIST INFO` key e.g. `INAM`.
+    /// * The tag goes to the native tags of the encoder: the FLAC comments or the Vorbis comments. For the other formats, it goes to the `LIST INFO` chunk.
+    /// * The MP3 encoder's ID3 tag is from `Mp3EncoderOptions`, the tags set here for MP3 go to the `LIST INFO` chunk.
+    /// * The native tags are written before the audio, so it must be called before writing any samples.
+    pub fn set_metadata_tag(&mut self, key: &str, value: &str) -> Result<(), AudioWriteError> {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, the metadata tags can't be set anymore.".to_owned(),
+            ));
+        }
+        if self.encoding_begun {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The encoding has begun, the metadata tag `{key}` must be set before writing any samples."
+            )));
+        }
+        let listinfo_flacmeta = get_listinfo_flacmeta();
+        let key = key.to_uppercase();
+        let (info_key, native_key) = match listinfo_flacmeta.get(key.as_str()) {
+            Some(native_key) => (Some(key.clone()), native_key.to_string()),
+            None => (
+                listinfo_flacmeta.iter().find(|(_, native_key)| **native_key == key).map(|(info_key, _)| info_key.to_string()),
+                key.clone(),
+            ),
+        };
+        if self.encoder.set_metadata_tag(&native_key, value)? {
+            return Ok(());
+        }
+        let info_key = match info_key {
+            Some(info_key) => info_key,
+            None if key.len() == 4 && key.starts_with('I') && key.is_ascii() => key,
+            None => {
+                return Err(AudioWriteError::InvalidArguments(format!(
+                    "The metadata tag `{key}` can't be stored in the `LIST INFO` chunk."
+                )));
+            }
+        };
+        let mut dict = self.list_chunk.iter().find_map(|chunk| match chunk {
+            ListChunk::Info(dict) => Some(dict.clone()),
+            _ => None,
+        }).unwrap_or_default();
+        self.list_chunk.retain(|chunk| !chunk.get_is_list_info());
+        dict.insert(info_key, value.to_owned());
+        self.list_chunk.insert(ListChunk::Info(dict));
+        Ok(())
+    }
+
+    /// * Tell the encoder how many audio frames are going to be written, e.g. the FLAC encoder puts it into the `STREAMINFO` as the total samples.
+    /// * It's only an estimate, the FLAC encoder writes the real number of frames into the `STREAMINFO` when finalizing.
+    /// * It must be called before writing any samples. `transfer_audio_from_decoder_to_encoder()` calls it with the number of frames of the `WaveReader`.
+    pub fn set_total_frames_estimate(&mut self, num_frames: u64) -> Result<(), AudioWriteError> {
+        if self.encoding_begun {
+            return Err(AudioWriteError::InvalidArguments(
+                "The encoding has begun, the total frames estimate must be set before writing any samples.".to_owned(),
+            ));
+        }
+        self.encoder.set_total_frames_estimate(num_frames)
+    }
+
+    /// * Dither the samples when they have more bits than the sample type of the WAV file, e.g. writing the `f32` samples to the 16-bit PCM file, see `DitherMode`.
+    /// * Only the PCM format dithers. It must be called before writing any samples, the default is `DitherMode::None`.
+    pub fn set_dither(&mut self, dither: DitherMode) -> Result<(), AudioWriteError> {
+        if self.encoding_begun {
+            return Err(AudioWriteError::InvalidArguments(
+                "The encoding has begun, the dither must be set before writing any samples.".to_owned(),
+            ));
+        }
+        self.encoder.set_dither(dither)
+    }
+
+    /// * Set the ID3 tag, it's written as the `id3 ` chunk.
+    /// * With the `id3` feature, the texts of the tag are also set to the native tags of the encoder, see `set_metadata_tag()`.
+    pub fn set_id3(&mut self, tag: &Id3::Tag) -> Result<(), AudioWriteError> {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, the metadata tags can't be set anymore.".to_owned(),
+            ));
+        }
+        if self.encoding_begun {
+            return Err(AudioWriteError::InvalidArguments(
+                "The encoding has begun, the ID3 tag must be set before writing any samples.".to_owned(),
+            ));
+        }
+        #[cfg(feature = "id3")]
+        for (info_key, native_key) in get_listinfo_flacmeta().iter() {
+            if let Some(text) = Id3::get_info_text(tag, info_key) {
+                self.encoder.set_metadata_tag(native_key, &text)?;
+            }
+        }
+        self.id3__chunk = Some(tag.clone());
+        Ok(())
+    }
+
+    /// * Write the aggregate view from `WaveReader::metadata()` back to the `LIST INFO` chunk, and to the `id3 ` chunk with the `id3` feature.
+    /// * Each chunk gets the value kept in `Metadata::other` for its source if there is one, otherwise the common field, so the disagreeing values survive the round trip.
+    /// * The other values of `MetadataSource::ListInfo` are written by their `LIST INFO` keys, the ones of `MetadataSource::Id3` by their frame IDs.
+    /// * The existing `LIST INFO` values and ID3 frames that the metadata doesn't have are kept. It must be called before writing any samples.
+    pub fn set_metadata(&mut self, metadata: &Metadata) -> Result<(), AudioWriteError> {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, the metadata tags can't be set anymore.".to_owned(),
+            ));
+        }
+        if self.encoding_begun {
+            return Err(AudioWriteError::InvalidArguments(
+                "The encoding has begun, the metadata must be set before writing any samples.".to_owned(),
+            ));
+        }
+        if cfg!(feature = "id3") {
+            let mut dict = InfoDict::new();
+            for (field, info_key) in METADATA_FIELDS.iter() {
+                if let Some(value) = metadata.get_for(MetadataSource::Id3, field) {
+                    dict.insert(info_key.to_string(), value.clone());
+                }
+            }
+            let mut tag = Id3::mirror_info(self.id3__chunk.clone(), &dict)?;
+            for (frame_id, text) in metadata.iter_other(MetadataSource::Id3) {
+                Id3::set_text_frame(&mut tag, frame_id, text)?;
+            }
+            self.set_id3(&tag)?;
+        }
+        let mut dict = self.list_chunk.iter().find_map(|chunk| match chunk {
+            ListChunk::Info(dict) => Some(dict.clone()),
+            _ => None,
+        }).unwrap_or_default();
+        dict.extend(metadata.to_info_dict(MetadataSource::ListInfo));
+        if !dict.is_empty() {
+            self.list_chunk.retain(|chunk| !chunk.get_is_list_info());
+            self.list_chunk.insert(ListChunk::Info(dict));
+        }
+        Ok(())
+    }
+
+    /// * Set how to write the text of the `LIST INFO` chunk, the policies are combinable.
+    /// * If both `Utf8WithBom` and `CodePage` are given, the non-ASCII values are written as UTF-8 with a BOM.
+    /// * By default, the `LIST INFO` chunk is written by the code page of the writer, the same as the other text chunks.
+    pub fn set_info_text_policies(&mut self, policies: Vec<InfoTextPolicy>) -> Result<(), AudioWriteError> {
+        if cfg!(not(feature = "id3")) && policies.iter().any(|policy| matches!(policy, InfoTextPolicy::MirrorToId3)) {
+            return Err(AudioWriteError::Unsupported(
+                "Feature \"id3\" was not enabled, can not mirror the `LIST INFO` chunk to the `id3 ` chunk".to_owned(),
+            ));
+        }
+        self.info_text_policies = policies;
+        Ok(())
+    }
+
+    /// * See `set_info_text_policies()`
+    pub fn get_info_text_policies(&self) -> &[InfoTextPolicy] {
+        &self.info_text_policies
+    }
+
+    /// * Set the code page of the writer by the string codec maps, the text chunks are written by it when finalizing.
+    /// * It encodes the `LIST INFO` values, the `labl`, `note` and `ltxt` labels, the `bext` description and the `iXML` and `axml` chunks.
+    ///   `InfoTextPolicy::CodePage` or `InfoTextPolicy::Utf8WithBom` still decides the `LIST INFO` chunk if given.
+    /// * Give the string codec maps from `WaveReader::get_text_codec()` to write the text of the copied metadata with its original bytes.
+    pub fn set_text_codec(&mut self, text_encoding: StringCodecMaps) {
+        self.text_encoding = text_encoding;
+    }
+
+    /// * See `set_text_codec()`
+    pub fn get_text_codec(&self) -> &StringCodecMaps {
+        &self.text_encoding
+    }
+
+    /// * Set the `acid` chunk to write an ACIDized WAV file, see `AcidChunk::for_loop()`.
+    /// * The chunk set here wins, `inherit_metadata_from_reader()` won't replace it, no matter which one is called first.
+    /// * If the beats and the tempo don't fit the length of the audio, `finalize()` reports it in `FinalizeReport::warnings`.
     pub fn set_acid_chunk(&mut self, chunk: &AcidChunk) {
         self.acid_chunk = Some(chunk.clone());
+        self.acid_chunk_is_set = true;
     }
     /// * See `WaveReader`
     pub fn add_junk_chunk(&mut self, chunk: JunkChunk) {
         self.junk_chunks.insert(chunk);
     }
 
+    /// * Compute the MD5 of the samples while they are written, and store it in a `hash` chunk when finalizing, then `WaveReader::verify_embedded_hash()` can check if the audio was changed.
+    /// * The MD5 is of the samples in the little-endian bytes of the target format, as they are in the `data` chunk. Only for PCM, and it must be called before writing any samples.
+    /// * FLAC has its own MD5 of the samples in the `STREAMINFO`, the FLAC encoder always fills it.
+    pub fn enable_audio_hash(&mut self) -> Result<(), AudioWriteError> {
+        if self.data_chunk.is_none() {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The `data` chunk was sealed, the audio hash can't cover the samples.".to_owned(),
+            ));
+        }
+        match self.data_format {
+            DataFormat::Pcm | DataFormat::PcmExtensible(_) => (),
+            ref other => {
+                return Err(AudioWriteError::Unsupported(format!(
+                    "Can't embed the audio hash for the format {other}, only PCM can be verified by the decoded samples."
+                )));
+            }
+        }
+        if self.encoding_begun || self.num_frames_written > 0 {
+            return Err(AudioWriteError::InvalidArguments(
+                "The audio hash must be enabled before writing any samples.".to_owned(),
+            ));
+        }
+        self.writer.hasher = Some(md5::Context::new());
+        Ok(())
+    }
+
+    /// * Write a chunk as it is, e.g. to preserve the proprietary chunks read by `WaveReader::read_raw_chunk()` through a remux.
+    /// * The raw chunks are written in the order they were added, after the other chunks when finalizing.
+    /// * The chunks that the `WaveWriter` manages by itself can't be written this way: `RIFF`, `RF64`, `ds64`, `fmt `, `fact` and `data`.
+    pub fn write_raw_chunk(&mut self, flag: &[u8; 4], data: &[u8]) -> Result<(), AudioWriteError> {
+        if self.finished {
+            return Err(AudioWriteError::AlreadyFinished(
+                "The WAV file was finalized, no more chunks can be written.".to_owned(),
+            ));
+        }
+        if [b"RIFF", b"RF64", b"ds64", b"fmt ", b"fact", b"data"].contains(&flag) {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "The \"{}\" chunk is written by the `WaveWriter` itself.",
+                String::from_utf8_lossy(flag)
+            )));
+        }
+        self.raw_chunks.push((*flag, data.to_vec()));
+        Ok(())
+    }
+
     /// Transfers audio metadata (e.g., track info) from the reader.
     pub fn inherit_metadata_from_reader(&mut self, reader: &WaveReader, include_junk_chunks: bool) {
         if reader.get_slnt_chunk().is_some() {self.slnt_chunk = *reader.get_slnt_chunk();}
@@ -514,13 +1686,188 @@ impl<'a> WaveWriter<'a> {
         if reader.get_plst_chunk().is_some() {self.plst_chunk = reader.get_plst_chunk().clone();}
         if reader.get_cue__chunk().is_some() {self.cue__chunk = reader.get_cue__chunk().clone();}
         if reader.get_axml_chunk().is_some() {self.axml_chunk = reader.get_axml_chunk().clone();}
+        if reader.get_chna_chunk().is_some() {self.chna_chunk = reader.get_chna_chunk().clone();}
         if reader.get_ixml_chunk().is_some() {self.ixml_chunk = reader.get_ixml_chunk().clone();}
-        if reader.get_acid_chunk().is_some() {self.acid_chunk = reader.get_acid_chunk().clone();}
+        if reader.get_acid_chunk().is_some() && !self.acid_chunk_is_set {self.acid_chunk = reader.get_acid_chunk().clone();}
         if reader.get_id3__chunk().is_some() {self.id3__chunk = reader.get_id3__chunk().clone();}
         if !reader.get_list_chunk().is_empty() {self.list_chunk = reader.get_list_chunk().clone();}
         if include_junk_chunks {
             self.junk_chunks.extend(reader.get_junk_chunks().clone());
         }
+
+        // Also give the tags to the encoder if it has native tags. Before the encoding begins, this is the best effort.
+        if !self.encoding_begun {
+            if let Some(tag) = reader.get_id3__chunk() {
+                let _ = self.set_id3(tag);
+            }
+            let dict = reader.get_list_chunk().iter().find_map(|chunk| match chunk {
+                ListChunk::Info(dict) => Some(dict.clone()),
+                _ => None,
+            }).unwrap_or_default();
+            for (info_key, native_key) in get_listinfo_flacmeta().iter() {
+                if let Some(value) = dict.get(*info_key) {
+                    let _ = self.encoder.set_metadata_tag(native_key, value);
+                }
+            }
+        }
+    }
+
+    /// * Remux the audio of the `reader` without re-encoding, the `WaveWriter` must be created with `DataFormat::PassThrough`.
+    /// * The `fmt ` chunk is cloned from the reader, with the overrides from the spec given to the `WaveWriter`:
+    ///   * The channel mask if it's `Some`, the PCM `fmt ` chunk becomes extensible for it.
+    ///   * The sample rate of the PCM formats, the other formats have the sample rate in their bitstreams.
+    ///   * The number of channels and the bits per sample can't be changed without re-encoding.
+    /// * The `data` chunk is copied byte by byte. The `fact` chunk is written for the non-PCM formats, if the reader doesn't know the number of frames, the audio is decoded to count them.
+    /// * The other chunks are inherited by `inherit_metadata_from_reader()`, change them afterwards if you like.
+    /// * It can be called only once, then `finalize()` the `WaveWriter`. The streaming `WaveReader` can't be remuxed.
+    pub fn copy_data_from_reader(&mut self, reader: &mut WaveReader) -> Result<(), AudioWriteError> {
+        self.remux_from_reader(reader, false)
+    }
+
+    /// * Remux the audio of the `reader` like `copy_data_from_reader()`, and correct what `WaveReader::validate()` finds in the header fields:
+    ///   * The `block_align` and the `byte_rate` of the uncompressed formats are calculated from the channels and the bits per sample.
+    ///   * The channel mask that doesn't have a speaker for each channel becomes `0`, it means the speakers aren't assigned.
+    ///   * The `data` chunk is cut to whole blocks, the `fact` chunk is written from the frames in the `data` chunk.
+    ///   * The cue points are sorted by their positions, the cue points beyond the audio are dropped.
+    ///   * The fields of the `bext` chunk that don't fit its version raise the version, the reserved bytes are cleared, the malformed date and time are cleared.
+    /// * The chunks are written with their pad bytes, the unknown chunks aren't copied.
+    pub fn repair_from_reader(&mut self, reader: &mut WaveReader) -> Result<(), AudioWriteError> {
+        self.remux_from_reader(reader, true)
+    }
+
+    fn remux_from_reader(&mut self, reader: &mut WaveReader, repair: bool) -> Result<(), AudioWriteError> {
+        if self.data_format != DataFormat::PassThrough {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "`data_format` is {}, only `DataFormat::PassThrough` copies the audio data.",
+                self.data_format
+            )));
+        }
+        if self.riff_chunk.is_some() || self.finished {
+            return Err(AudioWriteError::AlreadyFinished("The audio data was copied already.".to_owned()));
+        }
+        if reader.is_streaming() {
+            return Err(AudioWriteError::Unsupported(
+                "The streaming `WaveReader` can be read only once, it can't be remuxed.".to_owned(),
+            ));
+        }
+        let read_error = |e: AudioReadError| AudioWriteError::OtherReason(format!("Failed to read the source audio: {e}"));
+        let source_spec = reader.spec();
+        if (self.spec.channels, self.spec.bits_per_sample) != (source_spec.channels, source_spec.bits_per_sample) {
+            return Err(AudioWriteError::InvalidArguments(format!(
+                "Can't change {} channels {} bits to {} channels {} bits without re-encoding.",
+                source_spec.channels, source_spec.bits_per_sample, self.spec.channels, self.spec.bits_per_sample
+            )));
+        }
+
+        let mut fmt = reader.get_fmt__chunk().clone();
+        let mut data_length = reader.get_data_length();
+        let mut fixed_frames = None;
+        if repair {
+            if let Some(block_align) = validate::fixed_block_align(&fmt) {
+                fmt.block_align = block_align;
+                fmt.byte_rate = fmt.sample_rate * block_align as u32;
+                fixed_frames = Some(data_length / block_align as u64);
+            }
+            if fmt.block_align > 0 {
+                data_length -= data_length % fmt.block_align as u64;
+            }
+            let channels = fmt.channels as u32;
+            if let Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. }) = &mut fmt.extension {
+                if extensible.channel_mask.count_ones() != channels {
+                    extensible.channel_mask = 0;
+                }
+            }
+        }
+        if self.spec.sample_rate != fmt.sample_rate {
+            if !is_pcm_fmt(&fmt) {
+                return Err(AudioWriteError::Unsupported(format!(
+                    "Can't change the sample rate of the format 0x{:04x} without re-encoding.",
+                    fmt.format_tag
+                )));
+            }
+            fmt.sample_rate = self.spec.sample_rate;
+            fmt.byte_rate = self.spec.sample_rate * fmt.block_align as u32;
+        }
+        if let Some(channel_mask) = self.spec.channel_mask {
+            match &mut fmt.extension {
+                Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. }) => extensible.channel_mask = channel_mask,
+                _ if is_pcm_fmt(&fmt) => {
+                    fmt.extension = Some(FmtExtension::new_extensible(ExtensibleData {
+                        valid_bits_per_sample: fmt.bits_per_sample,
+                        channel_mask,
+                        sub_format: if fmt.format_tag == format_tags::FORMAT_TAG_PCM_IEEE {
+                            guids::GUID_IEEE_FLOAT_FORMAT
+                        } else {
+                            guids::GUID_PCM_FORMAT
+                        },
+                    }));
+                    fmt.format_tag = format_tags::FORMAT_TAG_EXTENSIBLE;
+                }
+                _ => {
+                    return Err(AudioWriteError::Unsupported(format!(
+                        "The format 0x{:04x} has no channel mask.",
+                        fmt.format_tag
+                    )));
+                }
+            }
+        }
+
+        // Count the frames for the `fact` chunk before writing anything.
+        let num_frames = match fixed_frames.or(reader.total_frames()) {
+            Some(num_frames) => num_frames,
+            None => {
+                let start_frame = reader.get_start_frame();
+                reader.seek_to_frame(0).map_err(read_error)?;
+                let mut num_frames = 0u64;
+                let mut iter = reader.frame_iter::<i16>().map_err(read_error)?;
+                loop {
+                    let frames = iter.decode_frames(4096).map_err(read_error)?;
+                    if frames.is_empty() {
+                        break;
+                    }
+                    num_frames += frames.len() as u64;
+                }
+                reader.seek_to_frame(start_frame).map_err(read_error)?;
+                num_frames
+            }
+        };
+
+        self.encoder = Encoder::new(PassThroughEncoder::new(fmt));
+        self.write_header()?;
+        let mut data = reader.open_raw_data().map_err(read_error)?;
+        io::copy(&mut (&mut data).take(data_length), &mut self.writer)?;
+        self.num_frames_written = num_frames;
+        self.inherit_metadata_from_reader(reader, true);
+        if repair {
+            self.repair_metadata();
+        }
+        self.begin_encoding()
+    }
+
+    /// * Correct the inherited chunks for `repair_from_reader()`.
+    fn repair_metadata(&mut self) {
+        let num_frames = self.num_frames_written;
+        if let Some(cue) = self.cue__chunk.as_mut() {
+            cue.cue_points.retain(|point| point.position <= num_frames);
+            cue.cue_points.sort_by_key(|point| point.position);
+            cue.num_cues = cue.cue_points.len() as u32;
+        }
+        if let Some(bext) = self.bext_chunk.as_mut() {
+            let loudness = [bext.loudness_value, bext.loudness_range, bext.max_true_peak_level, bext.max_momentary_loudness, bext.max_short_term_loudness];
+            if loudness.iter().any(|value| *value != 0) {
+                bext.version = bext.version.max(2);
+            }
+            if bext.umid.iter().any(|byte| *byte != 0) {
+                bext.version = bext.version.max(1);
+            }
+            bext.reserved = [0; 180];
+            if !validate::is_bext_date(&bext.origination_date) {
+                bext.origination_date.clear();
+            }
+            if !validate::is_bext_time(&bext.origination_time) {
+                bext.origination_time.clear();
+            }
+        }
     }
 
     /// * If your audio file has `plst`, `cue `, and `LIST adtl` chunks, then BAM you can call this function for full playlist info.
@@ -553,67 +1900,172 @@ impl<'a> WaveWriter<'a> {
         )))
     }
 
+    /// * Write the chunks straight to the writer through a `ChunkRecorder`, so every chunk written can be recorded without keeping the chunks in the memory.
+    fn write_recorded_chunks(
+        writer: &mut dyn Writer,
+        written_chunks: &mut Vec<ChunkInfo>,
+        write: impl FnOnce(&mut ChunkRecorder) -> Result<(), AudioWriteError>,
+    ) -> Result<(), AudioWriteError> {
+        let mut recorder = ChunkRecorder::new(writer, written_chunks)?;
+        write(&mut recorder)
+    }
+
     /// * Finalizes writing to the data chunk and updates relevant parameters in the `fmt` chunk.
-    fn on_drop(&mut self) -> Result<(), AudioWriteError> {
+    fn on_drop(&mut self) -> Result<FinalizeReport, AudioWriteError> {
+        // Whether it succeeds or not, never do it twice.
+        self.finished = true;
+        if self.riff_chunk.is_none() {
+            return Err(AudioWriteError::InvalidArguments(
+                "Nothing was written, the `DataFormat::PassThrough` needs `copy_data_from_reader()`.".to_owned(),
+            ));
+        }
+        self.begin_encoding()?;
         self.encoder.finish()?;
 
+        // The audio hash covers all of the samples, it goes to the end with the raw chunks.
+        if let Some(hasher) = self.writer.hasher.take() {
+            let mut data = b"MD5 ".to_vec();
+            data.extend_from_slice(&hasher.compute().0);
+            self.raw_chunks.push((*b"hash", data));
+        }
+
         // Finalizes writing to the data chunk and records its size.
         let mut data_size = 0u64;
-        if let Some(data_chunk) = &self.data_chunk {
+        if let Some(data_chunk) = self.data_chunk.take() {
             data_size = self.writer.stream_position()? - data_chunk.get_chunk_start_pos();
-            self.data_chunk = None;
+            data_chunk.end()?;
+        }
+        if let Some(info) = self.written_chunks.iter_mut().find(|info| &info.flag == b"data") {
+            info.length = data_size;
         }
+        let position = WriterPosition {
+            frames_accepted: self.num_frames_written,
+            frames_committed: self.num_frames_written - self.encoder.get_frames_buffered().min(self.num_frames_written),
+            bytes_committed: data_size,
+        };
 
         let end_of_data = self.writer.stream_position()?;
 
+        // The sample loops must be inside the audio data.
+        if let Some(chunk) = &self.smpl_chunk {
+            chunk.validate(self.num_frames_written)?;
+        }
+        let mut warnings = Vec::<String>::new();
+        if let Some(chunk) = &self.acid_chunk {
+            warnings.extend(chunk.check_duration(self.num_frames_written, self.spec.sample_rate));
+        }
+        if let Some(cue_builder) = self.cue_builder.take() {
+            cue_builder.validate(self.num_frames_written)?;
+            let (cue__chunk, adtl_chunk, plst_chunk) = cue_builder.build();
+            self.cue__chunk = Some(cue__chunk);
+            self.list_chunk.retain(|list_chunk| !matches!(list_chunk, ListChunk::Adtl(_)));
+            self.list_chunk.insert(adtl_chunk);
+            if plst_chunk.is_some() {
+                self.plst_chunk = plst_chunk;
+            }
+        }
+
+        // The positions that don't fit in the `cue `, `plst` and `ltxt` chunks go to the `cu64` chunk.
+        let adtl = self.list_chunk.iter().find_map(|list_chunk| match list_chunk {
+            ListChunk::Adtl(adtl) => Some(adtl),
+            _ => None,
+        });
+        let cu64_chunk = self.cue__chunk.as_ref().and_then(|cue| Cue64Chunk::collect(cue, adtl, self.plst_chunk.as_ref()));
+        if cu64_chunk.is_some() && self.cue_position_policy == CuePositionPolicy::Refuse {
+            return Err(AudioWriteError::InvalidData(
+                "Some of the cue point positions don't fit in 32 bits, and the `CuePositionPolicy` refuses to write the `cu64` chunk.".to_owned(),
+            ));
+        }
+
         // Updates `fmt` chunk fields (e.g., byte_rate, extension data) and rewrites the header.
         self.writer.seek(SeekFrom::Start(self.fmt_chunk_offset))?;
         self.encoder.update_fmt_chunk(&mut self.fmt__chunk)?;
         self.fmt__chunk.write(&mut self.writer)?;
 
-        // Updates `fact` chunk data, the total number of samples written to the `data` chunk.
-        self.writer.seek(SeekFrom::Start(self.fact_chunk_offset))?;
-        let fact_data = self.num_frames_written * self.spec.channels as u64;
-        match self.file_size_option {
-            FileSizeOption::NeverLargerThan4GB => {
-                (fact_data.clamp(0, 0xFFFFFFFF) as u32).write_le(&mut self.writer)?;
-            }
-            FileSizeOption::AllowLargerThan4GB | FileSizeOption::ForceUse4GBFormat => {
-                fact_data.write_le(&mut self.writer)?;
-            }
+        // Updates `fact` chunk data, the total number of sample frames written to the `data` chunk.
+        // If it doesn't fit in 32 bits, it's `0xFFFFFFFF` and the `ds64` chunk has the actual number.
+        let fact_data = self.num_frames_written;
+        if self.fact_chunk_offset != 0 {
+            self.writer.seek(SeekFrom::Start(self.fact_chunk_offset))?;
+            (fact_data.clamp(0, 0xFFFFFFFF) as u32).write_le(&mut self.writer)?;
         }
 
         // Get back to the end of the data chunk, and then write all remaining chunks (metadata, auxiliary data) to the file.
+        // Every chunk is recorded for the `FinalizeReport`.
         self.writer.seek(SeekFrom::Start(end_of_data))?;
-        self.bext_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer, &self.text_encoding).unwrap();});
-        self.smpl_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        self.inst_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        self.plst_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        self.trkn_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        self.cue__chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        self.list_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer, &self.text_encoding).unwrap();});
-        self.acid_chunk.iter().for_each(|chunk|{chunk.write(&mut self.writer).unwrap();});
-        if let Some(chunk) = &self.id3__chunk {
-            let mut cw = ChunkWriter::begin(&mut self.writer, b"id3 ")?;
-            Id3::id3_write(chunk, &mut cw.writer)?;
-        }
-
-        // Writes all remaining string-based chunks to the file.
-        let mut string_chunks_to_write = Vec::<([u8; 4], &String)>::new();
-        self.axml_chunk.iter().for_each(|chunk|{string_chunks_to_write.push((*b"axml", chunk))});
-        self.ixml_chunk.iter().for_each(|chunk|{string_chunks_to_write.push((*b"ixml", chunk))});
-        for (flag, chunk) in string_chunks_to_write.iter() {
-            let mut cw = ChunkWriter::begin(&mut self.writer, flag)?;
-            write_str(&mut cw.writer, chunk, &self.text_encoding)?;
-        }
-
-        // Writes all JUNK chunks to the file.
-        self.junk_chunks.iter().for_each(|chunk| {
-            chunk.write(&mut self.writer).unwrap();
-        });
+        let text_encoding = &self.text_encoding;
+        let mut info_text_encoding = text_encoding;
+        let mut info_utf8_with_bom = false;
+        for policy in self.info_text_policies.iter() {
+            match policy {
+                InfoTextPolicy::Utf8WithBom => info_utf8_with_bom = true,
+                InfoTextPolicy::CodePage(code_page) => info_text_encoding = code_page,
+                InfoTextPolicy::MirrorToId3 => {
+                    for chunk in self.list_chunk.iter() {
+                        if let ListChunk::Info(dict) = chunk {
+                            self.id3__chunk = Some(Id3::mirror_info(self.id3__chunk.take(), dict)?);
+                        }
+                    }
+                }
+            }
+        }
+        Self::write_recorded_chunks(&mut *self.writer, &mut self.written_chunks, |recorder| {
+            // The chunks that were after the `data` chunk of the file appended to.
+            for (flag, data) in self.relocated_chunks.iter() {
+                recorder.record(|writer| {
+                    let cw = ChunkWriter::begin(writer, flag)?;
+                    cw.writer.write_all(data)?;
+                    cw.end()
+                })?;
+            }
+            for chunk in self.bext_chunk.iter() {recorder.record(|writer| chunk.write(writer, text_encoding))?;}
+            for chunk in self.chna_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.axml_chunk.iter() {recorder.record(|writer| chunk.write(writer, text_encoding))?;}
+            for chunk in self.smpl_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.inst_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.plst_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.trkn_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.cue__chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in cu64_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            for chunk in self.list_chunk.iter() {recorder.record(|writer| chunk.write_with_bom(writer, info_text_encoding, info_utf8_with_bom))?;}
+            for chunk in self.acid_chunk.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            if let Some(chunk) = &self.id3__chunk {
+                recorder.record(|writer| {
+                    let mut cw = ChunkWriter::begin(writer, b"id3 ")?;
+                    Id3::id3_write(chunk, &mut cw.writer)?;
+                    cw.end()
+                })?;
+            }
+
+            // Writes all remaining string-based chunks to the file.
+            let mut string_chunks_to_write = Vec::<([u8; 4], &String)>::new();
+            self.ixml_chunk.iter().for_each(|chunk|{string_chunks_to_write.push((*b"ixml", chunk))});
+            for (flag, chunk) in string_chunks_to_write.iter() {
+                recorder.record(|writer| {
+                    let mut cw = ChunkWriter::begin(writer, flag)?;
+                    write_str(&mut cw.writer, chunk, text_encoding)?;
+                    cw.end()
+                })?;
+            }
+
+            // Writes the chunks that were given as they are.
+            for (flag, data) in self.raw_chunks.iter() {
+                recorder.record(|writer| {
+                    let cw = ChunkWriter::begin(writer, flag)?;
+                    cw.writer.write_all(data)?;
+                    cw.end()
+                })?;
+            }
+
+            // Writes all JUNK chunks to the file.
+            for chunk in self.junk_chunks.iter() {recorder.record(|writer| chunk.write(writer))?;}
+            Ok(())
+        })?;
 
         // Finished RIFF chunk writing.
-        self.riff_chunk = None;
+        if let Some(riff_chunk) = self.riff_chunk.take() {
+            riff_chunk.end()?;
+        }
 
         // Critical large-file handling workflow:
         // ---------------------------------------------------------------------
@@ -635,9 +2087,9 @@ impl<'a> WaveWriter<'a> {
         //    - Fails if RF64 is required but no JUNK placeholder was pre-reserved.
         //    - Callers must invoke `prepare_rf64_placeholder()` before writing chunks
         //      that may exceed 4GB.
-        let file_end_pos = self.writer.stream_position()?;
+        let file_end_pos = self.writer.stream_position()? - self.riff_offset;
         let mut change_to_4gb_hreader = || -> Result<(), AudioWriteError> {
-            self.writer.seek(SeekFrom::Start(0))?;
+            self.writer.seek(SeekFrom::Start(self.riff_offset))?;
             self.writer.write_all(b"RF64")?;
             0xFFFFFFFFu32.write_le(&mut self.writer)?;
             self.writer.write_all(b"WAVE")?;
@@ -651,31 +2103,407 @@ impl<'a> WaveWriter<'a> {
             0u32.write_le(&mut self.writer)?; // table length
             Ok(())
         };
-        match self.file_size_option {
+        let container = match self.file_size_option {
             FileSizeOption::NeverLargerThan4GB => {
                 if file_end_pos > 0xFFFFFFFFu64 {
                     Err(AudioWriteError::NotPreparedFor4GBFile)?;
                 }
+                ContainerKind::Riff
             }
             FileSizeOption::AllowLargerThan4GB => {
                 if file_end_pos > 0xFFFFFFFFu64 {
                     change_to_4gb_hreader()?;
+                    ContainerKind::Rf64
+                } else {
+                    ContainerKind::Riff
                 }
             }
             FileSizeOption::ForceUse4GBFormat => {
                 change_to_4gb_hreader()?;
+                ContainerKind::Rf64
+            }
+        };
+        if container == ContainerKind::Rf64 {
+            // The placeholder `JUNK` chunk was replaced by the `ds64` chunk.
+            if let Some(info) = self.written_chunks.iter_mut().find(|info| &info.flag == b"JUNK") {
+                info.flag = *b"ds64";
+                info.back_patched = true;
             }
         }
+        self.writer.seek(SeekFrom::Start(self.riff_offset + file_end_pos))?;
         self.writer.flush()?;
-        Ok(())
+        Ok(FinalizeReport {
+            chunks: self.written_chunks.clone(),
+            riff_size: file_end_pos - 8,
+            container,
+            position,
+            warnings,
+        })
     }
 
     /// * If you don't want your `WaveWriter` anymore, call this method.
-    pub fn finalize(self) {}
+    /// * It finishes the encoder, updates the `fmt ` and `fact` chunks, writes the metadata chunks, patches the RIFF sizes and flushes the writer.
+    /// * Returns the `FinalizeReport` that tells every chunk written into the file.
+    /// * Any IO error while finalizing is returned here. If the `WaveWriter` is just dropped, it's finalized too, but the errors can only be printed.
+    pub fn finalize(mut self) -> Result<FinalizeReport, AudioWriteError> {
+        if self.finished {
+            return Err(AudioWriteError::AlreadyFinished("the WAV file, it was finalized when the transfer was cancelled".to_owned()));
+        }
+        self.on_drop()
+    }
+
+    /// * Finalize the WAV file without giving up the `WaveWriter`, for the transfer functions that only borrow it, e.g. when they're cancelled.
+    pub(crate) fn finalize_in_place(&mut self) -> Result<FinalizeReport, AudioWriteError> {
+        self.on_drop()
+    }
+
+    /// * Whether the WAV file was finalized. It's finalized only once, even if it failed, then the writes return `AlreadyFinished`.
+    pub fn is_finalized(&self) -> bool {
+        self.finished
+    }
+
+    /// * Finalize the WAV file if it isn't finished, and get the writer back.
+    /// * The writer is positioned at the end of the WAV file.
+    pub fn into_inner(mut self) -> Result<Box<dyn Writer + 'a>, AudioWriteError> {
+        if !self.finished {
+            self.on_drop()?;
+        }
+
+        // The encoder borrows the writer, it must be gone before the writer is taken away.
+        self.encoder = Encoder::default();
+        self.writer.flush_buffer()?;
+        Ok(mem::replace(&mut self.writer.inner, Box::new(CursorVecU8::default())))
+    }
 }
 
 impl Drop for WaveWriter<'_> {
     fn drop(&mut self) {
-        self.on_drop().unwrap()
+        // The pass-through `WaveWriter` that didn't get the audio has nothing to finalize.
+        // Panicking here could abort the program, so the errors are just printed, call `finalize()` to get them.
+        if !self.finished && self.riff_chunk.is_some() {
+            if let Err(e) = self.on_drop() {
+                eprintln!("Failed to finalize the WAV file on drop: {e}");
+            }
+        }
+    }
+}
+
+/// * Build a `WaveWriter` from the spec given once. The fields of the codec parameters that repeat the spec are filled from it when building:
+///   * FLAC: `channels`, `sample_rate` and `bits_per_sample`.
+///   * OggVorbis: `channels` and `sample_rate`.
+///   * MP3: `channels` becomes `Mono` for the mono audio, and `JointStereo` for the stereo audio unless a stereo mode was chosen.
+///   * Opus: the sample rate of the spec is rounded up to the one that the Opus encoder takes, resample your audio to `WaveWriter::spec()`.
+/// * The overrides for a codec are refused by `build()` if the data format is another codec.
+#[derive(Debug, Clone)]
+pub struct WaveWriterBuilder {
+    spec: Spec,
+    data_format: DataFormat,
+    file_size_option: FileSizeOption,
+
+    /// * The first override that doesn't fit the data format
+    mismatch: Option<String>,
+}
+
+impl WaveWriterBuilder {
+    /// * The WAV file is never larger than 4 GB by default.
+    pub fn new(spec: Spec, data_format: DataFormat) -> Self {
+        Self {
+            spec,
+            data_format,
+            file_size_option: FileSizeOption::NeverLargerThan4GB,
+            mismatch: None,
+        }
+    }
+
+    pub fn file_size(mut self, file_size_option: FileSizeOption) -> Self {
+        self.file_size_option = file_size_option;
+        self
+    }
+
+    pub fn flac_compression(mut self, compression: FlacCompression) -> Self {
+        match self.data_format {
+            DataFormat::Flac(ref mut params) => params.compression = compression,
+            _ => self.set_mismatch("flac_compression"),
+        }
+        self
+    }
+
+    pub fn mp3_bitrate(mut self, bitrate: Mp3Bitrate) -> Self {
+        match self.data_format {
+            DataFormat::Mp3(ref mut options) => options.bitrate = bitrate,
+            _ => self.set_mismatch("mp3_bitrate"),
+        }
+        self
+    }
+
+    /// * The quality VBR mode of the Vorbis encoder, from -0.1 to 1.0, a higher number means better quality.
+    pub fn vorbis_quality(mut self, quality: f32) -> Self {
+        match self.data_format {
+            DataFormat::OggVorbis(ref mut params) => params.bitrate = Some(OggVorbisBitrateStrategy::QualityVbr(quality)),
+            _ => self.set_mismatch("vorbis_quality"),
+        }
+        self
+    }
+
+    fn set_mismatch(&mut self, method: &str) {
+        if self.mismatch.is_none() {
+            self.mismatch = Some(format!("`{method}()` doesn't apply to the data format {}", self.data_format));
+        }
+    }
+
+    /// * The spec and the data format that the `WaveWriter` is going to be created with.
+    pub fn configure(&self) -> Result<(Spec, DataFormat), AudioWriteError> {
+        if let Some(mismatch) = &self.mismatch {
+            return Err(AudioWriteError::InvalidArguments(mismatch.clone()));
+        }
+        let mut spec = self.spec;
+        let mut data_format = self.data_format.clone();
+        match data_format {
+            DataFormat::Flac(ref mut params) => {
+                params.channels = spec.channels;
+                params.sample_rate = spec.sample_rate;
+                params.bits_per_sample = spec.bits_per_sample as u32;
+            }
+            DataFormat::OggVorbis(ref mut params) => {
+                if let Some(OggVorbisBitrateStrategy::QualityVbr(quality)) = params.bitrate {
+                    if !(-0.1..=1.0).contains(&quality) {
+                        return Err(AudioWriteError::InvalidArguments(format!(
+                            "The Vorbis quality must be between -0.1 and 1.0, got {quality}."
+                        )));
+                    }
+                }
+                params.channels = spec.channels;
+                params.sample_rate = spec.sample_rate;
+            }
+            DataFormat::Mp3(ref mut options) => match (spec.channels, options.channels) {
+                (1, _) => options.channels = Mp3Channels::Mono,
+                (2, Mp3Channels::Mono | Mp3Channels::NotSet) => options.channels = Mp3Channels::JointStereo,
+                _ => (),
+            },
+            DataFormat::Opus(ref options) => spec.sample_rate = options.get_rounded_up_sample_rate(spec.sample_rate),
+            _ => (),
+        }
+        Ok((spec, data_format))
+    }
+
+    /// * Create the WAV file through a file path.
+    #[cfg(feature = "fs")]
+    pub fn build<'a, P: AsRef<Path>>(self, filename: P) -> Result<WaveWriter<'a>, AudioWriteError> {
+        let (spec, data_format) = self.configure()?;
+        WaveWriter::create(filename, spec, data_format, self.file_size_option)
+    }
+
+    /// * Write the WAV file to the writer, see `WaveWriter::from()`.
+    pub fn build_writer<'a>(self, writer: Box<dyn Writer + 'a>) -> Result<WaveWriter<'a>, AudioWriteError> {
+        let (spec, data_format) = self.configure()?;
+        WaveWriter::from(writer, spec, data_format, self.file_size_option)
+    }
+}
+
+/// * The progress callback of the `WaveWriter` and when to call it next time.
+struct ProgressCallback<'a> {
+    callback: Box<dyn FnMut(&WriteStats) + Send + 'a>,
+    interval_frames: u64,
+    next_report: u64,
+}
+
+impl Debug for ProgressCallback<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressCallback")
+            .field("interval_frames", &self.interval_frames)
+            .field("next_report", &self.next_report)
+            .finish_non_exhaustive()
+    }
+}
+
+/// * The write buffer of the `WaveWriter` between the encoders and the writer, see `WaveWriter::set_write_buffer_size()`.
+/// * Seeking flushes it, except that getting the position is answered by the position it keeps track of.
+struct WriteBuffer<'a> {
+    inner: Box<dyn Writer + 'a>,
+    buffer: Vec<u8>,
+    capacity: usize,
+
+    /// The position after the buffered bytes, `None` until the writer is asked, or after an error.
+    position: Option<u64>,
+
+    /// The MD5 of the bytes written while it's `Some`, see `WaveWriter::enable_audio_hash()`.
+    hasher: Option<md5::Context>,
+}
+
+impl<'a> WriteBuffer<'a> {
+    fn new(inner: Box<dyn Writer + 'a>) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            capacity: 0,
+            position: None,
+            hasher: None,
+        }
+    }
+
+    /// * Write the buffered bytes to the writer, the bytes that were written are removed from the buffer even if it fails.
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let mut result = Ok(());
+        while written < self.buffer.len() {
+            match self.inner.write(&self.buffer[written..]) {
+                Ok(0) => {
+                    result = Err(io::Error::new(io::ErrorKind::WriteZero, "Failed to write the buffered data"));
+                    break;
+                }
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.buffer.drain(..written);
+        if result.is_err() {
+            self.position = None;
+        }
+        result
+    }
+}
+
+impl io::Write for WriteBuffer<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + buf.len() > self.capacity {
+            self.flush_buffer()?;
+        }
+        let written = if buf.len() >= self.capacity {
+            match self.inner.write(buf) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.position = None;
+                    return Err(e);
+                }
+            }
+        } else {
+            self.buffer.extend_from_slice(buf);
+            buf.len()
+        };
+        if let Some(hasher) = self.hasher.as_mut() {
+            hasher.consume(&buf[..written]);
+        }
+        if let Some(position) = self.position.as_mut() {
+            *position += written as u64;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.inner.flush()
+    }
+}
+
+impl io::Seek for WriteBuffer<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let (SeekFrom::Current(0), Some(position)) = (pos, self.position) {
+            return Ok(position);
+        }
+        self.flush_buffer()?;
+        let position = self.inner.seek(pos);
+        self.position = position.as_ref().ok().copied();
+        position
+    }
+}
+
+impl Debug for WriteBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WriteBuffer")
+            .field("inner", &self.inner)
+            .field("buffered", &self.buffer.len())
+            .field("capacity", &self.capacity)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+/// * Passes the chunks after the `data` chunk straight to the writer, see `WaveWriter::write_recorded_chunks()`.
+/// * Only the 8-byte header of the chunk being written is kept, the chunk size is back-patched into it as it passes by, then the chunk is recorded.
+#[derive(Debug)]
+struct ChunkRecorder<'a> {
+    writer: &'a mut dyn Writer,
+    written_chunks: &'a mut Vec<ChunkInfo>,
+
+    /// The position of the writer, tracked here so the writes don't have to ask the writer.
+    position: u64,
+
+    /// Where the header of the chunk being written is.
+    start: u64,
+    header: [u8; 8],
+}
+
+impl<'a> ChunkRecorder<'a> {
+    fn new(writer: &'a mut dyn Writer, written_chunks: &'a mut Vec<ChunkInfo>) -> Result<Self, AudioWriteError> {
+        let position = writer.stream_position()?;
+        Ok(Self {
+            writer,
+            written_chunks,
+            position,
+            start: position,
+            header: [0u8; 8],
+        })
+    }
+
+    /// * Write one chunk by `write`, then record it by the header it wrote.
+    fn record(&mut self, write: impl FnOnce(&mut dyn Writer) -> Result<(), AudioWriteError>) -> Result<(), AudioWriteError> {
+        self.start = self.position;
+        self.header = [0u8; 8];
+        write(self)?;
+        let [f0, f1, f2, f3, l0, l1, l2, l3] = self.header;
+        self.written_chunks.push(ChunkInfo {
+            flag: [f0, f1, f2, f3],
+            offset: self.start,
+            length: u32::from_le_bytes([l0, l1, l2, l3]) as u64,
+            back_patched: false,
+        });
+        Ok(())
+    }
+}
+
+impl io::Write for ChunkRecorder<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        // Keep the bytes that land on the header, including the chunk size written at the end of the chunk.
+        let header_end = self.start + 8;
+        let end = self.position + written as u64;
+        if self.position < header_end && end > self.start {
+            let from = self.position.max(self.start);
+            let to = end.min(header_end);
+            self.header[(from - self.start) as usize..(to - self.start) as usize]
+                .copy_from_slice(&buf[(from - self.position) as usize..(to - self.position) as usize]);
+        }
+        self.position = end;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl io::Seek for ChunkRecorder<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.writer.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+/// * Is the `fmt ` chunk the PCM or the IEEE float format, which has no `fact` chunk and whose byte rate follows the sample rate.
+pub(crate) fn is_pcm_fmt(fmt: &FmtChunk) -> bool {
+    use format_tags::*;
+    use guids::*;
+    match fmt.format_tag {
+        FORMAT_TAG_PCM | FORMAT_TAG_PCM_IEEE => true,
+        FORMAT_TAG_EXTENSIBLE => matches!(
+            &fmt.extension,
+            Some(FmtExtension { data: ExtensionData::Extensible(extensible), .. })
+                if [GUID_PCM_FORMAT, GUID_IEEE_FLOAT_FORMAT, GUID_AMBISONIC_B_FORMAT_PCM, GUID_AMBISONIC_B_FORMAT_IEEE_FLOAT].contains(&extensible.sub_format)
+        ),
+        _ => false,
     }
 }
@@ -0,0 +1,46 @@
+use rustwav_core::*;
+use rustwav_core::io_utils::CursorVecU8;
+
+use format_specs::*;
+use options::*;
+
+/// * Runs under `cargo test -p rustwav-core --no-default-features` too, then the reader spools the decoded samples into the memory instead of a temporary file.
+#[test]
+pub fn test_pcm_adpcm_in_memory() {
+    let spec = Spec {
+        channels: 2,
+        channel_mask: None,
+        sample_rate: 22050,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let frames: Vec<Vec<i16>> = (0..5000).map(|i| {
+        let s = ((i as f64 * 0.01).sin() * 8000.0) as i16;
+        vec![s, s / 2]
+    }).collect();
+
+    for data_format in [DataFormat::Pcm, DataFormat::PcmALaw, DataFormat::PcmMuLaw, DataFormat::Adpcm(AdpcmSubFormat::Ima)] {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_frames(&frames).unwrap();
+        wavewriter.into_inner().unwrap();
+
+        let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert!(decoded.len() >= frames.len(), "{data_format}");
+        if data_format == DataFormat::Pcm {
+            assert_eq!(decoded, frames);
+            continue;
+        }
+
+        // The lossy formats stay close to the source.
+        let (mut signal, mut error) = (0.0f64, 0.0f64);
+        for (frame, decoded) in frames.iter().zip(decoded.iter()) {
+            for (s, d) in frame.iter().zip(decoded.iter()) {
+                signal += (*s as f64).powi(2);
+                error += (*s as f64 - *d as f64).powi(2);
+            }
+        }
+        assert!(error < signal * 0.05, "{data_format}: the error energy {error} is too large for {signal}");
+    }
+}
@@ -6,121 +6,7 @@ use options::*;
 use std::{env::args, process::ExitCode};
 
 /// * The list for the command line program to parse the argument and we have the pre-filled encoder initializer parameter structs for each format.
-#[allow(clippy::large_const_arrays)]
-pub const FORMATS: [(&str, DataFormat); 16] = [
-    ("pcm", DataFormat::Pcm),
-    ("pcm-alaw", DataFormat::PcmALaw),
-    ("pcm-ulaw", DataFormat::PcmMuLaw),
-    ("adpcm-ms", DataFormat::Adpcm(AdpcmSubFormat::Ms)),
-    ("adpcm-ima", DataFormat::Adpcm(AdpcmSubFormat::Ima)),
-    ("adpcm-yamaha", DataFormat::Adpcm(AdpcmSubFormat::Yamaha)),
-    (
-        "mp3",
-        DataFormat::Mp3(Mp3EncoderOptions {
-            channels: Mp3Channels::NotSet,
-            quality: Mp3Quality::Best,
-            bitrate: Mp3Bitrate::Kbps320,
-            vbr_mode: Mp3VbrMode::Off,
-            id3tag: None,
-        }),
-    ),
-    (
-        "opus",
-        DataFormat::Opus(OpusEncoderOptions {
-            bitrate: OpusBitrate::Max,
-            encode_vbr: false,
-            samples_cache_duration: OpusEncoderSampleDuration::MilliSec60,
-        }),
-    ),
-    (
-        "flac",
-        DataFormat::Flac(FlacEncoderParams {
-            verify_decoded: false,
-            compression: FlacCompression::Level8,
-            channels: 2,
-            sample_rate: 44100,
-            bits_per_sample: 32,
-            total_samples_estimate: 0,
-        }),
-    ),
-    (
-        "vorbis",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::NakedVorbis,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Vbr(160000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis1",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::OriginalStreamCompatible,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis2",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::HaveIndependentHeader,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis3",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::HaveNoCodebookHeader,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Vbr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis1p",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::OriginalStreamCompatible,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis2p",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::HaveIndependentHeader,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-    (
-        "oggvorbis3p",
-        DataFormat::OggVorbis(OggVorbisEncoderParams {
-            mode: OggVorbisMode::HaveNoCodebookHeader,
-            channels: 2,
-            sample_rate: 44100,
-            stream_serial: None,
-            bitrate: Some(OggVorbisBitrateStrategy::Abr(320_000)),
-            minimum_page_data_size: None,
-        }),
-    ),
-];
+pub use format_specs::FORMATS;
 
 /// * The `test()` function
 /// * arg1: the format, e.g. "pcm"
@@ -128,16 +14,8 @@ pub const FORMATS: [(&str, DataFormat); 16] = [
 /// * arg3: the output file to encode, test the encoder.
 /// * arg4: re-decode arg3 and encode to pcm to test the decoder.
 pub fn test(arg1: &str, arg2: &str, arg3: &str, arg4: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut data_format = DataFormat::Unspecified;
-    for format in FORMATS {
-        if arg1 == format.0 {
-            data_format = format.1;
-            break;
-        }
-    }
-
     // Failed to match the data format
-    if data_format == DataFormat::Unspecified {
+    let Some(data_format) = DataFormat::from_name(arg1) else {
         return Err(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
             format!(
@@ -150,52 +28,31 @@ pub fn test(arg1: &str, arg2: &str, arg3: &str, arg4: &str) -> Result<(), Box<dy
             ),
         )
         .into());
-    }
+    };
 
     println!("======== TEST 1 ========");
     println!("{:?}", data_format);
 
     // This is the decoder
-    let mut wavereader = WaveReader::open(arg2).unwrap();
+    let mut wavereader = WaveReader::open(arg2)?;
 
     let orig_spec = wavereader.spec();
 
-    // The spec for the encoder
-    let mut spec = Spec {
+    // The spec for the encoder, the builder fills the parameters of the codec from it
+    let spec = Spec {
         channels: orig_spec.channels,
-        channel_mask: 0,
+        channel_mask: None,
         sample_rate: orig_spec.sample_rate,
         bits_per_sample: 16,
         sample_format: SampleFormat::Int,
     };
 
-    match data_format {
-        DataFormat::Mp3(ref mut options) => match spec.channels {
-            1 => options.channels = Mp3Channels::Mono,
-            2 => options.channels = Mp3Channels::JointStereo,
-            o => panic!("MP3 format can't encode {o} channels audio."),
-        },
-        DataFormat::Opus(ref options) => {
-            spec.sample_rate = options.get_rounded_up_sample_rate(spec.sample_rate);
-        }
-        DataFormat::Flac(ref mut options) => {
-            options.channels = spec.channels;
-            options.sample_rate = spec.sample_rate;
-            options.bits_per_sample = spec.bits_per_sample as u32;
-        }
-        DataFormat::OggVorbis(ref mut options) => {
-            options.channels = spec.channels;
-            options.sample_rate = spec.sample_rate;
-        }
-        _ => (),
-    }
-
     // Just to let you know, WAV file can be larger than 4 GB
     #[allow(unused_imports)]
     use options::FileSizeOption::{AllowLargerThan4GB, ForceUse4GBFormat, NeverLargerThan4GB};
 
     // This is the encoder
-    let mut wavewriter = WaveWriter::create(arg3, spec, data_format, NeverLargerThan4GB).unwrap();
+    let mut wavewriter = WaveWriterBuilder::new(spec, data_format).file_size(NeverLargerThan4GB).build(arg3).unwrap();
 
     // Transfer audio samples from the decoder to the encoder
     transfer_audio_from_decoder_to_encoder(&mut wavereader, &mut wavewriter);
@@ -208,19 +65,22 @@ pub fn test(arg1: &str, arg2: &str, arg3: &str, arg4: &str) -> Result<(), Box<dy
     dbg!(&wavewriter);
 
     drop(wavereader);
-    drop(wavewriter);
+    let report = wavewriter.finalize()?;
 
     println!("======== TEST 2 ========");
 
     let spec2 = Spec {
         channels: spec.channels,
-        channel_mask: 0,
+        channel_mask: None,
         sample_rate: orig_spec.sample_rate,
         bits_per_sample: 16,
         sample_format: SampleFormat::Int,
     };
 
-    let mut wavereader_2 = WaveReader::open(arg3).unwrap();
+    let mut wavereader_2 = WaveReader::open(arg3)?;
+
+    // The layout the writer reported must be what the reader finds
+    assert!(report.matches_chunk_map(wavereader_2.chunk_map()), "{:?} vs {:?}", report.chunks, wavereader_2.chunk_map());
     let mut wavewriter_2 = WaveWriter::create(arg4, spec2, DataFormat::Pcm, NeverLargerThan4GB).unwrap();
 
     // Transfer audio samples from the decoder to the encoder
@@ -234,8 +94,59 @@ pub fn test(arg1: &str, arg2: &str, arg3: &str, arg4: &str) -> Result<(), Box<dy
     dbg!(&wavewriter_2);
 
     drop(wavereader_2);
-    drop(wavewriter_2);
+    let report_2 = wavewriter_2.finalize()?;
+    let wavereader_3 = WaveReader::open(arg4)?;
+    assert!(report_2.matches_chunk_map(wavereader_3.chunk_map()), "{:?} vs {:?}", report_2.chunks, wavereader_3.chunk_map());
+    assert_eq!(report_2.container, ContainerKind::Riff);
+
+    Ok(())
+}
+
+/// * The spec most of the tests write: 16-bit integer PCM without the channel mask.
+#[cfg(test)]
+fn test_spec(channels: u16, sample_rate: u32) -> Spec {
+    Spec {
+        channels,
+        channel_mask: None,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    }
+}
+
+/// * A new temporary directory and the path of the file `name` in it, the directory is removed when it's dropped.
+#[cfg(test)]
+fn temp_wav(name: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    (dir, path)
+}
+
+/// * Write a WAV file at `path` by `write`, finalize it and open it again for reading.
+#[cfg(test)]
+fn write_and_reopen(path: impl AsRef<std::path::Path>, spec: Spec, data_format: DataFormat, write: impl FnOnce(&mut WaveWriter)) -> WaveReader {
+    let path = path.as_ref();
+    let mut wavewriter = WaveWriter::create(path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+    write(&mut wavewriter);
+    wavewriter.finalize().unwrap();
+    WaveReader::open(path.to_str().unwrap()).unwrap()
+}
+
+/// * The stereo ramp the tests write when the values only need to be told apart: the left channel counts up, the right channel counts down.
+#[cfg(test)]
+fn ramp_stereos(num_frames: usize) -> Vec<(i16, i16)> {
+    (0..num_frames).map(|i| (i as i16, -(i as i16))).collect()
+}
 
+/// * Generate the input file for the tests: one second of sine, sweep and white noise each, stereo 44100 Hz 16-bit PCM.
+#[cfg(test)]
+fn create_test_wav(path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = test_spec(2, 44100);
+    let mut wavewriter = WaveWriter::create(path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB)?;
+    testsig::write_sine(&mut wavewriter, 440.0, 1.0)?;
+    testsig::write_sweep(&mut wavewriter, 20.0, 20000.0, 1.0)?;
+    testsig::write_white_noise(&mut wavewriter, 1, 1.0)?;
+    wavewriter.finalize()?;
     Ok(())
 }
 
@@ -244,11 +155,14 @@ macro_rules! test_fn {
         #[test]
         pub fn $name() {
             let fmt = FORMATS[$index].0;
+            let dir = tempfile::tempdir().unwrap();
+            let input = dir.path().join("test.wav");
+            create_test_wav(&input).unwrap();
             test(
                 fmt,
-                "test.wav",
-                &format!("{fmt}_test_encode.wav"),
-                &format!("{fmt}_test_decode.wav"),
+                input.to_str().unwrap(),
+                dir.path().join(format!("{fmt}_test_encode.wav")).to_str().unwrap(),
+                dir.path().join(format!("{fmt}_test_decode.wav")).to_str().unwrap(),
             )
             .unwrap();
         }
@@ -271,7 +185,1054 @@ test_fn!(test_oggvorbis3, 12);
 test_fn!(test_oggvorbis1p, 13);
 test_fn!(test_oggvorbis2p, 14);
 test_fn!(test_oggvorbis3p, 15);
+test_fn!(test_pcm_extensible, 16);
+test_fn!(test_adpcm_ima2, 17);
+test_fn!(test_adpcm_ima3, 18);
+test_fn!(test_adpcm_ima5, 19);
+
+#[test]
+pub fn test_formats_table() {
+    // Every name maps back to its format.
+    for (name, data_format) in FORMATS.iter() {
+        assert_eq!(DataFormat::from_name(name).as_ref(), Some(data_format), "{name}");
+    }
+    assert_eq!(DataFormat::from_name("no-such-format"), None);
+
+    // Every variant of `DataFormat` is in the table, the match has no wildcard so a new variant won't compile until it's listed here.
+    let variant_index = |data_format: &DataFormat| match data_format {
+        DataFormat::Unspecified => 0,
+        DataFormat::Pcm => 1,
+        DataFormat::PcmExtensible(_) => 2,
+        DataFormat::Adpcm(_) => 3,
+        DataFormat::PcmALaw => 4,
+        DataFormat::PcmMuLaw => 5,
+        DataFormat::Mp3(_) => 6,
+        DataFormat::Opus(_) => 7,
+        DataFormat::Flac(_) => 8,
+        DataFormat::OggVorbis(_) => 9,
+        DataFormat::Custom(_) => 10,
+        DataFormat::PassThrough => 11,
+    };
+    let mut covered = [false; 12];
+    FORMATS.iter().for_each(|(_, data_format)| covered[variant_index(data_format)] = true);
+    assert!(!covered[0], "`Unspecified` is not a format to encode");
+    assert!(!covered[10], "`Custom` needs the encoder provided by you");
+    assert!(!covered[11], "`PassThrough` copies the audio without encoding");
+    assert!(covered[1..10].iter().all(|c| *c), "{covered:?}");
+
+    // The options are the defaults except for these intentional overrides:
+    // * FLAC: compression level 8 and 32-bit samples for the best quality
+    // * Vorbis: stereo 44100 Hz, the mode and the bitrate strategy are what each entry tests
+    for (name, data_format) in FORMATS.iter() {
+        match data_format {
+            DataFormat::PcmExtensible(options) => assert_eq!(*options, PcmEncoderOptions::default(), "{name}"),
+            DataFormat::Mp3(options) => assert_eq!(*options, Mp3EncoderOptions::default(), "{name}"),
+            DataFormat::Opus(options) => assert_eq!(*options, OpusEncoderOptions::default(), "{name}"),
+            DataFormat::Flac(options) => assert_eq!(
+                FlacEncoderParams {
+                    compression: FlacEncoderParams::default().compression,
+                    bits_per_sample: FlacEncoderParams::default().bits_per_sample,
+                    ..*options
+                },
+                FlacEncoderParams::default(),
+                "{name}"
+            ),
+            DataFormat::OggVorbis(options) => assert_eq!(
+                OggVorbisEncoderParams {
+                    mode: OggVorbisEncoderParams::default().mode,
+                    bitrate: OggVorbisEncoderParams::default().bitrate,
+                    channels: OggVorbisEncoderParams::default().channels,
+                    sample_rate: OggVorbisEncoderParams::default().sample_rate,
+                    ..*options
+                },
+                OggVorbisEncoderParams::default(),
+                "{name}"
+            ),
+            _ => (),
+        }
+    }
+}
+
+#[test]
+pub fn test_open_truncated_header() {
+    let (_dir, path) = temp_wav("truncated.wav");
+    std::fs::write(&path, b"RIFF\x24\x00\x00\x00WA").unwrap();
+    let path = path.to_str().unwrap();
+    let err = WaveReader::open(path).unwrap_err();
+    assert!(err.is_parse_error());
+    assert_eq!(err.get_path(), Some(path));
+    let message = err.to_string();
+    assert!(message.contains(path));
+    assert!(message.contains("Incomplete data"));
+}
+
+#[test]
+pub fn test_ambisonic_b_format_round_trip() {
+    use format_specs::guids::GUID_AMBISONIC_B_FORMAT_PCM;
+    let (_dir, path) = temp_wav("b_format.wav");
+    let path = path.to_str().unwrap();
+    let spec = test_spec(4, 48000);
+    let options = PcmEncoderOptions {
+        sub_format_guid: Some(GUID_AMBISONIC_B_FORMAT_PCM),
+    };
+    let frames: Vec<Vec<i16>> = (0..1000i16).map(|i| vec![i, -i, i / 2, -i / 2]).collect();
+    let mut wavereader = write_and_reopen(path, spec, DataFormat::PcmExtensible(options), |wavewriter| wavewriter.write_frames(&frames).unwrap());
+    assert_eq!(wavereader.get_sub_format_guid(), Some(GUID_AMBISONIC_B_FORMAT_PCM));
+    assert_eq!(wavereader.spec().channels, 4);
+    let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, frames);
+}
+
+/// * Build a minimal mono 16-bit WAV file with the development format tag `0xFFFF` and the given sub-format GUID.
+#[cfg(test)]
+fn create_development_format_wav(sub_format: &format_specs::guids::GUID, samples: &[i16]) -> Vec<u8> {
+    let mut fmt = Vec::<u8>::new();
+    fmt.extend_from_slice(&0xFFFFu16.to_le_bytes()); // format_tag
+    fmt.extend_from_slice(&1u16.to_le_bytes()); // channels
+    fmt.extend_from_slice(&8000u32.to_le_bytes()); // sample_rate
+    fmt.extend_from_slice(&16000u32.to_le_bytes()); // byte_rate
+    fmt.extend_from_slice(&2u16.to_le_bytes()); // block_align
+    fmt.extend_from_slice(&16u16.to_le_bytes()); // bits_per_sample
+    fmt.extend_from_slice(&22u16.to_le_bytes()); // ext_len
+    fmt.extend_from_slice(&16u16.to_le_bytes()); // valid_bits_per_sample
+    fmt.extend_from_slice(&4u32.to_le_bytes()); // channel_mask
+    fmt.extend_from_slice(&sub_format.to_bytes());
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let mut ret = Vec::<u8>::new();
+    ret.extend_from_slice(b"RIFF");
+    ret.extend_from_slice(&(4 + 8 + fmt.len() as u32 + 8 + data.len() as u32).to_le_bytes());
+    ret.extend_from_slice(b"WAVE");
+    ret.extend_from_slice(b"fmt ");
+    ret.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    ret.extend_from_slice(&fmt);
+    ret.extend_from_slice(b"data");
+    ret.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    ret.extend_from_slice(&data);
+    ret
+}
+
+#[test]
+pub fn test_development_format_tag() {
+    use format_specs::guids::{GUID, GUID_PCM_FORMAT};
+    let dir = tempfile::tempdir().unwrap();
+    let samples: Vec<i16> = (0..1000).map(|i| (i * 31) as i16).collect();
+
+    // With the PCM GUID, the data decodes as PCM.
+    let path = dir.path().join("development_pcm.wav");
+    std::fs::write(&path, create_development_format_wav(&GUID_PCM_FORMAT, &samples)).unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, samples);
+
+    // With an unknown GUID, the error tells the GUID.
+    let unknown = GUID(0x12345678, 0x9abc, 0xdef0, [1, 2, 3, 4, 5, 6, 7, 8]);
+    let path = dir.path().join("development_unknown.wav");
+    std::fs::write(&path, create_development_format_wav(&unknown, &samples)).unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    match wavereader.mono_iter::<i16>().unwrap_err() {
+        errors::AudioReadError::UnsupportedFormat(0xFFFF, Some(guid)) => assert_eq!(guid, unknown),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert_eq!(wavereader.get_data_length(), samples.len() as u64 * 2);
+}
+
+/// * Counts the allocations and the allocated bytes of the current thread, for the tests to check memory usage.
+#[cfg(test)]
+struct CountingAllocator;
+
+#[cfg(test)]
+thread_local! {
+    static NUM_ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    static ALLOCATED_BYTES: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    static PEAK_ALLOCATED_BYTES: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+impl CountingAllocator {
+    fn count_bytes(delta: isize) {
+        let _ = ALLOCATED_BYTES.try_with(|bytes| {
+            bytes.set(bytes.get() + delta);
+            let _ = PEAK_ALLOCATED_BYTES.try_with(|peak| peak.set(peak.get().max(bytes.get())));
+        });
+    }
+}
+
+#[cfg(test)]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = NUM_ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        Self::count_bytes(layout.size() as isize);
+        unsafe { std::alloc::System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        Self::count_bytes(-(layout.size() as isize));
+        unsafe { std::alloc::System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        let _ = NUM_ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        Self::count_bytes(new_size as isize - layout.size() as isize);
+        unsafe { std::alloc::System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+pub fn test_write_frames_allocations() {
+    let (_dir, path) = temp_wav("allocations.wav");
+    let spec = test_spec(6, 48000);
+    let frames: Vec<Vec<f32>> = (0..4096).map(|i| vec![(i as f32 / 4096.0).sin(); 6]).collect();
+    let mut wavewriter = WaveWriter::create(path.to_str().unwrap(), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+
+    // Warm up the scratch buffers
+    wavewriter.write_frames(&frames).unwrap();
+
+    let allocations_before = NUM_ALLOCATIONS.with(|n| n.get());
+    for _ in 0..100 {
+        wavewriter.write_frames(&frames).unwrap();
+    }
+    let allocations = NUM_ALLOCATIONS.with(|n| n.get()) - allocations_before;
+    assert!(allocations < 10, "{allocations} allocations for 100 blocks");
+    wavewriter.finalize().unwrap();
+}
+
+#[test]
+pub fn test_transfer_allocations() {
+    let (dir, src_path) = temp_wav("transfer_src.wav");
+    let spec = test_spec(6, 48000);
+    let frames: Vec<Vec<i16>> = (0..4096).map(|i| vec![(i * 7) as i16; 6]).collect();
+    let mut wavewriter = WaveWriter::create(&src_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    for _ in 0..200 {
+        wavewriter.write_frames(&frames).unwrap();
+    }
+    wavewriter.finalize().unwrap();
+
+    // Same spec, no gains: the native transfer goes through 200 blocks of 4096 frames.
+    let dst_path = dir.path().join("transfer_dst.wav");
+    let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    let mut wavewriter = WaveWriter::create(&dst_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    let allocations_before = NUM_ALLOCATIONS.with(|n| n.get());
+    transfer_audio_with_options(&mut wavereader, &mut wavewriter, &TransferOptions::default()).unwrap();
+    let allocations = NUM_ALLOCATIONS.with(|n| n.get()) - allocations_before;
+    assert!(allocations < 100, "{allocations} allocations for 200 blocks");
+    wavewriter.finalize().unwrap();
+
+    let wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(200 * 4096));
+}
+
+#[test]
+pub fn test_write_planar_allocations() {
+    let (_dir, path) = temp_wav("planar.wav");
+    let spec = test_spec(8, 48000);
+    let planar: Vec<Vec<i16>> = (0..8).map(|c| (0..4096).map(|i| (i * 8 + c) as i16).collect()).collect();
+    let channels: Vec<&[i16]> = planar.iter().map(|channel| channel.as_slice()).collect();
+    let frames: Vec<Vec<i16>> = (0..4096).map(|i| planar.iter().map(|channel| channel[i]).collect()).collect();
+    let frame_slices: Vec<&[i16]> = frames.iter().map(|frame| frame.as_slice()).collect();
+    let mut wavewriter = WaveWriter::create(path.to_str().unwrap(), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+
+    // Warm up the scratch buffers
+    wavewriter.write_monos_planar(&channels).unwrap();
+
+    let allocations_before = NUM_ALLOCATIONS.with(|n| n.get());
+    for _ in 0..100 {
+        wavewriter.write_monos_planar(&channels).unwrap();
+        wavewriter.write_frames_planar(&frame_slices).unwrap();
+    }
+    let allocations = NUM_ALLOCATIONS.with(|n| n.get()) - allocations_before;
+    assert!(allocations < 10, "{allocations} allocations for 200 blocks");
+
+    match wavewriter.write_monos_planar(&[&planar[0][..], &planar[1][..10]]).unwrap_err() {
+        AudioWriteError::ChannelsNotInSameSize => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    wavewriter.finalize().unwrap();
+
+    // Both ways of writing give the same frames
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(201 * 4096));
+    let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+    for block in decoded.chunks(4096) {
+        assert_eq!(block, &frames[..]);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_open_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+    let (_dir, path) = temp_wav("denied.wav");
+    create_test_wav(&path).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o000)).unwrap();
+    if std::fs::File::open(&path).is_ok() {
+        // Running as root, the permission can't be denied.
+        return;
+    }
+    let path = path.to_str().unwrap();
+    let err = WaveReader::open(path).unwrap_err();
+    assert!(err.is_io_error());
+    assert_eq!(err.io_error_kind(), Some(std::io::ErrorKind::PermissionDenied));
+    let message = err.to_string();
+    assert!(message.contains(path));
+    assert!(message.contains("IO error"));
+}
+
+
+#[test]
+pub fn test_adpcm_ima_stereo_interop() {
+    let dir = tempfile::tempdir().unwrap();
+    let source_path = dir.path().join("ima_source.wav");
+    let ours_path = dir.path().join("ima_ours.wav");
+    let ffmpeg_path = dir.path().join("ima_ffmpeg.wav");
+    let reference_path = dir.path().join("ima_ffmpeg_decoded.wav");
+    let spec = test_spec(2, 44100);
+    // Different waveforms for the two channels, so swapped channels can't go unnoticed.
+    let stereos: Vec<(i16, i16)> = (0..10000)
+        .map(|i| {
+            let t = i as f32 / 44100.0;
+            (
+                ((t * 440.0 * std::f32::consts::TAU).sin() * 12000.0) as i16,
+                ((t * 1234.0 * std::f32::consts::TAU).sin() * 8000.0) as i16,
+            )
+        })
+        .collect();
+    for (path, data_format) in [(&source_path, DataFormat::Pcm), (&ours_path, DataFormat::Adpcm(AdpcmSubFormat::Ima))] {
+        let mut wavewriter = WaveWriter::create(path.to_str().unwrap(), spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_stereos(&stereos).unwrap();
+        wavewriter.finalize().unwrap();
+    }
+
+    // The block header stores the first sample verbatim, and no extra sample is inserted at the block boundaries.
+    let mut wavereader = WaveReader::open(ours_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded.len(), stereos.len());
+    assert_eq!(decoded[0], stereos[0]);
+    assert_eq!(decoded[1017], stereos[1017]);
+
+    // Decode a file encoded by ffmpeg, compare with ffmpeg's own decode of the same file.
+    let ffmpeg = |input: &std::path::Path, codec: &str, output: &std::path::Path| -> bool {
+        std::process::Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-i"])
+            .arg(input)
+            .args(["-c:a", codec])
+            .arg(output)
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    if !ffmpeg(&source_path, "adpcm_ima_wav", &ffmpeg_path) || !ffmpeg(&ffmpeg_path, "pcm_s16le", &reference_path) {
+        eprintln!("`ffmpeg` is not available, skipped the interoperability test.");
+        return;
+    }
+    let mut wavereader = WaveReader::open(reference_path.to_str().unwrap()).unwrap();
+    let reference: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    let mut wavereader = WaveReader::open(ffmpeg_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    // Our decoder ends at the length of the `fact` chunk, ffmpeg may decode the padding of the last block too.
+    assert!(decoded.len() >= stereos.len());
+    for (i, (ours, theirs)) in decoded.iter().zip(reference.iter()).enumerate() {
+        assert!(
+            (ours.0 as i32 - theirs.0 as i32).abs() <= 1 && (ours.1 as i32 - theirs.1 as i32).abs() <= 1,
+            "Frame {i} differs: ours {ours:?}, ffmpeg {theirs:?}"
+        );
+    }
+}
+
+#[test]
+pub fn test_decode_budget() {
+    use errors::{AudioReadError, DecodeBudgetLimit};
+    let dir = tempfile::tempdir().unwrap();
+    let mut cases = vec![("budget_ima.wav", 8000, DataFormat::Adpcm(AdpcmSubFormat::Ima))];
+    if cfg!(feature = "oggvorbis") {
+        // A real decompressing decoder, the budget must hold for the frames it produces from the packets.
+        cases.push(("budget_vorbis.wav", 44100, DataFormat::OggVorbis(OggVorbisEncoderParams {
+            channels: 1,
+            sample_rate: 44100,
+            bitrate: Some(OggVorbisBitrateStrategy::Vbr(128_000)),
+            ..OggVorbisEncoderParams::new()
+        })));
+    }
+    for (name, sample_rate, data_format) in cases {
+        let path = dir.path().join(name);
+        let path = path.to_str().unwrap();
+        let spec = test_spec(1, sample_rate);
+        let monos: Vec<i16> = (0..20000).map(|i| ((i % 100) * 300) as i16).collect();
+        let mut wavewriter = WaveWriter::create(path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_mono_channel(&monos).unwrap();
+        wavewriter.finalize().unwrap();
 
+        // Exceeds the number of frames
+        let read_options = ReadOptions {
+            max_decoded_frames: Some(5000),
+            max_decode_wall_time: None,
+            ..ReadOptions::default()
+        };
+        let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+        let mut iter = wavereader.mono_iter::<i16>().unwrap();
+        assert_eq!(iter.decode_monos(5000).unwrap().len(), 5000, "{name}");
+        match iter.decode_monos(monos.len()).unwrap_err() {
+            AudioReadError::BudgetExceeded(DecodeBudgetLimit::Frames(5000)) => (),
+            other => panic!("Unexpected error of {name}: {other}"),
+        }
+
+        // One large buffer gets the frames within the budget, then the next call exceeds it.
+        let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+        let mut buf = vec![0i16; monos.len()];
+        assert_eq!(wavereader.decode_into(&mut buf).unwrap(), 5000, "{name}");
+        match wavereader.decode_into(&mut buf).unwrap_err() {
+            AudioReadError::BudgetExceeded(DecodeBudgetLimit::Frames(5000)) => (),
+            other => panic!("Unexpected error of {name}: {other}"),
+        }
+
+        // Exceeds the decoding time
+        let read_options = ReadOptions {
+            max_decoded_frames: None,
+            max_decode_wall_time: Some(std::time::Duration::ZERO),
+            ..ReadOptions::default()
+        };
+        let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+        match wavereader.frame_iter::<i16>().unwrap().decode_frames(monos.len()).unwrap_err() {
+            AudioReadError::BudgetExceeded(DecodeBudgetLimit::WallTime(_)) => (),
+            other => panic!("Unexpected error of {name}: {other}"),
+        }
+
+        // The untrusted defaults don't get in the way of normal files
+        let mut wavereader = WaveReader::open_with_options(path, ReadOptions::untrusted()).unwrap();
+        assert!(wavereader.mono_iter::<i16>().unwrap().decode_monos(monos.len() * 2).unwrap().len() >= monos.len(), "{name}");
+    }
+}
+
+#[test]
+pub fn test_info_text_policies() {
+    use chunks::{ListChunk, ListInfo};
+    use utils::StringCodecMaps;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(1, 8000);
+    let title = "夜に駆ける 🎵";
+    let artist = "周杰倫 🎤";
+    let write_with = |name: &str, policies: Vec<InfoTextPolicy>| -> Option<WaveReader> {
+        let path = dir.path().join(name);
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        if wavewriter.set_info_text_policies(policies).is_err() {
+            // Mirroring to ID3 requires the `id3` feature.
+            assert!(cfg!(not(feature = "id3")));
+            return None;
+        }
+        let mut info = ListChunk::Info(Default::default());
+        info.set_name(title).unwrap();
+        info.set_artist(artist).unwrap();
+        info.set_software("rustwav").unwrap();
+        wavewriter.set_list_chunk(info);
+        wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+        wavewriter.finalize().unwrap();
+        Some(WaveReader::open(path.to_str().unwrap()).unwrap())
+    };
+
+    // The UTF-8 text with a BOM survives in the `LIST INFO` chunk itself.
+    let wavereader = write_with("utf8_bom.wav", vec![InfoTextPolicy::Utf8WithBom]).unwrap();
+    let info = wavereader.get_list_chunk().iter().find(|chunk| chunk.get_is_list_info()).unwrap();
+    assert_eq!(info.get_name().unwrap(), title);
+    assert_eq!(info.get_artist().unwrap(), artist);
+    assert_eq!(info.get_software().unwrap(), "rustwav");
+    assert!(wavereader.get_id3__chunk().is_none());
+    assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), title);
+
+    // The code page text may lose the characters, but the mirrored ID3 tag keeps them.
+    if let Some(wavereader) = write_with("code_page_id3.wav", vec![InfoTextPolicy::CodePage(StringCodecMaps::new()), InfoTextPolicy::MirrorToId3]) {
+        assert!(wavereader.get_list_chunk().iter().any(|chunk| chunk.get_name().is_some()));
+        assert!(wavereader.get_id3__chunk().is_some());
+        assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), title);
+        assert_eq!(wavereader.get_metadata_text("IART").unwrap(), artist);
+        assert_eq!(wavereader.get_metadata_text("ISFT").unwrap(), "rustwav");
+    }
+
+    // Mirror alone, the `LIST INFO` chunk is still written by the default code page.
+    if let Some(wavereader) = write_with("id3.wav", vec![InfoTextPolicy::MirrorToId3]) {
+        assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), title);
+        assert_eq!(wavereader.get_metadata_text("IART").unwrap(), artist);
+    }
+}
+
+#[test]
+pub fn test_streaming_reader() {
+    use errors::AudioReadError;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos: Vec<(i16, i16)> = (0..10000).map(|i| (((i % 200) * 150) as i16, -(((i % 300) * 100) as i16))).collect();
+    let mut data_formats = vec![
+        DataFormat::Pcm,
+        DataFormat::Adpcm(AdpcmSubFormat::Ms),
+        DataFormat::Adpcm(AdpcmSubFormat::Ima),
+        DataFormat::Adpcm(AdpcmSubFormat::Yamaha),
+        DataFormat::PcmALaw,
+        DataFormat::PcmMuLaw,
+    ];
+    if cfg!(all(feature = "mp3enc", feature = "mp3dec")) {
+        data_formats.push(DataFormat::Mp3(Mp3EncoderOptions::new()));
+    }
+    for (i, data_format) in data_formats.into_iter().enumerate() {
+        let path = dir.path().join(format!("stream_{i}.wav"));
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        let mut info = chunks::ListChunk::Info(Default::default());
+        chunks::ListInfo::set_name(&mut info, "Trailing metadata").unwrap();
+        wavewriter.set_list_chunk(info);
+        wavewriter.write_stereos(&stereos).unwrap();
+        wavewriter.finalize().unwrap();
+
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let expected: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+
+        // Feed the file as a plain `Read` that can't seek, like the stdin.
+        let bytes = std::fs::read(&path).unwrap();
+        let mut wavereader = WaveReader::from_reader(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(wavereader.spec().channels, 2);
+        assert_eq!(wavereader.spec().sample_rate, 44100);
+        assert!(wavereader.get_list_chunk().is_empty(), "The chunks after the `data` chunk can't be read from a stream");
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, expected, "Streaming decode mismatch for {:?}", wavereader.get_fmt__chunk().format_tag);
+
+        // The stream can't be read twice.
+        match wavereader.stereo_iter::<i16>().unwrap_err() {
+            AudioReadError::Unsupported(_) => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+    }
+}
+
+#[test]
+pub fn test_writer_position() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+    let num_frames = 12345u64;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| (((i % 200) * 150) as i16, -(((i % 300) * 100) as i16))).collect();
+    for (name, data_format) in FORMATS.into_iter().take(10) {
+        let path = dir.path().join(format!("position_{name}.wav"));
+        let mut wavewriter = WaveWriterBuilder::new(spec, data_format).build(&path).unwrap();
+        wavewriter.write_stereos(&stereos).unwrap();
+
+        // Every frame is accepted immediately, some of them may still be in the encoder buffers.
+        let position = wavewriter.position().unwrap();
+        assert_eq!(position.frames_accepted, num_frames, "{name}");
+        assert!(position.frames_committed <= position.frames_accepted, "{name}: {position:?}");
+        if name == "pcm" {
+            assert_eq!(position.frames_committed, num_frames);
+            assert_eq!(position.bytes_committed, num_frames * 4);
+        }
+
+        // After finishing, the committed frames catch up exactly.
+        let report = wavewriter.finalize().unwrap();
+        assert_eq!(report.position.frames_accepted, num_frames, "{name}");
+        assert_eq!(report.position.frames_committed, num_frames, "{name}");
+        assert!(report.position.bytes_committed >= position.bytes_committed, "{name}");
+        assert_eq!(
+            report.position.bytes_committed,
+            WaveReader::open(path.to_str().unwrap()).unwrap().get_data_length(),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+pub fn test_mp3_gapless() {
+    if !cfg!(all(feature = "mp3enc", feature = "mp3dec")) {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    for (channels, sample_rate, vbr_mode) in [(2u16, 44100u32, Mp3VbrMode::Off), (2, 44100, Mp3VbrMode::Mtrh), (1, 22050, Mp3VbrMode::Off)] {
+        let spec = test_spec(channels, sample_rate);
+
+        // Silence with a tone burst at a known position, the number of frames isn't a multiple of the MP3 frame size.
+        let num_frames = 12345usize;
+        let monos: Vec<i16> = (0..num_frames).map(|i| {
+            if (5000..6000).contains(&i) {
+                ((i as f64 * 1000.0 / sample_rate as f64 * std::f64::consts::TAU).sin() * 10000.0) as i16
+            } else {
+                0
+            }
+        }).collect();
+        let options = Mp3EncoderOptions {
+            channels: if channels == 1 { Mp3Channels::Mono } else { Mp3Channels::JointStereo },
+            bitrate: Mp3Bitrate::Kbps128,
+            vbr_mode,
+            ..Mp3EncoderOptions::new()
+        };
+        let path = dir.path().join(format!("gapless_{channels}_{sample_rate}_{vbr_mode:?}.wav"));
+        let path = path.to_str().unwrap();
+        // The encoder delay and the padding are trimmed by the LAME tag, the burst stays where it was.
+        let mut wavereader = write_and_reopen(path, spec, DataFormat::Mp3(options), |wavewriter| wavewriter.write_mono_channel(&monos).unwrap());
+        assert_eq!(wavereader.total_frames(), Some(num_frames as u64));
+        let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames, "{channels} channels, {sample_rate} Hz, {vbr_mode:?}");
+        let onset = decoded.iter().position(|s| s.abs() > 2000).unwrap();
+        assert!((4400..5050).contains(&onset), "{channels} channels, {sample_rate} Hz, {vbr_mode:?}: the burst begins at {onset}");
+    }
+}
+
+#[test]
+pub fn test_mp3_vbr_headers() {
+    if !cfg!(all(feature = "mp3enc", feature = "mp3dec")) {
+        return;
+    }
+    use std::io::SeekFrom;
+    use decoders::mp3::Mp3Decoder;
+    use io_utils::CursorVecU8;
+    let spec = test_spec(2, 44100);
+    let frames: Vec<Vec<i16>> = (0..44100).map(|i| {
+        let phase = (i as f64 / 44100.0).powi(2) * 2000.0 * std::f64::consts::TAU;
+        vec![(phase.sin() * 8000.0) as i16, (phase.cos() * 8000.0) as i16]
+    }).collect();
+    let options = Mp3EncoderOptions {
+        channels: Mp3Channels::JointStereo,
+        vbr_mode: Mp3VbrMode::Mtrh,
+        ..Mp3EncoderOptions::new()
+    };
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Mp3(options), FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_frames(&frames).unwrap();
+    wavewriter.into_inner().unwrap();
+    let bytes = cursor.into_inner();
+
+    // The gapless decode by the LAME tag is the reference.
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes.clone())))).unwrap();
+    let chunk = |flag: &[u8; 4]| wavereader.chunk_map().iter().find(|info| &info.flag == flag).copied().unwrap();
+    let (fmt_, data) = (chunk(b"fmt "), chunk(b"data"));
+    let fmt_bytes = bytes[fmt_.offset as usize..(fmt_.offset + 8 + fmt_.length + (fmt_.length & 1)) as usize].to_vec();
+    let mp3_data = &bytes[(data.offset + 8) as usize..(data.offset + 8 + data.length) as usize];
+    let reference: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+
+    // Take the audio frames after the Xing/Info frame of the encoder.
+    const BITRATES: [usize; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    let mut audio_frames = Vec::<&[u8]>::new();
+    let mut pos = 0;
+    while pos + 4 <= mp3_data.len() {
+        assert_eq!(&mp3_data[pos..pos + 2], &[0xFF, 0xFB]);
+        let size = 144 * BITRATES[(mp3_data[pos + 2] >> 4) as usize] * 1000 / 44100 + ((mp3_data[pos + 2] >> 1) & 1) as usize;
+        audio_frames.push(&mp3_data[pos..pos + size]);
+        pos += size;
+    }
+    audio_frames.remove(0);
+    let num_frames = audio_frames.len();
+    let audio_size: usize = audio_frames.iter().map(|frame| frame.len()).sum();
+
+    // The 320 kbps frame holds the VBR header, without the LAME tag.
+    let header_frame = |vbri: bool| -> Vec<u8> {
+        let mut frame = vec![0u8; 1044];
+        frame[0..4].copy_from_slice(&[0xFF, 0xFB, 0xE0, audio_frames[0][3]]);
+        let total_size = (frame.len() + audio_size) as u64;
+        if !vbri {
+            frame[36..40].copy_from_slice(b"Xing");
+            frame[40..44].copy_from_slice(&7u32.to_be_bytes());
+            frame[44..48].copy_from_slice(&(num_frames as u32).to_be_bytes());
+            frame[48..52].copy_from_slice(&(total_size as u32).to_be_bytes());
+            for percent in 0..100 {
+                let frame_start = 1044 + audio_frames[..percent * num_frames / 100].iter().map(|frame| frame.len()).sum::<usize>();
+                frame[52 + percent] = (frame_start as u64 * 256 / total_size) as u8;
+            }
+        } else {
+            let frames_per_entry = 10;
+            let toc_entries = num_frames / frames_per_entry;
+            frame[36..40].copy_from_slice(b"VBRI");
+            frame[40..42].copy_from_slice(&1u16.to_be_bytes());
+            frame[46..50].copy_from_slice(&(total_size as u32).to_be_bytes());
+            frame[50..54].copy_from_slice(&(num_frames as u32).to_be_bytes());
+            frame[54..56].copy_from_slice(&(toc_entries as u16).to_be_bytes());
+            frame[56..58].copy_from_slice(&1u16.to_be_bytes());
+            frame[58..60].copy_from_slice(&2u16.to_be_bytes());
+            frame[60..62].copy_from_slice(&(frames_per_entry as u16).to_be_bytes());
+            for (i, entry) in audio_frames.chunks(frames_per_entry).take(toc_entries).enumerate() {
+                let size = entry.iter().map(|frame| frame.len()).sum::<usize>() as u16;
+                frame[62 + i * 2..64 + i * 2].copy_from_slice(&size.to_be_bytes());
+            }
+        }
+        frame
+    };
+
+    for vbri in [false, true] {
+        // No `fact` chunk, the length is from the VBR header.
+        let mut payload = header_frame(vbri);
+        audio_frames.iter().for_each(|frame| payload.extend_from_slice(frame));
+        let mut riff = Vec::<u8>::new();
+        riff.extend_from_slice(b"RIFF");
+        riff.extend_from_slice(&((4 + fmt_bytes.len() + 8 + payload.len() + (payload.len() & 1)) as u32).to_le_bytes());
+        riff.extend_from_slice(b"WAVE");
+        riff.extend_from_slice(&fmt_bytes);
+        riff.extend_from_slice(b"data");
+        riff.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        let data_offset = riff.len() as u64;
+        riff.extend_from_slice(&payload);
+        if payload.len() & 1 != 0 {
+            riff.push(0);
+        }
+
+        // The header frame isn't decoded as a frame of silence. Without the LAME tag, the encoder delay is in the audio.
+        let total_samples = (num_frames * 1152) as u64;
+        let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(riff.clone())))).unwrap();
+        assert_eq!(wavereader.total_frames(), Some(total_samples), "VBRI: {vbri}");
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len() as u64, total_samples, "VBRI: {vbri}");
+        const DELAY: usize = 576 + 529;
+        let compared = reference.len().min(decoded.len() - DELAY);
+        assert_eq!(decoded[DELAY..DELAY + compared], reference[..compared], "VBRI: {vbri}");
+
+        // The approximate seek skips to the seek point before the target, the frames are right after the bit reservoir refills.
+        let mut decoder = Mp3Decoder::new(
+            Box::new(CursorVecU8::new(riff)),
+            data_offset,
+            payload.len() as u64,
+            wavereader.get_fmt__chunk(),
+            0,
+            DecodeErrorPolicy::Abort,
+        ).unwrap();
+        let target = (num_frames / 2 + 4) * 1152 + 100;
+        decoder.seek_approximate(SeekFrom::Start(target as u64)).unwrap();
+        assert_eq!(decoder.get_cur_frame_index(), target as u64, "VBRI: {vbri}");
+        let mut error_energy = 0.0f64;
+        let mut signal_energy = 0.0f64;
+        for expected in decoded[target..target + 4000].iter() {
+            let frame = decoder.decode_frame::<i16>().unwrap().unwrap();
+            for (a, b) in frame.iter().zip(expected.iter()) {
+                error_energy += (*a as f64 - *b as f64).powi(2);
+                signal_energy += (*b as f64).powi(2);
+            }
+        }
+        assert!(error_energy <= signal_energy * 1e-4, "VBRI: {vbri}: the error energy is {error_energy} of {signal_energy}");
+    }
+}
+
+#[test]
+pub fn test_write_interleaved_samples() {
+    use errors::AudioWriteError;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let num_frames = 5000usize;
+    let samples: Vec<f32> = (0..num_frames * 2).map(|i| ((i as f32) * 0.01).sin() * 0.5).collect();
+    let mut data_formats = vec![
+        DataFormat::Pcm,
+        DataFormat::Adpcm(AdpcmSubFormat::Ima),
+    ];
+    if cfg!(feature = "mp3enc") {
+        data_formats.push(DataFormat::Mp3(Mp3EncoderOptions::new_stereo()));
+    }
+    for (i, data_format) in data_formats.into_iter().enumerate() {
+        let path = dir.path().join(format!("interleaved_{i}.wav"));
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        assert_eq!(wavewriter.get_num_frames_written(), num_frames as u64);
+
+        // A half frame is rejected without touching the bookkeeping.
+        match wavewriter.write_interleaved_samples(&samples[..3]).unwrap_err() {
+            AudioWriteError::TruncatedSamples => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+        assert_eq!(wavewriter.get_num_frames_written(), num_frames as u64);
+        wavewriter.finalize().unwrap();
+
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let decoded: Vec<(f32, f32)> = wavereader.stereo_iter::<f32>().unwrap().collect();
+        assert!(decoded.len() >= num_frames);
+    }
+}
+
+#[test]
+pub fn test_passthrough_remux() {
+    use std::io::Read;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let read_raw = |wavereader: &WaveReader| -> Vec<u8> {
+        let mut raw = Vec::new();
+        wavereader.open_raw_data().unwrap().take(wavereader.get_data_length()).read_to_end(&mut raw).unwrap();
+        raw
+    };
+    let stereos: Vec<(f32, f32)> = (0..20000).map(|i| (((i as f32) * 0.01).sin() * 0.5, ((i as f32) * 0.02).cos() * 0.5)).collect();
+    let mut data_formats = vec![DataFormat::Pcm, DataFormat::Adpcm(AdpcmSubFormat::Ima)];
+    if cfg!(feature = "mp3enc") {
+        data_formats.push(DataFormat::Mp3(Mp3EncoderOptions::new_stereo()));
+    }
+    for (i, data_format) in data_formats.into_iter().enumerate() {
+        let src_path = dir.path().join(format!("remux_src_{i}.wav"));
+        let mut wavewriter = WaveWriter::create(&src_path, spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB).unwrap();
+        let mut info = chunks::ListChunk::Info(Default::default());
+        chunks::ListInfo::set_name(&mut info, "Remuxed").unwrap();
+        wavewriter.set_list_chunk(info);
+        wavewriter.write_stereos(&stereos).unwrap();
+        wavewriter.finalize().unwrap();
+        let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+        let src_raw = read_raw(&wavereader);
+
+        // The 4GB options still work on the remuxed file.
+        for (j, file_size_option) in [FileSizeOption::NeverLargerThan4GB, FileSizeOption::ForceUse4GBFormat].into_iter().enumerate() {
+            let dst_path = dir.path().join(format!("remux_dst_{i}_{j}.wav"));
+            let mut wavewriter = WaveWriter::create(&dst_path, wavereader.spec(), DataFormat::PassThrough, file_size_option).unwrap();
+            wavewriter.copy_data_from_reader(&mut wavereader).unwrap();
+            match wavewriter.copy_data_from_reader(&mut wavereader).unwrap_err() {
+                errors::AudioWriteError::AlreadyFinished(_) => (),
+                other => panic!("Unexpected error: {other}"),
+            }
+            assert!(wavewriter.write_stereos(&stereos[..10]).is_err(), "The pass-through format can't encode");
+            let report = wavewriter.finalize().unwrap();
+            assert_eq!(report.container == ContainerKind::Rf64, j == 1);
+
+            let mut remuxed = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+            assert!(report.matches_chunk_map(remuxed.chunk_map()), "{:?} vs {:?}", report.chunks, remuxed.chunk_map());
+            assert_eq!(read_raw(&remuxed), src_raw, "{data_format}: the payload must be bit-identical");
+            assert_eq!(remuxed.get_fmt__chunk().to_bytes().unwrap(), wavereader.get_fmt__chunk().to_bytes().unwrap());
+            assert_eq!(remuxed.total_frames(), wavereader.total_frames());
+            assert_eq!(remuxed.get_metadata_text("INAM").as_deref(), Some("Remuxed"));
+            let expected: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+            let decoded: Vec<(i16, i16)> = remuxed.stereo_iter::<i16>().unwrap().collect();
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    // Fix the channel mask of the PCM file, the `fmt ` chunk becomes extensible.
+    let mut wavereader = WaveReader::open(dir.path().join("remux_src_0.wav").to_str().unwrap()).unwrap();
+    let dst_path = dir.path().join("remux_mask.wav");
+    let mask_spec = Spec {
+        channel_mask: Some(0x3),
+        ..spec
+    };
+    let remuxed = write_and_reopen(&dst_path, mask_spec, DataFormat::PassThrough, |wavewriter| wavewriter.copy_data_from_reader(&mut wavereader).unwrap());
+    assert_eq!(remuxed.get_fmt__chunk().format_tag, 0xFFFE);
+    assert_eq!(remuxed.spec().channel_mask, Some(0x3));
+    assert_eq!(read_raw(&remuxed), read_raw(&wavereader));
+
+    // Only the pass-through format copies, and the channels can't be changed.
+    let mut wavewriter = WaveWriter::create(dir.path().join("remux_bad.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    match wavewriter.copy_data_from_reader(&mut wavereader).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    let mono_spec = Spec {
+        channels: 1,
+        ..spec
+    };
+    let mut wavewriter = WaveWriter::create(dir.path().join("remux_bad_2.wav"), mono_spec, DataFormat::PassThrough, FileSizeOption::NeverLargerThan4GB).unwrap();
+    match wavewriter.copy_data_from_reader(&mut wavereader).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert!(wavewriter.finalize().is_err(), "Nothing to finalize");
+}
+
+#[test]
+pub fn test_write_frames_at() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let region = |start: usize, length: usize| -> Vec<Vec<i16>> {
+        (start..start + length).map(|i| vec![(i * 7) as i16, -((i * 3) as i16)]).collect()
+    };
+    let silence = |length: usize| -> Vec<Vec<i16>> { vec![vec![0i16; 2]; length] };
+
+    for (i, data_format) in [DataFormat::Pcm, DataFormat::PcmMuLaw].into_iter().enumerate() {
+        // The reference is written in order, with the silent gap written explicitly.
+        let reference_path = dir.path().join(format!("in_order_{i}.wav"));
+        let mut wavewriter = WaveWriter::create(reference_path.to_str().unwrap(), spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_frames(&region(0, 1000)).unwrap();
+        wavewriter.write_frames(&silence(500)).unwrap();
+        wavewriter.write_frames(&region(1500, 1000)).unwrap();
+        wavewriter.write_frames(&region(2500, 1000)).unwrap();
+        wavewriter.finalize().unwrap();
+
+        // Render the regions out of order, the gap between 1000 and 1500 is never written.
+        let sparse_path = dir.path().join(format!("out_of_order_{i}.wav"));
+        let mut wavewriter = WaveWriter::create(sparse_path.to_str().unwrap(), spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_frames_at(2500, &region(2500, 1000)).unwrap();
+        wavewriter.write_frames_at(0, &region(0, 1000)).unwrap();
+        wavewriter.write_frames_at(1500, &region(1500, 1000)).unwrap();
+        assert_eq!(wavewriter.get_num_frames_written(), 3500);
+        wavewriter.finalize().unwrap();
+
+        let mut wavereader = WaveReader::open(reference_path.to_str().unwrap()).unwrap();
+        let reference: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        let mut wavereader = WaveReader::open(sparse_path.to_str().unwrap()).unwrap();
+        let sparse: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(reference.len(), 3500);
+        assert_eq!(reference, sparse);
+    }
+
+    // The compressed formats can't be written at a position.
+    let path = dir.path().join("adpcm.wav");
+    let mut wavewriter = WaveWriter::create(path.to_str().unwrap(), spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), FileSizeOption::NeverLargerThan4GB).unwrap();
+    match wavewriter.write_frames_at(100, &region(0, 10)).unwrap_err() {
+        AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert_eq!(wavewriter.get_num_frames_written(), 0);
+}
+
+#[test]
+pub fn test_seek_to_frame() {
+    let (dir, path) = temp_wav("seek.wav");
+    let spec = test_spec(2, 44100);
+    let num_frames = 10000u64;
+    let frames: Vec<Vec<i16>> = (0..num_frames).map(|i| vec![i as i16, -(i as i16)]).collect();
+    let mut wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+    let full: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+    assert_eq!(full.len() as u64, num_frames);
+
+    // Seek to the middle, every iterator resumes from there.
+    assert_eq!(wavereader.seek_to_frame(4321).unwrap(), 4321);
+    let tail: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+    assert_eq!(tail, full[4321..]);
+    let tail: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().take(100).collect();
+    assert_eq!(tail, full[4321..4421].iter().map(|f| (f[0], f[1])).collect::<Vec<_>>());
+    assert_eq!(wavereader.mono_iter::<i16>().unwrap().count() as u64, num_frames - 4321);
+
+    // Seeking beyond the end lands on the end.
+    assert_eq!(wavereader.seek_to_frame(num_frames + 100).unwrap(), num_frames);
+    assert_eq!(wavereader.frame_iter::<i16>().unwrap().count(), 0);
+
+    // The ADPCM decoder seeks to the block and decodes up to the frame.
+    let adpcm_path = dir.path().join("seek_adpcm.wav");
+    let mut wavereader = write_and_reopen(&adpcm_path, spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), |wavewriter| wavewriter.write_frames(&frames).unwrap());
+    let full: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+    let landed = wavereader.seek_to_frame(4321).unwrap() as usize;
+    let tail: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().take(100).collect();
+    assert_eq!(tail, full[landed..landed + 100]);
+}
+
+#[test]
+pub fn test_iterator_skip_frames() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let num_frames = 20000usize;
+    let frames: Vec<Vec<i16>> = (0..num_frames).map(|i| {
+        let t = i as f64 / 44100.0;
+        let l = (t * 440.0 * std::f64::consts::TAU).sin() * 10000.0;
+        let r = (t * 550.0 * std::f64::consts::TAU).sin() * 8000.0;
+        vec![l as i16, r as i16]
+    }).collect();
+    for data_format in [DataFormat::Pcm, DataFormat::Adpcm(AdpcmSubFormat::Ms), DataFormat::Adpcm(AdpcmSubFormat::Ima)] {
+        let path = dir.path().join(format!("skip_frames_{data_format}.wav"));
+        let path = path.to_str().unwrap();
+        let mut wavereader = write_and_reopen(path, spec, data_format, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        let full: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+
+        // Skip into the middle of a block, then across several blocks, every frame after the skips is the same as decoding all of them.
+        let mut iter = wavereader.frame_iter::<i16>().unwrap();
+        iter.skip_frames(1001).unwrap();
+        assert_eq!(iter.next().unwrap(), full[1001], "{data_format}");
+        iter.skip_frames(7777).unwrap();
+        assert_eq!(iter.size_hint().0, full.len() - 9779);
+        let tail: Vec<Vec<i16>> = iter.collect();
+        assert_eq!(tail, full[9779..], "{data_format}");
+
+        let mut iter = wavereader.stereo_iter::<i16>().unwrap();
+        assert_eq!(iter.nth(12345).unwrap(), (full[12345][0], full[12345][1]), "{data_format}");
+        iter.skip_frames(num_frames as u64).unwrap();
+        assert!(iter.next().is_none());
+
+        let mut iter = wavereader.mono_iter::<i16>().unwrap();
+        iter.skip_frames(full.len() as u64 - 10).unwrap();
+        assert_eq!(iter.count(), 10, "{data_format}");
+
+        // The streaming reader can only skip forward, the skipped bytes are read and dropped.
+        let mut wavereader = WaveReader::from_reader(std::io::Cursor::new(std::fs::read(path).unwrap())).unwrap();
+        let mut iter = wavereader.frame_iter::<i16>().unwrap();
+        iter.skip_frames(5555).unwrap();
+        let tail: Vec<Vec<i16>> = iter.take(100).collect();
+        assert_eq!(tail, full[5555..5655], "{data_format}");
+    }
+}
+
+#[test]
+pub fn test_write_to_memory() {
+    use io_utils::CursorVecU8;
+    let spec = test_spec(2, 44100);
+    let frames: Vec<Vec<i16>> = (0..3000).map(|i| vec![i as i16, -(i as i16)]).collect();
+    for (file_size_option, flag) in [(FileSizeOption::NeverLargerThan4GB, b"RIFF"), (FileSizeOption::ForceUse4GBFormat, b"RF64")] {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, file_size_option).unwrap();
+        wavewriter.write_frames(&frames).unwrap();
+        wavewriter.into_inner().unwrap();
+
+        let bytes = cursor.into_inner();
+        assert_eq!(&bytes[0..4], flag);
+        let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, frames);
+    }
+}
+
+#[test]
+pub fn test_channel_mask_states() {
+    let dir = tempfile::tempdir().unwrap();
+    let frames: Vec<Vec<i16>> = (0..1000).map(|i| vec![i as i16, -(i as i16)]).collect();
+
+    // No mask writes the plain `fmt ` chunk, a mask writes the extensible one, even if the mask is zero.
+    for (i, channel_mask) in [None, Some(0), Some(3)].into_iter().enumerate() {
+        let path = dir.path().join(format!("mask_{i}.wav"));
+        let spec = Spec {
+            channel_mask,
+            ..test_spec(2, 44100)
+        };
+        let mut wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        assert_eq!(wavereader.spec().channel_mask, channel_mask);
+        match channel_mask {
+            None => assert_eq!(wavereader.get_fmt__chunk().format_tag, format_tags::FORMAT_TAG_PCM),
+            Some(_) => assert_eq!(wavereader.get_fmt__chunk().format_tag, format_tags::FORMAT_TAG_EXTENSIBLE),
+        }
+
+        // Without a mask, the speaker positions are guessed from the number of channels.
+        assert_eq!(wavereader.spec().effective_channel_mask(), channel_mask.unwrap_or(3));
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, frames);
+    }
+
+    // The mask that doesn't match the channels is rejected, no mask is always valid.
+    let spec = Spec {
+        channel_mask: Some(7),
+        ..test_spec(2, 44100)
+    };
+    assert!(!spec.is_channel_mask_valid());
+    assert!(Spec {channel_mask: None, ..spec}.is_channel_mask_valid());
+    assert!(WaveWriter::create(dir.path().join("bad_mask.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).is_err());
+}
+
+#[test]
+pub fn test_adpcm_ima_bits() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 8000);
+    let num_frames = 10000usize;
+    let frames: Vec<Vec<i16>> = (0..num_frames)
+        .map(|i| {
+            let t = i as f32 / 8000.0;
+            vec![((t * 440.0 * std::f32::consts::TAU).sin() * 12000.0) as i16, ((t * 300.0 * std::f32::consts::TAU).sin() * 8000.0) as i16]
+        })
+        .collect();
+    let mut errors = Vec::<f64>::new();
+    for (sub_format, bits) in [(AdpcmSubFormat::Ima2, 2), (AdpcmSubFormat::Ima3, 3), (AdpcmSubFormat::Ima, 4), (AdpcmSubFormat::Ima5, 5)] {
+        let path = dir.path().join(format!("ima{bits}.wav"));
+        let mut wavereader = write_and_reopen(&path, spec, DataFormat::Adpcm(sub_format), |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        let fmt = wavereader.get_fmt__chunk().clone();
+        assert_eq!(fmt.format_tag, format_tags::FORMAT_TAG_ADPCM_IMA);
+        assert_eq!(fmt.bits_per_sample, bits);
+        let samples_per_block = match fmt.extension.as_ref().map(|extension| &extension.data) {
+            Some(chunks::ext::ExtensionData::AdpcmIma(adpcm_ima)) => adpcm_ima.samples_per_block as usize,
+            other => panic!("Unexpected extension: {other:?}"),
+        };
+        // The header sample, plus the code words after the headers.
+        assert_eq!(samples_per_block, 1 + (fmt.block_align as usize - 4 * 2) * 8 / (bits as usize * 2));
+
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames);
+        let error = decoded.iter().zip(frames.iter())
+            .map(|(a, b)| a.iter().zip(b.iter()).map(|(a, b)| (*a as f64 - *b as f64).powi(2)).sum::<f64>())
+            .sum::<f64>() / num_frames as f64;
+        errors.push(error);
+
+        // Seeking to a frame in the middle decodes the same samples.
+        let landed = wavereader.seek_to_frame(5000).unwrap() as usize;
+        let tail: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().take(100).collect();
+        assert_eq!(tail, decoded[landed..landed + 100]);
+    }
+
+    // More bits, less error.
+    assert!(errors.windows(2).all(|pair| pair[0] > pair[1]), "{errors:?}");
+}
 
 /// * A function dedicated to testing WAV encoding and decoding. This function is actually a `main()` function for a command-line program that parses `args` and returns an `ExitCode`.
 /// * The usage is `arg0 [format] [test.wav] [output.wav]`
@@ -296,3 +1257,4412 @@ pub fn test_wav() -> ExitCode {
         }
     }
 }
+
+#[test]
+pub fn test_decode_error_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+    let stereos: Vec<(i16, i16)> = (0..96000).map(|i| {
+        let s = ((i as f64 * 0.05).sin() * 10000.0) as i16;
+        (s, s / 2)
+    }).collect();
+
+    // The corrupted bytes: `0xFF` makes an invalid Opus packet and breaks the Ogg page CRC, `0x00` can't be synced as an MP3 frame.
+    let mut fixtures = Vec::<(&str, u8, usize)>::new();
+    if cfg!(feature = "opus") {
+        fixtures.push(("opus", 0xFF, 1));
+    }
+    if cfg!(all(feature = "mp3enc", feature = "mp3dec")) {
+        fixtures.push(("mp3", 0x00, 2000));
+    }
+    if cfg!(feature = "oggvorbis") {
+        fixtures.push(("oggvorbis1", 0xFF, 64));
+    }
+    for (name, corrupt_byte, corrupt_length) in fixtures {
+        let mut data_format = DataFormat::from_name(name).unwrap();
+        if let DataFormat::OggVorbis(ref mut options) = data_format {
+            options.channels = spec.channels;
+            options.sample_rate = spec.sample_rate;
+        }
+        let path = dir.path().join(format!("{name}_corrupted.wav"));
+        let path = path.to_str().unwrap();
+        let mut wavereader = write_and_reopen(path, spec, data_format, |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+        let block_align = wavereader.get_fmt__chunk().block_align as usize;
+        let clean_frames = wavereader.stereo_iter::<i16>().unwrap().decode_stereos(stereos.len() * 2).unwrap().len();
+
+        // Corrupt one packet in the middle of the `data` chunk
+        let mut bytes = std::fs::read(path).unwrap();
+        let mut data_offset = 12usize;
+        while &bytes[data_offset..data_offset + 4] != b"data" {
+            let chunk_size = u32::from_le_bytes(bytes[data_offset + 4..data_offset + 8].try_into().unwrap()) as usize;
+            data_offset += 8 + chunk_size + (chunk_size & 1);
+        }
+        let data_length = u32::from_le_bytes(bytes[data_offset + 4..data_offset + 8].try_into().unwrap()) as usize;
+        data_offset += 8;
+        let (corrupt_start, corrupt_length) = if name == "opus" {
+            let packet_index = data_length / block_align / 2;
+            (data_offset + packet_index * block_align, block_align * corrupt_length)
+        } else {
+            (data_offset + data_length / 2, corrupt_length)
+        };
+        bytes[corrupt_start..corrupt_start + corrupt_length].fill(corrupt_byte);
+        std::fs::write(path, &bytes).unwrap();
+
+        let decode_with = |on_decode_error: DecodeErrorPolicy| -> (Result<usize, errors::AudioReadError>, Vec<errors::DecodeWarning>) {
+            let read_options = ReadOptions {
+                on_decode_error,
+                ..ReadOptions::default()
+            };
+            let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+            let mut iter = wavereader.stereo_iter::<i16>().unwrap();
+            let result = iter.decode_stereos(stereos.len() * 2).map(|frames| frames.len());
+            (result, iter.get_warnings().to_vec())
+        };
+
+        // Abort: the codec error escapes, the MP3 decoder resyncs silently.
+        let (result, warnings) = decode_with(DecodeErrorPolicy::Abort);
+        assert!(warnings.is_empty(), "{name}: {warnings:?}");
+        if name == "mp3" {
+            assert!(result.unwrap() < clean_frames, "{name}");
+        } else {
+            assert!(result.is_err(), "{name}");
+        }
+
+        // TruncateStream: ends at the last good frame.
+        let (result, warnings) = decode_with(DecodeErrorPolicy::TruncateStream);
+        let truncated_frames = result.unwrap();
+        assert!(truncated_frames > 0 && truncated_frames < clean_frames, "{name}: {truncated_frames} of {clean_frames} frames");
+        assert_eq!(warnings.len(), 1, "{name}: {warnings:?}");
+        assert!(warnings[0].message.starts_with("Truncated"), "{name}: {}", warnings[0]);
+
+        // SkipPacket: the bad packet becomes silence and the decoding goes on.
+        let (result, warnings) = decode_with(DecodeErrorPolicy::SkipPacket);
+        let skipped_frames = result.unwrap();
+        assert!(skipped_frames > truncated_frames, "{name}: {skipped_frames} vs {truncated_frames} frames");
+        assert!(!warnings.is_empty(), "{name}");
+        assert!(warnings.iter().all(|w| w.message.starts_with("Skipped")), "{name}: {warnings:?}");
+        match name {
+            "opus" => {
+                // The silence keeps the timing, each packet has a fixed size.
+                assert_eq!(skipped_frames, clean_frames);
+                assert_eq!(warnings.len(), 1);
+                assert_eq!(warnings[0].offset, corrupt_start as u64);
+            }
+            "mp3" => {
+                assert_eq!(warnings.len(), 1);
+                assert!((warnings[0].offset as usize) < corrupt_start + corrupt_length, "{}", warnings[0]);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[test]
+pub fn test_transfer_audio_with_gain() {
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("gain_src.wav");
+    let dst_path = dir.path().join("gain_dst.wav");
+    let spec = test_spec(2, 44100);
+    let stereos: Vec<(i16, i16)> = (0..10000).map(|i| (if i % 2 == 0 { 1000 } else { -1000 }, 20000)).collect();
+    let mut wavereader = write_and_reopen(&src_path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+    let mut wavewriter = WaveWriter::create(&dst_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+
+    // The number of gains must match the number of channels
+    match transfer_audio_with_gain(&mut wavereader, &mut wavewriter, &[0.5]).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // The right channel is boosted too much and gets clipped
+    transfer_audio_with_gain(&mut wavereader, &mut wavewriter, &[0.5, 2.0]).unwrap();
+    wavewriter.finalize().unwrap();
+
+    let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded.len(), stereos.len());
+    for (i, (l, r)) in decoded.into_iter().enumerate() {
+        let expected_l = if i % 2 == 0 { 500 } else { -500 };
+        assert!((l as i32 - expected_l).abs() <= 1, "frame {i}: {l}");
+        assert_eq!(r, i16::MAX, "frame {i}");
+    }
+}
+
+#[test]
+pub fn test_transfer_audio_normalized() {
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("normalize_src.wav");
+    let spec = test_spec(2, 44100);
+
+    // The sine peaks at -12 dBFS on the left channel and -18 dBFS on the right channel.
+    let stereos: Vec<(f32, f32)> = (0..44100).map(|i| {
+        let sample = (i as f64 / 100.0 * std::f64::consts::TAU).sin();
+        ((sample * 10f64.powf(-12.0 / 20.0)) as f32, (sample * 10f64.powf(-18.0 / 20.0)) as f32)
+    }).collect();
+    let mut wavewriter = WaveWriter::create(&src_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+
+    for target in [-1.0f32, -6.0, 0.0] {
+        let dst_path = dir.path().join(format!("normalize_dst_{target}.wav"));
+        let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+        let mut wavewriter = WaveWriter::create(&dst_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        let gain = transfer_audio_normalized(&mut wavereader, &mut wavewriter, target).unwrap();
+        wavewriter.finalize().unwrap();
+        assert!((gain - (target + 12.0)).abs() < 0.1, "{gain}");
+
+        let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+        let stats = wavereader.scan_levels().unwrap();
+        assert_eq!(stats.num_frames, stereos.len() as u64);
+        assert!((stats.peak_dbfs[0] - target as f64).abs() < 0.1, "{:?}", stats.peak_dbfs);
+        assert!((stats.peak_dbfs[1] - (target as f64 - 6.0)).abs() < 0.1, "{:?}", stats.peak_dbfs);
+
+        // The samples near the full scale don't wrap around.
+        let source: Vec<(i16, i16)> = WaveReader::open(src_path.to_str().unwrap()).unwrap().stereo_iter::<i16>().unwrap().collect();
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        for (i, ((sl, sr), (dl, dr))) in source.into_iter().zip(decoded).enumerate() {
+            assert!((sl as i32 * dl as i32) >= 0 && (sr as i32 * dr as i32) >= 0, "frame {i}: ({sl}, {sr}) -> ({dl}, {dr})");
+        }
+    }
+
+    let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    let mut wavewriter = WaveWriter::create(dir.path().join("normalize_bad.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    match transfer_audio_normalized(&mut wavereader, &mut wavewriter, 1.0).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // The stream can't be scanned and transferred both.
+    let mut wavereader = WaveReader::from_reader(std::io::Cursor::new(std::fs::read(&src_path).unwrap())).unwrap();
+    assert!(wavereader.is_streaming());
+    match transfer_audio_normalized(&mut wavereader, &mut wavewriter, -1.0).unwrap_err() {
+        errors::AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_recommend_format() {
+    use std::f32::consts::PI;
+    let dir = tempfile::tempdir().unwrap();
+    let sample_rate = 44100u32;
+
+    // The narrowband "voice": syllables of harmonics below 3 kHz with pauses between them.
+    let voice_path = dir.path().join("voice.wav");
+    let voice_spec = test_spec(1, sample_rate);
+    let voice: Vec<f32> = (0..sample_rate * 5).map(|i| {
+        let t = i as f32 / sample_rate as f32;
+        let syllable = (t / 0.35) as u32;
+        if t % 0.35 >= 0.2 {
+            return 0.0;
+        }
+        let f0 = 120.0 + (syllable % 5) as f32 * 25.0;
+        (1..=3000 / f0 as u32).map(|k| (2.0 * PI * f0 * k as f32 * t).sin() * 0.3 / k as f32).sum()
+    }).collect();
+    let mut wavewriter = WaveWriter::create(&voice_path, voice_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&voice).unwrap();
+    wavewriter.finalize().unwrap();
+
+    // The full-band "music": sustained harmonics up to 16 kHz with some noise.
+    let music_path = dir.path().join("music.wav");
+    let music_spec = Spec {
+        bits_per_sample: 24,
+        ..test_spec(2, sample_rate)
+    };
+    let mut seed = 0x12345678u32;
+    let music: Vec<(f32, f32)> = (0..sample_rate * 5).map(|i| {
+        let t = i as f32 / sample_rate as f32;
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+        let tone: f32 = (1..=72).map(|k| (2.0 * PI * 220.0 * k as f32 * t).sin() * 0.01).sum();
+        (tone + noise * 0.1, tone - noise * 0.1)
+    }).collect();
+    let mut wavewriter = WaveWriter::create(&music_path, music_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&music).unwrap();
+    wavewriter.finalize().unwrap();
+
+    let mut voice_reader = WaveReader::open(voice_path.to_str().unwrap()).unwrap();
+    let mut music_reader = WaveReader::open(music_path.to_str().unwrap()).unwrap();
+    let voice_analysis = analyze_source(&mut voice_reader).unwrap();
+    let music_analysis = analyze_source(&mut music_reader).unwrap();
+    assert!(voice_analysis.speech_like, "{voice_analysis:?}");
+    assert!(!music_analysis.speech_like, "{music_analysis:?}");
+    assert!(voice_analysis.bandwidth < 8000.0, "{voice_analysis:?}");
+    assert!(music_analysis.bandwidth > 12000.0, "{music_analysis:?}");
+    assert!(voice_analysis.silence_ratio > music_analysis.silence_ratio);
+
+    let opus_bitrate = |recommendation: &Recommendation| match recommendation.format {
+        DataFormat::Opus(OpusEncoderOptions { bitrate: OpusBitrate::Bits(bitrate), .. }) => bitrate,
+        ref other => panic!("Expected Opus, got {other:?}"),
+    };
+
+    // Streaming: the speech gets a lower bitrate and a lower sample rate.
+    let voice_streaming = recommend_format(&mut voice_reader, UseCase::Streaming).unwrap();
+    let music_streaming = recommend_format(&mut music_reader, UseCase::Streaming).unwrap();
+    assert!(opus_bitrate(&voice_streaming) < opus_bitrate(&music_streaming));
+    assert!(voice_streaming.spec.sample_rate < music_streaming.spec.sample_rate);
+    assert_eq!(voice_streaming.spec.channels, 1);
+    assert_eq!(music_streaming.spec.channels, 2);
+    assert!(voice_streaming.rationale.iter().any(|r| r.contains("speech")), "{:?}", voice_streaming.rationale);
+
+    // Streaming: multistream Opus up to 8 channels, OggVorbis beyond them.
+    for channels in [6u16, 10] {
+        let surround_path = dir.path().join(format!("surround_{channels}.wav"));
+        let surround_spec = test_spec(channels, sample_rate);
+        let frames: Vec<Vec<f32>> = music.iter().map(|(l, r)| (0..channels).map(|c| if c % 2 == 0 { *l } else { *r }).collect()).collect();
+        let mut surround_reader = write_and_reopen(&surround_path, surround_spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        let surround_streaming = recommend_format(&mut surround_reader, UseCase::Streaming).unwrap();
+        assert_eq!(surround_streaming.spec.channels, channels);
+        match surround_streaming.format {
+            DataFormat::Opus(_) => assert_eq!(channels, 6, "{:?}", surround_streaming.rationale),
+            DataFormat::OggVorbis(params) => assert_eq!((channels, params.channels), (10, 10), "{:?}", surround_streaming.rationale),
+            other => panic!("Expected Opus or OggVorbis, got {other:?}"),
+        }
+    }
+
+    // Voice: the music is downmixed to mono too.
+    let music_voice = recommend_format(&mut music_reader, UseCase::Voice).unwrap();
+    assert_eq!(opus_bitrate(&music_voice), 24000);
+    assert_eq!(music_voice.spec.channels, 1);
+
+    // Archival: FLAC level 8 keeping the source depth.
+    let music_archival = recommend_format(&mut music_reader, UseCase::Archival).unwrap();
+    match music_archival.format {
+        DataFormat::Flac(params) => {
+            assert_eq!(params.compression, FlacCompression::Level8);
+            assert_eq!(params.bits_per_sample, 24);
+            assert_eq!(params.channels, 2);
+        }
+        other => panic!("Expected FLAC, got {other:?}"),
+    }
+    assert_eq!(music_archival.spec.channels, music_spec.channels);
+    assert_eq!(music_archival.spec.sample_rate, music_spec.sample_rate);
+    assert_eq!(music_archival.spec.bits_per_sample, 24);
+
+    // Preview: the speech gets a lower MP3 bitrate.
+    let voice_preview = recommend_format(&mut voice_reader, UseCase::Preview).unwrap();
+    let music_preview = recommend_format(&mut music_reader, UseCase::Preview).unwrap();
+    match (voice_preview.format, music_preview.format) {
+        (DataFormat::Mp3(voice_options), DataFormat::Mp3(music_options)) => {
+            assert!((voice_options.bitrate as u32) < (music_options.bitrate as u32));
+            assert_eq!(voice_options.channels, Mp3Channels::Mono);
+        }
+        other => panic!("Expected MP3, got {other:?}"),
+    }
+
+    // The reader is still usable after the analysis
+    assert_eq!(voice_reader.mono_iter::<f32>().unwrap().count(), voice.len());
+}
+
+#[test]
+pub fn test_transfer_channel_conversion() {
+    use downmixer::speaker_positions::DOLBY_5_1_FRONT_SIDE_LAYOUT;
+    let dir = tempfile::tempdir().unwrap();
+    let spec_of = |channels: u16, channel_mask: Option<u32>| Spec {
+        channel_mask,
+        ..test_spec(channels, 44100)
+    };
+    let num_frames = 4410usize;
+    let wave = |i: usize| ((i as f32 * 0.1).sin() * 0.5);
+    let rms = |samples: &[f32]| (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+
+    // Write the source with `frames`, transfer it to an encoder of `channels`, then read back the frames.
+    let transfer = |name: &str, source_spec: Spec, frames: Vec<Vec<f32>>, channels: u16| -> Vec<Vec<f32>> {
+        let src_path = dir.path().join(format!("{name}_src.wav"));
+        let dst_path = dir.path().join(format!("{name}_dst.wav"));
+        let mut wavereader = write_and_reopen(&src_path, source_spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        let mut wavewriter = WaveWriter::create(&dst_path, spec_of(channels, None), DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        transfer_audio_from_decoder_to_encoder(&mut wavereader, &mut wavewriter);
+        wavewriter.finalize().unwrap();
+        let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+        assert_eq!(wavereader.spec().channels, channels);
+        wavereader.frame_iter::<f32>().unwrap().collect()
+    };
+
+    // 2 -> 1: averaging, the opposite channels cancel each other.
+    let decoded = transfer("2to1", spec_of(2, None), (0..num_frames).map(|i| vec![wave(i), -wave(i)]).collect(), 1);
+    assert_eq!(decoded.len(), num_frames);
+    assert!(rms(&decoded.iter().map(|f| f[0]).collect::<Vec<f32>>()) < 0.001);
+    let decoded = transfer("2to1_same", spec_of(2, None), (0..num_frames).map(|i| vec![wave(i), wave(i)]).collect(), 1);
+    assert!((rms(&decoded.iter().map(|f| f[0]).collect::<Vec<f32>>()) - rms(&(0..num_frames).map(wave).collect::<Vec<f32>>())).abs() < 0.001);
+
+    // 1 -> 2: duplication
+    let decoded = transfer("1to2", spec_of(1, None), (0..num_frames).map(|i| vec![wave(i)]).collect(), 2);
+    assert_eq!(decoded.len(), num_frames);
+    assert!(decoded.iter().all(|f| f[0] == f[1]));
+
+    // 2 -> 6: the front left and front right, the others are silent.
+    let decoded = transfer("2to6", spec_of(2, None), (0..num_frames).map(|i| vec![wave(i), wave(i) * 0.5]).collect(), 6);
+    assert_eq!(decoded.len(), num_frames);
+    assert!(decoded.iter().all(|f| f[2..].iter().all(|s| *s == 0.0)));
+
+    // 6 -> 2: the front left goes to the left, the LFE doesn't get louder.
+    let source_spec = spec_of(6, Some(DOLBY_5_1_FRONT_SIDE_LAYOUT));
+    let decoded = transfer("6to2_fl", source_spec, (0..num_frames).map(|i| vec![wave(i), 0.0, 0.0, 0.0, 0.0, 0.0]).collect(), 2);
+    assert_eq!(decoded.len(), num_frames);
+    let left = rms(&decoded.iter().map(|f| f[0]).collect::<Vec<f32>>());
+    let right = rms(&decoded.iter().map(|f| f[1]).collect::<Vec<f32>>());
+    assert!(left > 0.05 && right < left * 0.1, "left {left} right {right}");
+    let decoded = transfer("6to2_lfe", source_spec, (0..num_frames).map(|i| vec![0.0, 0.0, 0.0, wave(i), 0.0, 0.0]).collect(), 2);
+    assert_eq!(decoded.len(), num_frames);
+    let lfe = rms(&(0..num_frames).map(wave).collect::<Vec<f32>>());
+    assert!(decoded.iter().all(|f| (f[0] - f[1]).abs() < 0.001), "The LFE is mixed into both channels equally");
+    assert!(rms(&decoded.iter().map(|f| f[0]).collect::<Vec<f32>>()) <= lfe);
+
+    // 6 -> 4 can't be done
+    let src_path = dir.path().join("6to2_fl_src.wav");
+    let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    let mut wavewriter = WaveWriter::create(dir.path().join("6to4.wav"), spec_of(4, None), DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    match transfer_audio_with_gain(&mut wavereader, &mut wavewriter, &[1.0; 6]).unwrap_err() {
+        errors::AudioWriteError::WrongChannels(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_opus_round_trip_length() {
+    if !cfg!(feature = "opus") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+
+    // Not a multiple of the Opus frame size, so the last block is padded.
+    for num_frames in [12345usize, 48000, 100] {
+        let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| {
+            let s = ((i as f64 * 0.05).sin() * 10000.0) as i16;
+            (s, -s)
+        }).collect();
+        let path = dir.path().join(format!("opus_{num_frames}.wav"));
+        let path = path.to_str().unwrap();
+        let mut wavereader = write_and_reopen(path, spec, DataFormat::Opus(OpusEncoderOptions::default()), |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+        match wavereader.get_fmt__chunk().extension {
+            Some(chunks::ext::FmtExtension { data: chunks::ext::ExtensionData::Opus(opus_data), .. }) => {
+                assert!(opus_data.pre_skip > 0);
+                assert!(opus_data.padding >= opus_data.pre_skip as u32);
+            }
+            ref other => panic!("Expected the Opus extension data, got {other:?}"),
+        }
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames);
+    }
+}
+
+#[test]
+pub fn test_opus_vbr_round_trip() {
+    if !cfg!(feature = "opus") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+
+    // Silence in the middle makes the VBR packets vary in size a lot.
+    let num_frames = 96000usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| {
+        if (30000..50000).contains(&i) {
+            (0, 0)
+        } else {
+            let s = ((i as f64 * 0.05).sin() * 10000.0) as i16;
+            (s, ((i * 7919) % 4096) as i16 - 2048)
+        }
+    }).collect();
+    for bitrate in [OpusBitrate::Bits(16000), OpusBitrate::Bits(64000), OpusBitrate::Bits(192000), OpusBitrate::Max] {
+        for encode_vbr in [true, false] {
+            let path = dir.path().join(format!("opus_{bitrate:?}_{encode_vbr}.wav"));
+            let path = path.to_str().unwrap();
+            let options = OpusEncoderOptions {
+                bitrate,
+                encode_vbr,
+                ..OpusEncoderOptions::default()
+            };
+            let mut wavereader = write_and_reopen(path, spec, DataFormat::Opus(options), |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+            assert_eq!(wavereader.total_frames(), Some(num_frames as u64), "{bitrate:?}, VBR: {encode_vbr}");
+            let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+            assert_eq!(decoded.len(), num_frames, "{bitrate:?}, VBR: {encode_vbr}");
+        }
+    }
+}
+
+#[test]
+pub fn test_write_silence() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec_of = |bits_per_sample: u16, sample_format: SampleFormat| Spec {
+        bits_per_sample,
+        sample_format,
+        ..test_spec(2, 8000)
+    };
+    let fixtures = [
+        ("u8", spec_of(8, SampleFormat::UInt), DataFormat::Pcm),
+        ("i16", spec_of(16, SampleFormat::Int), DataFormat::Pcm),
+        ("alaw", spec_of(8, SampleFormat::Int), DataFormat::PcmALaw),
+        ("ulaw", spec_of(8, SampleFormat::Int), DataFormat::PcmMuLaw),
+    ];
+    for (name, spec, data_format) in fixtures {
+        let path = dir.path().join(format!("silence_{name}.wav"));
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_stereos(&[(1000i16, -1000i16); 10]).unwrap();
+        wavewriter.write_silence_frames(10000).unwrap();
+        wavewriter.write_silence_duration(0.5).unwrap();
+        wavewriter.write_stereos(&[(1000i16, -1000i16); 10]).unwrap();
+        assert_eq!(wavewriter.get_num_frames_written(), 10 + 10000 + 4000 + 10);
+        assert!(wavewriter.write_silence_duration(-1.0).is_err());
+        wavewriter.finalize().unwrap();
+
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), 14020, "{name}");
+        assert!(decoded[10..14010].iter().all(|&(l, r)| l.abs() < 64 && r.abs() < 64), "{name}");
+    }
+}
+
+#[test]
+pub fn test_axml_chna_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = Spec {
+        bits_per_sample: 24,
+        ..test_spec(2, 48000)
+    };
+    let axml = chunks::AxmlChunk::new("<ebuCoreMain><coreMetadata><format><audioFormatExtended/></format></coreMetadata></ebuCoreMain>");
+    let mut audio_ids = vec![
+        chunks::ChnaAudioId::new(1, "ATU_00000001", "AT_00010001_01", "AP_00010002"),
+        chunks::ChnaAudioId::new(2, "ATU_00000002", "AT_00010002_01", "AP_00010002"),
+    ];
+    audio_ids.push(chunks::ChnaAudioId::default());
+    let chna = chunks::ChnaChunk::new(audio_ids);
+    assert_eq!((chna.num_tracks, chna.num_uids), (2, 2));
+
+    let path = dir.path().join("adm.wav");
+    // Read them back, and inherit them into another file.
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.set_axml_chunk(&axml);
+        wavewriter.set_chna_chunk(&chna);
+        wavewriter.write_stereos(&[(0i16, 0i16); 100]).unwrap();
+    });
+    assert_eq!(wavereader.get_axml_chunk().as_ref(), Some(&axml));
+    assert_eq!(wavereader.get_chna_chunk().as_ref(), Some(&chna));
+    let path2 = dir.path().join("adm_inherited.wav");
+    let wavereader = write_and_reopen(&path2, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.inherit_metadata_from_reader(&wavereader, false);
+        wavewriter.write_stereos(&[(0i16, 0i16); 100]).unwrap();
+    });
+    assert_eq!(wavereader.get_axml_chunk().as_ref(), Some(&axml));
+    assert_eq!(wavereader.get_chna_chunk().as_ref(), Some(&chna));
+
+    // The IDs are fixed-width
+    let mut wavewriter = WaveWriter::create(dir.path().join("adm_bad.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_chna_chunk(&chunks::ChnaChunk::new(vec![chunks::ChnaAudioId::new(1, "ATU_000000001", "AT_00010001_01", "AP_00010002")]));
+    wavewriter.write_stereos(&[(0i16, 0i16); 100]).unwrap();
+    assert!(wavewriter.finalize().is_err());
+}
+
+#[test]
+pub fn test_rf64_bw64_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let frames: Vec<(i16, i16)> = (0..100).map(|i| (i as i16 * 100, -(i as i16) * 100)).collect();
+
+    // Build the RF64 header by hand, every 32-bit size is `0xFFFFFFFF` and the actual sizes are in the `ds64` chunk.
+    let build = |riff_flag: &[u8; 4]| -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(riff_flag);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"ds64");
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        let riff_size_pos = bytes.len();
+        bytes.extend_from_slice(&0u64.to_le_bytes()); // riff size
+        bytes.extend_from_slice(&(frames.len() as u64 * 4).to_le_bytes()); // data size
+        bytes.extend_from_slice(&(frames.len() as u64).to_le_bytes()); // sample count
+        bytes.extend_from_slice(&1u32.to_le_bytes()); // table length
+        bytes.extend_from_slice(b"JUNK");
+        bytes.extend_from_slice(&6u64.to_le_bytes());
+        bytes.extend_from_slice(b"JUNK");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 6]);
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        for (l, r) in frames.iter() {
+            bytes.extend_from_slice(&l.to_le_bytes());
+            bytes.extend_from_slice(&r.to_le_bytes());
+        }
+        let riff_size = bytes.len() as u64 - 8;
+        bytes[riff_size_pos..riff_size_pos + 8].copy_from_slice(&riff_size.to_le_bytes());
+        bytes
+    };
+
+    for riff_flag in [b"RF64", b"BW64"] {
+        let path = dir.path().join(format!("{}.wav", String::from_utf8_lossy(riff_flag)));
+        std::fs::write(&path, build(riff_flag)).unwrap();
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(wavereader.spec().channels, 2);
+        assert_eq!(wavereader.spec().sample_rate, 44100);
+        assert_eq!(wavereader.get_fact_data(), frames.len() as u64 * 2);
+        assert!(wavereader.chunk_map().iter().any(|info| &info.flag == b"JUNK" && info.length == 6));
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, frames);
+    }
+}
+
+#[test]
+pub fn test_fact_chunk() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let num_frames = 4567usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| {
+        let s = ((i as f64 * 0.05).sin() * 10000.0) as i16;
+        (s, -s)
+    }).collect();
+
+    // Find the `fact` chunk in the file
+    let find_fact = |bytes: &[u8]| -> Option<Vec<u8>> {
+        let mut offset = 12usize;
+        while offset + 8 <= bytes.len() {
+            let chunk_size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            if &bytes[offset..offset + 4] == b"fact" {
+                return Some(bytes[offset + 8..offset + 8 + chunk_size].to_vec());
+            }
+            offset += 8 + chunk_size + (chunk_size & 1);
+        }
+        None
+    };
+
+    for (name, data_format) in [
+        ("pcm", DataFormat::Pcm),
+        ("alaw", DataFormat::PcmALaw),
+        ("ulaw", DataFormat::PcmMuLaw),
+        ("adpcm_ms", DataFormat::Adpcm(AdpcmSubFormat::Ms)),
+        ("adpcm_ima", DataFormat::Adpcm(AdpcmSubFormat::Ima)),
+    ] {
+        let path = dir.path().join(format!("fact_{name}.wav"));
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::AllowLargerThan4GB).unwrap();
+        wavewriter.write_stereos(&stereos).unwrap();
+        wavewriter.finalize().unwrap();
+
+        // Only the non-PCM formats have the 32-bit `fact` chunk of the sample frames.
+        let fact = find_fact(&std::fs::read(&path).unwrap());
+        if name == "pcm" {
+            assert!(fact.is_none());
+            continue;
+        }
+        assert_eq!(fact, Some((num_frames as u32).to_le_bytes().to_vec()), "{name}");
+
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(wavereader.get_fact_data(), num_frames as u64 * 2, "{name}");
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames, "{name}");
+    }
+}
+
+#[test]
+pub fn test_total_frames_and_duration() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let num_frames = 44100usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| {
+        let s = ((i as f64 * 0.05).sin() * 10000.0) as i16;
+        (s, -s)
+    }).collect();
+    assert_eq!(spec.duration(num_frames as u64), std::time::Duration::from_secs(1));
+
+    for (name, data_format) in [
+        ("pcm", DataFormat::Pcm),
+        ("alaw", DataFormat::PcmALaw),
+        ("adpcm_ms", DataFormat::Adpcm(AdpcmSubFormat::Ms)),
+        ("adpcm_ima", DataFormat::Adpcm(AdpcmSubFormat::Ima)),
+    ] {
+        let path = dir.path().join(format!("length_{name}.wav"));
+        let mut wavereader = write_and_reopen(&path, spec, data_format, |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+        assert_eq!(wavereader.total_frames(), Some(num_frames as u64), "{name}");
+        assert_eq!(wavereader.duration(), Some(std::time::Duration::from_secs(1)), "{name}");
+
+        // The `size_hint()` goes down as the iterator goes.
+        let mut iter = wavereader.stereo_iter::<i16>().unwrap();
+        assert_eq!(iter.size_hint(), (num_frames, Some(num_frames)), "{name}");
+        iter.next().unwrap();
+        iter.nth(9).unwrap();
+        assert_eq!(iter.size_hint(), (num_frames - 11, Some(num_frames - 11)), "{name}");
+        assert_eq!(iter.decode_stereos(100).unwrap().len(), 100);
+        assert_eq!(iter.size_hint().0, num_frames - 111, "{name}");
+        assert_eq!(iter.count(), num_frames - 111, "{name}");
+    }
+}
+
+#[test]
+pub fn test_set_metadata_tag() {
+    use chunks::ListInfo;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+
+    // PCM doesn't have native tags, the tags go to the `LIST INFO` chunk.
+    let path = dir.path().join("tags_pcm.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_metadata_tag("title", "The Title").unwrap();
+    wavewriter.set_metadata_tag("IART", "The Artist").unwrap();
+    wavewriter.set_metadata_tag("ISBJ", "The Subject").unwrap();
+    assert!(wavewriter.set_metadata_tag("NO_SUCH_TAG", "?").is_err());
+    wavewriter.write_stereos(&[(0i16, 0i16); 100]).unwrap();
+    match wavewriter.set_metadata_tag("ALBUM", "Too late").unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    wavewriter.finalize().unwrap();
+    let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let info = wavereader.get_list_chunk().iter().find(|chunk| chunk.get_is_list_info()).unwrap();
+    assert_eq!(info.get("INAM").map(|s| s.as_str()), Some("The Title"));
+    assert_eq!(info.get("IART").map(|s| s.as_str()), Some("The Artist"));
+    assert_eq!(info.get("ISBJ").map(|s| s.as_str()), Some("The Subject"));
+
+    // FLAC has native tags, the tags go to the FLAC comments.
+    if cfg!(feature = "flac") {
+        let path = dir.path().join("tags_flac.wav");
+        let mut data_format = DataFormat::from_name("flac").unwrap();
+        if let DataFormat::Flac(ref mut options) = data_format {
+            options.channels = spec.channels;
+            options.sample_rate = spec.sample_rate;
+            options.bits_per_sample = spec.bits_per_sample as u32;
+        }
+        let wavereader = write_and_reopen(&path, spec, data_format, |wavewriter| {
+            wavewriter.set_metadata_tag("TITLE", "The Title").unwrap();
+            wavewriter.write_stereos(&[(0i16, 0i16); 100]).unwrap();
+        });
+        assert!(wavereader.get_list_chunk().iter().all(|chunk| !chunk.get_is_list_info()));
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), 100);
+    }
+}
+
+#[test]
+pub fn test_flac_worker_threads() {
+    if !cfg!(feature = "flac") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+
+    // Long enough for more than one batch of segments of the worker threads, and ends with a partial block.
+    let num_frames = 600_000usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames)
+        .map(|i| {
+            let t = i as f64 / spec.sample_rate as f64;
+            let l = (t * 440.0 * std::f64::consts::TAU).sin() * 12000.0;
+            let r = (t * 660.0 * std::f64::consts::TAU).sin() * 8000.0 + ((i * 7919) % 512) as f64;
+            (l as i16, r as i16)
+        })
+        .collect();
+
+    let mut outputs = Vec::new();
+    for worker_threads in [1, 2, 4] {
+        let path = dir.path().join(format!("flac_{worker_threads}_threads.wav"));
+        let mut data_format = DataFormat::from_name("flac").unwrap();
+        if let DataFormat::Flac(ref mut options) = data_format {
+            options.channels = spec.channels;
+            options.sample_rate = spec.sample_rate;
+            options.bits_per_sample = spec.bits_per_sample as u32;
+            options.verify_decoded = true;
+            options.worker_threads = worker_threads;
+        }
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        for chunk in stereos.chunks(100_000) {
+            wavewriter.write_stereos(chunk).unwrap();
+        }
+        wavewriter.finalize().unwrap();
+
+        let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert!(decoded == stereos, "{worker_threads} threads");
+        outputs.push(std::fs::read(&path).unwrap());
+    }
+    assert!(outputs.iter().all(|output| *output == outputs[0]));
+}
+
+#[test]
+pub fn test_flac_total_samples() {
+    if !cfg!(feature = "flac") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let num_frames = 30000usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| ((i % 3000) as i16, -((i % 2000) as i16))).collect();
+    let pcm_path = dir.path().join("total_samples_pcm.wav");
+    let mut wavewriter = WaveWriter::create(&pcm_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+
+    // The 36-bit total samples of the `STREAMINFO`, which is right after `fLaC` and the metadata block header in the `data` chunk.
+    let stream_info_total_samples = |path: &std::path::Path| -> u64 {
+        let data_chunk = *WaveReader::open(path.to_str().unwrap()).unwrap().chunk_map().iter().find(|info| &info.flag == b"data").unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let offset = data_chunk.offset as usize + 8 + 8 + 13;
+        assert_eq!(&bytes[data_chunk.offset as usize + 8..data_chunk.offset as usize + 12], b"fLaC");
+        bytes[offset..offset + 5].iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64) & 0xF_FFFF_FFFF
+    };
+    let flac_format = || {
+        let mut data_format = DataFormat::from_name("flac").unwrap();
+        if let DataFormat::Flac(ref mut options) = data_format {
+            options.channels = spec.channels;
+            options.sample_rate = spec.sample_rate;
+            options.bits_per_sample = spec.bits_per_sample as u32;
+        }
+        data_format
+    };
+
+    // The transfer gives the encoder the number of frames of the source.
+    let flac_path = dir.path().join("total_samples_flac.wav");
+    let mut wavereader = WaveReader::open(pcm_path.to_str().unwrap()).unwrap();
+    let mut wavewriter = WaveWriter::create(&flac_path, spec, flac_format(), FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_metadata_tag("TITLE", "Total samples").unwrap();
+    transfer_audio_from_decoder_to_encoder(&mut wavereader, &mut wavewriter);
+    wavewriter.finalize().unwrap();
+    assert_eq!(stream_info_total_samples(&flac_path), num_frames as u64);
+    let wavereader = WaveReader::open(flac_path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(num_frames as u64));
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos);
+
+    // A wrong estimate is corrected when finalizing.
+    let flac_path = dir.path().join("wrong_estimate_flac.wav");
+    let mut wavewriter = WaveWriter::create(&flac_path, spec, flac_format(), FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_total_frames_estimate(num_frames as u64 * 2).unwrap();
+    wavewriter.write_stereos(&stereos[..10000]).unwrap();
+    assert!(wavewriter.set_total_frames_estimate(num_frames as u64).is_err());
+    wavewriter.finalize().unwrap();
+    assert_eq!(stream_info_total_samples(&flac_path), 10000);
+}
+
+#[test]
+pub fn test_truncated_data_chunk() {
+    let (_dir, path) = temp_wav("truncated_data.wav");
+    let path = path.to_str().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos = ramp_stereos(1000);
+    let mut wavewriter = WaveWriter::create(path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    let data_chunk = *WaveReader::open(path).unwrap().chunk_map().iter().find(|info| &info.flag == b"data").unwrap();
+    let bytes = std::fs::read(path).unwrap();
+    let data_end = data_chunk.offset as usize + 8 + 4000;
+
+    // Cut off in the middle of the 601st frame.
+    std::fs::write(path, &bytes[..data_end - 1599]).unwrap();
+    match WaveReader::open(path).unwrap_err().root() {
+        errors::AudioReadError::IncompleteData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    let read_options = ReadOptions {
+        allow_truncated: true,
+        ..ReadOptions::default()
+    };
+    let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+    assert!(wavereader.is_truncated());
+    assert_eq!(wavereader.total_frames(), Some(600));
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos[..600]);
+
+    // The size of `0xFFFFFFFF` means the audio data lasts until the end of the file.
+    let mut bytes = bytes[..data_end].to_vec();
+    let size_offset = data_chunk.offset as usize + 4;
+    bytes[size_offset..size_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    std::fs::write(path, &bytes).unwrap();
+    let mut wavereader = WaveReader::open(path).unwrap();
+    assert!(!wavereader.is_truncated());
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos);
+}
+
+#[test]
+pub fn test_pcm_same_type_fast_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let samples: Vec<i16> = (0..4 * 1024 * 1024).map(|i: i32| (i.wrapping_mul(7919) & 0xFFFF) as i16).collect();
+    let samples_i32: Vec<i32> = samples.iter().map(|&s| (s as i32) << 16).collect();
+
+    // The same sample type is written as bytes, the other type goes through the per-sample conversion.
+    let fast_path = dir.path().join("fast_i16.wav");
+    let slow_path = dir.path().join("slow_i16.wav");
+    let mut wavewriter = WaveWriter::create(&fast_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_interleaved_samples(&samples).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavewriter = WaveWriter::create(&slow_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_interleaved_samples(&samples_i32).unwrap();
+    wavewriter.finalize().unwrap();
+    assert_eq!(std::fs::read(&fast_path).unwrap(), std::fs::read(&slow_path).unwrap());
+
+    let mut wavereader = WaveReader::open(fast_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i16> = wavereader.frame_iter::<i16>().unwrap().flatten().collect();
+    assert_eq!(decoded, samples);
+    let mut wavereader = WaveReader::open(fast_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i32> = wavereader.frame_iter::<i32>().unwrap().flatten().collect();
+    assert_eq!(decoded, samples_i32);
+
+    // Floats too
+    let spec = Spec {
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+        ..spec
+    };
+    let samples: Vec<f32> = (0..65536).map(|i| (i as f32 * 0.001).sin() * 0.5).collect();
+    let samples_f64: Vec<f64> = samples.iter().map(|&s| s as f64).collect();
+    let fast_path = dir.path().join("fast_f32.wav");
+    let slow_path = dir.path().join("slow_f32.wav");
+    let mut wavewriter = WaveWriter::create(&fast_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_interleaved_samples(&samples).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavewriter = WaveWriter::create(&slow_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_interleaved_samples(&samples_f64).unwrap();
+    wavewriter.finalize().unwrap();
+    assert_eq!(std::fs::read(&fast_path).unwrap(), std::fs::read(&slow_path).unwrap());
+    let mut wavereader = WaveReader::open(fast_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<f32> = wavereader.frame_iter::<f32>().unwrap().flatten().collect();
+    assert_eq!(decoded, samples);
+}
+
+#[test]
+pub fn test_smpl_loops() {
+    use chunks::{SmplChunk, SmplSampleLoop};
+    use errors::AudioWriteError;
+    let (_dir, path) = temp_wav("smpl_loops.wav");
+    let spec = test_spec(1, 44100);
+    let samples: Vec<i16> = (0..1000).map(|i| (i * 30) as i16).collect();
+    let mut smpl = SmplChunk::new(44100, 60, 0x80000000);
+    smpl.add_loop(100, 499, SmplSampleLoop::LOOP_FORWARD, 0)
+        .add_loop(500, 999, SmplSampleLoop::LOOP_ALTERNATING, 3);
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.set_smpl_chunk(&smpl);
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+    });
+    let read_smpl = wavereader.get_smpl_chunk().clone().unwrap();
+    assert_eq!(read_smpl.sample_period, 22675);
+    assert_eq!(read_smpl.midi_unity_note, 60);
+    assert_eq!(read_smpl.midi_pitch_fraction, 0x80000000);
+    assert_eq!(read_smpl.num_sample_loops, 2);
+    let loops: Vec<(u32, u32, u32, u32, u32)> = read_smpl.loops.iter().map(|l| (l.identifier, l.type_, l.start, l.end, l.play_count)).collect();
+    assert_eq!(loops, [(0, 0, 100, 499, 0), (1, 1, 500, 999, 3)]);
+
+    // The loop goes beyond the audio data.
+    smpl.add_loop(900, 1000, SmplSampleLoop::LOOP_BACKWARD, 1);
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_smpl_chunk(&smpl);
+    wavewriter.write_interleaved_samples(&samples).unwrap();
+    match wavewriter.finalize().unwrap_err() {
+        AudioWriteError::InvalidData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_looped_frame_iter() {
+    use chunks::{SmplChunk, SmplSampleLoop};
+    use std::time::Duration;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(1, 44100);
+
+    // Every sample is its frame index, the loop is from frame 100 to frame 499.
+    let samples: Vec<i16> = (0..1000).collect();
+    let write = |name: &str, smpl: Option<&SmplChunk>| -> WaveReader {
+        let path = dir.path().join(name);
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        if let Some(smpl) = smpl {
+            wavewriter.set_smpl_chunk(smpl);
+        }
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        wavewriter.finalize().unwrap();
+        WaveReader::open(path.to_str().unwrap()).unwrap()
+    };
+    let play = |wavereader: &mut WaveReader, loop_policy: LoopPolicy| -> Vec<i16> {
+        wavereader.looped_frame_iter::<i16>(loop_policy).unwrap().map(|frame| frame[0]).collect()
+    };
+    let frames = |ranges: &[std::ops::Range<i16>]| -> Vec<i16> {
+        ranges.iter().cloned().flatten().collect()
+    };
+    let duration_of = |num_frames: u32| Duration::from_secs_f64(num_frames as f64 / 44100.0);
+
+    let mut smpl = SmplChunk::new(44100, 60, 0);
+    smpl.add_loop(100, 499, SmplSampleLoop::LOOP_FORWARD, 0);
+    let mut wavereader = write("looped.wav", Some(&smpl));
+    assert_eq!(play(&mut wavereader, LoopPolicy::Once), frames(&[0..1000]));
+    assert_eq!(play(&mut wavereader, LoopPolicy::Repeat(1)), frames(&[0..1000]));
+    assert_eq!(play(&mut wavereader, LoopPolicy::Repeat(3)), frames(&[0..500, 100..500, 100..500, 500..1000]));
+    assert_eq!(play(&mut wavereader, LoopPolicy::ForDuration(duration_of(1000))), frames(&[0..500, 100..500, 100..200]));
+
+    // Starting inside the loop, the loop starts from its start when it jumps back.
+    wavereader.seek_to_frame(300).unwrap();
+    assert_eq!(play(&mut wavereader, LoopPolicy::Repeat(2)), frames(&[300..500, 100..1000]));
+
+    // Without the `smpl` chunk, it's the normal iterator, the duration still limits it.
+    let mut wavereader = write("not_looped.wav", None);
+    assert_eq!(play(&mut wavereader, LoopPolicy::Repeat(3)), frames(&[0..1000]));
+    assert_eq!(play(&mut wavereader, LoopPolicy::ForDuration(duration_of(500))), frames(&[0..500]));
+    assert_eq!(play(&mut wavereader, LoopPolicy::ForDuration(duration_of(5000))), frames(&[0..1000]));
+}
+
+#[test]
+pub fn test_cue_builder() {
+    use chunks::CueBuilder;
+    use errors::AudioWriteError;
+    let (_dir, path) = temp_wav("cues.wav");
+    let spec = test_spec(1, 44100);
+    let samples: Vec<i16> = (0..10000).map(|i| (i * 3) as i16).collect();
+    let mut cues = CueBuilder::new();
+    let intro = cues.add_cue(0, "Intro");
+    let verse = cues.add_cue(2000, "Verse");
+    let outro = cues.add_cue(9000, "Outro");
+    cues.set_note(verse, "Sing loudly").unwrap();
+    cues.set_region(verse, 5000, "The first verse").unwrap();
+    cues.set_repeats(verse, 2).unwrap();
+    assert!(cues.set_note(4, "No such cue").is_err());
+    cues.with_playlist = true;
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.set_cues(&cues);
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+    });
+    let full_info = wavereader.create_full_info_cue_data().unwrap();
+    assert_eq!(full_info.keys().copied().collect::<Vec<u32>>(), [intro, verse, outro]);
+    let cue = &full_info[&intro];
+    assert_eq!((cue.start_sample, cue.label.as_str(), cue.note.as_str()), (0, "Intro", ""));
+    assert_eq!((cue.num_samples, cue.repeats), (0, 1));
+    let cue = &full_info[&verse];
+    assert_eq!(&cue.data_chunk_id, b"data");
+    assert_eq!((cue.start_sample, cue.label.as_str(), cue.note.as_str()), (2000, "Verse", "Sing loudly"));
+    assert_eq!((cue.sample_length, cue.purpose_id.trim_end(), cue.text_data.as_str()), (5000, "rgn", "The first verse"));
+    assert_eq!((cue.num_samples, cue.repeats), (5000, 2));
+    assert_eq!(full_info[&outro].label, "Outro");
+
+    // Without the playlist
+    cues.with_playlist = false;
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.set_cues(&cues);
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+    });
+    assert!(wavereader.get_plst_chunk().is_none());
+    assert_eq!(wavereader.create_full_info_cue_data().unwrap()[&verse].label, "Verse");
+
+    // The region of the last cue point goes beyond the audio data.
+    cues.set_region(outro, 1001, "Too long").unwrap();
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_cues(&cues);
+    wavewriter.write_interleaved_samples(&samples).unwrap();
+    match wavewriter.finalize().unwrap_err() {
+        AudioWriteError::InvalidData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_many_channels_zero_mask() {
+    let dir = tempfile::tempdir().unwrap();
+    for channels in [32u16, 64] {
+        let path = dir.path().join(format!("channels_{channels}.wav"));
+        let spec = Spec {
+            channel_mask: Some(0),
+            bits_per_sample: 24,
+            ..test_spec(channels, 48000)
+        };
+        assert!(spec.is_channel_mask_valid());
+        let frames: Vec<Vec<i32>> = (0..500).map(|i| (0..channels as i32).map(|c| (i * 1000 + c) << 8).collect()).collect();
+        let mut wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        assert_eq!(wavereader.get_fmt__chunk().format_tag, format_tags::FORMAT_TAG_EXTENSIBLE);
+        assert_eq!(wavereader.spec().channels, channels);
+        assert_eq!(wavereader.spec().channel_mask, Some(0));
+        let decoded: Vec<Vec<i32>> = wavereader.frame_iter::<i32>().unwrap().collect();
+        assert_eq!(decoded, frames);
+
+        // Without a mask, the speaker positions can't be guessed for so many channels.
+        let spec = Spec { channel_mask: None, ..spec };
+        assert!(WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).is_err());
+    }
+}
+
+/// * A toy codec for testing the third-party codec hooks: 16-bit samples stored as the wrapping differences from the previous sample of the channel.
+#[cfg(test)]
+mod toy_delta_codec {
+    use super::*;
+    use std::{cell::RefCell, io::SeekFrom, rc::Rc};
+    use chunks::FmtChunk;
+    use decoders::Decoder;
+    use encoders::EncoderToImpl;
+    use errors::{AudioReadError, AudioWriteError};
+    use io_utils::{Reader, Writer};
+
+    pub const FORMAT_TAG_TOY_DELTA: u16 = 0x7A55;
+
+    /// * The toy codec under the development format tag `0xFFFF` is told by this GUID.
+    pub const GUID_TOY_DELTA: format_specs::guids::GUID = format_specs::guids::GUID(0x7A550001, 0x0000, 0x0010, [0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71]);
+
+    #[derive(Debug)]
+    pub struct ToyDeltaEncoder<'a> {
+        pub writer: &'a mut dyn Writer,
+        pub spec: Spec,
+        pub prev: Vec<i16>,
+        pub calls: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl ToyDeltaEncoder<'_> {
+        fn encode<S: SampleType>(&mut self, samples: &[S]) -> Result<(), AudioWriteError> {
+            let channels = self.spec.channels as usize;
+            for (i, sample) in samples.iter().enumerate() {
+                let sample = i16::scale_from(*sample);
+                let delta = sample.wrapping_sub(self.prev[i % channels]);
+                self.prev[i % channels] = sample;
+                self.writer.write_all(&delta.to_le_bytes())?;
+            }
+            Ok(())
+        }
+    }
+
+    macro_rules! toy_write_fns {
+        ($($name:ident: $type:ty),*) => {
+            $(fn $name(&mut self, samples: &[$type]) -> Result<(), AudioWriteError> { self.encode(samples) })*
+        };
+    }
+
+    impl EncoderToImpl for ToyDeltaEncoder<'_> {
+        fn get_channels(&self) -> u16 { self.spec.channels }
+        fn get_max_channels(&self) -> u16 { 8 }
+        fn get_bitrate(&self) -> u32 { self.spec.sample_rate * self.spec.channels as u32 * 16 }
+        fn new_fmt_chunk(&mut self) -> Result<FmtChunk, AudioWriteError> {
+            self.calls.borrow_mut().push("new_fmt_chunk");
+            Ok(FmtChunk {
+                format_tag: FORMAT_TAG_TOY_DELTA,
+                channels: self.spec.channels,
+                sample_rate: self.spec.sample_rate,
+                byte_rate: 0,
+                block_align: self.spec.channels * 2,
+                bits_per_sample: 16,
+                extension: None,
+            })
+        }
+        fn update_fmt_chunk(&self, fmt: &mut FmtChunk) -> Result<(), AudioWriteError> {
+            self.calls.borrow_mut().push("update_fmt_chunk");
+            fmt.byte_rate = self.get_bitrate() / 8;
+            Ok(())
+        }
+        fn begin_encoding(&mut self) -> Result<(), AudioWriteError> {
+            self.calls.borrow_mut().push("begin_encoding");
+            Ok(())
+        }
+        fn finish(&mut self) -> Result<(), AudioWriteError> {
+            self.calls.borrow_mut().push("finish");
+            Ok(())
+        }
+        toy_write_fns!(
+            write_interleaved_samples__i8: i8,
+            write_interleaved_samples_i16: i16,
+            write_interleaved_samples_i24: i24,
+            write_interleaved_samples_i32: i32,
+            write_interleaved_samples_i64: i64,
+            write_interleaved_samples__u8: u8,
+            write_interleaved_samples_u16: u16,
+            write_interleaved_samples_u24: u24,
+            write_interleaved_samples_u32: u32,
+            write_interleaved_samples_u64: u64,
+            write_interleaved_samples_f32: f32,
+            write_interleaved_samples_f64: f64
+        );
+    }
+
+    #[derive(Debug)]
+    pub struct ToyDeltaDecoder {
+        reader: Box<dyn Reader>,
+        data_offset: u64,
+        channels: u16,
+        total_frames: u64,
+        frame_index: u64,
+        prev: Vec<i16>,
+    }
+
+    pub fn create_toy_delta_decoder(
+        reader: Box<dyn Reader>,
+        data_offset: u64,
+        data_length: u64,
+        fmt: &FmtChunk,
+        _fact_data: u64,
+    ) -> Result<Box<dyn Decoder<i32>>, AudioReadError> {
+        let mut decoder = ToyDeltaDecoder {
+            reader,
+            data_offset,
+            channels: fmt.channels,
+            total_frames: data_length / fmt.block_align as u64,
+            frame_index: 0,
+            prev: vec![0; fmt.channels as usize],
+        };
+        decoder.reader.seek(SeekFrom::Start(data_offset))?;
+        Ok(Box::new(decoder))
+    }
+
+    impl Decoder<i32> for ToyDeltaDecoder {
+        fn get_channels(&self) -> u16 {
+            self.channels
+        }
+
+        fn decode_frame(&mut self) -> Result<Option<Vec<i32>>, AudioReadError> {
+            if self.frame_index >= self.total_frames {
+                return Ok(None);
+            }
+            let mut frame = Vec::with_capacity(self.channels as usize);
+            for prev in self.prev.iter_mut() {
+                let mut bytes = [0u8; 2];
+                self.reader.read_exact(&mut bytes)?;
+                *prev = prev.wrapping_add(i16::from_le_bytes(bytes));
+                frame.push(i32::scale_from(*prev));
+            }
+            self.frame_index += 1;
+            Ok(Some(frame))
+        }
+
+        /// * The deltas have to be decoded from the beginning.
+        fn seek(&mut self, seek_from: SeekFrom) -> Result<(), AudioReadError> {
+            let target = match seek_from {
+                SeekFrom::Start(frame_index) => frame_index,
+                _ => return Err(AudioReadError::Unsupported("Only `SeekFrom::Start` is supported".to_owned())),
+            };
+            self.reader.seek(SeekFrom::Start(self.data_offset))?;
+            self.prev.fill(0);
+            self.frame_index = 0;
+            while self.frame_index < target.min(self.total_frames) {
+                self.decode_frame()?;
+            }
+            Ok(())
+        }
+
+        fn get_cur_frame_index(&mut self) -> Result<u64, AudioReadError> {
+            Ok(self.frame_index)
+        }
+    }
+}
+
+#[test]
+pub fn test_custom_codec() {
+    use std::{cell::RefCell, rc::Rc};
+    use decoders::CustomDecoder;
+    use toy_delta_codec::*;
+    let (_dir, path) = temp_wav("toy_delta.wav");
+    let spec = test_spec(2, 22050);
+    let stereos: Vec<(i16, i16)> = (0..3000).map(|i: i32| ((i * 37) as i16, (i * -91) as i16)).collect();
+    let calls = Rc::new(RefCell::new(Vec::<&'static str>::new()));
+    let encoder_calls = calls.clone();
+    let mut wavewriter = WaveWriter::create_with_encoder(&path, spec, |writer, spec| {
+        Ok(Box::new(ToyDeltaEncoder {
+            writer,
+            spec,
+            prev: vec![0; spec.channels as usize],
+            calls: encoder_calls,
+        }))
+    }, FileSizeOption::NeverLargerThan4GB).unwrap();
+    assert_eq!(wavewriter.get_data_format(), DataFormat::Custom(FORMAT_TAG_TOY_DELTA));
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    assert_eq!(*calls.borrow(), ["new_fmt_chunk", "begin_encoding", "finish", "update_fmt_chunk"]);
+
+    // The built-in decoders don't know the format.
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.get_fmt__chunk().format_tag, FORMAT_TAG_TOY_DELTA);
+    assert_eq!(wavereader.get_fmt__chunk().byte_rate, 22050 * 4);
+    assert!(wavereader.stereo_iter::<i16>().is_err());
+
+    let read_options = ReadOptions {
+        custom_decoder: Some(CustomDecoder {
+            format_tag: FORMAT_TAG_TOY_DELTA,
+            sub_format: None,
+            create: create_toy_delta_decoder,
+        }),
+        ..ReadOptions::default()
+    };
+    let mut wavereader = WaveReader::open_with_options(path.to_str().unwrap(), read_options).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(stereos.len() as u64));
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos);
+    let decoded: Vec<(f32, f32)> = wavereader.stereo_iter::<f32>().unwrap().collect();
+    assert_eq!(decoded.len(), stereos.len());
+
+    // The decoder of a third-party codec may not be `Send`, the `SyncWaveReader` doesn't take it.
+    match SyncWaveReader::open_with_options(path.to_str().unwrap(), read_options).unwrap_err() {
+        errors::AudioReadError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // `DataFormat::Custom` can't create the encoder by itself.
+    assert!(WaveWriter::create(&path, spec, DataFormat::Custom(FORMAT_TAG_TOY_DELTA), FileSizeOption::NeverLargerThan4GB).is_err());
+
+    // Under the development format tag, the GUID picks the decoder.
+    let monos: Vec<i16> = (0..1000).map(|i| (i * 53) as i16).collect();
+    let deltas: Vec<i16> = monos.iter().scan(0i16, |prev, s| {
+        let delta = s.wrapping_sub(*prev);
+        *prev = *s;
+        Some(delta)
+    }).collect();
+    let (_dir, path) = temp_wav("toy_delta_development.wav");
+    std::fs::write(&path, create_development_format_wav(&GUID_TOY_DELTA, &deltas)).unwrap();
+    let custom_decoder = CustomDecoder {
+        format_tag: format_specs::format_tags::FORMAT_TAG_DEVELOPMENT,
+        sub_format: Some(GUID_TOY_DELTA),
+        create: create_toy_delta_decoder,
+    };
+    let read_options = ReadOptions {
+        custom_decoder: Some(custom_decoder),
+        ..ReadOptions::default()
+    };
+    let mut wavereader = WaveReader::open_with_options(path.to_str().unwrap(), read_options).unwrap();
+    let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, monos);
+
+    // Another GUID under the same format tag isn't for this decoder.
+    let read_options = ReadOptions {
+        custom_decoder: Some(CustomDecoder {
+            sub_format: Some(format_specs::guids::GUID_IEEE_FLOAT_FORMAT),
+            ..custom_decoder
+        }),
+        ..ReadOptions::default()
+    };
+    let mut wavereader = WaveReader::open_with_options(path.to_str().unwrap(), read_options).unwrap();
+    match wavereader.mono_iter::<i16>().unwrap_err() {
+        errors::AudioReadError::UnsupportedFormat(0xFFFF, Some(guid)) => assert_eq!(guid, GUID_TOY_DELTA),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_metadata_round_trip() {
+    use chunks::{Id3, InfoDict, ListChunk, ListInfo, MetadataSource, TrknChunk};
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(1, 8000);
+    let trkn = TrknChunk {
+        track_no: 4,
+        total_tracks: 12,
+    };
+    let path = dir.path().join("metadata.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    let mut info = ListChunk::Info(Default::default());
+    info.set_name("The INFO Title").unwrap();
+    info.set_album("The Album").unwrap();
+    info.set_genre("Ambient").unwrap();
+    info.set_track_no("3").unwrap();
+    wavewriter.set_list_chunk(info);
+    wavewriter.set_trkn_chunk(&trkn);
+    if cfg!(feature = "id3") {
+        let dict = InfoDict::from([
+            ("INAM".to_owned(), "The ID3 Title 🎵".to_owned()),
+            ("IART".to_owned(), "The Artist".to_owned()),
+            ("IPRD".to_owned(), "The Album".to_owned()),
+        ]);
+        wavewriter.set_id3(&Id3::mirror_info(None, &dict).unwrap()).unwrap();
+    }
+    wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    wavewriter.finalize().unwrap();
+
+    let metadata = WaveReader::open(path.to_str().unwrap()).unwrap().metadata();
+    assert_eq!(metadata.album.as_deref(), Some("The Album"));
+    assert_eq!(metadata.track.as_deref(), Some("3"));
+    assert_eq!(metadata.other[&(MetadataSource::ListInfo, "IGNR".to_owned())], "Ambient");
+    assert_eq!(metadata.other[&(MetadataSource::Trkn, "track".to_owned())], "4");
+    assert_eq!(metadata.other[&(MetadataSource::Trkn, "total_tracks".to_owned())], "12");
+    assert!(!metadata.other.contains_key(&(MetadataSource::ListInfo, "album".to_owned())));
+    if cfg!(feature = "id3") {
+        // The ID3 title wins, the disagreeing `LIST INFO` title is kept.
+        assert_eq!(metadata.title.as_deref(), Some("The ID3 Title 🎵"));
+        assert_eq!(metadata.artist.as_deref(), Some("The Artist"));
+        assert_eq!(metadata.other[&(MetadataSource::ListInfo, "title".to_owned())], "The INFO Title");
+    } else {
+        assert_eq!(metadata.title.as_deref(), Some("The INFO Title"));
+        assert_eq!(metadata.artist, None);
+    }
+
+    // Fan the metadata back out, each chunk gets its own values.
+    let path = dir.path().join("metadata_copy.wav");
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.set_metadata(&metadata).unwrap();
+        wavewriter.set_trkn_chunk(&trkn);
+        wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    });
+    assert_eq!(wavereader.metadata(), metadata);
+    assert_eq!(wavereader.get_id3__chunk().is_some(), cfg!(feature = "id3"));
+    let info = wavereader.get_list_chunk().iter().find(|chunk| chunk.get_is_list_info()).unwrap();
+    assert_eq!(info.get_name().unwrap(), "The INFO Title");
+    assert_eq!(info.get_genre().unwrap(), "Ambient");
+
+    // Too late after writing the samples.
+    let mut wavewriter = WaveWriter::create(dir.path().join("late.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    assert!(wavewriter.set_metadata(&metadata).is_err());
+}
+
+#[test]
+pub fn test_bext_writer() {
+    use chunks::BextChunk;
+    let (_dir, path) = temp_wav("bext.wav");
+    let spec = Spec {
+        bits_per_sample: 24,
+        ..test_spec(2, 48000)
+    };
+
+    let mut bext = BextChunk {
+        description: "Broadcast delivery".to_string(),
+        originator: "rustwav".to_string(),
+        originator_ref: "REF0001".to_string(),
+        origination_date: "2024-05-06".to_string(),
+        origination_time: "07:08:09".to_string(),
+        loudness_value: -2300,
+        max_true_peak_level: -100,
+        ..Default::default()
+    };
+    bext.set_time_reference_from_timecode(25.0, 10, 0, 0, 12, 48000).unwrap();
+    assert_eq!(bext.time_ref, 36000 * 48000 + 12 * 48000 / 25);
+    bext.set_time_reference_from_timecode(30000.0 / 1001.0, 0, 0, 1, 0, 48000).unwrap();
+    assert_eq!(bext.time_ref, 48048);
+    bext.append_coding_history("A=PCM,F=48000,W=16,M=stereo,T=original").unwrap();
+    bext.append_coding_history("A=PCM,F=48000,W=24,M=stereo,T=rustwav").unwrap();
+    assert!(bext.append_coding_history("two\r\nlines").is_err());
+    assert!(bext.set_time_reference_from_timecode(25.0, 0, 0, 0, 25, 48000).is_err());
+
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+
+    // The invalid fields are refused before anything is written.
+    for bad in [
+        BextChunk { originator: "x".repeat(33), ..bext.clone() },
+        BextChunk { description: "Caf\u{e9}".to_string(), ..bext.clone() },
+        BextChunk { origination_date: "06/05/2024".to_string(), ..bext.clone() },
+        BextChunk { origination_time: "7:08".to_string(), ..bext.clone() },
+        BextChunk { coding_history: "no line end".to_string(), ..bext.clone() },
+    ] {
+        match wavewriter.set_bext(bad).unwrap_err() {
+            errors::AudioWriteError::InvalidArguments(_) => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+    }
+    wavewriter.set_bext(bext.clone()).unwrap();
+    wavewriter.write_stereos(&[(0i32, 0i32); 4800]).unwrap();
+    wavewriter.finalize().unwrap();
+
+    let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let read = wavereader.get_bext_chunk().clone().unwrap();
+    assert_eq!(read.description, bext.description);
+    assert_eq!(read.originator, bext.originator);
+    assert_eq!(read.originator_ref, bext.originator_ref);
+    assert_eq!(read.origination_date, bext.origination_date);
+    assert_eq!(read.origination_time, bext.origination_time);
+    assert_eq!(read.time_ref, 48048);
+    assert_eq!(read.version, 2, "The loudness values need the version 2");
+    assert_eq!((read.loudness_value, read.max_true_peak_level), (-2300, -100));
+    assert_eq!(read.coding_history, bext.coding_history);
+    let info = wavereader.chunk_map().iter().find(|info| &info.flag == b"bext").unwrap();
+    assert_eq!(info.length as usize, BextChunk::FIXED_SIZE + bext.coding_history.len());
+}
+
+#[test]
+pub fn test_sync_wave_reader_writer() {
+    use rayon::prelude::*;
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send::<SyncWaveReader>();
+    assert_send::<SyncWaveWriter>();
+    assert_send_sync::<SyncWaveReader>();
+    assert_send_sync::<SyncWaveWriter>();
+
+    let (_dir, path) = temp_wav("sync.wav");
+    let spec = test_spec(2, 44100);
+    let frames: Vec<Vec<i16>> = (0..10000).map(|i: i32| vec![(i * 3) as i16, (i * -7) as i16]).collect();
+
+    // Write the file on another thread.
+    let wavewriter = SyncWaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    let to_write = frames.clone();
+    std::thread::spawn(move || {
+        wavewriter.write_frames(&to_write).unwrap();
+        assert_eq!(wavewriter.position().unwrap().frames_accepted, to_write.len() as u64);
+        wavewriter.into_inner().finalize().unwrap();
+    }).join().unwrap();
+
+    // Decode the parts of the file on the thread pool.
+    const PART_LEN: usize = 1000;
+    let wavereader = SyncWaveReader::open(path.to_str().unwrap()).unwrap();
+    let parts: Vec<Vec<Vec<i16>>> = (0..frames.len() / PART_LEN).into_par_iter().map(|part| {
+        wavereader.read_frames_range::<i16>((part * PART_LEN) as u64, PART_LEN).unwrap()
+    }).collect();
+    assert_eq!(parts.concat(), frames);
+    assert_eq!(wavereader.total_frames(), Some(frames.len() as u64));
+    assert_eq!(wavereader.spec(), spec);
+}
+
+#[test]
+pub fn test_aiff_reader() {
+    // Build a stereo AIFF or AIFF-C file, `ssnd` is the sample data.
+    fn build_aiff(form_type: &[u8; 4], compression_type: &[u8; 4], num_frames: u32, bits_per_sample: u16, sample_rate: u32, extra_chunks: &[u8], ssnd: &[u8]) -> Vec<u8> {
+        let mut comm = Vec::<u8>::new();
+        comm.extend(2u16.to_be_bytes());
+        comm.extend(num_frames.to_be_bytes());
+        comm.extend(bits_per_sample.to_be_bytes());
+        let log2 = 31 - sample_rate.leading_zeros();
+        comm.extend((16383 + log2 as u16).to_be_bytes());
+        comm.extend(((sample_rate as u64) << (63 - log2)).to_be_bytes());
+        if form_type == b"AIFC" {
+            comm.extend(compression_type);
+            comm.extend([0u8, 0u8]);
+        }
+        let mut body = Vec::<u8>::new();
+        body.extend(form_type);
+        body.extend(b"COMM");
+        body.extend((comm.len() as u32).to_be_bytes());
+        body.extend(comm);
+        body.extend(extra_chunks);
+        body.extend(b"SSND");
+        body.extend((ssnd.len() as u32 + 8).to_be_bytes());
+        body.extend([0u8; 8]);
+        body.extend(ssnd);
+        if body.len() & 1 != 0 {
+            body.push(0);
+        }
+        let mut file = b"FORM".to_vec();
+        file.extend((body.len() as u32).to_be_bytes());
+        file.extend(body);
+        file
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let frames: Vec<Vec<i16>> = (0..2000).map(|i: i32| vec![(i * 13) as i16, (i * -29) as i16]).collect();
+
+    // The equivalent WAV file.
+    let wav_path = dir.path().join("equivalent.wav");
+    let spec = test_spec(2, 44100);
+    let mut wavereader = write_and_reopen(&wav_path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_frames(&frames).unwrap());
+    let wav_frames: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+
+    // 16-bit AIFF with the markers, the instrument loop and the name.
+    let mut extra_chunks = Vec::<u8>::new();
+    extra_chunks.extend(b"MARK");
+    extra_chunks.extend(24u32.to_be_bytes());
+    extra_chunks.extend(2u16.to_be_bytes());
+    extra_chunks.extend(1u16.to_be_bytes());
+    extra_chunks.extend(100u32.to_be_bytes());
+    extra_chunks.extend(b"\x05Start");
+    extra_chunks.extend(2u16.to_be_bytes());
+    extra_chunks.extend(1100u32.to_be_bytes());
+    extra_chunks.extend(b"\x03End");
+    extra_chunks.extend(b"INST");
+    extra_chunks.extend(20u32.to_be_bytes());
+    extra_chunks.extend([60u8, 0, 0, 127, 1, 127]);
+    extra_chunks.extend(0i16.to_be_bytes());
+    extra_chunks.extend([0u8, 1, 0, 1, 0, 2]);
+    extra_chunks.extend([0u8; 6]);
+    extra_chunks.extend(b"NAME");
+    extra_chunks.extend(5u32.to_be_bytes());
+    extra_chunks.extend(b"Tone\0\0");
+    let ssnd: Vec<u8> = frames.iter().flatten().flat_map(|sample| sample.to_be_bytes()).collect();
+    let aiff_path = dir.path().join("16bit.aiff");
+    std::fs::write(&aiff_path, build_aiff(b"AIFF", b"NONE", frames.len() as u32, 16, 44100, &extra_chunks, &ssnd)).unwrap();
+    let mut aiffreader = WaveReader::open(aiff_path.to_str().unwrap()).unwrap();
+    assert_eq!(aiffreader.spec().channels, 2);
+    assert_eq!(aiffreader.spec().sample_rate, 44100);
+    assert_eq!(aiffreader.spec().bits_per_sample, 16);
+    assert_eq!(aiffreader.total_frames(), Some(frames.len() as u64));
+    let aiff_frames: Vec<Vec<i16>> = aiffreader.frame_iter::<i16>().unwrap().collect();
+    assert_eq!(aiff_frames, wav_frames);
+    let cue_points = &aiffreader.get_cue__chunk().as_ref().unwrap().cue_points;
+    assert_eq!(cue_points.iter().map(|cue| (cue.cue_point_id, cue.position)).collect::<Vec<_>>(), [(1, 100), (2, 1100)]);
+    let full_info_cues = aiffreader.create_full_info_cue_data().unwrap();
+    assert_eq!(full_info_cues[&1].label, "Start");
+    assert_eq!(full_info_cues[&2].label, "End");
+    assert_eq!(aiffreader.get_inst_chunk().as_ref().unwrap().base_note, 60);
+    let smpl_chunk = aiffreader.get_smpl_chunk().as_ref().unwrap();
+    assert_eq!((smpl_chunk.loops[0].start, smpl_chunk.loops[0].end), (100, 1099));
+    assert_eq!(aiffreader.get_metadata_text("INAM").unwrap(), "Tone");
+
+    // The little-endian AIFF-C samples are the same as the WAV samples.
+    let ssnd: Vec<u8> = frames.iter().flatten().flat_map(|sample| sample.to_le_bytes()).collect();
+    let aiff_path = dir.path().join("sowt.aifc");
+    std::fs::write(&aiff_path, build_aiff(b"AIFC", b"sowt", frames.len() as u32, 16, 44100, &[], &ssnd)).unwrap();
+    let aiff_frames: Vec<Vec<i16>> = WaveReader::open(aiff_path.to_str().unwrap()).unwrap().frame_iter::<i16>().unwrap().collect();
+    assert_eq!(aiff_frames, wav_frames);
+
+    // 24-bit AIFF-C, the samples are byte-swapped by 3 bytes.
+    let ssnd: Vec<u8> = frames.iter().flatten().flat_map(|sample| {
+        let [b0, b1, b2, _] = ((*sample as i32) << 8 | 0x5A).to_be_bytes();
+        [b0, b1, b2]
+    }).collect();
+    let aiff_path = dir.path().join("24bit.aifc");
+    std::fs::write(&aiff_path, build_aiff(b"AIFC", b"NONE", frames.len() as u32, 24, 48000, &[], &ssnd)).unwrap();
+    let mut aiffreader = WaveReader::open(aiff_path.to_str().unwrap()).unwrap();
+    assert_eq!(aiffreader.spec().bits_per_sample, 24);
+    assert_eq!(aiffreader.spec().sample_rate, 48000);
+    let aiff_frames: Vec<Vec<i32>> = aiffreader.frame_iter::<i32>().unwrap().collect();
+    let expected: Vec<Vec<i32>> = frames.iter().map(|frame| frame.iter().map(|sample| ((*sample as i32) << 16) | 0x5A00).collect()).collect();
+    assert_eq!(aiff_frames, expected);
+
+    // The signed 8-bit AIFF samples become the unsigned 8-bit WAV samples.
+    let ssnd: Vec<u8> = frames.iter().flatten().map(|sample| (*sample >> 8) as i8 as u8).collect();
+    let aiff_path = dir.path().join("8bit.aiff");
+    std::fs::write(&aiff_path, build_aiff(b"AIFF", b"NONE", frames.len() as u32, 8, 22050, &[], &ssnd)).unwrap();
+    let aiff_frames: Vec<Vec<i16>> = WaveReader::open(aiff_path.to_str().unwrap()).unwrap().frame_iter::<i16>().unwrap().collect();
+    let expected: Vec<Vec<i16>> = frames.iter().map(|frame| frame.iter().map(|sample| sample & !0xFF).collect()).collect();
+    assert_eq!(aiff_frames, expected);
+
+    // The compressed AIFF-C isn't supported.
+    let aiff_path = dir.path().join("ima4.aifc");
+    std::fs::write(&aiff_path, build_aiff(b"AIFC", b"ima4", 0, 16, 44100, &[], &[])).unwrap();
+    assert!(WaveReader::open(aiff_path.to_str().unwrap()).is_err());
+}
+
+#[test]
+pub fn test_vorbis_arbitrary_sample_rates() {
+    if !cfg!(feature = "oggvorbis") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    for sample_rate in [32000u32, 16000, 96000] {
+        for bitrate in [OggVorbisBitrateStrategy::QualityVbr(0.5), OggVorbisBitrateStrategy::Abr(64000)] {
+            let params = OggVorbisEncoderParams {
+                channels: 2,
+                sample_rate,
+                bitrate: Some(bitrate),
+                ..OggVorbisEncoderParams::new()
+            };
+
+            // The nominal bitrate of the quality VBR mode is between the ones of the neighboring sample rates of the table.
+            let nominal = params.get_bitrate();
+            match (bitrate, sample_rate) {
+                (OggVorbisBitrateStrategy::QualityVbr(_), 32000) => assert!((112000..=160000).contains(&nominal), "{nominal}"),
+                (OggVorbisBitrateStrategy::QualityVbr(_), 16000) => assert!((64000..=112000).contains(&nominal), "{nominal}"),
+                (OggVorbisBitrateStrategy::QualityVbr(_), _) => assert_eq!(nominal, 160000),
+                _ => assert_eq!(nominal, 64000),
+            }
+
+            let spec = test_spec(2, sample_rate);
+            let path = dir.path().join(format!("vorbis_{sample_rate}_{nominal}.wav"));
+            let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::OggVorbis(params), FileSizeOption::NeverLargerThan4GB).unwrap();
+            let stereos: Vec<(i16, i16)> = (0..sample_rate).map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let l = (t * 440.0 * std::f64::consts::TAU).sin() * 8000.0;
+                let r = (t * 1234.5 * std::f64::consts::TAU).sin() * 6000.0 + ((i * 7919) % 1024) as f64;
+                (l as i16, r as i16)
+            }).collect();
+            wavewriter.write_stereos(&stereos).unwrap();
+            wavewriter.finalize().unwrap();
+
+            let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+            let byte_rate = wavereader.get_fmt__chunk().byte_rate;
+            assert_eq!(wavereader.get_fmt__chunk().sample_rate, sample_rate);
+            assert!((2000..=60000).contains(&byte_rate), "{sample_rate} Hz {bitrate:?}: byte rate {byte_rate}");
+        }
+    }
+}
+
+#[test]
+pub fn test_scan_levels() {
+    let (dir, path) = temp_wav("levels.wav");
+    let spec = test_spec(2, 48000);
+
+    // The 1 kHz sine at -23 dBFS on both channels is -23 LUFS, the right channel has a spike.
+    let amplitude = 10f64.powf(-23.0 / 20.0);
+    let mut stereos: Vec<(f32, f32)> = (0..48000 * 5).map(|i| {
+        let sample = ((i as f64 / 48.0 * std::f64::consts::TAU).sin() * amplitude) as f32;
+        (sample, sample)
+    }).collect();
+    stereos[123456].1 = 0.5;
+    let mut wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+    let stats = wavereader.scan_levels().unwrap();
+    assert_eq!(stats.num_frames, stereos.len() as u64);
+    assert_eq!((stats.peak_frame, stats.peak_channel), (123456, 1));
+    assert!((stats.peak_dbfs[0] + 23.0).abs() < 0.1, "{:?}", stats.peak_dbfs);
+    assert!((stats.peak_dbfs[1] - to_dbfs(0.5)).abs() < 0.1, "{:?}", stats.peak_dbfs);
+    assert!((stats.rms_dbfs[0] + 26.01).abs() < 0.1, "{:?}", stats.rms_dbfs);
+    let loudness = stats.integrated_loudness.unwrap();
+    assert!((loudness + 23.0).abs() < 0.2, "{loudness}");
+
+    // Scan from the start frame, the start frame stays.
+    wavereader.seek_to_frame(48000).unwrap();
+    let stats = wavereader.scan_levels().unwrap();
+    assert_eq!(stats.num_frames, stereos.len() as u64 - 48000);
+    assert_eq!(stats.peak_frame, 123456);
+    assert_eq!(wavereader.get_start_frame(), 48000);
+    assert_eq!(wavereader.scan_levels().unwrap(), stats);
+
+    // The silence has no level.
+    let path = dir.path().join("silence.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&[(0i16, 0i16); 48000]).unwrap();
+    wavewriter.finalize().unwrap();
+    let stats = WaveReader::open(path.to_str().unwrap()).unwrap().scan_levels().unwrap();
+    assert_eq!(stats.peak_dbfs, [f64::NEG_INFINITY, f64::NEG_INFINITY]);
+    assert_eq!(stats.integrated_loudness, None);
+}
+
+#[test]
+pub fn test_read_frames_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos: Vec<(i16, i16)> = (0..30000).map(|i| (((i * 7) % 20000) as i16 - 10000, ((i * 13) % 30000) as i16 - 15000)).collect();
+    for (i, data_format) in [DataFormat::Pcm, DataFormat::Adpcm(AdpcmSubFormat::Ima)].into_iter().enumerate() {
+        let path = dir.path().join(format!("range_{i}.wav"));
+        let mut wavereader = write_and_reopen(&path, spec, data_format, |wavewriter| wavewriter.write_stereos(&stereos).unwrap());
+        let expected: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        let total = expected.len() as u64;
+
+        // Forward, backward, across the blocks, repeated, and ignoring the start frame.
+        wavereader.seek_to_frame(1000).unwrap();
+        for (start, count) in [(0u64, 100usize), (5000, 3000), (4000, 200), (20000, 9000), (5000, 3000), (100, 1)] {
+            let frames = wavereader.read_frames_range::<i16>(start, count).unwrap();
+            assert_eq!(frames, expected[start as usize..start as usize + count], "{start}, {count}");
+        }
+
+        // Fewer frames only at the end, never panic beyond it.
+        assert_eq!(wavereader.read_frames_range::<i16>(total - 10, 100).unwrap(), expected[total as usize - 10..]);
+        assert!(wavereader.read_frames_range::<i16>(total, 100).unwrap().is_empty());
+        assert!(wavereader.read_frames_range::<i16>(u64::MAX, 100).unwrap().is_empty());
+        assert!(wavereader.read_frames_range::<i16>(0, 0).unwrap().is_empty());
+
+        // Another sample type drops the cache.
+        let floats = wavereader.read_frames_range::<f32>(5000, 10).unwrap();
+        assert_eq!(floats.len(), 10);
+        assert_eq!(wavereader.read_frames_range::<i16>(5000, 10).unwrap(), expected[5000..5010]);
+    }
+}
+
+#[test]
+pub fn test_g711_tables() {
+    use std::io::{Cursor, Read};
+    let dir = tempfile::tempdir().unwrap();
+    let spec = Spec {
+        bits_per_sample: 8,
+        ..test_spec(1, 8000)
+    };
+    for (format_tag, data_format) in [(6u16, DataFormat::PcmALaw), (7u16, DataFormat::PcmMuLaw)] {
+        // Every code once, in a hand-made WAV file.
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(4u32 + 8 + 16 + 8 + 256).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        for field in [format_tag, 1] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        for field in [1u16, 8] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&256u32.to_le_bytes());
+        bytes.extend(0..=255u8);
+
+        let mut wavereader = WaveReader::from_reader(Cursor::new(bytes.clone())).unwrap();
+        let table: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+        assert_eq!(table.len(), 256);
+        let mut wavereader = WaveReader::from_reader(Cursor::new(bytes)).unwrap();
+        let stereos: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert!(stereos.iter().zip(table.iter()).all(|(s, m)| *s == (*m, *m)));
+
+        // The G.711 reference values, the sign bit mirrors the value.
+        let reference: &[(u8, i16)] = if format_tag == 6 {
+            &[(0xD5, 8), (0x55, -8), (0xD4, 24), (0xC5, 264), (0xAA, 32256), (0x2A, -32256)]
+        } else {
+            &[(0xFF, 0), (0x7F, 0), (0xFE, 8), (0xEF, 132), (0x6F, -132), (0x80, 32124), (0x00, -32124)]
+        };
+        for (code, value) in reference.iter() {
+            assert_eq!(table[*code as usize], *value, "{format_tag}: {code:02X}");
+        }
+        for code in 0..128usize {
+            assert_eq!(table[code], -table[code | 0x80], "{format_tag}: {code:02X}");
+        }
+        let mut sorted = table.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), if format_tag == 6 { 256 } else { 255 });
+
+        // Encoding the decoded values gives back the codes, the μ-law negative zero becomes the positive zero.
+        let path = dir.path().join(format!("g711_{format_tag}.wav"));
+        let wavereader = write_and_reopen(&path, spec, data_format, |wavewriter| wavewriter.write_mono_channel(&table).unwrap());
+        let mut codes = Vec::<u8>::new();
+        wavereader.open_raw_data().unwrap().read_to_end(&mut codes).unwrap();
+        let expected: Vec<u8> = (0..=255u8).map(|code| if format_tag == 7 && code == 0x7F { 0xFF } else { code }).collect();
+        assert_eq!(codes, expected);
+        let decoded: Vec<i16> = WaveReader::open(path.to_str().unwrap()).unwrap().mono_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, table);
+    }
+}
+
+#[test]
+pub fn test_transfer_audio_with_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("tone_96k.wav");
+    let src_spec = Spec {
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+        ..test_spec(1, 96000)
+    };
+    let dst_spec = Spec {
+        sample_rate: 22050,
+        ..src_spec
+    };
+
+    // The full-scale 20 kHz tone is far beyond the Nyquist frequency of 22.05 kHz, nothing of it should be left.
+    let monos: Vec<f32> = (0..96000 * 2).map(|i| (i as f64 * 20000.0 / 96000.0 * std::f64::consts::TAU).sin() as f32).collect();
+    let mut wavereader = write_and_reopen(&src_path, src_spec, DataFormat::Pcm, |wavewriter| wavewriter.write_mono_channel(&monos).unwrap());
+    let dst_path = dir.path().join("tone_22k_bad.wav");
+    let mut wavewriter = WaveWriter::create(&dst_path, dst_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    let options = TransferOptions {
+        fft_size: Some(1001),
+        ..Default::default()
+    };
+    match transfer_audio_with_options(&mut wavereader, &mut wavewriter, &options).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    for (i, quality) in [ResampleQuality::Fast, ResampleQuality::Default, ResampleQuality::Best].into_iter().enumerate() {
+        let dst_path = dir.path().join(format!("tone_22k_{i}.wav"));
+        let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+        let mut wavewriter = WaveWriter::create(&dst_path, dst_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        let options = TransferOptions {
+            quality,
+            ..Default::default()
+        };
+        transfer_audio_with_options(&mut wavereader, &mut wavewriter, &options).unwrap();
+        wavewriter.finalize().unwrap();
+
+        let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+        let resampled: Vec<f32> = wavereader.mono_iter::<f32>().unwrap().collect();
+        assert!((resampled.len() as i64 - 22050 * 2).abs() < 64, "{quality:?}: {}", resampled.len());
+        if quality == ResampleQuality::Best {
+            let rms = (resampled.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>() / resampled.len() as f64).sqrt();
+            assert!(to_dbfs(rms) < -40.0, "The aliased energy is {} dBFS", to_dbfs(rms));
+        }
+    }
+}
+
+#[test]
+pub fn test_finalize_errors() {
+    use std::{cell::Cell, io::{self, Cursor, Seek, SeekFrom, Write}, rc::Rc};
+
+    // A sink that fails when it used up its budget of bytes.
+    #[derive(Debug)]
+    struct FailingWriter {
+        cursor: Cursor<Vec<u8>>,
+        budget: Rc<Cell<usize>>,
+    }
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.budget.get() {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "The sink is full"));
+            }
+            self.budget.set(self.budget.get() - buf.len());
+            self.cursor.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    impl Seek for FailingWriter {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.cursor.seek(pos)
+        }
+    }
+
+    let spec = test_spec(2, 44100);
+    let stereos = ramp_stereos(1000);
+    for finalize in [true, false] {
+        let budget = Rc::new(Cell::new(usize::MAX));
+        let writer = FailingWriter {
+            cursor: Cursor::new(Vec::new()),
+            budget: budget.clone(),
+        };
+        let mut wavewriter = WaveWriter::from(Box::new(writer), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_stereos(&stereos).unwrap();
+        assert!(!wavewriter.is_finalized());
+
+        // Updating the header can't be done anymore.
+        budget.set(0);
+        if finalize {
+            match wavewriter.finalize().unwrap_err() {
+                errors::AudioWriteError::IOError(_) => (),
+                other => panic!("Unexpected error: {other}"),
+            }
+        } else {
+            // Dropping it doesn't panic.
+            drop(wavewriter);
+        }
+    }
+}
+
+#[test]
+pub fn test_opus_multistream() {
+    if !cfg!(feature = "opus") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+
+    // 5.1 goes with the surround mapping family, front-left front-right front-center LFE goes with the family 255.
+    for (channels, channel_mask, mapping_family) in [(6u16, 0x3Fu32, 1u8), (8, 0x63F, 1), (4, 0x0F, 255)] {
+        let spec = Spec {
+            channel_mask: Some(channel_mask),
+            ..test_spec(channels, 48000)
+        };
+
+        // Each channel has its own tone, so the channels can be told apart after decoding.
+        // The tones are low enough for the LFE channel, and have whole cycles in the range to compare.
+        let num_frames = 48000usize;
+        let tone = |channel: usize, i: usize| ((i as f64 * (channel + 1) as f64 * 20.0 / 48000.0 * std::f64::consts::TAU).sin() * 0.3) as f32;
+        let frames: Vec<Vec<f32>> = (0..num_frames).map(|i| (0..channels as usize).map(|c| tone(c, i)).collect()).collect();
+        let path = dir.path().join(format!("opus_{channels}ch.wav"));
+        let path = path.to_str().unwrap();
+        let options = OpusEncoderOptions {
+            bitrate: OpusBitrate::Bits(256000),
+            ..OpusEncoderOptions::default()
+        };
+        let mut wavereader = write_and_reopen(path, spec, DataFormat::Opus(options), |wavewriter| wavewriter.write_frames(&frames).unwrap());
+        match wavereader.get_fmt__chunk().extension {
+            Some(chunks::ext::FmtExtension { data: chunks::ext::ExtensionData::Opus(opus_data), .. }) => {
+                assert_eq!(opus_data.mapping_family, mapping_family);
+                assert_eq!(opus_data.streams as u16 + opus_data.coupled_streams as u16, channels);
+            }
+            ref other => panic!("Expected the Opus extension data, got {other:?}"),
+        }
+        let decoded: Vec<Vec<f32>> = wavereader.frame_iter::<f32>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames);
+
+        // Every channel is still in its place.
+        let range = 4800..num_frames - 4800;
+        for c in 0..channels as usize {
+            for r in 0..channels as usize {
+                let dot: f64 = range.clone().map(|i| decoded[i][c] as f64 * tone(r, i) as f64).sum();
+                let norm_decoded: f64 = range.clone().map(|i| (decoded[i][c] as f64).powi(2)).sum();
+                let norm_tone: f64 = range.clone().map(|i| (tone(r, i) as f64).powi(2)).sum();
+                let correlation = dot / (norm_decoded * norm_tone).sqrt();
+                if c == r {
+                    assert!(correlation > 0.9, "{channels} channels: channel {c} correlation {correlation}");
+                } else {
+                    assert!(correlation.abs() < 0.3, "{channels} channels: channel {c} vs {r} correlation {correlation}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_wavl_slnt() {
+    use io_utils::CursorVecU8;
+    let dir = tempfile::tempdir().unwrap();
+
+    // A 16-bit mono WAV file whose audio data is the `LIST wavl` chunk with the data/slnt/data segments.
+    let build = |silent_frames: u32| -> Vec<u8> {
+        let chunk = |flag: &[u8; 4], data: &[u8]| -> Vec<u8> {
+            let mut bytes = flag.to_vec();
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+            if data.len() & 1 != 0 {
+                bytes.push(0);
+            }
+            bytes
+        };
+        let samples = |range: std::ops::Range<i16>| -> Vec<u8> { range.flat_map(|s| (s * 100).to_le_bytes()).collect() };
+        let mut fmt = Vec::<u8>::new();
+        for field in [1u16, 1] {
+            fmt.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&16000u32.to_le_bytes());
+        for field in [2u16, 16] {
+            fmt.extend_from_slice(&field.to_le_bytes());
+        }
+        let mut wavl = b"wavl".to_vec();
+        wavl.extend(chunk(b"data", &samples(1..101)));
+        wavl.extend(chunk(b"slnt", &silent_frames.to_le_bytes()));
+        wavl.extend(chunk(b"data", &samples(-30..0)));
+        let mut riff = b"WAVE".to_vec();
+        riff.extend(chunk(b"fmt ", &fmt));
+        riff.extend(chunk(b"LIST", &wavl));
+        chunk(b"RIFF", &riff)
+    };
+    let expected: Vec<i16> = (1..101).map(|s| s * 100).chain(vec![0; 50]).chain((-30..0).map(|s| s * 100)).collect();
+
+    // From a file and from a reader, both decode to the concatenated waveform.
+    let path = dir.path().join("wavl.wav");
+    std::fs::write(&path, build(50)).unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(180));
+    assert_eq!(wavereader.mono_iter::<i16>().unwrap().collect::<Vec<i16>>(), expected);
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(build(50))))).unwrap();
+    assert_eq!(wavereader.mono_iter::<i16>().unwrap().collect::<Vec<i16>>(), expected);
+
+    // The long silence takes no room, seek over it to the last segment.
+    let path = dir.path().join("wavl_long.wav");
+    std::fs::write(&path, build(100_000_000)).unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let total_frames = wavereader.total_frames().unwrap();
+    assert_eq!(total_frames, 100 + 100_000_000 + 30);
+    wavereader.seek_to_frame(total_frames - 40).unwrap();
+    let tail: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    assert_eq!(tail, expected[expected.len() - 40..]);
+}
+
+#[cfg(feature = "async")]
+#[test]
+pub fn test_async_wave_reader_writer() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let spec = test_spec(2, 44100);
+        let frames: Vec<Vec<i16>> = (0..20000).map(|i| vec![(i % 1000) as i16, -((i % 777) as i16)]).collect();
+
+        // Write into the memory through the async writer, in small pieces to go through the channel many times.
+        let (output, mut input) = tokio::io::duplex(1 << 24);
+        let mut wavewriter = AsyncWaveWriter::<i16>::new(output, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).await.unwrap();
+        for chunk in frames.chunks(1234) {
+            wavewriter.write_frames(chunk).await.unwrap();
+        }
+        let report = wavewriter.finalize().await.unwrap();
+        let mut bytes = Vec::<u8>::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut input, &mut bytes).await.unwrap();
+        assert_eq!(bytes.len() as u64, report.riff_size + 8);
+
+        // Read it back through the async reader.
+        let mut wavereader = AsyncWaveReader::<i16>::from_async_read(std::io::Cursor::new(bytes)).await.unwrap();
+        assert_eq!(wavereader.total_frames(), Some(frames.len() as u64));
+        let mut decoded = Vec::<Vec<i16>>::new();
+        loop {
+            let got = wavereader.next_frames(5000).await.unwrap();
+            if got.is_empty() {
+                break;
+            }
+            decoded.extend(got);
+        }
+        assert_eq!(decoded, frames);
+
+        // The errors come out of the async functions.
+        let (output, _input) = tokio::io::duplex(64);
+        let bad_spec = Spec { channels: 0, ..spec };
+        assert!(AsyncWaveWriter::<i16>::new(output, bad_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).await.is_err());
+        assert!(AsyncWaveReader::<i16>::from_async_read(std::io::Cursor::new(b"not a WAV file".to_vec())).await.is_err());
+    });
+}
+
+#[test]
+pub fn test_clip_detection() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+
+    // Disabled by default, nothing is counted.
+    let path = dir.path().join("clip_16.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_frames(&[vec![1.5f32, 0.0]]).unwrap();
+    assert_eq!(wavewriter.clip_stats(), ClipStats::default());
+
+    // Every write function contributes.
+    wavewriter.enable_clip_detection(true);
+    wavewriter.write_frames(&[vec![1.25f32, -0.5], vec![0.25, -1.0]]).unwrap();
+    wavewriter.write_interleaved_samples(&[0.5f64, -1.125]).unwrap();
+    wavewriter.write_frame(&[1.0f32, 1.0625]).unwrap();
+    wavewriter.write_stereos(&[(-1.5f32, 0.0)]).unwrap();
+    wavewriter.write_mono(2.0f64).unwrap();
+    wavewriter.write_monos(&[vec![0.0f32, 1.125], vec![0.0, 0.0]]).unwrap();
+
+    // The integer samples never clip.
+    wavewriter.write_frames(&[vec![i32::MAX, i32::MIN]]).unwrap();
+    let stats = wavewriter.clip_stats();
+    assert_eq!(stats.clipped_samples, 6);
+    assert_eq!(stats.max_overshoot, 1.0);
+
+    // Disabling it keeps the stats.
+    wavewriter.enable_clip_detection(false);
+    wavewriter.write_frames(&[vec![3.0f32, 3.0]]).unwrap();
+    assert_eq!(wavewriter.clip_stats(), stats);
+    wavewriter.finalize().unwrap();
+
+    // The float PCM keeps the samples as is.
+    let spec = Spec {
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+        ..spec
+    };
+    let path = dir.path().join("clip_f32.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.enable_clip_detection(true);
+    wavewriter.write_frames(&[vec![1.5f32, -2.0]]).unwrap();
+    assert_eq!(wavewriter.clip_stats().clipped_samples, 0);
+    wavewriter.finalize().unwrap();
+}
+
+#[test]
+pub fn test_write_buffer() {
+    use io_utils::CursorVecU8;
+    let spec = test_spec(2, 44100);
+    let frames: Vec<Vec<i16>> = (0..20000).map(|i| vec![(i % 3000) as i16, -((i % 1234) as i16)]).collect();
+
+    // Write frame by frame, the buffer only changes how the bytes go to the writer.
+    let write = |data_format: DataFormat, file_size_option: FileSizeOption, buffer_size: usize| -> Vec<u8> {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, data_format, file_size_option).unwrap();
+        wavewriter.set_write_buffer_size(buffer_size).unwrap();
+        assert_eq!(wavewriter.get_write_buffer_size(), buffer_size);
+        wavewriter.set_metadata_tag("ARTIST", "Buffered").unwrap();
+        for (i, frame) in frames.iter().enumerate() {
+            wavewriter.write_frame(frame).unwrap();
+            if i == frames.len() / 2 {
+                wavewriter.flush().unwrap();
+                wavewriter.set_write_buffer_size(buffer_size / 2).unwrap();
+            }
+        }
+        wavewriter.into_inner().unwrap();
+        cursor.into_inner()
+    };
+    for data_format in [DataFormat::Pcm, DataFormat::Adpcm(AdpcmSubFormat::Ms)] {
+        let unbuffered = write(data_format.clone(), FileSizeOption::NeverLargerThan4GB, 0);
+        for buffer_size in [1, 7, 4096, 1 << 20] {
+            assert_eq!(write(data_format.clone(), FileSizeOption::NeverLargerThan4GB, buffer_size), unbuffered, "{data_format}, {buffer_size}");
+        }
+        let unbuffered = write(data_format.clone(), FileSizeOption::ForceUse4GBFormat, 0);
+        assert_eq!(write(data_format.clone(), FileSizeOption::ForceUse4GBFormat, 65536), unbuffered, "{data_format}");
+    }
+}
+
+#[test]
+pub fn test_write_stats() {
+    use io_utils::CursorVecU8;
+    use std::sync::{Arc, Mutex};
+    let spec = test_spec(2, 44100);
+    let frames: Vec<Vec<i16>> = (0..1000).map(|i| vec![((i as f32 * 0.05).sin() * 10000.0) as i16, (i % 500) as i16]).collect();
+    for (data_format, bits_per_frame) in [(DataFormat::Pcm, 32.0), (DataFormat::PcmALaw, 16.0), (DataFormat::Adpcm(AdpcmSubFormat::Ima), 8.0)] {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, data_format.clone(), FileSizeOption::NeverLargerThan4GB).unwrap();
+        assert_eq!(wavewriter.stats().unwrap(), WriteStats::default());
+        let reports = Arc::new(Mutex::new(Vec::<WriteStats>::new()));
+        let reports_ = reports.clone();
+        wavewriter.set_progress_callback(4096, Box::new(move |stats| reports_.lock().unwrap().push(*stats)));
+
+        // The callback is called after the write that passes every 4096 frames.
+        for _ in 0..10 {
+            wavewriter.write_frames(&frames).unwrap();
+        }
+        let reports = reports.lock().unwrap().clone();
+        assert_eq!(reports.iter().map(|stats| stats.frames_written).collect::<Vec<_>>(), [5000, 9000], "{data_format}");
+
+        let stats = wavewriter.stats().unwrap();
+        assert_eq!(stats.frames_written, 10000, "{data_format}");
+        assert!(stats.bytes_written > reports[1].bytes_written, "{data_format}");
+        let expected_bitrate = bits_per_frame * 44100.0;
+        assert!((stats.current_bitrate - expected_bitrate).abs() < expected_bitrate * 0.05, "{data_format}: {}", stats.current_bitrate);
+        if data_format == DataFormat::Pcm {
+            assert_eq!(stats.bytes_written, 40000);
+        }
+        wavewriter.clear_progress_callback();
+        wavewriter.write_frames(&frames).unwrap();
+        wavewriter.into_inner().unwrap();
+    }
+}
+
+#[test]
+pub fn test_unpadded_odd_chunks() {
+    use io_utils::CursorVecU8;
+    let spec = Spec {
+        bits_per_sample: 8,
+        sample_format: SampleFormat::UInt,
+        ..test_spec(1, 8000)
+    };
+
+    // The writer pads the odd-sized chunks, every chunk starts at an even position.
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_metadata_tag("INAM", "Odd").unwrap();
+    wavewriter.write_mono_channel(&[0x10u8, 0x20, 0x30]).unwrap();
+    let report = wavewriter.finalize().unwrap();
+    assert!(report.chunks.iter().all(|info| info.offset & 1 == 0), "{:?}", report.chunks);
+    let bytes = cursor.into_inner();
+    assert_eq!(bytes.len() & 1, 0);
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+    assert!(wavereader.warnings().is_empty());
+
+    // The chunks without the pad bytes are found right after the odd-sized chunks.
+    let mut fmt_ = Vec::<u8>::new();
+    for field in [1u16, 1] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [8000u32, 8000] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [1u16, 8] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    let chunks: [(&[u8; 4], Vec<u8>); 4] = [
+        (b"fmt ", fmt_),
+        (b"odd ", b"abc".to_vec()),
+        (b"data", vec![0x10, 0x20, 0x30]),
+        (b"LIST", [&b"INFO"[..], b"INAM", &4u32.to_le_bytes(), b"Odd\0"].concat()),
+    ];
+    let mut body = b"WAVE".to_vec();
+    for (flag, data) in chunks.iter() {
+        body.extend_from_slice(*flag);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+    }
+    let bytes = [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat();
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+    let flags: Vec<[u8; 4]> = wavereader.chunk_map().iter().map(|info| info.flag).collect();
+    assert_eq!(flags, [*b"fmt ", *b"odd ", *b"data", *b"LIST"]);
+    assert_eq!(wavereader.warnings().iter().map(|warning| (warning.kind, warning.offset)).collect::<Vec<_>>(), [(errors::DecodeWarningKind::ChunkLayout, 12 + 24 + 11), (errors::DecodeWarningKind::ChunkLayout, 12 + 24 + 11 + 11)]);
+    assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), "Odd");
+    let decoded: Vec<u8> = wavereader.mono_iter::<u8>().unwrap().collect();
+    assert_eq!(decoded, [0x10, 0x20, 0x30]);
+}
+
+#[test]
+pub fn test_validate_and_repair() {
+    use io_utils::CursorVecU8;
+    let riff = |chunks: &[(&[u8; 4], Vec<u8>)]| -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        for (flag, data) in chunks.iter() {
+            body.extend_from_slice(*flag);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() & 1 == 1 {
+                body.push(0);
+            }
+        }
+        [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat()
+    };
+    // 16-bit stereo at 8000 Hz, `channel_mask` makes it `WAVEFORMATEXTENSIBLE`.
+    let fmt_ = |byte_rate: u32, block_align: u16, channel_mask: Option<u32>| -> Vec<u8> {
+        let mut fmt_ = Vec::<u8>::new();
+        let format_tag = if channel_mask.is_some() { 0xFFFEu16 } else { 1 };
+        for field in [format_tag, 2] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [8000u32, byte_rate] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [block_align, 16] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        if let Some(channel_mask) = channel_mask {
+            for field in [22u16, 16] {
+                fmt_.extend_from_slice(&field.to_le_bytes());
+            }
+            fmt_.extend_from_slice(&channel_mask.to_le_bytes());
+            fmt_.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71]);
+        }
+        fmt_
+    };
+    let cue_ = |positions: &[u32]| -> Vec<u8> {
+        let mut cue_ = (positions.len() as u32).to_le_bytes().to_vec();
+        for (i, position) in positions.iter().enumerate() {
+            cue_.extend_from_slice(&(i as u32 + 1).to_le_bytes());
+            cue_.extend_from_slice(&position.to_le_bytes());
+            cue_.extend_from_slice(b"data");
+            cue_.extend_from_slice(&[0u8; 8]);
+            cue_.extend_from_slice(&position.to_le_bytes());
+        }
+        cue_
+    };
+    // The `bext` chunk of version 0 with the loudness value of version 2.
+    let mut bext = vec![0u8; 602];
+    bext[412..414].copy_from_slice(&(-2300i16).to_le_bytes());
+    let data: Vec<u8> = (0..32u8).collect();
+    let validate = |bytes: Vec<u8>, read_options: ReadOptions| -> ValidationReport {
+        WaveReader::new_with_options(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes))), read_options).unwrap().validate()
+    };
+
+    // The file written by the `WaveWriter` has nothing to report.
+    let spec = test_spec(2, 8000);
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_frames(&[vec![0i16, 1], vec![2, 3], vec![4, 5]]).unwrap();
+    wavewriter.finalize().unwrap();
+    let report = validate(cursor.into_inner(), ReadOptions::default());
+    assert!(report.issues.is_empty(), "{report}");
+    assert!(validate(riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"data", data.clone())]), ReadOptions::default()).issues.is_empty());
+
+    // Each defect is reported as its kind, at the offset of the field.
+    let report = validate(riff(&[(b"fmt ", fmt_(16000, 4, None)), (b"data", data.clone())]), ReadOptions::default());
+    let issues: Vec<_> = report.find(ValidationIssueKind::ByteRate).collect();
+    assert_eq!((issues.len(), issues[0].severity, issues[0].offset), (1, ValidationSeverity::Warning, 12 + 8 + 8));
+    assert!(!report.is_clean() && !report.has_errors());
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 2, None)), (b"data", data.clone())]), ReadOptions::default());
+    let issues: Vec<_> = report.find(ValidationIssueKind::BlockAlign).collect();
+    assert_eq!((issues.len(), issues[0].severity, issues[0].offset), (1, ValidationSeverity::Error, 12 + 8 + 12));
+    assert!(report.has_errors());
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 4, Some(0x7))), (b"data", data.clone())]), ReadOptions::default());
+    assert_eq!(report.find(ValidationIssueKind::ChannelMask).count(), 1);
+    assert!(validate(riff(&[(b"fmt ", fmt_(32000, 4, Some(0x3))), (b"data", data.clone())]), ReadOptions::default()).is_clean());
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"fact", 5u32.to_le_bytes().to_vec()), (b"data", data.clone())]), ReadOptions::default());
+    assert_eq!(report.find(ValidationIssueKind::SizeMismatch).count(), 1, "{report}");
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"zzzz", vec![1, 2, 3]), (b"data", data.clone())]), ReadOptions::default());
+    let issues: Vec<_> = report.find(ValidationIssueKind::UnknownChunk).collect();
+    assert_eq!((issues.len(), issues[0].severity, issues[0].offset), (1, ValidationSeverity::Info, 12 + 8 + 16));
+    assert!(report.is_clean());
+
+    let mut truncated = riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"data", data.clone())]);
+    truncated.truncate(truncated.len() - 8);
+    let report = validate(truncated, ReadOptions { allow_truncated: true, ..Default::default() });
+    assert_eq!(report.find(ValidationIssueKind::TruncatedData).count(), 1, "{report}");
+    assert!(report.has_errors());
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"data", data.clone()), (b"cue ", cue_(&[6, 2, 100]))]), ReadOptions::default());
+    let issues: Vec<_> = report.find(ValidationIssueKind::CuePoints).map(|issue| issue.severity).collect();
+    assert_eq!(issues, [ValidationSeverity::Warning, ValidationSeverity::Error]);
+
+    let report = validate(riff(&[(b"fmt ", fmt_(32000, 4, None)), (b"bext", bext.clone()), (b"data", data.clone())]), ReadOptions::default());
+    let issues: Vec<_> = report.find(ValidationIssueKind::Bext).collect();
+    assert_eq!((issues.len(), issues[0].offset), (1, 12 + 8 + 16 + 8 + 412));
+
+    // All of the defects at once are repaired by the pass-through remux, the audio stays the same.
+    let broken = riff(&[
+        (b"fmt ", fmt_(16000, 2, Some(0x7))),
+        (b"fact", 5u32.to_le_bytes().to_vec()),
+        (b"bext", bext.clone()),
+        (b"zzzz", vec![1, 2, 3]),
+        (b"data", data.clone()),
+        (b"cue ", cue_(&[6, 2])),
+    ]);
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(broken)))).unwrap();
+    assert!(wavereader.validate().issues.len() >= 6);
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), wavereader.spec(), DataFormat::PassThrough, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.repair_from_reader(&mut wavereader).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+    let report = wavereader.validate();
+    assert!(report.is_clean(), "{report}");
+    assert_eq!((wavereader.get_fmt__chunk().block_align, wavereader.get_fmt__chunk().byte_rate), (4, 32000));
+    let positions: Vec<u64> = wavereader.get_cue__chunk().as_ref().unwrap().cue_points.iter().map(|point| point.position).collect();
+    assert_eq!(positions, [2, 6]);
+    assert_eq!(wavereader.get_bext_chunk().unwrap().version, 2);
+    let frames: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(frames.len(), 8);
+    assert_eq!(frames[1], (i16::from_le_bytes([4, 5]), i16::from_le_bytes([6, 7])));
+}
+
+#[test]
+pub fn test_channel_layout() {
+    use io_utils::CursorVecU8;
+    use errors::AudioWriteError;
+    assert_eq!(ChannelLayout::stereo().mask(), 0x3);
+    assert_eq!(ChannelLayout::surround_5_1().mask(), 0x3F);
+    assert_eq!(ChannelLayout::surround_5_1_side().mask(), 0x60F);
+    assert_eq!(ChannelLayout::surround_7_1().mask(), 0x63F);
+    assert_eq!(ChannelLayout::surround_7_1().num_channels(), 8);
+    assert_eq!(ChannelLayout::from_speakers(&[Speaker::FrontRight, Speaker::LowFrequency, Speaker::FrontLeft]), ChannelLayout::surround_2_1());
+    assert_eq!(ChannelLayout::from(0x60Fu32), ChannelLayout::surround_5_1_side());
+    assert_eq!(u32::from(ChannelLayout::quad()), 0x33);
+    assert_eq!(
+        ChannelLayout::surround_5_1_side().speakers(),
+        [Speaker::FrontLeft, Speaker::FrontRight, Speaker::FrontCenter, Speaker::LowFrequency, Speaker::SideLeft, Speaker::SideRight]
+    );
+    assert_eq!(ChannelLayout::surround_5_1_side().channel_index_of(Speaker::SideLeft), Some(4));
+    assert_eq!(ChannelLayout::surround_5_1_side().channel_index_of(Speaker::BackLeft), None);
+    assert_eq!(ChannelLayout::surround_7_1().to_string(), "FL+FR+FC+LFE+BL+BR+SL+SR");
+    assert_eq!(ChannelLayout::none().to_string(), "None");
+    assert!(ChannelLayout::none().is_valid_for(16));
+    assert!(!ChannelLayout::surround_5_1().is_valid_for(8));
+
+    let spec = test_spec(0, 48000).with_layout(ChannelLayout::surround_7_1());
+    assert_eq!((spec.channels, spec.channel_mask), (8, Some(0x63F)));
+    assert!(spec.is_channel_mask_valid());
+
+    // The layout goes into the extensible `fmt ` chunk and comes back from the reader.
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_frames(&[vec![0i16; 8], vec![1i16; 8]]).unwrap();
+    wavewriter.finalize().unwrap();
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+    assert_eq!(wavereader.channel_layout(), Some(ChannelLayout::surround_7_1()));
+    assert_eq!(wavereader.get_fmt__chunk().format_tag, format_tags::FORMAT_TAG_EXTENSIBLE);
+
+    // The 5.1 layout doesn't fit 8 channels.
+    let spec = Spec { channel_mask: Some(ChannelLayout::surround_5_1().mask()), ..spec };
+    assert!(!spec.is_channel_mask_valid());
+    let mut cursor = CursorVecU8::default();
+    match WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap_err() {
+        AudioWriteError::InvalidArguments(message) => assert!(message.contains("FL+FR+FC+LFE+BL+BR"), "{message}"),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // Without the channel mask, the plain `fmt ` chunk has no layout.
+    let spec = Spec { channels: 2, channel_mask: None, ..spec };
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_frames(&[vec![0i16; 2]]).unwrap();
+    wavewriter.finalize().unwrap();
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+    assert_eq!(wavereader.channel_layout(), None);
+}
+
+#[test]
+pub fn test_acid_chunk_authoring() {
+    use io_utils::CursorVecU8;
+    use chunks::AcidChunk;
+    let spec = Spec {
+        bits_per_sample: 8,
+        sample_format: SampleFormat::UInt,
+        ..test_spec(1, 8000)
+    };
+    let write = |acid: &AcidChunk, num_frames: usize, source: Option<&WaveReader>| -> (Vec<u8>, FinalizeReport) {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.set_acid_chunk(acid);
+        if let Some(source) = source {
+            wavewriter.inherit_metadata_from_reader(source, false);
+        }
+        wavewriter.write_mono_channel(&vec![0x80u8; num_frames]).unwrap();
+        let report = wavewriter.finalize().unwrap();
+        (cursor.into_inner(), report)
+    };
+
+    // 8 beats at 120 BPM are 4 seconds.
+    let acid = AcidChunk::for_loop(60, 8, 120.0);
+    assert!(!acid.is_one_shot());
+    assert_eq!(acid.beats_duration(), Some(std::time::Duration::from_secs(4)));
+    let (bytes, report) = write(&acid, 32000, None);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    let acid_info = report.chunks.iter().find(|info| &info.flag == b"acid").unwrap();
+    assert_eq!(acid_info.length, 24);
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+    let read_back = wavereader.get_acid_chunk().clone().unwrap();
+    assert_eq!(read_back.flags, AcidChunk::FLAG_ROOT_NOTE | AcidChunk::FLAG_STRETCH);
+    assert_eq!((read_back.root_node, read_back.num_beats, read_back.tempo), (60, 8, 120.0));
+    assert_eq!((read_back.meter_numerator, read_back.meter_denominator), (4, 4));
+
+    // The chunk set explicitly wins over the inherited one.
+    let other = AcidChunk::for_loop(48, 4, 60.0);
+    let (bytes, _) = write(&other, 32000, Some(&wavereader));
+    let inherited = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+    assert_eq!(inherited.get_acid_chunk().as_ref().unwrap().root_node, 48);
+
+    // 8 beats at 120 BPM don't fit 1 second of audio, the file is still written.
+    let (bytes, report) = write(&acid, 8000, None);
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].contains("4.000 seconds"), "{}", report.warnings[0]);
+    assert!(WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap().get_acid_chunk().is_some());
+
+    // The one-shot sample has no tempo to check.
+    let one_shot = AcidChunk { flags: AcidChunk::FLAG_ONE_SHOT, ..acid };
+    assert_eq!(one_shot.check_duration(8000, 8000), None);
+}
+
+#[test]
+pub fn test_lazy_id3_chunk() {
+    use std::io::Read;
+    use io_utils::CursorVecU8;
+
+    // An ID3v2.3 tag with a title and a 2 MB picture.
+    let picture: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let frame = |id: &[u8; 4], content: &[u8]| [&id[..], &(content.len() as u32).to_be_bytes(), &[0, 0], content].concat();
+    let frames = [
+        frame(b"TIT2", &[&[0u8][..], b"Lazy Title"].concat()),
+        frame(b"APIC", &[&[0u8][..], b"image/png\0", &[3, 0], &picture].concat()),
+    ].concat();
+    let syncsafe = |size: usize| [(size >> 21) as u8 & 0x7F, (size >> 14) as u8 & 0x7F, (size >> 7) as u8 & 0x7F, size as u8 & 0x7F];
+    let tag = [&b"ID3\x03\x00\x00"[..], &syncsafe(frames.len()), &frames].concat();
+
+    let mut fmt_ = Vec::<u8>::new();
+    for field in [1u16, 1] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [8000u32, 16000] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [2u16, 16] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    let mut body = b"WAVE".to_vec();
+    for (flag, data) in [(b"fmt ", fmt_), (b"data", vec![0u8; 200]), (b"id3 ", tag.clone())] {
+        body.extend_from_slice(flag);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&data);
+        if data.len() & 1 == 1 {
+            body.push(0);
+        }
+    }
+    let bytes = [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat();
+    let (_dir, path) = temp_wav("lazy_id3.wav");
+    std::fs::write(&path, &bytes).unwrap();
+
+    let readers = [
+        WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap(),
+        WaveReader::open(path.to_str().unwrap()).unwrap(),
+    ];
+    for mut wavereader in readers {
+        assert!(wavereader.chunk_map().iter().any(|info| &info.flag == b"id3 "));
+
+        // The raw bytes are streamed out of the chunk, the picture is found without parsing the tag.
+        let mut raw = Vec::<u8>::new();
+        wavereader.id3_raw_reader().unwrap().unwrap().read_to_end(&mut raw).unwrap();
+        assert_eq!(raw, tag);
+        let apic = raw.windows(4).position(|window| window == b"APIC").unwrap();
+        assert_eq!(&raw[apic + 10 + 13..], &picture[..]);
+
+        // The tag is parsed on demand and kept.
+        assert!(wavereader.id3().unwrap().is_some());
+        assert!(wavereader.get_id3__chunk().is_some());
+        if cfg!(feature = "id3") {
+            assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), "Lazy Title");
+        }
+        assert_eq!(wavereader.mono_iter::<i16>().unwrap().count(), 100);
+    }
+
+    // No `id3 ` chunk, nothing to parse.
+    let spec = test_spec(1, 8000);
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&[0i16; 10]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+    assert!(wavereader.id3().unwrap().is_none());
+    assert!(wavereader.id3_raw_reader().unwrap().is_none());
+}
+
+#[test]
+pub fn test_opus_head_interop() {
+    if !cfg!(feature = "opus") {
+        return;
+    }
+    use std::io::Read;
+    use io_utils::CursorVecU8;
+    use chunks::ext::{ExtensionData, FmtExtension};
+    let spec = test_spec(2, 48000);
+
+    // Encode the 20 ms CBR packets, they are repacked the way the other tools lay them out.
+    let num_frames = 48000usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| {
+        let s = ((i as f64 * 0.03).sin() * 8000.0) as i16;
+        (s, s / 2)
+    }).collect();
+    let options = OpusEncoderOptions {
+        bitrate: OpusBitrate::Bits(64000),
+        encode_vbr: false,
+        samples_cache_duration: OpusEncoderSampleDuration::MilliSec20,
+        max_frames_per_write: 16384,
+    };
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Opus(options), FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(cursor.into_inner())))).unwrap();
+    let pre_skip = match wavereader.get_fmt__chunk().extension {
+        Some(FmtExtension { data: ExtensionData::Opus(opus_data), .. }) => opus_data.pre_skip,
+        ref other => panic!("Expected the Opus extension data, got {other:?}"),
+    };
+    let reference: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(reference.len(), num_frames);
+    let mut data = Vec::<u8>::new();
+    wavereader.open_raw_data().unwrap().take(wavereader.get_data_length()).read_to_end(&mut data).unwrap();
+    let mut packets = Vec::<Vec<u8>>::new();
+    let mut offset = 0;
+    while offset + 2 <= data.len() {
+        let size = u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+        packets.push(data[offset + 2..offset + 2 + size].to_vec());
+        offset += 2 + size;
+    }
+    assert!(packets.iter().all(|packet| packet.len() == packets[0].len()));
+
+    // The headers as libopusenc writes them, the pre-skip is at 48 kHz and the input sample rate is only for information.
+    let opus_head = [&b"OpusHead"[..], &[1, 2], &pre_skip.to_le_bytes(), &44100u32.to_le_bytes(), &[0, 0, 0]].concat();
+    let vendor = b"libopus 1.3.1";
+    let comment = b"ENCODER=opusenc";
+    let opus_tags = [&b"OpusTags"[..], &(vendor.len() as u32).to_le_bytes(), vendor, &1u32.to_le_bytes(), &(comment.len() as u32).to_le_bytes(), comment].concat();
+    let prefixed = |packet: &[u8]| [&(packet.len() as u16).to_le_bytes()[..], packet].concat();
+    let prefixed_packets: Vec<u8> = packets.iter().flat_map(|packet| prefixed(packet)).collect();
+    let fmt_ = |block_align: u16, extension: &[u8]| -> Vec<u8> {
+        let mut fmt_ = Vec::<u8>::new();
+        for field in [0x704Fu16, 2] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [48000u32, 8000] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [block_align, 16, extension.len() as u16] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt_.extend_from_slice(extension);
+        fmt_
+    };
+    let riff = |fmt_: Vec<u8>, data: Vec<u8>| -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        for (flag, chunk) in [(b"fmt ", fmt_), (b"fact", (num_frames as u32).to_le_bytes().to_vec()), (b"data", data)] {
+            body.extend_from_slice(flag);
+            body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            body.extend_from_slice(&chunk);
+            if chunk.len() & 1 == 1 {
+                body.push(0);
+            }
+        }
+        [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat()
+    };
+
+    let fixtures = [
+        // The `OpusHead` in the `fmt ` chunk, the packets are size-prefixed as `block_align` doesn't tell the packet size.
+        ("fmt OpusHead", riff(fmt_(0, &opus_head), prefixed_packets.clone())),
+        // The `OpusHead` and the `OpusTags` as the first size-prefixed packets of the `data` chunk.
+        ("data OpusHead prefixed", riff(fmt_(0, &[]), [prefixed(&opus_head), prefixed(&opus_tags), prefixed_packets.clone()].concat())),
+        // The `OpusHead` and the `OpusTags` as is, then the CBR packets of `block_align` bytes.
+        ("data OpusHead CBR", riff(fmt_(packets[0].len() as u16, &[]), [opus_head.clone(), opus_tags.clone(), packets.concat()].concat())),
+    ];
+    for (name, bytes) in fixtures {
+        let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+        if name == "fmt OpusHead" {
+            match &wavereader.get_fmt__chunk().extension {
+                Some(FmtExtension { data: ExtensionData::OpusHead(head), .. }) => {
+                    assert_eq!((head.channels, head.pre_skip, head.input_sample_rate, head.mapping_family), (2, pre_skip, 44100, 0));
+                }
+                other => panic!("Expected the `OpusHead`, got {other:?}"),
+            }
+        }
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), num_frames, "{name}");
+        assert!(decoded == reference, "{name}");
+    }
+}
+
+#[test]
+pub fn test_flac_channel_order() {
+    if !cfg!(feature = "flac") {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+
+    // The FLAC channel order, the 5.1 with the side surrounds, the surrounds that go before the back center, the speakers that don't fit the FLAC channel order.
+    let cases = [(6u16, 0x3Fu32, 1usize), (6, 0x60F, 1), (7, 0x13F, 1), (7, 0x13F, 2), (3, 0x0B, 1), (8, 0x63F, 1)];
+    for (channels, channel_mask, worker_threads) in cases {
+        let spec = Spec {
+            channel_mask: Some(channel_mask),
+            ..test_spec(channels, 44100)
+        };
+
+        // Every channel has its own tone.
+        let frames: Vec<Vec<i16>> = (0..20000)
+            .map(|i| {
+                let t = i as f64 / spec.sample_rate as f64;
+                (0..channels).map(|c| ((t * 220.0 * (c + 1) as f64 * std::f64::consts::TAU).sin() * 8000.0) as i16).collect()
+            })
+            .collect();
+        let path = dir.path().join(format!("flac_{channels}_{channel_mask:x}_{worker_threads}.wav"));
+        let mut data_format = DataFormat::from_name("flac").unwrap();
+        if let DataFormat::Flac(ref mut options) = data_format {
+            options.channels = spec.channels;
+            options.sample_rate = spec.sample_rate;
+            options.bits_per_sample = spec.bits_per_sample as u32;
+            options.worker_threads = worker_threads;
+        }
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_frames(&frames[..10000]).unwrap();
+        let monos: Vec<Vec<i16>> = (0..channels as usize).map(|c| frames[10000..].iter().map(|frame| frame[c]).collect()).collect();
+        wavewriter.write_monos(&monos).unwrap();
+        wavewriter.finalize().unwrap();
+
+        let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert!(decoded == frames, "{channels} channels, mask 0x{channel_mask:X}, {worker_threads} threads");
+    }
+}
+
+#[test]
+pub fn test_append_to_wav() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos = ramp_stereos(1500);
+
+    // The metadata after the `data` chunk is moved to after the appended audio.
+    let path = dir.path().join("append.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_metadata_tag("TITLE", "Appended").unwrap();
+    wavewriter.write_stereos(&stereos[..1000]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavewriter = WaveWriter::append(&path).unwrap();
+    assert_eq!(wavewriter.get_num_frames_written(), 1000);
+    assert_eq!((wavewriter.spec().channels, wavewriter.spec().bits_per_sample), (2, 16));
+    assert_eq!(wavewriter.get_data_format(), DataFormat::Pcm);
+    wavewriter.write_stereos(&stereos[1000..]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert!(wavereader.warnings().is_empty());
+    assert_eq!(wavereader.total_frames(), Some(1500));
+    assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), "Appended");
+    let flags: Vec<[u8; 4]> = wavereader.chunk_map().iter().map(|info| info.flag).collect();
+    assert_eq!(flags, [*b"fmt ", *b"data", *b"LIST"]);
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos);
+
+    // The odd-sized `data` chunk loses its pad byte before the audio continues.
+    let spec = Spec {
+        bits_per_sample: 8,
+        sample_format: SampleFormat::UInt,
+        ..test_spec(1, 8000)
+    };
+    let path = dir.path().join("append_odd.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&[0x10u8, 0x20, 0x30]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavewriter = WaveWriter::append(&path).unwrap();
+    wavewriter.write_mono_channel(&[0x40u8, 0x50]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<u8> = wavereader.mono_iter::<u8>().unwrap().collect();
+    assert_eq!(decoded, [0x10, 0x20, 0x30, 0x40, 0x50]);
+
+    // PCM-aLaw continues the same way, the `fact` chunk is updated.
+    let spec = test_spec(1, 8000);
+    let monos: Vec<i16> = (0..800).map(|i| ((i % 100) * 300 - 15000) as i16).collect();
+    let path = dir.path().join("append_alaw.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::PcmALaw, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&monos[..500]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavewriter = WaveWriter::append(&path).unwrap();
+    assert_eq!(wavewriter.get_data_format(), DataFormat::PcmALaw);
+    assert_eq!(wavewriter.get_num_frames_written(), 500);
+    wavewriter.write_mono_channel(&monos[500..]).unwrap();
+    wavewriter.finalize().unwrap();
+    let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.total_frames(), Some(800));
+
+    // The compressed formats are refused, and the file is untouched.
+    let path = dir.path().join("append_adpcm.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&monos).unwrap();
+    wavewriter.finalize().unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    match WaveWriter::append(&path).unwrap_err() {
+        AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+}
+
+#[test]
+pub fn test_text_codec_round_trip() {
+    use io_utils::CursorVecU8;
+    use utils::StringCodecMaps;
+    if !cfg!(feature = "text_encoding") {
+        return;
+    }
+    // "テスト" in Shift-JIS, as a Japanese DAW writes it.
+    let sjis_title = [0x83u8, 0x65, 0x83, 0x58, 0x83, 0x67, 0x00];
+    let mut fmt_ = Vec::<u8>::new();
+    for field in [1u16, 1] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [8000u32, 8000] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [1u16, 8] {
+        fmt_.extend_from_slice(&field.to_le_bytes());
+    }
+    let chunks: [(&[u8; 4], Vec<u8>); 3] = [
+        (b"fmt ", fmt_),
+        (b"data", vec![0x80, 0x80]),
+        (b"LIST", [&b"INFO"[..], b"INAM", &(sjis_title.len() as u32).to_le_bytes(), &sjis_title, &[0]].concat()),
+    ];
+    let mut body = b"WAVE".to_vec();
+    for (flag, data) in chunks.iter() {
+        body.extend_from_slice(*flag);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+    }
+    let bytes = [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat();
+    let wavereader = WaveReader::new_with_text_codec(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes))), ReadOptions::default(), StringCodecMaps::new()).unwrap();
+    assert!(wavereader.get_metadata_text("INAM").is_some_and(|title| !title.is_empty()));
+
+    // Without transcoding, the same codec writes the same bytes back.
+    let mut cursor = CursorVecU8::default();
+    let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), wavereader.spec(), DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_text_codec(StringCodecMaps::new());
+    wavewriter.inherit_metadata_from_reader(&wavereader, false);
+    wavewriter.write_mono_channel(&[0x80u8, 0x80]).unwrap();
+    wavewriter.finalize().unwrap();
+    let written = cursor.into_inner();
+    let expected = [&b"INAM"[..], &(sjis_title.len() as u32).to_le_bytes(), &sjis_title].concat();
+    assert!(written.windows(expected.len()).any(|window| window == expected), "The Shift-JIS title was transcoded");
+}
+
+#[test]
+pub fn test_float_lossless_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let values = [1.5f64, -2.0, 0.25, -1.0, 1.0, 1000.125, -0.0, 1e-30];
+    for bits_per_sample in [32u16, 64] {
+        let spec = Spec {
+            bits_per_sample,
+            sample_format: SampleFormat::Float,
+            ..test_spec(2, 48000)
+        };
+        let path = dir.path().join(format!("float{bits_per_sample}.wav"));
+        // The values out of [-1.0, 1.0] are read back bit-exactly by both float types.
+        let mut wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_interleaved_samples(&values).unwrap());
+        let decoded: Vec<f64> = wavereader.frame_iter::<f64>().unwrap().flatten().collect();
+        let expected: Vec<f64> = values.iter().map(|&v| if bits_per_sample == 32 { v as f32 as f64 } else { v }).collect();
+        assert_eq!(decoded.iter().map(|v| v.to_bits()).collect::<Vec<_>>(), expected.iter().map(|v| v.to_bits()).collect::<Vec<_>>());
+        let decoded: Vec<f32> = wavereader.frame_iter::<f32>().unwrap().flatten().collect();
+        assert_eq!(decoded.iter().map(|v| v.to_bits()).collect::<Vec<_>>(), values.iter().map(|&v| (v as f32).to_bits()).collect::<Vec<_>>());
+
+        // Read and write again, the stored bytes don't change.
+        let copy_path = dir.path().join(format!("float{bits_per_sample}_copy.wav"));
+        let mut wavewriter = WaveWriter::create(&copy_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        for frame in wavereader.frame_iter::<f32>().unwrap() {
+            wavewriter.write_frame(&frame).unwrap();
+        }
+        wavewriter.finalize().unwrap();
+        let mut copy_reader = WaveReader::open(copy_path.to_str().unwrap()).unwrap();
+        let copied: Vec<f64> = copy_reader.frame_iter::<f64>().unwrap().flatten().collect();
+        assert_eq!(copied.iter().map(|v| v.to_bits()).collect::<Vec<_>>(), values.iter().map(|&v| (v as f32 as f64).to_bits()).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+pub fn test_pcm_dither() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(1, 48000);
+
+    // A 1 kHz sine of 2 LSB, its quantization error without the dither is all harmonics.
+    let sine: Vec<f32> = (0..48000).map(|i| (2.0 / 32768.0 * (i as f64 * 1000.0 / 48000.0 * std::f64::consts::TAU).sin()) as f32).collect();
+    let magnitude = |error: &[f64], freq: f64| {
+        let (mut re, mut im) = (0.0f64, 0.0f64);
+        for (i, e) in error.iter().enumerate() {
+            let phase = i as f64 * freq / 48000.0 * std::f64::consts::TAU;
+            re += e * phase.cos();
+            im -= e * phase.sin();
+        }
+        re.hypot(im)
+    };
+
+    // How much louder the harmonics are than the noise around them
+    let spur_ratio = |dither: DitherMode| {
+        let path = dir.path().join(format!("dither_{dither:?}.wav"));
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.set_dither(dither).unwrap();
+        wavewriter.write_mono_channel(&sine).unwrap();
+        match wavewriter.set_dither(DitherMode::None).unwrap_err() {
+            AudioWriteError::InvalidArguments(_) => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+        wavewriter.finalize().unwrap();
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+        let error: Vec<f64> = decoded.iter().zip(sine.iter()).map(|(&q, &x)| q as f64 - x as f64 * 32768.0).collect();
+        (2..=5).map(|harmonic| {
+            let freq = harmonic as f64 * 1000.0;
+            let noise = [-37.0, -31.0, -23.0, -17.0, -11.0, 11.0, 17.0, 23.0, 31.0, 37.0].iter().map(|offset| magnitude(&error, freq + offset)).sum::<f64>() / 10.0;
+            magnitude(&error, freq) / noise.max(1e-9)
+        }).fold(0.0f64, f64::max)
+    };
+    let undithered = spur_ratio(DitherMode::None);
+    assert!(undithered > 20.0, "The undithered error has the harmonic spurs of {undithered}x");
+    for dither in [DitherMode::Triangular, DitherMode::NoiseShaped] {
+        let ratio = spur_ratio(dither);
+        assert!(ratio < 6.0, "{dither:?} left the harmonic spurs of {ratio}x");
+    }
+
+    // Only the PCM encoder dithers.
+    let path = dir.path().join("dither_alaw.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::PcmALaw, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_dither(DitherMode::None).unwrap();
+    match wavewriter.set_dither(DitherMode::Triangular).unwrap_err() {
+        AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_split_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos = ramp_stereos(1500);
+
+    // 4000 bytes of the `data` chunk are 1000 frames, the 1500 frames go to two files.
+    let mut splitwriter = WaveWriter::create_split(dir.path().join("split.wav"), spec, DataFormat::Pcm, SplitPolicy::MaxBytes(4000)).unwrap();
+    splitwriter.set_metadata_tag("TITLE", "Split").unwrap();
+    assert_eq!(splitwriter.get_frames_per_part(), 1000);
+    splitwriter.write_stereos(&stereos[..700]).unwrap();
+    splitwriter.write_stereos(&stereos[700..]).unwrap();
+    assert_eq!(splitwriter.get_num_frames_written(), 1500);
+    let paths = splitwriter.get_paths().to_vec();
+    let reports = splitwriter.finalize().unwrap();
+    assert_eq!(paths, [dir.path().join("split_001.wav"), dir.path().join("split_002.wav")]);
+    assert_eq!(reports.iter().map(|report| report.position.frames_committed).collect::<Vec<_>>(), [1000, 500]);
+    let mut decoded = Vec::<(i16, i16)>::new();
+    for path in paths.iter() {
+        let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        assert!(wavereader.warnings().is_empty());
+        assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), "Split");
+        decoded.extend(wavereader.stereo_iter::<i16>().unwrap());
+    }
+    assert_eq!(decoded, stereos);
+
+    // The ADPCM parts end on the block boundaries.
+    let mut splitwriter = WaveWriter::create_split(dir.path().join("split_adpcm.wav"), spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), SplitPolicy::MaxDuration(std::time::Duration::from_millis(100))).unwrap();
+    let frames_per_part = splitwriter.get_frames_per_part();
+    assert!(frames_per_part <= 4410);
+    splitwriter.write_stereos(&[(0i16, 0i16); 10000]).unwrap();
+    let paths = splitwriter.get_paths().to_vec();
+    splitwriter.finalize().unwrap();
+    assert_eq!(paths.len() as u64, 10000u64.div_ceil(frames_per_part));
+    let wavereader = WaveReader::open(paths[0].to_str().unwrap()).unwrap();
+    let samples_per_block = match &wavereader.get_fmt__chunk().extension {
+        Some(chunks::FmtExtension { data: chunks::ExtensionData::AdpcmIma(adpcm_ima), .. }) => adpcm_ima.samples_per_block as u64,
+        other => panic!("Unexpected extension: {other:?}"),
+    };
+    assert_eq!(frames_per_part % samples_per_block, 0);
+    assert_eq!(wavereader.total_frames(), Some(frames_per_part));
+
+    // The compressed formats can't be split by bytes.
+    if cfg!(feature = "flac") {
+        match WaveWriter::create_split(dir.path().join("split_flac.wav"), spec, DataFormat::from_name("flac").unwrap(), SplitPolicy::MaxBytes(4000)).unwrap_err() {
+            AudioWriteError::Unsupported(_) => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+    }
+
+    // The part shorter than a block is rejected before any file is created.
+    match WaveWriter::create_split(dir.path().join("split_tiny.wav"), spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), SplitPolicy::MaxDuration(std::time::Duration::from_micros(10))).unwrap_err() {
+        AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert!(!dir.path().join("split_tiny_001.wav").exists());
+}
+
+#[test]
+pub fn test_adpcm_ms_nonstandard_fmt() {
+    use io_utils::CursorVecU8;
+
+    // With the predictor 1, the decoder extrapolates the last two samples, the zero nibbles continue a ramp exactly.
+    const STEP: i32 = 7;
+    fn ramp(channel: usize, frame: usize) -> i16 {
+        let value = -20000 + frame as i32 * STEP;
+        (if channel == 0 { value } else { -value }) as i16
+    }
+
+    // The blocks hold `frames_in_block` frames, the rest of each block is filled with garbage that must not be decoded.
+    fn build(channels: u16, block_align: u16, frames_in_block: usize, fmt_ext: Option<&[u8]>, num_blocks: usize) -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        for block in 0..num_blocks {
+            let first_frame = block * frames_in_block;
+            let block_start = data.len();
+            let mut header = [Vec::<u8>::new(), Vec::new(), Vec::new(), Vec::new()];
+            for channel in 0..channels as usize {
+                header[0].push(1);
+                header[1].extend(16i16.to_le_bytes());
+                header[2].extend(ramp(channel, first_frame + 1).to_le_bytes());
+                header[3].extend(ramp(channel, first_frame).to_le_bytes());
+            }
+            data.extend(header.concat());
+            let data_bytes = (frames_in_block - 2) * channels as usize / 2;
+            data.extend(vec![0u8; data_bytes]);
+            data.resize(block_start + block_align as usize, 0x77);
+        }
+        let mut fmt = Vec::<u8>::new();
+        fmt.extend(2u16.to_le_bytes());
+        fmt.extend(channels.to_le_bytes());
+        fmt.extend(44100u32.to_le_bytes());
+        fmt.extend((44100 * block_align as u32 / frames_in_block as u32).to_le_bytes());
+        fmt.extend(block_align.to_le_bytes());
+        fmt.extend(4u16.to_le_bytes());
+        if let Some(fmt_ext) = fmt_ext {
+            fmt.extend((fmt_ext.len() as u16).to_le_bytes());
+            fmt.extend(fmt_ext);
+        }
+        let mut riff = Vec::<u8>::new();
+        riff.extend(b"RIFF");
+        riff.extend(((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        riff.extend(b"WAVE");
+        riff.extend(b"fmt ");
+        riff.extend((fmt.len() as u32).to_le_bytes());
+        riff.extend(fmt);
+        riff.extend(b"data");
+        riff.extend((data.len() as u32).to_le_bytes());
+        riff.extend(data);
+        riff
+    }
+
+    let coeffs: [(i16, i16); 7] = [(256, 0), (512, -256), (0, 0), (192, 64), (240, 0), (460, -208), (392, -232)];
+    let full_ext = |samples_per_block: u16| -> Vec<u8> {
+        let mut ext = Vec::<u8>::new();
+        ext.extend(samples_per_block.to_le_bytes());
+        ext.extend(7u16.to_le_bytes());
+        for (coeff1, coeff2) in coeffs {
+            ext.extend(coeff1.to_le_bytes());
+            ext.extend(coeff2.to_le_bytes());
+        }
+        ext
+    };
+    let short_ext = |samples_per_block: u16| -> Vec<u8> {
+        [samples_per_block.to_le_bytes(), 7u16.to_le_bytes()].concat()
+    };
+
+    // A block of 64 bytes for each channel holds 116 frames.
+    let fixtures: [(&str, u16, u16, usize, Option<Vec<u8>>, usize); 6] = [
+        ("custom samples per block", 1, 64, 100, Some(full_ext(100)), 0),
+        ("no coefficient table", 1, 64, 100, Some(short_ext(100)), 1),
+        ("empty extension", 1, 64, 116, Some(Vec::new()), 2),
+        ("no extension", 1, 64, 116, None, 1),
+        ("oversized samples per block", 1, 64, 116, Some(full_ext(500)), 1),
+        ("stereo without coefficient table", 2, 128, 100, Some(short_ext(100)), 1),
+    ];
+    const NUM_BLOCKS: usize = 5;
+    for (name, channels, block_align, frames_in_block, fmt_ext, num_warnings) in fixtures {
+        let bytes = build(channels, block_align, frames_in_block, fmt_ext.as_deref(), NUM_BLOCKS);
+        let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes)))).unwrap();
+        assert_eq!(wavereader.warnings().len(), num_warnings, "{name}: {:?}", wavereader.warnings());
+        assert_eq!(wavereader.total_frames(), Some((frames_in_block * NUM_BLOCKS) as u64), "{name}");
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), frames_in_block * NUM_BLOCKS, "{name}");
+        for (i, frame) in decoded.iter().enumerate() {
+            let expected: Vec<i16> = (0..channels as usize).map(|channel| ramp(channel, i)).collect();
+            assert_eq!(frame, &expected, "{name}: the ramp breaks at frame {i}");
+        }
+    }
+}
+
+#[test]
+pub fn test_raw_chunks() {
+    use errors::AudioReadError;
+
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let stereos = ramp_stereos(1000);
+    let minf: Vec<u8> = b"odd sized".to_vec();
+    let elm1: Vec<u8> = (0..=255u8).collect();
+
+    // Find the whole chunk in the file: the header, the payload and the pad byte.
+    fn find_chunk(bytes: &[u8], flag: &[u8; 4], payload: &[u8]) -> Option<usize> {
+        let mut chunk = flag.to_vec();
+        chunk.extend((payload.len() as u32).to_le_bytes());
+        chunk.extend(payload);
+        if payload.len() & 1 != 0 {
+            chunk.push(0);
+        }
+        bytes.windows(chunk.len()).position(|window| window == chunk.as_slice())
+    }
+
+    let src_path = dir.path().join("raw_chunks.wav");
+    let mut wavewriter = WaveWriter::create(&src_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    match wavewriter.write_raw_chunk(b"fmt ", &[0u8; 16]).unwrap_err() {
+        AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    wavewriter.write_raw_chunk(b"minf", &minf).unwrap();
+    wavewriter.write_raw_chunk(b"elm1", &elm1).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    let src_bytes = std::fs::read(&src_path).unwrap();
+    assert!(find_chunk(&src_bytes, b"minf", &minf).is_some());
+    assert!(find_chunk(&src_bytes, b"elm1", &elm1).is_some());
+
+    // From the file path, any chunk can be read. From a reader, only the unknown chunks are kept.
+    let wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    let minf_info = wavereader.chunk_map().iter().find(|info| &info.flag == b"minf").copied().unwrap();
+    assert_eq!(minf_info.length, minf.len() as u64);
+    assert_eq!(&src_bytes[minf_info.offset as usize..minf_info.offset as usize + 4], b"minf");
+    assert_eq!(wavereader.read_raw_chunk(b"minf", 0).unwrap(), minf);
+    assert_eq!(wavereader.read_raw_chunk(b"elm1", 0).unwrap(), elm1);
+    assert_eq!(wavereader.read_raw_chunk(b"fmt ", 0).unwrap(), wavereader.get_fmt__chunk().to_bytes().unwrap());
+    match wavereader.read_raw_chunk(b"minf", 1).unwrap_err() {
+        AudioReadError::MissingData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    let wavereader = WaveReader::from_reader(std::io::Cursor::new(src_bytes.clone())).unwrap();
+    assert_eq!(wavereader.read_raw_chunk(b"minf", 0).unwrap(), minf);
+    assert_eq!(wavereader.read_raw_chunk(b"elm1", 0).unwrap(), elm1);
+    match wavereader.read_raw_chunk(b"fmt ", 0).unwrap_err() {
+        AudioReadError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // Remux the file, the unknown chunks are preserved byte for byte.
+    let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    let dst_path = dir.path().join("raw_chunks_remux.wav");
+    let mut wavewriter = WaveWriter::create(&dst_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    let unknown_flags: Vec<[u8; 4]> = wavereader.chunk_map().iter().map(|info| info.flag).filter(|flag| [b"minf", b"elm1"].contains(&flag)).collect();
+    assert_eq!(unknown_flags, [*b"minf", *b"elm1"]);
+    for flag in unknown_flags.iter() {
+        wavewriter.write_raw_chunk(flag, &wavereader.read_raw_chunk(flag, 0).unwrap()).unwrap();
+    }
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    wavewriter.write_stereos(&decoded).unwrap();
+    wavewriter.finalize().unwrap();
+    let dst_bytes = std::fs::read(&dst_path).unwrap();
+    for (flag, payload) in [(b"minf", &minf), (b"elm1", &elm1)] {
+        let src_pos = find_chunk(&src_bytes, flag, payload).unwrap();
+        let dst_pos = find_chunk(&dst_bytes, flag, payload).unwrap();
+        let chunk_len = 8 + payload.len().next_multiple_of(2);
+        assert_eq!(src_bytes[src_pos..src_pos + chunk_len], dst_bytes[dst_pos..dst_pos + chunk_len]);
+    }
+    let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.read_raw_chunk(b"minf", 0).unwrap(), minf);
+    assert_eq!(wavereader.stereo_iter::<i16>().unwrap().collect::<Vec<_>>(), stereos);
+}
+
+#[test]
+pub fn test_transfer_native_sample_type() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // The samples use every bit of the sample type, the conversion through `f32` can't keep them all.
+    for bits_per_sample in [24u16, 32] {
+        let spec = Spec {
+            bits_per_sample,
+            ..test_spec(2, 44100)
+        };
+        let unused_bits = 32 - bits_per_sample as u32;
+        let mut seed = 0x12345678u32;
+        let samples: Vec<i32> = (0..20000).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed as i32 >> unused_bits) << unused_bits
+        }).collect();
+
+        let src_path = dir.path().join(format!("native_{bits_per_sample}.wav"));
+        let mut wavewriter = WaveWriter::create(&src_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        wavewriter.finalize().unwrap();
+
+        let dst_path = dir.path().join(format!("native_{bits_per_sample}_copy.wav"));
+        let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+        let mut wavewriter = WaveWriter::create(&dst_path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        assert_eq!(wavewriter.native_sample_type(), wavereader.spec().get_sample_type());
+        transfer_audio_from_decoder_to_encoder(&mut wavereader, &mut wavewriter);
+        wavewriter.finalize().unwrap();
+
+        let src_data = wavereader.read_raw_chunk(b"data", 0).unwrap();
+        let dst_data = WaveReader::open(dst_path.to_str().unwrap()).unwrap().read_raw_chunk(b"data", 0).unwrap();
+        assert_eq!(src_data.len(), samples.len() * bits_per_sample as usize / 8);
+        assert!(src_data == dst_data, "The {bits_per_sample}-bit PCM wasn't copied bit-exact");
+    }
+
+    // The encoders report the sample types they take.
+    let spec = test_spec(2, 48000);
+    let mut expected = vec![
+        (DataFormat::Pcm, WaveSampleType::S16),
+        (DataFormat::Adpcm(AdpcmSubFormat::Ms), WaveSampleType::S16),
+        (DataFormat::PcmALaw, WaveSampleType::S16),
+    ];
+    if cfg!(feature = "flac") {
+        expected.push((DataFormat::from_name("flac").unwrap(), WaveSampleType::S32));
+    }
+    if cfg!(feature = "opus") {
+        expected.push((DataFormat::from_name("opus").unwrap(), WaveSampleType::F32));
+    }
+    for (data_format, sample_type) in expected {
+        let name = format!("{data_format:?}");
+        let wavewriter = WaveWriter::create(dir.path().join("native_type.wav"), spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        assert_eq!(wavewriter.native_sample_type(), sample_type, "{name}");
+    }
+
+    // The dither needs the samples with more bits than the WAV file.
+    let mut wavewriter = WaveWriter::create(dir.path().join("native_dither.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_dither(DitherMode::Triangular).unwrap();
+    assert_eq!(wavewriter.native_sample_type(), WaveSampleType::F64);
+}
+
+#[test]
+pub fn test_read_error_chunk_context() {
+    use errors::AudioReadError;
+    let (_dir, path) = temp_wav("short_fmt.wav");
+
+    // The `fmt ` chunk is two bytes too short.
+    let mut bytes = Vec::<u8>::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(4u32 + 8 + 14).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&14u32.to_le_bytes());
+    bytes.extend_from_slice(&[1, 0, 1, 0, 0x44, 0xAC, 0, 0, 0x88, 0x58, 1, 0, 2, 0]);
+    let path = path.to_str().unwrap();
+    std::fs::write(path, &bytes).unwrap();
+    let err = WaveReader::open(path).unwrap_err();
+    assert_eq!(err.get_path(), Some(path));
+    assert_eq!(err.chunk_flag(), Some(*b"fmt "));
+    assert_eq!(err.get_offset(), Some(12));
+    assert_eq!(err.expected_and_found(), Some(("at least 16 bytes", "14 bytes")));
+    assert!(err.to_string().contains("\"fmt \" chunk at 0xc"), "{err}");
+    match err.root() {
+        AudioReadError::IncompleteData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // The same bytes from a reader or a stream have the chunk context but no path.
+    let err = WaveReader::new(WaveDataSource::Reader(Box::new(std::io::Cursor::new(bytes.clone())))).unwrap_err();
+    assert_eq!(err.get_path(), None);
+    assert_eq!(err.get_offset(), Some(12));
+    let err = WaveReader::from_reader(std::io::Cursor::new(bytes.clone())).unwrap_err();
+    assert_eq!(err.get_path(), None);
+    assert_eq!(err.chunk_flag(), Some(*b"fmt "));
+
+    // Not a `WAVE` form, the offset points to the form type.
+    bytes[8..12].copy_from_slice(b"AVI ");
+    std::fs::write(path, &bytes).unwrap();
+    let err = WaveReader::open(path).unwrap_err();
+    assert_eq!(err.chunk_flag(), Some(*b"RIFF"));
+    assert_eq!(err.chunk_context().unwrap().offset, Some(8));
+    assert_eq!(err.expected_and_found(), Some(("WAVE", "AVI ")));
+    match err.without_context() {
+        AudioReadError::UnexpectedFlag(_, _) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_signal_generators() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 8000);
+    let write = |name: &str, seed: u32| -> Vec<(i16, i16)> {
+        let path = dir.path().join(name);
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        testsig::write_sine(&mut wavewriter, 1000.0, 0.5).unwrap();
+        testsig::write_sweep(&mut wavewriter, 100.0, 3000.0, 0.5).unwrap();
+        testsig::write_white_noise(&mut wavewriter, seed, 0.5).unwrap();
+        wavewriter.finalize().unwrap();
+        WaveReader::open(path.to_str().unwrap()).unwrap().stereo_iter::<i16>().unwrap().collect()
+    };
+
+    // The signals are deterministic, and the seed picks the noise.
+    let frames = write("signal_1.wav", 1);
+    assert_eq!(frames.len(), 12000);
+    assert_eq!(frames, write("signal_1_again.wav", 1));
+    let other_noise = write("signal_2.wav", 2);
+    assert_eq!(frames[..8000], other_noise[..8000]);
+    assert_ne!(frames[8000..], other_noise[8000..]);
+
+    // The sine starts from zero on the left channel and from the negative peak on the right channel, never above the amplitude.
+    let peak = (testsig::AMPLITUDE * 32768.0) as i16;
+    assert_eq!(frames[0].0, 0);
+    assert!((frames[0].1 + peak).abs() <= 1, "{:?}", frames[0]);
+    assert!(frames.iter().all(|&(l, r)| l.abs() <= peak && r.abs() <= peak));
+
+    // The arguments are checked.
+    let mut wavewriter = WaveWriter::create(dir.path().join("invalid.wav"), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    assert!(testsig::write_sine(&mut wavewriter, 4000.0, 1.0).is_err());
+    assert!(testsig::write_sweep(&mut wavewriter, 0.0, 1000.0, 1.0).is_err());
+    assert!(testsig::write_white_noise(&mut wavewriter, 1, -1.0).is_err());
+}
+
+#[test]
+pub fn test_storage_info() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = Spec {
+        bits_per_sample: 24,
+        ..test_spec(2, 44100)
+    };
+    let write = |name: &str, data_format: DataFormat| -> WaveReader {
+        let path = dir.path().join(name);
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        testsig::write_sine(&mut wavewriter, 440.0, 1.0).unwrap();
+        wavewriter.finalize().unwrap();
+        WaveReader::open(path.to_str().unwrap()).unwrap()
+    };
+
+    let wavereader = write("storage_pcm.wav", DataFormat::Pcm);
+    let info = wavereader.storage_info();
+    assert_eq!(info.codec, "PCM");
+    assert_eq!(info.bit_depth, Some(24));
+    assert_eq!(info.block_size, Some(6));
+    assert_eq!(info.samples_per_block, Some(1));
+    assert_eq!(info.bitrate, Some(44100 * 6 * 8));
+    assert_eq!(info.bitrate_mode, Some(BitrateMode::Constant));
+    assert_eq!(info.data_length, 44100 * 6);
+    assert_eq!(info.to_string(), "PCM 24-bit 2117 kbps CBR, 44.1 kHz, 2 channels");
+    assert!(format!("{wavereader:?}").contains("storage_info"));
+
+    // The ADPCM decodes to 16-bit PCM, but it's stored in blocks.
+    let wavereader = write("storage_adpcm_ms.wav", DataFormat::Adpcm(AdpcmSubFormat::Ms));
+    let info = wavereader.storage_info();
+    assert_eq!(wavereader.spec().bits_per_sample, 16);
+    assert_eq!(info.codec, "ADPCM-MS");
+    assert_eq!(info.bit_depth, None);
+    assert_eq!(info.block_size, Some(wavereader.get_fmt__chunk().block_align as u32));
+    assert_eq!(info.samples_per_block, Some(2036));
+    assert!(matches!(info.extension, Some(chunks::ext::ExtensionData::AdpcmMs(_))));
+
+    if cfg!(feature = "mp3enc") {
+        let options = Mp3EncoderOptions {
+            channels: Mp3Channels::JointStereo,
+            ..Mp3EncoderOptions::new()
+        };
+        let wavereader = write("storage_mp3.wav", DataFormat::Mp3(options));
+        let info = wavereader.storage_info();
+        assert_eq!(info.samples_per_block, Some(1152));
+        assert_eq!(info.bitrate, Some(320000));
+        assert_eq!(info.bitrate_mode, Some(BitrateMode::Constant));
+        assert_eq!(info.to_string(), "MP3 320 kbps CBR, 44.1 kHz, 2 channels");
+    }
+}
+
+#[test]
+pub fn test_vorbis_no_codebook_header_round_trip() {
+    if !cfg!(feature = "oggvorbis") {
+        return;
+    }
+    use format_specs::format_tags::{FORMAT_TAG_OGG_VORBIS3, FORMAT_TAG_OGG_VORBIS3P};
+    let dir = tempfile::tempdir().unwrap();
+    for (name, channels, sample_rate) in [("oggvorbis3", 1u16, 44100u32), ("oggvorbis3p", 1, 44100), ("oggvorbis3", 2, 48000), ("oggvorbis3p", 2, 48000)] {
+        let Some(DataFormat::OggVorbis(mut params)) = DataFormat::from_name(name) else {
+            panic!("{name} is not an OggVorbis format");
+        };
+        params.channels = channels;
+        params.sample_rate = sample_rate;
+        if channels == 1 {
+            params.bitrate = match params.bitrate {
+                Some(OggVorbisBitrateStrategy::Abr(_)) => Some(OggVorbisBitrateStrategy::Abr(128_000)),
+                _ => Some(OggVorbisBitrateStrategy::Vbr(128_000)),
+            };
+        }
+        let spec = test_spec(channels, sample_rate);
+        let path = dir.path().join(format!("{name}_{channels}_{sample_rate}.wav"));
+        // The header isn't stored, only the bitrate strategy to regenerate it.
+        let mut wavereader = write_and_reopen(&path, spec, DataFormat::OggVorbis(params), |wavewriter| {
+            testsig::write_sine(wavewriter, 440.0, 1.0).unwrap();
+            testsig::write_white_noise(wavewriter, 1, 0.5).unwrap();
+        });
+        let fmt = wavereader.get_fmt__chunk().clone();
+        let expected_tag = if name.ends_with('p') { FORMAT_TAG_OGG_VORBIS3P } else { FORMAT_TAG_OGG_VORBIS3 };
+        assert_eq!(fmt.format_tag, expected_tag, "{name}");
+        match fmt.extension.map(|extension| extension.data) {
+            Some(chunks::ext::ExtensionData::OggVorbis(data)) => assert_eq!(data.bitrate, params.bitrate, "{name}"),
+            other => panic!("Unexpected extension data: {other:?}"),
+        }
+
+        let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded.len(), sample_rate as usize * 3 / 2, "{name} {channels} channels {sample_rate} Hz");
+        assert!(decoded.iter().all(|frame| frame.len() == channels as usize));
+    }
+}
+
+#[test]
+pub fn test_transfer_cancelled() {
+    use errors::AudioReadError;
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("test.wav");
+    create_test_wav(&src_path).unwrap();
+    let src_spec = WaveReader::open(src_path.to_str().unwrap()).unwrap().spec();
+
+    // The first one goes bit-exact in blocks of 4096 frames, the second one goes through the resampler.
+    for (i, sample_rate) in [44100u32, 22050].into_iter().enumerate() {
+        let dst_path = dir.path().join(format!("cancelled_{i}.wav"));
+        let dst_spec = Spec {
+            sample_rate,
+            ..src_spec
+        };
+        let cancel = CancelToken::new();
+        let options = TransferOptions {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+        let mut wavewriter = WaveWriter::create(&dst_path, dst_spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        let token = cancel.clone();
+        wavewriter.set_progress_callback(8192, Box::new(move |_| token.cancel()));
+        match transfer_audio_with_options(&mut wavereader, &mut wavewriter, &options).unwrap_err() {
+            errors::AudioWriteError::Cancelled => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+        assert!(cancel.is_cancelled());
+        let frames_written = wavewriter.get_num_frames_written();
+        match wavewriter.finalize().unwrap_err() {
+            errors::AudioWriteError::AlreadyFinished(_) => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+
+        let mut wavereader = WaveReader::open(dst_path.to_str().unwrap()).unwrap();
+        let stereos: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(stereos.len() as u64, frames_written);
+        assert!(frames_written >= 8192 && frames_written < sample_rate as u64 * 3, "{frames_written}");
+        if sample_rate == 44100 {
+            assert_eq!(frames_written, 8192);
+            let mut srcreader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+            let original: Vec<(i16, i16)> = srcreader.stereo_iter::<i16>().unwrap().take(8192).collect();
+            assert_eq!(stereos, original);
+        }
+    }
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let mut wavereader = WaveReader::open(src_path.to_str().unwrap()).unwrap();
+    match wavereader.scan_levels_cancellable(&cancel).unwrap_err() {
+        AudioReadError::Cancelled => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert!(wavereader.scan_levels_cancellable(&CancelToken::new()).is_ok());
+}
+
+#[test]
+pub fn test_decode_into() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+    for (name, mut data_format) in FORMATS.into_iter().take(10) {
+        match data_format {
+            DataFormat::Mp3(ref mut options) => options.channels = Mp3Channels::JointStereo,
+            DataFormat::Flac(ref mut options) => {
+                options.channels = spec.channels;
+                options.sample_rate = spec.sample_rate;
+                options.bits_per_sample = spec.bits_per_sample as u32;
+            }
+            DataFormat::OggVorbis(ref mut options) => {
+                options.channels = spec.channels;
+                options.sample_rate = spec.sample_rate;
+            }
+            _ => (),
+        }
+        let path = dir.path().join(format!("decode_into_{name}.wav"));
+        let mut wavereader = write_and_reopen(&path, spec, data_format, |wavewriter| {
+            testsig::write_sine(wavewriter, 440.0, 0.5).unwrap();
+            testsig::write_white_noise(wavewriter, 1, 0.5).unwrap();
+        });
+        let expected: Vec<i16> = wavereader.frame_iter::<i16>().unwrap().flatten().collect();
+
+        // The odd-sized buffer only gets the whole frames, the iterator created in between doesn't disturb it.
+        let mut buf = vec![0i16; 1001];
+        let mut decoded = Vec::<i16>::new();
+        loop {
+            let written = wavereader.decode_into(&mut buf).unwrap();
+            if written == 0 {
+                break;
+            }
+            assert_eq!(written % 2, 0, "{name}");
+            assert!(written == 1000 || decoded.len() + written == expected.len(), "{name}: {written}");
+            decoded.extend_from_slice(&buf[..written]);
+            if decoded.len() == 1000 {
+                assert_eq!(wavereader.frame_iter::<i16>().unwrap().count() * 2, expected.len(), "{name}");
+            }
+        }
+        assert_eq!(decoded, expected, "{name}");
+        assert_eq!(wavereader.decode_into(&mut buf).unwrap(), 0, "{name}");
+
+        // Seeking restarts it from the new start frame.
+        wavereader.seek_to_frame(0).unwrap();
+        assert_eq!(wavereader.decode_into(&mut buf).unwrap(), 1000, "{name}");
+        assert_eq!(buf[..1000], expected[..1000], "{name}");
+    }
+}
+
+#[test]
+pub fn test_list_info_verbatim_round_trip() {
+    use chunks::{ListChunk, ListInfo};
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(1, 8000);
+
+    // The vendor-specific keys, a lowercase key and the odd-sized values, not in the alphabetical order.
+    let entries: [(&[u8; 4], &[u8]); 5] = [
+        (b"ITRK", b"7\0"),
+        (b"TLEN", b"183000\0"),
+        (b"INAM", b"Oddball\0"),
+        (b"abcd", b"lowercase vendor key\0"),
+        (b"IART", b"The Artist\0"),
+    ];
+    let mut info_bytes = b"INFO".to_vec();
+    for (flag, value) in entries.iter() {
+        info_bytes.extend_from_slice(*flag);
+        info_bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        info_bytes.extend_from_slice(value);
+        if value.len() & 1 != 0 {
+            info_bytes.push(0);
+        }
+    }
+    let src_path = dir.path().join("info_src.wav");
+    let wavereader = write_and_reopen(&src_path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.write_raw_chunk(b"LIST", &info_bytes).unwrap();
+        wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    });
+    let info = wavereader.get_list_chunk().iter().find(|chunk| chunk.get_is_list_info()).unwrap().clone();
+    let ListChunk::Info(dict) = &info else {
+        panic!("Not a `LIST INFO` chunk: {info:?}");
+    };
+    assert_eq!(dict.keys().map(|key| key.as_str()).collect::<Vec<_>>(), ["ITRK", "TLEN", "INAM", "abcd", "IART"]);
+    assert_eq!(dict.get("TLEN").map(|value| value.as_str()), Some("183000"));
+    assert_eq!(dict.get("ABCD").map(|value| value.as_str()), Some("lowercase vendor key"));
+
+    // Read, inherit and write, the `LIST INFO` chunk comes out byte by byte the same.
+    let dst_path = dir.path().join("info_dst.wav");
+    let wavereader = write_and_reopen(&dst_path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.inherit_metadata_from_reader(&wavereader, false);
+        wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    });
+    let list = wavereader.chunk_map().iter().find(|chunk| &chunk.flag == b"LIST").unwrap();
+    let bytes = std::fs::read(&dst_path).unwrap();
+    assert_eq!(&bytes[list.offset as usize + 8..(list.offset + 8 + list.length) as usize], &info_bytes[..]);
+
+    // The edited value stays where it was.
+    let dst_path = dir.path().join("info_edited.wav");
+    let wavereader = write_and_reopen(&dst_path, spec, DataFormat::Pcm, |wavewriter| {
+        wavewriter.inherit_metadata_from_reader(&wavereader, false);
+        wavewriter.set_metadata_tag("INAM", "Edited").unwrap();
+        wavewriter.write_mono_channel(&[0i16; 100]).unwrap();
+    });
+    let info = wavereader.get_list_chunk().iter().find(|chunk| chunk.get_is_list_info()).unwrap().clone();
+    let ListChunk::Info(dict) = &info else {
+        panic!("Not a `LIST INFO` chunk: {info:?}");
+    };
+    assert_eq!(dict.keys().map(|key| key.as_str()).collect::<Vec<_>>(), ["ITRK", "TLEN", "INAM", "abcd", "IART"]);
+    assert_eq!(info.get_name().map(|value| value.as_str()), Some("Edited"));
+}
+
+#[test]
+pub fn test_pcm_block_decode_matches_per_sample() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Decode the `data` chunk sample by sample as the decoder used to.
+    fn reference<T: SampleType, S: SampleType>(path: &std::path::Path) -> Vec<S> {
+        let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        let data = wavereader.chunk_map().iter().find(|chunk| &chunk.flag == b"data").unwrap().clone();
+        let bytes = std::fs::read(path).unwrap();
+        let mut bytes = &bytes[data.offset as usize + 8..(data.offset + 8 + data.length) as usize];
+        let mut samples = Vec::<S>::new();
+        while !bytes.is_empty() {
+            samples.push(S::scale_from(T::read_le(&mut bytes).unwrap()));
+        }
+        samples
+    }
+
+    // Long enough for several blocks of 64 KiB, and not a whole number of blocks.
+    let create = |name: &str, bits_per_sample: u16, sample_format: SampleFormat| {
+        let path = dir.path().join(name);
+        let spec = Spec {
+            bits_per_sample,
+            sample_format,
+            ..test_spec(2, 44100)
+        };
+        let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+        testsig::write_sweep(&mut wavewriter, 20.0, 20000.0, 1.0).unwrap();
+        testsig::write_white_noise(&mut wavewriter, 7, 0.77).unwrap();
+        wavewriter.finalize().unwrap();
+        path
+    };
+
+    let path = create("pcm24.wav", 24, SampleFormat::Int);
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<f32> = wavereader.frame_iter::<f32>().unwrap().flatten().collect();
+    assert_eq!(decoded, reference::<i24, f32>(&path));
+    let decoded: Vec<i32> = wavereader.stereo_iter::<i32>().unwrap().flat_map(|(l, r)| [l, r]).collect();
+    assert_eq!(decoded, reference::<i24, i32>(&path));
+
+    let path = create("pcm16.wav", 16, SampleFormat::Int);
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i16> = wavereader.frame_iter::<i16>().unwrap().flatten().collect();
+    assert_eq!(decoded, reference::<i16, i16>(&path));
+
+    let path = create("pcm8.wav", 8, SampleFormat::UInt);
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i16> = wavereader.frame_iter::<i16>().unwrap().flatten().collect();
+    assert_eq!(decoded, reference::<u8, i16>(&path));
+
+    // Seeking into the middle of a block gets the same samples.
+    let path = create("pcm32f.wav", 32, SampleFormat::Float);
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let expected: Vec<f64> = reference::<f32, f32>(&path).into_iter().map(|sample| sample as f64).collect();
+    let decoded: Vec<f64> = wavereader.frame_iter::<f64>().unwrap().flatten().collect();
+    assert_eq!(decoded, expected);
+    wavereader.seek_to_frame(12345).unwrap();
+    let decoded: Vec<f64> = wavereader.frame_iter::<f64>().unwrap().flatten().collect();
+    assert_eq!(decoded, expected[12345 * 2..]);
+
+    let path = create("pcm64f.wav", 64, SampleFormat::Float);
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let decoded: Vec<f64> = wavereader.frame_iter::<f64>().unwrap().flatten().collect();
+    assert_eq!(decoded, reference::<f64, f64>(&path));
+}
+
+#[test]
+pub fn test_embedded_audio_hash() {
+    use errors::AudioReadError;
+    let (dir, path) = temp_wav("hashed.wav");
+    let spec = test_spec(2, 44100);
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.enable_audio_hash().unwrap();
+    testsig::write_sine(&mut wavewriter, 440.0, 1.0).unwrap();
+    match wavewriter.write_frames_at(0, &[vec![0i16; 2]]).unwrap_err() {
+        errors::AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    wavewriter.finalize().unwrap();
+
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert!(wavereader.verify_embedded_hash().unwrap());
+
+    // Flip one byte in the middle of the `data` chunk.
+    let data = wavereader.chunk_map().iter().find(|chunk| &chunk.flag == b"data").unwrap().clone();
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[(data.offset + 8 + data.length / 2) as usize] ^= 0x01;
+    std::fs::write(&path, &bytes).unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert!(!wavereader.verify_embedded_hash().unwrap());
+
+    // The hash must be enabled before any samples, and only for PCM.
+    let path = dir.path().join("unhashed.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    testsig::write_sine(&mut wavewriter, 440.0, 0.1).unwrap();
+    match wavewriter.enable_audio_hash().unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    match wavereader.verify_embedded_hash().unwrap_err() {
+        AudioReadError::MissingData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    let path = dir.path().join("adpcm.wav");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Adpcm(AdpcmSubFormat::Ima), FileSizeOption::NeverLargerThan4GB).unwrap();
+    match wavewriter.enable_audio_hash().unwrap_err() {
+        errors::AudioWriteError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_vorbis_granule_offset_chain() {
+    if !cfg!(feature = "oggvorbis") {
+        return;
+    }
+    use encoders::oggvorbis_enc::OggVorbisEncoderWrap;
+    let dir = tempfile::tempdir().unwrap();
+    let sample_rate = 44100u32;
+    let mut granule_offset = 0u64;
+    let mut serials = Vec::<i32>::new();
+    let mut chain = Vec::<u8>::new();
+    for (i, num_frames) in [30000u64, 20000].into_iter().enumerate() {
+        let path = dir.path().join(format!("segment{i}.ogg"));
+        let mut file = std::fs::File::create(&path).unwrap();
+        let params = OggVorbisEncoderParams {
+            channels: 2,
+            sample_rate,
+            granule_offset,
+            ..OggVorbisEncoderParams::new()
+        };
+        let mut encoder = OggVorbisEncoderWrap::new(&mut file, &params).unwrap();
+        encoder.begin_to_encode().unwrap();
+        let samples: Vec<f32> = (0..num_frames * 2)
+            .map(|i| ((granule_offset + i / 2) as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin() * 0.5)
+            .collect();
+        encoder.write_interleaved_samples(&samples).unwrap();
+        assert_eq!(encoder.get_final_granule_position(), None);
+        encoder.finish().unwrap();
+        let serial = encoder.get_stream_serial();
+        let final_granule = encoder.get_final_granule_position().unwrap();
+        assert_eq!(final_granule, granule_offset + num_frames);
+        drop(encoder);
+        drop(file);
+
+        // The header pages stay at zero, the audio pages continue from the previous segment.
+        let bytes = std::fs::read(&path).unwrap();
+        let mut pages = Vec::<(u8, u64, i32)>::new();
+        let mut cursor = 0usize;
+        while cursor < bytes.len() {
+            assert_eq!(&bytes[cursor..cursor + 4], b"OggS");
+            let segments = bytes[cursor + 26] as usize;
+            let body_length: usize = bytes[cursor + 27..cursor + 27 + segments].iter().map(|&length| length as usize).sum();
+            pages.push((
+                bytes[cursor + 5],
+                u64::from_le_bytes(bytes[cursor + 6..cursor + 14].try_into().unwrap()),
+                i32::from_le_bytes(bytes[cursor + 14..cursor + 18].try_into().unwrap()),
+            ));
+            cursor += 27 + segments + body_length;
+        }
+        assert!(pages.iter().all(|page| page.2 == serial));
+        assert_eq!(pages[0].0 & 0x02, 0x02);
+        assert_eq!(pages[0].1, 0);
+        assert_eq!(pages.last().unwrap().0 & 0x04, 0x04);
+        assert_eq!(pages.last().unwrap().1, final_granule);
+        assert!(pages.iter().all(|page| page.1 == 0 || page.1 == u64::MAX || page.1 > granule_offset));
+
+        serials.push(serial);
+        chain.extend(bytes);
+        granule_offset = final_granule;
+    }
+    assert_ne!(serials[0], serials[1]);
+
+    // Play through the join of the chained streams by `ffmpeg`.
+    let chain_path = dir.path().join("chain.ogg");
+    let decoded_path = dir.path().join("chain.wav");
+    std::fs::write(&chain_path, &chain).unwrap();
+    let decoded = std::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(&chain_path)
+        .args(["-c:a", "pcm_s16le"])
+        .arg(&decoded_path)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !decoded {
+        eprintln!("`ffmpeg` is not available, skipped the interoperability test.");
+        return;
+    }
+    let mut wavereader = WaveReader::open(decoded_path.to_str().unwrap()).unwrap();
+    let num_frames = wavereader.stereo_iter::<i16>().unwrap().count() as u64;
+    assert!(num_frames >= granule_offset * 9 / 10, "Decoded {num_frames} of {granule_offset} frames.");
+}
+
+#[test]
+pub fn test_spec_validate() {
+    let spec = test_spec(2, 44100);
+    let flac = DataFormat::from_name("flac").unwrap();
+    let mp3_mono = DataFormat::Mp3(Mp3EncoderOptions::new_mono());
+    let mp3_stereo = DataFormat::Mp3(Mp3EncoderOptions::new_stereo());
+    let opus = DataFormat::Opus(OpusEncoderOptions::new());
+
+    // Every error names the offending field.
+    let invalid = [
+        (Spec { channels: 0, ..spec }, DataFormat::Pcm, "`channels`"),
+        (Spec { sample_rate: 0, ..spec }, DataFormat::Pcm, "`sample_rate`"),
+        (Spec { bits_per_sample: 20, ..spec }, DataFormat::Pcm, "`bits_per_sample`"),
+        (Spec { sample_format: SampleFormat::Float, ..spec }, DataFormat::Pcm, "`bits_per_sample`"),
+        (Spec { sample_format: SampleFormat::Float, ..spec }, DataFormat::Adpcm(AdpcmSubFormat::Ima), "`bits_per_sample`"),
+        (Spec { channels: 3, ..spec }, DataFormat::Adpcm(AdpcmSubFormat::Ms), "`channels`"),
+        (Spec { channels: 3, ..spec }, DataFormat::PcmMuLaw, "`channels`"),
+        (Spec { sample_rate: 44100, ..spec }, opus.clone(), "`sample_rate`"),
+        (Spec { channels: 9, ..spec }, opus.clone(), "`channels`"),
+        (Spec { sample_rate: 37800, ..spec }, mp3_stereo.clone(), "`sample_rate`"),
+        (spec, mp3_mono.clone(), "`channels`"),
+        (Spec { channels: 9, channel_mask: Some(0), ..spec }, flac.clone(), "`channels`"),
+        (Spec { channel_mask: Some(0x3F), ..spec }, DataFormat::Pcm, "`channel_mask`"),
+        (Spec { channels: 32, ..spec }, DataFormat::PcmExtensible(PcmEncoderOptions::new()), "`channel_mask`"),
+    ];
+    for (spec, format, field) in invalid {
+        match spec.validate(&format).unwrap_err() {
+            errors::AudioWriteError::InvalidArguments(message) => assert!(message.contains(field), "{format}: {message}"),
+            other => panic!("Unexpected error: {other}"),
+        }
+    }
+
+    // The edge cases that are still valid.
+    let valid = [
+        (Spec { channels: 1, bits_per_sample: 64, sample_format: SampleFormat::Float, ..spec }, DataFormat::Pcm),
+        (Spec { bits_per_sample: 8, sample_format: SampleFormat::UInt, ..spec }, DataFormat::Pcm),
+        (Spec { channels: 32, channel_mask: Some(0), ..spec }, DataFormat::PcmExtensible(PcmEncoderOptions::new())),
+        (Spec { sample_rate: 8000, ..spec }, DataFormat::PcmALaw),
+        (Spec { sample_rate: 8000, ..spec }, DataFormat::PcmMuLaw),
+        (Spec { sample_rate: 192000, ..spec }, DataFormat::Adpcm(AdpcmSubFormat::Yamaha)),
+        (Spec { channels: 1, sample_rate: 8000, ..spec }, mp3_mono),
+        (Spec { sample_rate: 48000, ..spec }, DataFormat::Mp3(Mp3EncoderOptions::new())),
+        (Spec { channels: 8, sample_rate: 8000, ..spec }, opus),
+        (Spec { channels: 8, sample_rate: 96000, bits_per_sample: 24, ..spec }, flac),
+        (Spec { channels: 255, sample_rate: 11025, ..spec }, DataFormat::from_name("oggvorbis1").unwrap()),
+    ];
+    for (spec, format) in valid {
+        spec.validate(&format).unwrap_or_else(|err| panic!("{format}: {err}"));
+    }
+
+    // `WaveWriter` refuses the bad spec before writing anything.
+    let (_dir, path) = temp_wav("invalid.wav");
+    match WaveWriter::create(&path, Spec { bits_per_sample: 20, ..spec }, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap_err() {
+        errors::AudioWriteError::InvalidArguments(message) => assert!(message.contains("`bits_per_sample`"), "{message}"),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_gsm610_decode() {
+    use io_utils::CursorVecU8;
+
+    // Random GSM frames with the valid lags and the moderate block maximums, packed two frames a block from the LSB of each byte.
+    // The second frame of each block begins in the middle of the 33rd byte.
+    let num_blocks = 3usize;
+    let mut state = 0x2545F491u32;
+    let mut random = |modulo: u32| -> u32 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 16) % modulo
+    };
+    let mut fields = Vec::<(u32, u32)>::new();
+    for _ in 0..num_blocks * 2 {
+        for bits in [6, 6, 5, 5, 4, 4, 3, 3] {
+            fields.push((random(1 << bits), bits));
+        }
+        for _ in 0..4 {
+            fields.push((40 + random(81), 7));
+            fields.push((random(4), 2));
+            fields.push((random(4), 2));
+            fields.push((random(32), 6));
+            for _ in 0..13 {
+                fields.push((random(8), 3));
+            }
+        }
+    }
+    let mut data = vec![0u8; num_blocks * 65];
+    let mut bit_pos = 0usize;
+    for (value, bits) in fields {
+        for i in 0..bits {
+            data[bit_pos >> 3] |= (((value >> i) & 1) as u8) << (bit_pos & 7);
+            bit_pos += 1;
+        }
+    }
+    assert_eq!(bit_pos, data.len() * 8);
+
+    let riff = |data: &[u8]| -> Vec<u8> {
+        let num_frames = (data.len() / 65 * 320) as u32;
+        let mut fmt_ = Vec::<u8>::new();
+        for field in [0x0031u16, 1] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt_.extend_from_slice(&8000u32.to_le_bytes());
+        fmt_.extend_from_slice(&1625u32.to_le_bytes());
+        for field in [65u16, 0, 2, 320] {
+            fmt_.extend_from_slice(&field.to_le_bytes());
+        }
+        let mut body = b"WAVE".to_vec();
+        for (flag, chunk) in [(b"fmt ", fmt_), (b"fact", num_frames.to_le_bytes().to_vec()), (b"data", data.to_vec())] {
+            body.extend_from_slice(flag);
+            body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            body.extend_from_slice(&chunk);
+            if chunk.len() & 1 == 1 {
+                body.push(0);
+            }
+        }
+        [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat()
+    };
+
+    // Every 20th sample of the reference decode by the fixed-point reference implementation.
+    let reference: [i16; 48] = [
+        0, -840, 2112, 616, 24, -616, -1344, -1624, 2144, 288, -1584, 2304, -3152, 720, 2848, 4200,
+        9712, 2120, -256, 1000, 1144, 2616, 4592, 4296, 5736, -504, 576, 616, 3640, -544, -328, 368,
+        -3304, -424, 1328, 32760, 32760, 32760, 32760, 13424, -4104, -640, 1880, -32, 464, -1840, -616, -576,
+    ];
+    let bytes = riff(&data);
+    let mut wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(bytes.clone())))).unwrap();
+    assert_eq!(wavereader.storage_info().codec, "GSM 6.10");
+    assert_eq!(wavereader.storage_info().samples_per_block, Some(320));
+    let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded.len(), num_blocks * 320);
+    let strided: Vec<i16> = decoded.iter().copied().step_by(20).collect();
+    assert_eq!(strided, reference);
+
+    // After seeking, the decoder starts over at the block of the frame.
+    wavereader.seek_to_frame(480).unwrap();
+    let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().take(8).collect();
+    assert_eq!(decoded, [136, -1176, 120, -1400, -192, -1112, 440, -440]);
+
+    // Compare with ffmpeg's decode of the same file.
+    let dir = tempfile::tempdir().unwrap();
+    let gsm_path = dir.path().join("gsm610.wav");
+    let reference_path = dir.path().join("gsm610_ffmpeg.wav");
+    std::fs::write(&gsm_path, &bytes).unwrap();
+    let decoded_by_ffmpeg = std::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(&gsm_path)
+        .args(["-c:a", "pcm_s16le"])
+        .arg(&reference_path)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !decoded_by_ffmpeg {
+        eprintln!("`ffmpeg` is not available, skipped the interoperability test.");
+        return;
+    }
+    let mut wavereader = WaveReader::open(reference_path.to_str().unwrap()).unwrap();
+    let reference: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    let mut wavereader = WaveReader::open(gsm_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<i16> = wavereader.mono_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, reference);
+}
+
+#[test]
+pub fn test_wave_writer_builder() {
+    use errors::AudioWriteError;
+    let spec = Spec {
+        bits_per_sample: 24,
+        ..test_spec(1, 22050)
+    };
+
+    // The parameters of the codecs that repeat the spec are filled from the spec.
+    let (_, data_format) = WaveWriterBuilder::new(spec, DataFormat::from_name("flac").unwrap()).flac_compression(FlacCompression::Level3).configure().unwrap();
+    match data_format {
+        DataFormat::Flac(params) => {
+            assert_eq!((params.channels, params.sample_rate, params.bits_per_sample), (1, 22050, 24));
+            assert_eq!(params.compression, FlacCompression::Level3);
+        }
+        other => panic!("Unexpected data format: {other}"),
+    }
+    let (_, data_format) = WaveWriterBuilder::new(spec, DataFormat::from_name("oggvorbis1").unwrap()).vorbis_quality(0.6).configure().unwrap();
+    match data_format {
+        DataFormat::OggVorbis(params) => {
+            assert_eq!((params.channels, params.sample_rate), (1, 22050));
+            assert_eq!(params.bitrate, Some(OggVorbisBitrateStrategy::QualityVbr(0.6)));
+        }
+        other => panic!("Unexpected data format: {other}"),
+    }
+    let (_, data_format) = WaveWriterBuilder::new(spec, DataFormat::Mp3(Mp3EncoderOptions::new())).mp3_bitrate(Mp3Bitrate::Kbps192).configure().unwrap();
+    match data_format {
+        DataFormat::Mp3(options) => {
+            assert_eq!(options.channels, Mp3Channels::Mono);
+            assert_eq!(options.bitrate, Mp3Bitrate::Kbps192);
+        }
+        other => panic!("Unexpected data format: {other}"),
+    }
+    let (_, data_format) = WaveWriterBuilder::new(Spec { channels: 2, ..spec }, DataFormat::Mp3(Mp3EncoderOptions::new())).configure().unwrap();
+    match data_format {
+        DataFormat::Mp3(options) => assert_eq!(options.channels, Mp3Channels::JointStereo),
+        other => panic!("Unexpected data format: {other}"),
+    }
+    let (opus_spec, _) = WaveWriterBuilder::new(spec, DataFormat::Opus(OpusEncoderOptions::new())).configure().unwrap();
+    assert_eq!(opus_spec.sample_rate, 24000);
+
+    // The overrides of another codec and the bad Vorbis quality are refused when building.
+    match WaveWriterBuilder::new(spec, DataFormat::Pcm).mp3_bitrate(Mp3Bitrate::Kbps192).build_writer(Box::new(io_utils::CursorVecU8::default())).unwrap_err() {
+        AudioWriteError::InvalidArguments(message) => assert!(message.contains("`mp3_bitrate()`"), "{message}"),
+        other => panic!("Unexpected error: {other}"),
+    }
+    match WaveWriterBuilder::new(spec, DataFormat::from_name("oggvorbis1").unwrap()).vorbis_quality(1.5).configure().unwrap_err() {
+        AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // The file is created with the spec and the file size option.
+    let (_dir, path) = temp_wav("builder.wav");
+    let mut wavewriter = WaveWriterBuilder::new(spec, DataFormat::Pcm).file_size(FileSizeOption::ForceUse4GBFormat).build(&path).unwrap();
+    wavewriter.write_mono_channel(&[0i32, 1000, -1000]).unwrap();
+    let report = wavewriter.finalize().unwrap();
+    assert_eq!(report.container, ContainerKind::Rf64);
+    let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(wavereader.spec().sample_rate, 22050);
+    assert_eq!(wavereader.spec().bits_per_sample, 24);
+}
+
+#[test]
+pub fn test_block_iter() {
+    use iterators::FrameBlock;
+    let (dir, path) = temp_wav("blocks.wav");
+    let spec = test_spec(2, 44100);
+    let num_frames = 10000usize;
+    let stereos: Vec<(i16, i16)> = (0..num_frames).map(|i| (i as i16, -(i as i16) - 1)).collect();
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    let interleaved: Vec<i16> = stereos.iter().flat_map(|&(l, r)| [l, r]).collect();
+
+    let mut wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+
+    // The length is a multiple of the block size, there's no padding and no extra block.
+    let blocks: Vec<FrameBlock<i16>> = wavereader.block_iter::<i16>(1000, PadMode::Zero).unwrap().collect();
+    assert_eq!(blocks.len(), 10);
+    assert!(blocks.iter().all(|block| block.valid_frames == 1000 && !block.is_padded()));
+    assert_eq!(blocks.iter().flat_map(|block| block.samples.iter().copied()).collect::<Vec<i16>>(), interleaved);
+
+    // The length isn't a multiple of the block size, the last block has 784 valid frames.
+    for pad_mode in [PadMode::None, PadMode::Zero, PadMode::ReflectLast] {
+        let mut iter = wavereader.block_iter::<i16>(1024, pad_mode).unwrap();
+        let mut valid = Vec::<i16>::new();
+        let mut buffer = None;
+        let mut num_blocks = 0;
+        while let Some(block) = iter.next_block().unwrap() {
+            num_blocks += 1;
+            valid.extend_from_slice(&block.samples[..block.valid_frames * 2]);
+
+            // The buffer of the block is reused.
+            assert_eq!(*buffer.get_or_insert(block.samples.as_ptr()), block.samples.as_ptr());
+            if num_blocks < 10 {
+                assert_eq!(block.valid_frames, 1024);
+                continue;
+            }
+            assert_eq!(block.valid_frames, 784);
+            let padding: Vec<&[i16]> = block.frames().skip(784).collect();
+            match pad_mode {
+                PadMode::None => assert_eq!(block.num_frames(), 784),
+                PadMode::Zero => assert!(padding.len() == 240 && padding.iter().all(|frame| frame == &[0, 0])),
+                PadMode::ReflectLast => {
+                    assert_eq!(padding.len(), 240);
+                    // Reflected about the last frame 9999.
+                    assert_eq!(padding[0], [9998, -9999]);
+                    assert_eq!(padding[239], [9759, -9760]);
+                }
+            }
+        }
+        assert_eq!(num_blocks, 10, "{pad_mode:?}");
+        assert!(iter.next_block().unwrap().is_none());
+        assert_eq!(valid, interleaved, "{pad_mode:?}");
+    }
+
+    // A short audio is reflected back and forth.
+    let short_path = dir.path().join("short.wav");
+    let mut wavewriter = WaveWriter::create(&short_path, Spec { channels: 1, ..spec }, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_mono_channel(&[1i16, 2, 3, 4]).unwrap();
+    wavewriter.finalize().unwrap();
+    let mut wavereader = WaveReader::open(short_path.to_str().unwrap()).unwrap();
+    let blocks: Vec<FrameBlock<i16>> = wavereader.block_iter::<i16>(8, PadMode::ReflectLast).unwrap().collect();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].samples, [1, 2, 3, 4, 3, 2, 1, 2]);
+    assert_eq!(blocks[0].valid_frames, 4);
+    assert!(wavereader.block_iter::<i16>(0, PadMode::None).is_err());
+}
+
+#[test]
+pub fn test_cue_positions_beyond_32_bits() {
+    use chunks::{AdtlChunk, CueChunk, CuePoint, LablChunk, ListChunk, LtxtChunk, Plst, PlstChunk};
+    use errors::AudioWriteError;
+    use io_utils::CursorVecU8;
+    use std::collections::BTreeMap;
+
+    let spec = test_spec(1, 48000);
+
+    // The synthetic cue points, the audio isn't really that long.
+    let cue_chunk = |far_position: u64| CueChunk {
+        num_cues: 2,
+        cue_points: [(1, 100), (2, far_position)].iter().map(|&(cue_point_id, position)| CuePoint {
+            cue_point_id,
+            position,
+            data_chunk_id: *b"data",
+            chunk_start: 0,
+            block_start: 0,
+            offset: position,
+        }).collect(),
+    };
+    let adtl: BTreeMap<u32, Vec<AdtlChunk>> = [
+        (1, vec![AdtlChunk::Labl(LablChunk { cue_point_id: 1, data: "Near".to_owned() })]),
+        (2, vec![
+            AdtlChunk::Labl(LablChunk { cue_point_id: 2, data: "Far".to_owned() }),
+            AdtlChunk::Ltxt(LtxtChunk { cue_point_id: 2, sample_length: 6_000_000_000, purpose_id: "rgn ".to_owned(), ..Default::default() }),
+        ]),
+    ].into_iter().collect();
+    let plst_chunk = PlstChunk {
+        playlist_len: 2,
+        data: vec![
+            Plst { cue_point_id: 1, num_samples: 0, repeats: 1 },
+            Plst { cue_point_id: 2, num_samples: 6_000_000_000, repeats: 1 },
+        ],
+    };
+    let write = |far_position: u64, policy: CuePositionPolicy| -> Result<Vec<u8>, AudioWriteError> {
+        let mut cursor = CursorVecU8::default();
+        let mut wavewriter = WaveWriter::from(Box::new(&mut cursor), spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB)?;
+        wavewriter.set_cue_position_policy(policy);
+        wavewriter.set_cue__chunk(&cue_chunk(far_position));
+        wavewriter.set_plst_chunk(&plst_chunk);
+        wavewriter.list_chunk.insert(ListChunk::Adtl(adtl.clone()));
+        wavewriter.write_interleaved_samples(&[0i16; 16])?;
+        wavewriter.finalize()?;
+        drop(wavewriter);
+        Ok(cursor.into_inner())
+    };
+
+    let data = write(5_000_000_000, CuePositionPolicy::WithCue64).unwrap();
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(data.clone())))).unwrap();
+    assert!(wavereader.chunk_map().iter().any(|info| &info.flag == b"cu64"));
+    let positions: Vec<u64> = wavereader.get_cue__chunk().as_ref().unwrap().cue_points.iter().map(|point| point.position).collect();
+    assert_eq!(positions, [100, 5_000_000_000]);
+    assert_eq!(wavereader.get_plst_chunk().as_ref().unwrap().data[1].num_samples, 6_000_000_000);
+    let full_info = wavereader.create_full_info_cue_data().unwrap();
+    assert_eq!((full_info[&1].start_sample, full_info[&1].label.as_str()), (100, "Near"));
+    let far = &full_info[&2];
+    assert_eq!((far.start_sample, far.sample_length, far.num_samples), (5_000_000_000, 6_000_000_000, 6_000_000_000));
+
+    // The other readers see the standard `cue ` chunk, the position that doesn't fit is saturated.
+    let cue_info = wavereader.chunk_map().iter().find(|info| &info.flag == b"cue ").copied().unwrap();
+    let far_point = &data[cue_info.offset as usize + 8 + 4 + 24..][..24];
+    assert_eq!(far_point[4..8], u32::MAX.to_le_bytes());
+
+    // The positions that fit are written as the standard chunks only.
+    let data = write(200, CuePositionPolicy::Refuse).unwrap();
+    let wavereader = WaveReader::new(WaveDataSource::Reader(Box::new(CursorVecU8::new(data)))).unwrap();
+    assert!(wavereader.chunk_map().iter().all(|info| &info.flag != b"cu64"));
+    assert_eq!(wavereader.get_cue__chunk().as_ref().unwrap().cue_points[1].position, 200);
+
+    match write(5_000_000_000, CuePositionPolicy::Refuse).unwrap_err() {
+        AudioWriteError::InvalidData(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_flac_file_writer() {
+    if !cfg!(feature="flac") {return;}
+    use errors::AudioWriteError;
+    let (dir, path) = temp_wav("bare.flac");
+    let params = FlacEncoderParams {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        ..Default::default()
+    };
+    let stereos: Vec<(i16, i16)> = (0..10000).map(|i| {
+        let phase = i as f64 * 440.0 * std::f64::consts::TAU / 44100.0;
+        ((phase.sin() * 12000.0) as i16, (phase.cos() * 12000.0) as i16)
+    }).collect();
+    let mut flacwriter = FlacFileWriter::create(&path, &params).unwrap();
+    flacwriter.set_metadata_tag("TITLE", "Bare FLAC").unwrap();
+    flacwriter.write_stereos(&stereos[..4000]).unwrap();
+    flacwriter.write_interleaved_samples(&stereos[4000..].iter().flat_map(|(l, r)| [*l, *r]).collect::<Vec<i16>>()).unwrap();
+    match flacwriter.set_metadata_tag("ARTIST", "Too late").unwrap_err() {
+        AudioWriteError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    assert_eq!(flacwriter.get_num_frames_written(), 10000);
+    flacwriter.finalize().unwrap();
+
+    // No RIFF container, the `STREAMINFO` has the sample rate, the channels, the bits per sample and the number of the frames.
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[0..4], b"fLaC");
+    assert_eq!(bytes[4] & 0x7F, 0);
+    let stream_info = u64::from_be_bytes(bytes[18..26].try_into().unwrap());
+    assert_eq!(stream_info >> 44, 44100);
+    assert_eq!((stream_info >> 41) & 0x7, 1);
+    assert_eq!((stream_info >> 36) & 0x1F, 15);
+    assert_eq!(stream_info & 0xF_FFFF_FFFF, 10000);
+    assert!(bytes.windows(15).any(|window| window == b"TITLE=Bare FLAC"));
+
+    let decoded_path = dir.path().join("bare_ffmpeg.wav");
+    let decoded_by_ffmpeg = std::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(&path)
+        .args(["-c:a", "pcm_s16le"])
+        .arg(&decoded_path)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !decoded_by_ffmpeg {
+        eprintln!("`ffmpeg` is not available, skipped the interoperability test.");
+        return;
+    }
+    let mut wavereader = WaveReader::open(decoded_path.to_str().unwrap()).unwrap();
+    let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+    assert_eq!(decoded, stereos);
+}
+
+#[test]
+pub fn test_huge_write_bounded_memory() {
+    if !cfg!(feature="opus") || !cfg!(feature="oggvorbis") {return;}
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 48000);
+
+    // A small `max_frames_per_write` keeps the test quick, the slice is still much bigger than the memory allowed to write it.
+    const MAX_FRAMES_PER_WRITE: usize = 1024;
+    const MAX_PEAK_BYTES: usize = 2 * 1024 * 1024;
+    let samples = vec![0i16; 4_000_000];
+    let formats = [
+        ("huge_opus.wav", DataFormat::Opus(OpusEncoderOptions {
+            max_frames_per_write: MAX_FRAMES_PER_WRITE,
+            ..OpusEncoderOptions::new()
+        })),
+        ("huge_vorbis.wav", DataFormat::OggVorbis(OggVorbisEncoderParams {
+            mode: OggVorbisMode::NakedVorbis,
+            channels: 2,
+            sample_rate: 48000,
+            max_frames_per_write: MAX_FRAMES_PER_WRITE,
+            ..OggVorbisEncoderParams::new()
+        })),
+    ];
+    for (file_name, data_format) in formats {
+        let path = dir.path().join(file_name);
+        let mut wavewriter = WaveWriter::create(&path, spec, data_format, FileSizeOption::AllowLargerThan4GB).unwrap();
+
+        // The whole slice is written in one call, the encoder only takes `max_frames_per_write` frames of it at a time.
+        let bytes_before = ALLOCATED_BYTES.with(|bytes| bytes.get());
+        PEAK_ALLOCATED_BYTES.with(|peak| peak.set(bytes_before));
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        let peak = PEAK_ALLOCATED_BYTES.with(|peak| peak.get()) - bytes_before;
+        assert!(peak < MAX_PEAK_BYTES, "{file_name}: {peak} bytes allocated at the peak for writing {} samples", samples.len());
+        wavewriter.finalize().unwrap();
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn test_describe_json() {
+    use chunks::BextChunk;
+    let (_dir, path) = temp_wav("describe.wav");
+    let spec = test_spec(2, 48000);
+    let mut umid = [0u8; 64];
+    umid[..4].copy_from_slice(b"UMID");
+    let mut cues = CueBuilder::new();
+    cues.add_cue(100, "Start");
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_bext(BextChunk {
+        description: "Described".to_string(),
+        origination_date: "2024-05-06".to_string(),
+        origination_time: "07:08:09".to_string(),
+        umid,
+        ..Default::default()
+    }).unwrap();
+    wavewriter.set_cues(&cues);
+    wavewriter.set_metadata_tag("INAM", "The Title").unwrap();
+    wavewriter.write_interleaved_samples(&vec![0i16; 2000]).unwrap();
+    wavewriter.finalize().unwrap();
+
+    let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+    let json = serde_json::to_value(wavereader.describe()).unwrap();
+    let keys: Vec<&str> = json.as_object().unwrap().keys().map(|key| key.as_str()).collect();
+    assert_eq!(keys, [
+        "acid_chunk", "axml_chunk", "bext_chunk", "chna_chunk", "chunk_map", "cue__chunk", "fact_data", "fmt__chunk", "id3__length", "inst_chunk",
+        "ixml_chunk", "junk_chunks", "list_chunk", "metadata", "plst_chunk", "slnt_chunk", "smpl_chunk", "spec", "start_frame", "storage_info",
+        "trkn_chunk", "truncated", "warnings",
+    ]);
+    assert_eq!(json["spec"]["channels"], 2);
+    assert_eq!(json["spec"]["sample_format"], "Int");
+    assert_eq!(json["fmt__chunk"]["format_tag"], 1);
+    assert_eq!(json["storage_info"]["codec"], "PCM");
+    assert_eq!(json["storage_info"]["data_length"], 4000);
+
+    // The flags are the text, the binary fields are base64 text or the lengths.
+    let flags: Vec<&str> = json["chunk_map"].as_array().unwrap().iter().map(|chunk| chunk["flag"].as_str().unwrap()).collect();
+    assert!(flags.contains(&"fmt ") && flags.contains(&"bext") && flags.contains(&"data"));
+    assert_eq!(json["bext_chunk"]["description"], "Described");
+    assert_eq!(json["bext_chunk"]["umid"], format!("VU1JRA{}==", "A".repeat(80)));
+    assert!(json["bext_chunk"].get("reserved").is_none());
+    assert_eq!(json["cue__chunk"]["cue_points"][0]["position"], 100);
+    assert_eq!(json["cue__chunk"]["cue_points"][0]["data_chunk_id"], "data");
+    assert_eq!(json["metadata"]["title"], "The Title");
+    assert!(json["id3__length"].is_null());
+}
+
+#[test]
+pub fn test_float64_pcm() {
+    use chunks::ext::ExtensionData;
+    use format_specs::{format_tags::FORMAT_TAG_PCM_IEEE, guids::GUID_IEEE_FLOAT_FORMAT};
+    let dir = tempfile::tempdir().unwrap();
+    let spec = Spec {
+        bits_per_sample: 64,
+        sample_format: SampleFormat::Float,
+        ..test_spec(2, 96000)
+    };
+    // The values that a 32-bit float can't keep.
+    let values: Vec<f64> = (0..10000).map(|i| (i as f64 * 0.001).sin() / 3.0).chain([1e-300, -0.5, 1.0 / 3.0, f64::MIN_POSITIVE, 2.5]).collect();
+    for data_format in [DataFormat::Pcm, DataFormat::PcmExtensible(PcmEncoderOptions::new())] {
+        let path = dir.path().join(format!("float64_{data_format}.wav"));
+        let mut wavereader = write_and_reopen(&path, spec, data_format.clone(), |wavewriter| wavewriter.write_interleaved_samples(&values).unwrap());
+        let fmt = wavereader.get_fmt__chunk().clone();
+        assert_eq!((fmt.bits_per_sample, fmt.block_align, fmt.byte_rate), (64, 16, 96000 * 16), "{data_format}");
+        match (&data_format, fmt.extension.map(|extension| extension.data)) {
+            // The plain one is the `WAVEFORMATEX` with the zero `cbSize` and the `fact` chunk.
+            (DataFormat::Pcm, Some(ExtensionData::Nodata)) => {
+                assert_eq!(fmt.format_tag, FORMAT_TAG_PCM_IEEE);
+                assert_eq!(wavereader.get_fact_data(), values.len() as u64);
+            }
+            (DataFormat::PcmExtensible(_), Some(ExtensionData::Extensible(extensible))) => {
+                assert_eq!(extensible.sub_format, GUID_IEEE_FLOAT_FORMAT);
+                assert_eq!(extensible.valid_bits_per_sample, 64);
+            }
+            (_, other) => panic!("Unexpected extension of {data_format}: {other:?}"),
+        }
+        assert_eq!(wavereader.spec().get_sample_type(), WaveSampleType::F64);
+        assert_eq!(wavereader.total_frames(), Some(values.len() as u64 / 2));
+        assert!(wavereader.validate().issues.is_empty(), "{data_format}");
+
+        let decoded: Vec<f64> = wavereader.frame_iter::<f64>().unwrap().flatten().collect();
+        assert_eq!(decoded.iter().map(|v| v.to_bits()).collect::<Vec<_>>(), values.iter().map(|v| v.to_bits()).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+pub fn test_open_with_visitor() {
+    use chunks::ChunkHeader;
+    use errors::AudioReadError;
+    let (_dir, path) = temp_wav("visitor.wav");
+    let spec = test_spec(1, 44100);
+    let samples: Vec<i16> = (0..1000).map(|i| (i * 30) as i16).collect();
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.set_metadata_tag("INAM", "Visited").unwrap();
+    wavewriter.write_mono_channel(&samples).unwrap();
+    wavewriter.finalize().unwrap();
+    let path = path.to_str().unwrap();
+
+    // Every chunk is visited in the order of the file, at the positions of the chunk map.
+    let mut visited = Vec::<([u8; 4], u64)>::new();
+    let wavereader = WaveReader::open_with_visitor(path, |chunk: &ChunkHeader| {
+        visited.push((chunk.flag, chunk.chunk_start_pos - 8));
+        ChunkAction::Parse
+    }).unwrap();
+    assert_eq!(visited, WaveReader::open(path).unwrap().chunk_map().iter().map(|info| (info.flag, info.offset)).collect::<Vec<_>>());
+    assert_eq!(wavereader.get_metadata_text("INAM").unwrap(), "Visited");
+
+    // The skipped chunk isn't parsed, the `fmt ` chunk is parsed anyway.
+    let mut wavereader = WaveReader::open_with_visitor(path, |_| ChunkAction::Skip).unwrap();
+    assert_eq!(wavereader.get_metadata_text("INAM"), None);
+    assert!(wavereader.chunk_map().iter().any(|info| &info.flag == b"LIST"));
+    assert_eq!(wavereader.mono_iter::<i16>().unwrap().collect::<Vec<_>>(), samples);
+
+    // Stop after the `data` chunk, the chunks after it are never read.
+    let mut wavereader = WaveReader::open_with_visitor(path, |chunk| if &chunk.flag == b"LIST" { ChunkAction::StopEarly } else { ChunkAction::Parse }).unwrap();
+    assert_eq!(wavereader.chunk_map().iter().map(|info| info.flag).collect::<Vec<_>>(), [*b"fmt ", *b"data"]);
+    assert_eq!(wavereader.get_metadata_text("INAM"), None);
+    assert_eq!(wavereader.mono_iter::<i16>().unwrap().collect::<Vec<_>>(), samples);
+
+    // Stopping before the `data` chunk leaves nothing to read.
+    match WaveReader::open_with_visitor(path, |chunk| if &chunk.flag == b"data" { ChunkAction::StopEarly } else { ChunkAction::Parse }).unwrap_err().root() {
+        AudioReadError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+pub fn test_adpcm_final_block_padding() {
+    let dir = tempfile::tempdir().unwrap();
+    for channels in [1u16, 2] {
+        let spec = test_spec(channels, 22050);
+        for (sub_format, frames_per_block) in [(AdpcmSubFormat::Ms, 2036usize), (AdpcmSubFormat::Ima, 1017), (AdpcmSubFormat::Yamaha, 2048 / channels as usize)] {
+            for num_frames in [frames_per_block - 1, frames_per_block, frames_per_block + 1] {
+                let samples: Vec<i16> = (0..num_frames * channels as usize).map(|i| ((i as f64 * 0.02).sin() * 10000.0) as i16).collect();
+                let path = dir.path().join(format!("padding_{sub_format}_{channels}_{num_frames}.wav"));
+                // Every block is full, the `fact` chunk has the frames before the padding.
+                let mut wavereader = write_and_reopen(&path, spec, DataFormat::Adpcm(sub_format), |wavewriter| wavewriter.write_interleaved_samples(&samples).unwrap());
+                let block_align = wavereader.get_fmt__chunk().block_align as u64;
+                assert_eq!(wavereader.get_data_length() % block_align, 0, "{sub_format}, {channels} channels, {num_frames} frames");
+                assert_eq!(wavereader.get_fact_data(), samples.len() as u64, "{sub_format}, {channels} channels, {num_frames} frames");
+                assert_eq!(wavereader.total_frames(), Some(num_frames as u64));
+
+                // The padding isn't decoded.
+                let decoded: Vec<Vec<i16>> = wavereader.frame_iter::<i16>().unwrap().collect();
+                assert_eq!(decoded.len(), num_frames, "{sub_format}, {channels} channels");
+                let mut buf = vec![0i16; samples.len() + 100];
+                wavereader.seek_to_frame(0).unwrap();
+                assert_eq!(wavereader.decode_into(&mut buf).unwrap(), samples.len(), "{sub_format}, {channels} channels");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+pub fn test_map_pcm_data() {
+    use errors::AudioReadError;
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let samples: Vec<i16> = (0..2000).map(|i| (i * 17 - 16000) as i16).collect();
+    let path = dir.path().join("mmap_16.wav");
+    // SAFETY: nothing modifies the temporary files while they're mapped.
+    let wavereader = write_and_reopen(&path, spec, DataFormat::Pcm, |wavewriter| wavewriter.write_interleaved_samples(&samples).unwrap());
+    let view = unsafe { wavereader.map_pcm_data() }.unwrap();
+    assert_eq!((view.sample_type(), view.channels(), view.num_frames()), (WaveSampleType::S16, 2, 1000));
+    assert_eq!(view.as_i16_slice().unwrap(), &samples);
+    assert_eq!(view.as_bytes().len(), 4000);
+    match view.as_f32_slice().unwrap_err() {
+        AudioReadError::InvalidArguments(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+    let windows: Vec<Vec<Vec<i16>>> = view.frames::<i16>(300).unwrap().collect();
+    assert_eq!(windows.len(), 4);
+    assert_eq!(windows[3][0].len(), 100);
+    let lefts: Vec<i16> = windows.iter().flat_map(|window| window[0].clone()).collect();
+    let rights: Vec<i16> = windows.iter().flat_map(|window| window[1].clone()).collect();
+    assert_eq!(lefts, samples.iter().step_by(2).copied().collect::<Vec<_>>());
+    assert_eq!(rights, samples[1..].iter().step_by(2).copied().collect::<Vec<_>>());
+
+    // The plain IEEE float `fmt ` chunk is 18 bytes and followed by the `fact` chunk, the `data` chunk begins at the offset 58.
+    let path = dir.path().join("mmap_f32.wav");
+    let spec_f32 = Spec {bits_per_sample: 32, sample_format: SampleFormat::Float, ..spec};
+    let wavereader = write_and_reopen(&path, spec_f32, DataFormat::Pcm, |wavewriter| wavewriter.write_interleaved_samples(&[0.25f32, -0.25, 0.5, -0.5]).unwrap());
+    let view = unsafe { wavereader.map_pcm_data() }.unwrap();
+    assert_eq!(view.as_bytes().len(), 16);
+    match view.as_f32_slice().unwrap_err() {
+        AudioReadError::Unsupported(_) => (),
+        other => panic!("Unexpected error: {other}"),
+    }
+
+    // The 24-bit samples and the compressed formats can't be mapped.
+    for (name, bits_per_sample, data_format) in [("mmap_24.wav", 24, DataFormat::Pcm), ("mmap_adpcm.wav", 16, DataFormat::Adpcm(AdpcmSubFormat::Ima))] {
+        let path = dir.path().join(name);
+        let mut wavewriter = WaveWriter::create(&path, Spec {bits_per_sample, ..spec}, data_format, FileSizeOption::NeverLargerThan4GB).unwrap();
+        wavewriter.write_interleaved_samples(&samples).unwrap();
+        wavewriter.finalize().unwrap();
+        let wavereader = WaveReader::open(path.to_str().unwrap()).unwrap();
+        match unsafe { wavereader.map_pcm_data() }.unwrap_err() {
+            AudioReadError::Unsupported(_) if bits_per_sample == 24 => (),
+            AudioReadError::UnsupportedFormat(_, _) if bits_per_sample == 16 => (),
+            other => panic!("Unexpected error: {other}"),
+        }
+    }
+}
+
+#[test]
+pub fn test_byte_rate_of_each_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = test_spec(2, 44100);
+    let mut data_formats = vec![
+        DataFormat::Pcm,
+        DataFormat::PcmALaw,
+        DataFormat::PcmMuLaw,
+        DataFormat::Adpcm(AdpcmSubFormat::Ms),
+        DataFormat::Adpcm(AdpcmSubFormat::Ima),
+        DataFormat::Adpcm(AdpcmSubFormat::Yamaha),
+    ];
+    if cfg!(feature = "mp3enc") {
+        data_formats.push(DataFormat::Mp3(Mp3EncoderOptions::new_stereo()));
+    }
+    if cfg!(feature = "opus") {
+        data_formats.push(DataFormat::Opus(OpusEncoderOptions {bitrate: OpusBitrate::Bits(96000), ..OpusEncoderOptions::default()}));
+    }
+    if cfg!(feature = "flac") {
+        data_formats.push(DataFormat::Flac(FlacEncoderParams {channels: 2, sample_rate: 44100, bits_per_sample: 16, ..Default::default()}));
+    }
+    if cfg!(feature = "oggvorbis") {
+        data_formats.push(DataFormat::OggVorbis(OggVorbisEncoderParams {
+            channels: 2,
+            sample_rate: 44100,
+            bitrate: Some(OggVorbisBitrateStrategy::Abr(128000)),
+            ..OggVorbisEncoderParams::new()
+        }));
+    }
+    let near = |byte_rate: u32, expected: u32, tolerance: f64| (byte_rate as f64 - expected as f64).abs() <= expected as f64 * tolerance;
+    for data_format in data_formats {
+        let path = dir.path().join(format!("byte_rate_{data_format}.wav"));
+        // The `byte_rate` is the bytes per second of all of the channels together.
+        let wavereader = write_and_reopen(&path, spec, data_format.clone(), |wavewriter| {
+            testsig::write_sine(wavewriter, 440.0, 2.0).unwrap();
+        });
+        let fmt = wavereader.get_fmt__chunk().clone();
+        let samples_per_block = match fmt.extension.as_ref().map(|extension| &extension.data) {
+            Some(chunks::ext::ExtensionData::AdpcmMs(adpcm_ms)) => adpcm_ms.samples_per_block as u32,
+            Some(chunks::ext::ExtensionData::AdpcmIma(adpcm_ima)) => adpcm_ima.samples_per_block as u32,
+            _ => 0,
+        };
+        match data_format {
+            DataFormat::Pcm => assert_eq!(fmt.byte_rate, 176400),
+            DataFormat::PcmALaw | DataFormat::PcmMuLaw => assert_eq!(fmt.byte_rate, 88200, "{data_format}"),
+            DataFormat::Adpcm(AdpcmSubFormat::Yamaha) => assert_eq!(fmt.byte_rate, 44100),
+            // The block headers are counted, a bit more than 4 bits per sample.
+            DataFormat::Adpcm(_) => {
+                assert_eq!(fmt.byte_rate, 44100 * fmt.block_align as u32 / samples_per_block, "{data_format}");
+                assert!((44100..=46000).contains(&fmt.byte_rate), "{data_format}: {}", fmt.byte_rate);
+            }
+            DataFormat::Mp3(_) => assert_eq!(fmt.byte_rate, 320000 / 8),
+            DataFormat::Opus(_) => assert!(near(fmt.byte_rate, 96000 / 8, 0.1), "{}", fmt.byte_rate),
+            DataFormat::Flac(_) => assert!(fmt.byte_rate > 0 && fmt.byte_rate < 176400, "{}", fmt.byte_rate),
+            DataFormat::OggVorbis(_) => assert!(near(fmt.byte_rate, 128000 / 8, 0.3), "{}", fmt.byte_rate),
+            other => panic!("Unexpected format {other}"),
+        }
+    }
+}
+
+#[test]
+pub fn test_assume_spec_salvage() {
+    use errors::AudioReadError;
+    let (dir, path) = temp_wav("salvage.wav");
+    let spec = test_spec(2, 44100);
+    let stereos: Vec<(i16, i16)> = (0..5000).map(|i| ((i * 7) as i16, (-i * 5) as i16)).collect();
+    let mut wavewriter = WaveWriter::create(&path, spec, DataFormat::Pcm, FileSizeOption::NeverLargerThan4GB).unwrap();
+    wavewriter.write_stereos(&stereos).unwrap();
+    wavewriter.finalize().unwrap();
+    let original = std::fs::read(&path).unwrap();
+    assert_eq!(&original[12..16], b"fmt ");
+
+    // The format tag is damaged, or the whole `fmt ` chunk is gone.
+    let mut bad_tag = original.clone();
+    bad_tag[20..22].copy_from_slice(&0xDEADu16.to_le_bytes());
+    let mut no_fmt = original.clone();
+    no_fmt[12..16].copy_from_slice(b"fmX ");
+    for (name, bytes) in [("bad_tag.wav", bad_tag), ("no_fmt.wav", no_fmt)] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        let path = path.to_str().unwrap();
+
+        // Strict by default.
+        match WaveReader::open(path) {
+            Ok(mut wavereader) => assert!(wavereader.stereo_iter::<i16>().is_err(), "{name}"),
+            Err(err) => match err.root() {
+                AudioReadError::InvalidData(_) => (),
+                other => panic!("Unexpected error: {other}"),
+            },
+        }
+
+        let read_options = ReadOptions {
+            assume_spec: Some(spec),
+            ..ReadOptions::default()
+        };
+        let mut wavereader = WaveReader::open_with_options(path, read_options).unwrap();
+        assert_eq!(wavereader.spec(), spec, "{name}");
+        assert!(wavereader.warnings().iter().any(|warning| warning.kind == errors::DecodeWarningKind::AssumedSpec), "{name}");
+        assert_eq!(wavereader.validate().find(ValidationIssueKind::AssumedSpec).count(), 1, "{name}");
+        let decoded: Vec<(i16, i16)> = wavereader.stereo_iter::<i16>().unwrap().collect();
+        assert_eq!(decoded, stereos, "{name}");
+    }
+
+    // The readable `fmt ` chunk is still used, nothing is assumed.
+    let read_options = ReadOptions {
+        assume_spec: Some(Spec {sample_rate: 8000, ..spec}),
+        ..ReadOptions::default()
+    };
+    let wavereader = WaveReader::open_with_options(path.to_str().unwrap(), read_options).unwrap();
+    assert_eq!(wavereader.spec().sample_rate, 44100);
+    assert!(wavereader.warnings().is_empty());
+}